@@ -0,0 +1,105 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A loop calling tiny helpers, like the accessors compilers to WebAssembly
+/// leave behind, which only inlining gets rid of.
+#[cfg(feature = "cranelift")]
+const HELPER_CALLS: &str = r#"
+(module
+  (memory 1)
+  (func $addr (param $i i32) (result i32)
+    (i32.shl (i32.and (local.get $i) (i32.const 0xfff)) (i32.const 2)))
+  (func $get (param $i i32) (result i32)
+    (i32.load (call $addr (local.get $i))))
+  (func $set (param $i i32) (param $v i32)
+    (i32.store (call $addr (local.get $i)) (local.get $v)))
+  (func $mix (param $a i64) (param $b i32) (result i64)
+    (i64.add (i64.mul (local.get $a) (i64.const 31)) (i64.extend_i32_u (local.get $b))))
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $hash i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (call $set (local.get $i) (i32.add (call $get (local.get $i)) (local.get $i)))
+        (local.set $hash (call $mix (local.get $hash) (call $get (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $hash)))
+"#;
+
+/// A numeric loop, which benefits from the mid-end optimizations.
+#[cfg(feature = "cranelift")]
+const SUM_OF_SQUARES: &str = r#"
+(module
+  (memory 1)
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $sum i64)
+    (block $done
+      (loop $sum
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $sum
+          (i64.add
+            (local.get $sum)
+            (i64.extend_i32_u
+              (i32.mul
+                (i32.load (i32.shl (i32.and (local.get $i) (i32.const 0xfff)) (i32.const 2)))
+                (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $sum)))
+    (local.get $sum)))
+"#;
+
+/// How long it takes to compile `wat` with each combination of Cranelift's
+/// settings, and how long the compiled code takes to run.
+#[cfg(feature = "cranelift")]
+fn compare_settings(c: &mut Criterion, name: &str, wat: &str, arg: i32) {
+    use wasmer::*;
+    use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
+
+    let wasm = wat2wasm(wat.as_bytes()).unwrap().into_owned();
+    let levels = [
+        ("speed", CraneliftOptLevel::Speed),
+        ("speed_and_size", CraneliftOptLevel::SpeedAndSize),
+    ];
+
+    for (level_name, opt_level) in levels {
+        for use_egraphs in [false, true] {
+            for inline_threshold in [0, 16] {
+                let mut compiler = Cranelift::new();
+                compiler
+                    .opt_level(opt_level)
+                    .use_egraphs(use_egraphs)
+                    .inline_threshold(inline_threshold);
+                let engine: Engine = compiler.into();
+                let settings =
+                    format!("{level_name}, egraphs {use_egraphs}, inline {inline_threshold}");
+
+                c.bench_function(&format!("compile {name} with {settings}"), |b| {
+                    b.iter(|| black_box(Module::new(&engine, &wasm).unwrap()))
+                });
+
+                let mut store = Store::new(engine.clone());
+                let module = Module::new(&store, &wasm).unwrap();
+                let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+                let run: TypedFunction<i32, i64> =
+                    instance.exports.get_typed_function(&store, "run").unwrap();
+                c.bench_function(&format!("run {name} with {settings}"), |b| {
+                    b.iter(|| black_box(run.call(&mut store, black_box(arg)).unwrap()))
+                });
+            }
+        }
+    }
+}
+
+fn run_cranelift_settings_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "cranelift")]
+    {
+        compare_settings(_c, "helper calls", HELPER_CALLS, 100_000);
+        compare_settings(_c, "sum of squares", SUM_OF_SQUARES, 100_000);
+    }
+}
+
+criterion_group!(benches, run_cranelift_settings_benchmarks);
+
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// A module handling a "request": it writes to its memory and bumps a
+/// counter, so there is state to reset between requests.
+const MODULE: &str = r#"
+(module
+  (memory (export "memory") 16)
+  (table 16 funcref)
+  (global $requests (mut i32) (i32.const 0))
+  (data (i32.const 0) "hello world")
+  (func $handle (export "handle") (param $len i32) (result i32)
+    (memory.fill (i32.const 1024) (i32.const 42) (local.get $len))
+    (global.set $requests (i32.add (global.get $requests) (i32.const 1)))
+    (global.get $requests)))
+"#;
+
+fn handle(instance: &Instance, store: &mut Store) {
+    let handle: TypedFunction<i32, i32> = instance
+        .exports
+        .get_typed_function(store, "handle")
+        .unwrap();
+    assert_eq!(handle.call(store, 64 * 1024).unwrap(), 1);
+}
+
+fn run_instance_pool_benchmarks(c: &mut Criterion) {
+    let store = Store::default();
+    let engine = store.engine().clone();
+    let module = Module::new(&store, MODULE).unwrap();
+
+    c.bench_function("request with a fresh store and instance", |b| {
+        b.iter(|| {
+            let mut store = Store::new(engine.clone());
+            let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+            handle(&instance, &mut store);
+        })
+    });
+
+    let pool = InstancePool::new(engine, 1);
+    pool.preallocate(&module, 1).unwrap();
+    c.bench_function("request with a pooled instance", |b| {
+        b.iter(|| {
+            let mut pooled = pool.get(&module).unwrap();
+            let (instance, store) = pooled.instance_and_store_mut();
+            handle(instance, store);
+        })
+    });
+    println!("{:?}", pool.stats());
+}
+
+criterion_group!(benches, run_instance_pool_benchmarks);
+
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// The number of functions the benchmarked module imports.
+const IMPORTS: usize = 100;
+
+fn module_wat() -> String {
+    let mut wat = String::from("(module\n");
+    for i in 0..IMPORTS {
+        wat.push_str(&format!(
+            "  (import \"env\" \"f{i}\" (func (param i32) (result i32)))\n"
+        ));
+    }
+    wat.push_str("  (memory (export \"memory\") 1)\n");
+    wat.push_str("  (data (i32.const 0) \"hello world\"))\n");
+    wat
+}
+
+fn run_instantiation_benchmarks(c: &mut Criterion) {
+    let mut store = Store::default();
+    let module = Module::new(&store, module_wat()).unwrap();
+
+    let mut imports = Imports::new();
+    for i in 0..IMPORTS {
+        imports.define(
+            "env",
+            &format!("f{i}"),
+            Function::new_typed(&mut store, |x: i32| x),
+        );
+    }
+
+    c.bench_function("instantiate with Instance::new", |b| {
+        b.iter(|| black_box(Instance::new(&mut store, &module, &imports).unwrap()))
+    });
+
+    let pre = InstancePre::new(&store, &module, &imports).unwrap();
+    c.bench_function("instantiate with InstancePre", |b| {
+        b.iter(|| black_box(pre.instantiate(&mut store).unwrap()))
+    });
+}
+
+criterion_group!(benches, run_instantiation_benchmarks);
+
+criterion_main!(benches);
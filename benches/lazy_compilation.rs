@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// The number of functions in the benchmarked module. Only one of them is
+/// ever called.
+#[cfg(feature = "cranelift")]
+const FUNCTIONS: usize = 5000;
+
+#[cfg(feature = "cranelift")]
+fn module_wat() -> String {
+    let mut wat = String::from("(module\n");
+    for i in 0..FUNCTIONS {
+        wat.push_str(&format!(
+            "  (func $f{i} (export \"f{i}\") (param i32) (result i32)
+    local.get 0
+    i32.const {i}
+    i32.add
+    i32.const 3
+    i32.mul
+    local.get 0
+    i32.xor)\n"
+        ));
+    }
+    wat.push(')');
+    wat
+}
+
+/// How long it takes to compile the module, instantiate it and call one of
+/// its functions.
+#[cfg(feature = "cranelift")]
+fn time_to_first_call(c: &mut Criterion, name: &str, compiler: impl Fn() -> Store) {
+    let wasm = wat2wasm(module_wat().as_bytes()).unwrap().into_owned();
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let mut store = compiler();
+            let module = Module::new(&store, &wasm).unwrap();
+            let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+            let f: TypedFunction<i32, i32> =
+                instance.exports.get_typed_function(&store, "f0").unwrap();
+            black_box(f.call(&mut store, 42).unwrap())
+        })
+    });
+}
+
+fn run_lazy_compilation_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "cranelift")]
+    {
+        time_to_first_call(_c, "time to first call with cranelift", || {
+            Store::new(wasmer_compiler_cranelift::Cranelift::new())
+        });
+        time_to_first_call(_c, "time to first call with lazy cranelift", || {
+            let mut compiler = wasmer_compiler_cranelift::Cranelift::new();
+            compiler.enable_lazy_compilation(true);
+            Store::new(compiler)
+        });
+    }
+}
+
+criterion_group!(benches, run_lazy_compilation_benchmarks);
+
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A numeric loop, which benefits from vectorization and loop optimizations.
+#[cfg(feature = "llvm")]
+const SUM_OF_SQUARES: &str = r#"
+(module
+  (memory 1)
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $sum i64)
+    ;; fill the memory with i32s
+    (block $done
+      (loop $fill
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (i32.store (i32.shl (local.get $i) (i32.const 2)) (local.get $i))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $fill)))
+    (local.set $i (i32.const 0))
+    (block $done
+      (loop $sum
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $sum
+          (i64.add
+            (local.get $sum)
+            (i64.extend_i32_u
+              (i32.mul
+                (i32.load (i32.shl (local.get $i) (i32.const 2)))
+                (i32.load (i32.shl (local.get $i) (i32.const 2)))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $sum)))
+    (local.get $sum)))
+"#;
+
+/// Lots of small calls and branches.
+#[cfg(feature = "llvm")]
+const FIBONACCI: &str = r#"
+(module
+  (func $fib (export "run") (param $n i32) (result i64)
+    (if (result i64) (i32.lt_u (local.get $n) (i32.const 2))
+      (then (i64.extend_i32_u (local.get $n)))
+      (else
+        (i64.add
+          (call $fib (i32.sub (local.get $n) (i32.const 1)))
+          (call $fib (i32.sub (local.get $n) (i32.const 2))))))))
+"#;
+
+/// How long it takes to compile `wat` at `opt_level`, and how long the
+/// compiled code takes to run.
+#[cfg(feature = "llvm")]
+fn compare_opt_levels(c: &mut Criterion, name: &str, wat: &str, arg: i32) {
+    use wasmer::*;
+    use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
+
+    let wasm = wat2wasm(wat.as_bytes()).unwrap().into_owned();
+    let levels = [
+        LLVMOptLevel::O0,
+        LLVMOptLevel::O1,
+        LLVMOptLevel::O2,
+        LLVMOptLevel::O3,
+        LLVMOptLevel::Os,
+    ];
+
+    for opt_level in levels {
+        let mut compiler = LLVM::new();
+        compiler.opt_level(opt_level);
+        let engine: Engine = compiler.into();
+
+        c.bench_function(&format!("compile {name} with llvm {opt_level}"), |b| {
+            b.iter(|| black_box(Module::new(&engine, &wasm).unwrap()))
+        });
+
+        let mut store = Store::new(engine.clone());
+        let module = Module::new(&store, &wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let run: TypedFunction<i32, i64> =
+            instance.exports.get_typed_function(&store, "run").unwrap();
+        c.bench_function(&format!("run {name} with llvm {opt_level}"), |b| {
+            b.iter(|| black_box(run.call(&mut store, black_box(arg)).unwrap()))
+        });
+    }
+}
+
+fn run_llvm_opt_level_benchmarks(_c: &mut Criterion) {
+    #[cfg(feature = "llvm")]
+    {
+        compare_opt_levels(_c, "sum of squares", SUM_OF_SQUARES, 10_000);
+        compare_opt_levels(_c, "fibonacci", FIBONACCI, 20);
+    }
+}
+
+criterion_group!(benches, run_llvm_opt_level_benchmarks);
+
+criterion_main!(benches);
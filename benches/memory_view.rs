@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// The number of `u64`s read per iteration (8MiB).
+const LEN: u64 = 1024 * 1024;
+
+fn run_memory_view_benchmarks(c: &mut Criterion) {
+    let mut store = Store::default();
+    let memory = Memory::new(&mut store, MemoryType::new(256, None, false)).unwrap();
+    let view = memory.view(&store);
+    let ptr: WasmPtr<u64> = WasmPtr::new(0);
+
+    c.bench_function("sum with WasmSlice::read_to_vec", |b| {
+        b.iter(|| {
+            let values = ptr.slice(&view, LEN as u32).unwrap().read_to_vec().unwrap();
+            black_box(values.iter().sum::<u64>())
+        })
+    });
+
+    c.bench_function("sum with WasmSliceRef::get", |b| {
+        b.iter(|| {
+            let slice = view.slice_at::<u64>(0, LEN).unwrap();
+            black_box((0..slice.len()).filter_map(|i| slice.get(i)).sum::<u64>())
+        })
+    });
+
+    let mut buf = vec![0u64; LEN as usize];
+    c.bench_function("copy with WasmSliceRef::copy_to_slice", |b| {
+        b.iter(|| {
+            view.slice_at::<u64>(0, LEN)
+                .unwrap()
+                .copy_to_slice(&mut buf);
+            black_box(&buf);
+        })
+    });
+}
+
+criterion_group!(benches, run_memory_view_benchmarks);
+
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// A tight loop, which pays for profiling at every iteration.
+#[cfg(all(feature = "cranelift", feature = "middlewares"))]
+const TIGHT_LOOP: &str = r#"
+(module
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $sum i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $sum (i64.add (local.get $sum) (i64.extend_i32_u (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $sum)))
+"#;
+
+/// A loop calling a small function, which pays for profiling at every
+/// iteration and every call.
+#[cfg(all(feature = "cranelift", feature = "middlewares"))]
+const CALLS: &str = r#"
+(module
+  (func $mix (param $a i64) (param $b i32) (result i64)
+    (i64.add (i64.mul (local.get $a) (i64.const 31)) (i64.extend_i32_u (local.get $b))))
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $hash i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $hash (call $mix (local.get $hash) (local.get $i)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $hash)))
+"#;
+
+/// A loop with a long body, where the cost of profiling is spread over
+/// many operators.
+#[cfg(all(feature = "cranelift", feature = "middlewares"))]
+const LONG_BODY: &str = r#"
+(module
+  (func (export "run") (param $n i32) (result i64)
+    (local $i i32)
+    (local $x i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+        (local.set $x (i64.xor (i64.mul (local.get $x) (i64.const 6364136223846793005)) (i64.extend_i32_u (local.get $i))))
+        (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 33))))
+        (local.set $x (i64.mul (local.get $x) (i64.const -49064778989728563)))
+        (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 33))))
+        (local.set $x (i64.mul (local.get $x) (i64.const -4265267296055464877)))
+        (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 33))))
+        (local.set $x (i64.rotl (local.get $x) (i64.extend_i32_u (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $x)))
+"#;
+
+/// How long `wat` takes to run with and without the profiling middleware.
+#[cfg(all(feature = "cranelift", feature = "middlewares"))]
+fn compare_profiling(c: &mut Criterion, name: &str, wat: &str, arg: i32) {
+    use std::sync::Arc;
+    use wasmer::*;
+    use wasmer_compiler_cranelift::Cranelift;
+    use wasmer_middlewares::Profiling;
+
+    let wasm = wat2wasm(wat.as_bytes()).unwrap().into_owned();
+
+    for profiling in [false, true] {
+        let mut compiler = Cranelift::new();
+        if profiling {
+            compiler.push_middleware(Arc::new(Profiling::new()));
+        }
+        let mut store = Store::new(compiler);
+        let module = Module::new(&store, &wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let run: TypedFunction<i32, i64> =
+            instance.exports.get_typed_function(&store, "run").unwrap();
+
+        let setting = if profiling { "with" } else { "without" };
+        c.bench_function(&format!("run {name} {setting} profiling"), |b| {
+            b.iter(|| black_box(run.call(&mut store, black_box(arg)).unwrap()))
+        });
+    }
+}
+
+fn run_profiling_benchmarks(_c: &mut Criterion) {
+    #[cfg(all(feature = "cranelift", feature = "middlewares"))]
+    {
+        compare_profiling(_c, "tight loop", TIGHT_LOOP, 100_000);
+        compare_profiling(_c, "calls", CALLS, 100_000);
+        compare_profiling(_c, "long body", LONG_BODY, 100_000);
+    }
+}
+
+criterion_group!(benches, run_profiling_benchmarks);
+
+criterion_main!(benches);
@@ -47,6 +47,21 @@ fn main() -> anyhow::Result<()> {
                 "tests/wast/spec/proposals/threads",
                 wast_processor,
             )?;
+            test_directory_module(
+                spectests,
+                "tests/wast/spec/proposals/tail-call",
+                wast_processor,
+            )?;
+            test_directory_module(
+                spectests,
+                "tests/wast/spec/proposals/exception-handling",
+                wast_processor,
+            )?;
+            test_directory_module(
+                spectests,
+                "tests/wast/spec/proposals/memory64",
+                wast_processor,
+            )?;
             // test_directory_module(spectests, "tests/wast/spec/proposals/bulk-memory-operations", wast_processor)?;
             Ok(())
         })?;
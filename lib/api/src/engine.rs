@@ -60,6 +60,19 @@ impl Engine {
         Ok(Arc::new(Artifact::deserialize(&self.0, bytes)?))
     }
 
+    #[cfg(all(feature = "sys", not(target_arch = "wasm32")))]
+    /// Deserializes a WebAssembly module which was previously serialized with
+    /// `Module::serialize`, checking its integrity and compatibility first.
+    ///
+    /// # Safety
+    /// See [`Artifact::deserialize_checked`].
+    pub unsafe fn deserialize_checked(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        Ok(Arc::new(Artifact::deserialize_checked(&self.0, bytes)?))
+    }
+
     #[cfg(all(feature = "sys", not(target_arch = "wasm32")))]
     /// Load a serialized WebAssembly module from a file and deserialize it.
     ///
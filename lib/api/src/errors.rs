@@ -5,7 +5,7 @@ use crate::jsc::trap::Trap;
 use std::fmt;
 use std::sync::Arc;
 use thiserror::Error;
-use wasmer_types::{FrameInfo, TrapCode};
+use wasmer_types::{FrameInfo, MemoryError, TrapCode, Type};
 #[cfg(feature = "sys")]
 use wasmer_vm::Trap;
 
@@ -31,6 +31,22 @@ pub enum LinkError {
     /// Insufficient resources available for linking.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// Several imports couldn't be resolved, or had the wrong type.
+    ///
+    /// Returned by [`Instance::new_with_resolver_strict`][crate::Instance::new_with_resolver_strict],
+    /// which checks every import before reporting.
+    #[cfg_attr(feature = "std", error("{}", display_import_errors(.0)))]
+    Imports(Vec<(String, String, ImportError)>),
+}
+
+#[cfg(feature = "std")]
+fn display_import_errors(errors: &[(String, String, ImportError)]) -> String {
+    let mut message = format!("{} import(s) could not be resolved:", errors.len());
+    for (module, field, error) in errors {
+        message.push_str(&format!("\n  {module:?}.{field:?}: {error}"));
+    }
+    message
 }
 
 /// An error while instantiating a module.
@@ -54,7 +70,7 @@ pub enum InstantiationError {
 
     /// The module was compiled with a CPU feature that is not available on
     /// the current host.
-    #[cfg_attr(feature = "std", error("missing required CPU features: {0:?}"))]
+    #[cfg_attr(feature = "std", error("missing required CPU features: {0}"))]
     CpuFeature(String),
 
     /// Import from a different [`Store`][super::Store].
@@ -68,6 +84,117 @@ pub enum InstantiationError {
     DifferentArchOS,
 }
 
+/// An error while manipulating a [`Table`][super::Table] from the host.
+///
+/// Host-side table operations return a [`RuntimeError`] wrapping one of these,
+/// which can be retrieved with [`RuntimeError::downcast_ref`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TableError {
+    /// The value's type doesn't match the table's element type.
+    #[error("table element type mismatch: expected {expected}, found {found}")]
+    TypeMismatch {
+        /// The table's element type.
+        expected: Type,
+        /// The type of the value that was provided.
+        found: Type,
+    },
+
+    /// The range `start..start + len` is outside the table.
+    #[error("table access out of bounds: {start} + {len} is larger than the table size {size}")]
+    OutOfBounds {
+        /// The first element accessed.
+        start: u32,
+        /// The number of elements accessed.
+        len: u32,
+        /// The current size of the table.
+        size: u32,
+    },
+
+    /// Growing the table would take it past its declared maximum.
+    #[error("cannot grow table of size {current} by {delta}: the maximum is {maximum}")]
+    MaximumExceeded {
+        /// The current size of the table.
+        current: u32,
+        /// The requested number of extra elements.
+        delta: u32,
+        /// The table's declared maximum.
+        maximum: u32,
+    },
+}
+
+impl From<TableError> for RuntimeError {
+    fn from(error: TableError) -> Self {
+        Self::user(Box::new(error))
+    }
+}
+
+/// An error from a host-side atomic wait or notify on a [`Memory`][crate::Memory].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AtomicsError {
+    /// The address isn't a multiple of the access size.
+    #[error("unaligned atomic access: {offset} is not a multiple of {alignment}")]
+    Unaligned {
+        /// The address that was accessed.
+        offset: u64,
+        /// The required alignment, in bytes.
+        alignment: u64,
+    },
+
+    /// The access is outside the memory.
+    #[error("atomic access out of bounds: {offset} + {len} is larger than the memory size {size}")]
+    OutOfBounds {
+        /// The address that was accessed.
+        offset: u64,
+        /// The number of bytes accessed.
+        len: u64,
+        /// The current size of the memory, in bytes.
+        size: u64,
+    },
+
+    /// Only shared memories can be waited on.
+    #[error("atomic wait requires a shared memory")]
+    NotShared,
+
+    /// The maximum number of waiters has been reached.
+    #[error("too many waiters")]
+    TooManyWaiters,
+}
+
+impl From<AtomicsError> for RuntimeError {
+    fn from(error: AtomicsError) -> Self {
+        Self::user(Box::new(error))
+    }
+}
+
+/// An error taking or copying the contents of a [`Memory`][crate::Memory]
+/// into an owned buffer.
+#[derive(Debug, Error)]
+pub enum MemoryBufferError {
+    /// The memory doesn't belong to the store it was used with.
+    #[error("the memory belongs to a different store")]
+    WrongStore,
+
+    /// The memory is shared, so other stores or threads may still be using
+    /// its allocation.
+    #[error("shared memories can't be taken out of their store")]
+    Shared,
+
+    /// The memory couldn't be taken or copied.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+}
+
+/// The error a call fails with when it runs out of stack.
+///
+/// The limit is set with [`Store::set_stack_limit`][crate::Store::set_stack_limit],
+/// and calls made from host functions share it with the call they're nested in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("call stack exhausted: the stack limit is {limit} bytes")]
+pub struct StackOverflow {
+    /// The store's stack limit, in bytes.
+    pub limit: usize,
+}
+
 /// A struct representing an aborted instruction execution, with a message
 /// indicating the cause.
 #[derive(Clone)]
@@ -153,6 +280,10 @@ impl RuntimeError {
     ///
     /// This error object can be passed through Wasm frames and later retrieved
     /// using the `downcast` method.
+    ///
+    /// With the `anyhow` feature, errors from `anyhow` should be converted
+    /// with `RuntimeError::from` (or `?`) instead, which keeps the error
+    /// they wrap available to `downcast`.
     pub fn user(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
         match error.downcast::<Self>() {
             Ok(err) => *err,
@@ -162,7 +293,9 @@ impl RuntimeError {
 
     /// Returns a reference the `message` stored in `Trap`.
     pub fn message(&self) -> String {
-        if let Some(trap_code) = self.inner.trap_code {
+        if let Some(overflow) = self.downcast_ref::<StackOverflow>() {
+            overflow.to_string()
+        } else if let Some(trap_code) = self.inner.trap_code {
             trap_code.message().to_string()
         } else {
             self.inner.source.to_string()
@@ -180,15 +313,61 @@ impl RuntimeError {
         self.inner.trap_code
     }
 
+    /// Whether the call was stopped with an `InterruptHandle` rather than
+    /// failing.
+    pub fn is_interrupted(&self) -> bool {
+        self.inner.trap_code == Some(TrapCode::Interrupted)
+    }
+
+    /// Whether the call ran out of stack. The limit it had is available
+    /// through [`StackOverflow`], using `downcast_ref`.
+    pub fn is_stack_overflow(&self) -> bool {
+        self.inner.trap_code == Some(TrapCode::StackOverflow)
+    }
+
+    /// Attaches the stack limit the failed call ran with if it ran out of
+    /// stack, keeping its trace.
+    #[cfg(feature = "sys")]
+    pub(crate) fn with_stack_limit(self, limit: usize) -> Self {
+        if !self.is_stack_overflow() || self.is::<StackOverflow>() {
+            return self;
+        }
+        Self::new_from_source(
+            Trap::user(Box::new(StackOverflow { limit })),
+            self.inner.wasm_trace.clone(),
+            Some(TrapCode::StackOverflow),
+        )
+    }
+
     // /// Returns trap code, if it's a Trap
     // pub fn to_source(self) -> &'static Trap {
     //     &self.inner.as_ref().source
     // }
 
     /// Attempts to downcast the `RuntimeError` to a concrete type.
-    pub fn downcast<T: std::error::Error + 'static>(self) -> Result<T, Self> {
+    ///
+    /// This finds the error a host function failed with, even when the trap
+    /// unwound through several host and Wasm frames, or (with the `anyhow`
+    /// feature) it was wrapped in an `anyhow::Error`.
+    pub fn downcast<T: std::error::Error + Send + Sync + 'static>(self) -> Result<T, Self> {
         match Arc::try_unwrap(self.inner) {
             Ok(inner) if inner.source.is::<T>() => Ok(inner.source.downcast::<T>().unwrap()),
+            #[cfg(feature = "anyhow")]
+            Ok(inner) if inner.source.is::<HostError>() => {
+                let RuntimeErrorInner {
+                    source,
+                    trap_code,
+                    wasm_trace,
+                } = inner;
+                let HostError(error) = source.downcast::<HostError>().unwrap();
+                error.downcast::<T>().map_err(|error| Self {
+                    inner: Arc::new(RuntimeErrorInner {
+                        source: Trap::user(Box::new(HostError(error))),
+                        trap_code,
+                        wasm_trace,
+                    }),
+                })
+            }
             Ok(inner) => Err(Self {
                 inner: Arc::new(inner),
             }),
@@ -197,13 +376,21 @@ impl RuntimeError {
     }
 
     /// Attempts to downcast the `RuntimeError` to a concrete type.
-    pub fn downcast_ref<T: std::error::Error + 'static>(&self) -> Option<&T> {
-        self.inner.as_ref().source.downcast_ref::<T>()
+    ///
+    /// Like [`RuntimeError::downcast`], this looks inside the `anyhow::Error`s
+    /// returned by host functions.
+    pub fn downcast_ref<T: std::error::Error + Send + Sync + 'static>(&self) -> Option<&T> {
+        let source = &self.inner.as_ref().source;
+        #[cfg(feature = "anyhow")]
+        if let Some(HostError(error)) = source.downcast_ref::<HostError>() {
+            return error.downcast_ref::<T>();
+        }
+        source.downcast_ref::<T>()
     }
 
     /// Returns true if the `RuntimeError` is the same as T
-    pub fn is<T: std::error::Error + 'static>(&self) -> bool {
-        self.inner.source.is::<T>()
+    pub fn is<T: std::error::Error + Send + Sync + 'static>(&self) -> bool {
+        self.downcast_ref::<T>().is_some()
     }
 }
 
@@ -229,19 +416,20 @@ impl fmt::Display for RuntimeError {
             writeln!(f)?;
             write!(f, "    at ")?;
             match frame.function_name() {
-                Some(name) => match rustc_demangle::try_demangle(name) {
-                    Ok(name) => write!(f, "{}", name)?,
-                    Err(_) => write!(f, "{}", name)?,
-                },
+                Some(name) => write!(f, "{}", name)?,
                 None => write!(f, "<unnamed>")?,
             }
             write!(
                 f,
-                " ({}[{}]:0x{:x})",
+                " ({}[{}]:0x{:x}",
                 name,
                 func_index,
                 frame.module_offset()
             )?;
+            if let Some(location) = frame.wat_location() {
+                write!(f, ", {}:{}", name, location)?;
+            }
+            write!(f, ")")?;
         }
         Ok(())
     }
@@ -253,6 +441,47 @@ impl std::error::Error for RuntimeError {
     }
 }
 
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for RuntimeError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<Self>() {
+            // The error came from a nested call into Wasm, so we re-raise
+            // the original error instead of wrapping it again
+            Ok(runtime_error) => runtime_error,
+            Err(error) => Self::user(Box::new(HostError(error))),
+        }
+    }
+}
+
+/// An [`anyhow::Error`] returned by a host function.
+///
+/// `anyhow::Error` doesn't implement [`std::error::Error`], and boxing it
+/// hides the error it wraps, so we keep it as is to be able to downcast it
+/// later.
+#[cfg(feature = "anyhow")]
+struct HostError(anyhow::Error);
+
+#[cfg(feature = "anyhow")]
+impl fmt::Debug for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl fmt::Display for HostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl std::error::Error for HostError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
 impl From<Box<dyn std::error::Error + Send + Sync>> for RuntimeError {
     fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
         match error.downcast::<Self>() {
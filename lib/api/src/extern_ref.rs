@@ -10,9 +10,25 @@ use crate::jsc::extern_ref as extern_ref_imp;
 use crate::sys::extern_ref as extern_ref_imp;
 use crate::vm::VMExternRef;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 /// An opaque reference to some data. This reference can be passed through Wasm.
+///
+/// # Lifetime
+///
+/// The data is owned by the [`Store`][crate::Store] the `ExternRef` was
+/// created in. Cloning an `ExternRef` is cheap and creates another handle to
+/// the same object, and two handles compare equal if they refer to the same
+/// object. Passing an `ExternRef` through tables, globals, function
+/// parameters, and results preserves its identity.
+///
+/// The object stays alive while the host holds at least one `ExternRef` to
+/// it, or while it is stored in a table or global. Once none of these hold,
+/// it will be dropped the next time [`Store::gc_externrefs()`] is called, or
+/// when the store itself is dropped. Use [`ExternRef::set_drop_observer()`]
+/// to be notified when that happens.
+///
+/// [`Store::gc_externrefs()`]: crate::Store::gc_externrefs
 pub struct ExternRef(pub(crate) extern_ref_imp::ExternRef);
 
 impl ExternRef {
@@ -32,6 +48,20 @@ impl ExternRef {
         self.0.downcast(store)
     }
 
+    /// Register a callback which will be invoked once the referenced object
+    /// has been dropped by the store, replacing any previous observer.
+    ///
+    /// This lets embedders find out when the guest stops referencing an
+    /// object. The callback is invoked immediately if the object was already
+    /// dropped.
+    pub fn set_drop_observer(
+        &self,
+        store: &mut impl AsStoreMut,
+        observer: impl FnOnce() + Send + Sync + 'static,
+    ) {
+        self.0.set_drop_observer(store, observer)
+    }
+
     pub(crate) fn vm_externref(&self) -> VMExternRef {
         self.0.vm_externref()
     }
@@ -8,6 +8,7 @@ use crate::sys::externals::function as function_impl;
 use crate::exports::{ExportError, Exportable};
 use crate::store::{AsStoreMut, AsStoreRef};
 use crate::vm::{VMExtern, VMExternFunction, VMFuncRef, VMFunctionCallback, VMTrampoline};
+use crate::weak::WeakFunction;
 use crate::{
     Extern, FunctionEnv, FunctionEnvMut, FunctionType, RuntimeError, TypedFunction, Value,
 };
@@ -157,6 +158,10 @@ impl Function {
     }
 
     /// Creates a new host `Function` from a native function.
+    ///
+    /// Native functions can take up to 26 parameters. Functions with more
+    /// parameters than that can be created with [`Function::new`], which
+    /// receives them as a `&[Value]`.
     pub fn new_typed<F, Args, Rets>(store: &mut impl AsStoreMut, func: F) -> Self
     where
         F: HostFunction<(), Args, Rets, WithoutEnv> + 'static + Send + Sync,
@@ -169,7 +174,8 @@ impl Function {
     /// Creates a new host `Function` with an environment from a typed function.
     ///
     /// The function signature is automatically retrieved using the
-    /// Rust typing system.
+    /// Rust typing system. Like [`Function::new_typed`], this supports up to
+    /// 26 parameters; use [`Function::new_with_env`] for more.
     ///
     /// # Example
     ///
@@ -451,6 +457,15 @@ impl Function {
         self.0.is_from_store(store)
     }
 
+    /// Creates a [`WeakFunction`] which can be upgraded back to this
+    /// function for as long as `store` is alive.
+    ///
+    /// Useful for host callbacks which outlive the store they were written
+    /// for.
+    pub fn downgrade(&self, store: &impl AsStoreRef) -> WeakFunction {
+        WeakFunction::new(self, store)
+    }
+
     pub(crate) fn to_vm_extern(&self) -> VMExtern {
         self.0.to_vm_extern()
     }
@@ -90,6 +90,9 @@ impl Global {
 
     /// Retrieves the current value [`Value`] that the Global has.
     ///
+    /// `v128` globals are supported everywhere except the `js` and `jsc`
+    /// backends, where JavaScript has no way to represent them.
+    ///
     /// # Example
     ///
     /// ```
@@ -9,10 +9,15 @@ use super::memory_view::MemoryView;
 use crate::exports::{ExportError, Exportable};
 use crate::store::{AsStoreMut, AsStoreRef};
 use crate::vm::{VMExtern, VMExternMemory, VMMemory};
+use crate::weak::WeakMemory;
 use crate::Extern;
 use crate::MemoryAccessError;
 use crate::MemoryType;
+#[cfg(feature = "sys")]
+use crate::{AtomicsError, MemoryBufferError, OwnedMemoryBuffer, Store};
 use std::mem::MaybeUninit;
+#[cfg(feature = "sys")]
+use std::time::Duration;
 use wasmer_types::{MemoryError, Pages};
 
 /// A WebAssembly `memory` instance.
@@ -121,6 +126,147 @@ impl Memory {
         self.0.grow(store, delta)
     }
 
+    #[cfg(feature = "sys")]
+    /// Register a callback which is invoked every time this memory grows.
+    ///
+    /// The callback is given the memory, its old size and its new size, and
+    /// runs after any successful grow performed through this store, whether
+    /// it came from the guest's `memory.grow`, [`Memory::grow()`] or the
+    /// WASI layer. It runs on the thread which grew the memory, before
+    /// control returns to WebAssembly, and can't access the store so it
+    /// can't re-enter WebAssembly.
+    ///
+    /// Callbacks are invoked in the order they were registered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use wasmer::{Memory, MemoryType, Pages, Store};
+    /// # let mut store = Store::default();
+    /// #
+    /// let m = Memory::new(&mut store, MemoryType::new(1, Some(3), false)).unwrap();
+    /// let grows = Arc::new(Mutex::new(Vec::new()));
+    /// let g = Arc::clone(&grows);
+    /// m.subscribe_grow(&mut store, move |_, old, new| g.lock().unwrap().push((old, new)));
+    ///
+    /// m.grow(&mut store, 2).unwrap();
+    ///
+    /// assert_eq!(*grows.lock().unwrap(), vec![(Pages(1), Pages(3))]);
+    /// ```
+    pub fn subscribe_grow(
+        &self,
+        store: &mut impl AsStoreMut,
+        mut callback: impl FnMut(&Memory, Pages, Pages) + Send + Sync + 'static,
+    ) {
+        let memory = self.clone();
+        self.0
+            .subscribe_grow(store, move |old, new| callback(&memory, old, new));
+    }
+
+    #[cfg(feature = "sys")]
+    /// The number of times this memory has grown.
+    ///
+    /// Growing a memory may move its data, invalidating any pointers
+    /// obtained from [`MemoryView::data_ptr()`]. Caching the generation
+    /// alongside such a pointer gives a cheap way to check whether it is
+    /// stale.
+    ///
+    /// Only grows performed through this store are counted.
+    pub fn generation(&self, store: &impl AsStoreRef) -> u64 {
+        self.0.generation(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Wake up to `count` threads waiting on the 32-bit cell at `offset`,
+    /// returning the number of threads that were woken.
+    ///
+    /// This is the host-side equivalent of `memory.atomic.notify`, and wakes
+    /// guest threads parked on `memory.atomic.wait32`/`memory.atomic.wait64`
+    /// as well as host threads using [`Memory::atomic_wait32()`] or
+    /// [`Memory::atomic_wait64()`], regardless of which store or instance
+    /// they are using the memory from.
+    ///
+    /// Memories that aren't shared can't have any waiters, so this always
+    /// returns `0` for them.
+    pub fn atomic_notify(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        count: u32,
+    ) -> Result<u32, AtomicsError> {
+        self.0.atomic_notify(store, offset, count)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Block the current thread until the 32-bit cell at `offset` is
+    /// notified, as long as it currently contains `expected`.
+    ///
+    /// This is the host-side equivalent of `memory.atomic.wait32` and uses
+    /// the same wait queues, so the thread can be woken by the guest's
+    /// `memory.atomic.notify` or by [`Memory::atomic_notify()`]. A `None`
+    /// timeout waits forever.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the memory isn't shared, or `offset` is out of bounds or not
+    /// 4-byte aligned.
+    pub fn atomic_wait32(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, AtomicsError> {
+        self.0.atomic_wait32(store, offset, expected, timeout)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Block the current thread until the 64-bit cell at `offset` is
+    /// notified, as long as it currently contains `expected`.
+    ///
+    /// This is the host-side equivalent of `memory.atomic.wait64`, see
+    /// [`Memory::atomic_wait32()`] for details. `offset` must be 8-byte
+    /// aligned.
+    pub fn atomic_wait64(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        expected: u64,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, AtomicsError> {
+        self.0.atomic_wait64(store, offset, expected, timeout)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Take the contents of this memory out of `store` without copying them,
+    /// once nothing is going to run in the store any more.
+    ///
+    /// The store is consumed, and with it every instance and function in it:
+    /// handles such as an [`Instance`][crate::Instance] that refer to the
+    /// store can't be used afterwards, so nothing can observe the memory
+    /// going away.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the memory doesn't belong to `store`, or if it is shared,
+    /// since another store or thread may still be using it. The store is
+    /// dropped either way; use [`Memory::try_clone_into_buffer()`] to keep
+    /// it.
+    pub fn take_buffer(&self, store: Store) -> Result<OwnedMemoryBuffer, MemoryBufferError> {
+        self.0.take_buffer(store)
+    }
+
+    #[cfg(feature = "sys")]
+    /// Copy the current contents of this memory into an owned buffer,
+    /// leaving the memory untouched.
+    pub fn try_clone_into_buffer(
+        &self,
+        store: &impl AsStoreRef,
+    ) -> Result<OwnedMemoryBuffer, MemoryBufferError> {
+        self.0.try_clone_into_buffer(store)
+    }
+
     /// Attempts to duplicate this memory (if its clonable) in a new store
     /// (copied memory)
     pub fn copy_to_store(
@@ -148,6 +294,15 @@ impl Memory {
         self.0.is_from_store(store)
     }
 
+    /// Creates a [`WeakMemory`] which can be upgraded back to this memory
+    /// for as long as `store` is alive.
+    ///
+    /// Useful for host callbacks which outlive the store they were written
+    /// for.
+    pub fn downgrade(&self, store: &impl AsStoreRef) -> WeakMemory {
+        WeakMemory::new(self, store)
+    }
+
     /// Attempts to clone this memory (if its clonable)
     pub fn try_clone(&self, store: &impl AsStoreRef) -> Result<VMMemory, MemoryError> {
         self.0.try_clone(store)
@@ -179,6 +334,17 @@ impl Memory {
 
 impl std::cmp::Eq for Memory {}
 
+/// The outcome of [`Memory::atomic_wait32()`] or [`Memory::atomic_wait64()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WaitResult {
+    /// The thread was woken up by a notify.
+    Ok,
+    /// The cell didn't contain the expected value, so the thread didn't wait.
+    NotEqual,
+    /// The timeout elapsed before the thread was notified.
+    TimedOut,
+}
+
 impl<'a> Exportable<'a> for Memory {
     fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
         match _extern {
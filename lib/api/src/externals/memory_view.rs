@@ -1,8 +1,8 @@
 use super::memory::{Memory, MemoryBuffer};
 use crate::store::AsStoreRef;
-use crate::MemoryAccessError;
+use crate::{MemoryAccessError, WasmSliceRef};
 use std::mem::MaybeUninit;
-use wasmer_types::Pages;
+use wasmer_types::{Pages, ValueType};
 
 #[cfg(feature = "js")]
 use crate::js::externals::memory_view as memory_view_impl;
@@ -143,6 +143,22 @@ impl<'a> MemoryView<'a> {
         self.0.write_u8(offset, val)
     }
 
+    /// Get a bounds-checked [`WasmSliceRef`] over `len` values of type `T`
+    /// starting at `offset`, which reads and writes them in place instead of
+    /// copying the whole array like [`WasmSlice::read_to_vec`].
+    ///
+    /// Returns a `MemoryAccessError` if any part of the slice is out of
+    /// bounds.
+    ///
+    /// [`WasmSlice::read_to_vec`]: crate::WasmSlice::read_to_vec
+    pub fn slice_at<T: ValueType>(
+        &self,
+        offset: u64,
+        len: u64,
+    ) -> Result<WasmSliceRef<'_, T>, MemoryAccessError> {
+        WasmSliceRef::new(self, offset, len)
+    }
+
     /// Copies the memory and returns it as a vector of bytes
     pub fn copy_to_vec(&self) -> Result<Vec<u8>, MemoryAccessError> {
         let mut new_memory = Vec::new();
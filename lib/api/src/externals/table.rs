@@ -12,6 +12,7 @@ use crate::Extern;
 use crate::RuntimeError;
 use crate::TableType;
 use crate::Value;
+use crate::{ExternRef, Function, TableError, Type};
 
 /// A WebAssembly `table` instance.
 ///
@@ -51,15 +52,90 @@ impl Table {
     }
 
     /// Sets an element `val` in the Table at the provided `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if `index` is out of bounds or `val` doesn't
+    /// match the table's element type.
     pub fn set(
         &self,
         store: &mut impl AsStoreMut,
         index: u32,
         val: Value,
     ) -> Result<(), RuntimeError> {
+        self.check_element_type(store, val.ty())?;
+        self.check_bounds(store, index, 1)?;
         self.0.set(store, index, val)
     }
 
+    /// Retrieves the function at `index` in a `funcref` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if `index` is out of bounds or this isn't a
+    /// `funcref` table.
+    pub fn get_funcref(
+        &self,
+        store: &mut impl AsStoreMut,
+        index: u32,
+    ) -> Result<Option<Function>, RuntimeError> {
+        self.check_element_type(store, Type::FuncRef)?;
+        self.check_bounds(store, index, 1)?;
+        match self.0.get(store, index) {
+            Some(Value::FuncRef(f)) => Ok(f),
+            _ => Err(self.out_of_bounds(store, index, 1).into()),
+        }
+    }
+
+    /// Sets the function at `index` in a `funcref` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if `index` is out of bounds or this isn't a
+    /// `funcref` table.
+    pub fn set_funcref(
+        &self,
+        store: &mut impl AsStoreMut,
+        index: u32,
+        func: Option<Function>,
+    ) -> Result<(), RuntimeError> {
+        self.set(store, index, Value::FuncRef(func))
+    }
+
+    /// Retrieves the reference at `index` in an `externref` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if `index` is out of bounds or this isn't an
+    /// `externref` table.
+    pub fn get_externref(
+        &self,
+        store: &mut impl AsStoreMut,
+        index: u32,
+    ) -> Result<Option<ExternRef>, RuntimeError> {
+        self.check_element_type(store, Type::ExternRef)?;
+        self.check_bounds(store, index, 1)?;
+        match self.0.get(store, index) {
+            Some(Value::ExternRef(e)) => Ok(e),
+            _ => Err(self.out_of_bounds(store, index, 1).into()),
+        }
+    }
+
+    /// Sets the reference at `index` in an `externref` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if `index` is out of bounds or this isn't an
+    /// `externref` table.
+    pub fn set_externref(
+        &self,
+        store: &mut impl AsStoreMut,
+        index: u32,
+        extern_ref: Option<ExternRef>,
+    ) -> Result<(), RuntimeError> {
+        self.set(store, index, Value::ExternRef(extern_ref))
+    }
+
     /// Retrieves the size of the `Table` (in elements)
     pub fn size(&self, store: &impl AsStoreRef) -> u32 {
         self.0.size(store)
@@ -73,23 +149,66 @@ impl Table {
     ///
     /// # Errors
     ///
-    /// Returns an error if the `delta` is out of bounds for the table.
+    /// Returns a [`TableError`] if `init` doesn't match the table's element
+    /// type or the new size would exceed the table's maximum, or a generic
+    /// error if the table couldn't be grown for any other reason.
     pub fn grow(
         &self,
         store: &mut impl AsStoreMut,
         delta: u32,
         init: Value,
     ) -> Result<u32, RuntimeError> {
+        self.check_element_type(store, init.ty())?;
+
+        let current = self.size(store);
+        if let Some(maximum) = self.ty(store).maximum {
+            if current.checked_add(delta).map_or(true, |n| n > maximum) {
+                return Err(TableError::MaximumExceeded {
+                    current,
+                    delta,
+                    maximum,
+                }
+                .into());
+            }
+        }
+
         self.0.grow(store, delta, init)
     }
 
+    /// Sets the `len` elements starting at `start` to `val`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if the range is out of bounds or `val`
+    /// doesn't match the table's element type. Nothing is written if an
+    /// error is returned.
+    pub fn fill(
+        &self,
+        store: &mut impl AsStoreMut,
+        start: u32,
+        val: Value,
+        len: u32,
+    ) -> Result<(), RuntimeError> {
+        self.check_element_type(store, val.ty())?;
+        self.check_bounds(store, start, len)?;
+
+        for index in start..start + len {
+            self.0.set(store, index, val.clone())?;
+        }
+
+        Ok(())
+    }
+
     /// Copies the `len` elements of `src_table` starting at `src_index`
     /// to the destination table `dst_table` at index `dst_index`.
     ///
+    /// Overlapping ranges within the same table are handled correctly.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the range is out of bounds of either the source or
-    /// destination tables.
+    /// Returns a [`TableError`] if the range is out of bounds of either the
+    /// source or destination tables, or if the tables have different element
+    /// types.
     pub fn copy(
         store: &mut impl AsStoreMut,
         dst_table: &Self,
@@ -98,9 +217,41 @@ impl Table {
         src_index: u32,
         len: u32,
     ) -> Result<(), RuntimeError> {
+        dst_table.check_element_type(store, src_table.ty(store).ty)?;
+        src_table.check_bounds(store, src_index, len)?;
+        dst_table.check_bounds(store, dst_index, len)?;
         table_impl::Table::copy(store, &dst_table.0, dst_index, &src_table.0, src_index, len)
     }
 
+    fn check_element_type(&self, store: &impl AsStoreRef, found: Type) -> Result<(), TableError> {
+        let expected = self.ty(store).ty;
+        if expected != found {
+            return Err(TableError::TypeMismatch { expected, found });
+        }
+        Ok(())
+    }
+
+    fn check_bounds(
+        &self,
+        store: &impl AsStoreRef,
+        start: u32,
+        len: u32,
+    ) -> Result<(), TableError> {
+        let size = self.size(store);
+        if start.checked_add(len).map_or(true, |end| end > size) {
+            return Err(self.out_of_bounds(store, start, len));
+        }
+        Ok(())
+    }
+
+    fn out_of_bounds(&self, store: &impl AsStoreRef, start: u32, len: u32) -> TableError {
+        TableError::OutOfBounds {
+            start,
+            len,
+            size: self.size(store),
+        }
+    }
+
     pub(crate) fn from_vm_extern(store: &mut impl AsStoreMut, extern_: VMExternTable) -> Self {
         Self(table_impl::Table::from_vm_extern(store, extern_))
     }
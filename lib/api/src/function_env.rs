@@ -1,9 +1,23 @@
-use std::{any::Any, fmt::Debug, marker::PhantomData};
+use std::{
+    any::Any,
+    fmt::Debug,
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+};
 
 use crate::vm::VMFunctionEnvironment;
 
 use crate::store::{AsStoreMut, AsStoreRef, StoreHandle, StoreMut, StoreObjects, StoreRef};
 
+/// The panic message used when a function environment's data is accessed
+/// while [`FunctionEnv::with_data_and_store_mut`] has lent it out.
+const DATA_LENT: &str =
+    "the function environment's data is already borrowed by `with_data_and_store_mut`";
+
+/// Takes the place of a function environment's data while it is lent out by
+/// [`FunctionEnv::with_data_and_store_mut`].
+struct LentData;
+
 #[derive(Debug)]
 #[repr(transparent)]
 /// An opaque reference to a function environment.
@@ -37,7 +51,7 @@ impl<T> FunctionEnv<T> {
             .get(store.as_store_ref().objects())
             .as_ref()
             .downcast_ref::<T>()
-            .unwrap()
+            .expect(DATA_LENT)
     }
 
     #[allow(dead_code)] // This function is only used in js
@@ -57,7 +71,44 @@ impl<T> FunctionEnv<T> {
             .get_mut(store.objects_mut())
             .as_mut()
             .downcast_mut::<T>()
-            .unwrap()
+            .expect(DATA_LENT)
+    }
+
+    /// Run `f` with mutable access to both the data and the rest of the
+    /// store.
+    ///
+    /// The data is moved out of the store while `f` runs, so the
+    /// [`StoreMut`] can be used for anything (calling functions, creating
+    /// objects, reading memory) without aliasing it. Accessing this
+    /// environment's data through the store in the meantime (e.g. from a host
+    /// function `f` calls back into) panics.
+    ///
+    /// See [`FunctionEnvMut::with_data_and_store_mut`] for examples.
+    pub fn with_data_and_store_mut<R>(
+        &self,
+        store: &mut impl AsStoreMut,
+        f: impl FnOnce(&mut T, StoreMut<'_>) -> R,
+    ) -> R
+    where
+        T: Any + Send + 'static + Sized,
+    {
+        let mut data = self
+            .handle
+            .get_mut(store.objects_mut())
+            .replace(Box::new(LentData));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let data = data.downcast_mut::<T>().expect(DATA_LENT);
+            f(data, store.as_store_mut())
+        }));
+
+        // Put the data back, even if `f` panicked
+        self.handle.get_mut(store.objects_mut()).replace(data);
+
+        match result {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        }
     }
 
     /// Convert it into a `FunctionEnvMut`
@@ -136,6 +187,11 @@ impl<T: Send + 'static> FunctionEnvMut<'_, T> {
     }
 
     /// Borrows a new mutable reference of both the attached Store and host state
+    ///
+    /// The returned [`StoreMut`] can still reach the host state (e.g. by
+    /// calling into a host function which uses it), so prefer
+    /// [`FunctionEnvMut::with_data_and_store_mut`], which moves the host
+    /// state out of the store while it is borrowed.
     pub fn data_and_store_mut(&mut self) -> (&mut T, StoreMut) {
         let data = self.func_env.as_mut(&mut self.store_mut) as *mut T;
         // telling the borrow check to close his eyes here
@@ -145,6 +201,58 @@ impl<T: Send + 'static> FunctionEnvMut<'_, T> {
         let data = unsafe { &mut *data };
         (data, self.store_mut.as_store_mut())
     }
+
+    /// Run `f` with mutable access to both the host state and the attached
+    /// store, so host functions can read guest memory or call guest exports
+    /// while updating their state.
+    ///
+    /// The host state is moved out of the store while `f` runs, which is
+    /// what lets both borrows be live at once. If something `f` does ends up
+    /// accessing this host state through the store (e.g. a guest export
+    /// calling back into a host function with the same environment), that
+    /// access panics.
+    ///
+    /// ```
+    /// # use wasmer::{FunctionEnvMut, Memory, WasmPtr};
+    /// struct Env {
+    ///     memory: Option<Memory>,
+    ///     messages: Vec<String>,
+    /// }
+    ///
+    /// fn log(mut env: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) {
+    ///     env.with_data_and_store_mut(|data, store| {
+    ///         let view = data.memory.as_ref().unwrap().view(&store);
+    ///         let message = ptr.read_utf8_string(&view, len).unwrap();
+    ///         data.messages.push(message);
+    ///     });
+    /// }
+    /// ```
+    ///
+    /// The environment itself stays borrowed, so the host state can't be
+    /// reached through it:
+    ///
+    /// ```compile_fail
+    /// # use wasmer::FunctionEnvMut;
+    /// fn increment(mut env: FunctionEnvMut<u32>) {
+    ///     env.with_data_and_store_mut(|data, _store| {
+    ///         *data += *env.data();
+    ///     });
+    /// }
+    /// ```
+    ///
+    /// and neither borrow can escape `f`:
+    ///
+    /// ```compile_fail
+    /// # use wasmer::FunctionEnvMut;
+    /// fn leak<'a>(env: &'a mut FunctionEnvMut<u32>) -> &'a mut u32 {
+    ///     env.with_data_and_store_mut(|data, _store| data)
+    /// }
+    /// ```
+    pub fn with_data_and_store_mut<R>(&mut self, f: impl FnOnce(&mut T, StoreMut<'_>) -> R) -> R {
+        self.func_env
+            .clone()
+            .with_data_and_store_mut(&mut self.store_mut, f)
+    }
 }
 
 impl<T> AsStoreRef for FunctionEnvMut<'_, T> {
@@ -4,7 +4,9 @@ use crate::{Extern, InstantiationError};
 use std::fmt;
 
 use crate::imports::Imports;
-use crate::store::AsStoreMut;
+use crate::resolver::{resolve_imports, Resolver};
+use crate::store::{AsStoreMut, AsStoreRef};
+use crate::weak::WeakInstance;
 
 #[cfg(feature = "js")]
 use crate::js::instance as instance_imp;
@@ -13,6 +15,9 @@ use crate::jsc::instance as instance_imp;
 #[cfg(feature = "sys")]
 use crate::sys::instance as instance_imp;
 
+/// The backend's handle to an instance.
+pub(crate) type InstanceHandle = instance_imp::Instance;
+
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
 ///
@@ -98,10 +103,100 @@ impl Instance {
         })
     }
 
+    /// Creates a new `Instance`, looking up each of the module's imports
+    /// with `resolver`.
+    ///
+    /// Several sources of imports can be combined with
+    /// [`Imports::chain_front`] and [`Imports::chain_back`].
+    ///
+    /// ## Errors
+    ///
+    /// Stops at the first import `resolver` can't provide, like
+    /// [`Instance::new`]. Use [`Instance::new_with_resolver_strict`] to get
+    /// every unresolved import at once.
+    #[allow(clippy::result_large_err)]
+    pub fn new_with_resolver(
+        store: &mut impl AsStoreMut,
+        module: &Module,
+        resolver: &dyn Resolver,
+    ) -> Result<Self, InstantiationError> {
+        let externs =
+            resolve_imports(&*store, module, resolver, false).map_err(InstantiationError::Link)?;
+        Self::new_by_index(store, module, &externs)
+    }
+
+    /// Like [`Instance::new_with_resolver`], but every import is resolved
+    /// and type-checked before instantiating.
+    ///
+    /// ## Errors
+    ///
+    /// If any imports are missing or have the wrong type, a
+    /// [`LinkError::Imports`][crate::LinkError::Imports] listing all of them
+    /// (with their module, field and expected type) is returned.
+    #[allow(clippy::result_large_err)]
+    pub fn new_with_resolver_strict(
+        store: &mut impl AsStoreMut,
+        module: &Module,
+        resolver: &dyn Resolver,
+    ) -> Result<Self, InstantiationError> {
+        let externs =
+            resolve_imports(&*store, module, resolver, true).map_err(InstantiationError::Link)?;
+        Self::new_by_index(store, module, &externs)
+    }
+
     /// Gets the [`Module`] associated with this instance.
     pub fn module(&self) -> &Module {
         &self.module
     }
+
+    /// Creates a [`WeakInstance`] which can be upgraded back to this
+    /// instance for as long as `store` is alive.
+    ///
+    /// Useful for host callbacks which outlive the store they were written
+    /// for.
+    pub fn downgrade(&self, store: &impl AsStoreRef) -> WeakInstance {
+        WeakInstance::new(self, store)
+    }
+
+    /// Label this instance in `store`, replacing any previous label.
+    ///
+    /// This is useful for telling several instances of the same [`Module`]
+    /// apart, e.g. one per tenant.
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let instance = Instance::new(&mut store, &module, &imports! {})?;
+    /// instance.set_label(&mut store, "tenant-a");
+    /// assert_eq!(instance.label(&store), Some("tenant-a"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_label(&self, store: &mut impl AsStoreMut, label: impl Into<String>) {
+        let label = label.into();
+        let store = store.as_store_mut();
+        let labels = &mut store.inner.instance_labels;
+        match labels
+            .iter_mut()
+            .find(|(instance, _)| *instance == self._inner)
+        {
+            Some((_, existing)) => *existing = label,
+            None => labels.push((self._inner.clone(), label)),
+        }
+    }
+
+    /// Gets the label given to this instance with [`Instance::set_label`].
+    pub fn label<'a>(&self, store: &'a impl AsStoreRef) -> Option<&'a str> {
+        store
+            .as_store_ref()
+            .inner
+            .instance_labels
+            .iter()
+            .find(|(instance, _)| *instance == self._inner)
+            .map(|(_, label)| label.as_str())
+    }
 }
 
 impl fmt::Debug for Instance {
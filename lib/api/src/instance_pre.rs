@@ -0,0 +1,103 @@
+use crate::resolver::{resolve_imports, Resolver};
+use crate::store::{AsStoreMut, AsStoreRef, StoreId};
+use crate::{Extern, Instance, InstantiationError, Module};
+use std::fmt;
+use std::sync::Arc;
+
+/// A [`Module`] whose imports have already been resolved and type-checked,
+/// so it can be instantiated many times with very little work.
+///
+/// The resolved imports are a snapshot: changing the [`Imports`] (or other
+/// [`Resolver`]) an `InstancePre` was created from doesn't affect it.
+///
+/// The imports belong to the [`Store`] the `InstancePre` was created with,
+/// so it can only instantiate the module in that store. Instantiating it
+/// in any other store fails with [`InstantiationError::DifferentStores`].
+///
+/// [`Imports`]: crate::Imports
+/// [`Store`]: crate::Store
+///
+/// ```
+/// # use wasmer::{imports, Global, InstancePre, Module, Store, Value};
+/// # fn main() -> anyhow::Result<()> {
+/// let mut store = Store::default();
+/// let module = Module::new(&store, r#"(module (import "host" "var" (global i32)))"#)?;
+/// let imports = imports! {
+///   "host" => {
+///     "var" => Global::new(&mut store, Value::I32(2))
+///   }
+/// };
+/// let pre = InstancePre::new(&store, &module, &imports)?;
+/// for _ in 0..10 {
+///     let instance = pre.instantiate(&mut store)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstancePre {
+    module: Module,
+    externs: Arc<[Extern]>,
+    store_id: StoreId,
+}
+
+impl InstancePre {
+    /// Resolve every import of `module` with `resolver`, checking their
+    /// types up front.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`LinkError::Imports`][crate::LinkError::Imports] listing
+    /// every missing or mismatched import, or
+    /// [`InstantiationError::DifferentStores`] if `resolver` returns externs
+    /// from another store.
+    #[allow(clippy::result_large_err)]
+    pub fn new(
+        store: &impl AsStoreRef,
+        module: &Module,
+        resolver: &dyn Resolver,
+    ) -> Result<Self, InstantiationError> {
+        let externs =
+            resolve_imports(store, module, resolver, true).map_err(InstantiationError::Link)?;
+        if externs.iter().any(|extern_| !extern_.is_from_store(store)) {
+            return Err(InstantiationError::DifferentStores);
+        }
+
+        Ok(Self {
+            module: module.clone(),
+            externs: externs.into(),
+            store_id: store.as_store_ref().objects().id(),
+        })
+    }
+
+    /// Create a new [`Instance`] of the module, reusing the pre-resolved
+    /// imports.
+    ///
+    /// ## Errors
+    ///
+    /// Fails with [`InstantiationError::DifferentStores`] if `store` isn't
+    /// the store this `InstancePre` was created with, and otherwise returns
+    /// the same errors as [`Instance::new`] (e.g. traps in the start
+    /// function).
+    #[allow(clippy::result_large_err)]
+    pub fn instantiate(&self, store: &mut impl AsStoreMut) -> Result<Instance, InstantiationError> {
+        if store.as_store_ref().objects().id() != self.store_id {
+            return Err(InstantiationError::DifferentStores);
+        }
+        Instance::new_by_index(store, &self.module, &self.externs)
+    }
+
+    /// Gets the [`Module`] this `InstancePre` instantiates.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+}
+
+impl fmt::Debug for InstancePre {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InstancePre")
+            .field("module", &self.module)
+            .field("store_id", &self.store_id)
+            .finish()
+    }
+}
@@ -194,6 +194,9 @@ impl AsJs for Extern {
             ExternType::Table(table_type) => {
                 Ok(Self::Table(Table::from_jsvalue(store, table_type, val)?))
             }
+            ExternType::Tag(_) => Err(JsError::new(
+                "Exception tags are not yet supported in the JS API",
+            )),
         }
     }
 }
@@ -3,7 +3,7 @@ use std::any::Any;
 use crate::js::vm::VMExternRef;
 use crate::store::{AsStoreMut, AsStoreRef};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct ExternRef;
 
@@ -22,6 +22,14 @@ impl ExternRef {
         unimplemented!("ExternRef is not yet supported in Javascript");
     }
 
+    pub fn set_drop_observer(
+        &self,
+        _store: &mut impl AsStoreMut,
+        _observer: impl FnOnce() + Send + Sync + 'static,
+    ) {
+        unimplemented!("ExternRef is not yet supported in Javascript");
+    }
+
     pub(crate) fn vm_externref(&self) -> VMExternRef {
         unimplemented!("ExternRef is not yet supported in Javascript");
     }
@@ -12,7 +12,7 @@ use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::panic::{self, AssertUnwindSafe};
 
-use wasmer_types::{FunctionType, NativeWasmType, RawValue};
+use wasmer_types::{FunctionType, NativeWasmType, RawValue, Type};
 
 use js_sys::{Array, Function as JSFunction};
 use wasm_bindgen::prelude::*;
@@ -212,6 +212,13 @@ impl Function {
         store: &mut impl AsStoreMut,
         params: &[Value],
     ) -> Result<Box<[Value]>, RuntimeError> {
+        let ty = self.ty(store);
+        if ty.params().contains(&Type::V128) || ty.results().contains(&Type::V128) {
+            return Err(RuntimeError::new(
+                "v128 values can't cross the JavaScript/WebAssembly boundary",
+            ));
+        }
+
         // Annotation is here to prevent spurious IDE warnings.
         let arr = js_sys::Array::new_with_length(params.len() as u32);
 
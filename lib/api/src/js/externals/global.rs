@@ -33,6 +33,11 @@ impl Global {
                 "cross-`WasmerEnv` values are not supported",
             ));
         }
+        if val.ty() == Type::V128 {
+            return Err(RuntimeError::new(
+                "v128 values can't cross the JavaScript/WebAssembly boundary",
+            ));
+        }
         let global_ty = GlobalType {
             mutability,
             ty: val.ty(),
@@ -117,6 +122,11 @@ impl Global {
             Value::I64(i) => JsValue::from_f64(i as _),
             Value::F32(f) => JsValue::from_f64(f as _),
             Value::F64(f) => JsValue::from_f64(f),
+            Value::V128(_) => {
+                return Err(RuntimeError::new(
+                    "v128 values can't cross the JavaScript/WebAssembly boundary",
+                ))
+            }
             _ => {
                 return Err(RuntimeError::new(
                     "The type is not yet supported in the JS Global API".to_owned(),
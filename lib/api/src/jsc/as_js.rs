@@ -149,6 +149,10 @@ impl AsJs for Extern {
                     VMTable::new(obj_val, table_type.clone()),
                 )))
             }
+            ExternType::Tag(_) => Err(JSValue::string(
+                &context,
+                "Exception tags are not yet supported in the JSC API".to_string(),
+            )),
         }
     }
 }
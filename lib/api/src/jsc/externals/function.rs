@@ -13,7 +13,7 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::panic::{self, AssertUnwindSafe};
 
-use wasmer_types::{FunctionType, RawValue};
+use wasmer_types::{FunctionType, RawValue, Type};
 
 use rusty_jsc::{
     callback, callback_closure, JSContext, JSObject, JSObjectCallAsFunctionCallback, JSValue,
@@ -183,6 +183,13 @@ impl Function {
         store: &mut impl AsStoreMut,
         params: &[Value],
     ) -> Result<Box<[Value]>, RuntimeError> {
+        let ty = self.ty(store);
+        if ty.params().contains(&Type::V128) || ty.results().contains(&Type::V128) {
+            return Err(RuntimeError::new(
+                "v128 values can't cross the JavaScript/WebAssembly boundary",
+            ));
+        }
+
         let store_mut = store.as_store_mut();
         let engine = store_mut.engine();
         let context = engine.0.context();
@@ -32,6 +32,11 @@ impl Global {
         if !val.is_from_store(store) {
             return Err(RuntimeError::new("cross-`Store` values are not supported"));
         }
+        if val.ty() == Type::V128 {
+            return Err(RuntimeError::new(
+                "v128 values can't cross the JavaScript/WebAssembly boundary",
+            ));
+        }
         let global_ty = GlobalType {
             mutability,
             ty: val.ty(),
@@ -90,6 +95,11 @@ impl Global {
     }
 
     pub fn set(&self, store: &mut impl AsStoreMut, val: Value) -> Result<(), RuntimeError> {
+        if val.ty() == Type::V128 {
+            return Err(RuntimeError::new(
+                "v128 values can't cross the JavaScript/WebAssembly boundary",
+            ));
+        }
         let store_mut = store.as_store_mut();
         let new_value = val.as_jsvalue(&store_mut);
         let engine = store_mut.engine();
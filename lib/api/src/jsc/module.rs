@@ -231,6 +231,10 @@ impl Module {
         self.info().custom_sections(name)
     }
 
+    pub fn custom_section_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.info().custom_section_names()
+    }
+
     pub(crate) fn info(&self) -> &ModuleInfo {
         &self.info
     }
@@ -9,8 +9,9 @@ use core::convert::TryFrom;
 use std::vec::Vec;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
-    ExportIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalType, ImportIndex, MemoryIndex,
-    MemoryType, ModuleInfo, Pages, SignatureIndex, TableIndex, TableType, Type,
+    CustomSectionIndex, ExportIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalType,
+    ImportIndex, MemoryIndex, MemoryType, ModuleInfo, Pages, SignatureIndex, TableIndex, TableType,
+    Type,
 };
 
 use wasmparser::{
@@ -33,6 +34,14 @@ impl ModuleInfoPolyfill {
         Ok(())
     }
 
+    pub(crate) fn declare_custom_section(&mut self, name: &str, data: &[u8]) -> WasmResult<()> {
+        let index = CustomSectionIndex::new(self.info.custom_sections_data.len());
+        self.info.custom_sections.insert(String::from(name), index);
+        self.info.custom_sections_data.push(Box::from(data));
+        self.info.custom_section_names.push(String::from(name));
+        Ok(())
+    }
+
     pub(crate) fn declare_import(
         &mut self,
         import: ImportIndex,
@@ -284,14 +293,19 @@ pub fn translate_module<'data>(data: &'data [u8]) -> WasmResult<ModuleInfoPolyfi
             }
 
             Payload::CustomSection {
-                name: "name",
+                name,
                 data,
                 data_offset,
                 ..
-            } => parse_name_section(
-                NameSectionReader::new(data, data_offset).map_err(transform_err)?,
-                &mut module_info,
-            )?,
+            } => {
+                module_info.declare_custom_section(name, data)?;
+                if name == "name" {
+                    parse_name_section(
+                        NameSectionReader::new(data, data_offset).map_err(transform_err)?,
+                        &mut module_info,
+                    )?;
+                }
+            }
 
             _ => {}
         }
@@ -388,6 +402,7 @@ pub fn parse_import_section<'data>(
                         minimum: Pages(initial as u32),
                         maximum: maximum.map(|p| Pages(p as u32)),
                         shared,
+                        memory64: false,
                     },
                     module_name,
                     field_name.unwrap_or_default(),
@@ -475,6 +490,7 @@ pub fn parse_memory_section(
             minimum: Pages(initial as u32),
             maximum: maximum.map(|p| Pages(p as u32)),
             shared,
+            memory64: false,
         })?;
     }
 
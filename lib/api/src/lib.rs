@@ -309,7 +309,17 @@
 //! - `compilation`
 #![cfg_attr(feature = "compiler", doc = "(enabled),")]
 #![cfg_attr(not(feature = "compiler"), doc = "(disabled),")]
-//!   enables compilation with the wasmer engine.
+//!   enables compilation with the wasmer engine,
+//! - `symbolication`
+#![cfg_attr(feature = "symbolication", doc = "(enabled),")]
+#![cfg_attr(not(feature = "symbolication"), doc = "(disabled),")]
+//!   names the functions missing from the `name` section after their
+//!   DWARF debug info in trap backtraces,
+//! - `dump`
+#![cfg_attr(feature = "dump", doc = "(enabled),")]
+#![cfg_attr(not(feature = "dump"), doc = "(disabled),")]
+//!   enables dumping what the compilers generate for each function with
+//!   `CompilerConfig::enable_dump`.
 //!
 #![cfg_attr(
     feature = "js",
@@ -435,15 +445,18 @@ mod externals;
 mod function_env;
 mod imports;
 mod instance;
+mod instance_pre;
 mod into_bytes;
 mod mem_access;
 mod module;
 mod native_type;
 mod ptr;
+mod resolver;
 mod store;
 mod typed_function;
 mod value;
 pub mod vm;
+mod weak;
 
 #[cfg(feature = "sys")]
 mod sys;
@@ -463,27 +476,37 @@ mod jsc;
 #[cfg(feature = "jsc")]
 pub use jsc::*;
 
-pub use crate::externals::{Extern, Function, Global, HostFunction, Memory, MemoryView, Table};
+pub use crate::externals::{
+    Extern, Function, Global, HostFunction, Memory, MemoryView, Table, WaitResult,
+};
 pub use access::WasmSliceAccess;
 pub use engine::{AsEngineRef, Engine, EngineRef};
-pub use errors::{InstantiationError, LinkError, RuntimeError};
+pub use errors::{
+    AtomicsError, InstantiationError, LinkError, MemoryBufferError, RuntimeError, StackOverflow,
+    TableError,
+};
 pub use exports::{ExportError, Exportable, Exports, ExportsIterator};
 pub use extern_ref::ExternRef;
 pub use function_env::{FunctionEnv, FunctionEnvMut};
 pub use imports::Imports;
 pub use instance::Instance;
+pub use instance_pre::InstancePre;
 pub use into_bytes::IntoBytes;
-pub use mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
+pub use mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter, WasmSliceRef};
 pub use module::{IoCompileError, Module};
 pub use native_type::{FromToNativeWasmType, NativeWasmTypeInto, WasmTypeList};
 pub use ptr::{Memory32, Memory64, MemorySize, WasmPtr, WasmPtr64};
-pub use store::{AsStoreMut, AsStoreRef, OnCalledHandler, Store, StoreId, StoreMut, StoreRef};
+pub use resolver::{Resolver, ResolverChain};
+pub use store::{
+    AsStoreMut, AsStoreRef, InterruptHandle, OnCalledHandler, Store, StoreId, StoreMut, StoreRef,
+};
 #[cfg(feature = "sys")]
 pub use store::{TrapHandlerFn, Tunables};
 #[cfg(any(feature = "sys", feature = "jsc"))]
 pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
 pub use typed_function::TypedFunction;
 pub use value::Value;
+pub use weak::{WeakFunction, WeakInstance, WeakMemory};
 
 // Reexport from other modules
 
@@ -491,10 +514,12 @@ pub use wasmer_derive::ValueType;
 // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
 pub use wasmer_types::{
     is_wasm, Bytes, CompileError, CpuFeature, DeserializeError, ExportIndex, ExportType,
-    ExternType, FrameInfo, FunctionType, GlobalInit, GlobalType, ImportType, LocalFunctionIndex,
-    MemoryError, MemoryType, MiddlewareError, Mutability, OnCalledAction, Pages,
-    ParseCpuFeatureError, SerializeError, TableType, Target, Type, ValueType, WasmError,
-    WasmResult, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    ExternType, FrameInfo, FunctionIndex, FunctionLocation, FunctionType, GlobalInit, GlobalType,
+    ImportError, ImportType, LocalFunctionIndex, MemoryError, MemoryType, MiddlewareError,
+    ModuleInterface, Mutability, OnCalledAction, Pages, ParseCpuFeatureError, SerializeError,
+    TableType, TagType, Target, Type, ValidationError, ValidationErrorKind, ValueType, WasmError,
+    WasmResult, WatLocation, WatSourceMap, V128, WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_MIN_PAGES,
+    WASM_PAGE_SIZE,
 };
 #[cfg(feature = "wat")]
 pub use wat::parse_bytes as wat2wasm;
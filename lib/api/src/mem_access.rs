@@ -407,3 +407,181 @@ impl<'a, T: ValueType> DoubleEndedIterator for WasmSliceIter<'a, T> {
 }
 
 impl<'a, T: ValueType> ExactSizeIterator for WasmSliceIter<'a, T> {}
+
+/// A bounds-checked view of an array of values in Wasm memory, created with
+/// [`MemoryView::slice_at`].
+///
+/// Unlike [`WasmSlice::read_to_vec`], this doesn't copy the whole array out of
+/// the memory: elements are read and written in place, with volatile
+/// accesses so that they stay well-defined if the guest (or another thread
+/// sharing the memory) modifies them concurrently. Such reads may observe a
+/// mix of old and new bytes, but never anything outside the slice.
+///
+/// The bounds are checked once, when the `WasmSliceRef` is created. Calling
+/// into WebAssembly or growing the memory needs mutable access to the store,
+/// so it's impossible while a `WasmSliceRef` (or the [`MemoryView`] it
+/// borrows) is alive; get a new view afterwards instead.
+///
+/// The address is not required to be aligned: unaligned accesses are fully
+/// supported.
+#[derive(Clone, Copy)]
+pub struct WasmSliceRef<'a, T: ValueType> {
+    buffer: MemoryBuffer<'a>,
+    offset: u64,
+    len: u64,
+    marker: PhantomData<*mut T>,
+}
+
+impl<'a, T: ValueType> WasmSliceRef<'a, T> {
+    pub(crate) fn new(
+        view: &'a MemoryView,
+        offset: u64,
+        len: u64,
+    ) -> Result<Self, MemoryAccessError> {
+        let end = len
+            .checked_mul(mem::size_of::<T>() as u64)
+            .and_then(|total_len| offset.checked_add(total_len))
+            .ok_or(MemoryAccessError::Overflow)?;
+        if end > view.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        Ok(Self {
+            buffer: view.buffer(),
+            offset,
+            len,
+            marker: PhantomData,
+        })
+    }
+
+    /// Get the offset into Wasm linear memory for this `WasmSliceRef`.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the number of elements in this slice.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the number of elements is 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads the element at `idx`, or returns `None` if it's out of range.
+    #[inline]
+    pub fn get(&self, idx: u64) -> Option<T> {
+        if idx >= self.len {
+            return None;
+        }
+        let mut out = MaybeUninit::<T>::uninit();
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(
+                out.as_mut_ptr() as *mut MaybeUninit<u8>,
+                mem::size_of::<T>(),
+            )
+        };
+        self.buffer
+            .read_uninit(self.element_offset(idx), bytes)
+            .expect("WasmSliceRef bounds were checked when it was created");
+        Some(unsafe { out.assume_init() })
+    }
+
+    /// Writes `val` to the element at `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    #[inline]
+    pub fn set(&self, idx: u64, val: T) {
+        assert!(idx < self.len, "WasmSliceRef index out of bounds");
+        let mut data = MaybeUninit::new(val);
+        let data = unsafe {
+            slice::from_raw_parts_mut(
+                data.as_mut_ptr() as *mut MaybeUninit<u8>,
+                mem::size_of::<T>(),
+            )
+        };
+        val.zero_padding_bytes(data);
+        let bytes = unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, data.len()) };
+        self.buffer
+            .write(self.element_offset(idx), bytes)
+            .expect("WasmSliceRef bounds were checked when it was created");
+    }
+
+    /// Get a `WasmSliceRef` for a subslice of this slice, or `None` if
+    /// `range` is out of range.
+    #[inline]
+    pub fn subslice(&self, range: Range<u64>) -> Option<Self> {
+        if range.start > range.end || range.end > self.len {
+            return None;
+        }
+        Some(Self {
+            buffer: self.buffer,
+            offset: self.element_offset(range.start),
+            len: range.end - range.start,
+            marker: PhantomData,
+        })
+    }
+
+    /// Copies the whole slice into `dst` with a single volatile memcpy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dst` doesn't have the same length as this slice.
+    #[inline]
+    pub fn copy_to_slice(&self, dst: &mut [T]) {
+        assert_eq!(
+            dst.len() as u64,
+            self.len,
+            "slice length doesn't match WasmSliceRef length"
+        );
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(
+                dst.as_mut_ptr() as *mut MaybeUninit<u8>,
+                dst.len() * mem::size_of::<T>(),
+            )
+        };
+        self.buffer
+            .read_uninit(self.offset, bytes)
+            .expect("WasmSliceRef bounds were checked when it was created");
+    }
+
+    /// Copies `src` over the whole slice with a single volatile memcpy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` doesn't have the same length as this slice.
+    #[inline]
+    pub fn copy_from_slice(&self, src: &[T]) {
+        assert_eq!(
+            src.len() as u64,
+            self.len,
+            "slice length doesn't match WasmSliceRef length"
+        );
+        let bytes = unsafe {
+            slice::from_raw_parts(src.as_ptr() as *const u8, src.len() * mem::size_of::<T>())
+        };
+        self.buffer
+            .write(self.offset, bytes)
+            .expect("WasmSliceRef bounds were checked when it was created");
+    }
+
+    fn element_offset(&self, idx: u64) -> u64 {
+        debug_assert!(idx <= self.len);
+        self.offset + idx * mem::size_of::<T>() as u64
+    }
+}
+
+impl<'a, T: ValueType> fmt::Debug for WasmSliceRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WasmSliceRef(offset: {}, len: {})",
+            self.offset, self.len
+        )
+    }
+}
@@ -6,12 +6,16 @@ use std::path::Path;
 
 use crate::engine::AsEngineRef;
 use thiserror::Error;
+#[cfg(all(feature = "sys", feature = "wat"))]
+use wasmer_types::is_wasm;
 #[cfg(feature = "wat")]
 use wasmer_types::WasmError;
 use wasmer_types::{
     CompileError, DeserializeError, ExportsIterator, ImportsIterator, ModuleInfo, SerializeError,
 };
-use wasmer_types::{ExportType, ImportType};
+use wasmer_types::{ExportType, ImportType, ModuleInterface, WatSourceMap};
+#[cfg(feature = "sys")]
+use wasmer_types::{Features, Triple, ValidationError};
 
 use crate::into_bytes::IntoBytes;
 
@@ -22,6 +26,14 @@ use crate::jsc::module as module_imp;
 #[cfg(feature = "sys")]
 use crate::sys::module as module_imp;
 
+#[cfg(feature = "wat")]
+pub(crate) fn wat_error(error: wat::Error) -> CompileError {
+    CompileError::Wasm(WasmError::Generic(format!(
+        "Error when converting wat: {}",
+        error
+    )))
+}
+
 /// IO Error on a Module Compilation
 #[derive(Error, Debug)]
 pub enum IoCompileError {
@@ -115,16 +127,15 @@ impl Module {
     /// ```
     pub fn new(engine: &impl AsEngineRef, bytes: impl AsRef<[u8]>) -> Result<Self, CompileError> {
         #[cfg(feature = "wat")]
-        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
-            CompileError::Wasm(WasmError::Generic(format!(
-                "Error when converting wat: {}",
-                e
-            )))
-        })?;
+        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(wat_error)?;
         Self::from_binary(engine, bytes.as_ref())
     }
 
     /// Creates a new WebAssembly module from a file path.
+    ///
+    /// The module is named after the file's stem (`foo` for
+    /// `path/to/foo.wasm`), so that the serialized artifact doesn't depend on
+    /// where the file is.
     pub fn from_file(
         engine: &impl AsEngineRef,
         file: impl AsRef<Path>,
@@ -133,13 +144,32 @@ impl Module {
         let canonical = file_ref.canonicalize()?;
         let wasm_bytes = std::fs::read(file_ref)?;
         let mut module = Self::new(engine, wasm_bytes)?;
-        // Set the module name to the absolute path of the filename.
-        // This is useful for debugging the stack traces.
-        let filename = canonical.as_path().to_str().unwrap();
-        module.set_name(filename);
+        // Set the module name to the file's stem, which is useful for
+        // debugging the stack traces. The rest of the path is left out, as
+        // it would end up in the serialized artifact.
+        if let Some(stem) = canonical.file_stem().and_then(|stem| stem.to_str()) {
+            module.set_name(stem);
+        }
         Ok(module)
     }
 
+    /// Creates a new WebAssembly module like [`Module::new`], but when
+    /// `bytes` is WebAssembly text, also keeps a map from the compiled
+    /// binary back to the text (see [`Module::source_map`]).
+    ///
+    /// Validation errors then say where in the text they are, and trap
+    /// backtraces say which line of the text each frame is at.
+    #[cfg(all(feature = "sys", feature = "wat"))]
+    pub fn new_with_source_map(
+        engine: &impl AsEngineRef,
+        bytes: impl AsRef<[u8]>,
+    ) -> Result<Self, CompileError> {
+        if is_wasm(bytes.as_ref()) {
+            return Self::from_binary(engine, bytes.as_ref());
+        }
+        Ok(Self(module_imp::Module::from_wat(engine, bytes.as_ref())?))
+    }
+
     /// Creates a new WebAssembly module from a Wasm binary.
     ///
     /// Opposed to [`Module::new`], this function is not compatible with
@@ -176,6 +206,64 @@ impl Module {
         module_imp::Module::validate(engine, binary)
     }
 
+    /// Validates a WebAssembly module against an explicit set of features
+    /// and a target, rather than the ones an engine was configured with.
+    ///
+    /// Unlike [`Module::validate`], the error says what went wrong in a
+    /// machine-readable way, and where.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// let wasm = wat2wasm(br#"(module (func (param v128)))"#).unwrap();
+    /// let mut features = Features::new();
+    /// features.simd(false);
+    ///
+    /// let error = Module::validate_with_features(&wasm, &features, &Triple::host()).unwrap_err();
+    ///
+    /// assert_eq!(
+    ///     error.kind,
+    ///     ValidationErrorKind::UnsupportedProposal { proposal: "simd" }
+    /// );
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn validate_with_features(
+        binary: &[u8],
+        features: &Features,
+        target: &Triple,
+    ) -> Result<(), ValidationError> {
+        module_imp::Module::validate_with_features(binary, features, target)
+    }
+
+    /// Like [`Module::validate_with_features`], but reports an error for every
+    /// invalid function instead of stopping at the first one.
+    #[cfg(feature = "sys")]
+    pub fn validate_all_with_features(
+        binary: &[u8],
+        features: &Features,
+        target: &Triple,
+    ) -> Result<(), Vec<ValidationError>> {
+        module_imp::Module::validate_all_with_features(binary, features, target)
+    }
+
+    /// Work out which WebAssembly proposals a module needs, so it can be
+    /// checked against an engine's [features][crate::NativeEngineExt::features]
+    /// before compiling it.
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// let wasm = wat2wasm(br#"(module (func (param v128)))"#).unwrap();
+    ///
+    /// let required = Module::required_features(&wasm).unwrap();
+    ///
+    /// assert_eq!(required.enabled(), vec!["simd"]);
+    /// ```
+    #[cfg(feature = "sys")]
+    pub fn required_features(binary: &[u8]) -> Result<Features, ValidationError> {
+        module_imp::Module::required_features(binary)
+    }
+
     /// Serializes a module into a binary representation that the `Engine`
     /// can later process via [`Module::deserialize`].
     ///
@@ -184,6 +272,34 @@ impl Module {
     /// This function will return a custom binary format that will be different than
     /// the `wasm` binary format, but faster to load in Native hosts.
     ///
+    /// # Reproducibility
+    ///
+    /// The artifact only depends on the module and on the settings it was
+    /// compiled with, so compiling the same module with the same settings
+    /// gives the same bytes, whichever machine or thread compiled it. That
+    /// lets a precompiled artifact be checked against its Wasm by recompiling
+    /// it and comparing the hashes.
+    ///
+    /// The settings which change the artifact are:
+    ///
+    /// - the version of Wasmer and the target (triple and CPU features),
+    ///   which are recorded in the artifact;
+    /// - the compiler and its code generation settings (optimization level,
+    ///   PIC, NaN canonicalization, ...), recorded as the engine's
+    ///   [`deterministic_id`][crate::Engine::deterministic_id];
+    /// - the enabled [`Features`][crate::Features] and the memory and table
+    ///   styles chosen by the [`Tunables`][crate::Tunables], which are
+    ///   recorded too;
+    /// - the middlewares and their settings, recorded as part of the engine's
+    ///   `deterministic_id` too, from each middleware's
+    ///   [`deterministic_id`][crate::ModuleMiddleware::deterministic_id]
+    ///   (so a custom middleware which changes the code should return one);
+    /// - the module's name, given by its name section or by
+    ///   [`Module::set_name`] (and by [`Module::from_file`], which uses the
+    ///   file's stem).
+    ///
+    /// The number of threads compiling the module doesn't change it.
+    ///
     /// # Usage
     ///
     /// ```ignore
@@ -291,6 +407,41 @@ impl Module {
         Ok(Self(module_imp::Module::deserialize(engine, bytes)?))
     }
 
+    /// Deserializes a serialized Module binary into a `Module`, checking that
+    /// it is intact and compatible with the engine first.
+    ///
+    /// Unlike [`Module::deserialize`], this verifies the checksum embedded by
+    /// [`Module::serialize`], makes sure the artifact was serialized by this
+    /// version of Wasmer for the engine's target and CPU features, and
+    /// bounds-checks its contents, all before any executable memory is
+    /// mapped. Prefer it for artifacts received from elsewhere, e.g. over
+    /// the network.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut store = Store::default();
+    /// let module = unsafe { Module::deserialize_checked(&store, serialized_data)? };
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    /// This function loads executable code into memory. The checks protect
+    /// against corrupted and incompatible artifacts, but the artifact must
+    /// still come from a trusted source.
+    #[cfg(feature = "sys")]
+    pub unsafe fn deserialize_checked(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+    ) -> Result<Self, DeserializeError> {
+        Ok(Self(module_imp::Module::deserialize_checked(
+            engine, bytes,
+        )?))
+    }
+
     /// Deserializes a serialized Module located in a `Path` into a `Module`.
     /// > Note: the module has to be serialized before with the `serialize` method.
     ///
@@ -391,6 +542,49 @@ impl Module {
         self.0.set_name(name)
     }
 
+    /// Returns the map back to the WebAssembly text this module was compiled
+    /// from, if it was compiled with [`Module::new_with_source_map`] or given
+    /// one with [`Module::set_source_map`].
+    ///
+    /// Trap backtraces use it to say which line of the text each frame is
+    /// at. It's never serialized along with the module.
+    pub fn source_map(&self) -> Option<&WatSourceMap> {
+        self.info().source_map.as_deref()
+    }
+
+    /// Sets the map back to the WebAssembly text this module was compiled
+    /// from, for modules which were compiled from a binary that was
+    /// converted separately (see [`wat2wasm_with_source_map`][crate::wat2wasm_with_source_map]).
+    ///
+    /// Like [`Module::set_name`], it returns `false` if the module has been
+    /// cloned or instantiated.
+    #[cfg(feature = "sys")]
+    pub fn set_source_map(&mut self, source_map: WatSourceMap) -> bool {
+        self.0.set_source_map(source_map)
+    }
+
+    /// Returns the compiler and the settings which affect the generated
+    /// code that this module was compiled with (eg. `llvm-O2`).
+    ///
+    /// For deserialized modules it's read from the serialized metadata, which
+    /// only [`Module::deserialize_checked`] looks at. It's empty for modules
+    /// loaded any other way, or serialized by older versions of Wasmer.
+    #[cfg(feature = "sys")]
+    pub fn compiler(&self) -> &str {
+        self.0.compiler()
+    }
+
+    /// The address range of the machine code of each function defined in
+    /// this module (so not the imported ones), in the order they're defined.
+    ///
+    /// These are the same addresses written to the engine's perf map. For
+    /// lazily compiled modules they're the stubs which compile the functions
+    /// on their first call.
+    #[cfg(feature = "sys")]
+    pub fn function_code_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        self.0.function_code_ranges()
+    }
+
     /// Returns an iterator over the imported types in the Module.
     ///
     /// The order of the imports is guaranteed to be the same as in the
@@ -446,17 +640,79 @@ impl Module {
         self.0.exports()
     }
 
+    /// Returns everything the module imports and exports, as a single
+    /// description that can be compared or serialized.
+    ///
+    /// With the `enable-serde` feature, the [`ModuleInterface`] serializes to
+    /// the same JSON that `wasmer inspect --json` prints.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let mut store = Store::default();
+    /// let wat = r#"(module
+    ///     (import "host" "log" (func (param i32)))
+    ///     (memory (export "memory") 1)
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let interface = module.interface();
+    /// assert_eq!(interface.imports.len(), 1);
+    /// assert_eq!(interface.exports[0].name(), "memory");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interface(&self) -> ModuleInterface {
+        ModuleInterface {
+            imports: self.imports().collect(),
+            exports: self.exports().collect(),
+        }
+    }
+
+    /// A digest of [`Module::interface`], for quickly checking whether two
+    /// modules can be swapped for each other.
+    ///
+    /// The digest only depends on the names and types of the imports and
+    /// exports, not on the code behind them.
+    pub fn interface_hash(&self) -> [u8; 32] {
+        self.interface().hash()
+    }
+
     /// Get the custom sections of the module given a `name`.
     ///
     /// # Important
     ///
     /// Following the WebAssembly spec, one name can have multiple
     /// custom sections. That's why an iterator (rather than one element)
-    /// is returned.
+    /// is returned. The sections are yielded in the order they appear in
+    /// the module.
     pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Box<[u8]>> + 'a {
         self.0.custom_sections(name)
     }
 
+    /// Get the names of all the custom sections in the module, in the order
+    /// they first appear.
+    ///
+    /// Each name is only returned once, even if the module contains several
+    /// custom sections with that name.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # let mut store = Store::default();
+    /// // An empty module with a custom section called "hello"
+    /// let wasm = b"\0asm\x01\0\0\0\0\x09\x05helloabc";
+    /// let module = Module::new(&store, wasm).unwrap();
+    ///
+    /// assert_eq!(module.custom_section_names().collect::<Vec<_>>(), ["hello"]);
+    /// ```
+    #[cfg(any(feature = "sys", feature = "jsc"))]
+    pub fn custom_section_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.custom_section_names()
+    }
+
     /// The ABI of the [`ModuleInfo`] is very unstable, we refactor it very often.
     /// This function is public because in some cases it can be useful to get some
     /// extra information from the module.
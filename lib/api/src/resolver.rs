@@ -0,0 +1,173 @@
+//! Resolvers look up the externs a module imports, which allows several
+//! sources of imports to be combined without merging them into a single
+//! [`Imports`].
+use crate::{AsStoreRef, Extern, Imports, LinkError, Module};
+use std::sync::Arc;
+use wasmer_types::{ExternType, ImportError};
+
+/// Something which can provide the externs imported by a module.
+///
+/// [`Imports`] (including the import objects generated for WASI) implements
+/// this trait, and resolvers can be combined with [`ResolverChain`].
+///
+/// # Usage
+/// ```no_run
+/// # use wasmer::{Imports, Instance, Module, Store};
+/// # fn foo_test(mut store: &mut Store, module: Module, wasi: Imports, overrides: Imports) {
+/// // Anything in `overrides` takes precedence over the WASI imports
+/// let resolver = wasi.chain_front(overrides);
+/// let instance = Instance::new_with_resolver(&mut store, &module, &resolver)
+///     .expect("Could not instantiate module.");
+/// # }
+/// ```
+pub trait Resolver {
+    /// Look up the extern imported as `module`.`field`, which the module
+    /// expects to have the type `ty`.
+    ///
+    /// Returns `None` if this resolver doesn't provide the import.
+    fn resolve(&self, module: &str, field: &str, ty: &ExternType) -> Option<Extern>;
+}
+
+impl Resolver for Imports {
+    fn resolve(&self, module: &str, field: &str, _ty: &ExternType) -> Option<Extern> {
+        self.get_export(module, field)
+    }
+}
+
+impl<T: Resolver + ?Sized> Resolver for &T {
+    fn resolve(&self, module: &str, field: &str, ty: &ExternType) -> Option<Extern> {
+        (**self).resolve(module, field, ty)
+    }
+}
+
+impl<T: Resolver + ?Sized> Resolver for Box<T> {
+    fn resolve(&self, module: &str, field: &str, ty: &ExternType) -> Option<Extern> {
+        (**self).resolve(module, field, ty)
+    }
+}
+
+impl<T: Resolver + ?Sized> Resolver for Arc<T> {
+    fn resolve(&self, module: &str, field: &str, ty: &ExternType) -> Option<Extern> {
+        (**self).resolve(module, field, ty)
+    }
+}
+
+/// A list of resolvers which are tried in order, so earlier sources take
+/// precedence over later ones.
+///
+/// Usually created with [`Imports::chain_front`] or [`Imports::chain_back`].
+#[derive(Default)]
+pub struct ResolverChain {
+    sources: Vec<Box<dyn Resolver>>,
+}
+
+impl ResolverChain {
+    /// Create an empty `ResolverChain`, which doesn't resolve anything.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a source which takes precedence over all the existing ones.
+    pub fn chain_front(mut self, source: impl Resolver + 'static) -> Self {
+        self.sources.insert(0, Box::new(source));
+        self
+    }
+
+    /// Add a source which is only used for imports none of the existing
+    /// ones provide.
+    pub fn chain_back(mut self, source: impl Resolver + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// The number of sources in this chain.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns `true` if this chain has no sources.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+impl Resolver for ResolverChain {
+    fn resolve(&self, module: &str, field: &str, ty: &ExternType) -> Option<Extern> {
+        self.sources
+            .iter()
+            .find_map(|source| source.resolve(module, field, ty))
+    }
+}
+
+impl Imports {
+    /// Combine these imports with `other`, giving precedence to `other`.
+    pub fn chain_front(self, other: impl Resolver + 'static) -> ResolverChain {
+        ResolverChain::new().chain_back(other).chain_back(self)
+    }
+
+    /// Combine these imports with `other`, which is only used for imports
+    /// that aren't defined here.
+    pub fn chain_back(self, other: impl Resolver + 'static) -> ResolverChain {
+        ResolverChain::new().chain_back(self).chain_back(other)
+    }
+}
+
+/// Resolve the imports of `module` in the order they are declared.
+///
+/// Normally this stops at the first import `resolver` can't provide and
+/// leaves type checking to the linker. In `strict` mode every import is
+/// resolved and type-checked, and all the failures are reported together as
+/// [`LinkError::Imports`].
+#[allow(clippy::result_large_err)]
+pub(crate) fn resolve_imports(
+    store: &impl AsStoreRef,
+    module: &Module,
+    resolver: &dyn Resolver,
+    strict: bool,
+) -> Result<Vec<Extern>, LinkError> {
+    let mut externs = Vec::new();
+    let mut errors = Vec::new();
+
+    for import in module.imports() {
+        let expected = import.ty();
+        let error = match resolver.resolve(import.module(), import.name(), expected) {
+            Some(extern_) if strict => {
+                let found = extern_.ty(store);
+                let runtime_size = match &extern_ {
+                    Extern::Memory(memory) => Some(memory.view(store).size().0),
+                    Extern::Table(table) => Some(table.size(store)),
+                    _ => None,
+                };
+                if found.is_compatible_with(expected, runtime_size) {
+                    externs.push(extern_);
+                    continue;
+                }
+                ImportError::IncompatibleType(expected.clone(), found)
+            }
+            Some(extern_) => {
+                externs.push(extern_);
+                continue;
+            }
+            None => ImportError::UnknownImport(expected.clone()),
+        };
+
+        if !strict {
+            return Err(LinkError::Import(
+                import.module().to_string(),
+                import.name().to_string(),
+                error,
+            ));
+        }
+        errors.push((
+            import.module().to_string(),
+            import.name().to_string(),
+            error,
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(externs)
+    } else {
+        Err(LinkError::Imports(errors))
+    }
+}
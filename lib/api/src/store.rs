@@ -1,16 +1,21 @@
 use crate::engine::{AsEngineRef, Engine, EngineRef};
+use crate::instance::InstanceHandle;
+#[cfg(feature = "sys")]
+use crate::sys::engine::NativeEngineExt;
 use derivative::Derivative;
+use std::sync::Arc;
 use std::{
     fmt,
+    future::Future,
     ops::{Deref, DerefMut},
 };
 #[cfg(feature = "sys")]
 pub use wasmer_compiler::Tunables;
 pub use wasmer_types::{OnCalledAction, StoreId};
 #[cfg(feature = "sys")]
-use wasmer_vm::init_traps;
-#[cfg(feature = "sys")]
 pub use wasmer_vm::TrapHandlerFn;
+#[cfg(feature = "sys")]
+use wasmer_vm::{init_traps, InterruptState};
 
 #[cfg(feature = "sys")]
 pub use wasmer_vm::{StoreHandle, StoreObjects};
@@ -39,8 +44,18 @@ pub(crate) struct StoreInner {
     #[cfg(feature = "sys")]
     #[derivative(Debug = "ignore")]
     pub(crate) trap_handler: Option<Box<TrapHandlerFn<'static>>>,
+    #[cfg(feature = "sys")]
+    pub(crate) interrupt: Arc<InterruptState>,
+    pub(crate) stack_limit: Option<usize>,
     #[derivative(Debug = "ignore")]
     pub(crate) on_called: Option<OnCalledHandler>,
+    /// Labels given with [`Instance::set_label`][crate::Instance::set_label].
+    #[derivative(Debug = "ignore")]
+    pub(crate) instance_labels: Vec<(InstanceHandle, String)>,
+    /// Never shared, so the weak handles (e.g. [`WeakMemory`][crate::WeakMemory])
+    /// can tell when the store has been dropped.
+    #[derivative(Debug = "ignore")]
+    pub(crate) alive: Arc<()>,
 }
 
 /// The store represents all global state that can be manipulated by
@@ -70,7 +85,12 @@ impl Store {
                 engine: engine.into(),
                 #[cfg(feature = "sys")]
                 trap_handler: None,
+                #[cfg(feature = "sys")]
+                interrupt: Arc::new(InterruptState::new()),
+                stack_limit: None,
                 on_called: None,
+                instance_labels: Vec::new(),
+                alive: Arc::new(()),
             }),
         }
     }
@@ -81,6 +101,55 @@ impl Store {
         self.inner.trap_handler = handler;
     }
 
+    /// Returns a handle which can stop the WebAssembly code running in this
+    /// store from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            #[cfg(feature = "sys")]
+            state: self.inner.interrupt.clone(),
+        }
+    }
+
+    /// Set the stack size, in bytes, that calls into this store run with,
+    /// overriding the engine's default. Values lower than 8K are rounded up
+    /// to 8K.
+    ///
+    /// Calls made by host functions that were themselves called from
+    /// WebAssembly share the stack of the call they're nested in, so the
+    /// limit covers the whole host→wasm→host→wasm chain. Running out of
+    /// stack fails the call with a [`StackOverflow`][crate::StackOverflow]
+    /// error.
+    ///
+    /// Only the `sys` backend enforces the limit; elsewhere the host's own
+    /// stack is used.
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.inner.stack_limit = Some(bytes);
+    }
+
+    /// The stack size set with [`Store::set_stack_limit`], if any.
+    pub fn stack_limit(&self) -> Option<usize> {
+        self.inner.stack_limit
+    }
+
+    #[cfg(feature = "sys")]
+    /// Drop every [`ExternRef`][crate::ExternRef] object which is no longer
+    /// referenced by the host, a table, or a global, returning the number of
+    /// objects that were collected.
+    ///
+    /// Drop observers registered with
+    /// [`ExternRef::set_drop_observer()`][crate::ExternRef::set_drop_observer]
+    /// are invoked for each collected object.
+    ///
+    /// This is only available on the [`Store`] itself, not on the
+    /// [`StoreMut`] host functions get, since WebAssembly code which may
+    /// still hold externrefs on its stack is running while they're called.
+    pub fn gc_externrefs(&mut self) -> usize {
+        // Safety: calling into WebAssembly code borrows the store mutably
+        // until the call returns and host functions only ever get a
+        // `StoreMut`, so no WebAssembly code can be running on it.
+        unsafe { self.inner.objects.gc_extern_objs() }
+    }
+
     /// Returns the [`Engine`].
     pub fn engine(&self) -> &Engine {
         &self.inner.engine
@@ -155,6 +224,59 @@ impl fmt::Debug for Store {
     }
 }
 
+/// Stops the WebAssembly code running in a [`Store`] from another thread.
+///
+/// Once [`InterruptHandle::interrupt`] is called, calls into the store which
+/// are running fail with a [`RuntimeError`][crate::RuntimeError] for which
+/// [`is_interrupted`][crate::RuntimeError::is_interrupted] is true. Compiled
+/// code is stopped wherever it is on unix, and when it returns from a host
+/// function everywhere. The store can be used again afterwards.
+///
+/// Only the `sys` backend can interrupt calls. Elsewhere the handle does
+/// nothing.
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    #[cfg(feature = "sys")]
+    state: Arc<InterruptState>,
+}
+
+impl InterruptHandle {
+    /// Interrupts the calls running in the store. Calls which start
+    /// afterwards aren't affected.
+    pub fn interrupt(&self) {
+        #[cfg(feature = "sys")]
+        self.state.interrupt();
+    }
+
+    /// Whether the running calls have been interrupted, meaning host
+    /// functions should return as soon as they can.
+    #[cfg(feature = "sys")]
+    pub fn is_interrupted(&self) -> bool {
+        self.state.is_interrupted()
+    }
+
+    /// Whether the running calls have been interrupted, meaning host
+    /// functions should return as soon as they can.
+    #[cfg(not(feature = "sys"))]
+    pub fn is_interrupted(&self) -> bool {
+        false
+    }
+
+    /// Completes once the running calls have been interrupted, for host
+    /// functions which are waiting on something else.
+    #[cfg(feature = "sys")]
+    pub fn interrupted(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        self.state.interrupted()
+    }
+
+    /// Completes once the running calls have been interrupted, for host
+    /// functions which are waiting on something else.
+    #[cfg(not(feature = "sys"))]
+    pub fn interrupted(&self) -> impl Future<Output = ()> + Send + Sync + 'static {
+        std::future::pending()
+    }
+}
+
 /// A temporary handle to a [`Store`].
 #[derive(Debug)]
 pub struct StoreRef<'a> {
@@ -177,6 +299,44 @@ impl<'a> StoreRef<'a> {
         a.inner.objects.id() == b.inner.objects.id()
     }
 
+    /// Returns a handle which can stop the WebAssembly code running in this
+    /// store from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            #[cfg(feature = "sys")]
+            state: self.inner.interrupt.clone(),
+        }
+    }
+
+    /// The stack size set with [`Store::set_stack_limit`], if any.
+    pub fn stack_limit(&self) -> Option<usize> {
+        self.inner.stack_limit
+    }
+
+    /// The stack size calls into this store actually run with: the store's
+    /// own limit, or else the engine's default.
+    #[cfg(feature = "sys")]
+    pub(crate) fn effective_stack_limit(&self) -> usize {
+        self.inner
+            .stack_limit
+            .or_else(|| self.engine().default_stack_limit())
+            .or(self.engine().tunables().vmconfig().wasm_stack_size)
+            .unwrap_or_else(wasmer_vm::default_stack_size)
+    }
+
+    /// The `VMConfig` calls into this store run with.
+    #[cfg(feature = "sys")]
+    pub(crate) fn vm_config(&self) -> wasmer_vm::VMConfig {
+        wasmer_vm::VMConfig {
+            wasm_stack_size: Some(self.effective_stack_limit()),
+            wasm_stack_guard_size: self.engine().stack_guard_size().or(self
+                .engine()
+                .tunables()
+                .vmconfig()
+                .wasm_stack_guard_size),
+        }
+    }
+
     /// The signal handler
     #[cfg(feature = "sys")]
     #[inline]
@@ -1,9 +1,8 @@
+use std::path::Path;
 pub use wasmer_compiler::{
-    Artifact, BaseTunables, CompilerConfig, Engine, EngineBuilder, Tunables,
+    Artifact, BaseTunables, CompilerConfig, Engine, EngineBuilder, PerfMap, Tunables,
 };
-#[cfg(feature = "compiler")]
-use wasmer_types::Features;
-use wasmer_types::Target;
+use wasmer_types::{Features, Target};
 
 /// Returns the default engine for the Sys engine
 pub(crate) fn default_engine() -> Engine {
@@ -83,6 +82,50 @@ pub trait NativeEngineExt {
 
     /// Get a reference to attached Tunable of this engine
     fn tunables(&self) -> &dyn Tunables;
+
+    /// Set the stack size, in bytes, that calls into stores using this
+    /// engine get unless the store sets its own with
+    /// [`Store::set_stack_limit`][crate::Store::set_stack_limit].
+    fn set_default_stack_limit(&mut self, bytes: usize);
+
+    /// The stack size set with [`NativeEngineExt::set_default_stack_limit`],
+    /// if any.
+    fn default_stack_limit(&self) -> Option<usize>;
+
+    /// Set the size, in bytes, of the extra guard below the stacks calls
+    /// into stores using this engine run on, so functions with big stack
+    /// frames overflow into it rather than past it.
+    fn set_stack_guard_size(&mut self, bytes: usize);
+
+    /// The guard size set with [`NativeEngineExt::set_stack_guard_size`],
+    /// if any.
+    fn stack_guard_size(&self) -> Option<usize>;
+
+    /// The WebAssembly proposals modules are compiled with, after any the
+    /// compiler doesn't support have been turned off.
+    fn features(&self) -> Features;
+
+    /// Write the symbols of every function compiled by this engine to the
+    /// [perf map][PerfMap] at `path`, so profilers like `perf` can attribute
+    /// samples to `<module>::<function>`.
+    ///
+    /// A module's functions are written when it's first instantiated, so
+    /// any name given with [`Module::set_name`][crate::Module::set_name]
+    /// before then is used. Lazily compiled functions are written when
+    /// they're compiled.
+    fn enable_perfmap(&self, path: impl AsRef<Path>) -> std::io::Result<()>;
+
+    /// Like [`NativeEngineExt::enable_perfmap`], but writes a
+    /// [jitdump][crate::JitDump] which also has the machine code of every
+    /// function, so `perf annotate` can show the hot instructions.
+    #[cfg(target_os = "linux")]
+    fn enable_jitdump(&self, path: impl AsRef<Path>) -> std::io::Result<()>;
+
+    /// Whether modules are compiled lazily, with each function only being
+    /// compiled the first time it's called.
+    ///
+    /// Serializing a lazily compiled module compiles all of it first.
+    fn lazy_compilation(&self) -> bool;
 }
 
 impl NativeEngineExt for crate::engine::Engine {
@@ -106,4 +149,37 @@ impl NativeEngineExt for crate::engine::Engine {
     fn tunables(&self) -> &dyn Tunables {
         self.0.tunables()
     }
+
+    fn set_default_stack_limit(&mut self, bytes: usize) {
+        self.0.set_default_stack_limit(bytes)
+    }
+
+    fn default_stack_limit(&self) -> Option<usize> {
+        self.0.default_stack_limit()
+    }
+
+    fn set_stack_guard_size(&mut self, bytes: usize) {
+        self.0.set_stack_guard_size(bytes)
+    }
+
+    fn stack_guard_size(&self) -> Option<usize> {
+        self.0.stack_guard_size()
+    }
+
+    fn features(&self) -> Features {
+        self.0.features()
+    }
+
+    fn enable_perfmap(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.0.enable_perfmap(path)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn enable_jitdump(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.0.enable_jitdump(path)
+    }
+
+    fn lazy_compilation(&self) -> bool {
+        self.0.lazy_compilation()
+    }
 }
@@ -0,0 +1,201 @@
+//! Exception tags and exceptions, from the exception handling proposal.
+
+use crate::exports::{ExportError, Exportable};
+use crate::store::{AsStoreMut, AsStoreRef};
+use crate::sys::engine::NativeEngineExt;
+use crate::{Extern, RuntimeError, TagType, Type, Value};
+use std::error::Error;
+use std::fmt;
+use wasmer_vm::{InternalStoreHandle, StoreHandle, VMException, VMExtern, VMTag};
+
+/// An exception tag, which WebAssembly code throws and catches exceptions
+/// with.
+///
+/// Tags can be exported by instances or created by the host, to be imported
+/// by WebAssembly modules so both can throw and catch the same exceptions.
+///
+/// Spec: <https://webassembly.github.io/exception-handling/core/syntax/modules.html#tags>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    handle: StoreHandle<VMTag>,
+}
+
+impl Tag {
+    /// Creates a new tag for exceptions carrying values of `params`.
+    ///
+    /// # Usage
+    ///
+    /// ```
+    /// # use wasmer::{Store, Tag, Type};
+    /// # let mut store = Store::default();
+    /// let tag = Tag::new(&mut store, [Type::I32]);
+    /// assert_eq!(tag.ty(&store).params(), &[Type::I32]);
+    /// ```
+    pub fn new<Params>(store: &mut impl AsStoreMut, params: Params) -> Self
+    where
+        Params: Into<Box<[Type]>>,
+    {
+        let tag = VMTag::new(TagType::new(params));
+        Self {
+            handle: StoreHandle::new(store.objects_mut(), tag),
+        }
+    }
+
+    /// The type of the tag.
+    pub fn ty(&self, store: &impl AsStoreRef) -> TagType {
+        self.handle.get(store.as_store_ref().objects()).ty().clone()
+    }
+
+    pub(crate) fn from_vm_extern(
+        store: &mut impl AsStoreMut,
+        vm_extern: InternalStoreHandle<VMTag>,
+    ) -> Self {
+        Self {
+            handle: unsafe {
+                StoreHandle::from_internal(store.as_store_ref().objects().id(), vm_extern)
+            },
+        }
+    }
+
+    /// Checks whether this `Tag` can be used with the given store.
+    pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
+        self.handle.store_id() == store.as_store_ref().objects().id()
+    }
+
+    pub(crate) fn to_vm_extern(&self) -> VMExtern {
+        VMExtern::Tag(self.handle.internal_handle())
+    }
+}
+
+impl<'a> Exportable<'a> for Tag {
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Tag(tag) => Ok(tag),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+}
+
+/// A WebAssembly exception: a tag and the values thrown with it.
+///
+/// An exception thrown by WebAssembly code and not caught by it is returned
+/// to the host as a [`RuntimeError`] which can be downcast to an
+/// `Exception`. Host functions throw exceptions into WebAssembly code by
+/// returning one converted to a [`RuntimeError`], so a host function
+/// returning the error it got from calling a WebAssembly function rethrows
+/// the exception.
+///
+/// Traps aren't exceptions, WebAssembly code can't catch them.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    tag: Tag,
+    payload: Vec<Value>,
+}
+
+impl Exception {
+    /// Creates an exception to throw with `tag`.
+    ///
+    /// Fails if `payload` doesn't match the type of the tag.
+    pub fn new(
+        store: &impl AsStoreRef,
+        tag: &Tag,
+        payload: &[Value],
+    ) -> Result<Self, RuntimeError> {
+        if !tag.is_from_store(store) || payload.iter().any(|v| !v.is_from_store(store)) {
+            return Err(RuntimeError::new("cross-`Store` values are not supported"));
+        }
+        let ty = tag.ty(store);
+        if !payload
+            .iter()
+            .map(Value::ty)
+            .eq(ty.params().iter().copied())
+        {
+            return Err(RuntimeError::new(format!(
+                "Exception payload {:?} doesn't match the tag type {}",
+                payload.iter().map(Value::ty).collect::<Vec<_>>(),
+                ty
+            )));
+        }
+        Ok(Self {
+            tag: tag.clone(),
+            payload: payload.to_vec(),
+        })
+    }
+
+    /// The tag the exception was thrown with.
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// The values thrown with the exception.
+    pub fn payload(&self) -> &[Value] {
+        &self.payload
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "uncaught exception with payload {:?}", self.payload)
+    }
+}
+
+impl Error for Exception {}
+
+impl From<Exception> for RuntimeError {
+    fn from(exception: Exception) -> Self {
+        Self::user(Box::new(exception))
+    }
+}
+
+/// Turns the exception being thrown on the store, if any, into an error,
+/// once WebAssembly code returned to the host.
+pub(crate) fn take_pending_exception(store: &mut impl AsStoreMut) -> Result<(), RuntimeError> {
+    let exception = match store.as_store_ref().objects().pending_exception().take() {
+        Some(exception) => exception,
+        None => return Ok(()),
+    };
+    let tag = Tag::from_vm_extern(store, exception.tag());
+    let ty = tag.ty(store);
+    let payload = ty
+        .params()
+        .iter()
+        .zip(exception.payload())
+        .map(|(ty, raw)| unsafe { Value::from_raw(store, *ty, *raw) })
+        .collect();
+    Err(Exception { tag, payload }.into())
+}
+
+/// Starts throwing `error` into the WebAssembly code which called a host
+/// function, if it is an exception.
+///
+/// Other errors are given back, to be raised as traps. So are exceptions
+/// when the engine doesn't have the exception handling proposal enabled, as
+/// the code it compiled doesn't check for them.
+pub(crate) fn throw_to_guest(
+    store: &mut impl AsStoreMut,
+    error: Box<dyn Error + Send + Sync>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !store.as_store_ref().engine().features().exceptions {
+        return Err(error);
+    }
+    let exception = match error.downcast_ref::<Exception>().or_else(|| {
+        error
+            .downcast_ref::<RuntimeError>()
+            .and_then(|error| error.downcast_ref::<Exception>())
+    }) {
+        Some(exception) => exception,
+        None => return Err(error),
+    };
+    let payload = exception
+        .payload
+        .iter()
+        .map(|value| value.as_raw(store))
+        .collect();
+    let exception = VMException::new(exception.tag.handle.internal_handle(), payload);
+    store
+        .as_store_ref()
+        .objects()
+        .pending_exception()
+        .set(exception);
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::sync::Arc;
 use wasmer_vm::VMExternRef;
 use wasmer_vm::{StoreHandle, VMExternObj};
 
@@ -7,7 +8,9 @@ use crate::store::{AsStoreMut, AsStoreRef};
 #[derive(Debug, Clone)]
 #[repr(transparent)]
 pub struct ExternRef {
-    handle: StoreHandle<VMExternObj>,
+    /// Shared by all of the host's references to the object, which keeps it
+    /// alive while the host holds any.
+    handle: Arc<StoreHandle<VMExternObj>>,
 }
 
 impl ExternRef {
@@ -15,8 +18,10 @@ impl ExternRef {
     where
         T: Any + Send + Sync + 'static + Sized,
     {
+        let objects = store.objects_mut();
+        let handle = StoreHandle::new(objects, VMExternObj::new(value));
         Self {
-            handle: StoreHandle::new(store.objects_mut(), VMExternObj::new(value)),
+            handle: handle.get_mut(objects).host_handle(handle.clone()),
         }
     }
 
@@ -30,6 +35,16 @@ impl ExternRef {
             .downcast_ref::<T>()
     }
 
+    pub fn set_drop_observer(
+        &self,
+        store: &mut impl AsStoreMut,
+        observer: impl FnOnce() + Send + Sync + 'static,
+    ) {
+        self.handle
+            .get_mut(store.objects_mut())
+            .set_drop_observer(observer);
+    }
+
     pub(crate) fn vm_externref(&self) -> VMExternRef {
         VMExternRef(self.handle.internal_handle())
     }
@@ -38,8 +53,10 @@ impl ExternRef {
         store: &mut impl AsStoreMut,
         vm_externref: VMExternRef,
     ) -> Self {
+        let objects = store.objects_mut();
+        let handle = StoreHandle::from_internal(objects.id(), vm_externref.0);
         Self {
-            handle: StoreHandle::from_internal(store.objects_mut().id(), vm_externref.0),
+            handle: handle.get_mut(objects).host_handle(handle.clone()),
         }
     }
 
@@ -47,3 +64,11 @@ impl ExternRef {
         self.handle.store_id() == store.as_store_ref().objects().id()
     }
 }
+
+impl PartialEq for ExternRef {
+    fn eq(&self, other: &Self) -> bool {
+        *self.handle == *other.handle
+    }
+}
+
+impl Eq for ExternRef {}
@@ -1,16 +1,17 @@
 use crate::externals::function::{HostFunction, WithEnv, WithoutEnv};
 use crate::native_type::{FromToNativeWasmType, IntoResult, NativeWasmTypeInto, WasmTypeList};
 use crate::store::{AsStoreMut, AsStoreRef, StoreInner, StoreMut};
-use crate::sys::engine::NativeEngineExt;
+use crate::sys::exception::{take_pending_exception, throw_to_guest};
 use crate::vm::{VMExternFunction, VMFunctionCallback};
 use crate::{FunctionEnv, FunctionEnvMut, FunctionType, RuntimeError, Value};
 use std::panic::{self, AssertUnwindSafe};
 use std::{cell::UnsafeCell, cmp::max, ffi::c_void};
-use wasmer_types::{NativeWasmType, RawValue};
+use wasmer_types::{NativeWasmType, RawValue, TrapCode};
 use wasmer_vm::{
-    on_host_stack, raise_user_trap, resume_panic, wasmer_call_trampoline, MaybeInstanceOwned,
-    StoreHandle, VMCallerCheckedAnyfunc, VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef,
-    VMFunction, VMFunctionContext, VMFunctionKind, VMTrampoline,
+    is_interrupted, on_host_stack, raise_lib_trap, raise_user_trap, resume_panic,
+    wasmer_call_trampoline, MaybeInstanceOwned, StoreHandle, Trap, VMCallerCheckedAnyfunc,
+    VMContext, VMDynamicFunctionContext, VMExtern, VMFuncRef, VMFunction, VMFunctionContext,
+    VMFunctionKind, VMTrampoline,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -54,7 +55,15 @@ impl Function {
                     store_mut,
                     func_env: func_env.clone(),
                 };
-                let returns = func(env, &args)?;
+                let returns = match func(env, &args) {
+                    Ok(returns) => returns,
+                    Err(error) => {
+                        // The caller ignores the results while an exception
+                        // is thrown
+                        throw_to_guest(&mut store, Box::new(error)).map_err(RuntimeError::user)?;
+                        return Ok(());
+                    }
+                };
 
                 // We need to dynamically check that the returns
                 // match the expected types, as well as expected length.
@@ -269,16 +278,18 @@ impl Function {
     ) -> Result<(), RuntimeError> {
         // Call the trampoline.
         let result = {
+            let stack_limit = store.as_store_ref().effective_stack_limit();
+            let config = store.as_store_ref().vm_config();
             let mut r;
             // TODO: This loop is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
             loop {
                 let storeref = store.as_store_ref();
                 let vm_function = self.handle.get(storeref.objects());
-                let config = storeref.engine().tunables().vmconfig();
                 r = unsafe {
+                    let _running = storeref.inner.interrupt.enter();
                     wasmer_call_trampoline(
                         store.as_store_ref().signal_handler(),
-                        config,
+                        &config,
                         vm_function.anyfunc.as_ptr().as_ref().vmctx,
                         trampoline,
                         vm_function.anyfunc.as_ptr().as_ref().func_ptr,
@@ -302,11 +313,12 @@ impl Function {
                 }
                 break;
             }
-            r
+            r.map_err(|trap| RuntimeError::from(trap).with_stack_limit(stack_limit))
         };
-        if let Err(error) = result {
-            return Err(error.into());
-        }
+        // An exception the function threw and didn't catch
+        let exception = take_pending_exception(store);
+        result?;
+        exception?;
 
         // Load the return values out of `values_vec`.
         let signature = self.ty(store);
@@ -426,6 +438,9 @@ where
             on_host_stack(|| panic::catch_unwind(AssertUnwindSafe(|| (this.ctx.func)(values_vec))));
 
         match result {
+            // Returning to wasm is an interruption point, and host functions
+            // woken up by the interrupt may have failed because of it
+            Ok(_) if is_interrupted() => raise_lib_trap(Trap::lib(TrapCode::Interrupted)),
             Ok(Ok(())) => {}
             Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
             Err(panic) => resume_panic(panic),
@@ -511,8 +526,14 @@ macro_rules! impl_host_function {
                         });
 
                         match result {
+                            Ok(_) if is_interrupted() => raise_lib_trap(Trap::lib(TrapCode::Interrupted)),
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => match throw_to_guest(&mut store, Box::new(trap)) {
+                                // The caller ignores the results while an
+                                // exception is thrown
+                                Ok(()) => return Rets::from_array(&mut store, Rets::empty_array()).into_c_struct(&mut store),
+                                Err(trap) => raise_user_trap(trap),
+                            },
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
@@ -588,8 +609,14 @@ macro_rules! impl_host_function {
                         });
 
                         match result {
+                            Ok(_) if is_interrupted() => raise_lib_trap(Trap::lib(TrapCode::Interrupted)),
                             Ok(Ok(result)) => return result.into_c_struct(&mut store),
-                            Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
+                            Ok(Err(trap)) => match throw_to_guest(&mut store, Box::new(trap)) {
+                                // The caller ignores the results while an
+                                // exception is thrown
+                                Ok(()) => return Rets::from_array(&mut store, Rets::empty_array()).into_c_struct(&mut store),
+                                Err(trap) => raise_user_trap(trap),
+                            },
                             Err(panic) => resume_panic(panic) ,
                         }
                     }
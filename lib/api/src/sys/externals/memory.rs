@@ -4,15 +4,21 @@ use crate::sys::engine::NativeEngineExt;
 use crate::vm::VMExternMemory;
 use crate::MemoryAccessError;
 use crate::MemoryType;
+use crate::{AtomicsError, MemoryBufferError, Store, WaitResult};
 use std::convert::TryInto;
 use std::marker::PhantomData;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::slice;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
 #[cfg(feature = "tracing")]
 use tracing::warn;
 use wasmer_types::Pages;
-use wasmer_vm::{LinearMemory, MemoryError, StoreHandle, VMExtern, VMMemory};
+use wasmer_vm::{
+    LinearMemory, MemoryError, NotifyLocation, OwnedMemoryBuffer, StoreHandle, VMExtern, VMMemory,
+    WaiterError,
+};
 
 #[derive(Debug, Clone)]
 pub struct Memory {
@@ -48,7 +54,106 @@ impl Memory {
     where
         IntoPages: Into<Pages>,
     {
-        self.handle.get_mut(store.objects_mut()).grow(delta.into())
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects.grow_memory(self.handle.internal_handle(), delta.into())
+    }
+
+    pub fn subscribe_grow(
+        &self,
+        store: &mut impl AsStoreMut,
+        callback: impl FnMut(Pages, Pages) + Send + Sync + 'static,
+    ) {
+        let objects = store.objects_mut();
+        assert_eq!(
+            self.handle.store_id(),
+            objects.id(),
+            "object used with the wrong context"
+        );
+        objects.subscribe_memory_grow(self.handle.internal_handle(), callback);
+    }
+
+    pub fn generation(&self, store: &impl AsStoreRef) -> u64 {
+        store
+            .as_store_ref()
+            .objects()
+            .memory_generation(self.handle.internal_handle())
+    }
+
+    pub fn atomic_notify(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        count: u32,
+    ) -> Result<u32, AtomicsError> {
+        let memory = self.handle.get(store.as_store_ref().objects());
+        let (location, _) = atomic_cell(memory, offset, 4)?;
+
+        if !memory.ty().shared {
+            return Ok(0);
+        }
+        // Clones of a shared memory share its wait queues
+        let mut shared = memory.try_clone().map_err(|_| AtomicsError::NotShared)?;
+        Ok(shared.do_notify(location, count))
+    }
+
+    pub fn atomic_wait32(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        expected: u32,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, AtomicsError> {
+        self.atomic_wait(store, offset, 4, timeout, |cell| unsafe {
+            (*(cell as *const AtomicU32)).load(Ordering::SeqCst) == expected
+        })
+    }
+
+    pub fn atomic_wait64(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        expected: u64,
+        timeout: Option<Duration>,
+    ) -> Result<WaitResult, AtomicsError> {
+        self.atomic_wait(store, offset, 8, timeout, |cell| unsafe {
+            (*(cell as *const AtomicU64)).load(Ordering::SeqCst) == expected
+        })
+    }
+
+    fn atomic_wait(
+        &self,
+        store: &impl AsStoreRef,
+        offset: u64,
+        len: u64,
+        timeout: Option<Duration>,
+        matches: impl FnOnce(*const u8) -> bool,
+    ) -> Result<WaitResult, AtomicsError> {
+        let memory = self.handle.get(store.as_store_ref().objects());
+        let (location, cell) = atomic_cell(memory, offset, len)?;
+
+        if !memory.ty().shared {
+            return Err(AtomicsError::NotShared);
+        }
+        // Wait on our own handle to the memory so the store isn't borrowed
+        // mutably while we are blocked.
+        let mut shared = memory.try_clone().map_err(|_| AtomicsError::NotShared)?;
+
+        if !matches(cell) {
+            return Ok(WaitResult::NotEqual);
+        }
+
+        match shared.do_wait(location, timeout) {
+            Ok(0) => Ok(WaitResult::Ok),
+            Ok(_) => Ok(WaitResult::TimedOut),
+            Err(WaiterError::TooManyWaiters) => Err(AtomicsError::TooManyWaiters),
+            // `WaiterError::Unimplemented`, or a reason added later
+            Err(_) => Err(AtomicsError::NotShared),
+        }
     }
 
     pub(crate) fn from_vm_extern(store: &impl AsStoreRef, vm_extern: VMExternMemory) -> Self {
@@ -82,12 +187,65 @@ impl Memory {
         mem.copy()
     }
 
+    pub fn take_buffer(&self, mut store: Store) -> Result<OwnedMemoryBuffer, MemoryBufferError> {
+        if !self.is_from_store(&store) {
+            return Err(MemoryBufferError::WrongStore);
+        }
+        let mem = self.handle.get_mut(store.objects_mut());
+        if mem.ty().shared {
+            return Err(MemoryBufferError::Shared);
+        }
+        // Safety: the store is dropped below, and with it everything that
+        // could still run code against this memory.
+        let buffer = unsafe { mem.take_buffer()? };
+        drop(store);
+        Ok(buffer)
+    }
+
+    pub fn try_clone_into_buffer(
+        &self,
+        store: &impl AsStoreRef,
+    ) -> Result<OwnedMemoryBuffer, MemoryBufferError> {
+        if !self.is_from_store(store) {
+            return Err(MemoryBufferError::WrongStore);
+        }
+        let mem = self.handle.get(store.as_store_ref().objects());
+        Ok(OwnedMemoryBuffer::copy_from(mem)?)
+    }
+
     /// To `VMExtern`.
     pub(crate) fn to_vm_extern(&self) -> VMExtern {
         VMExtern::Memory(self.handle.internal_handle())
     }
 }
 
+/// Check that `len` bytes at `offset` can be accessed atomically, returning
+/// the cell's wait queue location and address.
+fn atomic_cell(
+    memory: &VMMemory,
+    offset: u64,
+    len: u64,
+) -> Result<(NotifyLocation, *const u8), AtomicsError> {
+    if offset % len != 0 {
+        return Err(AtomicsError::Unaligned {
+            offset,
+            alignment: len,
+        });
+    }
+
+    let definition = unsafe { memory.vmmemory().as_ref() };
+    let size = definition.current_length as u64;
+    let out_of_bounds = AtomicsError::OutOfBounds { offset, len, size };
+    if offset.checked_add(len).map_or(true, |end| end > size) {
+        return Err(out_of_bounds);
+    }
+    // The VM's wait queues are keyed by 32-bit addresses
+    let address = u32::try_from(offset).map_err(|_| out_of_bounds)?;
+
+    let cell = unsafe { definition.base.add(offset as usize) as *const u8 };
+    Ok((NotifyLocation { address }, cell))
+}
+
 impl std::cmp::PartialEq for Memory {
     fn eq(&self, other: &Self) -> bool {
         self.handle == other.handle
@@ -0,0 +1,397 @@
+//! Reusing instances of a module between requests, instead of creating a new
+//! store and instance for each of them.
+
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use thiserror::Error;
+use wasmer_compiler::Tunables;
+use wasmer_types::{MemoryStyle, MemoryType, Pages, TableStyle, TableType};
+use wasmer_vm::{
+    MemoryError, StoreSnapshot, VMMemory, VMMemoryDefinition, VMTable, VMTableDefinition,
+};
+
+use crate::errors::InstantiationError;
+use crate::sys::{BaseTunables, NativeEngineExt};
+use crate::{Engine, Imports, Instance, Module, Store};
+
+/// Builds the imports of an instance in the store it will live in.
+type ImportsFn = dyn Fn(&mut Store, &Module) -> Imports + Send + Sync;
+
+/// A pool of instances which are reset and handed out again once they are
+/// dropped, avoiding the cost of allocating memories, tables and instances
+/// for every request.
+///
+/// Each instance gets its own [`Store`]. When a [`PooledInstance`] is
+/// dropped, the memories, tables and globals in its store are put back the
+/// way they were right after instantiation (data segments applied and start
+/// function run), so the next [`InstancePool::get`] sees exactly what a
+/// fresh instance would. Memory is reset in place: on Linux the pages are
+/// handed back to the kernel with `madvise`, elsewhere they are zeroed.
+///
+/// State held by the host, like the data in a [`FunctionEnv`][crate::FunctionEnv]
+/// created by [`InstancePool::with_imports`], isn't reset. Stores which
+/// gained objects while they were in use (e.g. new host functions) or whose
+/// memories can't be reset (shared memories) are dropped instead of being
+/// reused.
+///
+/// ```
+/// # use wasmer::{InstancePool, Module, Store, TypedFunction};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(
+///     &store,
+///     r#"(module
+///         (global $count (mut i32) (i32.const 0))
+///         (func (export "count") (result i32)
+///             (global.set $count (i32.add (global.get $count) (i32.const 1)))
+///             (global.get $count)))"#,
+/// )?;
+///
+/// let pool = InstancePool::new(store.engine().clone(), 4);
+/// for _ in 0..3 {
+///     let mut pooled = pool.get(&module)?;
+///     let (instance, store) = pooled.instance_and_store_mut();
+///     let count: TypedFunction<(), i32> = instance.exports.get_typed_function(store, "count")?;
+///     // Every request starts from a freshly initialized instance
+///     assert_eq!(count.call(store)?, 1);
+/// }
+///
+/// assert_eq!(pool.stats().instantiations, 1);
+/// assert_eq!(pool.stats().reuses, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstancePool {
+    engine: Engine,
+    capacity: usize,
+    imports: Option<Arc<ImportsFn>>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    idle: Vec<Slot>,
+    in_use: usize,
+    instantiations: u64,
+    reuses: u64,
+    discarded: u64,
+}
+
+/// An instance, the store it lives in, and the state to reset it to.
+struct Slot {
+    module: Module,
+    store: Store,
+    instance: Instance,
+    snapshot: StoreSnapshot,
+}
+
+impl InstancePool {
+    /// Creates a pool holding at most `capacity` instances, which are
+    /// created on demand with `engine`.
+    pub fn new(engine: Engine, capacity: usize) -> Self {
+        Self {
+            engine,
+            capacity,
+            imports: None,
+            state: Arc::default(),
+        }
+    }
+
+    /// Sets how the imports of each new instance are created.
+    ///
+    /// By default instances are created without any imports.
+    pub fn with_imports(
+        mut self,
+        imports: impl Fn(&mut Store, &Module) -> Imports + Send + Sync + 'static,
+    ) -> Self {
+        self.imports = Some(Arc::new(imports));
+        self
+    }
+
+    /// Limits each memory of the pooled instances to `pages`, no matter
+    /// what maximum the module declares.
+    ///
+    /// Instantiating a module whose memories need more than that to start
+    /// with fails.
+    pub fn with_max_memory(mut self, pages: Pages) -> Self {
+        let base = BaseTunables::for_target(self.engine.target());
+        self.engine
+            .set_tunables(MaxMemoryTunables { base, max: pages });
+        self
+    }
+
+    /// The maximum number of instances in the pool, counting both the idle
+    /// ones and the ones in use.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Creates instances of `module` until `count` of them are idle in the
+    /// pool, or the pool is full, so later calls to [`InstancePool::get`]
+    /// don't have to.
+    #[allow(clippy::result_large_err)]
+    pub fn preallocate(&self, module: &Module, count: usize) -> Result<(), InstancePoolError> {
+        let slots = (0..count)
+            .map(|_| self.get(module))
+            .take_while(|slot| !matches!(slot, Err(InstancePoolError::Exhausted { .. })))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Dropping them puts them back in the pool
+        drop(slots);
+        Ok(())
+    }
+
+    /// Hands out an instance of `module`, reusing an idle one when there is
+    /// one and creating a new one otherwise.
+    ///
+    /// When the pool is full, idle instances of other modules are evicted to
+    /// make room. If every instance is in use this fails with
+    /// [`InstancePoolError::Exhausted`].
+    #[allow(clippy::result_large_err)]
+    pub fn get(&self, module: &Module) -> Result<PooledInstance, InstancePoolError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(index) = state.idle.iter().position(|slot| slot.module == *module) {
+                let slot = state.idle.swap_remove(index);
+                state.in_use += 1;
+                state.reuses += 1;
+                return Ok(PooledInstance {
+                    slot: Some(slot),
+                    state: self.state.clone(),
+                });
+            }
+
+            if state.in_use + state.idle.len() >= self.capacity {
+                if state.idle.is_empty() {
+                    return Err(InstancePoolError::Exhausted {
+                        capacity: self.capacity,
+                    });
+                }
+                state.idle.remove(0);
+            }
+            // Reserve the slot while the instance is created
+            state.in_use += 1;
+        }
+
+        match self.instantiate(module) {
+            Ok(slot) => {
+                self.state.lock().unwrap().instantiations += 1;
+                Ok(PooledInstance {
+                    slot: Some(slot),
+                    state: self.state.clone(),
+                })
+            }
+            Err(e) => {
+                self.state.lock().unwrap().in_use -= 1;
+                Err(e.into())
+            }
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn instantiate(&self, module: &Module) -> Result<Slot, InstantiationError> {
+        let mut store = Store::new(self.engine.clone());
+        let imports = match &self.imports {
+            Some(imports) => imports(&mut store, module),
+            None => Imports::new(),
+        };
+        let instance = Instance::new(&mut store, module, &imports)?;
+        let snapshot = store.inner.objects.snapshot();
+
+        Ok(Slot {
+            module: module.clone(),
+            store,
+            instance,
+            snapshot,
+        })
+    }
+
+    /// How many instances are in the pool, and how often they were reused.
+    pub fn stats(&self) -> InstancePoolStats {
+        let state = self.state.lock().unwrap();
+        InstancePoolStats {
+            capacity: self.capacity,
+            in_use: state.in_use,
+            idle: state.idle.len(),
+            instantiations: state.instantiations,
+            reuses: state.reuses,
+            discarded: state.discarded,
+        }
+    }
+}
+
+impl fmt::Debug for InstancePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstancePool")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The occupancy of an [`InstancePool`], returned by [`InstancePool::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstancePoolStats {
+    /// The maximum number of instances in the pool.
+    pub capacity: usize,
+    /// How many instances are handed out right now.
+    pub in_use: usize,
+    /// How many instances are waiting to be reused.
+    pub idle: usize,
+    /// How many instances were created.
+    pub instantiations: u64,
+    /// How many times an idle instance was handed out again.
+    pub reuses: u64,
+    /// How many instances were dropped because they couldn't be reset.
+    pub discarded: u64,
+}
+
+/// An instance handed out by an [`InstancePool`].
+///
+/// It's reset and returned to the pool when dropped. Functions read out of
+/// its tables are dropped along with it, so they shouldn't be kept around.
+pub struct PooledInstance {
+    slot: Option<Slot>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl PooledInstance {
+    fn slot(&self) -> &Slot {
+        self.slot.as_ref().unwrap()
+    }
+
+    /// The module this is an instance of.
+    pub fn module(&self) -> &Module {
+        &self.slot().module
+    }
+
+    /// The instance.
+    pub fn instance(&self) -> &Instance {
+        &self.slot().instance
+    }
+
+    /// The store the instance lives in.
+    pub fn store(&self) -> &Store {
+        &self.slot().store
+    }
+
+    /// The store the instance lives in.
+    pub fn store_mut(&mut self) -> &mut Store {
+        &mut self.slot.as_mut().unwrap().store
+    }
+
+    /// The instance along with its store, for calling its exports.
+    pub fn instance_and_store_mut(&mut self) -> (&Instance, &mut Store) {
+        let slot = self.slot.as_mut().unwrap();
+        (&slot.instance, &mut slot.store)
+    }
+}
+
+impl fmt::Debug for PooledInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledInstance")
+            .field("instance", self.instance())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for PooledInstance {
+    fn drop(&mut self) {
+        let mut slot = match self.slot.take() {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        // Nothing can be running in the store: calls into it need a
+        // `&mut Store`, which can't outlive this.
+        let restored = unsafe { slot.store.inner.objects.restore(&slot.snapshot) };
+
+        let mut state = self.state.lock().unwrap();
+        state.in_use -= 1;
+        match restored {
+            Ok(()) => state.idle.push(slot),
+            Err(_) => state.discarded += 1,
+        }
+    }
+}
+
+/// The error returned by [`InstancePool::get`].
+#[derive(Debug, Error)]
+pub enum InstancePoolError {
+    /// Every instance in the pool is in use.
+    #[error("all {capacity} instances in the pool are in use")]
+    Exhausted {
+        /// The capacity of the pool.
+        capacity: usize,
+    },
+    /// A new instance couldn't be created.
+    #[error(transparent)]
+    Instantiation(#[from] InstantiationError),
+}
+
+/// Tunables which cap the maximum size of every memory.
+struct MaxMemoryTunables {
+    base: BaseTunables,
+    max: Pages,
+}
+
+impl MaxMemoryTunables {
+    fn adjust_memory(&self, requested: &MemoryType) -> Result<MemoryType, MemoryError> {
+        if requested.minimum > self.max {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: requested.minimum,
+                max_allowed: self.max,
+            });
+        }
+
+        let mut adjusted = *requested;
+        adjusted.maximum = Some(requested.maximum.map_or(self.max, |max| max.min(self.max)));
+        Ok(adjusted)
+    }
+}
+
+impl Tunables for MaxMemoryTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        match self.adjust_memory(memory) {
+            Ok(adjusted) => self.base.memory_style(&adjusted),
+            Err(_) => self.base.memory_style(memory),
+        }
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base
+            .create_host_memory(&self.adjust_memory(ty)?, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<VMMemory, MemoryError> {
+        self.base
+            .create_vm_memory(&self.adjust_memory(ty)?, style, vm_definition_location)
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+        self.base.create_host_table(ty, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<VMTable, String> {
+        self.base.create_vm_table(ty, style, vm_definition_location)
+    }
+}
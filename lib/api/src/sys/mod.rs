@@ -1,20 +1,38 @@
 pub(crate) mod engine;
 pub(crate) mod errors;
+pub(crate) mod exception;
 pub(crate) mod extern_ref;
 pub(crate) mod externals;
 pub(crate) mod instance;
+pub(crate) mod instance_pool;
 pub(crate) mod mem_access;
 pub(crate) mod module;
+#[cfg(feature = "wat")]
+mod source_map;
 mod tunables;
 pub(crate) mod typed_function;
 
 pub use crate::sys::engine::NativeEngineExt;
+pub use crate::sys::exception::{Exception, Tag};
+pub use crate::sys::instance_pool::{
+    InstancePool, InstancePoolError, InstancePoolStats, PooledInstance,
+};
+#[cfg(feature = "wat")]
+pub use crate::sys::source_map::wat2wasm_with_source_map;
 pub use crate::sys::tunables::BaseTunables;
+#[cfg(feature = "dump")]
+pub use wasmer_compiler::CompilerDump;
+#[cfg(target_os = "linux")]
+pub use wasmer_compiler::JitDump;
 #[cfg(feature = "compiler")]
 pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareReaderState, ModuleMiddleware,
+    wasmparser, CompileThreads, CompilerConfig, DumpFilter, FunctionBodyData, FunctionMiddleware,
+    MiddlewareReaderState, ModuleMiddleware, StackProbes, ThreadStartHook,
+};
+pub use wasmer_compiler::{
+    Artifact, EngineBuilder, FeatureSupport, Features, FeaturesBuilder, PerfMap, Tunables,
+    UnsupportedFeaturesError,
 };
-pub use wasmer_compiler::{Artifact, EngineBuilder, Features, Tunables};
 #[cfg(feature = "cranelift")]
 pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
 #[cfg(feature = "llvm")]
@@ -22,7 +40,7 @@ pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
 #[cfg(feature = "singlepass")]
 pub use wasmer_compiler_singlepass::Singlepass;
 
-pub use wasmer_vm::VMConfig;
+pub use wasmer_vm::{OwnedMemoryBuffer, VMConfig};
 
 pub(crate) mod vm {
     //! The `vm` module re-exports wasmer-vm types.
@@ -1,17 +1,22 @@
 use crate::engine::AsEngineRef;
 use bytes::Bytes;
+use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 use wasmer_compiler::Artifact;
 use wasmer_compiler::ArtifactCreate;
 use wasmer_types::{
-    CompileError, DeserializeError, ExportsIterator, ImportsIterator, ModuleInfo, SerializeError,
+    CompileError, DeserializeError, ExportsIterator, Features, ImportsIterator, ModuleInfo,
+    SerializeError, Triple, ValidationError,
 };
-use wasmer_types::{ExportType, ImportType};
+use wasmer_types::{ExportType, ImportType, WatSourceMap};
 
 use crate::sys::engine::NativeEngineExt;
+use crate::sys::exception::take_pending_exception;
+#[cfg(feature = "wat")]
+use crate::sys::source_map::wat2wasm_with_source_map;
 use crate::vm::VMInstance;
-use crate::{AsStoreMut, AsStoreRef, InstantiationError, IntoBytes};
+use crate::{AsStoreMut, AsStoreRef, InstantiationError, IntoBytes, RuntimeError};
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Module {
@@ -41,6 +46,40 @@ impl Module {
         unsafe { Self::from_binary_unchecked(engine, binary) }
     }
 
+    /// Compile the WebAssembly text format, keeping a map from the binary
+    /// back to the text. Validation errors say where in the text they are.
+    #[cfg(feature = "wat")]
+    pub(crate) fn from_wat(engine: &impl AsEngineRef, wat: &[u8]) -> Result<Self, CompileError> {
+        let (wasm, source_map) = wat2wasm_with_source_map(wat).map_err(crate::module::wat_error)?;
+        let source_map = match source_map {
+            Some(source_map) => source_map,
+            None => return Self::from_binary(engine, &wasm),
+        };
+
+        let mut module = Self::from_binary(engine, &wasm).map_err(|error| match error {
+            CompileError::Validate(message) => {
+                let engine = engine.as_engine_ref();
+                let engine = engine.engine();
+                let location = Self::validate_with_features(
+                    &wasm,
+                    &engine.features(),
+                    engine.target().triple(),
+                )
+                .err()
+                .and_then(|error| source_map.lookup(error.offset));
+                match location {
+                    Some(location) => {
+                        CompileError::Validate(format!("{message} (<wat>:{location})"))
+                    }
+                    None => CompileError::Validate(message),
+                }
+            }
+            error => error,
+        })?;
+        module.set_source_map(source_map);
+        Ok(module)
+    }
+
     pub(crate) unsafe fn from_binary_unchecked(
         engine: &impl AsEngineRef,
         binary: &[u8],
@@ -53,10 +92,39 @@ impl Module {
         engine.as_engine_ref().engine().0.validate(binary)
     }
 
+    pub(crate) fn validate_with_features(
+        binary: &[u8],
+        features: &Features,
+        target: &Triple,
+    ) -> Result<(), ValidationError> {
+        wasmer_compiler::validate_with_features(binary, features, target)
+    }
+
+    pub(crate) fn validate_all_with_features(
+        binary: &[u8],
+        features: &Features,
+        target: &Triple,
+    ) -> Result<(), Vec<ValidationError>> {
+        wasmer_compiler::validate_all_with_features(binary, features, target)
+    }
+
+    pub(crate) fn required_features(binary: &[u8]) -> Result<Features, ValidationError> {
+        wasmer_compiler::required_features(binary)
+    }
+
     #[cfg(feature = "compiler")]
     fn compile(engine: &impl AsEngineRef, binary: &[u8]) -> Result<Self, CompileError> {
+        // The module's name is only known once its name section is parsed
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("compile", module = tracing::field::Empty).entered();
+
         let artifact = engine.as_engine_ref().engine().0.compile(binary)?;
-        Ok(Self::from_artifact(artifact))
+        let module = Self::from_artifact(artifact);
+
+        #[cfg(feature = "tracing")]
+        span.record("module", module.info().name());
+
+        Ok(module)
     }
 
     #[cfg(not(feature = "compiler"))]
@@ -92,6 +160,19 @@ impl Module {
         Ok(Self::from_artifact(artifact))
     }
 
+    pub unsafe fn deserialize_checked(
+        engine: &impl AsEngineRef,
+        bytes: impl IntoBytes,
+    ) -> Result<Self, DeserializeError> {
+        let bytes = bytes.into_bytes();
+        let artifact = engine
+            .as_engine_ref()
+            .engine()
+            .0
+            .deserialize_checked(&bytes)?;
+        Ok(Self::from_artifact(artifact))
+    }
+
     pub unsafe fn deserialize_from_file_unchecked(
         engine: &impl AsEngineRef,
         path: impl AsRef<Path>,
@@ -126,6 +207,9 @@ impl Module {
         store: &mut impl AsStoreMut,
         imports: &[crate::Extern],
     ) -> Result<VMInstance, InstantiationError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("instantiate", module = self.info().name()).entered();
+
         if !self.artifact.allocated() {
             // Return an error mentioning that the artifact is compiled for a different
             // platform.
@@ -138,9 +222,10 @@ impl Module {
             }
         }
         let signal_handler = store.as_store_ref().signal_handler();
+        let stack_limit = store.as_store_ref().effective_stack_limit();
+        let config = store.as_store_ref().vm_config();
         let mut store_mut = store.as_store_mut();
         let (engine, objects) = store_mut.engine_and_objects_mut();
-        let config = engine.tunables().vmconfig();
         unsafe {
             let mut instance_handle = self.artifact.instantiate(
                 engine.tunables(),
@@ -157,7 +242,16 @@ impl Module {
             // as some of the Instance elements may have placed in other
             // instance tables.
             self.artifact
-                .finish_instantiation(config, signal_handler, &mut instance_handle)?;
+                .finish_instantiation(&config, signal_handler, &mut instance_handle)
+                .map_err(|error| match error {
+                    wasmer_compiler::InstantiationError::Start(trap) => InstantiationError::Start(
+                        RuntimeError::from(trap).with_stack_limit(stack_limit),
+                    ),
+                    other => other.into(),
+                })?;
+            // An exception the start function didn't catch fails the
+            // instantiation like a trap.
+            take_pending_exception(&mut store_mut).map_err(InstantiationError::Start)?;
 
             Ok(instance_handle)
         }
@@ -173,6 +267,31 @@ impl Module {
         })
     }
 
+    pub(crate) fn set_source_map(&mut self, source_map: WatSourceMap) -> bool {
+        Arc::get_mut(&mut self.artifact).map_or(false, |artifact| {
+            artifact.set_module_info_source_map(Arc::new(source_map))
+        })
+    }
+
+    pub(crate) fn compiler(&self) -> &str {
+        self.artifact.compiler()
+    }
+
+    pub(crate) fn function_code_ranges(&self) -> Vec<Range<usize>> {
+        if !self.artifact.allocated() {
+            return Vec::new();
+        }
+        self.artifact
+            .finished_functions()
+            .values()
+            .zip(self.artifact.finished_function_lengths().values())
+            .map(|(ptr, length)| {
+                let start = **ptr as usize;
+                start..start + length
+            })
+            .collect()
+    }
+
     pub(crate) fn imports(&self) -> ImportsIterator<impl Iterator<Item = ImportType> + '_> {
         self.info().imports()
     }
@@ -188,6 +307,10 @@ impl Module {
         self.info().custom_sections(name)
     }
 
+    pub(crate) fn custom_section_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.info().custom_section_names()
+    }
+
     pub(crate) fn info(&self) -> &ModuleInfo {
         self.artifact.module_info()
     }
@@ -0,0 +1,470 @@
+//! Building a [`WatSourceMap`] while converting the WebAssembly text format
+//! to a binary.
+//!
+//! The `wat` crate doesn't say where the instructions it emits came from, so
+//! the text is read a second time here. The instructions of each function
+//! are listed in the order they end up in the binary, unfolding folded
+//! expressions the same way `wat` does, and paired up with the instructions
+//! in the binary. Functions that can't be paired up exactly are mapped to
+//! where the function starts instead.
+
+use std::borrow::Cow;
+use std::iter;
+
+use wasmer_compiler::wasmparser::{Parser, Payload};
+use wasmer_types::{WatLocation, WatSourceMap};
+
+/// Convert the WebAssembly text format to a binary, like
+/// [`wat2wasm`][crate::wat2wasm], also returning a map from offsets in the
+/// binary back to the text.
+///
+/// Binary input is returned as is, without a source map.
+pub fn wat2wasm_with_source_map(
+    bytes: &[u8],
+) -> Result<(Cow<'_, [u8]>, Option<WatSourceMap>), wat::Error> {
+    let wasm = wat::parse_bytes(bytes)?;
+    if let Cow::Borrowed(_) = wasm {
+        return Ok((wasm, None));
+    }
+
+    let source_map = std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| build(text, &wasm));
+    Ok((wasm, source_map))
+}
+
+fn build(text: &str, wasm: &[u8]) -> Option<WatSourceMap> {
+    let top = parse(text)?;
+    let text_functions = text_functions(module_fields(&top)?);
+    let (binary_functions, code_end) = binary_functions(wasm)?;
+    if text_functions.len() != binary_functions.len() {
+        return None;
+    }
+
+    let lines = LineIndex::new(text);
+    let mut entries = Vec::new();
+    for (function, binary) in text_functions.iter().zip(&binary_functions) {
+        entries.push((binary.body_start, lines.location(function.start)));
+        match &function.instructions {
+            // The `end` closing the function body is implicit in the text
+            Some(instructions) if instructions.len() + 1 == binary.instructions.len() => {
+                let positions = instructions.iter().chain(iter::once(&function.end));
+                for (&offset, &position) in binary.instructions.iter().zip(positions) {
+                    entries.push((offset, lines.location(position)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(WatSourceMap::new(entries, code_end))
+}
+
+/// An s-expression, along with where it starts in the text.
+#[derive(Debug)]
+enum Sexp<'a> {
+    Atom {
+        text: &'a str,
+        start: usize,
+    },
+    List {
+        items: Vec<Sexp<'a>>,
+        start: usize,
+        end: usize,
+    },
+}
+
+impl<'a> Sexp<'a> {
+    fn start(&self) -> usize {
+        match self {
+            Sexp::Atom { start, .. } | Sexp::List { start, .. } => *start,
+        }
+    }
+
+    /// The keyword a list starts with.
+    fn head(&self) -> Option<&'a str> {
+        match self {
+            Sexp::List { items, .. } => match items.first() {
+                Some(Sexp::Atom { text, .. }) => Some(*text),
+                _ => None,
+            },
+            Sexp::Atom { .. } => None,
+        }
+    }
+}
+
+/// Split the text into s-expressions, skipping comments. Anything that isn't
+/// balanced gives `None`.
+fn parse(text: &str) -> Option<Vec<Sexp<'_>>> {
+    let bytes = text.as_bytes();
+    let mut top = Vec::new();
+    let mut open: Vec<(usize, Vec<Sexp<'_>>)> = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let item = match bytes[pos] {
+            b'(' if bytes.get(pos + 1) == Some(&b';') => {
+                pos = skip_block_comment(bytes, pos)?;
+                continue;
+            }
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                pos = bytes[pos..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(bytes.len(), |newline| pos + newline);
+                continue;
+            }
+            b'(' => {
+                open.push((pos, Vec::new()));
+                pos += 1;
+                continue;
+            }
+            b')' => {
+                let (start, items) = open.pop()?;
+                pos += 1;
+                Sexp::List {
+                    items,
+                    start,
+                    end: pos - 1,
+                }
+            }
+            b if b.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'"' => {
+                let start = pos;
+                pos = skip_string(bytes, pos)?;
+                Sexp::Atom {
+                    text: &text[start..pos],
+                    start,
+                }
+            }
+            _ => {
+                let start = pos;
+                while pos < bytes.len()
+                    && !bytes[pos].is_ascii_whitespace()
+                    && !matches!(bytes[pos], b'(' | b')' | b'"' | b';')
+                {
+                    pos += 1;
+                }
+                Sexp::Atom {
+                    text: &text[start..pos],
+                    start,
+                }
+            }
+        };
+
+        match open.last_mut() {
+            Some((_, items)) => items.push(item),
+            None => top.push(item),
+        }
+    }
+
+    open.is_empty().then_some(top)
+}
+
+/// Skip a `(; ... ;)` comment, which may be nested, returning the position
+/// after it.
+fn skip_block_comment(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    let mut depth = 0;
+    while pos < bytes.len() {
+        match &bytes[pos..] {
+            [b'(', b';', ..] => {
+                depth += 1;
+                pos += 2;
+            }
+            [b';', b')', ..] => {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// Skip a string literal, returning the position after its closing quote.
+fn skip_string(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    pos += 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+    None
+}
+
+/// The fields of the module, which may or may not be wrapped in
+/// `(module ...)`. Modules given as `binary` or `quote` aren't supported.
+fn module_fields<'s, 'a>(top: &'s [Sexp<'a>]) -> Option<&'s [Sexp<'a>]> {
+    let items = match top {
+        [module @ Sexp::List { items, .. }] if module.head() == Some("module") => &items[1..],
+        _ => return Some(top),
+    };
+
+    let fields = match items.first() {
+        Some(Sexp::Atom { text, .. }) if text.starts_with('$') => &items[1..],
+        _ => items,
+    };
+    match fields.first() {
+        Some(Sexp::Atom { .. }) => None,
+        _ => Some(fields),
+    }
+}
+
+/// A function defined in the text.
+struct TextFunction {
+    /// Where `(func` starts.
+    start: usize,
+    /// Where the closing paren is.
+    end: usize,
+    /// Where each instruction starts, in the order they are emitted, or
+    /// `None` if the body uses something that can't be unfolded here.
+    instructions: Option<Vec<usize>>,
+}
+
+/// The parts of a `(func ...)` which come before its body.
+const FUNC_HEADER: &[&str] = &["export", "import", "type", "param", "result", "local"];
+
+fn text_functions(fields: &[Sexp<'_>]) -> Vec<TextFunction> {
+    let mut functions = Vec::new();
+
+    for field in fields {
+        let (items, start, end) = match field {
+            Sexp::List { items, start, end } if field.head() == Some("func") => {
+                (items, *start, *end)
+            }
+            _ => continue,
+        };
+        // Imported functions don't have a body
+        if items.iter().any(|item| item.head() == Some("import")) {
+            continue;
+        }
+
+        let body = items
+            .iter()
+            .skip(1)
+            .position(|item| match item {
+                Sexp::Atom { text, .. } => !text.starts_with('$'),
+                Sexp::List { .. } => !item.head().map_or(false, |h| FUNC_HEADER.contains(&h)),
+            })
+            .map_or(&items[items.len()..], |index| &items[index + 1..]);
+
+        let mut instructions = Vec::new();
+        let instructions = unfold(body, &mut instructions).map(|_| instructions);
+        functions.push(TextFunction {
+            start,
+            end,
+            instructions,
+        });
+    }
+
+    functions
+}
+
+/// Keywords which are immediates rather than instructions.
+const IMMEDIATE_KEYWORDS: &[&str] = &[
+    "func", "extern", "any", "eq", "i31", "struct", "array", "none", "noextern", "nofunc", "exn",
+    "i8x16", "i16x8", "i32x4", "i64x2", "f32x4", "f64x2", "inf",
+];
+
+fn is_instruction(atom: &str) -> bool {
+    atom.starts_with(|c: char| c.is_ascii_lowercase())
+        && !atom.contains('=')
+        && !atom.starts_with("nan")
+        && !IMMEDIATE_KEYWORDS.contains(&atom)
+}
+
+/// Add the positions of the instructions in a sequence of plain and folded
+/// instructions to `out`, in the order they are emitted.
+fn unfold(items: &[Sexp<'_>], out: &mut Vec<usize>) -> Option<()> {
+    for item in items {
+        match item {
+            Sexp::Atom { text, start } => {
+                if is_instruction(text) {
+                    out.push(*start);
+                }
+            }
+            Sexp::List { .. } => unfold_folded(item, out)?,
+        }
+    }
+    Some(())
+}
+
+fn unfold_folded(expr: &Sexp<'_>, out: &mut Vec<usize>) -> Option<()> {
+    let (items, end) = match expr {
+        Sexp::List { items, end, .. } => (items, *end),
+        Sexp::Atom { .. } => return Some(()),
+    };
+    let head = expr.head()?;
+    let keyword = items[0].start();
+    let operands = &items[1..];
+
+    match head {
+        // Type uses and block types are immediates
+        "type" | "param" | "result" => {}
+        "block" | "loop" => {
+            out.push(keyword);
+            unfold(operands, out)?;
+            out.push(end);
+        }
+        "if" => {
+            let mut then = None;
+            let mut otherwise = None;
+            for operand in operands {
+                match operand.head() {
+                    Some("then") => then = Some(operand),
+                    Some("else") => otherwise = Some(operand),
+                    // The condition
+                    _ => unfold_folded(operand, out)?,
+                }
+            }
+
+            out.push(keyword);
+            if let Some(Sexp::List { items, .. }) = then {
+                unfold(&items[1..], out)?;
+            }
+            if let Some(Sexp::List { items, .. }) = otherwise {
+                out.push(items[0].start());
+                unfold(&items[1..], out)?;
+            }
+            out.push(end);
+        }
+        // Folded exception handling isn't supported
+        "then" | "else" | "try" | "try_table" | "do" | "catch" | "catch_all" | "delegate" => {
+            return None
+        }
+        _ => {
+            // Operands are evaluated before the instruction itself
+            for operand in operands {
+                unfold_folded(operand, out)?;
+            }
+            out.push(keyword);
+        }
+    }
+
+    Some(())
+}
+
+/// A function body in the binary.
+struct BinaryFunction {
+    body_start: usize,
+    /// The offset of each instruction.
+    instructions: Vec<usize>,
+}
+
+/// The function bodies in a binary, and where the code section ends.
+fn binary_functions(wasm: &[u8]) -> Option<(Vec<BinaryFunction>, usize)> {
+    let mut functions = Vec::new();
+    let mut code_end = 0;
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload.ok()? {
+            Payload::CodeSectionStart { range, .. } => code_end = range.end,
+            Payload::CodeSectionEntry(body) => {
+                let mut operators = body.get_operators_reader().ok()?;
+                let mut instructions = Vec::new();
+                while !operators.eof() {
+                    let (_, offset) = operators.read_with_offset().ok()?;
+                    instructions.push(offset);
+                }
+                functions.push(BinaryFunction {
+                    body_start: body.range().start,
+                    instructions,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Some((functions, code_end))
+}
+
+/// Turns positions in the text into lines and columns.
+struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    fn location(&self, pos: usize) -> WatLocation {
+        let line = self.line_starts.partition_point(|&start| start <= pos);
+        let line_start = self.line_starts[line - 1];
+        WatLocation {
+            line: line as u32,
+            column: self.text[line_start..pos].chars().count() as u32 + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, column: u32) -> WatLocation {
+        WatLocation { line, column }
+    }
+
+    /// The locations of each instruction in the first function.
+    fn locations(wat: &str) -> Vec<WatLocation> {
+        let (wasm, map) = wat2wasm_with_source_map(wat.as_bytes()).unwrap();
+        let map = map.unwrap();
+        let (functions, _) = binary_functions(&wasm).unwrap();
+        functions[0]
+            .instructions
+            .iter()
+            .map(|&offset| map.lookup(offset).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn plain_instructions() {
+        let wat = "(module\n  (func (result i32)\n    i32.const 1\n    i32.const 2\n    i32.add))";
+        assert_eq!(locations(wat), [at(3, 5), at(4, 5), at(5, 5), at(5, 12)]);
+    }
+
+    #[test]
+    fn folded_instructions_are_unfolded() {
+        let wat = "(func (result i32)\n  (i32.add\n    (i32.const 1)\n    (i32.const 2)))";
+        assert_eq!(locations(wat), [at(3, 6), at(4, 6), at(2, 4), at(4, 19)]);
+    }
+
+    #[test]
+    fn folded_if() {
+        let wat =
+            "(func (param i32)\n  (if (local.get 0)\n    (then nop)\n    (else unreachable)))";
+        assert_eq!(
+            locations(wat),
+            [
+                at(2, 8),
+                at(2, 4),
+                at(3, 11),
+                at(4, 6),
+                at(4, 11),
+                at(4, 23),
+                at(4, 24)
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_input_has_no_source_map() {
+        let wasm = wat::parse_str("(module)").unwrap();
+        let (bytes, map) = wat2wasm_with_source_map(&wasm).unwrap();
+        assert_eq!(bytes, wasm);
+        assert!(map.is_none());
+    }
+}
@@ -89,6 +89,7 @@ mod tests {
                 minimum: Pages::from(18u32),
                 maximum: Some(Pages::from(18u32)),
                 shared: false,
+                memory64: false,
             }
         }
         fn size(&self) -> Pages {
@@ -211,6 +212,7 @@ mod tests {
         fn vmconfig(&self) -> &crate::vm::VMConfig {
             &VMConfig {
                 wasm_stack_size: Some(8 * 1024),
+                wasm_stack_guard_size: None,
             }
         }
     }
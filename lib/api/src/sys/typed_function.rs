@@ -3,7 +3,7 @@ use wasmer_types::RawValue;
 
 use crate::native_type::NativeWasmTypeInto;
 use crate::store::{AsStoreMut, AsStoreRef};
-use crate::sys::engine::NativeEngineExt;
+use crate::sys::exception::take_pending_exception;
 
 macro_rules! impl_native_traits {
     (  $( $x:ident ),* ) => {
@@ -48,14 +48,16 @@ macro_rules! impl_native_traits {
                     rets_list.as_mut()
                 };
 
+                let stack_limit = store.as_store_ref().effective_stack_limit();
+                let config = store.as_store_ref().vm_config();
                 let mut r;
                 loop {
                     let storeref = store.as_store_ref();
-                    let config = storeref.engine().tunables().vmconfig();
                     r = unsafe {
+                        let _running = storeref.inner.interrupt.enter();
                         wasmer_vm::wasmer_call_trampoline(
                             store.as_store_ref().signal_handler(),
-                            config,
+                            &config,
                             anyfunc.vmctx,
                             anyfunc.call_trampoline,
                             anyfunc.func_ptr,
@@ -73,7 +75,10 @@ macro_rules! impl_native_traits {
                     }
                     break;
                 }
-                r?;
+                // An exception the function threw and didn't catch
+                let exception = take_pending_exception(store);
+                r.map_err(|trap| RuntimeError::from(trap).with_stack_limit(stack_limit))?;
+                exception?;
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -130,14 +135,16 @@ macro_rules! impl_native_traits {
                     rets_list.as_mut()
                 };
 
+                let stack_limit = store.as_store_ref().effective_stack_limit();
+                let config = store.as_store_ref().vm_config();
                 let mut r;
                 loop {
                     let storeref = store.as_store_ref();
-                    let config = storeref.engine().tunables().vmconfig();
                     r = unsafe {
+                        let _running = storeref.inner.interrupt.enter();
                         wasmer_vm::wasmer_call_trampoline(
                             store.as_store_ref().signal_handler(),
-                            config,
+                            &config,
                             anyfunc.vmctx,
                             anyfunc.call_trampoline,
                             anyfunc.func_ptr,
@@ -156,7 +163,10 @@ macro_rules! impl_native_traits {
                     }
                     break;
                 }
-                r?;
+                // An exception the function threw and didn't catch
+                let exception = take_pending_exception(store);
+                r.map_err(|trap| RuntimeError::from(trap).with_stack_limit(stack_limit))?;
+                exception?;
 
                 let num_rets = rets_list.len();
                 if !using_rets_array && num_rets > 0 {
@@ -214,3 +224,26 @@ impl_native_traits!(
 impl_native_traits!(
     A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20
 );
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26
+);
@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::string::{String, ToString};
 
-use wasmer_types::Type;
+use wasmer_types::{Type, V128};
 
 use crate::vm::{VMExternRef, VMFuncRef};
 
@@ -43,7 +43,11 @@ pub enum Value {
     /// A first-class reference to a WebAssembly function.
     FuncRef(Option<Function>),
 
-    /// A 128-bit number
+    /// A 128-bit vector.
+    ///
+    /// The bytes of the `u128` are in little-endian order, so lane 0 is the
+    /// least significant part. Use [`V128`] to construct or inspect the
+    /// individual lanes.
     V128(u128),
 }
 
@@ -240,6 +244,18 @@ impl From<f64> for Value {
     }
 }
 
+impl From<u128> for Value {
+    fn from(val: u128) -> Self {
+        Self::V128(val)
+    }
+}
+
+impl From<V128> for Value {
+    fn from(val: V128) -> Self {
+        Self::V128(val.into())
+    }
+}
+
 impl From<Function> for Value {
     fn from(val: Function) -> Self {
         Self::FuncRef(Some(val))
@@ -268,6 +284,7 @@ const NOT_I32: &str = "Value is not of Wasm type i32";
 const NOT_I64: &str = "Value is not of Wasm type i64";
 const NOT_F32: &str = "Value is not of Wasm type f32";
 const NOT_F64: &str = "Value is not of Wasm type f64";
+const NOT_V128: &str = "Value is not of Wasm type v128";
 const NOT_FUNCREF: &str = "Value is not of Wasm type funcref";
 const NOT_EXTERNREF: &str = "Value is not of Wasm type externref";
 
@@ -319,6 +336,22 @@ impl TryFrom<Value> for f64 {
     }
 }
 
+impl TryFrom<Value> for u128 {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.v128().ok_or(NOT_V128)
+    }
+}
+
+impl TryFrom<Value> for V128 {
+    type Error = &'static str;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.v128().map(Self::from).ok_or(NOT_V128)
+    }
+}
+
 impl TryFrom<Value> for Option<Function> {
     type Error = &'static str;
 
@@ -472,4 +505,18 @@ mod tests {
         let result = f64::try_from(value);
         assert_eq!(result.unwrap_err(), "Value is not of Wasm type f64");
     }
+
+    #[test]
+    fn convert_value_to_v128() {
+        let value = Value::from(V128::from_i32x4([1, 2, 3, 4]));
+        assert_eq!(value.ty(), Type::V128);
+        let result = V128::try_from(value.clone());
+        assert_eq!(result.unwrap().to_i32x4(), [1, 2, 3, 4]);
+        let result = u128::try_from(value);
+        assert_eq!(result.unwrap() & 0xffff_ffff, 1);
+
+        let value = Value::I64(1);
+        let result = V128::try_from(value);
+        assert_eq!(result.unwrap_err(), "Value is not of Wasm type v128");
+    }
 }
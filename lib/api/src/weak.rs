@@ -0,0 +1,121 @@
+//! Weak handles to objects in a [`Store`][crate::Store].
+//!
+//! Handles like [`Memory`] don't own the object they refer to, the store
+//! does, so holding on to one never keeps a store's objects alive. What they
+//! can't do is tell whether their store is still around: using a handle after
+//! its store has been dropped (with some other store) panics.
+//!
+//! The weak handles here remember their store and only hand out the strong
+//! handle again while that store is alive, which makes them safe to capture
+//! in long-lived host callbacks.
+
+use std::sync::{Arc, Weak};
+
+use crate::store::AsStoreRef;
+use crate::{Function, Instance, Memory, StoreId};
+
+/// The store a weak handle was created from.
+#[derive(Debug, Clone)]
+struct WeakStore {
+    id: StoreId,
+    alive: Weak<()>,
+}
+
+impl WeakStore {
+    fn new(store: &impl AsStoreRef) -> Self {
+        let store = store.as_store_ref();
+        Self {
+            id: store.objects().id(),
+            alive: Arc::downgrade(&store.inner.alive),
+        }
+    }
+
+    /// Is the store still alive, and is it `store`?
+    fn is(&self, store: &impl AsStoreRef) -> bool {
+        if self.alive.strong_count() == 0 {
+            return false;
+        }
+
+        let id = store.as_store_ref().objects().id();
+        debug_assert_eq!(
+            id, self.id,
+            "a weak handle was upgraded with a different store than the one it was created from"
+        );
+        id == self.id
+    }
+}
+
+/// A weak handle to a [`Memory`], created with [`Memory::downgrade`].
+#[derive(Debug, Clone)]
+pub struct WeakMemory {
+    memory: Memory,
+    store: WeakStore,
+}
+
+impl WeakMemory {
+    pub(crate) fn new(memory: &Memory, store: &impl AsStoreRef) -> Self {
+        Self {
+            memory: memory.clone(),
+            store: WeakStore::new(store),
+        }
+    }
+
+    /// Get the [`Memory`] back, or `None` if its store has been dropped.
+    ///
+    /// `store` must be the store the memory belongs to. Passing any other
+    /// store is a bug, which panics in debug builds and returns `None`
+    /// otherwise.
+    pub fn upgrade(&self, store: &impl AsStoreRef) -> Option<Memory> {
+        self.store.is(store).then(|| self.memory.clone())
+    }
+}
+
+/// A weak handle to a [`Function`], created with [`Function::downgrade`].
+#[derive(Debug, Clone)]
+pub struct WeakFunction {
+    function: Function,
+    store: WeakStore,
+}
+
+impl WeakFunction {
+    pub(crate) fn new(function: &Function, store: &impl AsStoreRef) -> Self {
+        Self {
+            function: function.clone(),
+            store: WeakStore::new(store),
+        }
+    }
+
+    /// Get the [`Function`] back, or `None` if its store has been dropped.
+    ///
+    /// `store` must be the store the function belongs to. Passing any other
+    /// store is a bug, which panics in debug builds and returns `None`
+    /// otherwise.
+    pub fn upgrade(&self, store: &impl AsStoreRef) -> Option<Function> {
+        self.store.is(store).then(|| self.function.clone())
+    }
+}
+
+/// A weak handle to an [`Instance`], created with [`Instance::downgrade`].
+#[derive(Debug, Clone)]
+pub struct WeakInstance {
+    instance: Instance,
+    store: WeakStore,
+}
+
+impl WeakInstance {
+    pub(crate) fn new(instance: &Instance, store: &impl AsStoreRef) -> Self {
+        Self {
+            instance: instance.clone(),
+            store: WeakStore::new(store),
+        }
+    }
+
+    /// Get the [`Instance`] back, or `None` if its store has been dropped.
+    ///
+    /// `store` must be the store the instance belongs to. Passing any other
+    /// store is a bug, which panics in debug builds and returns `None`
+    /// otherwise.
+    pub fn upgrade(&self, store: &impl AsStoreRef) -> Option<Instance> {
+        self.store.is(store).then(|| self.instance.clone())
+    }
+}
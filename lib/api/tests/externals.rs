@@ -75,6 +75,56 @@ fn global_set() -> Result<(), String> {
     Ok(())
 }
 
+#[universal_test]
+fn global_v128() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let mut store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+(module
+  (global $g (import "env" "g") (mut v128))
+  (func (export "add") (param v128) (result v128)
+    (i32x4.add (global.get $g) (local.get 0))))
+"#,
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let global = Global::new_mut(&mut store, Value::from(V128::from(0_u128)));
+        let imports = imports! {
+            "env" => {
+                "g" => global.clone(),
+            },
+        };
+        let instance =
+            Instance::new(&mut store, &module, &imports).map_err(|e| format!("{e:?}"))?;
+        let add = instance
+            .exports
+            .get_function("add")
+            .map_err(|e| format!("{e:?}"))?;
+
+        global
+            .set(&mut store, V128::from_i32x4([1, 2, 3, -4]).into())
+            .map_err(|e| format!("{e:?}"))?;
+        let results = add
+            .call(&mut store, &[V128::from_i32x4([10, 20, 30, 40]).into()])
+            .map_err(|e| format!("{e:?}"))?;
+
+        let sum = V128::try_from(results[0].clone())?;
+        assert_eq!(sum.to_i32x4(), [11, 22, 33, 36]);
+        // Lane 0 is stored in the least significant bytes
+        assert_eq!(sum.bytes()[..4], 11_i32.to_le_bytes());
+        assert_eq!(
+            V128::try_from(global.get(&mut store))?.to_i32x4(),
+            [1, 2, 3, -4]
+        );
+        // Setting a v128 global to a different type should error
+        assert!(global.set(&mut store, Value::I32(1)).is_err());
+    }
+
+    Ok(())
+}
+
 #[universal_test]
 fn table_new() -> Result<(), String> {
     let mut store = Store::default();
@@ -147,9 +197,29 @@ fn table_grow() -> Result<(), String> {
 
         // Growing to a bigger maximum should return None
         let old_len = table
-            .grow(&mut store, 5, Value::FuncRef(Some(f)))
+            .grow(&mut store, 5, Value::FuncRef(Some(f.clone())))
             .map_err(|e| format!("{e:?}"))?;
         assert_eq!(old_len, 0);
+
+        // Growing exactly to the maximum is fine
+        let old_len = table
+            .grow(&mut store, 5, Value::FuncRef(None))
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(old_len, 5);
+        assert_eq!(table.size(&store), 10);
+
+        let err = table
+            .grow(&mut store, 1, Value::FuncRef(Some(f)))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TableError>(),
+            Some(&TableError::MaximumExceeded {
+                current: 10,
+                delta: 1,
+                maximum: 10
+            })
+        );
+        assert_eq!(table.size(&store), 10);
     }
 
     Ok(())
@@ -157,7 +227,139 @@ fn table_grow() -> Result<(), String> {
 
 #[universal_test]
 fn table_copy() -> Result<(), String> {
-    // TODO: table copy test not yet implemented
+    #[cfg(feature = "sys")]
+    {
+        let mut store = Store::default();
+        let f = Function::new_typed(&mut store, |num: i32| num + 1);
+        let g = Function::new_typed(&mut store, |num: i32| num - 1);
+        let src = Table::new(
+            &mut store,
+            TableType::new(Type::FuncRef, 4, None),
+            Value::FuncRef(Some(f.clone())),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let dst = Table::new(
+            &mut store,
+            TableType::new(Type::FuncRef, 3, None),
+            Value::FuncRef(None),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        src.set_funcref(&mut store, 3, Some(g.clone()))
+            .map_err(|e| format!("{e:?}"))?;
+
+        Table::copy(&mut store, &dst, 1, &src, 2, 2).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(dst.get_funcref(&mut store, 0).unwrap(), None);
+        assert_eq!(dst.get_funcref(&mut store, 1).unwrap(), Some(f));
+        assert_eq!(dst.get_funcref(&mut store, 2).unwrap(), Some(g.clone()));
+
+        // Overlapping copies within the same table
+        Table::copy(&mut store, &src, 0, &src, 1, 3).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(src.get_funcref(&mut store, 2).unwrap(), Some(g));
+
+        let err = Table::copy(&mut store, &dst, 2, &src, 0, 2).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TableError>(),
+            Some(&TableError::OutOfBounds {
+                start: 2,
+                len: 2,
+                size: 3
+            })
+        );
+        let err = Table::copy(&mut store, &dst, 0, &src, 3, 2).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TableError>(),
+            Some(&TableError::OutOfBounds {
+                start: 3,
+                len: 2,
+                size: 4
+            })
+        );
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn table_fill() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let mut store = Store::default();
+        let f = Function::new_typed(&mut store, || {});
+        let table = Table::new(
+            &mut store,
+            TableType::new(Type::FuncRef, 5, None),
+            Value::FuncRef(None),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+
+        table
+            .fill(&mut store, 1, Value::FuncRef(Some(f.clone())), 3)
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(table.get_funcref(&mut store, 0).unwrap(), None);
+        assert_eq!(table.get_funcref(&mut store, 3).unwrap(), Some(f.clone()));
+        assert_eq!(table.get_funcref(&mut store, 4).unwrap(), None);
+
+        // Nothing is written when the range is out of bounds
+        let err = table
+            .fill(&mut store, 3, Value::FuncRef(Some(f)), 3)
+            .unwrap_err();
+        assert!(err.is::<TableError>());
+        assert_eq!(table.get_funcref(&mut store, 4).unwrap(), None);
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn table_type_mismatch() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let mut store = Store::default();
+        let funcs = Table::new(
+            &mut store,
+            TableType::new(Type::FuncRef, 2, None),
+            Value::FuncRef(None),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let externs = Table::new(
+            &mut store,
+            TableType::new(Type::ExternRef, 2, None),
+            Value::ExternRef(None),
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let mismatch = Some(TableError::TypeMismatch {
+            expected: Type::FuncRef,
+            found: Type::ExternRef,
+        });
+
+        let err = funcs
+            .set(&mut store, 0, Value::ExternRef(None))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TableError>(), mismatch.as_ref());
+        let err = funcs.get_externref(&mut store, 0).unwrap_err();
+        assert_eq!(err.downcast_ref::<TableError>(), mismatch.as_ref());
+        let err = funcs
+            .grow(&mut store, 1, Value::ExternRef(None))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TableError>(), mismatch.as_ref());
+        let err = funcs
+            .fill(&mut store, 0, Value::ExternRef(None), 1)
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TableError>(), mismatch.as_ref());
+        let err = Table::copy(&mut store, &funcs, 0, &externs, 0, 1).unwrap_err();
+        assert_eq!(err.downcast_ref::<TableError>(), mismatch.as_ref());
+
+        let err = externs.set_funcref(&mut store, 0, None).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TableError>(),
+            Some(&TableError::TypeMismatch {
+                expected: Type::ExternRef,
+                found: Type::FuncRef,
+            })
+        );
+        assert_eq!(funcs.size(&store), 2);
+    }
+
     Ok(())
 }
 
@@ -168,6 +370,7 @@ fn memory_new() -> Result<(), String> {
         shared: false,
         minimum: Pages(0),
         maximum: Some(Pages(10)),
+        memory64: false,
     };
     let memory = Memory::new(&mut store, memory_type).map_err(|e| format!("{e:?}"))?;
     assert_eq!(memory.view(&mut store).size(), Pages(0));
@@ -206,6 +409,347 @@ fn memory_grow() -> Result<(), String> {
     Ok(())
 }
 
+#[universal_test]
+fn memory64_beyond_4gib() -> Result<(), String> {
+    #[cfg(all(feature = "sys", target_pointer_width = "64"))]
+    {
+        let mut store = Store::default();
+        // One page past 4GiB. The memory is only touched sparsely, but hosts
+        // without enough address space can't create it at all.
+        let desc = MemoryType::new64(Pages(65537), None, false);
+        let memory = match Memory::new(&mut store, desc) {
+            Ok(memory) => memory,
+            Err(_) => return Ok(()),
+        };
+        assert_eq!(memory.ty(&store).maximum, None);
+
+        let view = memory.view(&store);
+        assert_eq!(view.data_size(), 65537 * WASM_PAGE_SIZE as u64);
+        let offset = 0xFFFF_FFFCu64;
+        view.write(offset, &[1, 2, 3, 4, 5, 6, 7, 8])
+            .map_err(|e| format!("{e:?}"))?;
+        let mut buf = [0u8; 8];
+        view.read(offset, &mut buf).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let ptr: WasmPtr<u64, Memory64> = WasmPtr::new(offset);
+        let ptr = ptr.add_offset(2).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(ptr.offset(), 0x1_0000_000C);
+        ptr.write(&view, u64::MAX).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(view.read_u8(0x1_0000_000C).unwrap(), 0xff);
+        assert_eq!(view.read_u8(0x0_0000_000C).unwrap(), 0);
+
+        let end = view.data_size();
+        assert!(matches!(
+            view.read(end - 4, &mut buf),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        ));
+        assert!(matches!(
+            view.write(u64::MAX, &[0]),
+            Err(MemoryAccessError::Overflow)
+        ));
+
+        let pages = Pages::try_from(2u64).map_err(|e| format!("{e:?}"))?;
+        assert_eq!(memory.grow(&mut store, pages), Ok(Pages(65537)));
+        assert_eq!(memory.view(&store).size(), Pages(65539));
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn memory_view_slice_at() -> Result<(), String> {
+    let mut store = Store::default();
+    let memory =
+        Memory::new(&mut store, MemoryType::new(1, None, false)).map_err(|e| format!("{e:?}"))?;
+    let view = memory.view(&store);
+
+    // Unaligned offsets are fine
+    let slice = view.slice_at::<u32>(3, 4).map_err(|e| format!("{e:?}"))?;
+    slice.copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(slice.get(2), Some(3));
+    assert_eq!(slice.get(4), None);
+    slice.set(0, 0xdead_beef);
+
+    let mut values = [0u32; 4];
+    slice.copy_to_slice(&mut values);
+    assert_eq!(values, [0xdead_beef, 2, 3, 4]);
+    let ptr: WasmPtr<u32> = WasmPtr::new(3);
+    assert_eq!(
+        ptr.slice(&view, 4)
+            .and_then(|s| s.read_to_vec())
+            .map_err(|e| format!("{e:?}"))?,
+        values
+    );
+
+    let tail = slice.subslice(2..4).unwrap();
+    assert_eq!((tail.offset(), tail.len()), (11, 2));
+    assert!(slice.subslice(3..5).is_none());
+
+    // The whole slice must be in bounds
+    let size = view.data_size();
+    assert!(view.slice_at::<u8>(size, 0).is_ok());
+    assert!(view.slice_at::<u32>(size - 4, 1).is_ok());
+    assert!(matches!(
+        view.slice_at::<u32>(size - 3, 1),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    ));
+    assert!(matches!(
+        view.slice_at::<u64>(0, u64::MAX),
+        Err(MemoryAccessError::Overflow)
+    ));
+
+    Ok(())
+}
+
+#[universal_test]
+fn memory_view_slice_at_tolerates_torn_reads() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let mut store = Store::default();
+        let memory = Memory::new(&mut store, MemoryType::new(1, Some(1), true))
+            .map_err(|e| format!("{e:?}"))?;
+        let view = memory.view(&store);
+        let slice = view
+            .slice_at::<u64>(1, 1024)
+            .map_err(|e| format!("{e:?}"))?;
+        let base = view.data_ptr() as usize;
+        let done = AtomicBool::new(false);
+
+        // Another thread keeps flipping the bytes while we read them
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let mut byte = 0u8;
+                while !done.load(Ordering::Relaxed) {
+                    byte = !byte;
+                    for i in 1..1 + 1024 * 8 {
+                        unsafe { ((base + i) as *mut u8).write_volatile(byte) };
+                    }
+                }
+            });
+
+            let mut buf = vec![0u64; 1024];
+            for _ in 0..100 {
+                slice.copy_to_slice(&mut buf);
+                for value in buf.iter().copied().chain(slice.get(7)) {
+                    assert!(value.to_le_bytes().iter().all(|&b| b == 0 || b == 0xff));
+                }
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn memory_grow_observers() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        use std::sync::{Arc, Mutex};
+
+        let mut store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+(module
+  (memory (export "memory") 1 10)
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0))))
+"#,
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let instance =
+            Instance::new(&mut store, &module, &imports! {}).map_err(|e| format!("{e:?}"))?;
+        let memory = instance
+            .exports
+            .get_memory("memory")
+            .map_err(|e| format!("{e:?}"))?
+            .clone();
+        let grow = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "grow")
+            .map_err(|e| format!("{e:?}"))?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for observer in ["first", "second"] {
+            let events = Arc::clone(&events);
+            let expected = memory.clone();
+            memory.subscribe_grow(&mut store, move |m, old, new| {
+                assert_eq!(*m, expected);
+                events.lock().unwrap().push((observer, old, new));
+            });
+        }
+        assert_eq!(memory.generation(&store), 0);
+
+        // Grown by the guest
+        assert_eq!(grow.call(&mut store, 2).map_err(|e| format!("{e:?}"))?, 1);
+        assert_eq!(memory.generation(&store), 1);
+        // Grown by the host
+        memory
+            .grow(&mut store, Pages(3))
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(memory.generation(&store), 2);
+        // Failed and zero-sized grows aren't reported
+        assert_eq!(
+            grow.call(&mut store, 100).map_err(|e| format!("{e:?}"))?,
+            -1
+        );
+        assert_eq!(grow.call(&mut store, 0).map_err(|e| format!("{e:?}"))?, 6);
+        assert!(memory.grow(&mut store, Pages(100)).is_err());
+        assert_eq!(memory.generation(&store), 2);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ("first", Pages(1), Pages(3)),
+                ("second", Pages(1), Pages(3)),
+                ("first", Pages(3), Pages(6)),
+                ("second", Pages(3), Pages(6)),
+            ]
+        );
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn memory_atomics_validation() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        use std::time::Duration;
+
+        let mut store = Store::default();
+        let shared = Memory::new(&mut store, MemoryType::new(1, Some(1), true))
+            .map_err(|e| format!("{e:?}"))?;
+        let unshared = Memory::new(&mut store, MemoryType::new(1, None, false))
+            .map_err(|e| format!("{e:?}"))?;
+        let timeout = Some(Duration::from_millis(10));
+
+        assert_eq!(shared.atomic_notify(&store, 0, 1), Ok(0));
+        assert_eq!(unshared.atomic_notify(&store, 0, 1), Ok(0));
+        assert_eq!(
+            shared.atomic_wait32(&store, 0, 1, timeout),
+            Ok(WaitResult::NotEqual)
+        );
+        assert_eq!(
+            shared.atomic_wait64(&store, 8, 0, timeout),
+            Ok(WaitResult::TimedOut)
+        );
+        assert_eq!(
+            unshared.atomic_wait32(&store, 0, 0, timeout),
+            Err(AtomicsError::NotShared)
+        );
+        assert_eq!(
+            shared.atomic_wait32(&store, 2, 0, timeout),
+            Err(AtomicsError::Unaligned {
+                offset: 2,
+                alignment: 4
+            })
+        );
+        assert_eq!(
+            shared.atomic_wait64(&store, 4, 0, timeout),
+            Err(AtomicsError::Unaligned {
+                offset: 4,
+                alignment: 8
+            })
+        );
+        assert_eq!(
+            shared.atomic_notify(&store, 65536, 1),
+            Err(AtomicsError::OutOfBounds {
+                offset: 65536,
+                len: 4,
+                size: 65536
+            })
+        );
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn memory_atomics_interoperate_with_guest() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        use std::time::Duration;
+
+        let mut store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"
+(module
+  (import "env" "memory" (memory 1 1 shared))
+  (func (export "wait") (param i32 i32) (result i32)
+    (memory.atomic.wait32 (local.get 0) (local.get 1) (i64.const -1)))
+  (func (export "notify") (param i32 i32) (result i32)
+    (memory.atomic.notify (local.get 0) (local.get 1))))
+"#,
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let memory = Memory::new(&mut store, MemoryType::new(1, Some(1), true))
+            .map_err(|e| format!("{e:?}"))?;
+
+        // Each guest thread gets its own store and instance, sharing the memory
+        let spawn = |name: &'static str| {
+            let engine = store.engine().clone();
+            let module = module.clone();
+            let shared = memory.try_clone(&store).unwrap();
+            std::thread::spawn(move || {
+                let mut store = Store::new(engine);
+                let memory = Memory::new_from_existing(&mut store, shared);
+                let imports = imports! {
+                    "env" => {
+                        "memory" => memory,
+                    },
+                };
+                let instance = Instance::new(&mut store, &module, &imports).unwrap();
+                let f = instance
+                    .exports
+                    .get_typed_function::<(i32, i32), i32>(&store, name)
+                    .unwrap();
+                (store, f)
+            })
+        };
+
+        // The host wakes up a guest thread
+        let guest = spawn("wait");
+        let waiter = std::thread::spawn(move || {
+            let (mut store, wait) = guest.join().unwrap();
+            wait.call(&mut store, 0, 0).unwrap()
+        });
+        let mut woken = 0;
+        while woken == 0 {
+            woken = memory
+                .atomic_notify(&store, 0, 1)
+                .map_err(|e| format!("{e:?}"))?;
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(woken, 1);
+        assert_eq!(waiter.join().unwrap(), 0);
+
+        // A guest thread wakes up the host
+        let guest = spawn("notify");
+        let notifier = std::thread::spawn(move || {
+            let (mut store, notify) = guest.join().unwrap();
+            loop {
+                if notify.call(&mut store, 4, 1).unwrap() == 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+        let result = memory
+            .atomic_wait32(&store, 4, 0, Some(Duration::from_secs(60)))
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(result, WaitResult::Ok);
+        notifier.join().unwrap();
+    }
+
+    Ok(())
+}
+
 #[universal_test]
 fn function_new() -> Result<(), String> {
     let mut store = Store::default();
@@ -41,3 +41,104 @@ fn data_and_store_mut() -> Result<(), String> {
 
     Ok(())
 }
+
+#[universal_test]
+fn with_data_and_store_mut() -> Result<(), String> {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "env" "log" (func $log (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "hello")
+  (func (export "double") (param i32) (result i32)
+    (i32.mul (local.get 0) (i32.const 2)))
+  (func (export "run")
+    (call $log (i32.const 16) (i32.const 5))))
+"#,
+    )
+    .map_err(|e| format!("{e:?}"))?;
+
+    struct Env {
+        instance: Option<Instance>,
+        messages: Vec<String>,
+        doubled: i32,
+    }
+
+    fn log(mut env: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) {
+        env.with_data_and_store_mut(|data, mut store| {
+            let instance = data.instance.as_ref().unwrap();
+
+            // Read guest memory...
+            let memory = instance.exports.get_memory("memory").unwrap();
+            let message = ptr.read_utf8_string(&memory.view(&store), len).unwrap();
+
+            // ... call back into the guest...
+            let double: TypedFunction<i32, i32> = instance
+                .exports
+                .get_typed_function(&store, "double")
+                .unwrap();
+            let doubled = double.call(&mut store, message.len() as i32).unwrap();
+
+            // ... and update the host state, all at the same time
+            data.messages.push(message);
+            data.doubled = doubled;
+        });
+    }
+
+    let env = FunctionEnv::new(
+        &mut store,
+        Env {
+            instance: None,
+            messages: Vec::new(),
+            doubled: 0,
+        },
+    );
+    let imports = imports! {
+        "env" => {
+            "log" => Function::new_typed_with_env(&mut store, &env, log),
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports).map_err(|e| format!("{e:?}"))?;
+    env.as_mut(&mut store).instance = Some(instance.clone());
+
+    let run: TypedFunction<(), ()> = instance
+        .exports
+        .get_typed_function(&store, "run")
+        .map_err(|e| format!("{e:?}"))?;
+    run.call(&mut store).map_err(|e| format!("{e:?}"))?;
+
+    let data = env.as_ref(&store);
+    assert_eq!(data.messages, vec!["hello".to_string()]);
+    assert_eq!(data.doubled, 10);
+
+    Ok(())
+}
+
+#[cfg(feature = "sys")]
+#[test]
+fn with_data_and_store_mut_rejects_reentrant_access() {
+    let mut store = Store::default();
+    let env = FunctionEnv::new(&mut store, 1_u32);
+
+    // Something that gets hold of the store while the data is lent out can't
+    // reach the data too
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        env.with_data_and_store_mut(&mut store, |data, store| {
+            *data += *env.as_ref(&store);
+        })
+    }));
+    let payload = result.unwrap_err();
+    let message = payload
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| payload.downcast_ref::<&str>().copied())
+        .unwrap();
+    assert!(message.contains("already borrowed"), "{message}");
+
+    // The data is put back afterwards
+    assert_eq!(*env.as_ref(&store), 1);
+    env.with_data_and_store_mut(&mut store, |data, _| *data += 1);
+    assert_eq!(*env.as_ref(&store), 2);
+}
@@ -78,3 +78,230 @@ fn unit_native_function_env() -> Result<(), String> {
 
     Ok(())
 }
+
+#[universal_test]
+fn chained_resolvers_prefer_earlier_sources() -> Result<(), String> {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "env" "a" (func $a (result i32)))
+  (import "env" "b" (func $b (result i32)))
+  (import "env" "c" (func $c (result i32)))
+  (func (export "run") (result i32)
+    call $a
+    i32.const 100
+    i32.mul
+    call $b
+    i32.const 10
+    i32.mul
+    i32.add
+    call $c
+    i32.add))
+"#,
+    )
+    .map_err(|e| format!("{e:?}"))?;
+
+    let mut first = Imports::new();
+    first.define("env", "a", Function::new_typed(&mut store, || 1));
+
+    let mut second = Imports::new();
+    second.define("env", "a", Function::new_typed(&mut store, || 2));
+    second.define("env", "b", Function::new_typed(&mut store, || 2));
+
+    let mut third = Imports::new();
+    third.define("env", "a", Function::new_typed(&mut store, || 3));
+    third.define("env", "b", Function::new_typed(&mut store, || 3));
+    third.define("env", "c", Function::new_typed(&mut store, || 3));
+
+    // first > second > third
+    let resolver = second.chain_front(first).chain_back(third);
+    assert_eq!(resolver.len(), 3);
+
+    let instance = Instance::new_with_resolver(&mut store, &module, &resolver)
+        .map_err(|e| format!("{e:?}"))?;
+    let run: TypedFunction<(), i32> = instance
+        .exports
+        .get_typed_function(&store, "run")
+        .map_err(|e| format!("{e:?}"))?;
+    assert_eq!(run.call(&mut store).map_err(|e| format!("{e:?}"))?, 123);
+
+    Ok(())
+}
+
+#[universal_test]
+fn strict_resolution_reports_every_import() -> Result<(), String> {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "env" "present" (func (result i32)))
+  (import "env" "missing" (func))
+  (import "env" "wrong_type" (func (param i64)))
+  (import "other" "also_missing" (global i32)))
+"#,
+    )
+    .map_err(|e| format!("{e:?}"))?;
+
+    let mut first = Imports::new();
+    first.define("env", "present", Function::new_typed(&mut store, || 1));
+
+    let mut second = Imports::new();
+    second.define(
+        "env",
+        "wrong_type",
+        Function::new_typed(&mut store, |_: i32| {}),
+    );
+
+    let mut third = Imports::new();
+    third.define(
+        "env",
+        "present",
+        Function::new_typed(&mut store, |_: i32| {}),
+    );
+
+    let resolver = first.chain_back(second).chain_back(third);
+
+    let errors = match Instance::new_with_resolver_strict(&mut store, &module, &resolver) {
+        Err(InstantiationError::Link(LinkError::Imports(errors))) => errors,
+        Err(e) => return Err(format!("unexpected error: {e:?}")),
+        Ok(_) => return Err("instantiation should have failed".to_string()),
+    };
+
+    let summary: Vec<_> = errors
+        .iter()
+        .map(|(module, field, error)| (module.as_str(), field.as_str(), error))
+        .collect();
+    assert_eq!(summary.len(), 3, "{summary:?}");
+
+    assert!(matches!(
+        summary[0],
+        ("env", "missing", ImportError::UnknownImport(ExternType::Function(ty)))
+            if ty.params().is_empty() && ty.results().is_empty()
+    ));
+    assert!(matches!(
+        summary[1],
+        (
+            "env",
+            "wrong_type",
+            ImportError::IncompatibleType(ExternType::Function(expected), ExternType::Function(found))
+        ) if expected.params() == [Type::I64] && found.params() == [Type::I32]
+    ));
+    assert!(matches!(
+        summary[2],
+        (
+            "other",
+            "also_missing",
+            ImportError::UnknownImport(ExternType::Global(_))
+        )
+    ));
+
+    // The non-strict mode only reports the first problem
+    match Instance::new_with_resolver(&mut store, &module, &resolver) {
+        Err(InstantiationError::Link(LinkError::Import(module, field, _))) => {
+            assert_eq!((module.as_str(), field.as_str()), ("env", "missing"));
+        }
+        Err(e) => return Err(format!("unexpected error: {e:?}")),
+        Ok(_) => return Err("instantiation should have failed".to_string()),
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn instance_pre_instantiates_many_times() -> Result<(), String> {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "env" "get" (func $get (result i32)))
+  (global $counter (mut i32) (i32.const 0))
+  (func (export "run") (result i32)
+    global.get $counter
+    call $get
+    i32.add
+    global.set $counter
+    global.get $counter))
+"#,
+    )
+    .map_err(|e| format!("{e:?}"))?;
+
+    let imports = imports! {
+        "env" => {
+            "get" => Function::new_typed(&mut store, || 5),
+        },
+    };
+    let pre = InstancePre::new(&store, &module, &imports).map_err(|e| format!("{e:?}"))?;
+
+    for _ in 0..3 {
+        // Each instance gets its own state
+        let instance = pre.instantiate(&mut store).map_err(|e| format!("{e:?}"))?;
+        let run: TypedFunction<(), i32> = instance
+            .exports
+            .get_typed_function(&store, "run")
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(run.call(&mut store).map_err(|e| format!("{e:?}"))?, 5);
+        assert_eq!(run.call(&mut store).map_err(|e| format!("{e:?}"))?, 10);
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn instance_pre_ignores_later_changes_to_imports() -> Result<(), String> {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+(module
+  (import "env" "get" (func $get (result i32)))
+  (func (export "run") (result i32)
+    call $get))
+"#,
+    )
+    .map_err(|e| format!("{e:?}"))?;
+
+    let mut imports = Imports::new();
+    imports.define("env", "get", Function::new_typed(&mut store, || 1));
+    let pre = InstancePre::new(&store, &module, &imports).map_err(|e| format!("{e:?}"))?;
+
+    // Swapping the import out afterwards doesn't affect the InstancePre
+    imports.define("env", "get", Function::new_typed(&mut store, || 2));
+
+    let call_run = |store: &mut Store, instance: &Instance| -> Result<i32, String> {
+        let run: TypedFunction<(), i32> = instance
+            .exports
+            .get_typed_function(store, "run")
+            .map_err(|e| format!("{e:?}"))?;
+        run.call(store).map_err(|e| format!("{e:?}"))
+    };
+
+    let stale = pre.instantiate(&mut store).map_err(|e| format!("{e:?}"))?;
+    assert_eq!(call_run(&mut store, &stale)?, 1);
+    let fresh = InstancePre::new(&store, &module, &imports)
+        .and_then(|pre| pre.instantiate(&mut store))
+        .map_err(|e| format!("{e:?}"))?;
+    assert_eq!(call_run(&mut store, &fresh)?, 2);
+
+    // Even replacing the import with one of the wrong type only matters to
+    // new InstancePres
+    imports.define("env", "get", Function::new_typed(&mut store, |_: i32| {}));
+    let stale = pre.instantiate(&mut store).map_err(|e| format!("{e:?}"))?;
+    assert_eq!(call_run(&mut store, &stale)?, 1);
+    assert!(matches!(
+        InstancePre::new(&store, &module, &imports),
+        Err(InstantiationError::Link(LinkError::Imports(_)))
+    ));
+
+    // The externs are tied to the original store
+    let mut other_store = Store::default();
+    assert!(matches!(
+        pre.instantiate(&mut other_store),
+        Err(InstantiationError::DifferentStores)
+    ));
+
+    Ok(())
+}
@@ -290,3 +290,208 @@ fn module_custom_sections() -> Result<(), String> {
     );
     Ok(())
 }
+
+#[universal_test]
+fn module_duplicate_custom_sections() -> Result<(), String> {
+    let store = Store::default();
+    // An empty module with the custom sections "meta" = "a", "other" = "x"
+    // and "meta" = "b", in that order
+    let wasm = b"\0asm\x01\0\0\0\0\x06\x04metaa\0\x07\x05otherx\0\x06\x04metab";
+    let module = Module::new(&store, &wasm[..]).map_err(|e| format!("{e:?}"))?;
+
+    let check = |module: &Module| {
+        let sections: Vec<Box<[u8]>> = module.custom_sections("meta").collect();
+        assert_eq!(sections, [Box::from(&b"a"[..]), Box::from(&b"b"[..])]);
+        assert_eq!(
+            module.custom_sections("other").collect::<Vec<_>>(),
+            [Box::from(&b"x"[..])]
+        );
+        assert_eq!(module.custom_sections("missing").count(), 0);
+    };
+    check(&module);
+
+    #[cfg(any(feature = "sys", feature = "jsc"))]
+    assert_eq!(
+        module.custom_section_names().collect::<Vec<_>>(),
+        ["meta", "other"]
+    );
+
+    #[cfg(feature = "sys")]
+    {
+        let serialized = module.serialize().map_err(|e| format!("{e:?}"))?;
+        let deserialized =
+            unsafe { Module::deserialize(&store, serialized) }.map_err(|e| format!("{e:?}"))?;
+        check(&deserialized);
+        assert_eq!(
+            deserialized.custom_section_names().collect::<Vec<_>>(),
+            ["meta", "other"]
+        );
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn module_validate_with_features() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let host = Triple::host();
+        let validate = |wat: &str, features: &Features, target: &Triple| {
+            let wasm = wat::parse_str(wat).unwrap();
+            let result = Module::validate_with_features(&wasm, features, target);
+            (wasm, result)
+        };
+
+        let mut features = Features::new();
+        features
+            .simd(false)
+            .threads(false)
+            .bulk_memory(false)
+            .memory64(false);
+
+        let (wasm, result) = validate(
+            "(module (func (drop (v128.const i64x2 0 0))))",
+            &features,
+            &host,
+        );
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.kind,
+            ValidationErrorKind::UnsupportedProposal { proposal: "simd" }
+        );
+        assert_eq!(error.function_index, Some(FunctionIndex::from_u32(0)));
+        // The SIMD instruction prefix
+        assert_eq!(wasm[error.offset], 0xfd);
+
+        let (_, result) = validate("(module (memory 1 1 shared))", &features, &host);
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.kind,
+            ValidationErrorKind::UnsupportedProposal {
+                proposal: "threads"
+            }
+        );
+        assert_eq!(error.function_index, None);
+
+        let (wasm, result) = validate(
+            "(module (memory 1) (func (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))))",
+            &features,
+            &host,
+        );
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.kind,
+            ValidationErrorKind::UnsupportedProposal {
+                proposal: "bulk-memory"
+            }
+        );
+        // The bulk memory instruction prefix
+        assert_eq!(wasm[error.offset], 0xfc);
+
+        let (wasm, result) = validate("(module (func (result i32) i64.const 0))", &features, &host);
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, ValidationErrorKind::TypeMismatch);
+        assert_eq!(error.function_index, Some(FunctionIndex::from_u32(0)));
+        // The function's closing `end`
+        assert_eq!(wasm[error.offset], 0x0b);
+
+        // memory64 is only supported on 64-bit targets
+        features.memory64(true);
+        let (_, result) = validate("(module (memory i64 1))", &features, &host);
+        assert_eq!(result, Ok(()));
+        let wasm32: Triple = "wasm32-unknown-unknown".parse().unwrap();
+        let (_, result) = validate("(module (memory i64 1))", &features, &wasm32);
+        assert_eq!(
+            result.unwrap_err().kind,
+            ValidationErrorKind::UnsupportedProposal {
+                proposal: "memory64"
+            }
+        );
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn module_wat_source_map() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let mut store = Store::default();
+
+        // Validation errors say where in the text they are
+        let wat = "(module\n  (func (result i32)\n    i64.const 0))";
+        let error = Module::new_with_source_map(&store, wat).unwrap_err();
+        assert!(error.to_string().contains("(<wat>:3:16)"), "{error}");
+
+        let wat = "(module\n  (func (export \"run\")\n    nop\n    unreachable))";
+        let module = Module::new_with_source_map(&store, wat).map_err(|e| format!("{e:?}"))?;
+        assert!(module.source_map().is_some());
+        let instance =
+            Instance::new(&mut store, &module, &imports! {}).map_err(|e| format!("{e:?}"))?;
+        let run = instance
+            .exports
+            .get_function("run")
+            .map_err(|e| format!("{e:?}"))?;
+
+        let error = run.call(&mut store, &[]).unwrap_err();
+        assert_eq!(
+            error.trace()[0].wat_location(),
+            Some(WatLocation { line: 4, column: 5 })
+        );
+        assert!(error.to_string().contains(":4:5)"), "{error}");
+
+        // It's only kept when asked for
+        let module = Module::new(&store, wat).map_err(|e| format!("{e:?}"))?;
+        assert!(module.source_map().is_none());
+
+        // Modules compiled from a binary don't have one
+        let wasm = wat2wasm(wat.as_bytes()).unwrap();
+        let module = Module::new_with_source_map(&store, wasm).map_err(|e| format!("{e:?}"))?;
+        assert!(module.source_map().is_none());
+    }
+
+    Ok(())
+}
+
+#[universal_test]
+fn module_validate_all_with_features() -> Result<(), String> {
+    #[cfg(feature = "sys")]
+    {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (result i32) i64.const 0)
+                (func (result i32) i32.const 0)
+                (func (drop (v128.const i64x2 0 0))))"#,
+        )
+        .unwrap();
+        let mut features = Features::new();
+        features.simd(false);
+
+        let errors =
+            Module::validate_all_with_features(&wasm, &features, &Triple::host()).unwrap_err();
+
+        let kinds: Vec<_> = errors
+            .iter()
+            .map(|e| (e.function_index, e.kind.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    Some(FunctionIndex::from_u32(0)),
+                    ValidationErrorKind::TypeMismatch
+                ),
+                (
+                    Some(FunctionIndex::from_u32(2)),
+                    ValidationErrorKind::UnsupportedProposal { proposal: "simd" }
+                ),
+            ]
+        );
+
+        // Without collecting, validation stops at the first error
+        let error = Module::validate_with_features(&wasm, &features, &Triple::host()).unwrap_err();
+        assert_eq!(error, errors[0]);
+    }
+
+    Ok(())
+}
@@ -242,6 +242,57 @@ pub mod reference_types {
         Ok(())
     }
 
+    #[universal_test]
+    fn extern_ref_host_data_and_drop_observer() -> Result<()> {
+        use std::sync::Mutex;
+
+        let mut store = Store::default();
+        let wat = r#"(module
+    (table $table (export "table") 1 externref)
+    (func (export "stash") (param $er externref)
+          (table.set $table (i32.const 0) (local.get $er)))
+    (func (export "fetch") (result externref)
+          (table.get $table (i32.const 0)))
+    (func (export "clear")
+          (table.set $table (i32.const 0) (ref.null extern)))
+)"#;
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let stash: TypedFunction<Option<ExternRef>, ()> =
+            instance.exports.get_typed_function(&store, "stash")?;
+        let fetch: TypedFunction<(), Option<ExternRef>> =
+            instance.exports.get_typed_function(&store, "fetch")?;
+        let clear: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "clear")?;
+
+        let data = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let dropped = Arc::new(AtomicBool::new(false));
+        {
+            let er = ExternRef::new(&mut store, Arc::clone(&data));
+            let flag = Arc::clone(&dropped);
+            er.set_drop_observer(&mut store, move || flag.store(true, Ordering::SeqCst));
+            stash.call(&mut store, Some(er.clone()))?;
+
+            let fetched = fetch.call(&mut store)?.unwrap();
+            assert_eq!(fetched, er);
+            let inner: &Arc<Mutex<Vec<i32>>> = fetched.downcast(&store).unwrap();
+            assert!(Arc::ptr_eq(inner, &data));
+            inner.lock().unwrap().push(4);
+        }
+        assert_eq!(*data.lock().unwrap(), [1, 2, 3, 4]);
+
+        // The table still holds a reference, so nothing gets collected
+        assert_eq!(store.gc_externrefs(), 0);
+        assert!(!dropped.load(Ordering::SeqCst));
+        assert_eq!(Arc::strong_count(&data), 2);
+
+        clear.call(&mut store)?;
+        assert_eq!(store.gc_externrefs(), 1);
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(Arc::strong_count(&data), 1);
+
+        Ok(())
+    }
+
     #[universal_test]
     fn refs_in_globals() -> Result<()> {
         let mut store = Store::default();
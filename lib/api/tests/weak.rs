@@ -0,0 +1,147 @@
+//! Weak handles, checked with an allocator which keeps track of how many
+//! bytes the current thread has allocated and not yet freed.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use wasmer::*;
+
+struct CountingAllocator;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = Cell::new(0);
+}
+
+fn track(delta: isize) {
+    // Ignore allocations made while the thread is being torn down
+    let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + delta));
+}
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(|live| live.get())
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        track(-(layout.size() as isize));
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The table makes the instance big enough to notice when it isn't freed.
+const MODULE: &str = r#"(module
+    (import "host" "peek" (func $peek (result i32)))
+    (memory (export "memory") 1)
+    (table 100000 funcref)
+    (data (i32.const 0) "\2a")
+    (func (export "run") (result i32)
+        call $peek))"#;
+
+const TABLE_BYTES: isize = 100_000 * std::mem::size_of::<usize>() as isize;
+
+#[derive(Default)]
+struct Env {
+    memory: Option<WeakMemory>,
+}
+
+/// Reads the first byte of memory, or returns -1 if the memory is gone.
+fn peek(env: FunctionEnvMut<Env>) -> i32 {
+    let memory = env.data().memory.as_ref().and_then(|m| m.upgrade(&env));
+    match memory {
+        Some(memory) => memory.view(&env).read_u8(0).unwrap() as i32,
+        None => -1,
+    }
+}
+
+fn instantiate(store: &mut Store, module: &Module) -> Instance {
+    let env = FunctionEnv::new(store, Env::default());
+    let imports = imports! {
+        "host" => {
+            "peek" => Function::new_typed_with_env(store, &env, peek),
+        },
+    };
+    let instance = Instance::new(store, module, &imports).unwrap();
+
+    let memory = instance.exports.get_memory("memory").unwrap();
+    env.as_mut(store).memory = Some(memory.downgrade(store));
+
+    instance
+}
+
+#[test]
+fn weak_handles_dont_outlive_the_store() {
+    let engine = Engine::default();
+    let module = Module::new(&engine, MODULE).unwrap();
+
+    // Get everything that is only set up once per process out of the way
+    drop(instantiate(&mut Store::new(engine.clone()), &module));
+
+    let before = live_bytes();
+
+    // Handles held somewhere that outlives the store
+    let mut registry = Vec::new();
+    let during = {
+        let mut store = Store::new(engine.clone());
+        let instance = instantiate(&mut store, &module);
+        let run = instance.exports.get_function("run").unwrap();
+        let memory = instance.exports.get_memory("memory").unwrap();
+
+        assert_eq!(
+            run.call(&mut store, &[]).unwrap().into_vec(),
+            [Value::I32(42)]
+        );
+
+        let weak_instance = instance.downgrade(&store);
+        let weak_function = run.downgrade(&store);
+        let weak_memory = memory.downgrade(&store);
+        assert!(weak_instance.upgrade(&store).is_some());
+        assert!(weak_function.upgrade(&store).is_some());
+        assert!(weak_memory.upgrade(&store).is_some());
+        registry.push((weak_instance, weak_function, weak_memory));
+
+        live_bytes()
+    };
+    let after = live_bytes();
+
+    assert!(
+        during - before > TABLE_BYTES,
+        "the instance used {} bytes",
+        during - before
+    );
+    assert!(
+        after - before < TABLE_BYTES / 10,
+        "{} bytes are still in use after dropping the store",
+        after - before
+    );
+
+    let other_store = Store::new(engine);
+    for (instance, function, memory) in &registry {
+        assert!(instance.upgrade(&other_store).is_none());
+        assert!(function.upgrade(&other_store).is_none());
+        assert!(memory.upgrade(&other_store).is_none());
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "a different store")]
+fn upgrading_with_the_wrong_store() {
+    let mut store = Store::default();
+    let memory = Memory::new(&mut store, MemoryType::new(1, None, false)).unwrap();
+    let weak = memory.downgrade(&store);
+
+    let other_store = Store::default();
+    weak.upgrade(&other_store);
+}
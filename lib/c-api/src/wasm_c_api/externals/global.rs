@@ -3,7 +3,7 @@ use super::super::types::wasm_globaltype_t;
 use super::super::value::wasm_val_t;
 use super::wasm_extern_t;
 use std::convert::TryInto;
-use wasmer_api::{Extern, Global, Value};
+use wasmer_api::{Extern, Global, RuntimeError, Value};
 
 #[allow(non_camel_case_types)]
 #[repr(C)]
@@ -63,14 +63,15 @@ pub unsafe extern "C" fn wasm_global_get(
         .extern_
         .global()
         .get(&mut global.extern_.store.store_mut());
-    *out = value.try_into().unwrap();
+    // `v128` values don't fit in a `wasm_val_t`, see `wasmer_global_get_v128`
+    *out = c_try!(value.try_into().map_err(RuntimeError::new); otherwise ());
 }
 
 /// Note: This function returns nothing by design but it can raise an
 /// error if setting a new value fails.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_global_set(global: &mut wasm_global_t, val: &wasm_val_t) {
-    let value: Value = val.try_into().unwrap();
+    let value: Value = c_try!(val.try_into().map_err(RuntimeError::new); otherwise ());
     c_try!(global
         .extern_
         .global()
@@ -4,6 +4,7 @@ mod memory;
 mod table;
 
 use super::store::StoreRef;
+use super::types::wasm_ref_t;
 // use super::types::{wasm_externkind_enum, wasm_externkind_t};
 pub use function::*;
 pub use global::*;
@@ -106,6 +107,27 @@ pub extern "C" fn wasm_table_as_extern(table: Option<&wasm_table_t>) -> Option<&
     Some(&table?.extern_)
 }
 
+#[no_mangle]
+pub extern "C" fn wasm_func_as_ref(func: Option<&wasm_func_t>) -> Option<&wasm_ref_t> {
+    // Both are a `wasm_extern_t` underneath
+    Some(unsafe { &*(func? as *const wasm_func_t as *const wasm_ref_t) })
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_func_as_ref_const(func: Option<&wasm_func_t>) -> Option<&wasm_ref_t> {
+    wasm_func_as_ref(func)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_ref_as_func(r#ref: Option<&wasm_ref_t>) -> Option<&wasm_func_t> {
+    wasm_func_t::try_from(&r#ref?.extern_)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_ref_as_func_const(r#ref: Option<&wasm_ref_t>) -> Option<&wasm_func_t> {
+    wasm_ref_as_func(r#ref)
+}
+
 #[no_mangle]
 pub extern "C" fn wasm_extern_as_func(r#extern: Option<&wasm_extern_t>) -> Option<&wasm_func_t> {
     wasm_func_t::try_from(r#extern?)
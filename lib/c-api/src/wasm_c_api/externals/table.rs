@@ -1,7 +1,8 @@
 use super::super::store::wasm_store_t;
 use super::super::types::{wasm_ref_t, wasm_table_size_t, wasm_tabletype_t};
 use super::wasm_extern_t;
-use wasmer_api::Extern;
+use crate::error::update_last_error;
+use wasmer_api::{Extern, Type, Value};
 
 #[allow(non_camel_case_types)]
 #[repr(C)]
@@ -50,13 +51,43 @@ pub unsafe extern "C" fn wasm_table_same(
     wasm_table1.extern_.table() == wasm_table2.extern_.table()
 }
 
+/// Grows the table by `delta` elements, set to `init`.
+///
+/// A `NULL` `init` stands for the null reference of the table's element
+/// type. Otherwise it must be a reference to a function (see
+/// `wasm_func_as_ref`) and the table must hold `funcref`s.
 #[no_mangle]
 pub unsafe extern "C" fn wasm_table_grow(
-    _table: &mut wasm_table_t,
-    _delta: wasm_table_size_t,
-    _init: *mut wasm_ref_t,
+    table: &mut wasm_table_t,
+    delta: wasm_table_size_t,
+    init: Option<&wasm_ref_t>,
 ) -> bool {
-    // TODO: maybe need to look at result to return `true`; also maybe report error here
-    //wasm_table.inner.grow(delta, init).is_ok()
-    todo!("Blocked on transforming ExternRef into a val type")
+    let wasm_table = table.extern_.table();
+    let mut store_mut = table.extern_.store.store_mut();
+    let init = match init {
+        None => null_ref(wasm_table.ty(&store_mut).ty),
+        Some(init) => match &init.extern_.inner {
+            Extern::Function(function) => Value::FuncRef(Some(function.clone())),
+            _ => {
+                update_last_error("`init` must be a reference to a function");
+                return false;
+            }
+        },
+    };
+
+    match wasm_table.grow(&mut store_mut, delta, init) {
+        Ok(_) => true,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+/// The null reference to use for a table with elements of type `ty`.
+pub(crate) fn null_ref(ty: Type) -> Value {
+    match ty {
+        Type::ExternRef => Value::ExternRef(None),
+        _ => Value::FuncRef(None),
+    }
 }
@@ -201,6 +201,13 @@ pub unsafe extern "C" fn wasm_instance_exports(
     let extern_vec: Vec<Option<Box<wasm_extern_t>>> = instance
         .exports
         .iter()
+        // Exception tags have no `wasm_externkind_t`, so they're left out
+        .filter(|(_name, r#extern)| {
+            matches!(
+                r#extern,
+                Extern::Function(_) | Extern::Global(_) | Extern::Table(_) | Extern::Memory(_)
+            )
+        })
         .map(|(_name, r#extern)| {
             Some(Box::new(wasm_extern_t::new(
                 original_instance.store.clone(),
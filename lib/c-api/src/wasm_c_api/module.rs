@@ -1,7 +1,7 @@
 use super::store::wasm_store_t;
 use super::types::{wasm_byte_vec_t, wasm_exporttype_vec_t, wasm_importtype_vec_t};
 use std::ptr::NonNull;
-use wasmer_api::Module;
+use wasmer_api::{ExternType, Module};
 
 /// Opaque type representing a WebAssembly module.
 #[derive(Clone)]
@@ -222,6 +222,8 @@ pub unsafe extern "C" fn wasm_module_exports(
     let exports = module
         .inner
         .exports()
+        // Exception tags have no `wasm_externkind_t`, so they're left out
+        .filter(|export| !matches!(export.ty(), ExternType::Tag(_)))
         .map(|export| Some(Box::new(export.into())))
         .collect();
 
@@ -362,6 +364,8 @@ pub unsafe extern "C" fn wasm_module_imports(
     let imports = module
         .inner
         .imports()
+        // Exception tags have no `wasm_externkind_t`, so they're left out
+        .filter(|import| !matches!(import.ty(), ExternType::Tag(_)))
         .map(|import| Some(Box::new(import.into())))
         .collect();
 
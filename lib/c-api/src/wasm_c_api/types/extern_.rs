@@ -32,6 +32,8 @@ impl From<&ExternType> for wasm_externkind_enum {
             ExternType::Global(_) => Self::WASM_EXTERN_GLOBAL,
             ExternType::Table(_) => Self::WASM_EXTERN_TABLE,
             ExternType::Memory(_) => Self::WASM_EXTERN_MEMORY,
+            // The C API never hands out tags (see `wasm_module_exports`)
+            ExternType::Tag(_) => unreachable!("exception tags aren't supported by the C API"),
         }
     }
 }
@@ -66,6 +68,10 @@ impl wasm_externtype_t {
                 ExternType::Memory(memory_type) => {
                     WasmExternType::Memory(WasmMemoryType::new(memory_type))
                 }
+                // The C API never hands out tags (see `wasm_module_exports`)
+                ExternType::Tag(_) => {
+                    unreachable!("exception tags aren't supported by the C API")
+                }
             },
         }
     }
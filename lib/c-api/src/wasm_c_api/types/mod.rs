@@ -9,6 +9,8 @@ mod mutability;
 mod table;
 mod value;
 
+use super::externals::wasm_extern_t;
+
 pub use export::*;
 pub use extern_::*;
 pub use frame::*;
@@ -34,9 +36,13 @@ impl From<String> for wasm_name_t {
     }
 }
 
-// opaque type over `ExternRef`?
+/// A reference to a WebAssembly object. Only functions can be
+/// referenced so far, with `wasm_func_as_ref`.
 #[allow(non_camel_case_types)]
-pub struct wasm_ref_t;
+#[repr(C)]
+pub struct wasm_ref_t {
+    pub(crate) extern_: wasm_extern_t,
+}
 
 #[allow(non_camel_case_types)]
 pub type wasm_message_t = wasm_byte_vec_t;
@@ -10,6 +10,8 @@ pub enum wasm_valkind_enum {
     WASM_I64 = 1,
     WASM_F32 = 2,
     WASM_F64 = 3,
+    /// Wasmer-specific, see [`WASMER_V128`][crate::wasm_c_api::unstable::global::WASMER_V128].
+    WASMER_V128 = 4,
     WASM_ANYREF = 128,
     WASM_FUNCREF = 129,
 }
@@ -21,7 +23,7 @@ impl From<Type> for wasm_valkind_enum {
             Type::I64 => Self::WASM_I64,
             Type::F32 => Self::WASM_F32,
             Type::F64 => Self::WASM_F64,
-            Type::V128 => todo!("no v128 type in Wasm C API yet!"),
+            Type::V128 => Self::WASMER_V128,
             Type::ExternRef => Self::WASM_ANYREF,
             Type::FuncRef => Self::WASM_FUNCREF,
         }
@@ -36,6 +38,7 @@ impl From<wasm_valkind_enum> for Type {
             WASM_I64 => Type::I64,
             WASM_F32 => Type::F32,
             WASM_F64 => Type::F64,
+            WASMER_V128 => Type::V128,
             WASM_ANYREF => Type::ExternRef,
             WASM_FUNCREF => Type::FuncRef,
         }
@@ -0,0 +1,206 @@
+//! Unstable non-standard Wasmer-specific extensions to work with
+//! `v128` globals from the host.
+//!
+//! A `v128` doesn't fit in the `wasm_val_t` union defined by the
+//! standard C API, so `wasm_global_get` and `wasm_global_set` can't
+//! be used with `v128` globals. These functions take a
+//! [`wasmer_v128_t`] instead.
+//!
+//! All functions return `false` (or `NULL`) on failure, in which case
+//! the error message can be retrieved with
+//! `wasmer_last_error_message`.
+
+use super::super::externals::{wasm_extern_t, wasm_global_t};
+use super::super::store::wasm_store_t;
+use super::super::types::wasm_globaltype_t;
+use super::super::value::wasm_valkind_t;
+use crate::error::update_last_error;
+use wasmer_api::{Global, RuntimeError, Type, Value, V128};
+
+/// The `wasm_valkind_t` of a `v128` value, as returned by
+/// `wasm_valtype_kind`.
+pub const WASMER_V128: wasm_valkind_t = 4;
+
+/// A 128-bit SIMD value.
+///
+/// The bytes are in the same order as guest memory, i.e. the order
+/// `v128.store` would write them in. Lanes are little-endian and lane
+/// 0 comes first, regardless of the host's endianness.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct wasmer_v128_t {
+    pub bytes: [u8; 16],
+}
+
+/// Unstable non-standard Wasmer-specific API to create a new `v128`
+/// global.
+///
+/// Returns `NULL` if `global_type` isn't a `v128` global type.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_global_new_v128(
+    store: Option<&mut wasm_store_t>,
+    global_type: Option<&wasm_globaltype_t>,
+    val: &wasmer_v128_t,
+) -> Option<Box<wasm_global_t>> {
+    let global_type = global_type?;
+    let store = store?;
+    let mut store_mut = store.inner.store_mut();
+
+    let global_type = &global_type.inner().global_type;
+    if global_type.ty != Type::V128 {
+        update_last_error(RuntimeError::new("The global type isn't a v128"));
+        return None;
+    }
+
+    let value = Value::from(V128::from(val.bytes));
+    let global = if global_type.mutability.is_mutable() {
+        Global::new_mut(&mut store_mut, value)
+    } else {
+        Global::new(&mut store_mut, value)
+    };
+    Some(Box::new(wasm_global_t {
+        extern_: wasm_extern_t::new(store.inner.clone(), global.into()),
+    }))
+}
+
+/// Unstable non-standard Wasmer-specific API to read the value of a
+/// `v128` global into `out`.
+///
+/// Returns `false` without modifying `out` if `global` isn't a
+/// `v128` global.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_global_get_v128(
+    global: &mut wasm_global_t,
+    out: &mut wasmer_v128_t,
+) -> bool {
+    let value = global
+        .extern_
+        .global()
+        .get(&mut global.extern_.store.store_mut());
+
+    match V128::try_from(value) {
+        Ok(value) => {
+            out.bytes = *value.bytes();
+            true
+        }
+        Err(e) => {
+            update_last_error(RuntimeError::new(e));
+            false
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to set the value of a
+/// mutable `v128` global.
+///
+/// Returns `false` without modifying the global if it is immutable or
+/// isn't a `v128` global.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_global_set_v128(
+    global: &mut wasm_global_t,
+    val: &wasmer_v128_t,
+) -> bool {
+    let value = Value::from(V128::from(val.bytes));
+
+    match global
+        .extern_
+        .global()
+        .set(&mut global.extern_.store.store_mut(), value)
+    {
+        Ok(()) => true,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(target_os = "windows"))]
+    use inline_c::assert_c;
+    #[cfg(target_os = "windows")]
+    use wasmer_inline_c::assert_c;
+
+    #[cfg_attr(coverage, ignore)]
+    #[test]
+    fn test_v128_globals() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (global $g (export \"g\") (mut v128) (v128.const i32x4 0 0 0 0))\n"
+                    "  (global (export \"i\") i32 (i32.const 0))\n"
+                    "  (func (export \"sum\") (result i32)\n"
+                    "    (local $v v128)\n"
+                    "    (local.set $v (i32x4.add (global.get $g) (v128.const i32x4 10 20 30 40)))\n"
+                    "    (i32.add\n"
+                    "      (i32.add (i32x4.extract_lane 0 (local.get $v)) (i32x4.extract_lane 1 (local.get $v)))\n"
+                    "      (i32.add (i32x4.extract_lane 2 (local.get $v)) (i32x4.extract_lane 3 (local.get $v))))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = NULL;
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                assert(exports.size == 3);
+
+                wasm_global_t* g = wasm_extern_as_global(exports.data[0]);
+                wasm_global_t* i = wasm_extern_as_global(exports.data[1]);
+                wasm_func_t* sum = wasm_extern_as_func(exports.data[2]);
+
+                wasm_globaltype_t* g_type = wasm_global_type(g);
+                assert(wasm_valtype_kind(wasm_globaltype_content(g_type)) == WASMER_V128);
+                wasm_globaltype_delete(g_type);
+
+                // Lanes are little-endian: i32x4 1 2 3 4
+                wasmer_v128_t value = {{ 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0 }};
+                assert(wasmer_global_set_v128(g, &value));
+
+                wasm_val_t results_val[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t args = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+                trap = wasm_func_call(sum, &args, &results);
+                assert(!trap);
+                assert(results_val[0].of.i32 == 110);
+
+                wasmer_v128_t read = {{ 0 }};
+                assert(wasmer_global_get_v128(g, &read));
+                assert(memcmp(read.bytes, value.bytes, 16) == 0);
+
+                // Only v128 globals can be used.
+                assert(!wasmer_global_get_v128(i, &read));
+                assert(!wasmer_global_set_v128(i, &value));
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+}
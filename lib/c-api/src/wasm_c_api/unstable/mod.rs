@@ -1,10 +1,12 @@
 pub mod engine;
 pub mod features;
+pub mod global;
 #[cfg(feature = "middlewares")]
 pub mod middlewares;
 pub mod module;
 #[cfg(feature = "compiler")]
 pub mod parser;
+pub mod table;
 pub mod target_lexicon;
 #[cfg(feature = "wasi")]
 pub mod wasi;
@@ -0,0 +1,232 @@
+//! Unstable non-standard Wasmer-specific extensions to manipulate
+//! tables from the host.
+//!
+//! The standard C API only supports reading, writing and growing
+//! tables one element at a time. These functions add bulk `fill` and
+//! `copy` operations with the same semantics as the `table.fill` and
+//! `table.copy` WebAssembly instructions.
+//!
+//! All functions return `false` on failure (out of bounds access,
+//! mismatched element types, …), in which case the error message can
+//! be retrieved with `wasmer_last_error_message`.
+
+use super::super::externals::{null_ref, wasm_table_t};
+use super::super::types::wasm_table_size_t;
+use crate::error::update_last_error;
+use wasmer_api::Table;
+
+/// Unstable non-standard Wasmer-specific API to set the `len`
+/// elements of `table` starting at `start` to the null reference.
+///
+/// Returns `false` without modifying the table if the range is out
+/// of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_table_fill(
+    table: &mut wasm_table_t,
+    start: wasm_table_size_t,
+    len: wasm_table_size_t,
+) -> bool {
+    let wasm_table = table.extern_.table();
+    let mut store_mut = table.extern_.store.store_mut();
+    let val = null_ref(wasm_table.ty(&store_mut).ty);
+
+    match wasm_table.fill(&mut store_mut, start, val, len) {
+        Ok(()) => true,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to copy the `len`
+/// elements of `src` starting at `src_index` into `dst` at
+/// `dst_index`.
+///
+/// Both tables must belong to the same store and have the same
+/// element type. `src` and `dst` may be the same table, in which
+/// case overlapping ranges are handled correctly.
+///
+/// Returns `false` without modifying `dst` if either range is out of
+/// bounds.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_table_copy_elements(
+    dst: &wasm_table_t,
+    dst_index: wasm_table_size_t,
+    src: &wasm_table_t,
+    src_index: wasm_table_size_t,
+    len: wasm_table_size_t,
+) -> bool {
+    let dst_table = dst.extern_.table();
+    let src_table = src.extern_.table();
+    // `src` and `dst` may alias, so we need our own handle to the store
+    let mut store = dst.extern_.store.clone();
+    let mut store_mut = store.store_mut();
+
+    match Table::copy(
+        &mut store_mut,
+        &dst_table,
+        dst_index,
+        &src_table,
+        src_index,
+        len,
+    ) {
+        Ok(()) => true,
+        Err(e) => {
+            update_last_error(e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(target_os = "windows"))]
+    use inline_c::assert_c;
+    #[cfg(target_os = "windows")]
+    use wasmer_inline_c::assert_c;
+
+    #[cfg_attr(coverage, ignore)]
+    #[test]
+    fn test_table_grow_fill_and_copy() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (table (export \"small\") 2 10 funcref)\n"
+                    "  (table (export \"large\") 4 funcref)\n"
+                    "  (table (export \"externs\") 4 externref))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = NULL;
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                assert(exports.size == 3);
+
+                wasm_table_t* small = wasm_extern_as_table(exports.data[0]);
+                wasm_table_t* large = wasm_extern_as_table(exports.data[1]);
+                wasm_table_t* externs = wasm_extern_as_table(exports.data[2]);
+
+                // Growing up to the maximum works, but not past it.
+                assert(wasm_table_grow(small, 8, NULL));
+                assert(wasm_table_size(small) == 10);
+                assert(!wasm_table_grow(small, 1, NULL));
+                assert(wasm_table_size(small) == 10);
+
+                // Filling is bounds checked.
+                assert(wasmer_table_fill(small, 0, 10));
+                assert(!wasmer_table_fill(small, 5, 10));
+
+                // Copying between tables is bounds checked.
+                assert(wasmer_table_copy_elements(small, 6, large, 0, 4));
+                assert(!wasmer_table_copy_elements(large, 1, small, 0, 4));
+                assert(wasmer_table_copy_elements(large, 1, large, 0, 3));
+
+                // Tables with different element types can't be mixed.
+                assert(!wasmer_table_copy_elements(externs, 0, large, 0, 1));
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+
+    #[test]
+    fn test_table_grow_with_a_function() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (type $t (func (result i32)))\n"
+                    "  (table (export \"funcs\") 1 funcref)\n"
+                    "  (table (export \"externs\") 1 externref)\n"
+                    "  (func (export \"answer\") (type $t) (i32.const 42))\n"
+                    "  (func (export \"call\") (param i32) (result i32)\n"
+                    "    (call_indirect (type $t) (local.get 0))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+                wasm_trap_t* trap = NULL;
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, &trap);
+                assert(instance);
+
+                wasm_extern_vec_t exports;
+                wasm_instance_exports(instance, &exports);
+                assert(exports.size == 4);
+
+                wasm_table_t* funcs = wasm_extern_as_table(exports.data[0]);
+                wasm_table_t* externs = wasm_extern_as_table(exports.data[1]);
+                wasm_func_t* answer = wasm_extern_as_func(exports.data[2]);
+                wasm_func_t* call = wasm_extern_as_func(exports.data[3]);
+
+                // The new elements can be called indirectly.
+                assert(wasm_table_grow(funcs, 2, wasm_func_as_ref(answer)));
+                assert(wasm_table_size(funcs) == 3);
+
+                wasm_val_t arguments[1] = { WASM_I32_VAL(2) };
+                wasm_val_t results[1] = { WASM_INIT_VAL };
+                wasm_val_vec_t arguments_as_array = WASM_ARRAY_VEC(arguments);
+                wasm_val_vec_t results_as_array = WASM_ARRAY_VEC(results);
+
+                trap = wasm_func_call(call, &arguments_as_array, &results_as_array);
+                assert(trap == NULL);
+                assert(results[0].of.i32 == 42);
+
+                // A function can't be stored in a table of `externref`s.
+                assert(!wasm_table_grow(externs, 1, wasm_func_as_ref(answer)));
+                assert(wasm_table_size(externs) == 1);
+                assert(wasmer_last_error_length() > 0);
+
+                wasm_extern_vec_delete(&exports);
+                wasm_instance_delete(instance);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+}
@@ -94,6 +94,9 @@ impl std::fmt::Debug for wasm_val_t {
             Ok(wasm_valkind_enum::WASM_F64) => {
                 ds.field("f64", &unsafe { self.of.float64_t });
             }
+            Ok(wasm_valkind_enum::WASMER_V128) => {
+                ds.field("v128", &"<not representable>");
+            }
             Ok(wasm_valkind_enum::WASM_ANYREF) => {
                 ds.field("anyref", &unsafe { self.of.wref });
             }
@@ -150,6 +153,7 @@ pub unsafe extern "C" fn wasm_val_copy(
             wasm_valkind_enum::WASM_F64 => wasm_val_inner {
                 float64_t: val.of.float64_t,
             },
+            wasm_valkind_enum::WASMER_V128 => val.of,
             wasm_valkind_enum::WASM_ANYREF => wasm_val_inner { wref: val.of.wref },
             wasm_valkind_enum::WASM_FUNCREF => wasm_val_inner { wref: val.of.wref },
         }
@@ -173,6 +177,7 @@ impl TryFrom<wasm_valkind_t> for wasm_valkind_enum {
             1 => wasm_valkind_enum::WASM_I64,
             2 => wasm_valkind_enum::WASM_F32,
             3 => wasm_valkind_enum::WASM_F64,
+            4 => wasm_valkind_enum::WASMER_V128,
             128 => wasm_valkind_enum::WASM_ANYREF,
             129 => wasm_valkind_enum::WASM_FUNCREF,
             _ => return Err("valkind value out of bounds"),
@@ -197,6 +202,9 @@ impl TryFrom<&wasm_val_t> for Value {
             wasm_valkind_enum::WASM_I64 => Value::I64(unsafe { item.of.int64_t }),
             wasm_valkind_enum::WASM_F32 => Value::F32(unsafe { item.of.float32_t }),
             wasm_valkind_enum::WASM_F64 => Value::F64(unsafe { item.of.float64_t }),
+            wasm_valkind_enum::WASMER_V128 => {
+                return Err("v128 values don't fit in a wasm_val_t, use wasmer_global_get_v128()")
+            }
             wasm_valkind_enum::WASM_ANYREF => return Err("ANYREF not supported at this time"),
             wasm_valkind_enum::WASM_FUNCREF => return Err("FUNCREF not supported at this time"),
         })
@@ -232,7 +240,9 @@ impl TryFrom<&Value> for wasm_val_t {
                 of: wasm_val_inner { float64_t: v },
                 kind: wasm_valkind_enum::WASM_F64 as _,
             },
-            Value::V128(_) => return Err("128bit SIMD types not yet supported in Wasm C API"),
+            Value::V128(_) => {
+                return Err("v128 values don't fit in a wasm_val_t, use wasmer_global_get_v128()")
+            }
             _ => todo!("Handle these values in TryFrom<Value> for wasm_val_t"),
         })
     }
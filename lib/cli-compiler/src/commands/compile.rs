@@ -4,12 +4,8 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use std::fs;
 use std::path::{Path, PathBuf};
-use wasmer_compiler::{ArtifactBuild, ArtifactCreate, ModuleEnvironment};
-use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{
-    Architecture, CompileError, CpuFeature, MemoryIndex, MemoryStyle, TableIndex, TableStyle,
-    Target, Triple,
-};
+use wasmer_compiler::{ArtifactBuild, ArtifactCreate};
+use wasmer_types::{Architecture, CpuFeature, Target, Triple};
 
 #[derive(Debug, Parser)]
 /// The options for the `wasmer compile` subcommand
@@ -86,25 +82,12 @@ impl Compile {
         // compile and save the artifact (without using module from api)
         let path: &Path = self.path.as_ref();
         let wasm_bytes = std::fs::read(path)?;
-        let environ = ModuleEnvironment::new();
-        let translation = environ.translate(&wasm_bytes).map_err(CompileError::Wasm)?;
-        let module = translation.module;
-        let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
-            .memories
-            .values()
-            .map(|memory_type| tunables.memory_style(memory_type))
-            .collect();
-        let table_styles: PrimaryMap<TableIndex, TableStyle> = module
-            .tables
-            .values()
-            .map(|table_type| tunables.table_style(table_type))
-            .collect();
         let artifact = ArtifactBuild::new(
             &mut engine.inner_mut(),
             &wasm_bytes,
             &target,
-            memory_styles,
-            table_styles,
+            &|memory_type| tunables.memory_style(memory_type),
+            &|table_type| tunables.table_style(table_type),
         )?;
         let serialized = artifact.serialize()?;
         fs::write(output_filename, serialized)?;
@@ -64,8 +64,9 @@ impl SubsetTunables {
         // tunables make it static.
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
+        // 64-bit memories can always exceed that, so they are dynamic.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if maximum <= self.static_memory_bound && !memory.memory64 {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
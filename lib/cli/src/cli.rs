@@ -9,16 +9,25 @@ use crate::commands::CreateExe;
 #[cfg(feature = "wast")]
 use crate::commands::Wast;
 use crate::commands::{
-    Add, Cache, Config, Init, Inspect, Login, Publish, Run, SelfUpdate, Validate, Whoami,
+    Add, Cache, Config, Init, Inspect, Keygen, Login, Package, Publish, Run, SelfUpdate, Sign,
+    Validate, Whoami,
 };
 #[cfg(feature = "static-artifact-create")]
 use crate::commands::{CreateObj, GenCHeader};
 use crate::error::PrettyError;
 use clap::{CommandFactory, Parser};
+use once_cell::sync::Lazy;
+use std::time::Instant;
 use wasmer_deploy_cli::cmd::CliCommand;
 
+/// Roughly when the process started, for timings like
+/// `wasmer run --exit-on-first-write`.
+pub(crate) static PROCESS_START: Lazy<Instant> = Lazy::new(Instant::now);
+
 /// The main function for the Wasmer CLI tool.
 pub fn wasmer_main() {
+    Lazy::force(&PROCESS_START);
+
     // We allow windows to print properly colors
     #[cfg(windows)]
     colored::control::set_virtual_terminal(true).unwrap();
@@ -31,7 +40,9 @@ fn wasmer_main_inner() -> Result<(), anyhow::Error> {
         Run::from_binfmt_args().execute(crate::logging::Output::default());
     }
 
-    match Args::try_parse() {
+    let args = Run::args_with_config(std::env::args_os().collect())?;
+
+    match Args::try_parse_from(&args) {
         Ok(args) => args.execute(),
         Err(e) => {
             let might_be_wasmer_run = matches!(
@@ -40,13 +51,18 @@ fn wasmer_main_inner() -> Result<(), anyhow::Error> {
             );
 
             if might_be_wasmer_run {
-                if let Ok(run) = Run::try_parse() {
+                if let Ok(run) = Run::try_parse_from(&args) {
                     // Try to parse the command using the `wasmer some/package`
                     // shorthand. Note that this has discoverability issues
                     // because it's not shown as part of the main argument
                     // parser's help, but that's fine.
                     let output = crate::logging::Output::default();
-                    output.initialize_logging();
+                    let logging = run
+                        .tracing_layer()
+                        .and_then(|layer| output.initialize_logging_with(layer, run.log_options()));
+                    if let Err(e) = logging {
+                        PrettyError::report::<()>(Err(e));
+                    }
                     run.execute(output);
                 }
             }
@@ -86,7 +102,12 @@ impl Args {
             output,
         } = self;
 
-        output.initialize_logging();
+        match &cmd {
+            Some(Cmd::Run(run)) => {
+                output.initialize_logging_with(run.tracing_layer()?, run.log_options())?
+            }
+            _ => output.initialize_logging(),
+        }
 
         if version {
             return print_version(output.is_verbose());
@@ -97,6 +118,8 @@ impl Args {
             Some(Cmd::SelfUpdate(options)) => options.execute(),
             Some(Cmd::Cache(cache)) => cache.execute(),
             Some(Cmd::Validate(validate)) => validate.execute(),
+            Some(Cmd::Sign(sign)) => sign.execute(),
+            Some(Cmd::Keygen(keygen)) => keygen.execute(),
             #[cfg(feature = "compiler")]
             Some(Cmd::Compile(compile)) => compile.execute(),
             #[cfg(any(feature = "static-artifact-create", feature = "wasmer-artifact-create"))]
@@ -116,6 +139,7 @@ impl Args {
             Some(Cmd::Binfmt(binfmt)) => binfmt.execute(),
             Some(Cmd::Whoami(whoami)) => whoami.execute(),
             Some(Cmd::Add(install)) => install.execute(),
+            Some(Cmd::Package(package)) => package.execute(),
 
             // Deploy commands.
             Some(Cmd::Deploy(c)) => c.run(),
@@ -148,6 +172,13 @@ enum Cmd {
     /// Validate a WebAssembly binary
     Validate(Validate),
 
+    /// Sign a WebAssembly binary with an Ed25519 key, for use with
+    /// `wasmer run --check-signatures`
+    Sign(Sign),
+
+    /// Generate an Ed25519 key pair for signing WebAssembly binaries
+    Keygen(Keygen),
+
     /// Compile a WebAssembly binary
     #[cfg(feature = "compiler")]
     Compile(Compile),
@@ -252,9 +283,13 @@ enum Cmd {
     /// Add a Wasmer package's bindings to your application.
     Add(Add),
 
+    /// Work with Wasmer packages
+    #[clap(subcommand)]
+    Package(Package),
+
     /// Run a WebAssembly file or Wasmer container.
     #[clap(alias = "run-unstable")]
-    Run(Run),
+    Run(Box<Run>),
 
     // DEPLOY commands
     /// Deploy apps to the Wasmer Edge.
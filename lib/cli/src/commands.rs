@@ -14,10 +14,13 @@ mod create_obj;
 mod gen_c_header;
 mod init;
 mod inspect;
+mod keygen;
 mod login;
+mod package;
 mod publish;
 mod run;
 mod self_update;
+mod sign;
 mod validate;
 #[cfg(feature = "wast")]
 mod wast;
@@ -32,8 +35,8 @@ pub use create_exe::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
 pub use {
-    add::*, cache::*, config::*, init::*, inspect::*, login::*, publish::*, run::Run,
-    self_update::*, validate::*, whoami::*,
+    add::*, cache::*, config::*, init::*, inspect::*, keygen::*, login::*, package::Package,
+    publish::*, run::Run, self_update::*, sign::*, validate::*, whoami::*,
 };
 #[cfg(feature = "static-artifact-create")]
 pub use {create_obj::*, gen_c_header::*};
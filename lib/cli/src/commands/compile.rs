@@ -1,8 +1,10 @@
 use crate::store::StoreOptions;
 use crate::warning;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
 use wasmer::*;
 
 #[derive(Debug, Parser)]
@@ -23,8 +25,62 @@ pub struct Compile {
     #[clap(flatten)]
     store: StoreOptions,
 
-    #[clap(short = 'm')]
-    cpu_features: Vec<CpuFeature>,
+    /// CPU features of the target to enable or disable, as a list such as
+    /// `+neon,+lse,-sve`. Can be given several times.
+    #[clap(long, short = 'm', number_of_values = 1, allow_hyphen_values = true)]
+    cpu_features: Vec<String>,
+
+    /// Optimize the module with binaryen's `wasm-opt` at this level (0-4, s
+    /// or z) before compiling it.
+    ///
+    /// This is independent of the optimizations done by the native compiler.
+    #[clap(long, value_name = "LEVEL")]
+    wasm_opt_level: Option<WasmOptLevel>,
+}
+
+/// An optimization level understood by `wasm-opt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WasmOptLevel {
+    O0,
+    O1,
+    O2,
+    O3,
+    O4,
+    Os,
+    Oz,
+}
+
+impl WasmOptLevel {
+    fn flag(self) -> &'static str {
+        match self {
+            WasmOptLevel::O0 => "-O0",
+            WasmOptLevel::O1 => "-O1",
+            WasmOptLevel::O2 => "-O2",
+            WasmOptLevel::O3 => "-O3",
+            WasmOptLevel::O4 => "-O4",
+            WasmOptLevel::Os => "-Os",
+            WasmOptLevel::Oz => "-Oz",
+        }
+    }
+}
+
+impl FromStr for WasmOptLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(WasmOptLevel::O0),
+            "1" => Ok(WasmOptLevel::O1),
+            "2" => Ok(WasmOptLevel::O2),
+            "3" => Ok(WasmOptLevel::O3),
+            "4" => Ok(WasmOptLevel::O4),
+            "s" => Ok(WasmOptLevel::Os),
+            "z" => Ok(WasmOptLevel::Oz),
+            _ => bail!(
+                "Expected an optimization level between 0 and 4, \"s\" or \"z\", but found \"{s}\""
+            ),
+        }
+    }
 }
 
 impl Compile {
@@ -35,23 +91,28 @@ impl Compile {
     }
 
     fn inner_execute(&self) -> Result<()> {
-        let target = self
-            .target_triple
-            .as_ref()
-            .map(|target_triple| {
-                let mut features = self
-                    .cpu_features
-                    .clone()
-                    .into_iter()
-                    .fold(CpuFeature::set(), |a, b| a | b);
+        let target = match &self.target_triple {
+            None if self.cpu_features.is_empty() => Target::default(),
+            target_triple => {
+                let target_triple = target_triple.clone().unwrap_or_else(Triple::host);
+                let mut features = CpuFeature::set();
                 // Cranelift requires SSE2, so we have this "hack" for now to facilitate
                 // usage
                 if target_triple.architecture == Architecture::X86_64 {
                     features |= CpuFeature::SSE2;
                 }
-                Target::new(target_triple.clone(), features)
-            })
-            .unwrap_or_default();
+                let mut target = Target::new(target_triple, features);
+                for list in &self.cpu_features {
+                    target = target.with_cpu_features_str(list)?;
+                }
+                if target.triple().architecture == Architecture::X86_64
+                    && !target.cpu_features().contains(CpuFeature::SSE2)
+                {
+                    bail!("sse2 can't be disabled on {}", target.triple().architecture);
+                }
+                target
+            }
+        };
         let (store, compiler_type) = self.store.get_store_for_target(target.clone())?;
         let output_filename = self
             .output
@@ -72,8 +133,19 @@ impl Compile {
         }
         println!("Compiler: {}", compiler_type.to_string());
         println!("Target: {}", target.triple());
+        if self.store.aot_text_section_size().is_some() {
+            warning!("the artifact isn't linked, so --aot-text-section-size only applies to the objects of `wasmer create-obj` and `wasmer create-exe`");
+        }
 
-        let module = Module::from_file(&store, &self.path)?;
+        let module = match self.wasm_opt_level {
+            Some(level) => {
+                println!("Wasm optimization: {}", level.flag());
+                let features = self.store.get_features_for_target(&target)?;
+                let optimized = run_wasm_opt(&self.path, level, &features)?;
+                Module::new(&store, optimized)?
+            }
+            None => Module::from_file(&store, &self.path)?,
+        };
         module.serialize_to_file(&self.output)?;
         eprintln!(
             "✔ File compiled successfully to `{}`.",
@@ -83,3 +155,69 @@ impl Compile {
         Ok(())
     }
 }
+
+/// Run `wasm-opt` on the module at `path`, returning the optimized binary.
+///
+/// Only the proposals enabled in `features` are enabled in `wasm-opt`, so it
+/// can't introduce instructions the compiler would reject.
+fn run_wasm_opt(path: &Path, level: WasmOptLevel, features: &Features) -> Result<Vec<u8>> {
+    let temp = tempfile::tempdir()?;
+    let output = temp.path().join("optimized.wasm");
+
+    let mut cmd = Command::new("wasm-opt");
+    cmd.arg(path)
+        .arg(level.flag())
+        .arg("-o")
+        .arg(&output)
+        // These are always enabled in wasmer
+        .args([
+            "--enable-sign-ext",
+            "--enable-mutable-globals",
+            "--enable-nontrapping-float-to-int",
+        ]);
+
+    let proposals = [
+        (features.bulk_memory, "--enable-bulk-memory"),
+        (features.threads, "--enable-threads"),
+        (features.reference_types, "--enable-reference-types"),
+        (features.multi_value, "--enable-multivalue"),
+        (features.simd, "--enable-simd"),
+        (features.tail_call, "--enable-tail-call"),
+        (features.multi_memory, "--enable-multimemory"),
+        (features.memory64, "--enable-memory64"),
+        (features.exceptions, "--enable-exception-handling"),
+        (features.extended_const, "--enable-extended-const"),
+        (features.relaxed_simd, "--enable-relaxed-simd"),
+    ];
+    for (enabled, flag) in proposals {
+        if enabled {
+            cmd.arg(flag);
+        }
+    }
+
+    let status = match cmd.status() {
+        Ok(status) => status,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            bail!("unable to find `wasm-opt`. Install binaryen (https://github.com/WebAssembly/binaryen) or drop --wasm-opt-level")
+        }
+        Err(e) => return Err(e).context("unable to run `wasm-opt`"),
+    };
+    if !status.success() {
+        bail!("`wasm-opt` failed ({status})");
+    }
+
+    std::fs::read(&output).context("unable to read the output of `wasm-opt`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wasm_opt_levels() {
+        assert_eq!("2".parse::<WasmOptLevel>().unwrap().flag(), "-O2");
+        assert_eq!("z".parse::<WasmOptLevel>().unwrap().flag(), "-Oz");
+        assert!("5".parse::<WasmOptLevel>().is_err());
+        assert!("-O2".parse::<WasmOptLevel>().is_err());
+    }
+}
@@ -14,7 +14,7 @@ use std::process::Command;
 use std::process::Stdio;
 use tar::Archive;
 use wasmer::*;
-use wasmer_object::{emit_serialized, get_object_for_target};
+use wasmer_object::{emit_serialized, get_object_for_target, PIC_MARKER_SYMBOL};
 use wasmer_types::{compilation::symbols::ModuleMetadataSymbolRegistry, ModuleInfo};
 use webc::{
     compat::{Container, Volume as WebcVolume},
@@ -76,8 +76,10 @@ pub struct CreateExe {
     #[clap(long, name = "URL_OR_RELEASE_VERSION")]
     use_wasmer_release: Option<String>,
 
-    #[clap(long, short = 'm', number_of_values = 1)]
-    cpu_features: Vec<CpuFeature>,
+    /// CPU features of the target to enable or disable, as a list such as
+    /// `+neon,+lse,-sve`. Can be given several times.
+    #[clap(long, short = 'm', number_of_values = 1, allow_hyphen_values = true)]
+    cpu_features: Vec<String>,
 
     /// Additional libraries to link against.
     /// This is useful for fixing linker errors that may occur on some systems.
@@ -172,6 +174,10 @@ pub struct CommandEntrypoint {
     pub header: Option<PathBuf>,
     /// Module info, set when the wasm file is compiled
     pub module_info: Option<ModuleInfo>,
+    /// Whether the object file was compiled to position-independent code,
+    /// set when the header file is generated
+    #[serde(default)]
+    pub pic: bool,
 }
 
 /// Volume object file (name + path to object file)
@@ -189,7 +195,7 @@ impl CreateExe {
         let path = normalize_path(&format!("{}", self.path.display()));
         let target_triple = self.target_triple.clone().unwrap_or_else(Triple::host);
         let mut cc = self.cross_compile.clone();
-        let target = utils::target_triple_to_target(&target_triple, &self.cpu_features);
+        let target = utils::target_triple_to_target(&target_triple, &self.cpu_features)?;
 
         let starting_cd = env::current_dir()?;
         let input_path = starting_cd.join(path);
@@ -339,7 +345,7 @@ pub(super) fn compile_pirita_into_directory(
     pirita: &Container,
     target_dir: &Path,
     compiler: &CompilerOptions,
-    cpu_features: &[CpuFeature],
+    cpu_features: &[String],
     triple: &Triple,
     prefixes: &[String],
     allow_multi_wasm: AllowMultiWasm,
@@ -363,7 +369,7 @@ pub(super) fn compile_pirita_into_directory(
         .map_err(|e| anyhow::anyhow!("cannot create / dir in {}: {e}", target_dir.display()))?;
 
     let target_dir = target_dir.canonicalize()?;
-    let target = &utils::target_triple_to_target(triple, cpu_features);
+    let target = &utils::target_triple_to_target(triple, cpu_features)?;
 
     std::fs::create_dir_all(target_dir.join("volumes")).map_err(|e| {
         anyhow::anyhow!(
@@ -444,6 +450,7 @@ pub(super) fn compile_pirita_into_directory(
             path: atom_path,
             header: header_path,
             module_info: module_infos.get(&atom_name).cloned(),
+            pic: false,
         });
     }
 
@@ -923,12 +930,12 @@ pub(super) fn prepare_directory_from_single_wasm_file(
     target_dir: &Path,
     compiler: &CompilerOptions,
     triple: &Triple,
-    cpu_features: &[CpuFeature],
+    cpu_features: &[String],
     prefix: &[String],
     debug: bool,
 ) -> anyhow::Result<Vec<(String, Vec<u8>)>, anyhow::Error> {
     let bytes = std::fs::read(wasm_file)?;
-    let target = &utils::target_triple_to_target(triple, cpu_features);
+    let target = &utils::target_triple_to_target(triple, cpu_features)?;
 
     std::fs::create_dir_all(target_dir)
         .map_err(|e| anyhow::anyhow!("cannot create / dir in {}: {e}", target_dir.display()))?;
@@ -978,6 +985,7 @@ pub(super) fn prepare_directory_from_single_wasm_file(
             path: atom_path,
             header: None,
             module_info: module_infos.get(atom_name).cloned(),
+            pic: false,
         });
     }
 
@@ -1028,7 +1036,7 @@ pub(crate) fn create_header_files_in_dir(
     atoms: &[(String, Vec<u8>)],
     prefixes: &[String],
 ) -> anyhow::Result<()> {
-    use object::{Object, ObjectSection};
+    use object::{Object, ObjectSection, ObjectSymbol};
 
     std::fs::create_dir_all(directory.join("include")).map_err(|e| {
         anyhow::anyhow!("cannot create /include dir in {}: {e}", directory.display())
@@ -1063,6 +1071,12 @@ pub(crate) fn create_header_files_in_dir(
                 )
             })?;
         let metadata_length = section.len();
+        // Mach-O prefixes every symbol with an underscore
+        atom.pic = obj_file.symbols().any(|symbol| {
+            symbol.name().map_or(false, |name| {
+                name.trim_start_matches('_') == PIC_MARKER_SYMBOL
+            })
+        });
 
         let module_info = atom
             .module_info
@@ -1127,6 +1141,10 @@ fn link_exe_from_dir(
 
     let library_path = &cross_compilation.library;
 
+    // Position-independent atoms can go in a PIE executable, as long as
+    // they all are
+    let pic = !entrypoint.atoms.is_empty() && entrypoint.atoms.iter().all(|a| a.pic);
+
     let mut object_paths = entrypoint
         .atoms
         .iter()
@@ -1208,6 +1226,7 @@ fn link_exe_from_dir(
             &cross_compilation.target,
             additional_libraries,
             &output_path,
+            pic,
             debug,
         );
     }
@@ -1265,6 +1284,10 @@ fn link_exe_from_dir(
     cmd.arg("-OReleaseSafe");
     cmd.arg("-fno-compiler-rt");
     cmd.arg("-fno-lto");
+    if pic {
+        cmd.arg("-fPIC");
+        cmd.arg("-fPIE");
+    }
     #[cfg(target_os = "windows")]
     let out_path = directory.join("wasmer_main.exe");
     #[cfg(not(target_os = "windows"))]
@@ -1365,6 +1388,7 @@ fn link_objects_system_linker(
     target: &Triple,
     additional_libraries: &[String],
     output_path: &Path,
+    pic: bool,
     debug: bool,
 ) -> Result<(), anyhow::Error> {
     let libwasmer_path = libwasmer_path
@@ -1382,6 +1406,13 @@ fn link_objects_system_linker(
         command = command.arg(format!("{}", target));
     }
 
+    if pic && target.operating_system != OperatingSystem::Windows {
+        command = command.arg("-fPIC");
+        if target.binary_format == target_lexicon::BinaryFormat::Elf {
+            command = command.arg("-pie");
+        }
+    }
+
     for include_dir in include_dirs {
         command = command.arg("-I");
         command = command.arg(normalize_path(&format!("{}", include_dir.display())));
@@ -1566,17 +1597,28 @@ pub(super) mod utils {
     use target_lexicon::{Architecture, Environment, OperatingSystem, Triple};
     use wasmer_types::{CpuFeature, Target};
 
+    /// The target for `target_triple`, with the features given by the
+    /// `--cpu-features` lists, such as `+neon,+lse,-sve`.
     pub(in crate::commands) fn target_triple_to_target(
         target_triple: &Triple,
-        cpu_features: &[CpuFeature],
-    ) -> Target {
-        let mut features = cpu_features.iter().fold(CpuFeature::set(), |a, b| a | *b);
+        cpu_features: &[String],
+    ) -> Result<Target, anyhow::Error> {
+        let mut features = CpuFeature::set();
         // Cranelift requires SSE2, so we have this "hack" for now to facilitate
         // usage
         if target_triple.architecture == Architecture::X86_64 {
             features |= CpuFeature::SSE2;
         }
-        Target::new(target_triple.clone(), features)
+        let mut target = Target::new(target_triple.clone(), features);
+        for list in cpu_features {
+            target = target.with_cpu_features_str(list)?;
+        }
+        if target_triple.architecture == Architecture::X86_64
+            && !target.cpu_features().contains(CpuFeature::SSE2)
+        {
+            anyhow::bail!("sse2 can't be disabled on {}", target_triple.architecture);
+        }
+        Ok(target)
     }
 
     pub(in crate::commands) fn get_cross_compile_setup(
@@ -7,6 +7,7 @@ use clap::Parser;
 use std::env;
 
 use std::path::PathBuf;
+use target_lexicon::Environment;
 
 use wasmer::*;
 
@@ -51,8 +52,18 @@ pub struct CreateObj {
     #[clap(long = "target")]
     target_triple: Option<Triple>,
 
-    #[clap(long, short = 'm', number_of_values = 1)]
-    cpu_features: Vec<CpuFeature>,
+    /// CPU features of the target to enable or disable, as a list such as
+    /// `+neon,+lse,-sve`. Can be given several times.
+    #[clap(long, short = 'm', number_of_values = 1, allow_hyphen_values = true)]
+    cpu_features: Vec<String>,
+
+    /// Emit position-independent code, so the object can be linked into
+    /// a shared library or a PIE executable.
+    ///
+    /// It's always emitted for targets which don't support anything else,
+    /// like Android.
+    #[clap(long)]
+    pic: bool,
 
     #[clap(flatten)]
     compiler: CompilerOptions,
@@ -79,8 +90,13 @@ impl CreateObj {
         let target = crate::commands::create_exe::utils::target_triple_to_target(
             &target_triple,
             &self.cpu_features,
-        );
-        let (_, compiler_type) = self.compiler.get_store_for_target(target.clone())?;
+        )?;
+        let compiler = if self.pic || requires_pic(&target_triple) {
+            self.compiler.with_pic()
+        } else {
+            self.compiler.clone()
+        };
+        let (_, compiler_type) = compiler.get_store_for_target(target.clone())?;
         println!("Compiler: {}", compiler_type.to_string());
         println!("Target: {}", target.triple());
 
@@ -88,7 +104,7 @@ impl CreateObj {
             crate::commands::create_exe::compile_pirita_into_directory(
                 &webc,
                 &output_directory_path,
-                &self.compiler,
+                &compiler,
                 &self.cpu_features,
                 &target_triple,
                 &prefix,
@@ -99,7 +115,7 @@ impl CreateObj {
             crate::commands::create_exe::prepare_directory_from_single_wasm_file(
                 &input_path,
                 &output_directory_path,
-                &self.compiler,
+                &compiler,
                 &target_triple,
                 &self.cpu_features,
                 &prefix,
@@ -169,3 +185,11 @@ impl CreateObj {
         Ok(())
     }
 }
+
+/// Whether binaries for the target have to be position-independent.
+fn requires_pic(triple: &Triple) -> bool {
+    matches!(
+        triple.environment,
+        Environment::Android | Environment::Androideabi
+    )
+}
@@ -5,7 +5,7 @@ use clap::Parser;
 use std::path::PathBuf;
 use wasmer_compiler::Artifact;
 use wasmer_types::compilation::symbols::ModuleMetadataSymbolRegistry;
-use wasmer_types::{CpuFeature, MetadataHeader, Triple};
+use wasmer_types::{MetadataHeader, Triple};
 use webc::{compat::SharedBytes, Container, DetectError};
 
 #[derive(Debug, Parser)]
@@ -42,8 +42,10 @@ pub struct GenCHeader {
     #[clap(long = "target")]
     target_triple: Option<Triple>,
 
-    #[clap(long, short = 'm', number_of_values = 1)]
-    cpu_features: Vec<CpuFeature>,
+    /// CPU features of the target to enable or disable, as a list such as
+    /// `+neon,+lse,-sve`. Can be given several times.
+    #[clap(long, short = 'm', number_of_values = 1, allow_hyphen_values = true)]
+    cpu_features: Vec<String>,
 }
 
 impl GenCHeader {
@@ -72,7 +74,7 @@ impl GenCHeader {
         let target = crate::commands::create_exe::utils::target_triple_to_target(
             &target_triple,
             &self.cpu_features,
-        );
+        )?;
         let (engine, _) = CompilerOptions::default().get_engine_for_target(target.clone())?;
         let engine_inner = engine.inner();
         let compiler = engine_inner.compiler()?;
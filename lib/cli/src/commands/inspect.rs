@@ -4,6 +4,8 @@ use bytesize::ByteSize;
 use clap::Parser;
 use std::path::PathBuf;
 use wasmer::*;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{disassemble_artifact, ArtifactBuild, DumpFilter};
 
 #[derive(Debug, Parser)]
 /// The options for the `wasmer validate` subcommand
@@ -12,6 +14,39 @@ pub struct Inspect {
     #[clap(name = "FILE")]
     path: PathBuf,
 
+    /// Dump the contents of every custom section with this name, instead of
+    /// summarizing the module
+    #[clap(long, value_name = "NAME")]
+    custom_section: Option<String>,
+
+    /// Print the module's imports and exports as JSON
+    #[clap(long, conflicts_with = "custom_section")]
+    json: bool,
+
+    /// Print the machine code of each function, disassembled and annotated
+    /// with the offsets of the WebAssembly instructions it was generated
+    /// for. Modules are compiled with the selected compiler, and
+    /// precompiled artifacts are read as they are.
+    #[cfg(feature = "compiler")]
+    #[clap(long, conflicts_with_all = &["custom_section", "json"])]
+    disassemble: bool,
+
+    /// Only disassemble the function with this index or name (can be
+    /// repeated)
+    #[cfg(feature = "compiler")]
+    #[clap(
+        long = "function",
+        value_name = "INDEX_OR_NAME",
+        requires = "disassemble"
+    )]
+    functions: Vec<DumpFilter>,
+
+    /// Compile for this target triple, rather than the host, when
+    /// disassembling
+    #[cfg(feature = "compiler")]
+    #[clap(long = "target", requires = "disassemble")]
+    target_triple: Option<Triple>,
+
     #[clap(flatten)]
     store: StoreOptions,
 }
@@ -24,13 +59,62 @@ impl Inspect {
     }
 
     fn inner_execute(&self) -> Result<()> {
+        #[cfg(feature = "compiler")]
+        if self.disassemble {
+            return self.disassemble();
+        }
+
         let (store, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
         let iswasm = is_wasm(&module_contents);
         let module_len = module_contents.len();
-        let module = Module::new(&store, module_contents)?;
-        println!("Type: {}", if !iswasm { "wat" } else { "wasm" });
+        #[cfg(feature = "compiler")]
+        let is_artifact = ArtifactBuild::is_deserializable(&module_contents);
+        #[cfg(not(feature = "compiler"))]
+        let is_artifact = false;
+        let module = if is_artifact {
+            unsafe { Module::deserialize_checked(&store, module_contents)? }
+        } else {
+            Module::new(&store, module_contents)?
+        };
+
+        if let Some(name) = &self.custom_section {
+            return dump_custom_sections(&module, name);
+        }
+
+        if self.json {
+            let interface = serde_json::to_string_pretty(&module.interface())?;
+            println!("{interface}");
+            return Ok(());
+        }
+
+        // Like `wasmer run`, fall back to the file name
+        let name = match module.name() {
+            Some(name) => name.to_string(),
+            None => self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        println!("Name: {name}");
+        let ty = if is_artifact {
+            "artifact"
+        } else if iswasm {
+            "wasm"
+        } else {
+            "wat"
+        };
+        println!("Type: {ty}");
         println!("Size: {}", ByteSize(module_len as _));
+        #[cfg(feature = "compiler")]
+        if is_artifact {
+            let compiler = match module.compiler() {
+                "" => "unknown",
+                compiler => compiler,
+            };
+            println!("Compiler: {compiler}");
+        }
         println!("Imports:");
         println!("  Functions:");
         for f in module.imports().functions() {
@@ -65,6 +149,66 @@ impl Inspect {
         for f in module.exports().globals() {
             println!("    \"{}\": {}", f.name(), f.ty());
         }
+        println!("Custom Sections:");
+        for name in module.custom_section_names() {
+            for section in module.custom_sections(name) {
+                println!("  \"{}\": {}", name, ByteSize(section.len() as _));
+            }
+        }
         Ok(())
     }
+
+    /// Print the disassembled machine code of the functions.
+    #[cfg(feature = "compiler")]
+    fn disassemble(&self) -> Result<()> {
+        let target = match &self.target_triple {
+            Some(triple) => {
+                let mut features = CpuFeature::set();
+                // Cranelift requires SSE2
+                if triple.architecture == Architecture::X86_64 {
+                    features |= CpuFeature::SSE2;
+                }
+                Target::new(triple.clone(), features)
+            }
+            None => Target::default(),
+        };
+        let (engine, _compiler_type) = self.store.get_engine_for_target(target)?;
+
+        let contents = std::fs::read(&self.path)?;
+        let artifact = if ArtifactBuild::is_deserializable(&contents) {
+            unsafe { engine.deserialize_checked(&contents)? }
+        } else {
+            engine.compile(&wat2wasm(&contents)?)?
+        };
+
+        print!(
+            "{}",
+            disassemble_artifact(artifact.build(), &self.functions)
+        );
+        Ok(())
+    }
+}
+
+/// Print a hex dump of each custom section called `name`.
+fn dump_custom_sections(module: &Module, name: &str) -> Result<()> {
+    let mut found = false;
+
+    for (i, section) in module.custom_sections(name).enumerate() {
+        found = true;
+        println!(
+            "Custom section \"{}\" #{} ({}):",
+            name,
+            i,
+            ByteSize(section.len() as _)
+        );
+        for (offset, chunk) in section.chunks(16).enumerate() {
+            println!("  {:08x}: {}", offset * 16, hex::encode(chunk));
+        }
+    }
+
+    if !found {
+        anyhow::bail!("The module doesn't contain a \"{}\" custom section", name);
+    }
+
+    Ok(())
 }
@@ -0,0 +1,113 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use ed25519_dalek::{
+    pkcs8::{spki::der::pem::LineEnding, EncodePrivateKey, EncodePublicKey},
+    SigningKey,
+};
+use rand_core::OsRng;
+
+#[derive(Debug, Parser)]
+/// The options for the `wasmer keygen` subcommand
+pub struct Keygen {
+    /// The directory to write the key pair to
+    #[clap(long, default_value = ".")]
+    out_dir: PathBuf,
+    /// How the keys should be encoded
+    #[clap(long, value_enum, default_value_t = KeyFormat::Pem)]
+    format: KeyFormat,
+    /// Overwrite any existing key files
+    #[clap(long)]
+    force: bool,
+}
+
+/// How a key pair should be encoded
+#[derive(Debug, PartialEq, Eq, Copy, Clone, clap::ValueEnum)]
+pub enum KeyFormat {
+    /// PKCS#8 and SPKI, PEM-encoded (`private.pem` and `public.pem`)
+    Pem,
+    /// The raw 32-byte keys as hex strings (`private.hex` and `public.hex`)
+    Hex,
+}
+
+impl Keygen {
+    /// Runs logic for the `keygen` subcommand
+    pub fn execute(&self) -> Result<()> {
+        let key = SigningKey::generate(&mut OsRng);
+
+        let (private_key, public_key, extension) = match self.format {
+            KeyFormat::Pem => (
+                key.to_pkcs8_pem(LineEnding::LF)
+                    .map_err(|e| anyhow::anyhow!("Unable to encode the private key: {e}"))?
+                    .to_string(),
+                key.verifying_key()
+                    .to_public_key_pem(LineEnding::LF)
+                    .map_err(|e| anyhow::anyhow!("Unable to encode the public key: {e}"))?,
+                "pem",
+            ),
+            KeyFormat::Hex => (
+                format!("{}\n", hex::encode(key.to_bytes())),
+                format!("{}\n", hex::encode(key.verifying_key().to_bytes())),
+                "hex",
+            ),
+        };
+
+        std::fs::create_dir_all(&self.out_dir)
+            .with_context(|| format!("Unable to create \"{}\"", self.out_dir.display()))?;
+        let private_path = self.out_dir.join(format!("private.{extension}"));
+        let public_path = self.out_dir.join(format!("public.{extension}"));
+
+        // Check both up front so we never leave half a key pair behind
+        if !self.force {
+            for path in [&private_path, &public_path] {
+                if path.exists() {
+                    anyhow::bail!(
+                        "\"{}\" already exists. Use --force to overwrite it",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        write_private_key(&private_path, &private_key)?;
+        std::fs::write(&public_path, public_key)
+            .with_context(|| format!("Unable to write \"{}\"", public_path.display()))?;
+
+        eprintln!("Wrote the private key to `{}`.", private_path.display());
+        eprintln!("Wrote the public key to `{}`.", public_path.display());
+        eprintln!(
+            "Keep the private key somewhere safe. Anyone who has it can sign modules as you."
+        );
+
+        Ok(())
+    }
+}
+
+/// Write the private key, making sure only the current user can read it.
+fn write_private_key(path: &Path, contents: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let write = || -> std::io::Result<()> {
+        let mut f = options.open(path)?;
+        // The mode is only used when the file gets created, so tighten the
+        // permissions of a key we are overwriting before anything is written
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            f.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        f.write_all(contents.as_bytes())
+    };
+
+    write().with_context(|| format!("Unable to write \"{}\"", path.display()))
+}
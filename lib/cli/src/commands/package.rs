@@ -0,0 +1,32 @@
+//! Subcommands for working with Wasmer packages.
+
+mod checksum;
+mod vendor;
+
+use clap::Parser;
+
+pub use self::checksum::Checksum;
+pub(crate) use self::vendor::vendored_source;
+pub use self::vendor::Vendor;
+
+/// The options for the `wasmer package` subcommand
+#[derive(Debug, Parser)]
+pub enum Package {
+    /// Download all of a package's dependencies into a local `vendor/`
+    /// directory, so it can be run with `wasmer run --offline`
+    Vendor(Vendor),
+    /// Check the packages downloaded from the registry haven't been
+    /// corrupted or tampered with, by comparing their SHA-256 hashes with
+    /// the registry's
+    Checksum(Checksum),
+}
+
+impl Package {
+    /// Execute the package command
+    pub fn execute(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Package::Vendor(vendor) => vendor.execute(),
+            Package::Checksum(checksum) => checksum.execute(),
+        }
+    }
+}
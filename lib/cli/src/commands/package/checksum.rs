@@ -0,0 +1,221 @@
+//! `wasmer package checksum`, which checks the packages `wasmer run`
+//! downloaded haven't been corrupted or tampered with since.
+//!
+//! Downloaded packages are kept in the `checkouts/` cache, named after the
+//! SHA-256 hash the registry gave for them. A package is fine as long as
+//! its file still has that hash.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Error};
+use clap::Parser;
+use wasmer_registry::wasmer_env::WasmerEnv;
+use wasmer_wasix::{
+    http::HttpClient,
+    runtime::{
+        package_loader::BuiltinPackageLoader,
+        resolver::{PackageSpecifier, PackageSummary, Source, WebcHash},
+    },
+};
+
+use crate::commands::run::wasi::Wasi;
+
+/// The options for the `wasmer package checksum` subcommand
+#[derive(Debug, Parser)]
+pub struct Checksum {
+    #[clap(flatten)]
+    env: WasmerEnv,
+
+    /// Check every downloaded package against the hash it was downloaded
+    /// with, without asking the registry.
+    #[clap(long, conflicts_with = "package")]
+    all: bool,
+
+    /// The package to check, as `<namespace>/<name>@<version>`. Its hash is
+    /// looked up in the registry.
+    #[clap(required_unless_present = "all")]
+    package: Option<String>,
+}
+
+impl Checksum {
+    /// Execute the checksum command
+    pub fn execute(&self) -> Result<(), Error> {
+        let client =
+            wasmer_wasix::http::default_http_client().context("No HTTP client available")?;
+        let client = Arc::new(client);
+        let checkouts = self.env.cache_dir().join("checkouts");
+        let loader = BuiltinPackageLoader::new_with_client(checkouts, client.clone());
+
+        match &self.package {
+            Some(package) => self.check_package(package, &loader, client),
+            None => check_all(&loader),
+        }
+    }
+
+    fn check_package(
+        &self,
+        package: &str,
+        loader: &BuiltinPackageLoader,
+        client: Arc<dyn HttpClient + Send + Sync>,
+    ) -> Result<(), Error> {
+        let specifier = PackageSpecifier::parse(package)
+            .with_context(|| format!("Invalid package, \"{package}\""))?;
+        anyhow::ensure!(
+            matches!(specifier, PackageSpecifier::Registry { .. }),
+            "Only packages from the registry can be checked, not \"{package}\""
+        );
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let source = Wasi::default().prepare_source(&self.env, client)?;
+        let summary: PackageSummary = runtime
+            .block_on(source.latest(&specifier))
+            .with_context(|| format!("Unable to look up {package} in the registry"))?;
+        let id = summary.package_id();
+        let expected = summary.dist.webc_sha256;
+
+        let path = loader
+            .cache_path(&expected)
+            .expect("the loader always has a filesystem cache");
+        anyhow::ensure!(
+            path.exists(),
+            "{id} isn't installed. Run it with `wasmer run` to download it."
+        );
+
+        let checked = CheckedPackage::check(&path, expected)?;
+        println!("{}", checked.describe(&id.to_string()));
+        anyhow::ensure!(checked.matches(), "{id} doesn't match its checksum");
+
+        Ok(())
+    }
+}
+
+/// Check every package in the cache against the hash it was saved under.
+fn check_all(loader: &BuiltinPackageLoader) -> Result<(), Error> {
+    let webcs = loader.cached_webcs()?;
+    if webcs.is_empty() {
+        println!("No packages are installed");
+        return Ok(());
+    }
+
+    let mut mismatches = 0;
+    for (expected, path) in &webcs {
+        let checked = CheckedPackage::check(path, *expected)?;
+        // Corrupted files might not even be readable as packages
+        let name = PackageSummary::from_webc_file(path)
+            .map(|summary| summary.package_id().to_string())
+            .unwrap_or_else(|_| path.display().to_string());
+        println!("{}", checked.describe(&name));
+        if !checked.matches() {
+            mismatches += 1;
+        }
+    }
+
+    anyhow::ensure!(
+        mismatches == 0,
+        "{mismatches} of the {} installed packages don't match their checksums",
+        webcs.len()
+    );
+
+    Ok(())
+}
+
+/// A downloaded package, and the hash it should have.
+#[derive(Debug, Clone, PartialEq)]
+struct CheckedPackage {
+    path: PathBuf,
+    expected: WebcHash,
+    actual: WebcHash,
+}
+
+impl CheckedPackage {
+    fn check(path: &Path, expected: WebcHash) -> Result<Self, Error> {
+        let actual = WebcHash::for_file(path)
+            .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+        Ok(CheckedPackage {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        })
+    }
+
+    fn matches(&self) -> bool {
+        self.expected == self.actual
+    }
+
+    fn describe(&self, name: &str) -> String {
+        if self.matches() {
+            format!("{name}: OK (sha256 {})", self.actual)
+        } else {
+            format!(
+                "{name}: MISMATCH (expected sha256 {}, but \"{}\" has {})",
+                self.expected,
+                self.path.display(),
+                self.actual,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn coreutils() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../tests/integration/cli/tests/webc")
+            .join("coreutils-1.0.16-e27dbb4f-2ef2-4b44-b46a-ddd86497c6d7.webc")
+    }
+
+    /// A loader whose cache has coreutils in it, saved under `hash`.
+    fn cache_coreutils(temp: &TempDir, hash: Option<WebcHash>) -> BuiltinPackageLoader {
+        let client = wasmer_wasix::http::default_http_client().unwrap();
+        let loader = BuiltinPackageLoader::new_with_client(temp.path(), Arc::new(client));
+        let hash = hash.unwrap_or_else(|| WebcHash::for_file(coreutils()).unwrap());
+        let path = loader.cache_path(&hash).unwrap();
+        std::fs::copy(coreutils(), path).unwrap();
+        loader
+    }
+
+    #[test]
+    fn intact_packages_match() {
+        let temp = TempDir::new().unwrap();
+        let loader = cache_coreutils(&temp, None);
+        let (hash, path) = loader.cached_webcs().unwrap().remove(0);
+
+        let checked = CheckedPackage::check(&path, hash).unwrap();
+
+        assert!(checked.matches());
+        assert!(checked.describe("coreutils").starts_with("coreutils: OK"));
+        check_all(&loader).unwrap();
+    }
+
+    #[test]
+    fn modified_packages_are_reported() {
+        let temp = TempDir::new().unwrap();
+        let loader = cache_coreutils(&temp, Some(WebcHash::from_bytes([0xaa; 32])));
+
+        let err = check_all(&loader).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "1 of the 1 installed packages don't match their checksums"
+        );
+    }
+
+    #[test]
+    fn nothing_installed() {
+        let temp = TempDir::new().unwrap();
+        let client = wasmer_wasix::http::default_http_client().unwrap();
+        let loader =
+            BuiltinPackageLoader::new_with_client(temp.path().join("checkouts"), Arc::new(client));
+
+        check_all(&loader).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+//! `wasmer package vendor`, which downloads every registry dependency of a
+//! package so it can be run without network access.
+//!
+//! Each dependency is saved to `vendor/<name>-<version>/package.webc`, and
+//! `vendor/config.toml` records which packages were vendored and their
+//! hashes. `wasmer run --offline` resolves packages using that file instead
+//! of the registry.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Error};
+use clap::Parser;
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+use wasmer_registry::wasmer_env::WasmerEnv;
+use wasmer_wasix::runtime::{
+    package_loader::BuiltinPackageLoader,
+    resolver::{
+        Dependency, InMemorySource, PackageInfo, PackageSpecifier, PackageSummary, WebcHash,
+    },
+};
+
+use crate::commands::run::wasi::Wasi;
+
+/// The file in the vendor directory listing the vendored packages.
+const CONFIG_FILE: &str = "config.toml";
+/// What each vendored package's `*.webc` file is called.
+const WEBC_FILE: &str = "package.webc";
+
+/// The options for the `wasmer package vendor` subcommand
+#[derive(Debug, Parser)]
+pub struct Vendor {
+    #[clap(flatten)]
+    env: WasmerEnv,
+
+    /// The directory containing the package's `wasmer.toml` file.
+    #[clap(long, default_value = ".")]
+    package_dir: PathBuf,
+
+    /// Where to save the dependencies (`vendor/` next to the `wasmer.toml`
+    /// by default).
+    vendor_dir: Option<PathBuf>,
+}
+
+impl Vendor {
+    /// Execute the vendor command
+    pub fn execute(&self) -> Result<(), Error> {
+        let manifest_path = self.package_dir.join("wasmer.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Unable to read \"{}\"", manifest_path.display()))?;
+        let manifest = wasmer_toml::Manifest::parse(&manifest)
+            .with_context(|| format!("Unable to parse \"{}\"", manifest_path.display()))?;
+        let root = root_package(&manifest)?;
+
+        let vendor_dir = self
+            .vendor_dir
+            .clone()
+            .unwrap_or_else(|| self.package_dir.join("vendor"));
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let client =
+            wasmer_wasix::http::default_http_client().context("No HTTP client available")?;
+        let client = Arc::new(client);
+        let source = Wasi::default().prepare_source(&self.env, client.clone())?;
+        let loader = BuiltinPackageLoader::new_only_client(client);
+
+        let resolution = runtime
+            .block_on(wasmer_wasix::runtime::resolver::resolve(
+                &root.id(),
+                &root,
+                &source,
+            ))
+            .context("Unable to resolve the package's dependencies")?;
+
+        let mut config = VendorConfig::default();
+
+        for (id, index) in resolution.graph.packages() {
+            // Only the root package doesn't come from somewhere else
+            let dist = match &resolution.graph[index].dist {
+                Some(dist) => dist,
+                None => continue,
+            };
+
+            let relative_path = format!("{}-{}/{WEBC_FILE}", id.package_name, id.version);
+            let path = vendor_dir.join(&relative_path);
+
+            if WebcHash::for_file(&path).ok() == Some(dist.webc_sha256) {
+                tracing::debug!(%id, path=%path.display(), "Already vendored");
+            } else {
+                eprintln!("Downloading {id}");
+                let webc = runtime
+                    .block_on(loader.download(dist))
+                    .with_context(|| format!("Unable to download {id}"))?;
+
+                let hash = WebcHash::sha256(&webc);
+                anyhow::ensure!(
+                    hash == dist.webc_sha256,
+                    "The registry said {id} has the hash {}, but the downloaded file's hash is {hash}",
+                    dist.webc_sha256,
+                );
+
+                let dir = path.parent().expect("always has a parent");
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Unable to create \"{}\"", dir.display()))?;
+                std::fs::write(&path, &webc)
+                    .with_context(|| format!("Unable to save {id} to \"{}\"", path.display()))?;
+            }
+
+            config.packages.push(VendoredPackage {
+                name: id.package_name.clone(),
+                version: id.version.to_string(),
+                path: relative_path,
+                webc_sha256: dist.webc_sha256.to_string(),
+            });
+        }
+
+        config.save(&vendor_dir)?;
+        eprintln!(
+            "Vendored {} packages into \"{}\"",
+            config.packages.len(),
+            vendor_dir.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Work out the root of the dependency graph from a `wasmer.toml` file.
+fn root_package(manifest: &wasmer_toml::Manifest) -> Result<PackageInfo, Error> {
+    let dependencies = manifest
+        .dependencies
+        .iter()
+        .flatten()
+        .map(|(name, version)| {
+            let version: VersionReq = version.parse().with_context(|| {
+                format!("Invalid version constraint for \"{name}\": \"{version}\"")
+            })?;
+            Ok(Dependency {
+                alias: name.clone(),
+                pkg: PackageSpecifier::Registry {
+                    full_name: name.clone(),
+                    version,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(PackageInfo {
+        name: manifest.package.name.clone(),
+        version: manifest.package.version.clone(),
+        commands: Vec::new(),
+        entrypoint: None,
+        dependencies,
+        filesystem: Vec::new(),
+    })
+}
+
+/// Load the packages saved by `wasmer package vendor` into `vendor_dir`,
+/// checking each of them still matches the hash in `config.toml`.
+pub(crate) fn vendored_source(vendor_dir: &Path) -> Result<InMemorySource, Error> {
+    let config = VendorConfig::load(vendor_dir)?;
+    let mut source = InMemorySource::new();
+
+    for pkg in &config.packages {
+        let path = vendor_dir.join(&pkg.path);
+        let summary = PackageSummary::from_webc_file(&path)
+            .with_context(|| format!("Unable to load \"{}\"", path.display()))?;
+
+        anyhow::ensure!(
+            summary.pkg.name == pkg.name && summary.pkg.version.to_string() == pkg.version,
+            "\"{}\" contains {}, but {CONFIG_FILE} says it should be {}@{}",
+            path.display(),
+            summary.package_id(),
+            pkg.name,
+            pkg.version,
+        );
+        anyhow::ensure!(
+            summary
+                .dist
+                .webc_sha256
+                .to_string()
+                .eq_ignore_ascii_case(&pkg.webc_sha256),
+            "\"{}\" has been modified since it was vendored. Run `wasmer package vendor` again to fix it.",
+            path.display(),
+        );
+
+        source.add(summary);
+    }
+
+    Ok(source)
+}
+
+/// The contents of `vendor/config.toml`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct VendorConfig {
+    #[serde(default, rename = "package")]
+    packages: Vec<VendoredPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct VendoredPackage {
+    name: String,
+    version: String,
+    /// Where the `*.webc` file is, relative to the vendor directory.
+    path: String,
+    webc_sha256: String,
+}
+
+impl VendorConfig {
+    fn load(vendor_dir: &Path) -> Result<Self, Error> {
+        let path = vendor_dir.join(CONFIG_FILE);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!(
+                    "There are no vendored packages in \"{}\". Run `wasmer package vendor` first.",
+                    vendor_dir.display()
+                );
+            }
+            Err(e) => {
+                return Err(Error::new(e).context(format!("Unable to read \"{}\"", path.display())))
+            }
+        };
+
+        toml::from_str(&contents).with_context(|| format!("Unable to parse \"{}\"", path.display()))
+    }
+
+    fn save(&self, vendor_dir: &Path) -> Result<(), Error> {
+        let path = vendor_dir.join(CONFIG_FILE);
+        let mut contents = String::from(
+            "# Generated by `wasmer package vendor`. `wasmer run --offline` uses\n\
+             # these packages instead of the registry.\n\n",
+        );
+        contents.push_str(&toml::to_string(self)?);
+
+        std::fs::create_dir_all(vendor_dir)
+            .with_context(|| format!("Unable to create \"{}\"", vendor_dir.display()))?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Unable to write \"{}\"", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use wasmer_wasix::runtime::resolver::Source;
+
+    use super::*;
+
+    fn coreutils() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../tests/integration/cli/tests/webc")
+            .join("coreutils-1.0.16-e27dbb4f-2ef2-4b44-b46a-ddd86497c6d7.webc")
+    }
+
+    fn vendor_coreutils(vendor_dir: &Path) {
+        let relative_path = format!("sharrattj/coreutils-1.0.16/{WEBC_FILE}");
+        let path = vendor_dir.join(&relative_path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::copy(coreutils(), &path).unwrap();
+
+        let config = VendorConfig {
+            packages: vec![VendoredPackage {
+                name: "sharrattj/coreutils".to_string(),
+                version: "1.0.16".to_string(),
+                path: relative_path,
+                webc_sha256: WebcHash::for_file(&path).unwrap().to_string(),
+            }],
+        };
+        config.save(vendor_dir).unwrap();
+    }
+
+    #[test]
+    fn config_round_trips() {
+        let temp = TempDir::new().unwrap();
+        vendor_coreutils(temp.path());
+
+        let config = VendorConfig::load(temp.path()).unwrap();
+
+        assert_eq!(config.packages.len(), 1);
+        assert_eq!(config.packages[0].name, "sharrattj/coreutils");
+        let contents = std::fs::read_to_string(temp.path().join(CONFIG_FILE)).unwrap();
+        assert!(contents.contains("[[package]]"), "{contents}");
+    }
+
+    #[tokio::test]
+    async fn vendored_packages_can_be_queried() {
+        let temp = TempDir::new().unwrap();
+        vendor_coreutils(temp.path());
+
+        let source = vendored_source(temp.path()).unwrap();
+        let summary = source
+            .latest(&PackageSpecifier::parse("sharrattj/coreutils@^1").unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(summary.pkg.version.to_string(), "1.0.16");
+        assert_eq!(summary.dist.webc.scheme(), "file");
+    }
+
+    #[test]
+    fn modified_packages_are_rejected() {
+        let temp = TempDir::new().unwrap();
+        vendor_coreutils(temp.path());
+        let mut config = VendorConfig::load(temp.path()).unwrap();
+        config.packages[0].webc_sha256 = "00".repeat(32);
+        config.save(temp.path()).unwrap();
+
+        let err = vendored_source(temp.path()).unwrap_err();
+
+        assert!(err.to_string().contains("has been modified"), "{err}");
+    }
+
+    #[test]
+    fn missing_vendor_directory() {
+        let temp = TempDir::new().unwrap();
+
+        let err = vendored_source(&temp.path().join("vendor")).unwrap_err();
+
+        assert!(err.to_string().contains("wasmer package vendor"), "{err}");
+    }
+}
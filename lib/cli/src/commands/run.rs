@@ -1,12 +1,34 @@
 #![allow(missing_docs, unused)]
 
-mod wasi;
+mod cbor;
+#[cfg(feature = "compiler")]
+mod coverage;
+#[cfg(feature = "compiler")]
+mod debug_info;
+mod deferred_cache;
+mod dns_cache;
+mod first_write;
+mod har;
+mod instances;
+mod net_connect;
+#[cfg(target_os = "linux")]
+mod netns;
+mod oci;
+mod perf_counters;
+mod pipe_modules;
+mod preload;
+mod read_config;
+mod snapshot;
+mod strace;
+mod type_reflection;
+pub(crate) mod wasi;
 
 use std::{
     collections::BTreeMap,
+    ffi::OsString,
     fmt::{Binary, Display},
     fs::File,
-    io::{ErrorKind, LineWriter, Read, Write},
+    io::{BufRead, ErrorKind, LineWriter, Read, Write},
     net::SocketAddr,
     path::{Path, PathBuf},
     str::FromStr,
@@ -21,11 +43,12 @@ use once_cell::sync::Lazy;
 use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
 use tokio::runtime::Handle;
+use tracing_subscriber::{filter::filter_fn, Layer, Registry};
 use url::Url;
 use wapm_targz_to_pirita::{webc::v1::DirOrFile, FileMap, TransformManifestFunctions};
 use wasmer::{
-    DeserializeError, Engine, Function, Imports, Instance, Module, Store, Type, TypedFunction,
-    Value,
+    DeserializeError, Engine, Function, Imports, Instance, InterruptHandle, Memory, Module,
+    NativeEngineExt, RuntimeError, Store, Type, TypedFunction, Value, WatLocation, WatSourceMap,
 };
 #[cfg(feature = "compiler")]
 use wasmer_compiler::ArtifactBuild;
@@ -50,10 +73,32 @@ use wasmer_wasix::{
 };
 use webc::{metadata::Manifest, Container};
 
-use crate::{commands::run::wasi::Wasi, error::PrettyError, logging::Output, store::StoreOptions};
+use crate::{
+    commands::run::{
+        cbor::{StdioFormat, Transcoders},
+        first_write::FirstWriteWatcher,
+        instances::{InstanceOutcome, OutputDir},
+        oci::OciBundle,
+        perf_counters::PerfCounters,
+        pipe_modules::{PipeFds, PipedModule},
+        snapshot::ExportSnapshot,
+        strace::StraceLayer,
+        wasi::Wasi,
+    },
+    error::PrettyError,
+    logging::{LogOptions, Output},
+    signature,
+    store::{CompilerType, StoreOptions},
+};
 
 const TICK: Duration = Duration::from_millis(250);
 
+/// The guard region `--mem-protect-stack` puts below the execution stack,
+/// which is bigger than any frame the compilers generate without probing
+/// the stack.
+#[cfg(feature = "sys")]
+const STACK_GUARD_SIZE: usize = 64 * 1024;
+
 /// The unstable `wasmer run` subcommand.
 #[derive(Debug, Parser)]
 pub struct Run {
@@ -65,35 +110,323 @@ pub struct Run {
     wasi: crate::commands::run::Wasi,
     #[clap(flatten)]
     wcgi: WcgiOptions,
+    /// Set environment variables from a JSON object (e.g.
+    /// `{"DEBUG": "true"}`), or from a JSON file when prefixed with `@`
+    #[clap(long, value_name = "JSON")]
+    env_json: Option<String>,
     /// Set the default stack size (default is 1048576)
     #[clap(long = "stack-size")]
     stack_size: Option<usize>,
+    /// Put a bigger guard region below the WebAssembly execution stack, so
+    /// overflowing the stack with a large frame traps rather than corrupting
+    /// the memory below it (does nothing on platforms without `mprotect`)
+    #[cfg(feature = "sys")]
+    #[clap(long)]
+    mem_protect_stack: bool,
     /// The function or command to invoke.
     #[clap(short, long, aliases = &["command", "invoke", "command-name"])]
     entrypoint: Option<String>,
     /// Generate a coredump at this path if a WebAssembly trap occurs
     #[clap(name = "COREDUMP PATH", long)]
     coredump_on_trap: Option<PathBuf>,
+    /// Stop the module if it is still running after this many seconds
+    #[clap(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+    /// Enable the SIMD proposal and warn if the module doesn't contain any
+    /// SIMD instructions
+    #[clap(long, conflicts_with = "disable_simd")]
+    wasm_simd: bool,
+    /// Enable the relaxed SIMD proposal (`f32x4.relaxed_madd`, etc.), whose
+    /// instructions may give different results on different platforms (only
+    /// supported by the Cranelift compiler)
+    #[clap(long, conflicts_with = "disable_simd")]
+    wasm_relaxed_simd: bool,
+    /// Enable the exception handling proposal, used by C++ and Java
+    /// toolchains for `throw`/`catch` (only supported by the Cranelift
+    /// compiler)
+    #[clap(long)]
+    wasm_exception_handling: bool,
+    /// Enable the tail call proposal (`return_call` and
+    /// `return_call_indirect`), used by functional languages for proper tail
+    /// recursion
+    #[clap(long = "tail-call")]
+    wasm_tail_call: bool,
+    /// Enable the extended constant expressions proposal, which allows
+    /// `i32.add`, `i32.sub` and `i32.mul` (and their `i64` versions) in the
+    /// initializers of globals and the offsets of data and element segments
+    #[clap(long = "extended-const")]
+    wasm_extended_const: bool,
+    /// Provide the `wasm:type-reflection` host functions (`wasm_typeof`,
+    /// `wasm_cast`, etc.), which let the module inspect the types of its
+    /// functions at runtime. Other imports from that namespace trap when
+    /// called
+    #[clap(long)]
+    type_reflection: bool,
+    /// Use the module's DWARF debug info to show source locations when a
+    /// trap occurs
+    #[clap(long)]
+    debug_info: bool,
+    /// Parse the input as WebAssembly text, regardless of its file extension
+    #[clap(long)]
+    wat: bool,
+    /// Read a WebAssembly text module from stdin and run it
+    #[clap(long, conflicts_with_all = &["input", "wat", "debug_info"])]
+    stdin_wat: bool,
+    /// Point traps and validation errors in WebAssembly text modules back at
+    /// the line of the text they happened at
+    #[clap(long)]
+    wat_source_map: bool,
+    /// Record which parts of the module's code run, and write them to this
+    /// file in the LCOV format once it exits, mapped back to source lines
+    /// with the module's DWARF debug info when it has some (only supported
+    /// by Cranelift and LLVM)
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &[
+            "stdin_wat",
+            "reuse_instance",
+            "exit_on_first_write",
+            "instance_count",
+            "pipe_modules",
+            "preload_modules",
+        ]
+    )]
+    coverage_out: Option<PathBuf>,
+    /// Verify the module's Ed25519 signature before running it
+    #[clap(long, requires = "trusted_key")]
+    check_signatures: bool,
+    /// The PEM-encoded public key used by --check-signatures
+    #[clap(long, value_name = "PUBKEY.PEM", requires = "check_signatures")]
+    trusted_key: Option<PathBuf>,
+    /// Write the symbols of the JIT-compiled functions where `perf` can find
+    /// them. `perfmap` writes /tmp/perf-<pid>.map, while `jitdump` (Linux
+    /// only) writes /tmp/jit-<pid>.dump with their machine code as well,
+    /// which needs `perf record -k mono` and `perf inject --jit`
+    #[cfg(feature = "sys")]
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    profile: Option<ProfileFormat>,
+    /// Same as --profile=perfmap
+    #[cfg(feature = "sys")]
+    #[clap(long, conflicts_with = "profile")]
+    perfmap: bool,
+    /// Count the CPU cycles, instructions, cache misses and branch
+    /// mispredictions of the module's execution with the hardware
+    /// performance counters, and print them to stderr when it finishes
+    /// (Linux only)
+    #[clap(long)]
+    perf_counters: bool,
+    /// Write a strace-compatible log of every WASI syscall to this file
+    #[clap(long, value_name = "PATH")]
+    strace_output: Option<PathBuf>,
+    #[clap(flatten)]
+    log: LogOptions,
+    /// Keep the instance alive and call --handler-fn once for each line of
+    /// stdin, using the line's whitespace-separated values as arguments and
+    /// printing the results to stdout
+    #[clap(long)]
+    reuse_instance: bool,
+    /// The function --reuse-instance calls for each line of stdin
+    #[clap(
+        long,
+        value_name = "NAME",
+        default_value = "handle",
+        requires = "reuse_instance"
+    )]
+    handler_fn: String,
+    /// Restore the instance's memory to its initial contents (i.e. the data
+    /// segments) before each call made by --reuse-instance
+    #[clap(long, requires = "reuse_instance")]
+    reset_memory_between_calls: bool,
+    /// How the data piped to stdin is encoded. With `cbor`, each CBOR item is
+    /// passed to the module as a line of JSON
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = StdioFormat::Raw,
+        conflicts_with_all = &["stdin_wat", "reuse_instance"]
+    )]
+    input_format: StdioFormat,
+    /// How to encode the module's stdout. With `cbor`, each line of JSON the
+    /// module prints is written as a CBOR item
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = StdioFormat::Raw,
+        conflicts_with = "reuse_instance"
+    )]
+    output_format: StdioFormat,
+    /// Exit with a status of 0 as soon as the module writes anything to
+    /// stdout, printing how long that took since wasmer started to stderr.
+    /// Useful for measuring the latency to the first byte of output
+    #[clap(long, conflicts_with_all = &["reuse_instance", "output_format"])]
+    exit_on_first_write: bool,
+    /// Run the WebAssembly module described by the `config.json` in this OCI
+    /// bundle directory, using its arguments and environment, with the
+    /// bundle's root filesystem mapped to `/`
+    #[clap(long, value_name = "BUNDLE", conflicts_with_all = &["input", "stdin_wat"])]
+    oci_runtime: Option<PathBuf>,
+    /// Run inside the Linux network namespace at this path (e.g.
+    /// `/var/run/netns/myns`), so all of the module's host networking goes
+    /// through that namespace
+    #[cfg(target_os = "linux")]
+    #[clap(long, value_name = "PATH")]
+    network_namespace: Option<PathBuf>,
+    /// Give the module a PID of 1 and a parent PID of 0 instead of the
+    /// host's. WASIX processes always get PIDs numbered from 1 within the
+    /// run, so this only changes what Emscripten's `getpid` and `getppid`
+    /// return.
+    #[clap(long)]
+    pid_namespace: bool,
+    /// Instantiate this module first and use its exports for the main
+    /// module's imports with the same name, ahead of the host functions. Can
+    /// be given several times, with later modules taking precedence. Each
+    /// module can import the exports of the ones preloaded before it
+    #[clap(long = "preload-module", value_name = "PATH")]
+    preload_modules: Vec<PathBuf>,
+    /// Give the module this name (its `argv[0]`) instead of the path it
+    /// was loaded from, for programs that behave differently depending on
+    /// the name they're run as, like BusyBox
+    #[clap(long, value_name = "NAME")]
+    inject_module_name: Option<String>,
+    /// Only write the compiled module to the cache if the run succeeds,
+    /// rather than regardless of whether it traps or exits with a non-zero
+    /// exit code
+    #[clap(long)]
+    cache_on_success_only: bool,
+    /// Run this many instances of the WASI module at the same time, each
+    /// with its own store and WASI environment, and with nothing on stdin
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = &["reuse_instance", "exit_on_first_write"]
+    )]
+    instance_count: Option<u32>,
+    /// Write each instance's stdout, stderr and exit code to their own files
+    /// in this directory (`instance-N-stdout.txt`, `instance-N-stderr.txt`
+    /// and `instance-N-exitcode.txt`), along with a `summary.json` listing
+    /// how every instance finished
+    #[clap(long, value_name = "PATH", requires = "instance_count")]
+    output_dir: Option<PathBuf>,
+    /// Once the entrypoint returns, write the values of the module's
+    /// exported globals and the sizes of its exported tables and memories to
+    /// this file as JSON (e.g. `{"counter": {"type": "i32", "value": 42}}`).
+    /// Nothing is written if the module traps or exits with a non-zero code
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["reuse_instance", "instance_count", "exit_on_first_write"]
+    )]
+    export_snapshot: Option<PathBuf>,
+    /// Run these two WASI modules at the same time, with a pipe from the
+    /// first to the second: the main module can write to it at file
+    /// descriptor 5, and the sidecar can read from it at file descriptor 6.
+    /// The sidecar reads EOF once the main module exits, and reading the main
+    /// module's end gives EOF once the sidecar exits
+    #[clap(
+        long,
+        number_of_values = 2,
+        value_names = &["MAIN", "SIDECAR"],
+        conflicts_with_all = &[
+            "input",
+            "stdin_wat",
+            "oci_runtime",
+            "reuse_instance",
+            "instance_count",
+            "export_snapshot",
+            "exit_on_first_write",
+        ]
+    )]
+    pipe_modules: Vec<PathBuf>,
+    /// The file descriptors --pipe-modules puts the pipe at, in the main
+    /// module and the sidecar
+    #[clap(
+        long,
+        number_of_values = 2,
+        value_names = &["MAIN_FD", "SIDECAR_FD"],
+        requires = "pipe_modules"
+    )]
+    pipe_fd: Vec<u32>,
+    /// Read more flags from this TOML file, one key per flag with the same
+    /// name (e.g. `stack_size = 4096` or `net = true`). Flags given on the
+    /// command line take precedence over the file
+    #[clap(long, value_name = "PATH")]
+    read_config: Option<PathBuf>,
     /// The file, URL, or package to run.
-    #[clap(value_parser = PackageSource::infer)]
-    input: PackageSource,
+    #[clap(
+        value_parser = PackageSource::infer,
+        required_unless_present_any = &["stdin_wat", "oci_runtime", "pipe_modules"]
+    )]
+    input: Option<PackageSource>,
     /// Command-line arguments passed to the package
     args: Vec<String>,
 }
 
 impl Run {
+    /// Expands the `--read-config` flag in the program's arguments into the
+    /// flags set by its file, ahead of parsing them.
+    pub fn args_with_config(args: Vec<OsString>) -> Result<Vec<OsString>, Error> {
+        read_config::args_with_config(args)
+    }
+
     pub fn execute(self, output: Output) -> ! {
         let result = self.execute_inner(output);
         exit_with_wasi_exit_code(result);
     }
 
-    fn execute_inner(self, output: Output) -> Result<(), Error> {
+    fn execute_inner(mut self, output: Output) -> Result<(), Error> {
+        if let Some(dir) = &self.oci_runtime {
+            let mut bundle = OciBundle::load(dir)?;
+            self.input = Some(PackageSource::File(bundle.program));
+            self.args = bundle.args;
+            bundle
+                .env
+                .retain(|(key, _)| !self.wasi.env_vars.iter().any(|(k, _)| k == key));
+            self.wasi.env_vars.splice(0..0, bundle.env);
+            self.wasi.mapped_dirs.push(MappedDirectory {
+                host: bundle.rootfs,
+                guest: "/".to_string(),
+            });
+        }
+
+        if let [main, _] = self.pipe_modules.as_slice() {
+            self.input = Some(PackageSource::File(main.clone()));
+        }
+
+        if let Some(json) = &self.env_json {
+            let mut env_vars = parse_env_json(json)?;
+            // Anything passed explicitly with --env takes precedence
+            env_vars.retain(|(key, _)| !self.wasi.env_vars.iter().any(|(k, _)| k == key));
+            self.wasi.env_vars.splice(0..0, env_vars);
+        }
+
+        self.wasi.mount_tempdir()?;
+
+        if self.check_signatures {
+            self.verify_signature()?;
+        }
+
+        #[cfg(not(feature = "compiler"))]
+        if self.coverage_out.is_some() {
+            anyhow::bail!("--coverage-out needs a compiler to instrument the module");
+        }
+
+        self.wasi.resize_stdout_pipe();
+
         let pb = ProgressBar::new_spinner();
         pb.set_draw_target(output.draw_target());
         pb.enable_steady_tick(TICK);
 
         pb.set_message("Initializing the WebAssembly VM");
 
+        // The runtime's worker threads inherit the namespace, so this has to
+        // happen before they get started
+        #[cfg(target_os = "linux")]
+        if let Some(path) = &self.network_namespace {
+            netns::enter(path)?;
+        }
+
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()?;
@@ -103,38 +436,247 @@ impl Run {
         if self.stack_size.is_some() {
             wasmer_vm::set_stack_size(self.stack_size.unwrap());
         }
+        #[cfg(feature = "sys")]
+        if self.mem_protect_stack {
+            wasmer_vm::set_stack_guard_size(STACK_GUARD_SIZE);
+        }
 
-        let (store, _) = self.store.get_store()?;
-        let runtime = self
-            .wasi
-            .prepare_runtime(store.engine().clone(), &self.env, handle)?;
+        let (store, compiler) = self.store_options().get_store()?;
+        #[cfg(feature = "compiler")]
+        if self.coverage_out.is_some() && compiler == CompilerType::Singlepass {
+            anyhow::bail!("--coverage-out is only supported by Cranelift and LLVM");
+        }
+        #[cfg(feature = "sys")]
+        self.enable_profiling(store.engine())?;
+        let interrupts = handle.spawn(watch_for_interrupts(
+            store.interrupt_handle(),
+            self.timeout.map(Duration::from_secs),
+        ));
+        let (runtime, deferred_cache) = self.wasi.prepare_runtime(
+            store.engine().clone(),
+            &self.env,
+            handle,
+            self.cache_on_success_only,
+        )?;
 
         // This is a slow operation, so let's temporarily wrap the runtime with
         // something that displays progress
         let monitoring_runtime = MonitoringRuntime::new(runtime, pb.clone());
 
-        let target = self.input.resolve_target(&monitoring_runtime, &pb)?;
+        let target = self.input().resolve_target(
+            &monitoring_runtime,
+            &pb,
+            self.wat,
+            self.wat_source_map,
+            output.is_verbose(),
+        )?;
 
         pb.finish_and_clear();
 
+        #[cfg(feature = "compiler")]
+        if self.wasm_simd && !self.stdin_wat {
+            if let ExecutableTarget::WebAssembly { path, .. } = &target {
+                warn_if_no_simd_instructions(path, self.wat)?;
+            }
+        }
+
+        #[cfg(feature = "compiler")]
+        if self.wasm_relaxed_simd && !has_native_relaxed_simd() {
+            crate::warning!(
+                "--wasm-relaxed-simd was specified, but this CPU has no fused multiply-add instructions, so the relaxed instructions will use their deterministic fallbacks"
+            );
+        }
+
+        #[cfg(feature = "compiler")]
+        let debug_info = match &target {
+            ExecutableTarget::WebAssembly { path, .. } if self.debug_info => {
+                load_debug_info(path, self.wat, "--debug-info")?
+            }
+            ExecutableTarget::Package(_) if self.debug_info => {
+                crate::warning!("--debug-info is only supported when running *.wasm files");
+                None
+            }
+            _ => None,
+        };
+
+        // Modules which were compiled ahead of time weren't instrumented
+        #[cfg(feature = "compiler")]
+        if let (ExecutableTarget::WebAssembly { path, .. }, Some(_)) = (&target, &self.coverage_out)
+        {
+            if let TargetOnDisk::Artifact = TargetOnDisk::from_file(path, self.wat)? {
+                anyhow::bail!("--coverage-out can't be used with pre-compiled modules");
+            }
+        }
+
         let runtime: Arc<dyn Runtime + Send + Sync> = Arc::new(monitoring_runtime.runtime);
 
+        let counters = self.perf_counters.then(PerfCounters::start);
+
         let result = {
             match target {
                 ExecutableTarget::WebAssembly { module, path } => {
-                    self.execute_wasm(&path, &module, store, runtime)
+                    self.execute_wasm(&path, &module, store, Arc::clone(&runtime))
                 }
-                ExecutableTarget::Package(pkg) => self.execute_webc(&pkg, runtime),
+                ExecutableTarget::Package(pkg) => self.execute_webc(&pkg, Arc::clone(&runtime)),
             }
         };
 
+        if let Some(counters) = counters {
+            let readings = counters.stop();
+            if !readings.is_empty() {
+                eprint!("{}", perf_counters::format_table(&readings));
+            }
+        }
+
+        interrupts.abort();
+        let result = result.map_err(|e| match self.timeout {
+            Some(timeout) if e.chain().any(is_interrupted) => {
+                e.context(format!("The module timed out after {timeout} seconds"))
+            }
+            _ => e,
+        });
+
         if let Err(e) = &result {
             self.maybe_save_coredump(e);
         }
 
+        if let Some(cache) = deferred_cache {
+            let succeeded = match &result {
+                Ok(()) => true,
+                Err(e) => e
+                    .chain()
+                    .find_map(get_exit_code)
+                    .map_or(false, |code| code.is_success()),
+            };
+            if succeeded {
+                runtime.task_manager().block_on(cache.commit());
+            }
+        }
+
+        #[cfg(feature = "compiler")]
+        let result = match (result, debug_info) {
+            (Err(e), Some(debug_info)) if e.chain().find_map(get_exit_code).is_none() => {
+                Err(debug_info.symbolicate(e))
+            }
+            (result, _) => result,
+        };
+
         result
     }
 
+    /// Turn on perfmap/jitdump output for `engine` as requested by
+    /// `--perfmap`/`--profile`.
+    #[cfg(feature = "sys")]
+    fn enable_profiling(&self, engine: &Engine) -> Result<(), Error> {
+        let format = if self.perfmap {
+            Some(ProfileFormat::Perfmap)
+        } else {
+            self.profile
+        };
+
+        match format {
+            None => {}
+            Some(ProfileFormat::Perfmap) => {
+                let path = wasmer::PerfMap::default_path();
+                engine
+                    .enable_perfmap(&path)
+                    .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+            }
+            #[cfg(target_os = "linux")]
+            Some(ProfileFormat::Jitdump) => {
+                let path = wasmer::JitDump::default_path();
+                engine
+                    .enable_jitdump(&path)
+                    .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(ProfileFormat::Jitdump) => {
+                anyhow::bail!("--profile=jitdump is only supported on Linux");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The module or package being run.
+    fn input(&self) -> PackageSource {
+        match &self.input {
+            Some(input) => input.clone(),
+            None => PackageSource::Stdin,
+        }
+    }
+
+    /// Make sure the module being run was signed by the trusted key.
+    fn verify_signature(&self) -> Result<(), Error> {
+        let key = self
+            .trusted_key
+            .as_deref()
+            .context("--check-signatures requires a --trusted-key")?;
+        let path = match self.input() {
+            PackageSource::File(path) => path,
+            other => anyhow::bail!(
+                "--check-signatures can only be used with modules on disk, not \"{other}\""
+            ),
+        };
+
+        let key = signature::load_verifying_key(key)?;
+        signature::verify_module(&path, &key)
+            .with_context(|| format!("Refusing to run \"{}\"", path.display()))
+    }
+
+    /// The [`StoreOptions`] to use, taking into account any WebAssembly
+    /// features that were enabled by `wasmer run`-specific flags.
+    fn store_options(&self) -> StoreOptions {
+        #[allow(unused_mut)]
+        let mut options = self.store.clone();
+
+        #[cfg(feature = "compiler")]
+        if self.wasm_simd {
+            options.features_mut().simd = true;
+        }
+        #[cfg(feature = "compiler")]
+        if self.wasm_relaxed_simd {
+            options.features_mut().simd = true;
+            options.features_mut().relaxed_simd = true;
+        }
+        #[cfg(feature = "compiler")]
+        if self.wasm_exception_handling {
+            options.features_mut().exceptions = true;
+        }
+        #[cfg(feature = "compiler")]
+        if self.wasm_tail_call {
+            options.features_mut().tail_call = true;
+        }
+        #[cfg(feature = "compiler")]
+        if self.wasm_extended_const {
+            options.features_mut().extended_const = true;
+        }
+        #[cfg(feature = "compiler")]
+        if self.coverage_out.is_some() {
+            options.push_middleware(Arc::new(wasmer_middlewares::Coverage::new()));
+        }
+
+        options
+    }
+
+    /// Where wasmer's own log messages go, and what they look like.
+    pub(crate) fn log_options(&self) -> &LogOptions {
+        &self.log
+    }
+
+    /// Get any extra [`Layer`]s that need to be registered when logging is
+    /// initialized.
+    pub(crate) fn tracing_layer(
+        &self,
+    ) -> Result<Option<impl Layer<Registry> + Send + Sync>, Error> {
+        let strace = match &self.strace_output {
+            Some(path) => StraceLayer::create(path)?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(strace.with_filter(filter_fn(StraceLayer::is_traced))))
+    }
+
     #[tracing::instrument(skip_all)]
     fn execute_wasm(
         &self,
@@ -144,11 +686,21 @@ impl Run {
         runtime: Arc<dyn Runtime + Send + Sync>,
     ) -> Result<(), Error> {
         if wasmer_emscripten::is_emscripten_module(module) {
-            self.execute_emscripten_module()
+            self.execute_emscripten_module(path, module, store)
+        } else if let [_, sidecar] = self.pipe_modules.as_slice() {
+            self.execute_piped_modules(path, module, sidecar, runtime, store)
+        } else if self.reuse_instance {
+            self.execute_reused_instance(path, module, runtime, store)
+        } else if let Some(count) = self.instance_count {
+            self.execute_wasi_instances(path, module, runtime, store.engine(), count)
         } else if wasmer_wasix::is_wasi_module(module) || wasmer_wasix::is_wasix_module(module) {
-            self.execute_wasi_module(path, module, runtime, store)
+            if self.export_snapshot.is_some() || self.coverage_out.is_some() {
+                self.execute_wasi_module_inline(path, module, runtime, store)
+            } else {
+                self.execute_wasi_module(path, module, runtime, store)
+            }
         } else {
-            self.execute_pure_wasm_module(module, &mut store)
+            self.execute_pure_wasm_module(path, module, &mut store)
         }
     }
 
@@ -158,6 +710,39 @@ impl Run {
         pkg: &BinaryPackage,
         runtime: Arc<dyn Runtime + Send + Sync>,
     ) -> Result<(), Error> {
+        if self.reuse_instance {
+            anyhow::bail!("--reuse-instance is only supported when running *.wasm files");
+        }
+        if self.exit_on_first_write {
+            anyhow::bail!("--exit-on-first-write is only supported when running *.wasm files");
+        }
+        if self.instance_count.is_some() {
+            anyhow::bail!("--instance-count is only supported when running *.wasm files");
+        }
+        if !self.preload_modules.is_empty() {
+            anyhow::bail!("--preload-module is only supported when running *.wasm files");
+        }
+        if self.type_reflection {
+            anyhow::bail!("--type-reflection is only supported when running *.wasm files");
+        }
+        if self.export_snapshot.is_some() {
+            anyhow::bail!("--export-snapshot is only supported when running *.wasm files");
+        }
+        if self.coverage_out.is_some() {
+            anyhow::bail!("--coverage-out is only supported when running *.wasm files");
+        }
+        if !self.pipe_modules.is_empty() {
+            anyhow::bail!("--pipe-modules is only supported when running *.wasm files");
+        }
+        if self.inject_module_name.is_some() {
+            anyhow::bail!("--inject-module-name is only supported when running *.wasm files");
+        }
+        if self.input_format != StdioFormat::Raw || self.output_format != StdioFormat::Raw {
+            anyhow::bail!(
+                "--input-format and --output-format are only supported when running *.wasm files"
+            );
+        }
+
         let id = match self.entrypoint.as_deref() {
             Some(cmd) => cmd,
             None => infer_webc_entrypoint(pkg)?,
@@ -214,6 +799,15 @@ impl Run {
         if self.wasi.forward_host_env {
             runner.set_forward_host_env();
         }
+        if self.wasi.stdin_null {
+            runner.set_stdin_null();
+        }
+        if let Some(file) = self.wasi.open_tee()? {
+            runner.set_stdout_tee(file);
+        }
+        if let Some((limit, mode)) = self.wasi.stdout_limit() {
+            runner.set_stdout_limit(limit, mode);
+        }
 
         *runner.capabilities() = self.wasi.capabilities();
 
@@ -253,13 +847,19 @@ impl Run {
     ) -> Result<(), Error> {
         let mut runner = wasmer_wasix::runners::emscripten::EmscriptenRunner::new();
         runner.set_args(self.args.clone());
+        runner.set_pid_namespace(self.pid_namespace);
 
         runner.run_command(command_name, pkg, runtime)
     }
 
     #[tracing::instrument(skip_all)]
-    fn execute_pure_wasm_module(&self, module: &Module, store: &mut Store) -> Result<(), Error> {
-        let imports = Imports::default();
+    fn execute_pure_wasm_module(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        store: &mut Store,
+    ) -> Result<(), Error> {
+        let imports = self.link(store, module)?;
         let instance = Instance::new(store, module, &imports)
             .context("Unable to instantiate the WebAssembly module")?;
 
@@ -275,16 +875,77 @@ impl Run {
             }
         };
 
-        let return_values = invoke_function(&instance, store, entrypoint, &self.args)?;
+        let result = invoke_function(&instance, store, entrypoint, &self.args);
+        self.save_coverage(wasm_path, &instance, store)?;
+        let return_values = result?;
 
-        println!(
-            "{}",
-            return_values
-                .iter()
-                .map(|val| val.to_string())
-                .collect::<Vec<String>>()
-                .join(" ")
-        );
+        println!("{}", format_values(&return_values));
+
+        if let Some(path) = &self.export_snapshot {
+            ExportSnapshot::capture(&instance, store).save(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Instantiate the module once, then call the handler function for each
+    /// line of stdin.
+    #[tracing::instrument(skip_all)]
+    fn execute_reused_instance(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        mut store: Store,
+    ) -> Result<(), Error> {
+        let preloaded = self.link(&mut store, module)?;
+        let (instance, _wasi_env) =
+            if wasmer_wasix::is_wasi_module(module) || wasmer_wasix::is_wasix_module(module) {
+                let program_name = self.program_name(wasm_path);
+                let (wasi_env, instance) = self.wasi.instantiate(
+                    module,
+                    program_name,
+                    self.args.clone(),
+                    runtime,
+                    &preloaded,
+                    &mut store,
+                )?;
+                (instance, Some(wasi_env))
+            } else {
+                let instance = Instance::new(&mut store, module, &preloaded)
+                    .context("Unable to instantiate the WebAssembly module")?;
+                (instance, None)
+            };
+
+        let handler = instance
+            .exports
+            .get_function(&self.handler_fn)
+            .with_context(|| {
+                format!(
+                    "The module doesn't contain a \"{}\" function",
+                    self.handler_fn
+                )
+            })?;
+
+        let snapshot = if self.reset_memory_between_calls {
+            Some(MemorySnapshot::capture(&instance, &store)?)
+        } else {
+            None
+        };
+
+        let mut stdout = std::io::stdout();
+        for line in std::io::stdin().lock().lines() {
+            let line = line.context("Unable to read from stdin")?;
+            if let Some(snapshot) = &snapshot {
+                snapshot.restore(&store)?;
+            }
+
+            let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+            let return_values = invoke_function(&instance, &mut store, handler, &args)?;
+
+            writeln!(stdout, "{}", format_values(&return_values))?;
+            stdout.flush()?;
+        }
 
         Ok(())
     }
@@ -295,29 +956,305 @@ impl Run {
         wasm_path: &Path,
         module: &Module,
         runtime: Arc<dyn Runtime + Send + Sync>,
-        store: Store,
+        mut store: Store,
     ) -> Result<(), Error> {
-        let program_name = wasm_path.display().to_string();
+        let program_name = self.program_name(wasm_path);
+        let preloaded = self.link(&mut store, module)?;
 
-        let builder = self
+        let mut builder = self
             .wasi
             .prepare(module, program_name, self.args.clone(), runtime)?;
+        builder.add_imports(&preloaded);
+        let transcoders = Transcoders::attach(&mut builder, self.input_format, self.output_format);
+        let watcher = self
+            .exit_on_first_write
+            .then(|| FirstWriteWatcher::attach(&mut builder));
+
+        let result = builder.run_with_store_async(module.clone(), store);
+        if let Some(watcher) = watcher {
+            // Exits if the module wrote anything before it finished
+            watcher.finish();
+        }
+        transcoders.finish()?;
+        result?;
+
+        Ok(())
+    }
+
+    /// Run a WASI module on this thread rather than a dedicated one, so its
+    /// instance is still around once `_start` returns, to take a snapshot of
+    /// or read the coverage from.
+    #[tracing::instrument(skip_all)]
+    fn execute_wasi_module_inline(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        mut store: Store,
+    ) -> Result<(), Error> {
+        if self.input_format != StdioFormat::Raw || self.output_format != StdioFormat::Raw {
+            anyhow::bail!(
+                "--input-format and --output-format can't be used with --export-snapshot or --coverage-out"
+            );
+        }
+
+        let preloaded = self.link(&mut store, module)?;
+        let (wasi_env, instance) = self.wasi.instantiate(
+            module,
+            self.program_name(wasm_path),
+            self.args.clone(),
+            runtime,
+            &preloaded,
+            &mut store,
+        )?;
+        let start = instance
+            .exports
+            .get_function("_start")
+            .context("The module doesn't contain a \"_start\" function")?;
+
+        wasi_env.data(&store).thread.set_status_running();
+        let result =
+            start
+                .call(&mut store, &[])
+                .map(|_| ())
+                .map_err(|e| match e.downcast::<WasiError>() {
+                    Ok(e) => Error::from(e),
+                    Err(e) => Error::from(e),
+                });
+        let exit_code = match &result {
+            Ok(()) => Some(0.into()),
+            Err(e) => e.chain().find_map(get_exit_code),
+        };
+
+        // Exiting with 0 is how some programs finish, rather than returning
+        if let Some(snapshot_path) = &self.export_snapshot {
+            if exit_code.map_or(false, |code| code.is_success()) {
+                ExportSnapshot::capture(&instance, &mut store).save(snapshot_path)?;
+            }
+        }
+        // Coverage is written however the module exited
+        self.save_coverage(wasm_path, &instance, &mut store)?;
+        wasi_env.cleanup(&mut store, exit_code);
+
+        result
+    }
+
+    /// Run `count` instances of a WASI module at the same time, failing if
+    /// any of them fails.
+    #[tracing::instrument(skip_all)]
+    fn execute_wasi_instances(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        engine: &Engine,
+        count: u32,
+    ) -> Result<(), Error> {
+        if !wasmer_wasix::is_wasi_module(module) && !wasmer_wasix::is_wasix_module(module) {
+            anyhow::bail!("--instance-count is only supported for WASI modules");
+        }
+        if self.input_format != StdioFormat::Raw || self.output_format != StdioFormat::Raw {
+            anyhow::bail!("--input-format and --output-format can't be used with --instance-count");
+        }
+        let output_dir = self
+            .output_dir
+            .as_deref()
+            .map(OutputDir::create)
+            .transpose()?;
+
+        // Everything is set up front, so a bad flag fails before anything runs
+        let mut instances = Vec::new();
+        for index in 0..count as usize {
+            let mut store = Store::new(engine.clone());
+            let preloaded = self.link(&mut store, module)?;
+            let mut builder = self.wasi.prepare(
+                module,
+                self.program_name(wasm_path),
+                self.args.clone(),
+                Arc::clone(&runtime),
+            )?;
+            builder.add_imports(&preloaded);
+            builder.set_stdin(Box::<virtual_fs::NullFile>::default());
+            if let Some(dir) = &output_dir {
+                let (stdout, stderr) = dir.stdio(index)?;
+                builder.set_stdout(stdout);
+                builder.set_stderr(stderr);
+            }
+            instances.push((builder, store));
+        }
+
+        let outcomes: Vec<InstanceOutcome> = std::thread::scope(|scope| {
+            let handles: Vec<_> = instances
+                .into_iter()
+                .enumerate()
+                .map(|(index, (builder, store))| {
+                    let output_dir = output_dir.as_ref();
+                    scope.spawn(move || {
+                        let result = builder
+                            .run_with_store_async(module.clone(), store)
+                            .map_err(Error::from);
+                        let outcome = InstanceOutcome::new(index, &result);
+                        if let Some(dir) = output_dir {
+                            if let Err(e) = dir.write_exit_code(&outcome) {
+                                tracing::warn!(
+                                    error = &*e as &dyn std::error::Error,
+                                    index,
+                                    "Unable to record the instance's exit code",
+                                );
+                            }
+                        }
+                        outcome
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .enumerate()
+                .map(|(index, handle)| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| InstanceOutcome::panicked(index))
+                })
+                .collect()
+        });
+
+        if let Some(dir) = &output_dir {
+            dir.write_summary(&outcomes)?;
+        }
+
+        let failed: Vec<_> = outcomes.iter().filter(|o| !o.succeeded()).collect();
+        match failed.as_slice() {
+            [] => Ok(()),
+            [first, ..] => Err(anyhow::anyhow!(
+                "{} of the {count} instances failed, starting with instance {} (exit code {})",
+                failed.len(),
+                first.index,
+                first.exit_code,
+            )),
+        }
+    }
+
+    /// Run the main module and the sidecar given with `--pipe-modules` side
+    /// by side, with a pipe between them.
+    #[tracing::instrument(skip_all)]
+    fn execute_piped_modules(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        sidecar_path: &Path,
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        mut store: Store,
+    ) -> Result<(), Error> {
+        if self.input_format != StdioFormat::Raw || self.output_format != StdioFormat::Raw {
+            anyhow::bail!("--input-format and --output-format can't be used with --pipe-modules");
+        }
+        let fds = PipeFds::from_args(&self.pipe_fd)?;
+
+        if !wasmer_wasix::is_wasi_module(module) && !wasmer_wasix::is_wasix_module(module) {
+            anyhow::bail!("--pipe-modules is only supported for WASI modules");
+        }
+
+        let mut sidecar_store = Store::new(store.engine().clone());
+        let sidecar_module = Module::from_file(&sidecar_store, sidecar_path)
+            .with_context(|| format!("Unable to load \"{}\"", sidecar_path.display()))?;
+        if !wasmer_wasix::is_wasi_module(&sidecar_module)
+            && !wasmer_wasix::is_wasix_module(&sidecar_module)
+        {
+            anyhow::bail!(
+                "The sidecar, \"{}\", isn't a WASI module",
+                sidecar_path.display()
+            );
+        }
+
+        let preloaded = self.link(&mut store, module)?;
+        let mut builder = self.wasi.prepare(
+            module,
+            self.program_name(wasm_path),
+            self.args.clone(),
+            Arc::clone(&runtime),
+        )?;
+        builder.add_imports(&preloaded);
+        let main = PipedModule {
+            builder,
+            module: module.clone(),
+            store,
+        };
+
+        let preloaded = self.link(&mut sidecar_store, &sidecar_module)?;
+        let mut builder = self.wasi.prepare(
+            &sidecar_module,
+            sidecar_path.display().to_string(),
+            Vec::new(),
+            runtime,
+        )?;
+        builder.add_imports(&preloaded);
+        // Only the main module gets to read stdin
+        builder.set_stdin(Box::<virtual_fs::NullFile>::default());
+        let sidecar = PipedModule {
+            builder,
+            module: sidecar_module,
+            store: sidecar_store,
+        };
 
-        builder.run_with_store_async(module.clone(), store)?;
+        pipe_modules::run(main, sidecar, fds)
+    }
 
+    /// Write the parts of the module which ran in `instance` to
+    /// --coverage-out, if it was given.
+    fn save_coverage(
+        &self,
+        wasm_path: &Path,
+        instance: &Instance,
+        store: &mut Store,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "compiler")]
+        if let Some(path) = &self.coverage_out {
+            let debug_info =
+                load_debug_info(wasm_path, self.wat, "--coverage-out")?.unwrap_or_default();
+            let module_name = wasm_path.display().to_string();
+            coverage::save(instance, store, &debug_info, &module_name, path)?;
+        }
         Ok(())
     }
 
+    /// The imports from the preloaded modules, and the type reflection
+    /// functions the module imports.
+    fn link(&self, store: &mut Store, module: &Module) -> Result<Imports, Error> {
+        let mut imports = preload::link(store, &self.preload_modules, module)?;
+        if self.type_reflection {
+            type_reflection::define_imports(store, module, &mut imports);
+        }
+        Ok(imports)
+    }
+
+    /// The name the module is run as, i.e. its `argv[0]`.
+    fn program_name(&self, wasm_path: &Path) -> String {
+        match &self.inject_module_name {
+            Some(name) => name.clone(),
+            None => wasm_path.display().to_string(),
+        }
+    }
+
     #[tracing::instrument(skip_all)]
-    fn execute_emscripten_module(&self) -> Result<(), Error> {
-        anyhow::bail!("Emscripten packages are not currently supported")
+    fn execute_emscripten_module(
+        &self,
+        wasm_path: &Path,
+        module: &Module,
+        store: Store,
+    ) -> Result<(), Error> {
+        let mut runner = EmscriptenRunner::new();
+        runner.set_args(self.args.clone());
+        runner.set_pid_namespace(self.pid_namespace);
+
+        runner.run_module(&self.program_name(wasm_path), module, store)
     }
 
     #[allow(unused_variables)]
     fn maybe_save_coredump(&self, e: &Error) {
         #[cfg(feature = "coredump")]
         if let Some(coredump) = &self.coredump_on_trap {
-            if let Err(e) = generate_coredump(e, self.input.to_string(), coredump) {
+            if let Err(e) = generate_coredump(e, self.input().to_string(), coredump) {
                 tracing::warn!(
                     error = &*e as &dyn std::error::Error,
                     coredump_path=%coredump.display(),
@@ -355,15 +1292,114 @@ impl Run {
             store,
             wasi: Wasi::for_binfmt_interpreter()?,
             wcgi: WcgiOptions::default(),
+            env_json: None,
             stack_size: None,
+            #[cfg(feature = "sys")]
+            mem_protect_stack: false,
             entrypoint: Some(original_executable.to_string()),
             coredump_on_trap: None,
-            input: PackageSource::infer(executable)?,
+            timeout: None,
+            wasm_simd: false,
+            wasm_relaxed_simd: false,
+            wasm_exception_handling: false,
+            wasm_tail_call: false,
+            wasm_extended_const: false,
+            type_reflection: false,
+            debug_info: false,
+            wat: false,
+            stdin_wat: false,
+            wat_source_map: false,
+            coverage_out: None,
+            check_signatures: false,
+            trusted_key: None,
+            #[cfg(feature = "sys")]
+            profile: None,
+            #[cfg(feature = "sys")]
+            perfmap: false,
+            perf_counters: false,
+            strace_output: None,
+            log: LogOptions::default(),
+            reuse_instance: false,
+            handler_fn: "handle".to_string(),
+            reset_memory_between_calls: false,
+            input_format: StdioFormat::Raw,
+            output_format: StdioFormat::Raw,
+            exit_on_first_write: false,
+            oci_runtime: None,
+            #[cfg(target_os = "linux")]
+            network_namespace: None,
+            pid_namespace: false,
+            preload_modules: Vec::new(),
+            inject_module_name: None,
+            cache_on_success_only: false,
+            instance_count: None,
+            output_dir: None,
+            export_snapshot: None,
+            pipe_modules: Vec::new(),
+            pipe_fd: Vec::new(),
+            read_config: None,
+            input: Some(PackageSource::infer(executable)?),
             args: args.to_vec(),
         })
     }
 }
 
+/// Interrupts the module once `timeout` has elapsed, or when Ctrl-C is
+/// pressed. Ctrl-C exits straight away when the module isn't running, or
+/// when it is pressed a second time.
+async fn watch_for_interrupts(interrupt: InterruptHandle, timeout: Option<Duration>) {
+    let timeout = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        _ = timeout => {
+            interrupt.interrupt();
+        }
+        Ok(()) = tokio::signal::ctrl_c() => {
+            interrupt.interrupt();
+            if !interrupt.is_interrupted() || tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    }
+}
+
+fn is_interrupted(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<RuntimeError>()
+        .map_or(false, RuntimeError::is_interrupted)
+}
+
+/// Parse the environment variables passed to `--env-json`, reading them from
+/// a file if the argument starts with `@`.
+fn parse_env_json(json: &str) -> Result<Vec<(String, String)>, Error> {
+    let (json, source) = match json.strip_prefix('@') {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read \"{path}\""))?;
+            (json, format!("\"{path}\""))
+        }
+        None => (json.to_string(), "--env-json".to_string()),
+    };
+
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json)
+        .with_context(|| format!("{source} doesn't contain a JSON object"))?;
+
+    object
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => Ok((key, value)),
+            other => Err(anyhow::anyhow!(
+                "The \"{key}\" environment variable in {source} must be a string, but found {other}"
+            )),
+        })
+        .collect()
+}
+
 fn invoke_function(
     instance: &Instance,
     store: &mut Store,
@@ -395,6 +1431,59 @@ fn invoke_function(
     Ok(return_values)
 }
 
+fn format_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|val| val.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The contents of an instance's exported memories right after it was
+/// instantiated, used by `--reset-memory-between-calls`.
+struct MemorySnapshot {
+    memories: Vec<(Memory, Vec<u8>)>,
+}
+
+impl MemorySnapshot {
+    fn capture(instance: &Instance, store: &Store) -> Result<Self, Error> {
+        let memories = instance
+            .exports
+            .iter()
+            .memories()
+            .map(|(name, memory)| {
+                let contents = memory
+                    .view(store)
+                    .copy_to_vec()
+                    .with_context(|| format!("Unable to read the \"{name}\" memory"))?;
+                Ok((memory.clone(), contents))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        if memories.is_empty() {
+            anyhow::bail!("--reset-memory-between-calls requires the module to export its memory");
+        }
+
+        Ok(MemorySnapshot { memories })
+    }
+
+    fn restore(&self, store: &Store) -> Result<(), Error> {
+        for (memory, contents) in &self.memories {
+            let view = memory.view(store);
+            view.write(0, contents)?;
+
+            // Memories can't shrink, so clear anything the module grew into
+            let initial_size = contents.len() as u64;
+            if view.data_size() > initial_size {
+                let grown = (view.data_size() - initial_size) as usize;
+                view.write(initial_size, &vec![0; grown])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 fn parse_value(s: &str, ty: wasmer_types::Type) -> Result<Value, Error> {
     let value = match ty {
         Type::I32 => Value::I32(s.parse()?),
@@ -407,6 +1496,182 @@ fn parse_value(s: &str, ty: wasmer_types::Type) -> Result<Value, Error> {
     Ok(value)
 }
 
+/// Print a warning if the WebAssembly module at `path` doesn't use any SIMD
+/// instructions, because it probably wasn't compiled with `-msimd128`.
+#[cfg(feature = "compiler")]
+fn warn_if_no_simd_instructions(path: &Path, force_wat: bool) -> Result<(), Error> {
+    let wasm = match TargetOnDisk::from_file(path, force_wat)? {
+        TargetOnDisk::WebAssemblyBinary => std::fs::read(path)?,
+        #[cfg(feature = "wat")]
+        TargetOnDisk::Wat => wasmer::wat2wasm(&std::fs::read(path)?)?.into_owned(),
+        _ => {
+            tracing::debug!(
+                path=%path.display(),
+                "Unable to check a pre-compiled module for SIMD instructions",
+            );
+            return Ok(());
+        }
+    };
+
+    if !contains_simd_instructions(&wasm)
+        .with_context(|| format!("Unable to validate \"{}\"", path.display()))?
+    {
+        crate::warning!(
+            "--wasm-simd was specified, but \"{}\" doesn't contain any SIMD instructions. Was it compiled with -msimd128?",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Load the DWARF debug info for the WebAssembly module at `path`, printing a
+/// warning mentioning `flag` if there is none.
+#[cfg(feature = "compiler")]
+fn load_debug_info(
+    path: &Path,
+    force_wat: bool,
+    flag: &str,
+) -> Result<Option<debug_info::DebugInfo>, Error> {
+    let wasm = match TargetOnDisk::from_file(path, force_wat)? {
+        TargetOnDisk::WebAssemblyBinary => std::fs::read(path)?,
+        #[cfg(feature = "wat")]
+        TargetOnDisk::Wat => wasmer::wat2wasm(&std::fs::read(path)?)?.into_owned(),
+        _ => {
+            crate::warning!(
+                "{flag} was specified, but debug info can't be loaded from pre-compiled modules"
+            );
+            return Ok(None);
+        }
+    };
+
+    let debug_info = debug_info::DebugInfo::parse(&wasm)
+        .with_context(|| format!("Unable to parse the debug info in \"{}\"", path.display()))?;
+
+    if debug_info.is_empty() {
+        crate::warning!(
+            "{flag} was specified, but \"{}\" doesn't contain any DWARF line information. Was it compiled with -g?",
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(debug_info))
+}
+
+/// Check whether a module uses the exception handling proposal, i.e. it
+/// declares tags or uses any of the `try`/`catch`/`throw` instructions.
+#[cfg(feature = "compiler")]
+fn uses_exception_handling(wasm: &[u8]) -> Result<bool, Error> {
+    use wasmer_compiler::wasmparser::{Operator, Parser, Payload};
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::TagSection(_) => return Ok(true),
+            Payload::CodeSectionEntry(body) => {
+                let mut operators = body.get_operators_reader()?;
+                while !operators.eof() {
+                    if matches!(
+                        operators.read()?,
+                        Operator::Try { .. }
+                            | Operator::Catch { .. }
+                            | Operator::Throw { .. }
+                            | Operator::Rethrow { .. }
+                            | Operator::Delegate { .. }
+                            | Operator::CatchAll
+                    ) {
+                        return Ok(true);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(false)
+}
+
+/// Check whether a module uses the extended constant expressions proposal,
+/// i.e. the initializer of a global or the offset of a segment is more than
+/// a single instruction.
+#[cfg(feature = "compiler")]
+fn uses_extended_const(wasm: &[u8]) -> Result<bool, Error> {
+    use wasmer_compiler::wasmparser::{
+        ConstExpr, DataKind, ElementKind, Operator, Parser, Payload,
+    };
+
+    fn is_extended(expr: &ConstExpr<'_>) -> Result<bool, Error> {
+        let mut reader = expr.get_binary_reader();
+        reader.read_operator()?;
+        Ok(!matches!(reader.read_operator()?, Operator::End))
+    }
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::GlobalSection(globals) => {
+                for global in globals {
+                    if is_extended(&global?.init_expr)? {
+                        return Ok(true);
+                    }
+                }
+            }
+            Payload::ElementSection(elements) => {
+                for element in elements {
+                    if let ElementKind::Active { offset_expr, .. } = element?.kind {
+                        if is_extended(&offset_expr)? {
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+            Payload::DataSection(data) => {
+                for data in data {
+                    if let DataKind::Active { offset_expr, .. } = data?.kind {
+                        if is_extended(&offset_expr)? {
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(false)
+}
+
+/// Whether the relaxed SIMD instructions are lowered to native ones on this
+/// machine, rather than to their deterministic fallbacks.
+#[cfg(feature = "compiler")]
+fn has_native_relaxed_simd() -> bool {
+    use wasmer::CpuFeature;
+
+    cfg!(target_arch = "x86_64") && CpuFeature::for_host().contains(CpuFeature::FMA)
+}
+
+/// Scan a WebAssembly binary's function bodies for SIMD instructions.
+#[cfg(feature = "compiler")]
+fn contains_simd_instructions(wasm: &[u8]) -> Result<bool, Error> {
+    use wasmer_compiler::wasmparser::{Parser, Payload};
+
+    /// The prefix byte shared by all instructions in the SIMD proposal.
+    const SIMD_PREFIX: u8 = 0xfd;
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CodeSectionEntry(body) = payload? {
+            let mut operators = body.get_operators_reader()?;
+            while !operators.eof() {
+                let (_, offset) = operators.read_with_offset()?;
+                if wasm.get(offset) == Some(&SIMD_PREFIX) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    Ok(false)
+}
+
 fn infer_webc_entrypoint(pkg: &BinaryPackage) -> Result<&str, Error> {
     if let Some(entrypoint) = pkg.entrypoint_cmd.as_deref() {
         return Ok(entrypoint);
@@ -426,6 +1691,16 @@ fn infer_webc_entrypoint(pkg: &BinaryPackage) -> Result<&str, Error> {
     }
 }
 
+/// The formats `wasmer run --profile` can write the JIT-compiled functions'
+/// symbols in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+enum ProfileFormat {
+    /// A perf map, with each function's address, size and name
+    Perfmap,
+    /// A jitdump, which has each function's machine code too
+    Jitdump,
+}
+
 /// The input that was passed in via the command-line.
 #[derive(Debug, Clone, PartialEq)]
 enum PackageSource {
@@ -435,6 +1710,8 @@ enum PackageSource {
     Dir(PathBuf),
     /// A package to be downloaded (a URL, package name, etc.)
     Package(PackageSpecifier),
+    /// WebAssembly text read from stdin (`--stdin-wat`).
+    Stdin,
 }
 
 impl PackageSource {
@@ -463,9 +1740,14 @@ impl PackageSource {
         &self,
         rt: &dyn Runtime,
         pb: &ProgressBar,
+        force_wat: bool,
+        wat_source_map: bool,
+        verbose: bool,
     ) -> Result<ExecutableTarget, Error> {
         match self {
-            PackageSource::File(path) => ExecutableTarget::from_file(path, rt, pb),
+            PackageSource::File(path) => {
+                ExecutableTarget::from_file(path, rt, pb, force_wat, wat_source_map, verbose)
+            }
             PackageSource::Dir(d) => ExecutableTarget::from_dir(d, rt, pb),
             PackageSource::Package(pkg) => {
                 pb.set_message("Loading from the registry");
@@ -474,6 +1756,9 @@ impl PackageSource {
                     .block_on(BinaryPackage::from_registry(pkg, rt))?;
                 Ok(ExecutableTarget::Package(pkg))
             }
+            PackageSource::Stdin => {
+                ExecutableTarget::from_stdin_wat(rt, pb, wat_source_map, verbose)
+            }
         }
     }
 }
@@ -483,6 +1768,7 @@ impl Display for PackageSource {
         match self {
             PackageSource::File(path) | PackageSource::Dir(path) => write!(f, "{}", path.display()),
             PackageSource::Package(p) => write!(f, "{p}"),
+            PackageSource::Stdin => write!(f, "<stdin>"),
         }
     }
 }
@@ -498,7 +1784,11 @@ enum TargetOnDisk {
 }
 
 impl TargetOnDisk {
-    fn from_file(path: &Path) -> Result<TargetOnDisk, Error> {
+    fn from_file(path: &Path, force_wat: bool) -> Result<TargetOnDisk, Error> {
+        if force_wat {
+            return Ok(TargetOnDisk::Wat);
+        }
+
         // Normally the first couple hundred bytes is enough to figure
         // out what type of file this is.
         let mut buffer = [0_u8; 512];
@@ -522,6 +1812,11 @@ impl TargetOnDisk {
             return Ok(TargetOnDisk::Artifact);
         }
 
+        // The text format always starts with an s-expression
+        if leading_bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'(') {
+            return Ok(TargetOnDisk::Wat);
+        }
+
         // If we can't figure out the file type based on its content, fall back
         // to checking the extension.
 
@@ -558,42 +1853,48 @@ impl ExecutableTarget {
         Ok(ExecutableTarget::Package(pkg))
     }
 
+    /// Read WebAssembly text from stdin and compile it.
+    #[tracing::instrument(skip_all)]
+    fn from_stdin_wat(
+        runtime: &dyn Runtime,
+        pb: &ProgressBar,
+        wat_source_map: bool,
+        verbose: bool,
+    ) -> Result<Self, Error> {
+        pb.set_message("Reading WebAssembly text from stdin");
+
+        let path = PathBuf::from("<stdin>");
+        let mut wat = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut wat)
+            .context("Unable to read from stdin")?;
+        let (wasm, source_map) = wat_to_wasm(&path, &wat, wat_source_map)?;
+        let module = compile_wasm(&wasm, source_map, &path, runtime, pb, verbose)?;
+
+        Ok(ExecutableTarget::WebAssembly { module, path })
+    }
+
     /// Try to load a file into something that can be used to run it.
     #[tracing::instrument(skip_all)]
-    fn from_file(path: &Path, runtime: &dyn Runtime, pb: &ProgressBar) -> Result<Self, Error> {
+    fn from_file(
+        path: &Path,
+        runtime: &dyn Runtime,
+        pb: &ProgressBar,
+        force_wat: bool,
+        wat_source_map: bool,
+        verbose: bool,
+    ) -> Result<Self, Error> {
         pb.set_message(format!("Loading from \"{}\"", path.display()));
 
-        match TargetOnDisk::from_file(path)? {
+        let target = TargetOnDisk::from_file(path, force_wat)?;
+        match target {
             TargetOnDisk::WebAssemblyBinary | TargetOnDisk::Wat => {
                 let wasm = std::fs::read(path)?;
-                let engine = runtime.engine().context("No engine available")?;
-                pb.set_message("Compiling to WebAssembly");
-
-                let tasks = runtime.task_manager();
-                let module_cache = runtime.module_cache();
-                let module_hash = ModuleHash::sha256(&wasm);
-
-                let module = match tasks.block_on(module_cache.load(module_hash, &engine)) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        if !matches!(e, CacheError::NotFound) {
-                            tracing::warn!(
-                                module.path=%path.display(),
-                                module.hash=%module_hash,
-                                error=&e as &dyn std::error::Error,
-                                "Unable to deserialize the pre-compiled module from the module cache",
-                            );
-                        }
-
-                        let module = tracing::debug_span!("compiling_wasm")
-                            .in_scope(|| Module::new(&engine, &wasm))
-                            .with_context(|| format!("Unable to compile \"{}\"", path.display()))?;
-
-                        tasks.block_on(module_cache.save(module_hash, &engine, &module))?;
-
-                        module
-                    }
+                let (wasm, source_map) = match target {
+                    TargetOnDisk::Wat => wat_to_wasm(path, &wasm, wat_source_map)?,
+                    _ => (wasm, None),
                 };
+                let module = compile_wasm(&wasm, source_map, path, runtime, pb, verbose)?;
 
                 Ok(ExecutableTarget::WebAssembly {
                     module,
@@ -604,6 +1905,7 @@ impl ExecutableTarget {
                 let engine = runtime.engine().context("No engine available")?;
                 pb.set_message("Deserializing pre-compiled WebAssembly module");
                 let module = unsafe { Module::deserialize_from_file(&engine, path)? };
+                let module = with_default_name(module, path);
 
                 Ok(ExecutableTarget::WebAssembly {
                     module,
@@ -622,6 +1924,169 @@ impl ExecutableTarget {
     }
 }
 
+/// Compile a WebAssembly module, reusing a pre-compiled version from the
+/// module cache when possible.
+///
+/// If the module was converted from text, `source_map` points validation
+/// errors and traps back at the text. With `verbose`, the WAT around the
+/// instructions functions failed to compile at is printed.
+fn compile_wasm(
+    wasm: &[u8],
+    source_map: Option<WatSourceMap>,
+    path: &Path,
+    runtime: &dyn Runtime,
+    pb: &ProgressBar,
+    verbose: bool,
+) -> Result<Module, Error> {
+    let engine = runtime.engine().context("No engine available")?;
+    pb.set_message("Compiling to WebAssembly");
+
+    let tasks = runtime.task_manager();
+    let module_cache = runtime.module_cache();
+    let module_hash = ModuleHash::sha256(wasm);
+
+    let module = match tasks.block_on(module_cache.load(module_hash, &engine)) {
+        Ok(m) => m,
+        Err(e) => {
+            if !matches!(e, CacheError::NotFound) {
+                tracing::warn!(
+                    module.path=%path.display(),
+                    module.hash=%module_hash,
+                    error=&e as &dyn std::error::Error,
+                    "Unable to deserialize the pre-compiled module from the module cache",
+                );
+            }
+
+            let result = tracing::debug_span!("compiling_wasm")
+                .in_scope(|| Module::new(&engine, wasm))
+                .with_context(|| format!("Unable to compile \"{}\"", path.display()));
+
+            #[cfg(feature = "compiler")]
+            let result = match result {
+                Err(e) if matches!(uses_exception_handling(wasm), Ok(true)) => Err(e.context(
+                    "The module uses the exception handling proposal, which needs \
+                         --wasm-exception-handling and the Cranelift compiler",
+                )),
+                Err(e)
+                    if !engine.features().extended_const
+                        && matches!(uses_extended_const(wasm), Ok(true)) =>
+                {
+                    Err(e.context(
+                        "The module uses extended constant expressions, which need --extended-const",
+                    ))
+                }
+                result => result,
+            };
+
+            let result = match (result, &source_map) {
+                (Err(e), Some(source_map)) => {
+                    match validation_error_location(wasm, &engine, source_map) {
+                        Some(location) => Err(e.context(format!(
+                            "Invalid WebAssembly at {}:{}",
+                            path.display(),
+                            location
+                        ))),
+                        None => Err(e),
+                    }
+                }
+                (result, _) => result,
+            };
+
+            #[cfg(feature = "compiler")]
+            if let (Err(e), true) = (&result, verbose) {
+                for snippet in compile_error_snippets(e, wasm) {
+                    pb.suspend(|| eprintln!("{snippet}"));
+                }
+            }
+
+            let module = result?;
+
+            tasks.block_on(module_cache.save(module_hash, &engine, &module))?;
+
+            module
+        }
+    };
+
+    let mut module = with_default_name(module, path);
+    if let Some(source_map) = source_map {
+        module.set_source_map(source_map);
+    }
+    Ok(module)
+}
+
+/// Where in the text a module that doesn't validate goes wrong.
+fn validation_error_location(
+    wasm: &[u8],
+    engine: &Engine,
+    source_map: &WatSourceMap,
+) -> Option<WatLocation> {
+    let error =
+        Module::validate_with_features(wasm, &engine.features(), engine.target().triple()).err()?;
+    source_map.lookup(error.offset)
+}
+
+/// The WAT around each instruction a function failed to compile at, headed
+/// by where the instruction is.
+#[cfg(feature = "compiler")]
+fn compile_error_snippets(error: &Error, wasm: &[u8]) -> Vec<String> {
+    let errors = match error
+        .chain()
+        .find_map(|e| e.downcast_ref::<wasmer::CompileError>())
+    {
+        Some(wasmer::CompileError::Multiple(errors)) => errors.iter().collect(),
+        Some(error) => vec![error],
+        None => Vec::new(),
+    };
+
+    errors
+        .into_iter()
+        .filter_map(|error| {
+            let location = error.function_location()?;
+            let wat = wasmer_compiler::wat_around(wasm, location.offset, 3)?;
+            Some(format!("In {location}:\n{wat}"))
+        })
+        .collect()
+}
+
+/// Name the module after the file it came from, unless its name section
+/// already gave it a name, so traps and profiles can tell modules apart.
+fn with_default_name(mut module: Module, path: &Path) -> Module {
+    if module.name().is_none() {
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            module.set_name(name);
+        }
+    }
+    module
+}
+
+/// Convert the WebAssembly text in `wat` to a binary module, along with a
+/// map back to the text if `with_source_map` is set.
+fn wat_to_wasm(
+    path: &Path,
+    wat: &[u8],
+    with_source_map: bool,
+) -> Result<(Vec<u8>, Option<WatSourceMap>), Error> {
+    #[cfg(feature = "wat")]
+    {
+        let context = || format!("Unable to parse \"{}\" as WebAssembly text", path.display());
+        if !with_source_map {
+            let wasm = wasmer::wat2wasm(wat).with_context(context)?;
+            return Ok((wasm.into_owned(), None));
+        }
+        let (wasm, source_map) = wasmer::wat2wasm_with_source_map(wat).with_context(context)?;
+        Ok((wasm.into_owned(), source_map))
+    }
+
+    #[cfg(not(feature = "wat"))]
+    {
+        let _ = (wat, with_source_map);
+        anyhow::bail!(
+            "Unable to run \"{}\" because this build of wasmer doesn't support the WebAssembly text format",
+            path.display()
+        );
+    }
+}
+
 #[tracing::instrument(level = "debug", skip_all)]
 fn construct_webc_in_memory(dir: &Path) -> Result<Vec<u8>, Error> {
     let mut files = BTreeMap::new();
@@ -0,0 +1,113 @@
+//! Transcoding for `--input-format cbor` and `--output-format cbor`, which
+//! exchange CBOR with the host while the guest reads and writes
+//! newline-delimited JSON.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    thread::JoinHandle,
+};
+
+use anyhow::{Context, Error};
+use virtual_fs::Pipe;
+use wasmer_wasix::WasiEnvBuilder;
+
+/// How the host's side of stdin or stdout is encoded.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, clap::ValueEnum)]
+pub(crate) enum StdioFormat {
+    /// Pass the bytes through unchanged
+    #[default]
+    Raw,
+    /// A sequence of CBOR items, which the guest sees as one JSON document
+    /// per line
+    Cbor,
+}
+
+/// The threads transcoding the guest's stdio.
+#[derive(Debug)]
+pub(crate) struct Transcoders {
+    stdout: Option<(Pipe, JoinHandle<Result<(), Error>>)>,
+}
+
+impl Transcoders {
+    /// Replace the guest's stdin and stdout with pipes which are transcoded
+    /// on background threads, as requested by `input` and `output`.
+    pub(crate) fn attach(
+        builder: &mut WasiEnvBuilder,
+        input: StdioFormat,
+        output: StdioFormat,
+    ) -> Self {
+        if input == StdioFormat::Cbor {
+            let (mut host, guest) = Pipe::channel();
+            builder.set_stdin(Box::new(guest));
+            // Reading from stdin can block forever, so this thread is never
+            // joined
+            std::thread::spawn(move || {
+                if let Err(e) = cbor_to_json_lines(std::io::stdin().lock(), &mut host) {
+                    crate::warning!("{e:?}");
+                }
+                host.close();
+            });
+        }
+
+        let stdout = match output {
+            StdioFormat::Raw => None,
+            StdioFormat::Cbor => {
+                let (guest, host) = Pipe::channel();
+                builder.set_stdout(Box::new(guest.clone()));
+                let thread =
+                    std::thread::spawn(move || json_lines_to_cbor(host, std::io::stdout().lock()));
+                Some((guest, thread))
+            }
+        };
+
+        Transcoders { stdout }
+    }
+
+    /// Wait for everything the guest wrote to be transcoded. This must be
+    /// called once the guest has exited.
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        if let Some((guest, thread)) = self.stdout {
+            guest.close();
+            thread
+                .join()
+                .expect("the stdout transcoding thread panicked")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the CBOR items read from `input`, writing each of them to `output`
+/// as a single line of JSON.
+fn cbor_to_json_lines(input: impl Read, mut output: impl Write) -> Result<(), Error> {
+    let mut input = BufReader::new(input);
+
+    while !input.fill_buf()?.is_empty() {
+        let item: ciborium::value::Value = ciborium::de::from_reader(&mut input)
+            .context("Unable to decode a CBOR item from stdin")?;
+        serde_json::to_writer(&mut output, &item)
+            .context("Unable to convert a CBOR item from stdin to JSON")?;
+        output.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Encode each line of JSON read from `input` as a CBOR item on `output`.
+///
+/// Lines which aren't valid JSON are encoded as text strings and blank lines
+/// are skipped.
+fn json_lines_to_cbor(input: impl Read, mut output: impl Write) -> Result<(), Error> {
+    for line in BufReader::new(input).lines() {
+        let line = line.context("Unable to read the module's stdout")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let item = serde_json::from_str(&line).unwrap_or(serde_json::Value::String(line));
+        ciborium::ser::into_writer(&item, &mut output)
+            .context("Unable to encode the module's stdout as CBOR")?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
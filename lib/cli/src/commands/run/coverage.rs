@@ -0,0 +1,117 @@
+//! Support for `wasmer run --coverage-out`, which compiles the module with
+//! the `Coverage` middleware and writes the parts of its code which ran to
+//! an LCOV file once it exits, whether it succeeded or not.
+//!
+//! The blocks of code are mapped back to source lines with the module's
+//! DWARF debug info. Functions without any are only reported by name, at
+//! line 0 of a file named after the module.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Error};
+use wasmer::{Instance, Store};
+use wasmer_middlewares::coverage::CoverageReport;
+
+use super::debug_info::DebugInfo;
+
+/// Write the parts of the module which ran in `instance` to `path`.
+pub(crate) fn save(
+    instance: &Instance,
+    store: &mut Store,
+    debug_info: &DebugInfo,
+    module_name: &str,
+    path: &Path,
+) -> Result<(), Error> {
+    let mut report = CoverageReport::new();
+    report.add_instance(store, instance);
+
+    let file =
+        File::create(path).with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+    let mut out = BufWriter::new(file);
+    write_lcov(&report, debug_info, module_name, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_lcov(
+    report: &CoverageReport,
+    debug_info: &DebugInfo,
+    module_name: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    report.write_lcov(out, module_name, |range| {
+        debug_info
+            .lookup_range(range)
+            .into_iter()
+            .filter_map(|location| Some((location.path.clone(), location.line?)))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer_middlewares::coverage::{CoveredBlock, CoveredFunction};
+
+    use super::*;
+    use crate::commands::run::debug_info::tests::module_with_debug_info;
+
+    #[test]
+    fn blocks_are_mapped_to_source_lines() {
+        let debug_info = DebugInfo::parse(&module_with_debug_info()).unwrap();
+        let mut report = CoverageReport::new();
+        report.functions.insert(
+            1,
+            CoveredFunction {
+                name: Some("main".to_string()),
+                blocks: [
+                    (0x10, CoveredBlock { end: 0x14, hits: 1 }),
+                    (0x14, CoveredBlock { end: 0x18, hits: 1 }),
+                    (0x18, CoveredBlock { end: 0x20, hits: 0 }),
+                ]
+                .into_iter()
+                .collect(),
+            },
+        );
+        // Outside of the line table
+        report.functions.insert(
+            2,
+            CoveredFunction {
+                name: None,
+                blocks: [(0x40, CoveredBlock { end: 0x48, hits: 1 })]
+                    .into_iter()
+                    .collect(),
+            },
+        );
+
+        let mut out = Vec::new();
+        write_lcov(&report, &debug_info, "main.wasm", &mut out).unwrap();
+
+        let path = &debug_info.lookup(0x10).unwrap().path;
+        let expected = format!(
+            "SF:{}\n\
+             FN:7,main\n\
+             FNDA:1,main\n\
+             FNF:1\n\
+             FNH:1\n\
+             DA:7,1\n\
+             DA:9,0\n\
+             LF:2\n\
+             LH:1\n\
+             end_of_record\n\
+             SF:main.wasm\n\
+             FN:0,wasm-function[2]\n\
+             FNDA:1,wasm-function[2]\n\
+             FNF:1\n\
+             FNH:1\n\
+             LF:0\n\
+             LH:0\n\
+             end_of_record\n",
+            path.display()
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
@@ -0,0 +1,334 @@
+//! Use the DWARF debug info embedded in a WebAssembly module to map trap
+//! locations back to the original source code.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    ops::Range,
+    path::PathBuf,
+};
+
+use anyhow::Error;
+use gimli::{Dwarf, EndianSlice, LittleEndian, SectionId};
+use wasmer::{FrameInfo, RuntimeError};
+use wasmer_compiler::wasmparser::{Parser, Payload};
+
+/// A mapping from code offsets to source locations, built from a module's
+/// `.debug_*` custom sections.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DebugInfo {
+    /// The offset of the code section's contents within the module. DWARF
+    /// addresses are relative to this.
+    code_section_offset: u64,
+    /// Line table rows, sorted by address. A `None` location marks the end of
+    /// a sequence.
+    rows: Vec<(u64, Option<SourceLocation>)>,
+}
+
+impl DebugInfo {
+    /// Parse the DWARF debug info from a WebAssembly binary.
+    ///
+    /// Modules without any debug info will give an empty [`DebugInfo`].
+    pub(crate) fn parse(wasm: &[u8]) -> Result<Self, Error> {
+        let mut code_section_offset = 0;
+        let mut sections = HashMap::new();
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::CodeSectionStart { range, .. } => {
+                    code_section_offset = range.start as u64;
+                }
+                Payload::CustomSection(section) if section.name().starts_with(".debug_") => {
+                    sections.insert(section.name(), section.data());
+                }
+                _ => {}
+            }
+        }
+
+        let dwarf = Dwarf::load(|id: SectionId| -> Result<_, gimli::Error> {
+            let data = sections.get(id.name()).copied().unwrap_or_default();
+            Ok(EndianSlice::new(data, LittleEndian))
+        })?;
+
+        let mut rows = Vec::new();
+        let mut units = dwarf.units();
+
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let program = match unit.line_program.clone() {
+                Some(program) => program,
+                None => continue,
+            };
+
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                if row.end_sequence() {
+                    rows.push((row.address(), None));
+                    continue;
+                }
+
+                let file = match row.file(header) {
+                    Some(file) => file,
+                    None => continue,
+                };
+
+                let mut path = PathBuf::new();
+                if let Some(dir) = file.directory(header) {
+                    path.push(dwarf.attr_string(&unit, dir)?.to_string_lossy().as_ref());
+                }
+                path.push(
+                    dwarf
+                        .attr_string(&unit, file.path_name())?
+                        .to_string_lossy()
+                        .as_ref(),
+                );
+
+                let location = SourceLocation {
+                    path,
+                    line: row.line().map(|line| line.get()),
+                    column: match row.column() {
+                        gimli::ColumnType::LeftEdge => None,
+                        gimli::ColumnType::Column(column) => Some(column.get()),
+                    },
+                };
+                rows.push((row.address(), Some(location)));
+            }
+        }
+
+        // Sequences aren't guaranteed to be in order, but sorting is stable so
+        // rows at the same address keep their relative order.
+        rows.sort_by_key(|(address, _)| *address);
+
+        Ok(DebugInfo {
+            code_section_offset,
+            rows,
+        })
+    }
+
+    /// Does the module contain any line information?
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Find the source location for an instruction at `module_offset` bytes
+    /// into the module.
+    pub(crate) fn lookup(&self, module_offset: usize) -> Option<&SourceLocation> {
+        let address = (module_offset as u64).checked_sub(self.code_section_offset)?;
+        let index = self.rows.partition_point(|(a, _)| *a <= address);
+        let (_, location) = self.rows.get(index.checked_sub(1)?)?;
+        location.as_ref()
+    }
+
+    /// Find the source locations of the instructions from `module_offsets.start`
+    /// up to `module_offsets.end` bytes into the module.
+    pub(crate) fn lookup_range(&self, module_offsets: Range<usize>) -> Vec<&SourceLocation> {
+        let start = (module_offsets.start as u64).saturating_sub(self.code_section_offset);
+        let end = (module_offsets.end as u64).saturating_sub(self.code_section_offset);
+        // The row before the first one in the range covers its start
+        let first = self
+            .rows
+            .partition_point(|(a, _)| *a <= start)
+            .saturating_sub(1);
+        self.rows[first..]
+            .iter()
+            .take_while(|(a, _)| *a < end)
+            .filter_map(|(_, location)| location.as_ref())
+            .collect()
+    }
+
+    /// Rewrite a trap's stack trace to include source locations, if `error`
+    /// was caused by a trap.
+    pub(crate) fn symbolicate(&self, error: Error) -> Error {
+        let trap = match error.chain().find_map(|e| e.downcast_ref::<RuntimeError>()) {
+            Some(trap) if !trap.trace().is_empty() => trap,
+            _ => return error,
+        };
+
+        let symbolicated = SymbolicatedTrap {
+            message: trap.message(),
+            frames: trap
+                .trace()
+                .iter()
+                .map(|frame| (frame.clone(), self.lookup(frame.module_offset()).cloned()))
+                .collect(),
+        };
+
+        if error.downcast_ref::<RuntimeError>().is_some() {
+            Error::new(symbolicated)
+        } else {
+            Error::new(symbolicated).context(error.to_string())
+        }
+    }
+}
+
+/// A location in the original source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceLocation {
+    pub(crate) path: PathBuf,
+    pub(crate) line: Option<u64>,
+    pub(crate) column: Option<u64>,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+            if let Some(column) = self.column {
+                write!(f, ":{column}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A trap whose stack trace has been mapped back to the original source code.
+#[derive(Debug)]
+struct SymbolicatedTrap {
+    message: String,
+    frames: Vec<(FrameInfo, Option<SourceLocation>)>,
+}
+
+impl Display for SymbolicatedTrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RuntimeError: {}", self.message)?;
+
+        for (frame, location) in &self.frames {
+            writeln!(f)?;
+            write!(f, "    at {}", frame.function_name().unwrap_or("<unnamed>"))?;
+            match location {
+                Some(location) => write!(f, " ({location})")?,
+                None => write!(
+                    f,
+                    " ({}[{}]:0x{:x})",
+                    frame.module_name(),
+                    frame.func_index(),
+                    frame.module_offset()
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for SymbolicatedTrap {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use gimli::write::{Address, DwarfUnit, EndianVec, LineProgram, LineString, Sections};
+
+    use super::*;
+
+    /// Append a custom section to a WebAssembly binary.
+    fn push_custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut contents = Vec::new();
+        leb128_u32(&mut contents, name.len() as u32);
+        contents.extend_from_slice(name.as_bytes());
+        contents.extend_from_slice(data);
+
+        wasm.push(0);
+        leb128_u32(wasm, contents.len() as u32);
+        wasm.extend(contents);
+    }
+
+    fn leb128_u32(buffer: &mut Vec<u8>, mut value: u32) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buffer.push(byte);
+                return;
+            }
+            buffer.push(byte | 0x80);
+        }
+    }
+
+    /// Generate a module where the code at `0x10..0x18` comes from line 7 of
+    /// `main.c`, and `0x18..0x20` comes from line 9.
+    pub(crate) fn module_with_debug_info() -> Vec<u8> {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"main.c".to_vec()),
+            None,
+        );
+        let dir = program.default_directory();
+        let file = program.add_file(LineString::String(b"main.c".to_vec()), dir, None);
+        program.begin_sequence(Some(Address::Constant(0x10)));
+        program.row().file = file;
+        program.row().line = 7;
+        program.generate_row();
+        program.row().address_offset = 0x8;
+        program.row().line = 9;
+        program.generate_row();
+        program.end_sequence(0x10);
+        dwarf.unit.line_program = program;
+
+        let mut sections = Sections::new(EndianVec::new(gimli::LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        sections
+            .for_each(|id, data| -> Result<(), ()> {
+                if !data.slice().is_empty() {
+                    push_custom_section(&mut wasm, id.name(), data.slice());
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        wasm
+    }
+
+    #[test]
+    fn look_up_source_locations() {
+        let wasm = module_with_debug_info();
+
+        let debug_info = DebugInfo::parse(&wasm).unwrap();
+
+        assert!(!debug_info.is_empty());
+        assert_eq!(debug_info.lookup(0x0f), None);
+        let location = debug_info.lookup(0x12).unwrap();
+        assert!(location.path.ends_with("main.c"));
+        assert_eq!(location.line, Some(7));
+        assert_eq!(debug_info.lookup(0x1f).unwrap().line, Some(9));
+        assert_eq!(debug_info.lookup(0x20), None);
+    }
+
+    #[test]
+    fn look_up_source_locations_in_a_range() {
+        let wasm = module_with_debug_info();
+
+        let debug_info = DebugInfo::parse(&wasm).unwrap();
+
+        let lines = |range| {
+            debug_info
+                .lookup_range(range)
+                .iter()
+                .map(|location| location.line.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert!(lines(0x08..0x10).is_empty());
+        assert_eq!(lines(0x08..0x11), [7]);
+        assert_eq!(lines(0x12..0x14), [7]);
+        assert_eq!(lines(0x12..0x19), [7, 9]);
+        assert_eq!(lines(0x1c..0x30), [9]);
+        assert!(lines(0x20..0x30).is_empty());
+    }
+
+    #[test]
+    fn modules_without_debug_info_are_empty() {
+        let debug_info = DebugInfo::parse(b"\0asm\x01\0\0\0").unwrap();
+
+        assert!(debug_info.is_empty());
+        assert_eq!(debug_info.lookup(0x12), None);
+    }
+}
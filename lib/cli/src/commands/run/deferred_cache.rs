@@ -0,0 +1,68 @@
+//! Support for `wasmer run --cache-on-success-only`, which only writes the
+//! compiled modules to the cache once the run succeeded.
+
+use std::sync::{Arc, Mutex};
+
+use wasmer::{Engine, Module};
+use wasmer_wasix::runtime::module_cache::{CacheError, ModuleCache, ModuleHash};
+
+/// A [`ModuleCache`] which holds on to the modules saved to it until
+/// [`DeferredCache::commit()`] is called, so nothing gets written if the run
+/// fails.
+///
+/// Loads go straight to the wrapped cache.
+#[derive(Debug, Clone)]
+pub struct DeferredCache<C> {
+    inner: Arc<C>,
+    pending: Arc<Mutex<Vec<(ModuleHash, Engine, Module)>>>,
+}
+
+impl<C> DeferredCache<C>
+where
+    C: ModuleCache + Send + Sync,
+{
+    pub(crate) fn new(inner: C) -> Self {
+        DeferredCache {
+            inner: Arc::new(inner),
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Write all of the modules saved so far to the wrapped cache.
+    pub(crate) async fn commit(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        for (key, engine, module) in pending {
+            if let Err(e) = self.inner.save(key, &engine, &module).await {
+                tracing::warn!(
+                    %key,
+                    error = &e as &dyn std::error::Error,
+                    "Unable to save the compiled module to the cache",
+                );
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> ModuleCache for DeferredCache<C>
+where
+    C: ModuleCache + Send + Sync,
+{
+    async fn load(&self, key: ModuleHash, engine: &Engine) -> Result<Module, CacheError> {
+        self.inner.load(key, engine).await
+    }
+
+    async fn save(
+        &self,
+        key: ModuleHash,
+        engine: &Engine,
+        module: &Module,
+    ) -> Result<(), CacheError> {
+        self.pending
+            .lock()
+            .unwrap()
+            .push((key, engine.clone(), module.clone()));
+        Ok(())
+    }
+}
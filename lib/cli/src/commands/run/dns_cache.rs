@@ -0,0 +1,260 @@
+//! Support for `wasmer run --net-dns-cache-ttl`, which caches the
+//! addresses the module's hostnames resolve to.
+//!
+//! The host's resolver doesn't give the TTLs of the records it found, so
+//! every answer is kept for the full `--net-dns-cache-ttl`, and failed
+//! lookups aren't cached at all. The host may still cache answers itself
+//! (e.g. with `nscd` or `systemd-resolved`).
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use virtual_net::{
+    IpCidr, IpRoute, StreamSecurity, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
+    VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+};
+
+/// A lookup: the host, the port and the DNS server.
+type Query = (String, Option<u16>, Option<IpAddr>);
+
+/// The answers to recent lookups, and when they expire.
+#[derive(Debug)]
+struct DnsCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<Query, (Vec<IpAddr>, Instant)>>,
+}
+
+impl DnsCache {
+    fn get(&self, query: &Query) -> Option<Vec<IpAddr>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(query) {
+            Some((ips, expires)) if *expires > Instant::now() => Some(ips.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, query: Query, ips: Vec<IpAddr>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        // Hosts which are never looked up again would stay around forever
+        entries.retain(|_, (_, expires)| *expires > now);
+        entries.insert(query, (ips, now + self.ttl));
+    }
+}
+
+/// Wraps a networking implementation, reusing the answers to its DNS
+/// lookups until they're `ttl` old.
+#[derive(Debug)]
+pub(crate) struct DnsCachingNetworking {
+    inner: Box<dyn VirtualNetworking>,
+    cache: DnsCache,
+}
+
+impl DnsCachingNetworking {
+    pub(crate) fn new(inner: impl VirtualNetworking, ttl: Duration) -> Self {
+        DnsCachingNetworking {
+            inner: Box::new(inner),
+            cache: DnsCache {
+                ttl,
+                entries: Mutex::new(HashMap::new()),
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for DnsCachingNetworking {
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> virtual_net::Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> virtual_net::Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> virtual_net::Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    fn ip_add(&self, ip: IpAddr, prefix: u8) -> virtual_net::Result<()> {
+        self.inner.ip_add(ip, prefix)
+    }
+
+    fn ip_remove(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.ip_remove(ip)
+    }
+
+    fn ip_clear(&self) -> virtual_net::Result<()> {
+        self.inner.ip_clear()
+    }
+
+    fn ip_list(&self) -> virtual_net::Result<Vec<IpCidr>> {
+        self.inner.ip_list()
+    }
+
+    fn mac(&self) -> virtual_net::Result<[u8; 6]> {
+        self.inner.mac()
+    }
+
+    fn gateway_set(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.gateway_set(ip)
+    }
+
+    fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> virtual_net::Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+    }
+
+    fn route_remove(&self, cidr: IpAddr) -> virtual_net::Result<()> {
+        self.inner.route_remove(cidr)
+    }
+
+    fn route_clear(&self) -> virtual_net::Result<()> {
+        self.inner.route_clear()
+    }
+
+    fn route_list(&self) -> virtual_net::Result<Vec<IpRoute>> {
+        self.inner.route_list()
+    }
+
+    async fn bind_raw(&self) -> virtual_net::Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(
+        &self,
+        addr: IpAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpSocket + Sync>> {
+        self.inner.connect_tcp(addr, peer).await
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> virtual_net::Result<Vec<IpAddr>> {
+        let key = (host.to_ascii_lowercase(), port, dns_server);
+        if let Some(ips) = self.cache.get(&key) {
+            return Ok(ips);
+        }
+        let ips = self.inner.resolve(host, port, dns_server).await?;
+        self.cache.insert(key, ips.clone());
+        Ok(ips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use virtual_net::NetworkError;
+
+    use super::*;
+
+    /// Resolves every host to 127.0.0.1, except `missing.invalid`, counting
+    /// the lookups.
+    #[derive(Debug)]
+    struct CountingResolver(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl VirtualNetworking for CountingResolver {
+        async fn resolve(
+            &self,
+            host: &str,
+            _port: Option<u16>,
+            _dns_server: Option<IpAddr>,
+        ) -> virtual_net::Result<Vec<IpAddr>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match host {
+                "missing.invalid" => Err(NetworkError::AddressNotAvailable),
+                _ => Ok(vec![[127, 0, 0, 1].into()]),
+            }
+        }
+    }
+
+    fn networking(ttl: Duration) -> (DnsCachingNetworking, Arc<AtomicUsize>) {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let resolver = CountingResolver(Arc::clone(&lookups));
+        (DnsCachingNetworking::new(resolver, ttl), lookups)
+    }
+
+    #[tokio::test]
+    async fn answers_are_reused_until_they_expire() {
+        let (networking, lookups) = networking(Duration::from_millis(200));
+
+        let ips = networking.resolve("example.com", None, None).await.unwrap();
+        assert_eq!(ips, [IpAddr::from([127, 0, 0, 1])]);
+        networking.resolve("Example.COM", None, None).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // Other ports and DNS servers are looked up separately
+        networking
+            .resolve("example.com", Some(80), None)
+            .await
+            .unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        networking.resolve("example.com", None, None).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn failures_are_not_cached() {
+        let (networking, lookups) = networking(Duration::from_secs(60));
+
+        for _ in 0..2 {
+            let err = networking.resolve("missing.invalid", None, None).await;
+            assert!(err.is_err());
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,68 @@
+//! `--exit-on-first-write`, which stops the process as soon as the guest
+//! writes anything to stdout and reports how long that took.
+
+use std::{
+    io::{Read, Write},
+    thread::JoinHandle,
+};
+
+use virtual_fs::Pipe;
+use wasmer_wasix::WasiEnvBuilder;
+
+use crate::cli::PROCESS_START;
+
+/// The thread watching the guest's stdout.
+#[derive(Debug)]
+pub(crate) struct FirstWriteWatcher {
+    stdout: Pipe,
+    thread: JoinHandle<()>,
+}
+
+impl FirstWriteWatcher {
+    /// Replace the guest's stdout with a pipe, exiting the process with a
+    /// status of 0 as soon as something is written to it.
+    pub(crate) fn attach(builder: &mut WasiEnvBuilder) -> Self {
+        let (guest, host) = Pipe::channel();
+        builder.set_stdout(Box::new(guest.clone()));
+        let thread =
+            std::thread::spawn(move || exit_on_first_write(host, std::io::stdout().lock()));
+
+        FirstWriteWatcher {
+            stdout: guest,
+            thread,
+        }
+    }
+
+    /// Wait for the watcher to see everything the guest wrote. This must be
+    /// called once the guest has exited, and only returns if it didn't write
+    /// anything.
+    pub(crate) fn finish(self) {
+        self.stdout.close();
+        self.thread
+            .join()
+            .expect("the thread watching stdout panicked");
+    }
+}
+
+/// Wait for the first bytes to arrive on `input`, then pass them on to
+/// `output` and exit, printing the time since the process started to stderr.
+fn exit_on_first_write(mut input: impl Read, mut output: impl Write) {
+    let mut buffer = [0; 8192];
+    let len = match input.read(&mut buffer) {
+        Ok(0) | Err(_) => return,
+        Ok(len) => len,
+    };
+    let elapsed = PROCESS_START.elapsed();
+
+    if let Err(e) = output
+        .write_all(&buffer[..len])
+        .and_then(|_| output.flush())
+    {
+        crate::warning!("Unable to write to stdout: {e}");
+    }
+    eprintln!(
+        "time to first write: {:.3}ms",
+        elapsed.as_secs_f64() * 1000.0
+    );
+    std::process::exit(0);
+}
@@ -0,0 +1,1033 @@
+//! Support for `wasmer run --net-capture-http`, which records the HTTP
+//! requests a module makes, and the responses it gets, in a [HAR] file.
+//!
+//! The module's TCP connections to ports 80 and 443 keep a copy of the bytes
+//! going through them, which is decoded as HTTP/1.x. Anything else is passed
+//! through untouched and left out of the file: that includes HTTP/2 and TLS,
+//! so most connections to port 443, as the module encrypts its traffic
+//! itself and the host only sees the ciphertext.
+//!
+//! The file is valid JSON after every entry, so it can be read while the
+//! module is still running, or after it exited without cleaning up.
+//!
+//! [HAR]: http://www.softwareishard.com/blog/har-12-spec/
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+    mem::MaybeUninit,
+    net::{IpAddr, Shutdown, SocketAddr},
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Error};
+use serde::Serialize;
+use url::Url;
+use virtual_net::{
+    IpCidr, IpRoute, SocketStatus, StreamSecurity, VirtualConnectedSocket, VirtualIcmpSocket,
+    VirtualNetworking, VirtualRawSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket,
+    VirtualUdpSocket,
+};
+
+/// Closes the `entries` array and the document, after the last entry.
+const FOOTER: &str = "]}}\n";
+
+/// Wraps a networking implementation, recording the HTTP requests made
+/// through it.
+#[derive(Debug)]
+pub(crate) struct HttpCapture {
+    inner: Box<dyn VirtualNetworking>,
+    har: Arc<HarWriter>,
+}
+
+impl HttpCapture {
+    /// Records the HTTP requests made through `inner` to a new HAR file at
+    /// `path`.
+    pub(crate) fn new(inner: impl VirtualNetworking, path: &Path) -> Result<Self, Error> {
+        let har = HarWriter::create(path)
+            .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+        Ok(HttpCapture {
+            inner: Box::new(inner),
+            har: Arc::new(har),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for HttpCapture {
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> virtual_net::Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> virtual_net::Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> virtual_net::Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    fn ip_add(&self, ip: IpAddr, prefix: u8) -> virtual_net::Result<()> {
+        self.inner.ip_add(ip, prefix)
+    }
+
+    fn ip_remove(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.ip_remove(ip)
+    }
+
+    fn ip_clear(&self) -> virtual_net::Result<()> {
+        self.inner.ip_clear()
+    }
+
+    fn ip_list(&self) -> virtual_net::Result<Vec<IpCidr>> {
+        self.inner.ip_list()
+    }
+
+    fn mac(&self) -> virtual_net::Result<[u8; 6]> {
+        self.inner.mac()
+    }
+
+    fn gateway_set(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.gateway_set(ip)
+    }
+
+    fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> virtual_net::Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+    }
+
+    fn route_remove(&self, cidr: IpAddr) -> virtual_net::Result<()> {
+        self.inner.route_remove(cidr)
+    }
+
+    fn route_clear(&self) -> virtual_net::Result<()> {
+        self.inner.route_clear()
+    }
+
+    fn route_list(&self) -> virtual_net::Result<Vec<IpRoute>> {
+        self.inner.route_list()
+    }
+
+    async fn bind_raw(&self) -> virtual_net::Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(
+        &self,
+        addr: IpAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let socket = self.inner.connect_tcp(addr, peer).await?;
+        if !matches!(peer.port(), 80 | 443) {
+            return Ok(socket);
+        }
+        Ok(Box::new(CapturingTcpSocket {
+            inner: socket,
+            exchange: HttpExchange::new(peer, Arc::clone(&self.har)),
+        }))
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> virtual_net::Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+/// A TCP connection which records the HTTP requests going through it.
+#[derive(Debug)]
+struct CapturingTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    exchange: HttpExchange,
+}
+
+impl VirtualSocket for CapturingTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> virtual_net::Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> virtual_net::Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> virtual_net::Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> virtual_net::Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualConnectedSocket for CapturingTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> virtual_net::Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> virtual_net::Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> virtual_net::Result<usize> {
+        let sent = self.inner.try_send(data)?;
+        self.exchange.sent(&data[..sent]);
+        Ok(sent)
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>, data: &[u8]) -> Poll<virtual_net::Result<usize>> {
+        let poll = self.inner.poll_send(cx, data);
+        if let Poll::Ready(Ok(sent)) = poll {
+            self.exchange.sent(&data[..sent]);
+        }
+        poll
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn close(&mut self) -> virtual_net::Result<()> {
+        self.exchange.closed();
+        self.inner.close()
+    }
+
+    fn poll_recv(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Poll<virtual_net::Result<usize>> {
+        let poll = self.inner.poll_recv(cx, buf);
+        if let Poll::Ready(Ok(received)) = poll {
+            self.exchange.received(initialized(buf, received));
+        }
+        poll
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> virtual_net::Result<usize> {
+        let received = self.inner.try_recv(buf)?;
+        self.exchange.received(initialized(buf, received));
+        Ok(received)
+    }
+}
+
+impl VirtualTcpSocket for CapturingTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> virtual_net::Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> virtual_net::Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> virtual_net::Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> virtual_net::Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, nodelay: bool) -> virtual_net::Result<()> {
+        self.inner.set_nodelay(nodelay)
+    }
+
+    fn nodelay(&self) -> virtual_net::Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn addr_peer(&self) -> virtual_net::Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> virtual_net::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+impl Drop for CapturingTcpSocket {
+    fn drop(&mut self) {
+        self.exchange.closed();
+    }
+}
+
+/// The first `len` bytes of `buf`, which a read just filled in.
+fn initialized(buf: &[MaybeUninit<u8>], len: usize) -> &[u8] {
+    let buf = &buf[..len];
+    // Safety: MaybeUninit<u8> has the same layout as u8, and the socket
+    // initialized the bytes it reported reading
+    unsafe { std::slice::from_raw_parts(buf.as_ptr().cast(), buf.len()) }
+}
+
+/// Decodes the HTTP/1.x requests and responses of one connection, recording
+/// each request once its response is complete.
+#[derive(Debug)]
+struct HttpExchange {
+    peer: SocketAddr,
+    har: Arc<HarWriter>,
+    /// Set once the connection turns out not to be HTTP/1.x, or is closed.
+    done: bool,
+    /// Bytes sent which aren't part of a complete request yet, and when the
+    /// first of them was sent.
+    sent: Vec<u8>,
+    request_started: Option<(SystemTime, Instant)>,
+    /// Requests sent in full, oldest first, waiting for their response.
+    pending: VecDeque<PendingRequest>,
+    /// Bytes received which aren't part of a complete response yet, and
+    /// when the first of them was received.
+    received: Vec<u8>,
+    response_started: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct PendingRequest {
+    request: Request,
+    started: SystemTime,
+    send_started: Instant,
+    sent: Instant,
+}
+
+impl HttpExchange {
+    fn new(peer: SocketAddr, har: Arc<HarWriter>) -> Self {
+        HttpExchange {
+            peer,
+            har,
+            done: false,
+            sent: Vec::new(),
+            request_started: None,
+            pending: VecDeque::new(),
+            received: Vec::new(),
+            response_started: None,
+        }
+    }
+
+    fn sent(&mut self, data: &[u8]) {
+        if self.done || data.is_empty() {
+            return;
+        }
+        self.request_started
+            .get_or_insert_with(|| (SystemTime::now(), Instant::now()));
+        self.sent.extend_from_slice(data);
+
+        loop {
+            match parse_request(&self.sent, self.peer) {
+                Ok(Some((request, len))) => {
+                    self.sent.drain(..len);
+                    let (started, send_started) = self
+                        .request_started
+                        .take()
+                        .unwrap_or_else(|| (SystemTime::now(), Instant::now()));
+                    self.pending.push_back(PendingRequest {
+                        request,
+                        started,
+                        send_started,
+                        sent: Instant::now(),
+                    });
+                    if !self.sent.is_empty() {
+                        self.request_started = Some((SystemTime::now(), Instant::now()));
+                    }
+                }
+                Ok(None) => break,
+                Err(NotHttp) => {
+                    self.stop();
+                    return;
+                }
+            }
+        }
+        if !self.received.is_empty() {
+            self.parse_responses(false);
+        }
+    }
+
+    fn received(&mut self, data: &[u8]) {
+        if self.done || data.is_empty() {
+            return;
+        }
+        if self.pending.is_empty() && self.sent.is_empty() {
+            // The server spoke first, so this isn't HTTP. It may answer a
+            // request before all of it was sent, with `100 Continue`.
+            self.stop();
+            return;
+        }
+        self.response_started.get_or_insert_with(Instant::now);
+        self.received.extend_from_slice(data);
+        self.parse_responses(false);
+    }
+
+    /// The connection was closed, which ends responses that don't say how
+    /// long they are.
+    fn closed(&mut self) {
+        if !self.done && !self.received.is_empty() {
+            self.parse_responses(true);
+        }
+        self.stop();
+    }
+
+    fn parse_responses(&mut self, at_eof: bool) {
+        while let Some(pending) = self.pending.front() {
+            let is_head = pending.request.method == "HEAD";
+            match parse_response(&self.received, is_head, at_eof) {
+                Ok(Some((response, len))) => {
+                    self.received.drain(..len);
+                    let response_started = self.response_started.take();
+                    if !self.received.is_empty() {
+                        self.response_started = Some(Instant::now());
+                    }
+                    if response.status == 101 {
+                        // The connection switched to another protocol
+                        self.stop();
+                        return;
+                    }
+                    if response.status < 200 {
+                        // Informational, the actual response follows
+                        continue;
+                    }
+                    let pending = self.pending.pop_front().expect("checked above");
+                    let entry = pending.into_entry(response, response_started, self.peer);
+                    self.har.record(&entry);
+                }
+                Ok(None) => return,
+                Err(NotHttp) => {
+                    self.stop();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Stops looking at the connection's traffic.
+    fn stop(&mut self) {
+        self.done = true;
+        self.sent = Vec::new();
+        self.received = Vec::new();
+        self.pending.clear();
+    }
+}
+
+impl PendingRequest {
+    fn into_entry(
+        self,
+        response: Response,
+        response_started: Option<Instant>,
+        peer: SocketAddr,
+    ) -> Entry {
+        let now = Instant::now();
+        let first_byte = response_started.unwrap_or(now).max(self.sent);
+        let send = millis(self.sent - self.send_started);
+        let wait = millis(first_byte - self.sent);
+        let receive = millis(now - first_byte);
+
+        let Request {
+            method,
+            url,
+            version,
+            headers,
+            headers_size,
+            body,
+        } = self.request;
+        let query_string = Url::parse(&url)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| NameValue {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let post_data = if body.is_empty() {
+            None
+        } else {
+            Some(PostData {
+                mime_type: header(&headers, "content-type").unwrap_or_default(),
+                text: String::from_utf8_lossy(&body).into_owned(),
+            })
+        };
+        let request = HarRequest {
+            method,
+            url,
+            http_version: version,
+            cookies: Vec::new(),
+            headers: name_values(headers),
+            query_string,
+            post_data,
+            headers_size,
+            body_size: body.len(),
+        };
+
+        let mime_type = header(&response.headers, "content-type").unwrap_or_default();
+        let redirect_url = header(&response.headers, "location").unwrap_or_default();
+        let response = HarResponse {
+            status: response.status,
+            status_text: response.reason,
+            http_version: response.version,
+            cookies: Vec::new(),
+            headers: name_values(response.headers),
+            content: Content {
+                size: response.body.len(),
+                mime_type,
+                text: String::from_utf8(response.body.clone()).ok(),
+            },
+            redirect_url,
+            headers_size: response.headers_size,
+            body_size: response.body.len(),
+        };
+
+        Entry {
+            started_date_time: format_timestamp(self.started),
+            time: send + wait + receive,
+            request,
+            response,
+            cache: Cache {},
+            timings: Timings {
+                send,
+                wait,
+                receive,
+            },
+            server_ip_address: peer.ip().to_string(),
+        }
+    }
+}
+
+/// The traffic isn't HTTP/1.x.
+#[derive(Debug)]
+struct NotHttp;
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    url: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    headers_size: usize,
+    body: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    reason: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    headers_size: usize,
+    body: Vec<u8>,
+}
+
+/// Parses the request at the start of `buf`, and how many bytes it takes,
+/// if all of it was sent.
+fn parse_request(buf: &[u8], peer: SocketAddr) -> Result<Option<(Request, usize)>, NotHttp> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut headers);
+    let headers_size = match request.parse(buf) {
+        Ok(httparse::Status::Complete(len)) => len,
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(_) => return Err(NotHttp),
+    };
+    let headers = owned_headers(request.headers);
+    // Requests without a length don't have a body
+    let (body, body_len) = match parse_body(&buf[headers_size..], &headers, true)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+
+    let target = request.path.unwrap_or("/");
+    let url = if target.starts_with("http://") || target.starts_with("https://") {
+        // Sent to a proxy
+        target.to_string()
+    } else {
+        let scheme = if peer.port() == 443 { "https" } else { "http" };
+        let host = header(&headers, "host").unwrap_or_else(|| peer.to_string());
+        format!("{scheme}://{host}{target}")
+    };
+
+    let request = Request {
+        method: request.method.unwrap_or_default().to_string(),
+        url,
+        version: http_version(request.version),
+        headers,
+        headers_size,
+        body,
+    };
+    Ok(Some((request, headers_size + body_len)))
+}
+
+/// Parses the response at the start of `buf`, and how many bytes it takes,
+/// if all of it was received. The connection being closed (`at_eof`) ends
+/// responses which don't say how long they are.
+fn parse_response(
+    buf: &[u8],
+    is_head: bool,
+    at_eof: bool,
+) -> Result<Option<(Response, usize)>, NotHttp> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut headers);
+    let headers_size = match response.parse(buf) {
+        Ok(httparse::Status::Complete(len)) => len,
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(_) => return Err(NotHttp),
+    };
+    let status = response.code.unwrap_or_default();
+    let headers = owned_headers(response.headers);
+    let rest = &buf[headers_size..];
+
+    let (body, body_len) = if is_head || status < 200 || status == 204 || status == 304 {
+        (Vec::new(), 0)
+    } else {
+        match parse_body(rest, &headers, false)? {
+            Some(body) => body,
+            None if at_eof && !has_length(&headers) => (rest.to_vec(), rest.len()),
+            None => return Ok(None),
+        }
+    };
+
+    let response = Response {
+        status,
+        reason: response.reason.unwrap_or_default().to_string(),
+        version: http_version(response.version),
+        headers,
+        headers_size,
+        body,
+    };
+    Ok(Some((response, headers_size + body_len)))
+}
+
+/// Parses a message's body from the bytes after its headers, and how many
+/// of them it takes, if all of it is there.
+///
+/// Messages without a `Content-Length` or `Transfer-Encoding` header have
+/// an empty body if `empty_by_default`, otherwise the body goes on until the
+/// connection is closed.
+fn parse_body(
+    buf: &[u8],
+    headers: &[(String, String)],
+    empty_by_default: bool,
+) -> Result<Option<(Vec<u8>, usize)>, NotHttp> {
+    if let Some(encoding) = header(headers, "transfer-encoding") {
+        if encoding.to_ascii_lowercase().contains("chunked") {
+            return parse_chunked(buf);
+        }
+    }
+    if let Some(length) = header(headers, "content-length") {
+        let length: usize = length.trim().parse().map_err(|_| NotHttp)?;
+        return Ok(buf.get(..length).map(|body| (body.to_vec(), length)));
+    }
+    if empty_by_default {
+        Ok(Some((Vec::new(), 0)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn has_length(headers: &[(String, String)]) -> bool {
+    header(headers, "transfer-encoding").is_some() || header(headers, "content-length").is_some()
+}
+
+/// Decodes a body with the chunked transfer encoding.
+fn parse_chunked(buf: &[u8]) -> Result<Option<(Vec<u8>, usize)>, NotHttp> {
+    let mut body = Vec::new();
+    let mut pos = 0;
+    loop {
+        let (len, size) = match httparse::parse_chunk_size(&buf[pos..]) {
+            Ok(httparse::Status::Complete(chunk)) => chunk,
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(_) => return Err(NotHttp),
+        };
+        pos += len;
+        let size = usize::try_from(size).map_err(|_| NotHttp)?;
+
+        if size == 0 {
+            // Skip the trailers, up to the empty line ending the body
+            loop {
+                let line_end = match buf[pos..].windows(2).position(|w| w == b"\r\n") {
+                    Some(end) => end,
+                    None => return Ok(None),
+                };
+                pos += line_end + 2;
+                if line_end == 0 {
+                    return Ok(Some((body, pos)));
+                }
+            }
+        }
+
+        match buf.get(pos..pos + size + 2) {
+            Some(chunk) if chunk.ends_with(b"\r\n") => body.extend_from_slice(&chunk[..size]),
+            Some(_) => return Err(NotHttp),
+            None => return Ok(None),
+        }
+        pos += size + 2;
+    }
+}
+
+fn owned_headers(headers: &[httparse::Header<'_>]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|h| {
+            (
+                h.name.to_string(),
+                String::from_utf8_lossy(h.value).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// The value of the first header called `name`, ignoring case.
+fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+fn name_values(headers: Vec<(String, String)>) -> Vec<NameValue> {
+    headers
+        .into_iter()
+        .map(|(name, value)| NameValue { name, value })
+        .collect()
+}
+
+fn http_version(minor: Option<u8>) -> String {
+    format!("HTTP/1.{}", minor.unwrap_or(1))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Formats `time` the way HAR files want it, as an ISO 8601 timestamp in UTC
+/// (e.g. `2023-06-13T06:45:12.345Z`).
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // The civil calendar date for a number of days since 1970-01-01, from
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Appends entries to a HAR file, keeping it valid after each of them.
+#[derive(Debug)]
+struct HarWriter {
+    output: Mutex<HarOutput>,
+}
+
+#[derive(Debug)]
+struct HarOutput {
+    file: File,
+    entries: usize,
+}
+
+impl HarWriter {
+    fn create(path: &Path) -> Result<Self, std::io::Error> {
+        let mut file = File::create(path)?;
+        let creator = serde_json::json!({ "name": "wasmer", "version": crate::VERSION });
+        write!(
+            file,
+            r#"{{"log":{{"version":"1.2","creator":{creator},"entries":[{FOOTER}"#
+        )?;
+        file.flush()?;
+        Ok(HarWriter {
+            output: Mutex::new(HarOutput { file, entries: 0 }),
+        })
+    }
+
+    fn record(&self, entry: &Entry) {
+        let mut output = self.output.lock().unwrap();
+        if let Err(e) = output.append(entry) {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                url = %entry.request.url,
+                "Unable to record an HTTP request",
+            );
+        }
+    }
+}
+
+impl HarOutput {
+    fn append(&mut self, entry: &Entry) -> Result<(), std::io::Error> {
+        let json = serde_json::to_string(entry)?;
+        // Overwrite the footer, and put it back after the new entry
+        self.file.seek(SeekFrom::End(-(FOOTER.len() as i64)))?;
+        if self.entries > 0 {
+            self.file.write_all(b",")?;
+        }
+        self.file.write_all(json.as_bytes())?;
+        self.file.write_all(FOOTER.as_bytes())?;
+        self.file.flush()?;
+        self.entries += 1;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Cache,
+    timings: Timings,
+    #[serde(rename = "serverIPAddress")]
+    server_ip_address: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: usize,
+    body_size: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: usize,
+    body_size: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: usize,
+    mime_type: String,
+    /// Left out for binary bodies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Cache {}
+
+#[derive(Debug, Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn exchange(dir: &TempDir, peer: &str) -> (HttpExchange, std::path::PathBuf) {
+        let path = dir.path().join("requests.har");
+        let har = Arc::new(HarWriter::create(&path).unwrap());
+        (HttpExchange::new(peer.parse().unwrap(), har), path)
+    }
+
+    fn entries(path: &Path) -> Vec<serde_json::Value> {
+        let har: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(har["log"]["version"], "1.2");
+        har["log"]["entries"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn requests_are_recorded_with_their_response() {
+        let dir = TempDir::new().unwrap();
+        let (mut exchange, path) = exchange(&dir, "93.184.216.34:80");
+        assert!(entries(&path).is_empty());
+
+        // Split across writes, as a module may send it
+        exchange.sent(b"POST /submit?a=1&b=two HTTP/1.1\r\nHost: exam");
+        exchange.sent(b"ple.com\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello");
+        exchange.received(b"HTTP/1.1 201 Created\r\nContent-Type: text/plain\r\n");
+        exchange.received(b"Content-Length: 2\r\n\r\nok");
+
+        let entries = entries(&path);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry["serverIPAddress"], "93.184.216.34");
+        let request = &entry["request"];
+        assert_eq!(request["method"], "POST");
+        assert_eq!(request["url"], "http://example.com/submit?a=1&b=two");
+        assert_eq!(request["httpVersion"], "HTTP/1.1");
+        assert_eq!(request["queryString"][1]["name"], "b");
+        assert_eq!(request["queryString"][1]["value"], "two");
+        assert_eq!(request["postData"]["mimeType"], "text/plain");
+        assert_eq!(request["postData"]["text"], "hello");
+        assert_eq!(request["bodySize"], 5);
+        let response = &entry["response"];
+        assert_eq!(response["status"], 201);
+        assert_eq!(response["statusText"], "Created");
+        assert_eq!(response["content"]["text"], "ok");
+        assert_eq!(response["content"]["mimeType"], "text/plain");
+    }
+
+    #[test]
+    fn pipelined_and_chunked_messages() {
+        let dir = TempDir::new().unwrap();
+        let (mut exchange, path) = exchange(&dir, "10.0.0.1:80");
+
+        exchange.sent(b"GET /a HTTP/1.1\r\nHost: h\r\n\r\nHEAD /b HTTP/1.1\r\nHost: h\r\n\r\n");
+        exchange.received(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n\
+              HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n",
+        );
+
+        let entries = entries(&path);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["request"]["url"], "http://h/a");
+        assert_eq!(entries[0]["response"]["content"]["text"], "abcde");
+        // Responses to HEAD requests don't have a body, whatever they say
+        assert_eq!(entries[1]["request"]["method"], "HEAD");
+        assert_eq!(entries[1]["response"]["bodySize"], 0);
+    }
+
+    #[test]
+    fn responses_read_until_close() {
+        let dir = TempDir::new().unwrap();
+        let (mut exchange, path) = exchange(&dir, "10.0.0.1:80");
+
+        exchange.sent(b"GET / HTTP/1.0\r\n\r\n");
+        exchange.received(b"HTTP/1.0 200 OK\r\n\r\npartial");
+        assert!(entries(&path).is_empty());
+        exchange.received(b" body");
+        exchange.closed();
+
+        let entries = entries(&path);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["request"]["url"], "http://10.0.0.1:80/");
+        assert_eq!(entries[0]["response"]["content"]["text"], "partial body");
+    }
+
+    #[test]
+    fn other_traffic_is_ignored() {
+        let dir = TempDir::new().unwrap();
+
+        // A TLS ClientHello
+        let (mut tls, path) = exchange(&dir, "10.0.0.1:443");
+        tls.sent(&[0x16, 0x03, 0x01, 0x02, 0x00, 0x01, 0x00, 0x01, 0xfc]);
+        tls.received(&[0x16, 0x03, 0x03, 0x00, 0x7a]);
+        tls.closed();
+        assert!(entries(&path).is_empty());
+
+        // The HTTP/2 connection preface
+        let (mut http2, path) = exchange(&dir, "10.0.0.1:80");
+        http2.sent(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+        http2.received(b"\x00\x00\x00\x04\x00\x00\x00\x00\x00");
+        http2.closed();
+        assert!(entries(&path).is_empty());
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_millis(1_686_638_712_345)),
+            "2023-06-13T06:45:12.345Z"
+        );
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Support for `wasmer run --instance-count`, which runs several instances of
+//! a WASI module side by side, and `--output-dir`, which keeps what each of
+//! them printed apart.
+//!
+//! With `--output-dir`, instance `N` writes its stdout and stderr to
+//! `instance-N-stdout.txt` and `instance-N-stderr.txt`, and the code it
+//! exited with to `instance-N-exitcode.txt` as it finishes. Once they've all
+//! finished, `summary.json` lists every instance and how it finished.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+use virtual_fs::{host_fs, VirtualFile};
+
+use super::get_exit_code;
+
+/// How one of the instances finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct InstanceOutcome {
+    pub index: usize,
+    pub status: InstanceStatus,
+    /// The code `wasmer run` would have exited with, had the instance been
+    /// run on its own.
+    pub exit_code: i32,
+    /// Why the instance didn't exit by itself, if it didn't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum InstanceStatus {
+    /// The instance exited with a zero exit code.
+    Success,
+    /// The instance exited with a non-zero exit code.
+    Failure,
+    /// The instance trapped, or couldn't be started.
+    Error,
+}
+
+impl InstanceOutcome {
+    pub fn new(index: usize, result: &Result<(), Error>) -> Self {
+        let (status, exit_code, error) = match result {
+            Ok(()) => (InstanceStatus::Success, 0, None),
+            Err(e) => match e.chain().find_map(get_exit_code) {
+                Some(code) if code.is_success() => (InstanceStatus::Success, 0, None),
+                Some(code) => (InstanceStatus::Failure, code.raw(), None),
+                None => (InstanceStatus::Error, 1, Some(format!("{e:#}"))),
+            },
+        };
+
+        InstanceOutcome {
+            index,
+            status,
+            exit_code,
+            error,
+        }
+    }
+
+    /// The outcome of an instance whose thread panicked.
+    pub fn panicked(index: usize) -> Self {
+        InstanceOutcome {
+            index,
+            status: InstanceStatus::Error,
+            exit_code: 1,
+            error: Some("The instance's thread panicked".to_string()),
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.status == InstanceStatus::Success
+    }
+}
+
+/// The directory given with `--output-dir`.
+#[derive(Debug, Clone)]
+pub(crate) struct OutputDir {
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    instance_count: usize,
+    succeeded: usize,
+    instances: Vec<SummaryEntry<'a>>,
+}
+
+#[derive(Serialize)]
+struct SummaryEntry<'a> {
+    #[serde(flatten)]
+    outcome: &'a InstanceOutcome,
+    stdout: String,
+    stderr: String,
+    exit_code_file: String,
+}
+
+impl OutputDir {
+    /// Use the directory at `path`, creating it if needed.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+        Ok(OutputDir {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Create the files instance `index`'s stdout and stderr go to.
+    #[allow(clippy::type_complexity)]
+    pub fn stdio(
+        &self,
+        index: usize,
+    ) -> Result<
+        (
+            Box<dyn VirtualFile + Send + Sync>,
+            Box<dyn VirtualFile + Send + Sync>,
+        ),
+        Error,
+    > {
+        Ok((
+            self.create_file(&stdout_name(index))?,
+            self.create_file(&stderr_name(index))?,
+        ))
+    }
+
+    fn create_file(&self, name: &str) -> Result<Box<dyn VirtualFile + Send + Sync>, Error> {
+        let path = self.path.join(name);
+        let file = File::create(&path)
+            .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+        Ok(Box::new(host_fs::File::new(file, path, false, true, false)))
+    }
+
+    /// Record the code an instance exited with.
+    pub fn write_exit_code(&self, outcome: &InstanceOutcome) -> Result<(), Error> {
+        let path = self.path.join(exit_code_name(outcome.index));
+        std::fs::write(&path, format!("{}\n", outcome.exit_code))
+            .with_context(|| format!("Unable to write to \"{}\"", path.display()))
+    }
+
+    /// Write `summary.json`, listing every instance and how it finished.
+    pub fn write_summary(&self, outcomes: &[InstanceOutcome]) -> Result<(), Error> {
+        let summary = Summary {
+            instance_count: outcomes.len(),
+            succeeded: outcomes.iter().filter(|o| o.succeeded()).count(),
+            instances: outcomes
+                .iter()
+                .map(|outcome| SummaryEntry {
+                    outcome,
+                    stdout: stdout_name(outcome.index),
+                    stderr: stderr_name(outcome.index),
+                    exit_code_file: exit_code_name(outcome.index),
+                })
+                .collect(),
+        };
+
+        let path = self.path.join("summary.json");
+        let json = serde_json::to_string_pretty(&summary)?;
+        std::fs::write(&path, json + "\n")
+            .with_context(|| format!("Unable to write to \"{}\"", path.display()))
+    }
+}
+
+fn stdout_name(index: usize) -> String {
+    format!("instance-{index}-stdout.txt")
+}
+
+fn stderr_name(index: usize) -> String {
+    format!("instance-{index}-stderr.txt")
+}
+
+fn exit_code_name(index: usize) -> String {
+    format!("instance-{index}-exitcode.txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use wasmer_wasix::WasiError;
+
+    use super::*;
+
+    #[test]
+    fn outcomes() {
+        let exited = |code: i32| -> Result<(), Error> { Err(WasiError::Exit(code.into()).into()) };
+
+        assert!(InstanceOutcome::new(0, &Ok(())).succeeded());
+        assert!(InstanceOutcome::new(1, &exited(0)).succeeded());
+        assert_eq!(
+            InstanceOutcome::new(2, &exited(3)),
+            InstanceOutcome {
+                index: 2,
+                status: InstanceStatus::Failure,
+                exit_code: 3,
+                error: None,
+            }
+        );
+        let trapped = InstanceOutcome::new(3, &Err(anyhow::anyhow!("unreachable")));
+        assert_eq!(trapped.status, InstanceStatus::Error);
+        assert_eq!(trapped.exit_code, 1);
+        assert_eq!(trapped.error.as_deref(), Some("unreachable"));
+    }
+
+    #[test]
+    fn summary_lists_every_instance() {
+        let temp = TempDir::new().unwrap();
+        let dir = OutputDir::create(&temp.path().join("results")).unwrap();
+        let outcomes = [
+            InstanceOutcome::new(0, &Ok(())),
+            InstanceOutcome::new(1, &Err(anyhow::anyhow!("unreachable"))),
+        ];
+
+        for outcome in &outcomes {
+            dir.write_exit_code(outcome).unwrap();
+        }
+        dir.write_summary(&outcomes).unwrap();
+
+        let exit_code =
+            std::fs::read_to_string(temp.path().join("results/instance-1-exitcode.txt")).unwrap();
+        assert_eq!(exit_code, "1\n");
+        let summary: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(temp.path().join("results/summary.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "instance_count": 2,
+                "succeeded": 1,
+                "instances": [
+                    {
+                        "index": 0,
+                        "status": "success",
+                        "exit_code": 0,
+                        "stdout": "instance-0-stdout.txt",
+                        "stderr": "instance-0-stderr.txt",
+                        "exit_code_file": "instance-0-exitcode.txt",
+                    },
+                    {
+                        "index": 1,
+                        "status": "error",
+                        "exit_code": 1,
+                        "error": "unreachable",
+                        "stdout": "instance-1-stdout.txt",
+                        "stderr": "instance-1-stderr.txt",
+                        "exit_code_file": "instance-1-exitcode.txt",
+                    },
+                ],
+            })
+        );
+    }
+}
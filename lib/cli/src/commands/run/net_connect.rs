@@ -0,0 +1,347 @@
+//! Support for `wasmer run --allow-net-connect`, which only lets the module
+//! open TCP connections to the hosts it lists.
+//!
+//! Hosts are given by name, so they're resolved when the module starts, and
+//! again every `--net-connect-dns-ttl` seconds to follow services whose
+//! addresses change. If resolving a host fails later on, the addresses it
+//! had are kept until it resolves again.
+
+use std::{
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::{Arc, RwLock, Weak},
+    time::Duration,
+};
+
+use anyhow::{Context, Error};
+use tokio::runtime::Handle;
+use virtual_net::{
+    IpCidr, IpRoute, NetworkError, StreamSecurity, VirtualIcmpSocket, VirtualNetworking,
+    VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+};
+
+/// A host given to `--allow-net-connect`, as `<host>:<port>`, or just
+/// `<host>` to allow every port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedHost {
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl FromStr for AllowedHost {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+            // An IPv6 address, like `[::1]:443`
+            let (host, rest) = rest
+                .split_once(']')
+                .with_context(|| format!("Missing \"]\" in \"{s}\""))?;
+            match rest {
+                "" => (host, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(port) => (host, Some(port)),
+                    None => anyhow::bail!("Expected a port after \"]\" in \"{s}\""),
+                },
+            }
+        } else {
+            match s.rsplit_once(':') {
+                // A bare IPv6 address has more than one colon
+                Some((host, port)) if !host.contains(':') => (host, Some(port)),
+                _ => (s, None),
+            }
+        };
+
+        anyhow::ensure!(!host.is_empty(), "No host in \"{s}\"");
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .with_context(|| format!("Invalid port in \"{s}\""))
+            })
+            .transpose()?;
+
+        Ok(AllowedHost {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl AllowedHost {
+    fn resolve(&self) -> std::io::Result<Vec<IpAddr>> {
+        let mut ips: Vec<IpAddr> = (self.host.as_str(), self.port.unwrap_or(0))
+            .to_socket_addrs()?
+            .map(|addr| addr.ip())
+            .collect();
+        ips.sort();
+        ips.dedup();
+        Ok(ips)
+    }
+
+    fn allows_port(&self, port: u16) -> bool {
+        self.port.map_or(true, |allowed| allowed == port)
+    }
+}
+
+/// The hosts the module may connect to, and the addresses they resolved to.
+#[derive(Debug)]
+pub(crate) struct ConnectAllowlist {
+    hosts: Vec<AllowedHost>,
+    addresses: RwLock<Vec<Vec<IpAddr>>>,
+}
+
+impl ConnectAllowlist {
+    /// Resolve each of `hosts`.
+    ///
+    /// Hosts which don't resolve make this fail when `strict`, and are only
+    /// warned about otherwise, in case they resolve later.
+    pub fn resolve(hosts: Vec<AllowedHost>, strict: bool) -> Result<Self, Error> {
+        let mut addresses = Vec::with_capacity(hosts.len());
+        for host in &hosts {
+            match host.resolve() {
+                Ok(ips) => addresses.push(ips),
+                Err(e) if strict => {
+                    return Err(e).with_context(|| format!("Unable to resolve \"{}\"", host.host))
+                }
+                Err(e) => {
+                    crate::warning!(
+                        "Unable to resolve \"{}\", connections to it will be refused until it resolves: {e}",
+                        host.host
+                    );
+                    addresses.push(Vec::new());
+                }
+            }
+        }
+
+        Ok(ConnectAllowlist {
+            hosts,
+            addresses: RwLock::new(addresses),
+        })
+    }
+
+    /// Resolve the hosts again, keeping the previous addresses of the ones
+    /// which don't resolve.
+    fn refresh(&self) {
+        for (i, host) in self.hosts.iter().enumerate() {
+            match host.resolve() {
+                Ok(ips) => self.addresses.write().unwrap()[i] = ips,
+                Err(e) => tracing::debug!(host = %host.host, error = %e, "Unable to resolve"),
+            }
+        }
+    }
+
+    /// Resolve the hosts again every `ttl`, for as long as the allowlist is
+    /// used.
+    pub fn refresh_every(self: &Arc<Self>, handle: &Handle, ttl: Duration) {
+        let allowlist: Weak<Self> = Arc::downgrade(self);
+        let blocking = handle.clone();
+        handle.spawn(async move {
+            loop {
+                tokio::time::sleep(ttl).await;
+                let allowlist = match allowlist.upgrade() {
+                    Some(allowlist) => allowlist,
+                    None => break,
+                };
+                // Resolving blocks until the DNS server answers
+                let _ = blocking.spawn_blocking(move || allowlist.refresh()).await;
+            }
+        });
+    }
+
+    /// Whether the module may connect to `peer`.
+    pub fn allows(&self, peer: SocketAddr) -> bool {
+        let addresses = self.addresses.read().unwrap();
+        self.hosts
+            .iter()
+            .zip(addresses.iter())
+            .any(|(host, ips)| host.allows_port(peer.port()) && ips.contains(&peer.ip()))
+    }
+}
+
+/// Wraps a networking implementation, refusing the TCP connections to
+/// hosts which aren't in the allowlist.
+#[derive(Debug)]
+pub(crate) struct AllowlistedNetworking {
+    inner: Box<dyn VirtualNetworking>,
+    allowlist: Arc<ConnectAllowlist>,
+}
+
+impl AllowlistedNetworking {
+    pub(crate) fn new(inner: impl VirtualNetworking, allowlist: Arc<ConnectAllowlist>) -> Self {
+        AllowlistedNetworking {
+            inner: Box::new(inner),
+            allowlist,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for AllowlistedNetworking {
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> virtual_net::Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> virtual_net::Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> virtual_net::Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    fn ip_add(&self, ip: IpAddr, prefix: u8) -> virtual_net::Result<()> {
+        self.inner.ip_add(ip, prefix)
+    }
+
+    fn ip_remove(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.ip_remove(ip)
+    }
+
+    fn ip_clear(&self) -> virtual_net::Result<()> {
+        self.inner.ip_clear()
+    }
+
+    fn ip_list(&self) -> virtual_net::Result<Vec<IpCidr>> {
+        self.inner.ip_list()
+    }
+
+    fn mac(&self) -> virtual_net::Result<[u8; 6]> {
+        self.inner.mac()
+    }
+
+    fn gateway_set(&self, ip: IpAddr) -> virtual_net::Result<()> {
+        self.inner.gateway_set(ip)
+    }
+
+    fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> virtual_net::Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+    }
+
+    fn route_remove(&self, cidr: IpAddr) -> virtual_net::Result<()> {
+        self.inner.route_remove(cidr)
+    }
+
+    fn route_clear(&self) -> virtual_net::Result<()> {
+        self.inner.route_clear()
+    }
+
+    fn route_list(&self) -> virtual_net::Result<Vec<IpRoute>> {
+        self.inner.route_list()
+    }
+
+    async fn bind_raw(&self) -> virtual_net::Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpListener + Sync>> {
+        self.inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> virtual_net::Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(
+        &self,
+        addr: IpAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> virtual_net::Result<Box<dyn VirtualTcpSocket + Sync>> {
+        if !self.allowlist.allows(peer) {
+            tracing::debug!(%peer, "Refused a connection to a host which isn't allowed");
+            return Err(NetworkError::PermissionDenied);
+        }
+        self.inner.connect_tcp(addr, peer).await
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> virtual_net::Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hosts() {
+        let parse = |s: &str| s.parse::<AllowedHost>().unwrap();
+        let host = |host: &str, port: Option<u16>| AllowedHost {
+            host: host.to_string(),
+            port,
+        };
+
+        assert_eq!(
+            parse("api.example.com:443"),
+            host("api.example.com", Some(443))
+        );
+        assert_eq!(parse("api.example.com"), host("api.example.com", None));
+        assert_eq!(parse("10.0.0.1:80"), host("10.0.0.1", Some(80)));
+        assert_eq!(parse("[::1]:8080"), host("::1", Some(8080)));
+        assert_eq!(parse("[::1]"), host("::1", None));
+        assert_eq!(parse("fe80::1"), host("fe80::1", None));
+        assert!("api.example.com:https".parse::<AllowedHost>().is_err());
+        assert!(":443".parse::<AllowedHost>().is_err());
+        assert!("[::1".parse::<AllowedHost>().is_err());
+    }
+
+    #[test]
+    fn only_allowed_hosts_and_ports() {
+        let allowlist = ConnectAllowlist::resolve(
+            vec!["127.0.0.1:443".parse().unwrap(), "[::1]".parse().unwrap()],
+            true,
+        )
+        .unwrap();
+
+        assert!(allowlist.allows("127.0.0.1:443".parse().unwrap()));
+        assert!(!allowlist.allows("127.0.0.1:80".parse().unwrap()));
+        assert!(!allowlist.allows("127.0.0.2:443".parse().unwrap()));
+        assert!(allowlist.allows("[::1]:80".parse().unwrap()));
+        assert!(allowlist.allows("[::1]:22".parse().unwrap()));
+    }
+
+    #[test]
+    fn unresolvable_hosts() {
+        let hosts = || vec!["does-not-exist.invalid:443".parse().unwrap()];
+
+        assert!(ConnectAllowlist::resolve(hosts(), true).is_err());
+        let allowlist = ConnectAllowlist::resolve(hosts(), false).unwrap();
+        assert!(!allowlist.allows("127.0.0.1:443".parse().unwrap()));
+    }
+}
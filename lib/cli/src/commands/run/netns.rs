@@ -0,0 +1,29 @@
+//! Support for `wasmer run --network-namespace`, which runs the module inside
+//! an existing Linux network namespace (e.g. one created with `ip netns add`).
+
+use std::{fs::File, os::unix::io::AsRawFd, path::Path};
+
+use anyhow::{Context, Error};
+
+/// Move the current thread into the network namespace at `path`.
+///
+/// Namespaces are per-thread, so this needs to happen before any of the
+/// threads doing host networking for the module are spawned. They inherit
+/// the namespace of the thread that created them.
+pub(crate) fn enter(path: &Path) -> Result<(), Error> {
+    let namespace =
+        File::open(path).with_context(|| format!("Unable to open \"{}\"", path.display()))?;
+
+    // Safety: the file descriptor stays open until setns() returns
+    let ret = unsafe { libc::setns(namespace.as_raw_fd(), libc::CLONE_NEWNET) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| {
+            format!(
+                "Unable to enter the network namespace at \"{}\"",
+                path.display()
+            )
+        });
+    }
+
+    Ok(())
+}
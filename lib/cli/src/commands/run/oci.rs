@@ -0,0 +1,182 @@
+//! Support for `wasmer run --oci-runtime`, which runs the WebAssembly module
+//! described by an [OCI runtime bundle][bundle].
+//!
+//! Only the parts of the bundle's `config.json` which make sense for a WASI
+//! program are used: the process' arguments, environment and working
+//! directory, and the root filesystem. Everything else (mounts, namespaces,
+//! cgroup resource limits, hooks, ...) is ignored.
+//!
+//! [bundle]: https://github.com/opencontainers/runtime-spec/blob/main/bundle.md
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use serde::Deserialize;
+
+/// The subset of an OCI bundle's `config.json` we care about.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Config {
+    oci_version: String,
+    process: Option<Process>,
+    root: Option<Root>,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+    linux: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Process {
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: Vec<String>,
+    cwd: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Root {
+    path: PathBuf,
+}
+
+/// What to run, as described by an OCI bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OciBundle {
+    /// The WebAssembly module to run (i.e. `process.args[0]`), as a path on
+    /// the host.
+    pub(crate) program: PathBuf,
+    /// The rest of `process.args`.
+    pub(crate) args: Vec<String>,
+    /// `process.env`, plus `PWD` if the bundle sets `process.cwd`.
+    pub(crate) env: Vec<(String, String)>,
+    /// The bundle's root filesystem, which is mapped to `/` in the guest.
+    pub(crate) rootfs: PathBuf,
+}
+
+impl OciBundle {
+    /// Read the `config.json` in the bundle directory `dir`.
+    pub(crate) fn load(dir: &Path) -> Result<Self, Error> {
+        let path = dir.join("config.json");
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+        Self::parse(dir, &json)
+            .with_context(|| format!("Unable to load the OCI bundle at \"{}\"", dir.display()))
+    }
+
+    fn parse(dir: &Path, json: &str) -> Result<Self, Error> {
+        let config: Config =
+            serde_json::from_str(json).context("Unable to parse the bundle's config.json")?;
+
+        tracing::debug!(
+            oci_version = %config.oci_version,
+            annotations = ?config.annotations,
+            "Loaded an OCI bundle",
+        );
+        if config.linux.is_some() {
+            tracing::debug!(
+                "Ignoring the bundle's Linux-specific settings (cgroups, namespaces, etc.)"
+            );
+        }
+
+        let process = config
+            .process
+            .context("The bundle's config.json doesn't specify a process to run")?;
+        let (program, args) = process
+            .args
+            .split_first()
+            .context("The bundle's process.args is empty")?;
+
+        let rootfs = dir.join(
+            config
+                .root
+                .map(|root| root.path)
+                .unwrap_or_else(|| PathBuf::from("rootfs")),
+        );
+        let program = rootfs.join(program.trim_start_matches('/'));
+        anyhow::ensure!(
+            program.is_file(),
+            "The bundle's root filesystem doesn't contain \"{}\"",
+            program.display()
+        );
+
+        let mut env = process
+            .env
+            .iter()
+            .map(|var| match var.split_once('=') {
+                Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                None => Err(anyhow::anyhow!(
+                    "The environment variable \"{var}\" in process.env isn't in KEY=VALUE form"
+                )),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        // WASI doesn't have a working directory, so follow the shell's
+        // convention instead
+        if let Some(cwd) = process.cwd {
+            env.retain(|(key, _)| key != "PWD");
+            env.push(("PWD".to_string(), cwd));
+        }
+
+        Ok(OciBundle {
+            program,
+            args: args.to_vec(),
+            env,
+            rootfs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn parse_bundle() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("fs/bin")).unwrap();
+        std::fs::write(dir.path().join("fs/bin/app.wasm"), b"\0asm").unwrap();
+        let json = r#"{
+            "ociVersion": "1.0.2",
+            "process": {
+                "terminal": false,
+                "user": { "uid": 0, "gid": 0 },
+                "args": ["/bin/app.wasm", "--verbose", "input.txt"],
+                "env": ["PATH=/bin", "GREETING=a=b"],
+                "cwd": "/data"
+            },
+            "root": { "path": "fs", "readonly": true },
+            "annotations": { "io.kubernetes.cri.container-type": "container" },
+            "linux": { "resources": { "memory": { "limit": 1024 } } }
+        }"#;
+
+        let bundle = OciBundle::parse(dir.path(), json).unwrap();
+
+        assert_eq!(
+            bundle,
+            OciBundle {
+                program: dir.path().join("fs/bin/app.wasm"),
+                args: vec!["--verbose".to_string(), "input.txt".to_string()],
+                env: vec![
+                    ("PATH".to_string(), "/bin".to_string()),
+                    ("GREETING".to_string(), "a=b".to_string()),
+                    ("PWD".to_string(), "/data".to_string()),
+                ],
+                rootfs: dir.path().join("fs"),
+            }
+        );
+    }
+
+    #[test]
+    fn program_must_exist_in_the_rootfs() {
+        let dir = TempDir::new().unwrap();
+        let json = r#"{ "ociVersion": "1.0.2", "process": { "args": ["/app.wasm"] } }"#;
+
+        let err = OciBundle::parse(dir.path(), json).unwrap_err();
+
+        assert!(err.to_string().contains("doesn't contain"), "{err}");
+    }
+}
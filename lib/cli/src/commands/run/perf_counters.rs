@@ -0,0 +1,139 @@
+//! Support for `wasmer run --perf-counters`, which counts the instructions,
+//! cache misses and branch mispredictions of the module's execution with the
+//! CPU's hardware performance counters, and prints them once it finishes.
+//!
+//! The counters are read with `perf_event_open`, so this is only supported
+//! on Linux. They follow the thread running the module, and the threads it
+//! spawns, but not the threads that existed before it started.
+
+/// The hardware events counted, and their names in the table.
+#[cfg(target_os = "linux")]
+const EVENTS: &[(&str, perf_event::events::Hardware)] = &[
+    ("cycles", perf_event::events::Hardware::CPU_CYCLES),
+    ("instructions", perf_event::events::Hardware::INSTRUCTIONS),
+    ("cache-misses", perf_event::events::Hardware::CACHE_MISSES),
+    ("branch-misses", perf_event::events::Hardware::BRANCH_MISSES),
+];
+
+/// The counters, while they're counting.
+pub(crate) struct PerfCounters {
+    #[cfg(target_os = "linux")]
+    counters: Vec<(&'static str, perf_event::Counter)>,
+}
+
+impl PerfCounters {
+    /// Open the counters and start counting.
+    ///
+    /// Counters that can't be opened, because the CPU doesn't have them or
+    /// `perf_event_paranoid` doesn't allow it, are skipped with a warning.
+    #[cfg(target_os = "linux")]
+    pub fn start() -> Self {
+        let mut counters = Vec::new();
+        for &(name, event) in EVENTS {
+            let counter = perf_event::Builder::new()
+                .kind(event)
+                .inherit(true)
+                .build()
+                .and_then(|mut counter| counter.enable().map(|_| counter));
+            match counter {
+                Ok(counter) => counters.push((name, counter)),
+                Err(e) => crate::warning!("Unable to count {name}: {e}"),
+            }
+        }
+
+        PerfCounters { counters }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn start() -> Self {
+        crate::warning!("--perf-counters is only supported on Linux");
+        PerfCounters {}
+    }
+
+    /// Stop counting, and read what was counted.
+    #[cfg(target_os = "linux")]
+    pub fn stop(self) -> Vec<(&'static str, u64)> {
+        let mut readings = Vec::new();
+        for (name, mut counter) in self.counters {
+            match counter.disable().and_then(|_| counter.read()) {
+                Ok(value) => readings.push((name, value)),
+                Err(e) => crate::warning!("Unable to read the {name} counter: {e}"),
+            }
+        }
+        readings
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn stop(self) -> Vec<(&'static str, u64)> {
+        Vec::new()
+    }
+}
+
+/// Lay the readings out as a table of counter names and values.
+pub(crate) fn format_table(readings: &[(&str, u64)]) -> String {
+    let name_width = readings
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["Counter".len()])
+        .max()
+        .unwrap_or_default();
+    let values: Vec<String> = readings
+        .iter()
+        .map(|(_, value)| thousands(*value))
+        .collect();
+    let value_width = values
+        .iter()
+        .map(String::len)
+        .chain(["Value".len()])
+        .max()
+        .unwrap_or_default();
+
+    let mut table = format!("{:<name_width$}  {:>value_width$}\n", "Counter", "Value");
+    for ((name, _), value) in readings.iter().zip(&values) {
+        table.push_str(&format!("{name:<name_width$}  {value:>value_width$}\n"));
+    }
+    table
+}
+
+/// Format a number with commas between each group of 3 digits.
+fn thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_of_readings() {
+        let table = format_table(&[
+            ("instructions", 1_234_567),
+            ("cache-misses", 42),
+            ("branch-misses", 1000),
+        ]);
+
+        assert_eq!(
+            table,
+            "Counter            Value\n\
+             instructions   1,234,567\n\
+             cache-misses          42\n\
+             branch-misses      1,000\n"
+        );
+    }
+
+    #[test]
+    fn group_digits_by_thousands() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1000), "1,000");
+        assert_eq!(thousands(123_456_789), "123,456,789");
+    }
+}
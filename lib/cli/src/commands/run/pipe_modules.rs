@@ -0,0 +1,207 @@
+//! Support for `wasmer run --pipe-modules`, which runs a main WASI module
+//! and a sidecar side by side, with a pipe from one to the other.
+//!
+//! The main module gets the write end of the pipe, at file descriptor 5,
+//! and the sidecar gets the read end, at file descriptor 6 (`--pipe-fd`
+//! picks others). The sidecar reads EOF once the main module has exited,
+//! and reading the main module's end gives EOF once the sidecar has exited,
+//! so either can tell when the other is gone.
+
+use anyhow::{Context, Error};
+use virtual_fs::Pipe;
+use wasmer::{Module, Store};
+use wasmer_wasix::{types::wasi::Rights, WasiEnvBuilder};
+
+use super::get_exit_code;
+
+/// The file descriptors the ends of the pipe are put at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct PipeFds {
+    /// The main module's end, which it writes to.
+    pub main: u32,
+    /// The sidecar's end, which it reads from.
+    pub sidecar: u32,
+}
+
+impl Default for PipeFds {
+    fn default() -> Self {
+        PipeFds {
+            main: 5,
+            sidecar: 6,
+        }
+    }
+}
+
+impl PipeFds {
+    /// The file descriptors given with `--pipe-fd`, if any.
+    pub fn from_args(fds: &[u32]) -> Result<Self, Error> {
+        let fds = match *fds {
+            [] => PipeFds::default(),
+            [main, sidecar] => PipeFds { main, sidecar },
+            _ => anyhow::bail!("--pipe-fd takes the main module's and the sidecar's descriptors"),
+        };
+
+        for fd in [fds.main, fds.sidecar] {
+            anyhow::ensure!(
+                fd > 2,
+                "The pipe can't be put at file descriptor {fd}, which is used for stdio"
+            );
+        }
+
+        Ok(fds)
+    }
+}
+
+/// A module to run, with its WASI environment and store.
+pub(crate) struct PipedModule {
+    pub builder: WasiEnvBuilder,
+    pub module: Module,
+    pub store: Store,
+}
+
+impl PipedModule {
+    fn run(self) -> Result<(), Error> {
+        self.builder
+            .run_with_store_async(self.module, self.store)
+            .map_err(Error::from)
+    }
+}
+
+/// Run `main` and `sidecar` at the same time, with a pipe from one to the
+/// other, until they have both exited.
+pub(crate) fn run(
+    mut main: PipedModule,
+    mut sidecar: PipedModule,
+    fds: PipeFds,
+) -> Result<(), Error> {
+    let (main_end, sidecar_end) = Pipe::channel();
+    main.builder
+        .add_pipe(fds.main, main_end.clone(), write_end_rights());
+    sidecar
+        .builder
+        .add_pipe(fds.sidecar, sidecar_end.clone(), read_end_rights());
+
+    let (main_result, sidecar_result) = std::thread::scope(|scope| {
+        let sidecar_thread = scope.spawn(move || {
+            let result = sidecar.run();
+            sidecar_end.close();
+            result
+        });
+
+        let main_result = main.run();
+        main_end.close();
+
+        let sidecar_result = sidecar_thread
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("The sidecar's thread panicked")));
+        (main_result, sidecar_result)
+    });
+
+    // Exiting with a zero exit code is reported as an error too
+    let main_succeeded = match &main_result {
+        Ok(()) => true,
+        Err(e) => e
+            .chain()
+            .find_map(get_exit_code)
+            .map_or(false, |code| code.is_success()),
+    };
+    if main_succeeded {
+        sidecar_result.context("The sidecar failed")?;
+    }
+    main_result
+}
+
+/// The main module's end can be written to, and read from to find out when
+/// the sidecar has exited.
+fn write_end_rights() -> Rights {
+    Rights::FD_WRITE
+        | Rights::FD_READ
+        | Rights::FD_SYNC
+        | Rights::FD_DATASYNC
+        | Rights::POLL_FD_READWRITE
+        | Rights::FD_FDSTAT_SET_FLAGS
+}
+
+fn read_end_rights() -> Rights {
+    Rights::FD_READ | Rights::POLL_FD_READWRITE | Rights::FD_FDSTAT_SET_FLAGS
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wasmer_wasix::{runtime::task_manager::tokio::TokioTaskManager, PluggableRuntime, WasiEnv};
+
+    use super::*;
+
+    /// Writes "hello, sidecar" to fd 5, then exits.
+    const MAIN: &str = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_write"
+                (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 16) "hello, sidecar")
+            (func (export "_start")
+                ;; iovec { buf: 16, len: 14 }
+                (i32.store (i32.const 0) (i32.const 16))
+                (i32.store (i32.const 4) (i32.const 14))
+                (drop (call $fd_write (i32.const 5) (i32.const 0) (i32.const 1) (i32.const 8))))
+        )
+    "#;
+
+    /// Reads fd 6 until EOF, then exits with the number of bytes it read.
+    const SIDECAR: &str = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_read"
+                (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory (export "memory") 1)
+            (func (export "_start")
+                (local $total i32)
+                ;; iovec { buf: 64, len: 4 }, so it takes several reads
+                (i32.store (i32.const 0) (i32.const 64))
+                (i32.store (i32.const 4) (i32.const 4))
+                (block $eof
+                    (loop $read
+                        (br_if $eof
+                            (call $fd_read (i32.const 6) (i32.const 0) (i32.const 1) (i32.const 8)))
+                        (br_if $eof (i32.eqz (i32.load (i32.const 8))))
+                        (local.set $total (i32.add (local.get $total) (i32.load (i32.const 8))))
+                        (br $read)))
+                (call $proc_exit (local.get $total)))
+        )
+    "#;
+
+    fn piped(wat: &str) -> PipedModule {
+        let store = Store::default();
+        let module = Module::new(&store, wat).unwrap();
+        let runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::shared()));
+        PipedModule {
+            builder: WasiEnv::builder("piped").runtime(Arc::new(runtime)),
+            module,
+            store,
+        }
+    }
+
+    #[test]
+    fn sidecar_reads_until_the_main_module_exits() {
+        let err = run(piped(MAIN), piped(SIDECAR), PipeFds::default()).unwrap_err();
+
+        // The sidecar saw everything the main module wrote, then EOF
+        let code = err.chain().find_map(get_exit_code).map(|code| code.raw());
+        assert_eq!(code, Some(14), "{err:?}");
+    }
+
+    #[test]
+    fn pipe_fds() {
+        assert_eq!(PipeFds::from_args(&[]).unwrap(), PipeFds::default());
+        assert_eq!(
+            PipeFds::from_args(&[10, 3]).unwrap(),
+            PipeFds {
+                main: 10,
+                sidecar: 3
+            }
+        );
+        assert!(PipeFds::from_args(&[1, 6]).is_err());
+    }
+}
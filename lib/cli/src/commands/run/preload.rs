@@ -0,0 +1,49 @@
+//! Support for `wasmer run --preload-module`, which links the exports of
+//! helper modules into the main module's imports.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{Context, Error};
+use wasmer::{Extern, Imports, Instance, Module, Store};
+
+/// Instantiate each of the modules at `paths`, in order, and work out which of
+/// `module`'s imports their exports satisfy.
+///
+/// Exports are matched to imports by name, whatever namespace the import
+/// comes from, so e.g. a shim exporting `fd_write` replaces
+/// `wasi_snapshot_preview1.fd_write`. When several modules export the same
+/// name, the last one wins. Each helper module can import the exports of the
+/// ones before it.
+pub(crate) fn link(
+    store: &mut Store,
+    paths: &[PathBuf],
+    module: &Module,
+) -> Result<Imports, Error> {
+    let mut exports = HashMap::new();
+
+    for path in paths {
+        let helper = Module::from_file(&*store, path)
+            .with_context(|| format!("Unable to compile \"{}\"", path.display()))?;
+        let imports = imports_for(&helper, &exports);
+        let instance = Instance::new(store, &helper, &imports)
+            .with_context(|| format!("Unable to instantiate \"{}\"", path.display()))?;
+
+        for (name, export) in instance.exports.iter() {
+            exports.insert(name.clone(), export.clone());
+        }
+    }
+
+    Ok(imports_for(module, &exports))
+}
+
+fn imports_for(module: &Module, exports: &HashMap<String, Extern>) -> Imports {
+    let mut imports = Imports::new();
+
+    for import in module.imports() {
+        if let Some(export) = exports.get(import.name()) {
+            imports.define(import.module(), import.name(), export.clone());
+        }
+    }
+
+    imports
+}
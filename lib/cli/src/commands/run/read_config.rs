@@ -0,0 +1,282 @@
+//! Support for `wasmer run --read-config`, which loads more flags from a TOML
+//! file.
+//!
+//! Each key of the file is the name of one of `wasmer run`'s flags, as the
+//! field of [`Run`] it's parsed into (e.g. `stack_size = 4096`), or as it
+//! is spelled on the command line. The flags are spliced into the arguments
+//! where `--read-config` is, before clap parses them, so they're validated
+//! exactly like the ones given on the command line. Flags given on the
+//! command line take precedence: the file's value for them is ignored.
+
+use std::{ffi::OsString, path::Path};
+
+use anyhow::{Context, Error};
+use clap::{Arg, CommandFactory};
+
+use super::Run;
+
+const FLAG: &str = "--read-config";
+
+/// Expands the `--read-config` flag in `args`, the program's arguments, into
+/// the flags set by its file.
+///
+/// `args` is returned unchanged if it doesn't contain the flag.
+pub(crate) fn args_with_config(mut args: Vec<OsString>) -> Result<Vec<OsString>, Error> {
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let (position, path) = match find_flag(&args[..end]) {
+        Some(found) => found,
+        None => return Ok(args),
+    };
+
+    let flags = load(Path::new(&path), &args[..end]).with_context(|| {
+        format!(
+            "Unable to read the flags from \"{}\"",
+            path.to_string_lossy()
+        )
+    })?;
+    args.splice(position..position, flags);
+    Ok(args)
+}
+
+/// Where `--read-config` is in `args`, and the path it was given.
+fn find_flag(args: &[OsString]) -> Option<(usize, OsString)> {
+    for (i, arg) in args.iter().enumerate() {
+        let arg = arg.to_string_lossy();
+        if arg == FLAG {
+            return args.get(i + 1).map(|path| (i, path.clone()));
+        }
+        if let Some(path) = arg.strip_prefix(FLAG).and_then(|s| s.strip_prefix('=')) {
+            return Some((i, path.into()));
+        }
+    }
+    None
+}
+
+/// Turns the TOML file at `path` into command-line flags, leaving out the
+/// ones already present in `cli_args`.
+fn load(path: &Path, cli_args: &[OsString]) -> Result<Vec<OsString>, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    let table: toml::value::Table = toml::from_str(&contents)?;
+    let command = Run::command();
+
+    let mut flags = Vec::new();
+    for (key, value) in table {
+        let arg = command
+            .get_arguments()
+            .find(|arg| is_named(arg, &key))
+            .with_context(|| format!("`{key}` isn't a `wasmer run` flag"))?;
+        let long = arg
+            .get_long()
+            .with_context(|| format!("`{key}` can only be given on the command line"))?;
+        if arg.get_id().as_str() == "read_config" {
+            anyhow::bail!("Config files can't read other config files");
+        }
+        if is_set_on_command_line(arg, cli_args) {
+            continue;
+        }
+        to_flags(long, &key, value, &mut flags)?;
+    }
+    Ok(flags)
+}
+
+/// Whether `key` names `arg`, by its field name or its long flag.
+fn is_named(arg: &Arg, key: &str) -> bool {
+    arg.get_id().as_str() == key
+        || arg
+            .get_long_and_visible_aliases()
+            .into_iter()
+            .flatten()
+            .chain(arg.get_all_aliases().into_iter().flatten())
+            .any(|long| long == key || long.replace('-', "_") == key)
+}
+
+fn is_set_on_command_line(arg: &Arg, cli_args: &[OsString]) -> bool {
+    let longs: Vec<_> = arg
+        .get_long_and_visible_aliases()
+        .into_iter()
+        .flatten()
+        .chain(arg.get_all_aliases().into_iter().flatten())
+        .map(|long| format!("--{long}"))
+        .collect();
+    let shorts: Vec<_> = arg
+        .get_short_and_visible_aliases()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    cli_args.iter().any(|cli_arg| {
+        let cli_arg = cli_arg.to_string_lossy();
+        let flag = cli_arg.split('=').next().unwrap_or_default();
+        if longs.iter().any(|long| long == flag) {
+            return true;
+        }
+        // Short flags can be combined, as in `-vv` or `-einvoke`
+        match cli_arg.strip_prefix('-') {
+            Some(rest) if !rest.starts_with('-') => {
+                rest.chars().next().map_or(false, |c| shorts.contains(&c))
+            }
+            _ => false,
+        }
+    })
+}
+
+fn to_flags(
+    long: &str,
+    key: &str,
+    value: toml::Value,
+    flags: &mut Vec<OsString>,
+) -> Result<(), Error> {
+    let value = match value {
+        toml::Value::Boolean(set) => {
+            if set {
+                flags.push(format!("--{long}").into());
+            }
+            return Ok(());
+        }
+        toml::Value::String(s) => s,
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Array(values) => {
+            for value in values {
+                to_flags(long, key, value, flags)?;
+            }
+            return Ok(());
+        }
+        toml::Value::Datetime(_) | toml::Value::Table(_) => {
+            anyhow::bail!("`{key}` should be a string, a number, a boolean or an array")
+        }
+    };
+    flags.push(format!("--{long}={value}").into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn flags_are_spliced_in() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("profile.toml");
+        std::fs::write(&config, "env = [\"A=1\", \"B=2\"]\n").unwrap();
+        let config = config.to_str().unwrap();
+
+        let expanded = args_with_config(args(&[
+            "wasmer",
+            "run",
+            "--read-config",
+            config,
+            "app.wasm",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            expanded,
+            args(&[
+                "wasmer",
+                "run",
+                "--env=A=1",
+                "--env=B=2",
+                "--read-config",
+                config,
+                "app.wasm",
+            ])
+        );
+    }
+
+    #[test]
+    fn values_become_flags() {
+        let mut flags = Vec::new();
+        to_flags(
+            "stack-size",
+            "stack_size",
+            toml::Value::Integer(4096),
+            &mut flags,
+        )
+        .unwrap();
+        to_flags(
+            "wasm-simd",
+            "wasm_simd",
+            toml::Value::Boolean(true),
+            &mut flags,
+        )
+        .unwrap();
+        to_flags(
+            "tail-call",
+            "tail_call",
+            toml::Value::Boolean(false),
+            &mut flags,
+        )
+        .unwrap();
+
+        assert_eq!(flags, args(&["--stack-size=4096", "--wasm-simd"]));
+    }
+
+    #[test]
+    fn command_line_takes_precedence() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("profile.toml");
+        std::fs::write(&config, "stack_size = 4096\nentrypoint = \"main\"\n").unwrap();
+        let config = format!("--read-config={}", config.display());
+
+        let expanded = args_with_config(args(&[
+            "wasmer",
+            "run",
+            &config,
+            "--stack-size",
+            "8192",
+            "--invoke=start",
+            "app.wasm",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            expanded,
+            args(&[
+                "wasmer",
+                "run",
+                &config,
+                "--stack-size",
+                "8192",
+                "--invoke=start",
+                "app.wasm",
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join("profile.toml");
+        std::fs::write(&config, "not_a_flag = true\n").unwrap();
+
+        let err = args_with_config(args(&[
+            "wasmer",
+            "run",
+            "--read-config",
+            config.to_str().unwrap(),
+            "app.wasm",
+        ]))
+        .unwrap_err();
+
+        assert!(format!("{err:?}").contains("`not_a_flag` isn't a `wasmer run` flag"));
+    }
+
+    #[test]
+    fn arguments_after_a_double_dash_are_left_alone() {
+        let original = args(&["wasmer", "run", "app.wasm", "--", "--read-config", "x"]);
+
+        let expanded = args_with_config(original.clone()).unwrap();
+
+        assert_eq!(expanded, original);
+    }
+}
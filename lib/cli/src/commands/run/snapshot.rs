@@ -0,0 +1,133 @@
+//! Support for `wasmer run --export-snapshot`, which records the state of
+//! the instance's exports once the entrypoint has returned.
+//!
+//! The snapshot is a JSON object with an entry for each exported global,
+//! table and memory, keyed by its export name:
+//!
+//! - globals give their type and value, e.g. `{"type": "i32", "value": 42}`.
+//!   Floats which aren't finite are written as `"nan"`, `"inf"` or `"-inf"`
+//! - tables give their size in elements, e.g. `{"type": "table", "value": 4}`
+//! - memories give their size in pages, e.g. `{"type": "memory", "value": 1}`
+//!
+//! Globals of other types (`v128` and references) and functions are left
+//! out.
+
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+use wasmer::{Extern, Instance, Store, Value};
+
+/// The state of an instance's exports.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub(crate) struct ExportSnapshot {
+    exports: BTreeMap<String, ExportState>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+enum ExportState {
+    I32(i32),
+    I64(i64),
+    F32(Float<f32>),
+    F64(Float<f64>),
+    Table(u32),
+    Memory(u32),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Float<T> {
+    Finite(T),
+    NonFinite(&'static str),
+}
+
+impl<T: Copy + Into<f64>> Float<T> {
+    fn new(value: T) -> Self {
+        let wide: f64 = value.into();
+        if wide.is_nan() {
+            Float::NonFinite("nan")
+        } else if wide == f64::INFINITY {
+            Float::NonFinite("inf")
+        } else if wide == f64::NEG_INFINITY {
+            Float::NonFinite("-inf")
+        } else {
+            Float::Finite(value)
+        }
+    }
+}
+
+impl ExportSnapshot {
+    pub fn capture(instance: &Instance, store: &mut Store) -> Self {
+        let mut exports = BTreeMap::new();
+        for (name, export) in instance.exports.iter() {
+            let state = match export {
+                Extern::Global(global) => match global.get(store) {
+                    Value::I32(value) => ExportState::I32(value),
+                    Value::I64(value) => ExportState::I64(value),
+                    Value::F32(value) => ExportState::F32(Float::new(value)),
+                    Value::F64(value) => ExportState::F64(Float::new(value)),
+                    _ => continue,
+                },
+                Extern::Table(table) => ExportState::Table(table.size(&*store)),
+                Extern::Memory(memory) => ExportState::Memory(memory.view(&*store).size().0),
+                _ => continue,
+            };
+            exports.insert(name.clone(), state);
+        }
+
+        ExportSnapshot { exports }
+    }
+
+    /// Write the snapshot to `path` as JSON.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)
+            .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        writeln!(file)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer::{imports, Module};
+
+    use super::*;
+
+    #[test]
+    fn snapshot_of_the_exports() {
+        let mut store = Store::default();
+        let module = Module::new(
+            &store,
+            r#"(module
+                (global (export "count") (mut i32) (i32.const 42))
+                (global (export "big") i64 (i64.const -9007199254740993))
+                (global (export "ratio") f32 (f32.const 0.5))
+                (global (export "missing") f64 (f64.const nan))
+                (global (export "vector") v128 (v128.const i64x2 0 0))
+                (table (export "table") 3 funcref)
+                (memory (export "memory") 2)
+                (func (export "_start"))
+            )"#,
+        )
+        .unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+
+        let snapshot = ExportSnapshot::capture(&instance, &mut store);
+        let json = serde_json::to_value(&snapshot).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "count": {"type": "i32", "value": 42},
+                "big": {"type": "i64", "value": -9007199254740993_i64},
+                "ratio": {"type": "f32", "value": 0.5},
+                "missing": {"type": "f64", "value": "nan"},
+                "table": {"type": "table", "value": 3},
+                "memory": {"type": "memory", "value": 2},
+            })
+        );
+    }
+}
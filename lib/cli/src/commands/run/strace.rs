@@ -0,0 +1,252 @@
+//! A [`tracing`] layer which records every WASI syscall to a file using the
+//! same notation as `strace -f -ttt`.
+
+use std::{
+    fmt::{Debug, Write as _},
+    fs::File,
+    io::{LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context as _, Error};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use wasmer::FromToNativeWasmType;
+use wasmer_wasix::types::wasi::Errno;
+
+/// The module all syscalls are implemented in. Every syscall is annotated with
+/// `#[instrument(ret)]` at either the `debug` or `trace` level, so we get a
+/// span per call and an event carrying its result.
+const SYSCALL_TARGET: &str = "wasmer_wasix::syscalls";
+
+/// The span `wasmer_wasix` enters while a thread runs the module, which
+/// records the WASI process ID in its `pid` field.
+const THREAD_SPAN: &str = "wasi_thread";
+
+/// Writes a strace-compatible log of all syscalls made by the guest.
+///
+/// Each syscall is written as a single line when it returns, e.g.
+///
+/// ```text
+/// 1234  1686638712.123456 fd_write(fd=1, nwritten=14) = 0
+/// 1234  1686638712.123789 path_open(fd=3, path="missing.txt") = -1 ENOENT (Errno::noent)
+/// ```
+///
+/// The underlying file is flushed after every line so the trace is complete
+/// even if the module (or the runtime) crashes.
+#[derive(Debug)]
+pub(crate) struct StraceLayer {
+    output: Mutex<LineWriter<File>>,
+}
+
+impl StraceLayer {
+    pub(crate) fn create(path: &Path) -> Result<Self, Error> {
+        let f = File::create(path)
+            .with_context(|| format!("Unable to create \"{}\"", path.display()))?;
+
+        Ok(StraceLayer {
+            output: Mutex::new(LineWriter::new(f)),
+        })
+    }
+
+    /// Is this span or event part of a syscall's instrumentation, or the span
+    /// saying which process made it?
+    ///
+    /// This should be used as a per-layer filter so syscalls get traced
+    /// regardless of the verbosity used for normal logging.
+    pub(crate) fn is_traced(metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with(SYSCALL_TARGET) || Self::is_thread(metadata)
+    }
+
+    fn is_thread(metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with("wasmer_wasix") && metadata.name() == THREAD_SPAN
+    }
+
+    fn write_line(&self, call: &Syscall, result: &SyscallResult) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut line = format!(
+            "{:<5} {}.{:06} {}({})",
+            call.pid,
+            timestamp.as_secs(),
+            timestamp.subsec_micros(),
+            call.name,
+            call.args.join(", "),
+        );
+
+        let _ = match result {
+            SyscallResult::Errno(Errno::Success) => write!(line, " = 0"),
+            SyscallResult::Errno(errno) => {
+                let name = errno.name().to_uppercase();
+                write!(line, " = -1 E{name} ({errno:?})")
+            }
+            SyscallResult::Other(value) => write!(line, " = {value}"),
+            SyscallResult::Error(error) => write!(line, " = ? <{error}>"),
+        };
+
+        if let Ok(mut output) = self.output.lock() {
+            let _ = writeln!(output, "{line}");
+        }
+    }
+}
+
+impl<S> Layer<S> for StraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+
+        if Self::is_thread(attrs.metadata()) {
+            let mut thread = Thread::default();
+            attrs.record(&mut thread);
+            span.extensions_mut().insert(thread);
+            return;
+        }
+
+        // Syscalls made outside of a thread span are reported as process 0
+        let pid = span
+            .scope()
+            .skip(1)
+            .find_map(|parent| parent.extensions().get::<Thread>().map(|t| t.pid))
+            .unwrap_or_default();
+        let mut call = Syscall {
+            pid,
+            name: attrs.metadata().name(),
+            args: Vec::new(),
+        };
+        attrs.record(&mut call);
+        span.extensions_mut().insert(call);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(call) = span.extensions_mut().get_mut::<Syscall>() {
+                values.record(call);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !event.metadata().target().starts_with(SYSCALL_TARGET) {
+            return;
+        }
+
+        let mut visitor = ResultVisitor::default();
+        event.record(&mut visitor);
+        let result = match visitor.result {
+            Some(r) => r,
+            // Just a normal log message emitted while inside the syscall
+            None => return,
+        };
+
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(call) = span.extensions().get::<Syscall>() {
+                self.write_line(call, &result);
+            }
+        }
+    }
+}
+
+/// The process which a thread running the module belongs to.
+#[derive(Debug, Default)]
+struct Thread {
+    pid: u64,
+}
+
+impl Visit for Thread {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "pid" {
+            self.pid = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+/// The process, name and arguments for a syscall currently in progress.
+#[derive(Debug)]
+struct Syscall {
+    pid: u64,
+    name: &'static str,
+    args: Vec<String>,
+}
+
+impl Syscall {
+    fn set(&mut self, field: &Field, value: String) {
+        let arg = format!("{}={}", field.name(), value);
+        let prefix = format!("{}=", field.name());
+
+        // Fields declared as `field::Empty` get filled in later on, so make
+        // sure we replace the previous value instead of appending.
+        match self.args.iter_mut().find(|a| a.starts_with(&prefix)) {
+            Some(existing) => *existing = arg,
+            None => self.args.push(arg),
+        }
+    }
+}
+
+impl Visit for Syscall {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, format!("{value:?}"));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field, format!("{value:?}"));
+    }
+}
+
+#[derive(Debug)]
+enum SyscallResult {
+    /// The syscall returned an [`Errno`].
+    Errno(Errno),
+    /// The syscall returned some other value.
+    Other(String),
+    /// The syscall failed with a [`WasiError`][wasmer_wasix::WasiError] (e.g.
+    /// the process exited).
+    Error(String),
+}
+
+/// Picks out the `return` and `error` fields emitted by `#[instrument(ret, err)]`.
+#[derive(Debug, Default)]
+struct ResultVisitor {
+    result: Option<SyscallResult>,
+}
+
+impl Visit for ResultVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}");
+
+        match field.name() {
+            "return" => {
+                self.result = Some(match parse_errno(&value) {
+                    Some(errno) => SyscallResult::Errno(errno),
+                    None => SyscallResult::Other(value),
+                });
+            }
+            "error" => self.result = Some(SyscallResult::Error(value)),
+            _ => {}
+        }
+    }
+}
+
+/// Turn the `Debug` representation of an [`Errno`] (e.g. `Errno::noent`)
+/// back into the [`Errno`]. Fields only give us `&dyn Debug`, so this is the
+/// only way to get at the value.
+fn parse_errno(value: &str) -> Option<Errno> {
+    let name = value.strip_prefix("Errno::")?;
+    (0..=Errno::Unknown as i32)
+        .map(Errno::from_native)
+        .find(|errno| errno.name() == name)
+}
@@ -0,0 +1,231 @@
+//! Support for `wasmer run --type-reflection`, which gives the module host
+//! functions to inspect the types of its functions at runtime, in the spirit
+//! of the type reflection proposal.
+//!
+//! The functions are imported from the `wasm:type-reflection` namespace, and
+//! refer to types by their index in the module's type section:
+//!
+//! - `wasm_typeof(funcref) -> i32`: the index of the function's type
+//! - `wasm_cast(funcref, i32) -> funcref`: the function if it has the type at
+//!   the index, or null
+//! - `wasm_type_param_count(i32) -> i32` and
+//!   `wasm_type_result_count(i32) -> i32`
+//! - `wasm_type_param(i32, i32) -> i32` and
+//!   `wasm_type_result(i32, i32) -> i32`: the type of a parameter or result,
+//!   as its binary encoding (`0x7f` for `i32`, `0x70` for `funcref`, etc.)
+//!
+//! Null references, and indices which are out of range, give -1. Other
+//! imports from the namespace, and imports with other signatures, are stubs
+//! which trap when called, so the module can still be instantiated.
+
+use wasmer::{
+    ExternType, Function, FunctionEnv, FunctionEnvMut, FunctionType, Imports, Module, RuntimeError,
+    Store, Type, Value,
+};
+
+/// The namespace the functions are imported from.
+pub(crate) const NAMESPACE: &str = "wasm:type-reflection";
+
+/// The types of the module's type section, by index.
+type Types = Vec<FunctionType>;
+
+type Reflection = fn(FunctionEnvMut<'_, Types>, &[Value]) -> Result<Vec<Value>, RuntimeError>;
+
+/// Define the module's imports from [`NAMESPACE`] which `imports` doesn't
+/// have yet.
+pub(crate) fn define_imports(store: &mut Store, module: &Module, imports: &mut Imports) {
+    let types: Types = module.info().signatures.values().cloned().collect();
+    let env = FunctionEnv::new(store, types);
+
+    for import in module.imports() {
+        let ty = match import.ty() {
+            ExternType::Function(ty) if import.module() == NAMESPACE => ty.clone(),
+            _ => continue,
+        };
+        if imports.exists(NAMESPACE, import.name()) {
+            continue;
+        }
+
+        let name = import.name().to_string();
+        let function = match reflection(&name, &ty) {
+            Some(reflection) => Function::new_with_env(store, &env, ty, reflection),
+            None => {
+                crate::warning!(
+                    "\"{NAMESPACE}\" doesn't have a \"{name}\" function with the signature {ty}, so calling it will trap"
+                );
+                let message = format!("\"{NAMESPACE}\" doesn't have a \"{name}\" function");
+                Function::new(store, ty, move |_| Err(RuntimeError::new(&message)))
+            }
+        };
+        imports.define(NAMESPACE, import.name(), function);
+    }
+}
+
+/// The implementation of the function called `name`, if it has the type
+/// `ty`.
+fn reflection(name: &str, ty: &FunctionType) -> Option<Reflection> {
+    use Type::{FuncRef, I32};
+
+    let (expected, reflection): (FunctionType, Reflection) = match name {
+        "wasm_typeof" => (FunctionType::new([FuncRef], [I32]), type_of),
+        "wasm_cast" => (FunctionType::new([FuncRef, I32], [FuncRef]), cast),
+        "wasm_type_param_count" => (FunctionType::new([I32], [I32]), param_count),
+        "wasm_type_result_count" => (FunctionType::new([I32], [I32]), result_count),
+        "wasm_type_param" => (FunctionType::new([I32, I32], [I32]), param),
+        "wasm_type_result" => (FunctionType::new([I32, I32], [I32]), result),
+        _ => return None,
+    };
+    (*ty == expected).then_some(reflection)
+}
+
+fn type_of(env: FunctionEnvMut<'_, Types>, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let index = match &args[0] {
+        Value::FuncRef(Some(function)) => {
+            let ty = function.ty(&env);
+            env.data()
+                .iter()
+                .position(|candidate| *candidate == ty)
+                .map_or(-1, |index| index as i32)
+        }
+        _ => -1,
+    };
+    Ok(vec![Value::I32(index)])
+}
+
+fn cast(env: FunctionEnvMut<'_, Types>, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let function = match (&args[0], lookup(&env, args[1].unwrap_i32())) {
+        (Value::FuncRef(Some(function)), Some(ty)) if function.ty(&env) == *ty => {
+            Some(function.clone())
+        }
+        _ => None,
+    };
+    Ok(vec![Value::FuncRef(function)])
+}
+
+fn param_count(env: FunctionEnvMut<'_, Types>, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let count = lookup(&env, args[0].unwrap_i32()).map_or(-1, |ty| ty.params().len() as i32);
+    Ok(vec![Value::I32(count)])
+}
+
+fn result_count(
+    env: FunctionEnvMut<'_, Types>,
+    args: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    let count = lookup(&env, args[0].unwrap_i32()).map_or(-1, |ty| ty.results().len() as i32);
+    Ok(vec![Value::I32(count)])
+}
+
+fn param(env: FunctionEnvMut<'_, Types>, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let code = lookup(&env, args[0].unwrap_i32())
+        .and_then(|ty| nth(ty.params(), args[1].unwrap_i32()))
+        .map_or(-1, encoding);
+    Ok(vec![Value::I32(code)])
+}
+
+fn result(env: FunctionEnvMut<'_, Types>, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let code = lookup(&env, args[0].unwrap_i32())
+        .and_then(|ty| nth(ty.results(), args[1].unwrap_i32()))
+        .map_or(-1, encoding);
+    Ok(vec![Value::I32(code)])
+}
+
+fn lookup<'a>(env: &'a FunctionEnvMut<'_, Types>, index: i32) -> Option<&'a FunctionType> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| env.data().get(index))
+}
+
+fn nth(types: &[Type], index: i32) -> Option<Type> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| types.get(index).copied())
+}
+
+/// The type's encoding in the binary format.
+fn encoding(ty: Type) -> i32 {
+    match ty {
+        Type::I32 => 0x7f,
+        Type::I64 => 0x7e,
+        Type::F32 => 0x7d,
+        Type::F64 => 0x7c,
+        Type::V128 => 0x7b,
+        Type::FuncRef => 0x70,
+        Type::ExternRef => 0x6f,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer::{imports, Instance, TypedFunction};
+
+    use super::*;
+
+    const REFLECTING: &str = r#"
+        (module
+            (type $binary (func (param i32 i64) (result f64)))
+            (type $unary (func (param i32) (result i32)))
+            (import "wasm:type-reflection" "wasm_typeof" (func $typeof (param funcref) (result i32)))
+            (import "wasm:type-reflection" "wasm_cast" (func $cast (param funcref i32) (result funcref)))
+            (import "wasm:type-reflection" "wasm_type_param_count" (func $param_count (param i32) (result i32)))
+            (import "wasm:type-reflection" "wasm_type_param" (func $param (param i32 i32) (result i32)))
+            (import "wasm:type-reflection" "wasm_type_result" (func $result (param i32 i32) (result i32)))
+            (import "wasm:type-reflection" "wasm_reify" (func $reify (param i32)))
+            (func $double (type $unary) (i32.mul (local.get 0) (i32.const 2)))
+            (elem declare func $double)
+
+            (func (export "typeof") (result i32) (call $typeof (ref.func $double)))
+            (func (export "typeof_null") (result i32) (call $typeof (ref.null func)))
+            (func (export "cast_ok") (result i32) (ref.is_null (call $cast (ref.func $double) (i32.const 1))))
+            (func (export "cast_wrong") (result i32) (ref.is_null (call $cast (ref.func $double) (i32.const 0))))
+            (func (export "param_count") (param i32) (result i32) (call $param_count (local.get 0)))
+            (func (export "param") (param i32 i32) (result i32) (call $param (local.get 0) (local.get 1)))
+            (func (export "result") (param i32 i32) (result i32) (call $result (local.get 0) (local.get 1)))
+            (func (export "reify") (call $reify (i32.const 0)))
+        )
+    "#;
+
+    #[test]
+    fn reflect_the_module_types() {
+        let mut store = Store::default();
+        let module = Module::new(&store, REFLECTING).unwrap();
+        let mut imports = imports! {};
+        define_imports(&mut store, &module, &mut imports);
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+
+        let call = |store: &mut Store, name: &str| -> i32 {
+            let f: TypedFunction<(), i32> =
+                instance.exports.get_typed_function(store, name).unwrap();
+            f.call(store).unwrap()
+        };
+        assert_eq!(call(&mut store, "typeof"), 1);
+        assert_eq!(call(&mut store, "typeof_null"), -1);
+        assert_eq!(call(&mut store, "cast_ok"), 0);
+        assert_eq!(call(&mut store, "cast_wrong"), 1);
+
+        let param_count: TypedFunction<i32, i32> = instance
+            .exports
+            .get_typed_function(&store, "param_count")
+            .unwrap();
+        assert_eq!(param_count.call(&mut store, 0).unwrap(), 2);
+        assert_eq!(param_count.call(&mut store, 7).unwrap(), -1);
+        let param: TypedFunction<(i32, i32), i32> = instance
+            .exports
+            .get_typed_function(&store, "param")
+            .unwrap();
+        assert_eq!(param.call(&mut store, 0, 1).unwrap(), 0x7e);
+        assert_eq!(param.call(&mut store, 0, 2).unwrap(), -1);
+        let result: TypedFunction<(i32, i32), i32> = instance
+            .exports
+            .get_typed_function(&store, "result")
+            .unwrap();
+        assert_eq!(result.call(&mut store, 0, 0).unwrap(), 0x7c);
+
+        // Unknown functions are stubs
+        let reify: TypedFunction<(), ()> = instance
+            .exports
+            .get_typed_function(&store, "reify")
+            .unwrap();
+        let err = reify.call(&mut store).unwrap_err();
+        assert!(err.message().contains("wasm_reify"), "{err}");
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
     collections::{BTreeSet, HashMap},
+    fs::{File, OpenOptions},
     path::{Path, PathBuf},
+    str::FromStr,
     sync::{mpsc::Sender, Arc},
     time::Duration,
 };
@@ -10,8 +12,14 @@ use bytes::Bytes;
 use clap::Parser;
 use tokio::runtime::Handle;
 use url::Url;
-use virtual_fs::{DeviceFile, FileSystem, PassthruFileSystem, RootFileSystemBuilder};
-use wasmer::{Engine, Function, Instance, Memory32, Memory64, Module, RuntimeError, Store, Value};
+use virtual_fs::{
+    host_fs::Stdout, DeviceFile, FileSystem, NullFile, PassthruFileSystem, RootFileSystemBuilder,
+    TeeFile, WriteLimitMode,
+};
+use virtual_net::VirtualNetworking;
+use wasmer::{
+    Engine, Function, Imports, Instance, Memory32, Memory64, Module, RuntimeError, Store, Value,
+};
 use wasmer_registry::wasmer_env::WasmerEnv;
 use wasmer_wasix::{
     bin_factory::BinaryPackage,
@@ -36,7 +44,11 @@ use wasmer_wasix::{
     WasiVersion,
 };
 
-use crate::utils::{parse_envvar, parse_mapdir};
+use crate::commands::run::deferred_cache::DeferredCache;
+use crate::commands::run::dns_cache::DnsCachingNetworking;
+use crate::commands::run::har::HttpCapture;
+use crate::commands::run::net_connect::{AllowedHost, AllowlistedNetworking, ConnectAllowlist};
+use crate::utils::{parse_envvar, parse_mapdir, parse_size};
 
 const WAPM_SOURCE_CACHE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
@@ -55,6 +67,12 @@ pub struct Wasi {
     )]
     pub(crate) mapped_dirs: Vec<MappedDirectory>,
 
+    /// Mount this host directory at `/tmp` and set `TMPDIR` to `/tmp`, so
+    /// the module's temporary files are created in it (it's created if it
+    /// doesn't exist).
+    #[clap(long, value_name = "PATH")]
+    pub(crate) tempdir: Option<PathBuf>,
+
     /// Pass custom environment variables
     #[clap(
         long = "env",
@@ -80,6 +98,16 @@ pub struct Wasi {
     #[clap(long = "map-command", name = "MAPCMD")]
     map_commands: Vec<String>,
 
+    /// Don't query the registry or download anything, only using the
+    /// packages saved by `wasmer package vendor`.
+    #[clap(long)]
+    pub(crate) offline: bool,
+
+    /// The directory `wasmer package vendor` saved packages to, for use
+    /// with `--offline` (`./vendor` by default).
+    #[clap(long, value_name = "DIR", requires = "offline")]
+    pub(crate) vendor_dir: Option<PathBuf>,
+
     /// Enable experimental IO devices
     #[cfg(feature = "experimental-io-devices")]
     #[cfg_attr(
@@ -94,14 +122,128 @@ pub struct Wasi {
     #[clap(long = "net")]
     pub networking: bool,
 
+    /// Send all of the module's outbound traffic through this host network
+    /// interface (e.g. `eth1`) instead of the default route (Linux only).
+    #[clap(long, value_name = "NAME", requires = "networking")]
+    pub net_interface: Option<String>,
+
+    /// Record the module's HTTP requests, and the responses to them, in a
+    /// HAR file at this path.
+    ///
+    /// Only plain-text HTTP/1.x is decoded, so HTTPS and HTTP/2 traffic
+    /// isn't recorded.
+    #[clap(long, value_name = "PATH", requires = "networking")]
+    pub net_capture_http: Option<PathBuf>,
+
+    /// Only allow the module to open TCP connections to this host, given as
+    /// `<host>:<port>` (e.g. `api.example.com:443`) or as `<host>` for any
+    /// port (can be repeated).
+    ///
+    /// Hosts are resolved at startup, and again every
+    /// `--net-connect-dns-ttl` seconds.
+    #[clap(long, value_name = "HOST", requires = "networking")]
+    pub allow_net_connect: Vec<AllowedHost>,
+
+    /// How often the `--allow-net-connect` hosts are resolved again, in
+    /// seconds (default: 60).
+    #[clap(long, value_name = "SECONDS", requires = "allow_net_connect")]
+    pub net_connect_dns_ttl: Option<u64>,
+
+    /// Abort if an `--allow-net-connect` host can't be resolved at startup,
+    /// rather than warning and refusing connections to it until it resolves.
+    #[clap(long, requires = "allow_net_connect")]
+    pub net_connect_strict: bool,
+
+    /// Keep the addresses the module's hostnames resolve to for this many
+    /// seconds, rather than asking the host's resolver again on every
+    /// lookup. `0` turns the cache off, which is the default.
+    #[clap(long, value_name = "SECONDS", requires = "networking")]
+    pub net_dns_cache_ttl: Option<u64>,
+
     /// Disables the TTY bridge
     #[clap(long = "no-tty")]
     pub no_tty: bool,
 
+    /// Make stdin behave like `/dev/null`, so the module reads EOF straight
+    /// away instead of waiting for input.
+    #[clap(long)]
+    pub stdin_null: bool,
+
+    /// Only let the first BYTES (e.g. `10m`) the module writes to stdout
+    /// through. Writes after that fail with `ENOSPC`, which makes most
+    /// programs exit.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    pub stdout_limit: Option<u64>,
+
+    /// Like `--stdout-limit`, but silently throw away anything written to
+    /// stdout past the limit instead of failing the writes.
+    #[clap(
+        long,
+        value_name = "BYTES",
+        value_parser = parse_size,
+        conflicts_with = "stdout_limit"
+    )]
+    pub stdout_limit_truncate: Option<u64>,
+
+    /// Copy everything the module writes to stdout into PATH as well as
+    /// printing it. Unlike piping through `tee`, the exit code is kept.
+    #[clap(long, value_name = "PATH")]
+    pub tee: Option<PathBuf>,
+
+    /// Like `--tee`, but append to PATH instead of truncating it.
+    #[clap(long, value_name = "PATH", conflicts_with = "tee")]
+    pub tee_append: Option<PathBuf>,
+
+    /// When stdout is a pipe, grow its buffer to this size (e.g. `1m`) so
+    /// streaming lots of output to the reading end takes fewer context
+    /// switches.
+    ///
+    /// Only supported on Linux, and ignored elsewhere.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    pub pipe_size: Option<u64>,
+
     /// Enables asynchronous threading
     #[clap(long = "enable-async-threads")]
     pub enable_async_threads: bool,
 
+    /// The maximum number of threads each process can spawn, or "unlimited"
+    /// to let trusted workloads spawn as many as they like.
+    ///
+    /// Spawning a thread beyond the limit fails with `EAGAIN`.
+    #[clap(long, value_name = "N")]
+    pub thread_count: Option<ThreadCount>,
+
+    /// The stack size of the native threads backing the threads the module
+    /// spawns (e.g. `2m`), for deeply recursive multi-threaded programs.
+    ///
+    /// Unlike `--stack-size`, this doesn't change the main thread's stack.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    pub wasm_threads_stack_size: Option<u64>,
+
+    /// How large the module's shared memory may grow (e.g. `256m`), which
+    /// must fit within the maximum the module declares.
+    ///
+    /// Threads' stacks are allocated inside this memory, so they all come
+    /// out of the same budget.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    pub shm_size: Option<u64>,
+
+    /// Only allow the module to call these WASI/WASIX syscalls, given as a
+    /// comma-separated list of function names (e.g. `fd_read,fd_write`).
+    ///
+    /// Any other syscall fails with `ENOSYS`. `proc_exit` is always allowed,
+    /// so an empty list lets the module exit but do nothing else.
+    #[clap(long, value_name = "SYSCALLS")]
+    pub sandbox_syscalls: Option<String>,
+
+    /// Raise the signals the module raises (e.g. `SIGTERM`, or the
+    /// `SIGALRM` of a timeout it sets up) in the `wasmer` process as well,
+    /// so they have the effect they'd have on a native program.
+    ///
+    /// Only supported on Unix.
+    #[clap(long)]
+    pub posix_signals: bool,
+
     /// Allow instances to send http requests.
     ///
     /// Access to domains is granted by default.
@@ -113,6 +255,29 @@ pub struct Wasi {
     pub deny_multiple_wasi_versions: bool,
 }
 
+/// The limit passed to `--thread-count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadCount {
+    /// Allow at most this many threads per process.
+    Limited(usize),
+    /// Don't limit the number of threads.
+    Unlimited,
+}
+
+impl FromStr for ThreadCount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "unlimited" {
+            return Ok(ThreadCount::Unlimited);
+        }
+
+        s.parse().map(ThreadCount::Limited).map_err(|_| {
+            anyhow::anyhow!("Expected a number of threads or \"unlimited\", but found \"{s}\"")
+        })
+    }
+}
+
 pub struct RunProperties {
     pub ctx: WasiFunctionEnv,
     pub path: PathBuf,
@@ -133,6 +298,35 @@ impl Wasi {
         self.env_vars.push((key.to_string(), value.to_string()));
     }
 
+    /// Map the directory given with `--tempdir` to `/tmp`, and point `TMPDIR`
+    /// at it unless it's set explicitly with `--env`.
+    pub fn mount_tempdir(&mut self) -> Result<()> {
+        let Some(tempdir) = &self.tempdir else {
+            return Ok(());
+        };
+
+        if self
+            .mapped_dirs
+            .iter()
+            .any(|dir| dir.guest.trim_end_matches('/') == "/tmp")
+        {
+            anyhow::bail!("--tempdir can't be used when another directory is mapped to /tmp");
+        }
+
+        std::fs::create_dir_all(tempdir)
+            .with_context(|| format!("Unable to create \"{}\"", tempdir.display()))?;
+        let host = tempdir
+            .canonicalize()
+            .with_context(|| format!("Unable to canonicalize \"{}\"", tempdir.display()))?;
+        self.map_dir("/tmp", host);
+
+        if !self.env_vars.iter().any(|(key, _)| key == "TMPDIR") {
+            self.set_env("TMPDIR", "/tmp");
+        }
+
+        Ok(())
+    }
+
     /// Gets the WASI version (if any) for the provided module
     pub fn get_versions(module: &Module) -> Option<BTreeSet<WasiVersion>> {
         // Get the wasi version in non-strict mode, so multiple wasi versions
@@ -197,6 +391,8 @@ impl Wasi {
                     } else {
                         host
                     };
+                    // Empty directories like `/tmp` can be mounted over
+                    let _ = root_fs.remove_dir(Path::new(&guest));
                     root_fs.mount(guest.into(), &fs_backing, host)?;
                 }
             }
@@ -220,6 +416,21 @@ impl Wasi {
 
         *builder.capabilities_mut() = self.capabilities();
 
+        if self.stdin_null {
+            builder.set_stdin(Box::<NullFile>::default());
+        }
+
+        if let Some(file) = self.open_tee()? {
+            builder.set_stdout(Box::new(TeeFile::new(
+                Box::<Stdout>::default(),
+                Box::new(file),
+            )));
+        }
+
+        if let Some((limit, mode)) = self.stdout_limit() {
+            builder.set_stdout_limit(limit, mode);
+        }
+
         #[cfg(feature = "experimental-io-devices")]
         {
             if self.enable_experimental_io_devices {
@@ -231,6 +442,76 @@ impl Wasi {
         Ok(builder)
     }
 
+    /// The cap on stdout given with `--stdout-limit` or
+    /// `--stdout-limit-truncate`, if any.
+    pub fn stdout_limit(&self) -> Option<(u64, WriteLimitMode)> {
+        match (self.stdout_limit, self.stdout_limit_truncate) {
+            (Some(limit), _) => Some((limit, WriteLimitMode::Error)),
+            (None, Some(limit)) => Some((limit, WriteLimitMode::Truncate)),
+            (None, None) => None,
+        }
+    }
+
+    /// Grow the pipe stdout is connected to, if it is one, to the size
+    /// given with `--pipe-size`.
+    ///
+    /// This is only ever an optimization, so failing to is just logged.
+    pub fn resize_stdout_pipe(&self) {
+        let Some(size) = self.pipe_size else {
+            return;
+        };
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let fd = std::io::stdout().as_raw_fd();
+            let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+            let is_pipe = unsafe { libc::fstat(fd, stat.as_mut_ptr()) } == 0
+                && unsafe { stat.assume_init() }.st_mode & libc::S_IFMT == libc::S_IFIFO;
+            if !is_pipe {
+                tracing::debug!("Ignoring --pipe-size because stdout isn't a pipe");
+                return;
+            }
+
+            let requested = libc::c_int::try_from(size).unwrap_or(libc::c_int::MAX);
+            match unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, requested) } {
+                -1 => tracing::debug!(
+                    size,
+                    error = %std::io::Error::last_os_error(),
+                    "Unable to resize the stdout pipe",
+                ),
+                // The kernel rounds the size up to a power of two pages
+                actual => tracing::debug!(size, actual, "Resized the stdout pipe"),
+            }
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        tracing::debug!(
+            size,
+            "Ignoring --pipe-size, which is only supported on Linux"
+        );
+    }
+
+    /// Open the file given with `--tee` or `--tee-append`, if any.
+    pub fn open_tee(&self) -> Result<Option<File>> {
+        let (path, append) = match (&self.tee, &self.tee_append) {
+            (Some(path), _) => (path, false),
+            (None, Some(path)) => (path, true),
+            (None, None) => return Ok(None),
+        };
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .with_context(|| format!("Unable to open \"{}\"", path.display()))?;
+
+        Ok(Some(file))
+    }
+
     pub fn capabilities(&self) -> Capabilities {
         let mut caps = Capabilities::default();
 
@@ -239,20 +520,96 @@ impl Wasi {
         }
 
         caps.threading.enable_asynchronous_threading = self.enable_async_threads;
+        caps.threading.max_threads_per_process = match self.thread_count {
+            Some(ThreadCount::Limited(count)) => Some(count),
+            Some(ThreadCount::Unlimited) | None => None,
+        };
+        caps.threading.shared_memory_size = self.shm_size;
+        caps.signals.forward_to_host = self.posix_signals;
+        caps.syscalls.allowlist = self.sandbox_syscalls.as_deref().map(|syscalls| {
+            syscalls
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        });
 
         caps
     }
 
+    /// Use `networking` for the module, caching its DNS lookups with
+    /// `--net-dns-cache-ttl` and recording its HTTP requests with
+    /// `--net-capture-http`.
+    fn set_networking(
+        &self,
+        rt: &mut PluggableRuntime,
+        networking: impl VirtualNetworking + Sync,
+    ) -> Result<()> {
+        match self.net_dns_cache_ttl {
+            Some(ttl) if ttl > 0 => {
+                let ttl = Duration::from_secs(ttl);
+                self.capture_http(rt, DnsCachingNetworking::new(networking, ttl))
+            }
+            _ => self.capture_http(rt, networking),
+        }
+    }
+
+    fn capture_http(
+        &self,
+        rt: &mut PluggableRuntime,
+        networking: impl VirtualNetworking + Sync,
+    ) -> Result<()> {
+        match &self.net_capture_http {
+            Some(path) => rt.set_networking_implementation(
+                HttpCapture::new(networking, path).context("Invalid --net-capture-http")?,
+            ),
+            None => rt.set_networking_implementation(networking),
+        };
+        Ok(())
+    }
+
+    /// Set up the runtime the module runs in.
+    ///
+    /// With `defer_cache_writes`, compiled modules are only written to the
+    /// on-disk cache when the returned [`DeferredCache`] is committed.
     pub fn prepare_runtime(
         &self,
         engine: Engine,
         env: &WasmerEnv,
         handle: Handle,
-    ) -> Result<impl Runtime + Send + Sync> {
-        let mut rt = PluggableRuntime::new(Arc::new(TokioTaskManager::new(handle)));
+        defer_cache_writes: bool,
+    ) -> Result<(
+        impl Runtime + Send + Sync,
+        Option<DeferredCache<FileSystemCache>>,
+    )> {
+        let mut task_manager = TokioTaskManager::new(handle.clone());
+        if let Some(stack_size) = self.wasm_threads_stack_size {
+            let stack_size =
+                usize::try_from(stack_size).context("Invalid --wasm-threads-stack-size")?;
+            task_manager = task_manager.with_wasm_thread_stack_size(stack_size);
+        }
+        let mut rt = PluggableRuntime::new(Arc::new(task_manager));
 
         if self.networking {
-            rt.set_networking_implementation(virtual_net::host::LocalNetworking::default());
+            let networking = match &self.net_interface {
+                Some(name) => virtual_net::host::LocalNetworking::with_interface(name)
+                    .context("Invalid --net-interface")?,
+                None => virtual_net::host::LocalNetworking::default(),
+            };
+            if self.allow_net_connect.is_empty() {
+                self.set_networking(&mut rt, networking)?;
+            } else {
+                let allowlist = ConnectAllowlist::resolve(
+                    self.allow_net_connect.clone(),
+                    self.net_connect_strict,
+                )
+                .context("Invalid --allow-net-connect")?;
+                let allowlist = Arc::new(allowlist);
+                let ttl = Duration::from_secs(self.net_connect_dns_ttl.unwrap_or(60));
+                allowlist.refresh_every(&handle, ttl);
+                self.set_networking(&mut rt, AllowlistedNetworking::new(networking, allowlist))?;
+            }
         } else {
             rt.set_networking_implementation(virtual_net::UnsupportedVirtualNetworking::default());
         }
@@ -273,16 +630,37 @@ impl Wasi {
 
         let registry = self.prepare_source(env, client)?;
 
-        let cache_dir = env.cache_dir().join("compiled");
-        let module_cache = wasmer_wasix::runtime::module_cache::in_memory()
-            .with_fallback(FileSystemCache::new(cache_dir));
+        #[cfg(feature = "sys")]
+        let lazy = wasmer::NativeEngineExt::lazy_compilation(&engine);
+        #[cfg(not(feature = "sys"))]
+        let lazy = false;
+
+        let mut deferred_cache = None;
+        if lazy {
+            // Saving a lazily compiled module to disk means compiling all of
+            // it up front, which is what --lazy is trying to avoid
+            rt.set_module_cache(wasmer_wasix::runtime::module_cache::in_memory());
+        } else {
+            let cache_dir = env.cache_dir().join("compiled");
+            let disk_cache = FileSystemCache::new(cache_dir);
+            if defer_cache_writes {
+                let cache = DeferredCache::new(disk_cache);
+                deferred_cache = Some(cache.clone());
+                rt.set_module_cache(
+                    wasmer_wasix::runtime::module_cache::in_memory().with_fallback(cache),
+                );
+            } else {
+                rt.set_module_cache(
+                    wasmer_wasix::runtime::module_cache::in_memory().with_fallback(disk_cache),
+                );
+            }
+        }
 
         rt.set_package_loader(package_loader)
-            .set_module_cache(module_cache)
             .set_source(registry)
             .set_engine(Some(engine));
 
-        Ok(rt)
+        Ok((rt, deferred_cache))
     }
 
     /// Helper function for instantiating a module with Wasi imports for the `Run` command.
@@ -292,9 +670,12 @@ impl Wasi {
         program_name: String,
         args: Vec<String>,
         runtime: Arc<dyn Runtime + Send + Sync>,
+        additional_imports: &Imports,
         store: &mut Store,
     ) -> Result<(WasiFunctionEnv, Instance)> {
-        let builder = self.prepare(module, program_name, args, runtime)?;
+        let builder = self
+            .prepare(module, program_name, args, runtime)?
+            .imports(additional_imports);
         let (instance, wasi_env) = builder.instantiate(module.clone(), store)?;
 
         Ok((wasi_env, instance))
@@ -323,7 +704,7 @@ impl Wasi {
         Ok(loader)
     }
 
-    fn prepare_source(
+    pub(crate) fn prepare_source(
         &self,
         env: &WasmerEnv,
         client: Arc<dyn HttpClient + Send + Sync>,
@@ -340,14 +721,21 @@ impl Wasi {
         }
         source.add_source(preloaded);
 
-        let graphql_endpoint = self.graphql_endpoint(env)?;
-        let cache_dir = env.cache_dir().join("queries");
-        let wapm_source = WapmSource::new(graphql_endpoint, Arc::clone(&client))
-            .with_local_cache(cache_dir, WAPM_SOURCE_CACHE_TIMEOUT);
-        source.add_source(wapm_source);
-
-        let cache_dir = env.cache_dir().join("downloads");
-        source.add_source(WebSource::new(cache_dir, client));
+        if self.offline {
+            let vendor_dir = self.vendor_dir.as_deref().unwrap_or(Path::new("vendor"));
+            let vendored = crate::commands::package::vendored_source(vendor_dir)
+                .context("Unable to load the vendored packages")?;
+            source.add_source(vendored);
+        } else {
+            let graphql_endpoint = self.graphql_endpoint(env)?;
+            let cache_dir = env.cache_dir().join("queries");
+            let wapm_source = WapmSource::new(graphql_endpoint, Arc::clone(&client))
+                .with_local_cache(cache_dir, WAPM_SOURCE_CACHE_TIMEOUT);
+            source.add_source(wapm_source);
+
+            let cache_dir = env.cache_dir().join("downloads");
+            source.add_source(WebSource::new(cache_dir, client));
+        }
 
         source.add_source(FileSystemSource::default());
 
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use crate::signature::{self, SIGNATURE_SECTION};
+
+#[derive(Debug, Parser)]
+/// The options for the `wasmer sign` subcommand
+pub struct Sign {
+    /// A PEM file containing the PKCS#8-encoded Ed25519 private key to sign with
+    #[clap(
+        long,
+        value_name = "PRIVKEY.PEM",
+        required_unless_present = "key_hex",
+        conflicts_with = "key_hex"
+    )]
+    key: Option<PathBuf>,
+    /// The Ed25519 private key to sign with, as 64 hex characters
+    #[clap(long, value_name = "HEX")]
+    key_hex: Option<String>,
+    /// Embed the signature in the module as a custom section (the default)
+    #[clap(long, conflicts_with = "sidecar")]
+    inline: bool,
+    /// Write the signature to a `*.sig` file next to the module instead of
+    /// modifying it
+    #[clap(long)]
+    sidecar: bool,
+    /// The WebAssembly module to sign
+    #[clap(name = "FILE")]
+    path: PathBuf,
+}
+
+impl Sign {
+    /// Runs logic for the `sign` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .with_context(|| format!("failed to sign `{}`", self.path.display()))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let key = match (&self.key, &self.key_hex) {
+            (Some(path), _) => signature::load_signing_key(path)?,
+            (None, Some(hex)) => signature::parse_signing_key_hex(hex)?,
+            (None, None) => anyhow::bail!("No private key was provided"),
+        };
+
+        let wasm = std::fs::read(&self.path)
+            .with_context(|| format!("Unable to read \"{}\"", self.path.display()))?;
+        let (unsigned, signature) = signature::sign_module(&wasm, &key)?;
+
+        // Embedding the signature is the default
+        if self.inline || !self.sidecar {
+            if !wasmer::is_wasm(&wasm) {
+                anyhow::bail!(
+                    "Only WebAssembly binaries can have an embedded signature. Use --sidecar instead"
+                );
+            }
+
+            let signed = signature::embed_signature(&unsigned, &signature);
+            std::fs::write(&self.path, signed)
+                .with_context(|| format!("Unable to write \"{}\"", self.path.display()))?;
+
+            eprintln!("Signed `{}`.", self.path.display());
+        } else {
+            let sidecar = signature::sidecar_path(&self.path);
+            std::fs::write(&sidecar, signature.to_bytes())
+                .with_context(|| format!("Unable to write \"{}\"", sidecar.display()))?;
+
+            if unsigned.len() != wasm.len() {
+                crate::warning!(
+                    "\"{}\" already has a \"{}\" section, which will be checked instead of \"{}\"",
+                    self.path.display(),
+                    SIGNATURE_SECTION,
+                    sidecar.display(),
+                );
+            }
+
+            eprintln!("Wrote the signature to `{}`.", sidecar.display());
+        }
+
+        Ok(())
+    }
+}
@@ -11,6 +11,16 @@ pub struct Validate {
     #[clap(name = "FILE")]
     path: PathBuf,
 
+    /// Validate against the features supported by this target triple
+    #[cfg(feature = "compiler")]
+    #[clap(long = "target")]
+    target_triple: Option<Triple>,
+
+    /// Report every invalid function instead of stopping at the first error
+    #[cfg(feature = "compiler")]
+    #[clap(long)]
+    all_errors: bool,
+
     #[clap(flatten)]
     store: StoreOptions,
 }
@@ -21,14 +31,86 @@ impl Validate {
         self.inner_execute()
             .context(format!("failed to validate `{}`", self.path.display()))
     }
+
+    #[cfg(feature = "compiler")]
+    fn inner_execute(&self) -> Result<()> {
+        let module_contents = self.read_module()?;
+        let target = match &self.target_triple {
+            Some(triple) => Target::new(triple.clone(), CpuFeature::set()),
+            None => Target::default(),
+        };
+        let features = self.store.get_features_for_target(&target)?;
+
+        let errors = if self.all_errors {
+            Module::validate_all_with_features(&module_contents, &features, target.triple())
+                .err()
+                .unwrap_or_default()
+        } else {
+            Module::validate_with_features(&module_contents, &features, target.triple())
+                .err()
+                .into_iter()
+                .collect()
+        };
+
+        if !errors.is_empty() {
+            for error in &errors {
+                eprintln!("{}", describe(error, &module_contents));
+            }
+            bail!("found {} validation error(s)", errors.len());
+        }
+
+        eprintln!("Validation passed for `{}`.", self.path.display());
+        Ok(())
+    }
+
+    #[cfg(not(feature = "compiler"))]
     fn inner_execute(&self) -> Result<()> {
         let (store, _compiler_type) = self.store.get_store()?;
+        let module_contents = self.read_module()?;
+        Module::validate(&store, &module_contents)?;
+        eprintln!("Validation passed for `{}`.", self.path.display());
+        Ok(())
+    }
+
+    fn read_module(&self) -> Result<Vec<u8>> {
         let module_contents = std::fs::read(&self.path)?;
         if !is_wasm(&module_contents) {
             bail!("`wasmer validate` only validates WebAssembly files");
         }
-        Module::validate(&store, &module_contents)?;
-        eprintln!("Validation passed for `{}`.", self.path.display());
-        Ok(())
+        Ok(module_contents)
     }
 }
+
+/// Format a validation error along with the bytes surrounding it, e.g.
+///
+/// ```text
+/// error: SIMD support is not enabled (at offset 0x1a) in func[0]
+///   0x00000012: 01 03 00 00 0b 0a 09 01 [fd] 0c 00 00 00 00 00 00
+/// ```
+#[cfg(feature = "compiler")]
+fn describe(error: &ValidationError, wasm: &[u8]) -> String {
+    /// How many bytes to show on either side of the offset.
+    const CONTEXT: usize = 8;
+
+    let mut description = format!("error: {error}");
+    if let Some(function_index) = error.function_index {
+        description.push_str(&format!(" in func[{}]", function_index.as_u32()));
+    }
+
+    let start = error.offset.saturating_sub(CONTEXT).min(wasm.len());
+    let end = error.offset.saturating_add(CONTEXT).min(wasm.len());
+    if start < end {
+        let bytes: Vec<String> = (start..end)
+            .map(|i| {
+                if i == error.offset {
+                    format!("[{:02x}]", wasm[i])
+                } else {
+                    format!("{:02x}", wasm[i])
+                }
+            })
+            .collect();
+        description.push_str(&format!("\n  0x{start:08x}: {}", bytes.join(" ")));
+    }
+
+    description
+}
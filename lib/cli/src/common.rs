@@ -13,6 +13,10 @@ pub struct WasmFeatures {
     #[clap(long = "enable-simd")]
     pub simd: bool,
 
+    /// Disable support for the SIMD proposal.
+    #[clap(long = "disable-simd", conflicts_with = "simd")]
+    pub disable_simd: bool,
+
     /// Disable support for the threads proposal.
     #[clap(long = "disable-threads")]
     pub disable_threads: bool,
@@ -33,6 +37,26 @@ pub struct WasmFeatures {
     #[clap(long = "enable-bulk-memory")]
     pub bulk_memory: bool,
 
+    /// Enable support for the exception handling proposal.
+    #[clap(long = "enable-exceptions")]
+    pub exceptions: bool,
+
+    /// Enable support for the tail call proposal.
+    #[clap(long = "enable-tail-call")]
+    pub tail_call: bool,
+
+    /// Enable support for the 64-bit memory proposal.
+    #[clap(long = "enable-memory64")]
+    pub memory64: bool,
+
+    /// Enable support for the relaxed SIMD proposal.
+    #[clap(long = "enable-relaxed-simd")]
+    pub relaxed_simd: bool,
+
+    /// Enable support for the extended constant expressions proposal.
+    #[clap(long = "enable-extended-const")]
+    pub extended_const: bool,
+
     /// Enable support for all pre-standard proposals.
     #[clap(long = "enable-all")]
     pub all: bool,
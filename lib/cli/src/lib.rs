@@ -28,6 +28,7 @@ pub mod c_gen;
 pub mod cli;
 pub mod logging;
 pub mod package_source;
+pub mod signature;
 pub mod store;
 pub mod suggestions;
 pub mod utils;
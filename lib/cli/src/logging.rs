@@ -1,8 +1,16 @@
 //! Logging functions for the debug feature.
 
+use std::{fs::OpenOptions, path::PathBuf, sync::Mutex};
+
+use anyhow::{Context, Error};
 use is_terminal::IsTerminal;
 use tracing::level_filters::LevelFilter;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    layer::{Identity, SubscriberExt},
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
 
 const WHITELISTED_LOG_TARGETS: &[&str] = &["wasmer", "wasmer_wasix", "virtual_fs"];
 
@@ -20,6 +28,23 @@ pub struct Output {
     pub color: clap::ColorChoice,
 }
 
+/// Where wasmer's own log messages go, and what they look like.
+#[derive(Debug, Default, Clone, PartialEq, clap::Parser)]
+pub struct LogOptions {
+    /// Append wasmer's own log messages to this file instead of writing them
+    /// to stderr. The module's stderr isn't affected
+    #[clap(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// The minimum severity of the log messages (trace, debug, info, warn or
+    /// error), for every crate, rather than the levels picked by -v and
+    /// $RUST_LOG
+    #[clap(long, value_name = "LEVEL")]
+    pub log_level: Option<LevelFilter>,
+    /// Write each log message as a line of JSON, for log aggregators
+    #[clap(long)]
+    pub log_json: bool,
+}
+
 impl Output {
     /// Has the `--verbose` flag been set?
     pub fn is_verbose(&self) -> bool {
@@ -29,20 +54,62 @@ impl Output {
     /// Initialize logging based on the `$RUST_LOG` environment variable and
     /// command-line flags.
     pub fn initialize_logging(&self) {
-        let fmt_layer = fmt::layer()
+        self.initialize_logging_with(Identity::new(), &LogOptions::default())
+            .expect("Logging to stderr can't fail");
+    }
+
+    /// Initialize logging, also installing an extra [`Layer`] which will
+    /// receive spans and events independently of the verbosity level.
+    ///
+    /// The extra layer should use per-layer filtering to select the things it
+    /// cares about.
+    ///
+    /// `options` says where the log messages go and how they're formatted.
+    pub(crate) fn initialize_logging_with<L>(
+        &self,
+        extra: L,
+        options: &LogOptions,
+    ) -> Result<(), Error>
+    where
+        L: Layer<Registry> + Send + Sync + 'static,
+    {
+        let writer = match &options.log_file {
+            Some(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Unable to open \"{}\"", path.display()))?;
+                BoxMakeWriter::new(Mutex::new(file))
+            }
+            None => BoxMakeWriter::new(std::io::stderr),
+        };
+
+        let layer = fmt::layer()
             .with_target(true)
             .with_span_events(fmt::format::FmtSpan::CLOSE)
-            .with_ansi(self.should_emit_colors())
-            .with_thread_ids(true)
-            .with_writer(std::io::stderr)
-            .compact();
+            .with_thread_ids(true);
+        // Only one of them is used, they're different types
+        let (compact, json) = if options.log_json {
+            (None, Some(layer.json().with_writer(writer)))
+        } else {
+            let ansi = options.log_file.is_none() && self.should_emit_colors();
+            (
+                Some(layer.compact().with_ansi(ansi).with_writer(writer)),
+                None,
+            )
+        };
 
-        let filter_layer = self.log_filter();
+        let filter_layer = match options.log_level {
+            Some(level) => EnvFilter::new(level.to_string()),
+            None => self.log_filter(),
+        };
 
         tracing_subscriber::registry()
-            .with(filter_layer)
-            .with(fmt_layer)
+            .with(extra)
+            .with(Layer::and_then(compact, json).with_filter(filter_layer))
             .init();
+        Ok(())
     }
 
     fn log_filter(&self) -> EnvFilter {
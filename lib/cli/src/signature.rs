@@ -0,0 +1,322 @@
+//! Ed25519 signatures for WebAssembly modules.
+//!
+//! A module's signature is stored either inline, in a custom section called
+//! [`SIGNATURE_SECTION`], or in a sidecar file next to the module (e.g.
+//! `module.wasm.sig`). Either way, it is the raw 64-byte Ed25519 signature
+//! over the module's bytes, excluding the signature section itself.
+
+use std::{
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    Signature, Signer, SigningKey, VerifyingKey,
+};
+
+/// The name of the custom section a module's signature is embedded in.
+pub const SIGNATURE_SECTION: &str = "wasmer.signature";
+
+/// Load an Ed25519 public key from a PEM file.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey, Error> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+
+    VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|e| Error::msg(e.to_string()))
+        .with_context(|| {
+            format!(
+                "\"{}\" doesn't contain a valid Ed25519 public key",
+                path.display()
+            )
+        })
+}
+
+/// Load an Ed25519 private key from a PKCS#8 PEM file.
+pub fn load_signing_key(path: &Path) -> Result<SigningKey, Error> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+
+    SigningKey::from_pkcs8_pem(&pem)
+        .map_err(|e| Error::msg(e.to_string()))
+        .with_context(|| {
+            format!(
+                "\"{}\" doesn't contain a valid Ed25519 private key",
+                path.display()
+            )
+        })
+}
+
+/// Parse an Ed25519 private key from its 32 bytes, hex-encoded.
+pub fn parse_signing_key_hex(key: &str) -> Result<SigningKey, Error> {
+    let bytes = hex::decode(key.trim()).context("The private key isn't valid hex")?;
+    let bytes: [u8; 32] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow::anyhow!(
+            "An Ed25519 private key is 32 bytes long, but {} bytes were provided",
+            bytes.len()
+        )
+    })?;
+
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// The sidecar file a module's signature may be stored in.
+pub fn sidecar_path(module: &Path) -> PathBuf {
+    let mut path = OsString::from(module.as_os_str());
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Check that the module at `path` was signed by `key`, using its embedded
+/// signature or, if it doesn't have one, its sidecar file.
+pub fn verify_module(path: &Path, key: &VerifyingKey) -> Result<(), Error> {
+    let wasm =
+        std::fs::read(path).with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+    let (unsigned, embedded) = split_signature(&wasm)
+        .with_context(|| format!("Unable to parse \"{}\"", path.display()))?;
+
+    let signature = match embedded {
+        Some(signature) => signature,
+        None => {
+            let sidecar = sidecar_path(path);
+            match std::fs::read(&sidecar) {
+                Ok(signature) => signature,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    anyhow::bail!(
+                        "\"{}\" isn't signed. Expected a \"{}\" custom section or a \"{}\" file",
+                        path.display(),
+                        SIGNATURE_SECTION,
+                        sidecar.display(),
+                    );
+                }
+                Err(e) => {
+                    return Err(
+                        Error::new(e).context(format!("Unable to read \"{}\"", sidecar.display()))
+                    );
+                }
+            }
+        }
+    };
+
+    let signature = Signature::from_slice(&signature)
+        .map_err(|_| anyhow::anyhow!("The signature for \"{}\" is malformed", path.display()))?;
+
+    key.verify_strict(&unsigned, &signature).map_err(|_| {
+        anyhow::anyhow!(
+            "The signature for \"{}\" doesn't match the trusted key",
+            path.display()
+        )
+    })
+}
+
+/// Sign a module, returning its unsigned bytes and the signature.
+///
+/// Any existing [`SIGNATURE_SECTION`] is ignored, so re-signing a module
+/// replaces its signature.
+pub fn sign_module(wasm: &[u8], key: &SigningKey) -> Result<(Vec<u8>, Signature), Error> {
+    let (unsigned, _) = split_signature(wasm)?;
+    let signature = key.sign(&unsigned);
+    Ok((unsigned, signature))
+}
+
+/// Append a [`SIGNATURE_SECTION`] containing `signature` to an unsigned
+/// module.
+pub fn embed_signature(unsigned: &[u8], signature: &Signature) -> Vec<u8> {
+    let mut contents = Vec::new();
+    write_u32(&mut contents, SIGNATURE_SECTION.len() as u32);
+    contents.extend_from_slice(SIGNATURE_SECTION.as_bytes());
+    contents.extend_from_slice(&signature.to_bytes());
+
+    let mut wasm = unsigned.to_vec();
+    wasm.push(0);
+    write_u32(&mut wasm, contents.len() as u32);
+    wasm.extend(contents);
+    wasm
+}
+
+/// Split a module into the bytes covered by its signature and the contents
+/// of its [`SIGNATURE_SECTION`] (if it has one).
+///
+/// Anything that isn't a WebAssembly binary (e.g. a `*.wat` file) is returned
+/// as-is.
+pub fn split_signature(wasm: &[u8]) -> Result<(Vec<u8>, Option<Vec<u8>>), Error> {
+    /// The `\0asm` magic number followed by the version.
+    const HEADER_LEN: usize = 8;
+
+    if !wasmer::is_wasm(wasm) {
+        return Ok((wasm.to_vec(), None));
+    }
+
+    let mut unsigned = wasm[..HEADER_LEN].to_vec();
+    let mut signature = None;
+    let mut offset = HEADER_LEN;
+
+    while offset < wasm.len() {
+        let id = wasm[offset];
+        let (size, size_len) = read_u32(&wasm[offset + 1..])?;
+        let contents = offset + 1 + size_len;
+        let end = contents
+            .checked_add(size as usize)
+            .filter(|&end| end <= wasm.len())
+            .context("A section extends past the end of the module")?;
+
+        if id == 0 {
+            let section = &wasm[contents..end];
+            let (name_len, name_len_len) = read_u32(section)?;
+            let data_start = name_len_len + name_len as usize;
+            let name = section
+                .get(name_len_len..data_start)
+                .context("A custom section's name extends past the end of the section")?;
+
+            if name == SIGNATURE_SECTION.as_bytes() {
+                signature = Some(section[data_start..].to_vec());
+                offset = end;
+                continue;
+            }
+        }
+
+        unsigned.extend_from_slice(&wasm[offset..end]);
+        offset = end;
+    }
+
+    Ok((unsigned, signature))
+}
+
+/// Read a LEB128-encoded `u32`, returning its value and encoded length.
+fn read_u32(bytes: &[u8]) -> Result<(u32, usize), Error> {
+    let mut value = 0_u32;
+
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+
+    anyhow::bail!("Invalid LEB128-encoded integer")
+}
+
+/// Append a LEB128-encoded `u32`.
+fn write_u32(buffer: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// The test key from section 7.1 of RFC 8032.
+    const SECRET_KEY: [u8; 32] = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c,
+        0xc4, 0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae,
+        0x7f, 0x60,
+    ];
+
+    /// An empty module with a custom section called "hello".
+    const MODULE: &[u8] = b"\0asm\x01\0\0\0\0\x09\x05helloabc";
+
+    fn custom_section(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut section = vec![0, (name.len() + data.len() + 1) as u8, name.len() as u8];
+        section.extend_from_slice(name.as_bytes());
+        section.extend_from_slice(data);
+        section
+    }
+
+    #[test]
+    fn embedded_signatures_are_stripped_before_verifying() {
+        let key = SigningKey::from_bytes(&SECRET_KEY);
+        let signature = key.sign(MODULE);
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("module.wasm");
+        let mut signed = MODULE.to_vec();
+        signed.extend(custom_section(SIGNATURE_SECTION, &signature.to_bytes()));
+        std::fs::write(&path, &signed).unwrap();
+
+        let (unsigned, embedded) = split_signature(&signed).unwrap();
+
+        assert_eq!(unsigned, MODULE);
+        assert_eq!(embedded.unwrap(), signature.to_bytes());
+        verify_module(&path, &key.verifying_key()).unwrap();
+    }
+
+    #[test]
+    fn sidecar_signatures() {
+        let key = SigningKey::from_bytes(&SECRET_KEY);
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("module.wasm");
+        std::fs::write(&path, MODULE).unwrap();
+        std::fs::write(sidecar_path(&path), key.sign(MODULE).to_bytes()).unwrap();
+
+        assert_eq!(sidecar_path(&path), temp.path().join("module.wasm.sig"));
+        verify_module(&path, &key.verifying_key()).unwrap();
+    }
+
+    #[test]
+    fn tampered_modules_are_rejected() {
+        let key = SigningKey::from_bytes(&SECRET_KEY);
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("module.wasm");
+        let mut tampered = MODULE.to_vec();
+        *tampered.last_mut().unwrap() = b'x';
+        std::fs::write(&path, &tampered).unwrap();
+        std::fs::write(sidecar_path(&path), key.sign(MODULE).to_bytes()).unwrap();
+
+        let err = verify_module(&path, &key.verifying_key()).unwrap_err();
+
+        assert!(err.to_string().contains("doesn't match the trusted key"));
+    }
+
+    #[test]
+    fn unsigned_modules_are_rejected() {
+        let key = SigningKey::from_bytes(&SECRET_KEY);
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("module.wasm");
+        std::fs::write(&path, MODULE).unwrap();
+
+        let err = verify_module(&path, &key.verifying_key()).unwrap_err();
+
+        assert!(err.to_string().contains("isn't signed"));
+    }
+
+    #[test]
+    fn signed_modules_can_be_verified() {
+        let key = SigningKey::from_bytes(&SECRET_KEY);
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("module.wasm");
+
+        let (unsigned, signature) = sign_module(MODULE, &key).unwrap();
+        let signed = embed_signature(&unsigned, &signature);
+        std::fs::write(&path, &signed).unwrap();
+
+        let mut expected = MODULE.to_vec();
+        expected.extend(custom_section(SIGNATURE_SECTION, &signature.to_bytes()));
+        assert_eq!(signed, expected);
+        verify_module(&path, &key.verifying_key()).unwrap();
+
+        // Re-signing replaces the existing signature
+        let (resigned, _) = sign_module(&signed, &key).unwrap();
+        assert_eq!(resigned, MODULE);
+    }
+
+    #[test]
+    fn hex_private_keys() {
+        let key = parse_signing_key_hex(&hex::encode(SECRET_KEY)).unwrap();
+        assert_eq!(key.to_bytes(), SECRET_KEY);
+
+        let err = parse_signing_key_hex("abcd").unwrap_err();
+        assert!(err.to_string().contains("32 bytes long"));
+    }
+}
@@ -1,10 +1,14 @@
 //! Common module with common used structures across different
 //! commands.
 
+#[cfg(feature = "compiler")]
+use anyhow::Context;
 use anyhow::Result;
 
 #[allow(unused_imports)]
 use crate::common::WasmFeatures;
+#[cfg(feature = "compiler")]
+use crate::utils::parse_size;
 use clap::Parser;
 #[allow(unused_imports)]
 use std::path::PathBuf;
@@ -13,9 +17,9 @@ use std::string::ToString;
 use std::sync::Arc;
 use wasmer::*;
 #[cfg(feature = "compiler")]
-use wasmer_compiler::CompilerConfig;
-#[cfg(feature = "compiler")]
 use wasmer_compiler::Engine;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{CompileThreads, CompilerConfig, CompilerDump, DumpFilter, ModuleMiddleware};
 
 #[derive(Debug, Clone, Parser, Default)]
 /// The compiler options
@@ -46,15 +50,121 @@ pub struct CompilerOptions {
     #[cfg(any(feature = "singlepass", feature = "cranelift", feature = "llvm"))]
     enable_verifier: bool,
 
+    /// Make every floating point operation which produces a NaN produce the
+    /// canonical NaN, so its bits are the same with every compiler and on
+    /// every architecture. Useful for deterministic execution, at the cost
+    /// of slower floating point code.
+    #[clap(long)]
+    canonicalize_nans: bool,
+
+    /// Compile each function the first time it's called, instead of the
+    /// whole module up front (only supported by Cranelift).
+    #[clap(long)]
+    lazy: bool,
+
+    /// The number of threads to compile with, one per CPU core by default.
+    /// With 0, everything is compiled in the main thread. The compiled code
+    /// is the same whatever the number of threads.
+    #[clap(long, value_name = "N")]
+    compile_jobs: Option<usize>,
+
+    /// Cranelift optimization level (none, speed or speed-and-size),
+    /// trading compile time for faster or smaller code. Defaults to speed.
+    #[cfg(feature = "cranelift")]
+    #[clap(long, value_name = "LEVEL", value_parser = parse_cranelift_opt_level)]
+    cranelift_opt_level: Option<wasmer_compiler_cranelift::CraneliftOptLevel>,
+
+    /// Optimize with Cranelift's e-graph based mid-end.
+    #[cfg(feature = "cranelift")]
+    #[clap(long)]
+    cranelift_egraphs: bool,
+
+    /// With Cranelift, inline the functions with at most N operators and no
+    /// locals, control flow or calls into their callers. Traps in inlined
+    /// functions are reported at the call.
+    #[cfg(feature = "cranelift")]
+    #[clap(long, value_name = "N")]
+    cranelift_inline_threshold: Option<usize>,
+
     /// LLVM debug directory, where IR and object files will be written to.
     #[cfg(feature = "llvm")]
     #[clap(long)]
     llvm_debug_dir: Option<PathBuf>,
 
+    /// LLVM optimization level (0, 1, 2, 3 or s), trading compile time for
+    /// faster code. Defaults to 3.
+    #[cfg(feature = "llvm")]
+    #[clap(long, value_name = "LEVEL", value_parser = parse_llvm_opt_level)]
+    llvm_opt_level: Option<wasmer_compiler_llvm::LLVMOptLevel>,
+
+    /// Write the WebAssembly, compiler IR and disassembled machine code of
+    /// each compiled function into this directory, along with an index.json
+    /// listing them. Modules loaded from the cache aren't compiled, so
+    /// nothing is written for them.
+    #[clap(long, value_name = "DIR")]
+    compiler_dump_dir: Option<PathBuf>,
+
+    /// Only dump the function with this index or name (can be repeated).
+    #[clap(long, value_name = "INDEX_OR_NAME", requires = "compiler_dump_dir")]
+    compiler_dump_filter: Vec<DumpFilter>,
+
+    /// Split the native code of the objects emitted by `create-obj` and
+    /// `create-exe` into sections of at most this size (e.g. `128m`), for
+    /// linkers which can't link the code of very big modules in one section
+    /// (only supported by Cranelift and LLVM). A function bigger than this
+    /// gets a section of its own.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    aot_text_section_size: Option<u64>,
+
+    /// Optimize modules whose native code is estimated to take more than
+    /// this size (e.g. `64m`) less aggressively, so they generate less code
+    /// (only supported by Cranelift and LLVM). Cranelift inlines fewer
+    /// functions, and LLVM optimizes like at `Os`.
+    #[clap(long, value_name = "BYTES", value_parser = parse_size)]
+    jit_code_size_limit: Option<u64>,
+
+    /// Whether to emit position-independent code, set by the commands
+    /// which emit objects for shared libraries.
+    #[clap(skip)]
+    pic: bool,
+
+    /// The middlewares to compile with, set by the commands which
+    /// instrument the module.
+    #[clap(skip)]
+    middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+
     #[clap(flatten)]
     features: WasmFeatures,
 }
 
+#[cfg(feature = "cranelift")]
+fn parse_cranelift_opt_level(level: &str) -> Result<wasmer_compiler_cranelift::CraneliftOptLevel> {
+    use wasmer_compiler_cranelift::CraneliftOptLevel as OptLevel;
+
+    match level {
+        "none" => Ok(OptLevel::None),
+        "speed" => Ok(OptLevel::Speed),
+        "speed-and-size" => Ok(OptLevel::SpeedAndSize),
+        _ => bail!("Expected \"none\", \"speed\" or \"speed-and-size\", but found \"{level}\""),
+    }
+}
+
+#[cfg(feature = "llvm")]
+fn parse_llvm_opt_level(level: &str) -> Result<wasmer_compiler_llvm::LLVMOptLevel> {
+    use wasmer_compiler_llvm::LLVMOptLevel;
+
+    match level.trim_start_matches('O') {
+        "0" => Ok(LLVMOptLevel::O0),
+        "1" => Ok(LLVMOptLevel::O1),
+        "2" => Ok(LLVMOptLevel::O2),
+        "3" => Ok(LLVMOptLevel::O3),
+        "s" => Ok(LLVMOptLevel::Os),
+        _ => {
+            bail!("Expected an optimization level between 0 and 3 or \"s\", but found \"{level}\"")
+        }
+    }
+}
+
 #[cfg(feature = "compiler")]
 impl CompilerOptions {
     fn get_compiler(&self) -> Result<CompilerType> {
@@ -82,27 +192,62 @@ impl CompilerOptions {
         }
     }
 
-    /// Get the enaled Wasm features.
-    pub fn get_features(&self, mut features: Features) -> Result<Features> {
-        if !self.features.disable_threads || self.features.all {
-            features.threads(true);
+    /// Get the enabled Wasm features, checking the ones requested on the
+    /// command-line against what `compiler_config` supports for `target`.
+    pub fn get_features(
+        &self,
+        compiler_config: &dyn CompilerConfig,
+        target: &Target,
+    ) -> Result<Features> {
+        let flags = &self.features;
+        let mut builder = if flags.all {
+            // Only the proposals the compiler supports are turned on
+            FeaturesBuilder::from(Features::all())
+        } else {
+            let mut defaults = compiler_config.default_features_for_target(target);
+            defaults.threads(true);
+            FeaturesBuilder::from(defaults)
+        };
+
+        if flags.multi_value {
+            builder = builder.multi_value(true);
+        }
+        if flags.simd {
+            builder = builder.simd(true);
+        }
+        if flags.bulk_memory {
+            builder = builder.bulk_memory(true);
+        }
+        if flags.reference_types {
+            builder = builder.reference_types(true);
+        }
+        if flags.exceptions {
+            builder = builder.exceptions(true);
         }
-        if self.features.disable_threads && !self.features.all {
-            features.threads(false);
+        if flags.tail_call {
+            builder = builder.tail_call(true);
         }
-        if self.features.multi_value || self.features.all {
-            features.multi_value(true);
+        if flags.memory64 {
+            builder = builder.memory64(true);
         }
-        if self.features.simd || self.features.all {
-            features.simd(true);
+        if flags.relaxed_simd {
+            builder = builder.relaxed_simd(true);
         }
-        if self.features.bulk_memory || self.features.all {
-            features.bulk_memory(true);
+        if flags.extended_const {
+            builder = builder.extended_const(true);
         }
-        if self.features.reference_types || self.features.all {
-            features.reference_types(true);
+        // Disabling wins over --enable-all
+        if flags.disable_threads {
+            builder = builder.threads(false);
         }
-        Ok(features)
+        if flags.disable_simd {
+            builder = builder.simd(false);
+        }
+
+        let compiler = self.get_compiler()?.to_string();
+        builder
+            .build_for_target(compiler_config, target)
+            .with_context(|| format!("The {compiler} compiler can't be used with these flags"))
     }
 
     /// Gets the Store for a given target.
@@ -126,7 +271,7 @@ impl CompilerOptions {
         target: Target,
         compiler_config: Box<dyn CompilerConfig>,
     ) -> Result<Engine> {
-        let features = self.get_features(compiler_config.default_features_for_target(&target))?;
+        let features = self.get_features(&*compiler_config, &target)?;
         let engine: Engine = wasmer_compiler::EngineBuilder::new(compiler_config)
             .set_features(Some(features))
             .set_target(Some(target))
@@ -135,11 +280,33 @@ impl CompilerOptions {
         Ok(engine)
     }
 
+    /// The same options, but emitting position-independent code.
+    pub(crate) fn with_pic(&self) -> Self {
+        Self {
+            pic: true,
+            ..self.clone()
+        }
+    }
+
     /// Get the Compiler Config for the current options
-    #[allow(unused_variables)]
+    #[allow(unused_variables, unreachable_code)]
     pub(crate) fn get_compiler_config(&self) -> Result<(Box<dyn CompilerConfig>, CompilerType)> {
         let compiler = self.get_compiler()?;
-        let compiler_config: Box<dyn CompilerConfig> = match compiler {
+        if self.lazy && compiler != CompilerType::Cranelift {
+            bail!(
+                "Lazy compilation is only supported by Cranelift, not `{}`",
+                compiler.to_string()
+            );
+        }
+        if self.aot_text_section_size.is_some() && compiler == CompilerType::Singlepass {
+            bail!("Limiting the size of text sections is only supported by Cranelift and LLVM");
+        }
+        if self.jit_code_size_limit.is_some() && compiler == CompilerType::Singlepass {
+            bail!(
+                "Limiting the size of the generated code is only supported by Cranelift and LLVM"
+            );
+        }
+        let mut compiler_config: Box<dyn CompilerConfig> = match compiler {
             CompilerType::Headless => bail!("The headless engine can't be chosen"),
             #[cfg(feature = "singlepass")]
             CompilerType::Singlepass => {
@@ -155,6 +322,16 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if self.lazy {
+                    config.enable_lazy_compilation(true);
+                }
+                if let Some(opt_level) = self.cranelift_opt_level {
+                    config.opt_level(opt_level);
+                }
+                config.use_egraphs(self.cranelift_egraphs);
+                if let Some(threshold) = self.cranelift_inline_threshold {
+                    config.inline_threshold(threshold);
+                }
                 Box::new(config)
             }
             #[cfg(feature = "llvm")]
@@ -258,6 +435,9 @@ impl CompilerOptions {
                 if self.enable_verifier {
                     config.enable_verifier();
                 }
+                if let Some(opt_level) = self.llvm_opt_level {
+                    config.opt_level(opt_level);
+                }
                 Box::new(config)
             }
             #[cfg(not(all(feature = "singlepass", feature = "cranelift", feature = "llvm",)))]
@@ -269,7 +449,32 @@ impl CompilerOptions {
             }
         };
 
-        #[allow(unreachable_code)]
+        if self.canonicalize_nans {
+            compiler_config.canonicalize_nans(true);
+        }
+
+        if self.pic {
+            compiler_config.enable_pic();
+        }
+
+        for middleware in &self.middlewares {
+            compiler_config.push_middleware(middleware.clone());
+        }
+
+        compiler_config.set_text_section_size(self.aot_text_section_size);
+        compiler_config.set_code_size_limit(self.jit_code_size_limit);
+
+        if let Some(dir) = &self.compiler_dump_dir {
+            compiler_config.enable_dump(CompilerDump::new(
+                dir.clone(),
+                self.compiler_dump_filter.clone(),
+            ));
+        }
+
+        if let Some(jobs) = self.compile_jobs {
+            compiler_config.set_compile_threads(CompileThreads::new(jobs));
+        }
+
         Ok((compiler_config, compiler))
     }
 }
@@ -328,6 +533,37 @@ impl StoreOptions {
         Ok((store, compiler_type))
     }
 
+    /// Gets the engine for a given target, with the compiler name selected.
+    pub fn get_engine_for_target(&self, target: Target) -> Result<(Engine, CompilerType)> {
+        let (compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        let engine = self.get_engine_with_compiler(target, compiler_config)?;
+        Ok((engine, compiler_type))
+    }
+
+    /// Get the WebAssembly features requested on the command-line, on top of
+    /// the selected compiler's defaults for `target`.
+    pub fn get_features_for_target(&self, target: &Target) -> Result<Features> {
+        let (compiler_config, _) = self.compiler.get_compiler_config()?;
+        self.compiler.get_features(&*compiler_config, target)
+    }
+
+    /// The size text sections are limited to in the objects emitted, if
+    /// it was given on the command-line.
+    pub(crate) fn aot_text_section_size(&self) -> Option<u64> {
+        self.compiler.aot_text_section_size
+    }
+
+    /// Get a mutable reference to the WebAssembly features requested on the
+    /// command-line.
+    pub(crate) fn features_mut(&mut self) -> &mut WasmFeatures {
+        &mut self.compiler.features
+    }
+
+    /// Compile with `middleware`, after the ones added before it.
+    pub(crate) fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
+        self.compiler.middlewares.push(middleware);
+    }
+
     #[cfg(feature = "compiler")]
     fn get_engine_with_compiler(
         &self,
@@ -69,9 +69,35 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     }
 }
 
+/// Parses a size in bytes, with an optional `k`, `m` or `g` suffix (e.g.
+/// `256m`) for kibibytes, mebibytes and gibibytes.
+pub fn parse_size(entry: &str) -> Result<u64> {
+    let entry = entry.trim();
+    let lowercase = entry.to_ascii_lowercase();
+    let number = lowercase.strip_suffix('b').unwrap_or(&lowercase);
+
+    let (number, multiplier) = match number.char_indices().last() {
+        Some((i, 'k')) => (&number[..i], 1 << 10),
+        Some((i, 'm')) => (&number[..i], 1 << 20),
+        Some((i, 'g')) => (&number[..i], 1 << 30),
+        _ => (number, 1),
+    };
+
+    match number.trim().parse::<u64>() {
+        Ok(n) => match n.checked_mul(multiplier) {
+            Some(size) => Ok(size),
+            None => bail!("The size `{}` is too large", entry),
+        },
+        Err(_) => bail!(
+            "Sizes must be a number of bytes, optionally followed by `k`, `m` or `g`; found `{}`",
+            entry
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_envvar;
+    use super::{parse_envvar, parse_size};
 
     #[test]
     fn test_parse_envvar() {
@@ -94,4 +120,18 @@ mod tests {
             ("A".into(), "B=C=D".into())
         );
     }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("64k").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("256m").unwrap(), 256 * 1024 * 1024);
+        assert_eq!(parse_size("256MB").unwrap(), 256 * 1024 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(
+            parse_size("lots").unwrap_err().to_string(),
+            "Sizes must be a number of bytes, optionally followed by `k`, `m` or `g`; found `lots`"
+        );
+        assert!(parse_size("99999999999999g").is_err());
+    }
 }
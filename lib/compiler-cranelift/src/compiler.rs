@@ -5,31 +5,36 @@ use crate::config::Cranelift;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
 use crate::func_environ::{get_function_name, FuncEnvironment};
+use crate::inlining::{threshold_within_size, InlinedFunctions};
+use crate::tail_calls::TailCallGraph;
 use crate::trampoline::{
-    make_trampoline_dynamic_function, make_trampoline_function_call, FunctionBuilderContext,
+    make_lazy_stub, make_trampoline_dynamic_function, make_trampoline_function_call,
+    FunctionBuilderContext,
 };
 use crate::translator::{
     compiled_function_unwind_info, irlibcall_to_libcall, irreloc_to_relocationkind,
-    signature_to_cranelift_ir, CraneliftUnwindInfo, FuncTranslator,
+    signature_to_cranelift_ir, CraneliftUnwindInfo, FuncTranslator, TailCallee,
 };
 use cranelift_codegen::ir::{ExternalName, UserFuncName};
+use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{ir, MachReloc};
 use cranelift_codegen::{Context, MachTrap};
 #[cfg(feature = "unwind")]
-use gimli::write::{Address, EhFrame, FrameTable};
-#[cfg(feature = "rayon")]
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use gimli::write::{Address, CieId, EhFrame, FrameDescriptionEntry, FrameTable};
 use std::sync::Arc;
+#[cfg(feature = "dump")]
+use wasmer_compiler::disassemble;
 use wasmer_compiler::{
-    Compiler, FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware,
-    ModuleMiddlewareChain, ModuleTranslationState,
+    CompileThreads, Compiler, FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader,
+    ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
-    CallingConvention, Compilation, CompileError, CompileModuleInfo, CompiledFunction,
-    CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, Dwarf, FunctionBody, FunctionIndex,
+    Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
+    CompiledFunction, CompiledFunctionFrameInfo, CompiledFunctionUnwindInfo, CpuFeature,
+    CustomSection, Dwarf, FunctionBody, FunctionIndex, FunctionLocation, LazyCompiledFunction,
     LocalFunctionIndex, ModuleInfo, Relocation, RelocationTarget, SectionIndex, SignatureIndex,
-    Target, TrapCode, TrapInformation,
+    Target, TrapCode, TrapInformation, VMOffsets, WasmError,
 };
 
 /// A compiler that compiles a WebAssembly module with Cranelift, translating the Wasm to Cranelift IR,
@@ -48,6 +53,68 @@ impl CraneliftCompiler {
     pub fn config(&self) -> &Cranelift {
         &self.config
     }
+
+    /// A reader for the code of the function at `index`, through the
+    /// middlewares.
+    fn function_reader<'a>(
+        &self,
+        input: &FunctionBodyData<'a>,
+        index: LocalFunctionIndex,
+    ) -> MiddlewareBinaryReader<'a> {
+        let mut reader = MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
+        reader.set_middleware_chain(
+            self.config
+                .middlewares
+                .generate_function_middleware_chain(index),
+        );
+        reader
+    }
+
+    /// Dump a compiled function, if it's one of the functions selected with
+    /// [`CompilerConfig::enable_dump`].
+    ///
+    /// [`CompilerConfig::enable_dump`]: wasmer_compiler::CompilerConfig::enable_dump
+    #[cfg(feature = "dump")]
+    fn dump_function(
+        &self,
+        target: &Target,
+        module: &ModuleInfo,
+        index: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+        context: &Context,
+        code: &[u8],
+    ) -> Result<(), CompileError> {
+        match &self.config.dump {
+            Some(dump) if dump.should_dump(module, index) => {
+                // Calls refer to functions by their index rather than through
+                // names declared on the function, which `display` looks up
+                let mut func = context.func.clone();
+                let user_names = func
+                    .dfg
+                    .ext_funcs
+                    .values()
+                    .filter_map(|ext_func| match ext_func.name {
+                        ExternalName::User(nameref) => Some(nameref.as_u32() as usize + 1),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(0);
+                for index in func.params.user_named_funcs().len()..user_names {
+                    func.params
+                        .ensure_user_func_name(ir::UserExternalName::new(0, index as u32));
+                }
+                let clif = func.display().to_string();
+                dump.dump_function(
+                    module,
+                    index,
+                    input,
+                    Some(("clif", &clif)),
+                    &disassemble(target, code),
+                )
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl Compiler for CraneliftCompiler {
@@ -55,6 +122,18 @@ impl Compiler for CraneliftCompiler {
         "cranelift"
     }
 
+    fn deterministic_id(&self) -> String {
+        self.config.settings_id()
+    }
+
+    fn is_pic(&self) -> bool {
+        self.config.enable_pic
+    }
+
+    fn text_section_size(&self) -> Option<u64> {
+        self.config.text_section_size
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
@@ -91,260 +170,207 @@ impl Compiler for CraneliftCompiler {
             // FDEs will cause some issues in Linux.
             None
         } else {
-            match target.triple().default_calling_convention() {
-                Ok(CallingConvention::SystemV) => {
-                    match isa.create_systemv_cie() {
-                        Some(cie) => {
-                            let mut dwarf_frametable = FrameTable::default();
-                            let cie_id = dwarf_frametable.add_cie(cie);
-                            Some((dwarf_frametable, cie_id))
-                        }
-                        // Even though we are in a SystemV system, Cranelift doesn't support it
-                        None => None,
-                    }
-                }
-                _ => None,
-            }
+            dwarf_frametable(&*isa, target)
         };
 
         let mut custom_sections = PrimaryMap::new();
 
-        #[cfg(not(feature = "rayon"))]
-        let mut func_translator = FuncTranslator::new();
-        #[cfg(not(feature = "rayon"))]
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .into_iter()
-            .map(|(i, input)| {
-                let func_index = module.func_index(i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
+        // Position independent code can't have the address of the functions
+        // `return_call_indirect`s compare the callee to
+        let indirect_tail_call_jumps = !isa.flags().is_pic();
+        let tail_calls = if compile_info.features.tail_call {
+            Some(TailCallGraph::new(
+                module,
+                &function_body_inputs,
+                indirect_tail_call_jumps,
+            )?)
+        } else {
+            None
+        };
+
+        // Middlewares wouldn't see the operators of the inlined functions
+        let inlined = if self.config.inline_threshold > 0 && self.config.middlewares.is_empty() {
+            // Inline less when the module would take too much native code
+            let inline_threshold = match self.config.code_size_limit {
+                Some(max_size) => threshold_within_size(
                     module,
-                    &signatures,
-                    &memory_styles,
-                    &table_styles,
-                );
-                context.func.name = match get_function_name(func_index) {
-                    ExternalName::User(nameref) => {
-                        if context.func.params.user_named_funcs().is_valid(nameref) {
-                            let name = &context.func.params.user_named_funcs()[nameref];
-                            UserFuncName::User(name.clone())
-                        } else {
-                            UserFuncName::default()
-                        }
-                    }
-                    ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
-                    _ => UserFuncName::default(),
-                };
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(
-                    self.config
-                        .middlewares
-                        .generate_function_middleware_chain(i),
-                );
-
-                func_translator.translate(
-                    module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
-                    i,
-                )?;
-
-                let mut code_buf: Vec<u8> = Vec::new();
-                context
-                    .compile_and_emit(&*isa, &mut code_buf)
-                    .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
-
-                let result = context.compiled_code().unwrap();
-                let func_relocs = result
-                    .buffer
-                    .relocs()
-                    .into_iter()
-                    .map(|r| mach_reloc_to_reloc(module, r))
-                    .collect::<Vec<_>>();
-
-                let traps = result
-                    .buffer
-                    .traps()
-                    .into_iter()
-                    .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
-
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::Fde(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
-                    }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
+                    &function_body_inputs,
+                    self.config.inline_threshold,
+                    max_size,
+                )?,
+                None => self.config.inline_threshold,
+            };
+            Some(Arc::new(InlinedFunctions::new(
+                module,
+                &function_body_inputs,
+                inline_threshold,
+            )?))
+        } else {
+            None
+        };
 
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
+        let new_func_translator = || {
+            let mut func_translator = FuncTranslator::new();
+            func_translator.set_tail_call(compile_info.features.tail_call);
+            func_translator.set_indirect_tail_call_jumps(indirect_tail_call_jumps);
+            func_translator.set_exceptions(compile_info.features.exceptions);
+            func_translator.set_inlined_functions(inlined.clone());
+            func_translator
+        };
 
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len());
-
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
-                    },
-                    fde,
-                ))
-            })
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .unzip();
-        #[cfg(feature = "rayon")]
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .par_iter()
-            .map_init(FuncTranslator::new, |func_translator, (i, input)| {
-                let func_index = module.func_index(*i);
-                let mut context = Context::new();
-                let mut func_env = FuncEnvironment::new(
-                    isa.frontend_config(),
-                    module,
-                    &signatures,
-                    memory_styles,
-                    table_styles,
-                );
-                context.func.name = match get_function_name(func_index) {
-                    ExternalName::User(nameref) => {
-                        if context.func.params.user_named_funcs().is_valid(nameref) {
-                            let name = &context.func.params.user_named_funcs()[nameref];
-                            UserFuncName::User(name.clone())
-                        } else {
-                            UserFuncName::default()
-                        }
+        let compile_function = |func_translator: &mut FuncTranslator,
+                                &(i, input): &(LocalFunctionIndex, &FunctionBodyData<'_>)|
+         -> Result<_, CompileError> {
+            let func_index = module.func_index(i);
+            let mut context = Context::new();
+            let mut func_env = FuncEnvironment::new(
+                isa.frontend_config(),
+                module,
+                &signatures,
+                memory_styles,
+                table_styles,
+            );
+            func_env.set_canonicalize_nans(self.config.enable_nan_canonicalization);
+            func_env.set_native_fma(has_native_fma(target));
+            context.func.name = match get_function_name(func_index) {
+                ExternalName::User(nameref) => {
+                    if context.func.params.user_named_funcs().is_valid(nameref) {
+                        let name = &context.func.params.user_named_funcs()[nameref];
+                        UserFuncName::User(name.clone())
+                    } else {
+                        UserFuncName::default()
                     }
-                    ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
-                    _ => UserFuncName::default(),
-                };
-                context.func.signature = signatures[module.functions[func_index]].clone();
-                // if generate_debug_info {
-                //     context.func.collect_debug_info();
-                // }
-                let mut reader =
-                    MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
-                reader.set_middleware_chain(
-                    self.config
-                        .middlewares
-                        .generate_function_middleware_chain(*i),
-                );
-
-                func_translator.translate(
+                }
+                ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
+                _ => UserFuncName::default(),
+            };
+            context.func.signature = signatures[module.functions[func_index]].clone();
+            // if generate_debug_info {
+            //     context.func.collect_debug_info();
+            // }
+            let mut reader = self.function_reader(input, i);
+            // The functions the function can reach through tail calls are
+            // compiled into it, so the tail calls can be jumps
+            let mut callee_readers = tail_calls
+                .as_ref()
+                .map(|tail_calls| tail_calls.group(i))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|index| {
+                    let input = &function_body_inputs[index];
+                    (index, self.function_reader(input, index))
+                })
+                .collect::<Vec<_>>();
+            let mut tail_callees = callee_readers
+                .iter_mut()
+                .map(|(index, reader)| TailCallee {
+                    index: *index,
+                    reader,
+                })
+                .collect::<Vec<_>>();
+
+            func_translator
+                .translate(
                     module_translation_state,
                     &mut reader,
                     &mut context.func,
                     &mut func_env,
-                    *i,
-                )?;
-
-                let mut code_buf: Vec<u8> = Vec::new();
-                context
-                    .compile_and_emit(&*isa, &mut code_buf)
-                    .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
-
-                let result = context.compiled_code().unwrap();
-                let func_relocs = result
-                    .buffer
-                    .relocs()
-                    .iter()
-                    .map(|r| mach_reloc_to_reloc(module, r))
-                    .collect::<Vec<_>>();
-
-                let traps = result
-                    .buffer
-                    .traps()
-                    .iter()
-                    .map(mach_trap_to_trap)
-                    .collect::<Vec<_>>();
-
-                let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
-                    #[cfg(feature = "unwind")]
-                    CraneliftUnwindInfo::Fde(fde) => {
-                        if dwarf_frametable.is_some() {
-                            let fde = fde.to_fde(Address::Symbol {
-                                // The symbol is the kind of relocation.
-                                // "0" is used for functions
-                                symbol: WriterRelocate::FUNCTION_SYMBOL,
-                                // We use the addend as a way to specify the
-                                // function index
-                                addend: i.index() as _,
-                            });
-                            // The unwind information is inserted into the dwarf section
-                            (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
-                        } else {
-                            (None, None)
-                        }
+                    i,
+                    &mut tail_callees,
+                )
+                .map_err(|error| {
+                    let readers = callee_readers
+                        .iter()
+                        .map(|(index, reader)| (*index, reader));
+                    translation_error(module, error, std::iter::once((i, &reader)).chain(readers))
+                })?;
+
+            let mut code_buf: Vec<u8> = Vec::new();
+            context
+                .compile_and_emit(&*isa, &mut code_buf)
+                .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
+            #[cfg(feature = "dump")]
+            self.dump_function(target, module, i, input, &context, &code_buf)?;
+
+            let result = context.compiled_code().unwrap();
+            let func_relocs = result
+                .buffer
+                .relocs()
+                .iter()
+                .map(|r| mach_reloc_to_reloc(module, r))
+                .collect::<Vec<_>>();
+
+            let traps = result
+                .buffer
+                .traps()
+                .iter()
+                .map(mach_trap_to_trap)
+                .collect::<Vec<_>>();
+
+            let (unwind_info, fde) = match compiled_function_unwind_info(&*isa, &context)? {
+                #[cfg(feature = "unwind")]
+                CraneliftUnwindInfo::Fde(fde) => {
+                    if dwarf_frametable.is_some() {
+                        let fde = function_fde(fde, i);
+                        // The unwind information is inserted into the dwarf section
+                        (Some(CompiledFunctionUnwindInfo::Dwarf), Some(fde))
+                    } else {
+                        (None, None)
                     }
-                    #[cfg(feature = "unwind")]
-                    other => (other.maybe_into_to_windows_unwind(), None),
-
-                    // This is a bit hacky, but necessary since gimli is not
-                    // available when the "unwind" feature is disabled.
-                    #[cfg(not(feature = "unwind"))]
-                    other => (other.maybe_into_to_windows_unwind(), None::<()>),
-                };
-
-                let range = reader.range();
-                let address_map = get_function_address_map(&context, range, code_buf.len());
-
-                Ok((
-                    CompiledFunction {
-                        body: FunctionBody {
-                            body: code_buf,
-                            unwind_info,
-                        },
-                        relocations: func_relocs,
-                        frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                }
+                #[cfg(feature = "unwind")]
+                other => (other.maybe_into_to_windows_unwind(), None),
+
+                // This is a bit hacky, but necessary since gimli is not
+                // available when the "unwind" feature is disabled.
+                #[cfg(not(feature = "unwind"))]
+                other => (other.maybe_into_to_windows_unwind(), None::<()>),
+            };
+
+            let range = reader.range();
+            let address_map = get_function_address_map(&context, range, code_buf.len());
+
+            Ok((
+                CompiledFunction {
+                    body: FunctionBody {
+                        body: code_buf,
+                        unwind_info,
                     },
-                    fde,
-                ))
-            })
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .unzip();
+                    relocations: func_relocs,
+                    frame_info: CompiledFunctionFrameInfo { address_map, traps },
+                },
+                fde,
+            ))
+        };
+        let results = self.config.threads.map_init(
+            &function_body_inputs.iter().collect::<Vec<_>>(),
+            new_func_translator,
+            |func_translator, item| {
+                let (index, input) = *item;
+                compile_function(func_translator, item).map_err(|error| {
+                    error.in_function(FunctionLocation::new(module, index, input.module_offset))
+                })
+            },
+        )?;
+        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) =
+            CompileError::collect(results)?.into_iter().unzip();
+
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &self.config.dump {
+            dump.write_index(
+                module,
+                self.name(),
+                function_body_inputs.keys(),
+                Some("clif"),
+            )?;
+        }
 
         #[cfg(feature = "unwind")]
         let dwarf = if let Some((mut dwarf_frametable, cie_id)) = dwarf_frametable {
             for fde in fdes.into_iter().flatten() {
                 dwarf_frametable.add_fde(cie_id, fde);
             }
-            let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
-            dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
-
-            let eh_frame_section = eh_frame.0.into_section();
+            let eh_frame_section = eh_frame_section(dwarf_frametable, target);
             custom_sections.push(eh_frame_section);
             Some(Dwarf::new(SectionIndex::new(custom_sections.len() - 1)))
         } else {
@@ -353,66 +379,336 @@ impl Compiler for CraneliftCompiler {
         #[cfg(not(feature = "unwind"))]
         let dwarf = None;
 
-        // function call trampolines (only for local functions, by signature)
-        #[cfg(not(feature = "rayon"))]
+        let (function_call_trampolines, dynamic_function_trampolines) =
+            compile_trampolines(&*isa, module, &self.config.threads)?;
+
+        Ok(Compilation {
+            functions: functions.into_iter().collect(),
+            custom_sections,
+            function_call_trampolines,
+            dynamic_function_trampolines,
+            debug: dwarf,
+        })
+    }
+
+    fn lazy_compilation(&self) -> bool {
+        self.config.enable_lazy_compilation
+    }
+
+    /// Compile a stub for each function of the module, which compiles the
+    /// real function with [`Compiler::compile_function`] when it's first
+    /// called.
+    fn compile_module_lazily(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        _module_translation_state: &ModuleTranslationState,
+    ) -> Result<Compilation, CompileError> {
+        let isa = self
+            .config()
+            .isa(target)
+            .map_err(|error| CompileError::Codegen(error.to_string()))?;
+        let module = &compile_info.module;
+        let offsets = VMOffsets::new(isa.frontend_config().pointer_bytes(), module);
+
+        // Stubs stay on the stack under the functions they call, so they
+        // need to be unwound through like any other function
+        #[cfg(feature = "unwind")]
+        let mut dwarf_frametable = if module.functions.len() == module.num_imported_functions {
+            None
+        } else {
+            dwarf_frametable(&*isa, target)
+        };
+
         let mut cx = FunctionBuilderContext::new();
-        #[cfg(not(feature = "rayon"))]
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|sig| make_trampoline_function_call(&*isa, &mut cx, sig))
-            .collect::<Result<Vec<FunctionBody>, CompileError>>()?
-            .into_iter()
-            .collect::<PrimaryMap<SignatureIndex, FunctionBody>>();
-        #[cfg(feature = "rayon")]
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map_init(FunctionBuilderContext::new, |cx, sig| {
-                make_trampoline_function_call(&*isa, cx, sig)
+        let functions = module
+            .functions
+            .iter()
+            .filter_map(|(func_index, sig_index)| {
+                let local_index = module.local_func_index(func_index)?;
+                Some((local_index, &module.signatures[*sig_index]))
             })
-            .collect::<Result<Vec<FunctionBody>, CompileError>>()?
-            .into_iter()
-            .collect::<PrimaryMap<SignatureIndex, FunctionBody>>();
-
-        use wasmer_types::VMOffsets;
-        let offsets = VMOffsets::new_for_trampolines(frontend_config.pointer_bytes());
-        // dynamic function trampolines (only for imported functions)
-        #[cfg(not(feature = "rayon"))]
-        let mut cx = FunctionBuilderContext::new();
-        #[cfg(not(feature = "rayon"))]
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|func_type| make_trampoline_dynamic_function(&*isa, &offsets, &mut cx, &func_type))
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
-        #[cfg(feature = "rayon")]
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .par_iter()
-            .map_init(FunctionBuilderContext::new, |cx, func_type| {
-                make_trampoline_dynamic_function(&*isa, &offsets, cx, func_type)
+            .map(|(local_index, func_type)| {
+                let (mut body, unwind_info) =
+                    make_lazy_stub(&*isa, &offsets, &mut cx, func_type, local_index)?;
+                body.unwind_info = match unwind_info {
+                    #[cfg(feature = "unwind")]
+                    CraneliftUnwindInfo::Fde(fde) => {
+                        dwarf_frametable.as_mut().map(|(dwarf_frametable, cie_id)| {
+                            dwarf_frametable.add_fde(*cie_id, function_fde(fde, local_index));
+                            CompiledFunctionUnwindInfo::Dwarf
+                        })
+                    }
+                    other => other.maybe_into_to_windows_unwind(),
+                };
+                Ok(CompiledFunction {
+                    body,
+                    relocations: vec![],
+                    frame_info: CompiledFunctionFrameInfo::default(),
+                })
             })
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
+            .collect::<Result<PrimaryMap<LocalFunctionIndex, _>, CompileError>>()?;
+
+        #[cfg_attr(not(feature = "unwind"), allow(unused_mut))]
+        let mut custom_sections = PrimaryMap::new();
+        #[cfg(feature = "unwind")]
+        let debug = dwarf_frametable.map(|(dwarf_frametable, _)| {
+            Dwarf::new(custom_sections.push(eh_frame_section(dwarf_frametable, target)))
+        });
+        #[cfg(not(feature = "unwind"))]
+        let debug = None;
+
+        let (function_call_trampolines, dynamic_function_trampolines) =
+            compile_trampolines(&*isa, module, &self.config.threads)?;
 
         Ok(Compilation {
-            functions: functions.into_iter().collect(),
+            functions,
             custom_sections,
             function_call_trampolines,
             dynamic_function_trampolines,
-            debug: dwarf,
+            debug,
         })
     }
+
+    /// Compile a single function of a lazily compiled module.
+    fn compile_function(
+        &self,
+        target: &Target,
+        compile_info: &CompileModuleInfo,
+        module_translation_state: &ModuleTranslationState,
+        index: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+    ) -> Result<LazyCompiledFunction, CompileError> {
+        let isa = self
+            .config()
+            .lazy_function_isa(target)
+            .map_err(|error| CompileError::Codegen(error.to_string()))?;
+        let frontend_config = isa.frontend_config();
+        let module = &compile_info.module;
+        let signatures = module
+            .signatures
+            .iter()
+            .map(|(_sig_index, func_type)| signature_to_cranelift_ir(func_type, frontend_config))
+            .collect::<PrimaryMap<SignatureIndex, ir::Signature>>();
+
+        let mut func_translator = FuncTranslator::new();
+        func_translator.set_tail_call(compile_info.features.tail_call);
+        func_translator.set_exceptions(compile_info.features.exceptions);
+
+        let func_index = module.func_index(index);
+        let mut context = Context::new();
+        let mut func_env = FuncEnvironment::new(
+            isa.frontend_config(),
+            module,
+            &signatures,
+            &compile_info.memory_styles,
+            &compile_info.table_styles,
+        );
+        // The function is allocated on its own, away from the functions it
+        // calls
+        func_env.use_far_calls();
+        func_env.set_canonicalize_nans(self.config.enable_nan_canonicalization);
+        func_env.set_native_fma(has_native_fma(target));
+        context.func.name = match get_function_name(func_index) {
+            ExternalName::User(nameref) => {
+                if context.func.params.user_named_funcs().is_valid(nameref) {
+                    let name = &context.func.params.user_named_funcs()[nameref];
+                    UserFuncName::User(name.clone())
+                } else {
+                    UserFuncName::default()
+                }
+            }
+            ExternalName::TestCase(testcase) => UserFuncName::Testcase(testcase),
+            _ => UserFuncName::default(),
+        };
+        context.func.signature = signatures[module.functions[func_index]].clone();
+        let mut reader = self.function_reader(input, index);
+
+        // The other functions aren't available to compile into this one, so
+        // only the tail calls of the function to itself can be jumps
+        func_translator
+            .translate(
+                module_translation_state,
+                &mut reader,
+                &mut context.func,
+                &mut func_env,
+                index,
+                &mut [],
+            )
+            .map_err(|error| {
+                translation_error(module, error, [(index, &reader)])
+                    .in_function(FunctionLocation::new(module, index, input.module_offset))
+            })?;
+
+        let mut code_buf: Vec<u8> = Vec::new();
+        context
+            .compile_and_emit(&*isa, &mut code_buf)
+            .map_err(|error| {
+                CompileError::Codegen(error.inner.to_string()).in_function(FunctionLocation::new(
+                    module,
+                    index,
+                    input.module_offset,
+                ))
+            })?;
+        #[cfg(feature = "dump")]
+        {
+            self.dump_function(target, module, index, input, &context, &code_buf)?;
+            if let Some(dump) = &self.config.dump {
+                dump.write_index(module, self.name(), [index], Some("clif"))?;
+            }
+        }
+
+        let result = context.compiled_code().unwrap();
+        let relocations = result
+            .buffer
+            .relocs()
+            .iter()
+            .map(|r| mach_reloc_to_reloc(module, r))
+            .collect::<Vec<_>>();
+
+        let traps = result
+            .buffer
+            .traps()
+            .iter()
+            .map(mach_trap_to_trap)
+            .collect::<Vec<_>>();
+
+        // The function gets an eh_frame section of its own
+        let (unwind_info, eh_frame) = match compiled_function_unwind_info(&*isa, &context)? {
+            #[cfg(feature = "unwind")]
+            CraneliftUnwindInfo::Fde(fde) => match dwarf_frametable(&*isa, target) {
+                Some((mut dwarf_frametable, cie_id)) => {
+                    dwarf_frametable.add_fde(cie_id, function_fde(fde, index));
+                    (
+                        Some(CompiledFunctionUnwindInfo::Dwarf),
+                        Some(eh_frame_section(dwarf_frametable, target)),
+                    )
+                }
+                None => (None, None),
+            },
+            other => (other.maybe_into_to_windows_unwind(), None),
+        };
+
+        let range = reader.range();
+        let address_map = get_function_address_map(&context, range, code_buf.len());
+
+        Ok(LazyCompiledFunction {
+            function: CompiledFunction {
+                body: FunctionBody {
+                    body: code_buf,
+                    unwind_info,
+                },
+                relocations,
+                frame_info: CompiledFunctionFrameInfo { address_map, traps },
+            },
+            eh_frame,
+        })
+    }
+}
+
+/// A DWARF frame table with the CIE for `isa`, if the target unwinds
+/// through an `.eh_frame` section.
+#[cfg(feature = "unwind")]
+fn dwarf_frametable(isa: &dyn TargetIsa, target: &Target) -> Option<(FrameTable, CieId)> {
+    match target.triple().default_calling_convention() {
+        Ok(CallingConvention::SystemV) => {
+            // Even though we are in a SystemV system, Cranelift may not
+            // support it
+            let cie = isa.create_systemv_cie()?;
+            let mut dwarf_frametable = FrameTable::default();
+            let cie_id = dwarf_frametable.add_cie(cie);
+            Some((dwarf_frametable, cie_id))
+        }
+        _ => None,
+    }
+}
+
+/// The FDE of the function at `index`, relocated against its address.
+#[cfg(feature = "unwind")]
+fn function_fde(
+    fde: cranelift_codegen::isa::unwind::systemv::UnwindInfo,
+    index: LocalFunctionIndex,
+) -> FrameDescriptionEntry {
+    fde.to_fde(Address::Symbol {
+        // The symbol is the kind of relocation.
+        // "0" is used for functions
+        symbol: WriterRelocate::FUNCTION_SYMBOL,
+        // We use the addend as a way to specify the
+        // function index
+        addend: index.index() as _,
+    })
+}
+
+/// The `.eh_frame` section for `dwarf_frametable`.
+#[cfg(feature = "unwind")]
+fn eh_frame_section(dwarf_frametable: FrameTable, target: &Target) -> CustomSection {
+    let mut eh_frame = EhFrame(WriterRelocate::new(target.triple().endianness().ok()));
+    dwarf_frametable.write_eh_frame(&mut eh_frame).unwrap();
+    eh_frame.0.into_section()
+}
+
+/// Compile the function call trampolines (one per signature) and the dynamic
+/// function trampolines (one per imported function) of a module.
+#[allow(clippy::type_complexity)]
+fn compile_trampolines(
+    isa: &dyn TargetIsa,
+    module: &ModuleInfo,
+    threads: &CompileThreads,
+) -> Result<
+    (
+        PrimaryMap<SignatureIndex, FunctionBody>,
+        PrimaryMap<FunctionIndex, FunctionBody>,
+    ),
+    CompileError,
+> {
+    // function call trampolines (only for local functions, by signature)
+    let function_call_trampolines = threads
+        .map_init(
+            &module.signatures.values().collect::<Vec<_>>(),
+            FunctionBuilderContext::new,
+            |cx, sig| make_trampoline_function_call(isa, cx, sig),
+        )?
+        .into_iter()
+        .collect::<Result<PrimaryMap<SignatureIndex, FunctionBody>, CompileError>>()?;
+
+    let offsets = VMOffsets::new_for_trampolines(isa.frontend_config().pointer_bytes());
+    // dynamic function trampolines (only for imported functions)
+    let dynamic_function_trampolines = threads
+        .map_init(
+            &module.imported_function_types().collect::<Vec<_>>(),
+            FunctionBuilderContext::new,
+            |cx, func_type| make_trampoline_dynamic_function(isa, &offsets, cx, func_type),
+        )?
+        .into_iter()
+        .collect::<Result<PrimaryMap<FunctionIndex, FunctionBody>, CompileError>>()?;
+
+    Ok((function_call_trampolines, dynamic_function_trampolines))
+}
+
+/// Whether `target` has fused multiply-add instructions Cranelift can lower
+/// `fma` to. Elsewhere, the relaxed multiply-adds are a multiply and an add.
+fn has_native_fma(target: &Target) -> bool {
+    target.triple().architecture == Architecture::X86_64
+        && target.cpu_features().contains(CpuFeature::FMA)
+}
+
+/// Say where translating a function failed: at the last operator read by
+/// the first of `readers` which stopped short, that is the function's own
+/// or the one of a tail callee translated into it.
+fn translation_error<'a, 'r: 'a>(
+    module: &ModuleInfo,
+    error: WasmError,
+    readers: impl IntoIterator<Item = (LocalFunctionIndex, &'a MiddlewareBinaryReader<'r>)>,
+) -> CompileError {
+    let failed = readers.into_iter().find_map(|(index, reader)| {
+        let offset = reader.operator_offset()?;
+        (!reader.eof()).then_some((index, offset))
+    });
+    let error = CompileError::from(error);
+    match failed {
+        Some((index, offset)) => error.in_function(FunctionLocation::new(module, index, offset)),
+        None => error,
+    }
 }
 
 fn mach_reloc_to_reloc(module: &ModuleInfo, reloc: &MachReloc) -> Relocation {
@@ -3,14 +3,18 @@ use cranelift_codegen::isa::{lookup, TargetIsa};
 use cranelift_codegen::settings::{self, Configurable};
 use cranelift_codegen::CodegenResult;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, Engine, EngineBuilder, ModuleMiddleware};
-use wasmer_types::{Architecture, CpuFeature, Target};
+#[cfg(feature = "dump")]
+use wasmer_compiler::CompilerDump;
+use wasmer_compiler::{
+    CompileThreads, Compiler, CompilerConfig, Engine, EngineBuilder, ModuleMiddleware, StackProbes,
+};
+use wasmer_types::{Architecture, CpuFeature, FeatureSupport, Features, Target};
 
 // Runtime Environment
 
 /// Possible optimization levels for the Cranelift codegen backend.
 #[non_exhaustive]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CraneliftOptLevel {
     /// No optimizations performed, minimizes compilation time by disabling most
     /// optimizations.
@@ -29,10 +33,19 @@ pub enum CraneliftOptLevel {
 /// consumed by `wasmer_engine::Engine::new`.
 #[derive(Debug, Clone)]
 pub struct Cranelift {
-    enable_nan_canonicalization: bool,
+    pub(crate) enable_nan_canonicalization: bool,
     enable_verifier: bool,
-    enable_pic: bool,
+    pub(crate) enable_pic: bool,
+    pub(crate) text_section_size: Option<u64>,
+    pub(crate) enable_lazy_compilation: bool,
     opt_level: CraneliftOptLevel,
+    use_egraphs: bool,
+    pub(crate) inline_threshold: usize,
+    pub(crate) code_size_limit: Option<u64>,
+    stack_probes: StackProbes,
+    #[cfg(feature = "dump")]
+    pub(crate) dump: Option<CompilerDump>,
+    pub(crate) threads: CompileThreads,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -45,28 +58,127 @@ impl Cranelift {
             enable_nan_canonicalization: false,
             enable_verifier: false,
             opt_level: CraneliftOptLevel::Speed,
+            use_egraphs: false,
+            inline_threshold: 0,
+            code_size_limit: None,
             enable_pic: false,
+            text_section_size: None,
+            enable_lazy_compilation: false,
+            stack_probes: StackProbes::default(),
+            #[cfg(feature = "dump")]
+            dump: None,
+            threads: CompileThreads::default(),
             middlewares: vec![],
         }
     }
 
     /// Enable NaN canonicalization.
     ///
-    /// NaN canonicalization is useful when trying to run WebAssembly
-    /// deterministically across different architectures.
+    /// See [`CompilerConfig::canonicalize_nans`].
     pub fn canonicalize_nans(&mut self, enable: bool) -> &mut Self {
         self.enable_nan_canonicalization = enable;
         self
     }
 
+    /// Identifies the settings which change the generated code, so
+    /// artifacts compiled with different settings can be told apart.
+    pub(crate) fn settings_id(&self) -> String {
+        let mut id = "cranelift".to_string();
+        match self.opt_level {
+            CraneliftOptLevel::Speed => {}
+            CraneliftOptLevel::None => id.push_str("-opt-none"),
+            CraneliftOptLevel::SpeedAndSize => id.push_str("-opt-speed-and-size"),
+        }
+        if self.use_egraphs {
+            id.push_str("-egraphs");
+        }
+        if self.inline_threshold > 0 {
+            id.push_str(&format!("-inline-{}", self.inline_threshold));
+        }
+        if let Some(limit) = self.code_size_limit {
+            id.push_str(&format!("-code-size-limit-{}", limit));
+        }
+        if self.enable_pic {
+            id.push_str("-pic");
+        }
+        if self.enable_nan_canonicalization {
+            id.push_str("-canonicalize-nans");
+        }
+        if self.stack_probes != StackProbes::default() {
+            match self.stack_probes.interval() {
+                Some(interval) => id.push_str(&format!("-probes-{}", interval)),
+                None => id.push_str("-no-probes"),
+            }
+        }
+        id
+    }
+
+    /// How functions with big stack frames probe them.
+    ///
+    /// See [`CompilerConfig::set_stack_probes`].
+    pub fn stack_probes(&mut self, probes: StackProbes) -> &mut Self {
+        self.stack_probes = probes;
+        self
+    }
+
     /// The optimization levels when optimizing the IR.
     pub fn opt_level(&mut self, opt_level: CraneliftOptLevel) -> &mut Self {
         self.opt_level = opt_level;
         self
     }
 
+    /// Optimize the IR with Cranelift's e-graph based mid-end, which can
+    /// find more simplifications than the default passes, but is newer.
+    /// Off by default.
+    pub fn use_egraphs(&mut self, enable: bool) -> &mut Self {
+        self.use_egraphs = enable;
+        self
+    }
+
+    /// Inline the functions of the module with at most `max_operators`
+    /// operators and no locals, control flow or calls into their direct
+    /// callers. 0, the default, disables inlining.
+    ///
+    /// Traps raised by an inlined function appear to come from the call to
+    /// it. Functions are only inlined when compiling the whole module up
+    /// front without middlewares, which wouldn't see the inlined operators.
+    pub fn inline_threshold(&mut self, max_operators: usize) -> &mut Self {
+        self.inline_threshold = max_operators;
+        self
+    }
+
+    /// Lower the inline threshold for modules whose native code would
+    /// otherwise be estimated to take more than `max_size` bytes, down to
+    /// not inlining at all.
+    ///
+    /// See [`CompilerConfig::set_code_size_limit`].
+    pub fn code_size_limit(&mut self, max_size: Option<u64>) -> &mut Self {
+        self.code_size_limit = max_size;
+        self
+    }
+
     /// Generates the ISA for the provided target
     pub fn isa(&self, target: &Target) -> CodegenResult<Box<dyn TargetIsa>> {
+        self.isa_with_flags(target, self.flags(target))
+    }
+
+    /// Generates the ISA used to compile functions lazily, one at a time.
+    ///
+    /// Their code isn't allocated next to the module's libcall trampolines,
+    /// so libcalls have to be called through their absolute address.
+    pub(crate) fn lazy_function_isa(&self, target: &Target) -> CodegenResult<Box<dyn TargetIsa>> {
+        let mut flags = self.flags_builder(target);
+        flags
+            .set("use_colocated_libcalls", "false")
+            .expect("should be a valid flag");
+        self.isa_with_flags(target, settings::Flags::new(flags))
+    }
+
+    fn isa_with_flags(
+        &self,
+        target: &Target,
+        flags: settings::Flags,
+    ) -> CodegenResult<Box<dyn TargetIsa>> {
         let mut builder =
             lookup(target.triple().clone()).expect("construct Cranelift ISA for triple");
         // Cpu Features
@@ -116,25 +228,52 @@ impl Cranelift {
         if cpu_features.contains(CpuFeature::LZCNT) {
             builder.enable("has_lzcnt").expect("should be valid flag");
         }
+        if cpu_features.contains(CpuFeature::FMA) {
+            builder.enable("has_fma").expect("should be valid flag");
+        }
+        if matches!(target.triple().architecture, Architecture::Aarch64(_))
+            && cpu_features.contains(CpuFeature::LSE)
+        {
+            builder.enable("has_lse").expect("should be valid flag");
+        }
 
-        builder.finish(self.flags(target))
+        builder.finish(flags)
     }
 
     /// Generates the flags for the compiler
     pub fn flags(&self, target: &Target) -> settings::Flags {
+        settings::Flags::new(self.flags_builder(target))
+    }
+
+    fn flags_builder(&self, target: &Target) -> settings::Builder {
         let is_riscv = matches!(target.triple().architecture, Architecture::Riscv64(_));
         let mut flags = settings::builder();
 
-        // Enable probestack
-        flags
-            .enable("enable_probestack")
-            .expect("should be valid flag");
+        // Probe the frames bigger than the probe interval
+        match self.stack_probes.interval() {
+            Some(interval) => {
+                flags
+                    .enable("enable_probestack")
+                    .expect("should be valid flag");
+                flags
+                    .set(
+                        "probestack_size_log2",
+                        &interval.trailing_zeros().to_string(),
+                    )
+                    .expect("should be valid flag");
 
-        // Only inline probestack is supported on AArch64
-        if matches!(target.triple().architecture, Architecture::Aarch64(_)) {
-            flags
-                .set("probestack_strategy", "inline")
-                .expect("should be valid flag");
+                // Only inline probestack is supported on AArch64
+                if matches!(target.triple().architecture, Architecture::Aarch64(_)) {
+                    flags
+                        .set("probestack_strategy", "inline")
+                        .expect("should be valid flag");
+                }
+            }
+            None => {
+                flags
+                    .set("enable_probestack", "false")
+                    .expect("should be valid flag");
+            }
         }
 
         // There are two possible traps for division, and this way
@@ -177,6 +316,11 @@ impl Cranelift {
             )
             .expect("should be valid flag");
 
+        let use_egraphs = if self.use_egraphs { "true" } else { "false" };
+        flags
+            .set("use_egraphs", use_egraphs)
+            .expect("should be valid flag");
+
         if is_riscv {
             flags
                 .set("enable_simd", "false")
@@ -196,7 +340,7 @@ impl Cranelift {
             .set("enable_nan_canonicalization", enable_nan_canonicalization)
             .expect("should be valid flag");
 
-        settings::Flags::new(flags)
+        flags
     }
 }
 
@@ -213,6 +357,31 @@ impl CompilerConfig for Cranelift {
         self.enable_nan_canonicalization = enable;
     }
 
+    fn enable_lazy_compilation(&mut self, enable: bool) {
+        self.enable_lazy_compilation = enable;
+    }
+
+    #[cfg(feature = "dump")]
+    fn enable_dump(&mut self, dump: CompilerDump) {
+        self.dump = Some(dump);
+    }
+
+    fn set_compile_threads(&mut self, threads: CompileThreads) {
+        self.threads = threads;
+    }
+
+    fn set_stack_probes(&mut self, probes: StackProbes) {
+        self.stack_probes = probes;
+    }
+
+    fn set_text_section_size(&mut self, max_size: Option<u64>) {
+        self.text_section_size = max_size;
+    }
+
+    fn set_code_size_limit(&mut self, max_size: Option<u64>) {
+        self.code_size_limit = max_size;
+    }
+
     /// Transform it into the compiler
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(CraneliftCompiler::new(*self))
@@ -224,6 +393,25 @@ impl CompilerConfig for Cranelift {
     }
 }
 
+impl FeatureSupport for Cranelift {
+    fn supported_features_for_target(&self, _target: &Target) -> Features {
+        Features {
+            threads: true,
+            reference_types: true,
+            simd: true,
+            bulk_memory: true,
+            multi_value: true,
+            tail_call: true,
+            module_linking: false,
+            multi_memory: true,
+            memory64: true,
+            exceptions: true,
+            relaxed_simd: true,
+            extended_const: true,
+        }
+    }
+}
+
 impl Default for Cranelift {
     fn default() -> Self {
         Self::new()
@@ -20,7 +20,7 @@ use wasmer_types::VMBuiltinFunctionIndex;
 use wasmer_types::VMOffsets;
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, ModuleInfo,
-    SignatureIndex, TableIndex, Type as WasmerType,
+    SignatureIndex, TableIndex, TagIndex, Type as WasmerType,
 };
 use wasmer_types::{MemoryStyle, TableStyle};
 use wasmer_types::{WasmError, WasmResult};
@@ -89,6 +89,18 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory.init`.
     memory_init_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `memory.copy`
+    /// for 64-bit memories.
+    memory64_copy_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.fill`
+    /// for 64-bit memories.
+    memory64_fill_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `memory.init`
+    /// for 64-bit memories.
+    memory64_init_sig: Option<ir::SigRef>,
+
     /// The external function signature for implementing wasm's `data.drop`.
     data_drop_sig: Option<ir::SigRef>,
 
@@ -113,6 +125,15 @@ pub struct FuncEnvironment<'module_environment> {
     /// The external function signature for implementing wasm's `memory32.atomic.notify`.
     memory32_atomic_notify_sig: Option<ir::SigRef>,
 
+    /// The external function signature for implementing wasm's `throw`.
+    throw_sig: Option<ir::SigRef>,
+
+    /// The external function signature for implementing wasm's `rethrow`.
+    rethrow_sig: Option<ir::SigRef>,
+
+    /// The external function signature for freeing a caught exception.
+    exception_drop_sig: Option<ir::SigRef>,
+
     /// Offsets to struct fields accessed by JIT code.
     offsets: VMOffsets,
 
@@ -121,6 +142,16 @@ pub struct FuncEnvironment<'module_environment> {
 
     /// The table styles
     table_styles: &'module_environment PrimaryMap<TableIndex, TableStyle>,
+
+    /// Whether the other functions in the module are close enough to be
+    /// called with a relative call instruction.
+    colocated_calls: bool,
+
+    /// Whether NaNs are canonicalized.
+    canonicalize_nans: bool,
+
+    /// Whether the target has fused multiply-add instructions.
+    native_fma: bool,
 }
 
 impl<'module_environment> FuncEnvironment<'module_environment> {
@@ -147,6 +178,9 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory_copy_sig: None,
             memory_fill_sig: None,
             memory_init_sig: None,
+            memory64_copy_sig: None,
+            memory64_fill_sig: None,
+            memory64_init_sig: None,
             table_get_sig: None,
             table_set_sig: None,
             data_drop_sig: None,
@@ -155,12 +189,36 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
             memory32_atomic_wait32_sig: None,
             memory32_atomic_wait64_sig: None,
             memory32_atomic_notify_sig: None,
+            throw_sig: None,
+            rethrow_sig: None,
+            exception_drop_sig: None,
             offsets: VMOffsets::new(target_config.pointer_bytes(), module),
             memory_styles,
             table_styles,
+            colocated_calls: true,
+            canonicalize_nans: false,
+            native_fma: false,
         }
     }
 
+    /// Call the module's other functions through their absolute address,
+    /// because this function's code won't be allocated next to theirs.
+    pub fn use_far_calls(&mut self) {
+        self.colocated_calls = false;
+    }
+
+    /// Canonicalize the NaNs of the float conversions too, as Cranelift only
+    /// canonicalizes the NaNs of arithmetic.
+    pub fn set_canonicalize_nans(&mut self, enable: bool) {
+        self.canonicalize_nans = enable;
+    }
+
+    /// Lower the relaxed fused multiply-adds to the target's own
+    /// instructions.
+    pub fn set_native_fma(&mut self, enable: bool) {
+        self.native_fma = enable;
+    }
+
     fn pointer_type(&self) -> ir::Type {
         self.target_config.pointer_type()
     }
@@ -557,25 +615,42 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_elem_drop_index())
     }
 
-    fn get_memory_copy_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_copy_sig.unwrap_or_else(|| {
+    /// The type of the addresses of the memory at `index`.
+    fn memory_index_type(&self, index: MemoryIndex) -> ir::Type {
+        if self.module.memories[index].memory64 {
+            I64
+        } else {
+            I32
+        }
+    }
+
+    fn get_memory_copy_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cache = if index_type == I64 {
+            &mut self.memory64_copy_sig
+        } else {
+            &mut self.memory_copy_sig
+        };
+        let sig = cache.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    AbiParam::special(
+                        self.target_config.pointer_type(),
+                        ArgumentPurpose::VMContext,
+                    ),
                     // Memory index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Source address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Length.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                 ],
                 returns: vec![],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_copy_sig = Some(sig);
+        *cache = Some(sig);
         sig
     }
 
@@ -584,41 +659,58 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_copy_sig(func);
+        let memory64 = self.module.memories[memory_index].memory64;
+        let sig = self.get_memory_copy_sig(func, self.memory_index_type(memory_index));
         if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
             (
                 sig,
                 local_memory_index.index(),
-                VMBuiltinFunctionIndex::get_memory_copy_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_memory64_copy_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_memory_copy_index()
+                },
             )
         } else {
             (
                 sig,
                 memory_index.index(),
-                VMBuiltinFunctionIndex::get_imported_memory_copy_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_imported_memory64_copy_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_imported_memory_copy_index()
+                },
             )
         }
     }
 
-    fn get_memory_fill_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_fill_sig.unwrap_or_else(|| {
+    fn get_memory_fill_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cache = if index_type == I64 {
+            &mut self.memory64_fill_sig
+        } else {
+            &mut self.memory_fill_sig
+        };
+        let sig = cache.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    AbiParam::special(
+                        self.target_config.pointer_type(),
+                        ArgumentPurpose::VMContext,
+                    ),
                     // Memory index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Value.
                     AbiParam::new(I32),
                     // Length.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                 ],
                 returns: vec![],
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_fill_sig = Some(sig);
+        *cache = Some(sig);
         sig
     }
 
@@ -627,33 +719,50 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         func: &mut Function,
         memory_index: MemoryIndex,
     ) -> (ir::SigRef, usize, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_fill_sig(func);
+        let memory64 = self.module.memories[memory_index].memory64;
+        let sig = self.get_memory_fill_sig(func, self.memory_index_type(memory_index));
         if let Some(local_memory_index) = self.module.local_memory_index(memory_index) {
             (
                 sig,
                 local_memory_index.index(),
-                VMBuiltinFunctionIndex::get_memory_fill_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_memory64_fill_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_memory_fill_index()
+                },
             )
         } else {
             (
                 sig,
                 memory_index.index(),
-                VMBuiltinFunctionIndex::get_imported_memory_fill_index(),
+                if memory64 {
+                    VMBuiltinFunctionIndex::get_imported_memory64_fill_index()
+                } else {
+                    VMBuiltinFunctionIndex::get_imported_memory_fill_index()
+                },
             )
         }
     }
 
-    fn get_memory_init_sig(&mut self, func: &mut Function) -> ir::SigRef {
-        let sig = self.memory_init_sig.unwrap_or_else(|| {
+    fn get_memory_init_sig(&mut self, func: &mut Function, index_type: ir::Type) -> ir::SigRef {
+        let cache = if index_type == I64 {
+            &mut self.memory64_init_sig
+        } else {
+            &mut self.memory_init_sig
+        };
+        let sig = cache.unwrap_or_else(|| {
             func.import_signature(Signature {
                 params: vec![
-                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    AbiParam::special(
+                        self.target_config.pointer_type(),
+                        ArgumentPurpose::VMContext,
+                    ),
                     // Memory index.
                     AbiParam::new(I32),
                     // Data index.
                     AbiParam::new(I32),
                     // Destination address.
-                    AbiParam::new(I32),
+                    AbiParam::new(index_type),
                     // Source index within the data segment.
                     AbiParam::new(I32),
                     // Length.
@@ -663,16 +772,22 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
                 call_conv: self.target_config.default_call_conv,
             })
         });
-        self.memory_init_sig = Some(sig);
+        *cache = Some(sig);
         sig
     }
 
     fn get_memory_init_func(
         &mut self,
         func: &mut Function,
+        memory_index: MemoryIndex,
     ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
-        let sig = self.get_memory_init_sig(func);
-        (sig, VMBuiltinFunctionIndex::get_memory_init_index())
+        let index_type = self.memory_index_type(memory_index);
+        let sig = self.get_memory_init_sig(func, index_type);
+        if index_type == I64 {
+            (sig, VMBuiltinFunctionIndex::get_memory64_init_index())
+        } else {
+            (sig, VMBuiltinFunctionIndex::get_memory_init_index())
+        }
     }
 
     fn get_data_drop_sig(&mut self, func: &mut Function) -> ir::SigRef {
@@ -696,6 +811,86 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         (sig, VMBuiltinFunctionIndex::get_data_drop_index())
     }
 
+    fn get_throw_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.throw_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Tag index.
+                    AbiParam::new(I32),
+                    // Values.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.throw_sig = Some(sig);
+        sig
+    }
+
+    fn get_throw_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_throw_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_throw_index())
+    }
+
+    fn get_rethrow_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.rethrow_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    AbiParam::special(self.pointer_type(), ArgumentPurpose::VMContext),
+                    // Exception.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.rethrow_sig = Some(sig);
+        sig
+    }
+
+    fn get_rethrow_func(&mut self, func: &mut Function) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_rethrow_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_rethrow_index())
+    }
+
+    fn get_exception_drop_sig(&mut self, func: &mut Function) -> ir::SigRef {
+        let sig = self.exception_drop_sig.unwrap_or_else(|| {
+            func.import_signature(Signature {
+                params: vec![
+                    // Exception.
+                    AbiParam::new(self.pointer_type()),
+                ],
+                returns: vec![],
+                call_conv: self.target_config.default_call_conv,
+            })
+        });
+        self.exception_drop_sig = Some(sig);
+        sig
+    }
+
+    fn get_exception_drop_func(
+        &mut self,
+        func: &mut Function,
+    ) -> (ir::SigRef, VMBuiltinFunctionIndex) {
+        let sig = self.get_exception_drop_sig(func);
+        (sig, VMBuiltinFunctionIndex::get_exception_drop_index())
+    }
+
+    /// Load the address of the store's pending exception.
+    fn load_pending_exception_address(&mut self, pos: &mut FuncCursor<'_>) -> ir::Value {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let offset = i32::try_from(self.offsets.vmctx_pending_exception()).unwrap();
+        pos.ins().load(pointer_type, mem_flags, base, offset)
+    }
+
     fn get_memory32_atomic_wait32_sig(&mut self, func: &mut Function) -> ir::SigRef {
         let sig = self.memory32_atomic_wait32_sig.unwrap_or_else(|| {
             func.import_signature(Signature {
@@ -829,6 +1024,81 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         }
     }
 
+    /// Translates the lookup of the function an indirect call calls, with
+    /// the null and signature checks, and returns the address of its code
+    /// and its `vmctx`.
+    fn load_indirect_callee(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> (ir::Value, ir::Value) {
+        let pointer_type = self.pointer_type();
+
+        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
+
+        // Dereference table_entry_addr to get the function address.
+        let mem_flags = ir::MemFlags::trusted();
+        let table_entry_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vm_funcref_anyfunc_ptr()),
+        );
+
+        // check if the funcref is null
+        pos.ins()
+            .trapz(table_entry_addr, ir::TrapCode::IndirectCallToNull);
+
+        let func_addr = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
+        );
+
+        // If necessary, check the signature.
+        match self.table_styles[table_index] {
+            TableStyle::CallerChecksSignature => {
+                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
+                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
+                let vmctx = self.vmctx(pos.func);
+                let base = pos.ins().global_value(pointer_type, vmctx);
+                let offset =
+                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
+
+                // Load the caller ID.
+                let mut mem_flags = ir::MemFlags::trusted();
+                mem_flags.set_readonly();
+                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
+
+                // Load the callee ID.
+                let mem_flags = ir::MemFlags::trusted();
+                let callee_sig_id = pos.ins().load(
+                    sig_id_type,
+                    mem_flags,
+                    table_entry_addr,
+                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
+                );
+
+                // Check that they match.
+                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
+                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
+            }
+        }
+
+        let vmctx = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            table_entry_addr,
+            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
+        );
+
+        (func_addr, vmctx)
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -865,6 +1135,14 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         index >= 1
     }
 
+    fn canonicalize_nans(&self) -> bool {
+        self.canonicalize_nans
+    }
+
+    fn has_native_fma(&self) -> bool {
+        self.native_fma
+    }
+
     fn make_table(&mut self, func: &mut ir::Function, index: TableIndex) -> WasmResult<ir::Table> {
         let pointer_type = self.pointer_type();
 
@@ -1142,7 +1420,11 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type: if self.module.memories[index].memory64 {
+                I64
+            } else {
+                I32
+            },
         }))
     }
 
@@ -1196,7 +1478,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(func.import_function(ir::ExtFuncData {
             name,
             signature,
-            colocated: true,
+            colocated: self.colocated_calls,
         }))
     }
 
@@ -1210,69 +1492,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         callee: ir::Value,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        let pointer_type = self.pointer_type();
-
-        let table_entry_addr = pos.ins().table_addr(pointer_type, table, callee, 0);
-
-        // Dereference table_entry_addr to get the function address.
-        let mem_flags = ir::MemFlags::trusted();
-        let table_entry_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vm_funcref_anyfunc_ptr()),
-        );
-
-        // check if the funcref is null
-        pos.ins()
-            .trapz(table_entry_addr, ir::TrapCode::IndirectCallToNull);
-
-        let func_addr = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_func_ptr()),
-        );
-
-        // If necessary, check the signature.
-        match self.table_styles[table_index] {
-            TableStyle::CallerChecksSignature => {
-                let sig_id_size = self.offsets.size_of_vmshared_signature_index();
-                let sig_id_type = ir::Type::int(u16::from(sig_id_size) * 8).unwrap();
-                let vmctx = self.vmctx(pos.func);
-                let base = pos.ins().global_value(pointer_type, vmctx);
-                let offset =
-                    i32::try_from(self.offsets.vmctx_vmshared_signature_id(sig_index)).unwrap();
-
-                // Load the caller ID.
-                let mut mem_flags = ir::MemFlags::trusted();
-                mem_flags.set_readonly();
-                let caller_sig_id = pos.ins().load(sig_id_type, mem_flags, base, offset);
-
-                // Load the callee ID.
-                let mem_flags = ir::MemFlags::trusted();
-                let callee_sig_id = pos.ins().load(
-                    sig_id_type,
-                    mem_flags,
-                    table_entry_addr,
-                    i32::from(self.offsets.vmcaller_checked_anyfunc_type_index()),
-                );
-
-                // Check that they match.
-                let cmp = pos.ins().icmp(IntCC::Equal, callee_sig_id, caller_sig_id);
-                pos.ins().trapz(cmp, ir::TrapCode::BadSignature);
-            }
-        }
+        let (func_addr, vmctx) =
+            self.load_indirect_callee(&mut pos, table_index, table, sig_index, callee);
 
         let mut real_call_args = Vec::with_capacity(call_args.len() + 2);
 
         // First append the callee vmctx address.
-        let vmctx = pos.ins().load(
-            pointer_type,
-            mem_flags,
-            table_entry_addr,
-            i32::from(self.offsets.vmcaller_checked_anyfunc_vmctx()),
-        );
         real_call_args.push(vmctx);
 
         // Then append the regular call arguments.
@@ -1281,6 +1506,17 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(pos.ins().call_indirect(sig_ref, func_addr, &real_call_args))
     }
 
+    fn translate_indirect_callee(
+        &mut self,
+        mut pos: FuncCursor<'_>,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> WasmResult<(ir::Value, ir::Value)> {
+        Ok(self.load_indirect_callee(&mut pos, table_index, table, sig_index, callee))
+    }
+
     fn translate_call(
         &mut self,
         mut pos: FuncCursor<'_>,
@@ -1337,13 +1573,36 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         _heap: ir::Heap,
         val: ir::Value,
     ) -> WasmResult<ir::Value> {
+        let memory64 = self.module.memories[index].memory64;
         let (func_sig, index_arg, func_idx) = self.get_memory_grow_func(pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
+        let delta = if memory64 {
+            // The libcall takes a 32-bit number of pages, and growing by more
+            // than that always fails, so larger deltas are clamped to a delta
+            // which fails too
+            let fits = pos
+                .ins()
+                .icmp_imm(IntCC::UnsignedLessThanOrEqual, val, u32::MAX as i64);
+            let truncated = pos.ins().ireduce(I32, val);
+            let too_large = pos.ins().iconst(I32, -1);
+            pos.ins().select(fits, truncated, too_large)
+        } else {
+            val
+        };
         let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
         let call_inst = pos
             .ins()
-            .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+            .call_indirect(func_sig, func_addr, &[vmctx, delta, memory_index]);
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if memory64 {
+            // A failure (-1) has to stay -1 once widened
+            let failed = pos.ins().icmp_imm(IntCC::Equal, result, -1);
+            let extended = pos.ins().uextend(I64, result);
+            let minus_one = pos.ins().iconst(I64, -1);
+            Ok(pos.ins().select(failed, minus_one, extended))
+        } else {
+            Ok(result)
+        }
     }
 
     fn translate_memory_size(
@@ -1358,7 +1617,12 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if self.module.memories[index].memory64 {
+            Ok(pos.ins().uextend(I64, result))
+        } else {
+            Ok(result)
+        }
     }
 
     fn translate_memory_copy(
@@ -1418,7 +1682,7 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
-        let (func_sig, func_idx) = self.get_memory_init_func(pos.func);
+        let (func_sig, func_idx) = self.get_memory_init_func(pos.func, memory_index);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
         let seg_index_arg = pos.ins().iconst(I32, seg_index as i64);
@@ -1443,6 +1707,127 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         Ok(())
     }
 
+    fn translate_load_pending_exception(&mut self, mut pos: FuncCursor) -> WasmResult<ir::Value> {
+        let pointer_type = self.pointer_type();
+        let address = self.load_pending_exception_address(&mut pos);
+        Ok(pos
+            .ins()
+            .load(pointer_type, ir::MemFlags::trusted(), address, 0))
+    }
+
+    fn translate_store_pending_exception(
+        &mut self,
+        mut pos: FuncCursor,
+        exception: ir::Value,
+    ) -> WasmResult<()> {
+        let address = self.load_pending_exception_address(&mut pos);
+        pos.ins()
+            .store(ir::MemFlags::trusted(), exception, address, 0);
+        Ok(())
+    }
+
+    fn translate_exception_is(
+        &mut self,
+        mut pos: FuncCursor,
+        exception: ir::Value,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value> {
+        let pointer_type = self.pointer_type();
+        let vmctx = self.vmctx(pos.func);
+        let base = pos.ins().global_value(pointer_type, vmctx);
+
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let tag_offset = i32::try_from(self.offsets.vmctx_vmtag_id(tag_index)).unwrap();
+        let tag = pos.ins().load(I32, mem_flags, base, tag_offset);
+        let exception_tag = pos.ins().load(
+            I32,
+            mem_flags,
+            exception,
+            i32::from(self.offsets.vmexception_tag()),
+        );
+        Ok(pos.ins().icmp(IntCC::Equal, exception_tag, tag))
+    }
+
+    fn translate_exception_payload(
+        &mut self,
+        mut pos: FuncCursor,
+        exception: ir::Value,
+        tag_index: TagIndex,
+    ) -> WasmResult<Vec<ir::Value>> {
+        let pointer_type = self.pointer_type();
+        let mut mem_flags = ir::MemFlags::trusted();
+        mem_flags.set_readonly();
+
+        let payload = pos.ins().load(
+            pointer_type,
+            mem_flags,
+            exception,
+            i32::from(self.offsets.vmexception_payload()),
+        );
+        let value_size = i32::from(self.offsets.size_of_vmexception_value());
+        let mut values = Vec::new();
+        for (i, ty) in self.get_tag_params(tag_index).iter().enumerate() {
+            let ty = type_to_irtype(*ty, self.target_config)?;
+            let offset = i32::try_from(i).unwrap() * value_size;
+            values.push(pos.ins().load(ty, mem_flags, payload, offset));
+        }
+        Ok(values)
+    }
+
+    fn translate_throw(
+        &mut self,
+        mut pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()> {
+        let pointer_type = self.pointer_type();
+        let (func_sig, func_idx) = self.get_throw_func(pos.func);
+
+        // The builtin copies the values into the exception
+        let values = if args.is_empty() {
+            pos.ins().iconst(pointer_type, 0)
+        } else {
+            let value_size = u32::from(self.offsets.size_of_vmexception_value());
+            let slot = pos.func.create_sized_stack_slot(ir::StackSlotData::new(
+                ir::StackSlotKind::ExplicitSlot,
+                value_size * u32::try_from(args.len()).unwrap(),
+            ));
+            let values = pos.ins().stack_addr(pointer_type, slot, 0);
+            for (i, arg) in args.iter().enumerate() {
+                let offset = i32::try_from(i).unwrap() * i32::try_from(value_size).unwrap();
+                pos.ins()
+                    .store(ir::MemFlags::trusted(), *arg, values, offset);
+            }
+            values
+        };
+        let tag_index_arg = pos.ins().iconst(I32, i64::from(tag_index.as_u32()));
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, tag_index_arg, values]);
+        Ok(())
+    }
+
+    fn translate_rethrow(&mut self, mut pos: FuncCursor, exception: ir::Value) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_rethrow_func(pos.func);
+        let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins()
+            .call_indirect(func_sig, func_addr, &[vmctx, exception]);
+        Ok(())
+    }
+
+    fn translate_exception_drop(
+        &mut self,
+        mut pos: FuncCursor,
+        exception: ir::Value,
+    ) -> WasmResult<()> {
+        let (func_sig, func_idx) = self.get_exception_drop_func(pos.func);
+        let (_, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+        pos.ins().call_indirect(func_sig, func_addr, &[exception]);
+        Ok(())
+    }
+
     fn translate_table_size(
         &mut self,
         mut pos: FuncCursor,
@@ -1590,6 +1975,10 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         }
     }
 
+    fn func_index(&self, function_index: LocalFunctionIndex) -> FunctionIndex {
+        self.module.func_index(function_index)
+    }
+
     fn get_local_type(&self, local_index: u32) -> Option<WasmerType> {
         self.type_stack.get(local_index as usize).cloned()
     }
@@ -1606,4 +1995,8 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType> {
         self.module.signatures.get(sig_index)
     }
+
+    fn get_tag_params(&self, tag_index: TagIndex) -> &[WasmerType] {
+        self.module.signatures[self.module.tags[tag_index]].params()
+    }
 }
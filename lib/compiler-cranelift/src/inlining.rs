@@ -0,0 +1,179 @@
+//! Finding the tiny functions of a module which are inlined into their
+//! callers.
+//!
+//! Cranelift doesn't inline, so a call to a small helper, like the getters
+//! and arithmetic wrappers compilers to WebAssembly leave behind, costs more
+//! than the helper itself. Functions which are a few straight-line operators
+//! on their parameters are translated at each direct call to them instead
+//! (see `translate_inlined_call`).
+//!
+//! Each inlined call copies the function's code, so with a code size limit,
+//! the threshold is lowered until the module's estimated native code fits
+//! (see [`threshold_within_size`]).
+
+use std::collections::HashMap;
+use std::vec::Vec;
+use wasmer_compiler::wasmparser::Operator;
+use wasmer_compiler::{FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{FunctionIndex, LocalFunctionIndex, ModuleInfo, WasmResult};
+
+/// The body of a function inlined into its callers.
+pub(crate) struct InlinedBody {
+    /// The function's code, as in the code section.
+    code: Vec<u8>,
+    /// The offset of the code in the module.
+    offset: usize,
+    /// The number of the function's parameters.
+    pub(crate) num_params: usize,
+}
+
+impl InlinedBody {
+    /// A reader of the function's operators, past its (empty) locals.
+    pub(crate) fn reader(&self) -> WasmResult<MiddlewareBinaryReader<'_>> {
+        let mut reader = MiddlewareBinaryReader::new_with_offset(&self.code, self.offset);
+        reader.read_local_count()?;
+        Ok(reader)
+    }
+}
+
+/// The functions of a module which are inlined into their callers.
+#[derive(Default)]
+pub(crate) struct InlinedFunctions {
+    bodies: HashMap<FunctionIndex, InlinedBody>,
+}
+
+impl InlinedFunctions {
+    /// Find the functions in `bodies` with at most `max_operators`
+    /// operators, no locals besides their parameters, and no control flow
+    /// or calls.
+    pub(crate) fn new(
+        module: &ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        max_operators: usize,
+    ) -> WasmResult<Self> {
+        let mut inlined = HashMap::new();
+        for (index, body) in bodies.iter() {
+            if inlinable_operators(body, max_operators)?.is_some() {
+                let func_index = module.func_index(index);
+                let num_params = module.signatures[module.functions[func_index]]
+                    .params()
+                    .len();
+                inlined.insert(
+                    func_index,
+                    InlinedBody {
+                        code: body.data.to_vec(),
+                        offset: body.module_offset,
+                        num_params,
+                    },
+                );
+            }
+        }
+
+        Ok(Self { bodies: inlined })
+    }
+
+    /// The body to translate instead of a call to `index`, if it's inlined.
+    pub(crate) fn get(&self, index: FunctionIndex) -> Option<&InlinedBody> {
+        self.bodies.get(&index)
+    }
+}
+
+/// The number of operators of the function, if it's inlined with a
+/// threshold of at least that many operators, up to `max_operators`.
+fn inlinable_operators(
+    body: &FunctionBodyData<'_>,
+    max_operators: usize,
+) -> WasmResult<Option<usize>> {
+    let mut reader = MiddlewareBinaryReader::new_with_offset(body.data, body.module_offset);
+    if reader.read_local_count()? != 0 {
+        return Ok(None);
+    }
+
+    let mut num_operators = 0;
+    loop {
+        match reader.read_operator()? {
+            // Without blocks, the first `end` is the function's
+            Operator::End => return Ok(reader.eof().then_some(num_operators)),
+            // The inlined body runs in the caller's frame, with the caller's
+            // control stack, and its parameters are the caller's values
+            Operator::Unreachable
+            | Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::CatchAll
+            | Operator::Throw { .. }
+            | Operator::Rethrow { .. }
+            | Operator::Delegate { .. }
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::LocalSet { .. }
+            | Operator::LocalTee { .. } => return Ok(None),
+            _ => {}
+        }
+        num_operators += 1;
+        if num_operators > max_operators {
+            return Ok(None);
+        }
+    }
+}
+
+/// A rough estimate of the bytes of native code Cranelift generates for
+/// each byte of WebAssembly code.
+const NATIVE_BYTES_PER_WASM_BYTE: u64 = 4;
+
+/// The highest inline threshold up to `max_operators` with which the
+/// module's native code is estimated to take at most `max_size` bytes.
+///
+/// Every function's code is counted once, plus once more for each direct
+/// call to it which is inlined, and smaller functions are inlined first.
+pub(crate) fn threshold_within_size(
+    module: &ModuleInfo,
+    bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    max_operators: usize,
+    max_size: u64,
+) -> WasmResult<usize> {
+    let mut calls: HashMap<FunctionIndex, u64> = HashMap::new();
+    let mut candidates = Vec::new();
+    let mut size = 0_u64;
+    for (index, body) in bodies.iter() {
+        let code_size = body.data.len() as u64 * NATIVE_BYTES_PER_WASM_BYTE;
+        size = size.saturating_add(code_size);
+        if let Some(num_operators) = inlinable_operators(body, max_operators)? {
+            candidates.push((num_operators, module.func_index(index), code_size));
+        }
+
+        let mut reader = MiddlewareBinaryReader::new_with_offset(body.data, body.module_offset);
+        reader.read_local_count()?;
+        while !reader.eof() {
+            if let Operator::Call { function_index } = reader.read_operator()? {
+                *calls
+                    .entry(FunctionIndex::from_u32(function_index))
+                    .or_default() += 1;
+            }
+        }
+    }
+    if size > max_size {
+        return Ok(0);
+    }
+
+    candidates.sort_by_key(|&(num_operators, _, _)| num_operators);
+    for (num_operators, func_index, code_size) in candidates {
+        let num_calls = calls.get(&func_index).copied().unwrap_or_default();
+        size = size.saturating_add(num_calls.saturating_mul(code_size));
+        if size > max_size {
+            // Functions with as many operators aren't inlined either
+            return Ok(num_operators.saturating_sub(1));
+        }
+    }
+    Ok(max_operators)
+}
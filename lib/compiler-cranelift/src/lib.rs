@@ -50,6 +50,8 @@ mod debug;
 #[cfg(feature = "unwind")]
 mod dwarf;
 mod func_environ;
+mod inlining;
+mod tail_calls;
 mod trampoline;
 mod translator;
 
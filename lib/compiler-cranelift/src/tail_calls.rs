@@ -0,0 +1,102 @@
+//! Finding the functions of a module which tail call each other.
+//!
+//! Cranelift can't lower tail calls yet, so they only run in constant stack
+//! space as jumps within a function. For that, each function is compiled
+//! together with the functions it can reach through `return_call`s and
+//! `return_call_indirect`s, its tail call group (see
+//! `FuncTranslator::translate`).
+
+use std::vec::Vec;
+use wasmer_compiler::wasmparser::Operator;
+use wasmer_compiler::{FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{FunctionIndex, LocalFunctionIndex, ModuleInfo, SignatureIndex, WasmResult};
+
+/// The most functions compiled into one, so modules with a lot of tail calls
+/// don't make every function huge. Tail calls to the functions left out are
+/// calls followed by returns.
+const MAX_GROUP_SIZE: usize = 16;
+
+/// The functions each function of a module tail calls.
+pub(crate) struct TailCallGraph {
+    callees: PrimaryMap<LocalFunctionIndex, Vec<LocalFunctionIndex>>,
+}
+
+impl TailCallGraph {
+    /// Find the tail calls in `bodies`.
+    ///
+    /// With `indirect`, the functions a `return_call_indirect` calls are all
+    /// the functions of its type in the module's element segments.
+    pub(crate) fn new(
+        module: &ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        indirect: bool,
+    ) -> WasmResult<Self> {
+        let mut in_tables = Vec::new();
+        if indirect {
+            let elements = module
+                .table_initializers
+                .iter()
+                .flat_map(|initializer| initializer.elements.iter())
+                .chain(module.passive_elements.values().flat_map(|e| e.iter()));
+            in_tables.extend(elements.filter_map(|index| module.local_func_index(*index)));
+            in_tables.sort();
+            in_tables.dedup();
+        }
+
+        let callees = bodies
+            .values()
+            .map(|body| {
+                let mut reader =
+                    MiddlewareBinaryReader::new_with_offset(body.data, body.module_offset);
+                for _ in 0..reader.read_local_count()? {
+                    reader.read_local_decl()?;
+                }
+
+                let mut callees = Vec::new();
+                while !reader.eof() {
+                    match reader.read_operator()? {
+                        Operator::ReturnCall { function_index } => {
+                            let index = FunctionIndex::from_u32(function_index);
+                            callees.extend(module.local_func_index(index));
+                        }
+                        Operator::ReturnCallIndirect { type_index, .. } => {
+                            let signature =
+                                &module.signatures[SignatureIndex::from_u32(type_index)];
+                            callees.extend(in_tables.iter().filter(|index| {
+                                let index = module.func_index(**index);
+                                module.signatures[module.functions[index]] == *signature
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+                callees.sort();
+                callees.dedup();
+                Ok(callees)
+            })
+            .collect::<WasmResult<_>>()?;
+
+        Ok(Self { callees })
+    }
+
+    /// The functions to compile together with `index`, the ones it can reach
+    /// through tail calls, the closest first.
+    pub(crate) fn group(&self, index: LocalFunctionIndex) -> Vec<LocalFunctionIndex> {
+        let mut group = vec![index];
+        let mut next = 0;
+        while next < group.len() {
+            for callee in &self.callees[group[next]] {
+                if group.len() == MAX_GROUP_SIZE {
+                    break;
+                }
+                if !group.contains(callee) {
+                    group.push(*callee);
+                }
+            }
+            next += 1;
+        }
+        group.remove(0);
+        group
+    }
+}
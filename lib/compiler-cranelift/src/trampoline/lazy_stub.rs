@@ -0,0 +1,102 @@
+//! A stub standing in for a lazily compiled function until it's called.
+
+use crate::translator::{
+    compiled_function_unwind_info, signature_to_cranelift_ir, CraneliftUnwindInfo,
+};
+use cranelift_codegen::ir;
+use cranelift_codegen::ir::{Function, InstBuilder, MemFlags, UserFuncName};
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::Context;
+
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use wasmer_types::{
+    CompileError, FunctionBody, FunctionType, LocalFunctionIndex, VMBuiltinFunctionIndex, VMOffsets,
+};
+
+/// Create the stub for a lazily compiled function.
+///
+/// The stub has the same signature as the function. It asks the
+/// `lazy_compile` builtin for the function's body, which compiles it on the
+/// first call, and then calls the body with its own arguments.
+///
+/// The stub stays on the stack while the body runs, so its unwind info is
+/// returned for the caller to put with the module's.
+pub(crate) fn make_lazy_stub(
+    isa: &dyn TargetIsa,
+    offsets: &VMOffsets,
+    fn_builder_ctx: &mut FunctionBuilderContext,
+    func_type: &FunctionType,
+    index: LocalFunctionIndex,
+) -> Result<(FunctionBody, CraneliftUnwindInfo), CompileError> {
+    let pointer_type = isa.pointer_type();
+    let frontend_config = isa.frontend_config();
+    let signature = signature_to_cranelift_ir(func_type, frontend_config);
+    let mut lazy_compile_sig = ir::Signature::new(frontend_config.default_call_conv);
+    // Add the `vmctx` parameter.
+    lazy_compile_sig.params.push(ir::AbiParam::special(
+        pointer_type,
+        ir::ArgumentPurpose::VMContext,
+    ));
+    // Add the function index parameter.
+    lazy_compile_sig
+        .params
+        .push(ir::AbiParam::new(ir::types::I32));
+    // The function's body is returned.
+    lazy_compile_sig
+        .returns
+        .push(ir::AbiParam::new(pointer_type));
+
+    let mut context = Context::new();
+    context.func =
+        Function::with_name_signature(UserFuncName::user(0, index.as_u32()), signature.clone());
+
+    {
+        let mut builder = FunctionBuilder::new(&mut context.func, fn_builder_ctx);
+        let block0 = builder.create_block();
+
+        builder.append_block_params_for_function_params(block0);
+        builder.switch_to_block(block0);
+        builder.seal_block(block0);
+
+        let args = builder.func.dfg.block_params(block0).to_vec();
+        let vmctx_ptr_val = args[0];
+
+        let mut mem_flags = MemFlags::trusted();
+        mem_flags.set_readonly();
+        let lazy_compile = builder.ins().load(
+            pointer_type,
+            mem_flags,
+            vmctx_ptr_val,
+            offsets.vmctx_builtin_function(VMBuiltinFunctionIndex::get_lazy_compile_index()) as i32,
+        );
+        let index_val = builder.ins().iconst(ir::types::I32, index.as_u32() as i64);
+        let lazy_compile_sig = builder.import_signature(lazy_compile_sig);
+        let call = builder.ins().call_indirect(
+            lazy_compile_sig,
+            lazy_compile,
+            &[vmctx_ptr_val, index_val],
+        );
+        let body = builder.inst_results(call)[0];
+
+        let new_sig = builder.import_signature(signature);
+        let call = builder.ins().call_indirect(new_sig, body, &args);
+        let results = builder.inst_results(call).to_vec();
+        builder.ins().return_(&results);
+        builder.finalize()
+    }
+
+    let mut code_buf = Vec::new();
+    context
+        .compile_and_emit(isa, &mut code_buf)
+        .map_err(|error| CompileError::Codegen(error.inner.to_string()))?;
+
+    let unwind_info = compiled_function_unwind_info(isa, &context)?;
+
+    Ok((
+        FunctionBody {
+            body: code_buf,
+            unwind_info: None,
+        },
+        unwind_info,
+    ))
+}
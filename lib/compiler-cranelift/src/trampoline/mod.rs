@@ -2,9 +2,11 @@
 
 mod dynamic_function;
 mod function_call;
+mod lazy_stub;
 
 pub use self::dynamic_function::make_trampoline_dynamic_function;
 pub use self::function_call::make_trampoline_function_call;
+pub(crate) use self::lazy_stub::make_lazy_stub;
 
 pub use cranelift_codegen::print_errors::pretty_error;
 pub use cranelift_codegen::Context;
@@ -75,8 +75,11 @@
 //!     ("Relax verification to allow I8X16 to act as a default vector type")
 
 use super::func_environ::{FuncEnvironment, GlobalVariable, ReturnMode};
-use super::func_state::{ControlStackFrame, ElseData, FuncTranslationState};
+use super::func_state::{
+    CatchClause, ControlStackFrame, ElseData, FuncTranslationState, TailCallTarget,
+};
 use super::translation_utils::{block_with_params, f32_translation, f64_translation};
+use crate::inlining::InlinedBody;
 use crate::{hash_map, HashMap};
 use core::cmp;
 use core::convert::TryFrom;
@@ -93,9 +96,13 @@ use smallvec::SmallVec;
 use std::vec::Vec;
 
 use wasmer_compiler::wasmparser::{MemArg, Operator};
-use wasmer_compiler::{from_binaryreadererror_wasmerror, wasm_unsupported, ModuleTranslationState};
+use wasmer_compiler::{
+    from_binaryreadererror_wasmerror, wasm_unsupported, FunctionBinaryReader,
+    ModuleTranslationState,
+};
 use wasmer_types::{
-    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, WasmResult,
+    FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, TagIndex,
+    Type as WasmerType, WasmResult,
 };
 
 // Clippy warns about "align: _" but its important to document that the align field is ignored
@@ -124,9 +131,12 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
          *  disappear in the Cranelift Code
          ***********************************************************************************/
         Operator::LocalGet { local_index } => {
-            let val = builder.use_var(Variable::from_u32(*local_index));
+            // Functions compiled into another one for tail calls have their
+            // locals after the other function's
+            let local_index = state.local_base + *local_index;
+            let val = builder.use_var(Variable::from_u32(local_index));
             state.push1(val);
-            let label = ValueLabel::from_u32(*local_index);
+            let label = ValueLabel::from_u32(local_index);
             builder.set_val_label(val, label);
         }
         Operator::LocalSet { local_index } => {
@@ -138,8 +148,9 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 val = optionally_bitcast_vector(val, I8X16, builder);
             }
 
-            builder.def_var(Variable::from_u32(*local_index), val);
-            let label = ValueLabel::from_u32(*local_index);
+            let local_index = state.local_base + *local_index;
+            builder.def_var(Variable::from_u32(local_index), val);
+            let label = ValueLabel::from_u32(local_index);
             builder.set_val_label(val, label);
         }
         Operator::LocalTee { local_index } => {
@@ -151,8 +162,9 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 val = optionally_bitcast_vector(val, I8X16, builder);
             }
 
-            builder.def_var(Variable::from_u32(*local_index), val);
-            let label = ValueLabel::from_u32(*local_index);
+            let local_index = state.local_base + *local_index;
+            builder.def_var(Variable::from_u32(local_index), val);
+            let label = ValueLabel::from_u32(local_index);
             builder.set_val_label(val, label);
         }
         /********************************** Globals ****************************************
@@ -371,6 +383,14 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
+        Operator::End
+            if matches!(
+                state.control_stack.last(),
+                Some(ControlStackFrame::Try { .. })
+            ) =>
+        {
+            translate_try_end(None, builder, state, environ)?;
+        }
         Operator::End => {
             let frame = state.control_stack.pop().unwrap();
             let next_block = frame.following_code();
@@ -433,20 +453,27 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 };
                 (return_count, frame.br_destination())
             };
+            translate_caught_exceptions_drop(*relative_depth, builder, state, environ)?;
             let destination_args = state.peekn(return_count);
             canonicalise_then_jump(builder, br_destination, destination_args);
             state.popn(return_count);
             state.reachable = false;
         }
-        Operator::BrIf { relative_depth } => translate_br_if(*relative_depth, builder, state),
+        Operator::BrIf { relative_depth } => {
+            translate_br_if(*relative_depth, builder, state, environ)?
+        }
         Operator::BrTable { targets } => {
             let default = targets.default();
             let mut min_depth = default;
+            let mut max_depth = default;
             for depth in targets.targets() {
                 let depth = depth.map_err(from_binaryreadererror_wasmerror)?;
                 if depth < min_depth {
                     min_depth = depth;
                 }
+                if depth > max_depth {
+                    max_depth = depth;
+                }
             }
             let jump_args_count = {
                 let i = state.control_stack.len() - 1 - (min_depth as usize);
@@ -457,9 +484,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                     min_depth_frame.num_return_values()
                 }
             };
+            // The deepest target leaves the most `catch` clauses
+            let drops_exceptions = !state.caught_exceptions(max_depth).is_empty();
             let val = state.pop1();
             let mut data = JumpTableData::with_capacity(targets.len() as usize);
-            if jump_args_count == 0 {
+            if jump_args_count == 0 && !drops_exceptions {
                 // No jump arguments
                 for depth in targets.targets() {
                     let depth = depth.map_err(from_binaryreadererror_wasmerror)?;
@@ -480,7 +509,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 };
                 builder.ins().br_table(val, block, jt);
             } else {
-                // Here we have jump arguments, but Cranelift's br_table doesn't support them
+                // Here we have jump arguments, or exceptions to free on the way, but Cranelift's
+                // br_table doesn't support them
                 // We then proceed to split the edges going out of the br_table
                 let return_count = jump_args_count;
                 let mut dest_block_sequence = vec![];
@@ -516,6 +546,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                         frame.set_branched_to_exit();
                         frame.br_destination()
                     };
+                    translate_caught_exceptions_drop(depth as u32, builder, state, environ)?;
                     let destination_args = state.peekn(return_count);
                     canonicalise_then_jump(builder, real_dest_block, destination_args);
                 }
@@ -529,6 +560,8 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 let return_count = frame.num_return_values();
                 (return_count, frame.br_destination())
             };
+            let relative_depth = (state.control_stack.len() - 1) as u32;
+            translate_caught_exceptions_drop(relative_depth, builder, state, environ)?;
             {
                 let return_args = state.peekn_mut(return_count);
                 // TODO(reftypes): maybe ref count here?
@@ -543,17 +576,50 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.popn(return_count);
             state.reachable = false;
         }
-        /********************************** Exception handing **********************************/
-        Operator::Try { .. }
-        | Operator::Catch { .. }
-        | Operator::Throw { .. }
-        | Operator::Rethrow { .. }
-        | Operator::Delegate { .. }
-        | Operator::CatchAll => {
-            return Err(wasm_unsupported!(
-                "proposed exception handling operator {:?}",
-                op
-            ));
+        /********************************** Exception handing **********************************
+         * Cranelift can't unwind, so throwing an exception makes it the store's pending
+         * exception (see `FuncEnvironment::translate_throw`) and branches to the handler of the
+         * innermost `try`, and so does every call returning with a pending exception. The
+         * handler takes the exception and tests its tag against each `catch` clause, and the
+         * exceptions no clause matches go to the next handler, up to the one returning from the
+         * function.
+         ***********************************************************************************/
+        Operator::Try { blockty } => {
+            let (params, results) = module_translation_state.blocktype_params_results(*blockty)?;
+            let next = block_with_params(builder, results, environ)?;
+            let landing = builder.create_block();
+            state.push_try(next, landing, params.len(), results.len());
+        }
+        Operator::Catch { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            translate_catch(Some(tag_index), builder, state, environ)?;
+        }
+        Operator::CatchAll => translate_catch(None, builder, state, environ)?,
+        Operator::Delegate { relative_depth } => {
+            translate_try_end(Some(*relative_depth), builder, state, environ)?;
+        }
+        Operator::Throw { tag_index } => {
+            let tag_index = TagIndex::from_u32(*tag_index);
+            let num_args = environ.get_tag_params(tag_index).len();
+            environ.translate_throw(builder.cursor(), tag_index, state.peekn(num_args))?;
+            state.popn(num_args);
+            let handler = state.exception_handler(builder, state.control_stack.len());
+            builder.ins().jump(handler, &[]);
+            state.reachable = false;
+        }
+        Operator::Rethrow { relative_depth } => {
+            let i = state.control_stack.len() - 1 - (*relative_depth as usize);
+            let exception = match state.control_stack[i] {
+                ControlStackFrame::Try {
+                    catch: Some(ref clause),
+                    ..
+                } => clause.exception,
+                _ => unreachable!(),
+            };
+            environ.translate_rethrow(builder.cursor(), exception)?;
+            let handler = state.exception_handler(builder, state.control_stack.len());
+            builder.ins().jump(handler, &[]);
+            state.reachable = false;
         }
         /************************************ Calls ****************************************
          * The call instructions pop off their arguments from the stack and append their
@@ -561,6 +627,20 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
          * argument referring to an index in the external functions table of the module.
          ************************************************************************************/
         Operator::Call { function_index } => {
+            let inlined = state.inlined.clone();
+            let body = inlined
+                .as_ref()
+                .and_then(|inlined| inlined.get(FunctionIndex::from_u32(*function_index)));
+            if let Some(body) = body {
+                return translate_inlined_call(
+                    module_translation_state,
+                    body,
+                    builder,
+                    state,
+                    environ,
+                );
+            }
+
             let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
 
             let args = state.peekn_mut(num_args);
@@ -585,6 +665,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             );
             state.popn(num_args);
             state.pushn(inst_results);
+            translate_pending_exception_check(builder, state, environ)?;
         }
         Operator::CallIndirect {
             type_index,
@@ -625,6 +706,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             );
             state.popn(num_args);
             state.pushn(inst_results);
+            translate_pending_exception_check(builder, state, environ)?;
         }
         /******************************* Memory management ***********************************
          * Memory management is handled by environment. It is usually translated into calls to
@@ -824,11 +906,13 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         }
         Operator::F64PromoteF32 => {
             let val = state.pop1();
-            state.push1(builder.ins().fpromote(F64, val));
+            let promoted = builder.ins().fpromote(F64, val);
+            state.push1(canonicalize_nan(promoted, environ, builder));
         }
         Operator::F32DemoteF64 => {
             let val = state.pop1();
-            state.push1(builder.ins().fdemote(F32, val));
+            let demoted = builder.ins().fdemote(F32, val);
+            state.push1(canonicalize_nan(demoted, environ, builder));
         }
         Operator::I64TruncF64S | Operator::I64TruncF32S => {
             let val = state.pop1();
@@ -1062,7 +1146,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let timeout = state.pop1(); // 64 (fixed)
             let expected = state.pop1(); // 32 or 64 (per the `Ixx` in `IxxAtomicWait`)
             let addr = state.pop1(); // 32 (fixed)
-            let addr = fold_atomic_mem_addr(addr, memarg, implied_ty, builder);
+            let addr = fold_atomic_mem_addr(addr, memarg, implied_ty, builder)?;
             assert!(builder.func.dfg.value_type(expected) == implied_ty);
             // `fn translate_atomic_wait` can inspect the type of `expected` to figure out what
             // code it needs to generate, if it wants.
@@ -1092,7 +1176,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let heap = state.get_heap(builder.func, memarg.memory, environ)?;
             let count = state.pop1(); // 32 (fixed)
             let addr = state.pop1(); // 32 (fixed)
-            let addr = fold_atomic_mem_addr(addr, memarg, I32, builder);
+            let addr = fold_atomic_mem_addr(addr, memarg, I32, builder)?;
             match environ.translate_atomic_notify(builder.cursor(), heap_index, heap, addr, count) {
                 Ok(res) => {
                     state.push1(res);
@@ -1806,11 +1890,13 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         }
         Operator::F64x2PromoteLowF32x4 => {
             let a = pop1_with_bitcast(state, F32X4, builder);
-            state.push1(builder.ins().fvpromote_low(a));
+            let promoted = builder.ins().fvpromote_low(a);
+            state.push1(canonicalize_nan(promoted, environ, builder));
         }
         Operator::F32x4DemoteF64x2Zero => {
             let a = pop1_with_bitcast(state, F64X2, builder);
-            state.push1(builder.ins().fvdemote(a));
+            let demoted = builder.ins().fvdemote(a);
+            state.push1(canonicalize_nan(demoted, environ, builder));
         }
         Operator::I32x4TruncSatF32x4S => {
             let a = pop1_with_bitcast(state, F32X4, builder);
@@ -2028,31 +2114,227 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let b_high = builder.ins().uwiden_high(b);
             state.push1(builder.ins().imul(a_high, b_high));
         }
-        Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
-            return Err(wasm_unsupported!("proposed tail-call operator {:?}", op));
+        /******************************* Tail calls ****************************************
+         * Cranelift can't lower tail calls yet, so a tail call to a function whose body is in
+         * the function being translated (see `FuncTranslator::translate`) is a jump to that
+         * body, and any other tail call is a call followed by a return.
+         ************************************************************************************/
+        Operator::ReturnCall { function_index } => {
+            let index = FunctionIndex::from_u32(*function_index);
+            match state.tail_call_targets.get_mut(&index) {
+                Some(target) => {
+                    target.used = true;
+                    let target = target.clone();
+                    let args = state.peekn(target.num_params).to_vec();
+                    let relative_depth = (state.control_stack.len() - 1) as u32;
+                    translate_caught_exceptions_drop(relative_depth, builder, state, environ)?;
+                    translate_tail_call_jump(builder, environ, &target, &args);
+                    state.popn(target.num_params);
+                    state.reachable = false;
+                }
+                None => {
+                    let call = Operator::Call {
+                        function_index: *function_index,
+                    };
+                    translate_operator(module_translation_state, &call, builder, state, environ)?;
+                    translate_operator(
+                        module_translation_state,
+                        &Operator::Return,
+                        builder,
+                        state,
+                        environ,
+                    )?;
+                }
+            }
+        }
+        Operator::ReturnCallIndirect {
+            type_index,
+            table_index,
+        } => {
+            let targets = indirect_tail_call_targets(state, environ, *type_index);
+            if !targets.is_empty() {
+                // Jump to the body of the function called if it's one of the
+                // targets, after the same checks as the call
+                let table = state.get_or_create_table(builder.func, *table_index, environ)?;
+                let callee = state.peek1();
+                let (code, vmctx) = environ.translate_indirect_callee(
+                    builder.cursor(),
+                    TableIndex::from_u32(*table_index),
+                    table,
+                    SignatureIndex::from_u32(*type_index),
+                    callee,
+                )?;
+                let caller_vmctx = builder
+                    .func
+                    .special_param(ir::ArgumentPurpose::VMContext)
+                    .unwrap();
+                let same_instance = builder.ins().icmp(IntCC::Equal, vmctx, caller_vmctx);
+
+                let call_block = builder.create_block();
+                let mut next_block = builder.create_block();
+                builder.ins().brz(same_instance, call_block, &[]);
+                builder.ins().jump(next_block, &[]);
+                builder.seal_block(next_block);
+                for (i, index) in targets.iter().enumerate() {
+                    builder.switch_to_block(next_block);
+                    let (func_ref, num_args) =
+                        state.get_direct_func(builder.func, index.as_u32(), environ)?;
+                    let target_code = builder.ins().func_addr(environ.pointer_type(), func_ref);
+                    let same_code = builder.ins().icmp(IntCC::Equal, code, target_code);
+
+                    let jump_block = builder.create_block();
+                    next_block = if i == targets.len() - 1 {
+                        call_block
+                    } else {
+                        builder.create_block()
+                    };
+                    builder.ins().brnz(same_code, jump_block, &[]);
+                    builder.ins().jump(next_block, &[]);
+                    builder.seal_block(jump_block);
+                    if next_block != call_block {
+                        builder.seal_block(next_block);
+                    }
+
+                    builder.switch_to_block(jump_block);
+                    let target = state.tail_call_targets.get_mut(index).unwrap();
+                    target.used = true;
+                    let target = target.clone();
+                    // The arguments are under the callee
+                    let args = state.peekn(num_args + 1)[..num_args].to_vec();
+                    let relative_depth = (state.control_stack.len() - 1) as u32;
+                    translate_caught_exceptions_drop(relative_depth, builder, state, environ)?;
+                    translate_tail_call_jump(builder, environ, &target, &args);
+                }
+                builder.seal_block(call_block);
+                builder.switch_to_block(call_block);
+            }
+
+            let call = Operator::CallIndirect {
+                type_index: *type_index,
+                table_index: *table_index,
+                table_byte: 0,
+            };
+            translate_operator(module_translation_state, &call, builder, state, environ)?;
+            translate_operator(
+                module_translation_state,
+                &Operator::Return,
+                builder,
+                state,
+                environ,
+            )?;
+        }
+        // The relaxed SIMD instructions may give different results on
+        // different platforms. Apart from the multiply-adds, which are fused
+        // where the target can, they give the results of the deterministic
+        // instructions they relax.
+        Operator::I8x16RelaxedSwizzle => {
+            let (a, b) = pop2_with_bitcast(state, I8X16, builder);
+            state.push1(builder.ins().swizzle(I8X16, a, b))
+        }
+        Operator::I32x4RelaxedTruncSatF32x4S => {
+            let a = pop1_with_bitcast(state, F32X4, builder);
+            state.push1(builder.ins().fcvt_to_sint_sat(I32X4, a))
+        }
+        Operator::I32x4RelaxedTruncSatF32x4U => {
+            let a = pop1_with_bitcast(state, F32X4, builder);
+            state.push1(builder.ins().fcvt_to_uint_sat(I32X4, a))
+        }
+        Operator::I32x4RelaxedTruncSatF64x2SZero => {
+            translate_operator(
+                module_translation_state,
+                &Operator::I32x4TruncSatF64x2SZero,
+                builder,
+                state,
+                environ,
+            )?;
+        }
+        Operator::I32x4RelaxedTruncSatF64x2UZero => {
+            translate_operator(
+                module_translation_state,
+                &Operator::I32x4TruncSatF64x2UZero,
+                builder,
+                state,
+                environ,
+            )?;
         }
-        Operator::I8x16RelaxedSwizzle
-        | Operator::I32x4RelaxedTruncSatF32x4S
-        | Operator::I32x4RelaxedTruncSatF32x4U
-        | Operator::I32x4RelaxedTruncSatF64x2SZero
-        | Operator::I32x4RelaxedTruncSatF64x2UZero
-        | Operator::F32x4RelaxedFma
+        Operator::F32x4RelaxedFma
         | Operator::F32x4RelaxedFnma
         | Operator::F64x2RelaxedFma
-        | Operator::F64x2RelaxedFnma
-        | Operator::I8x16RelaxedLaneselect
+        | Operator::F64x2RelaxedFnma => {
+            let ty = match op {
+                Operator::F32x4RelaxedFma | Operator::F32x4RelaxedFnma => F32X4,
+                _ => F64X2,
+            };
+            let c = pop1_with_bitcast(state, ty, builder);
+            let (mut a, b) = pop2_with_bitcast(state, ty, builder);
+            // `nmadd` is `-(a * b) + c`
+            if let Operator::F32x4RelaxedFnma | Operator::F64x2RelaxedFnma = op {
+                a = builder.ins().fneg(a);
+            }
+            if environ.has_native_fma() {
+                state.push1(builder.ins().fma(a, b, c))
+            } else {
+                let product = builder.ins().fmul(a, b);
+                state.push1(builder.ins().fadd(product, c))
+            }
+        }
+        Operator::I8x16RelaxedLaneselect
         | Operator::I16x8RelaxedLaneselect
         | Operator::I32x4RelaxedLaneselect
-        | Operator::I64x2RelaxedLaneselect
-        | Operator::F32x4RelaxedMin
-        | Operator::F32x4RelaxedMax
-        | Operator::F64x2RelaxedMin
-        | Operator::F64x2RelaxedMax
-        | Operator::F32x4RelaxedDotBf16x8AddF32x4
-        | Operator::I16x8RelaxedQ15mulrS
-        | Operator::I16x8DotI8x16I7x16S
-        | Operator::I32x4DotI8x16I7x16AddS => {
-            return Err(wasm_unsupported!("proposed relaxed-simd operator {:?}", op));
+        | Operator::I64x2RelaxedLaneselect => {
+            translate_operator(
+                module_translation_state,
+                &Operator::V128Bitselect,
+                builder,
+                state,
+                environ,
+            )?;
+        }
+        Operator::F32x4RelaxedMin | Operator::F64x2RelaxedMin => {
+            let ty = if let Operator::F32x4RelaxedMin = op {
+                F32X4
+            } else {
+                F64X2
+            };
+            let (a, b) = pop2_with_bitcast(state, ty, builder);
+            state.push1(builder.ins().fmin(a, b))
+        }
+        Operator::F32x4RelaxedMax | Operator::F64x2RelaxedMax => {
+            let ty = if let Operator::F32x4RelaxedMax = op {
+                F32X4
+            } else {
+                F64X2
+            };
+            let (a, b) = pop2_with_bitcast(state, ty, builder);
+            state.push1(builder.ins().fmax(a, b))
+        }
+        Operator::F32x4RelaxedDotBf16x8AddF32x4 => {
+            let c = pop1_with_bitcast(state, F32X4, builder);
+            let (a, b) = pop2_with_bitcast(state, I32X4, builder);
+            let (a_even, a_odd) = bf16x8_to_f32x4_pairs(a, builder);
+            let (b_even, b_odd) = bf16x8_to_f32x4_pairs(b, builder);
+            let even = builder.ins().fmul(a_even, b_even);
+            let odd = builder.ins().fmul(a_odd, b_odd);
+            let dot = builder.ins().fadd(even, odd);
+            state.push1(builder.ins().fadd(dot, c))
+        }
+        Operator::I16x8RelaxedQ15mulrS => {
+            let (a, b) = pop2_with_bitcast(state, I16X8, builder);
+            state.push1(builder.ins().sqmul_round_sat(a, b))
+        }
+        Operator::I16x8DotI8x16I7x16S => {
+            let (a, b) = pop2_with_bitcast(state, I16X8, builder);
+            state.push1(i8x16_pairwise_dot(a, b, builder))
+        }
+        Operator::I32x4DotI8x16I7x16AddS => {
+            let c = pop1_with_bitcast(state, I32X4, builder);
+            let (a, b) = pop2_with_bitcast(state, I16X8, builder);
+            let pairs = i8x16_pairwise_dot(a, b, builder);
+            // Add up the pairs of pairs, sign-extended to 32 bits
+            let one = builder.ins().iconst(I16, 1);
+            let ones = builder.ins().splat(I16X8, one);
+            let dot = builder.ins().widening_pairwise_dot_product_s(pairs, ones);
+            state.push1(builder.ins().iadd(dot, c))
         }
     };
     Ok(())
@@ -2085,7 +2367,9 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 blockty,
             );
         }
-        Operator::Loop { blockty: _ } | Operator::Block { blockty: _ } => {
+        Operator::Loop { blockty: _ }
+        | Operator::Block { blockty: _ }
+        | Operator::Try { blockty: _ } => {
             state.push_block(ir::Block::reserved_value(), 0, 0);
         }
         Operator::Else => {
@@ -2136,7 +2420,37 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
                 _ => unreachable!(),
             }
         }
-        Operator::End => {
+        // The clauses of a reachable `try` are reachable if its body can throw
+        Operator::Catch { tag_index }
+            if matches!(
+                state.control_stack.last(),
+                Some(ControlStackFrame::Try { .. })
+            ) =>
+        {
+            let tag_index = TagIndex::from_u32(tag_index);
+            translate_catch(Some(tag_index), builder, state, environ)?;
+        }
+        Operator::CatchAll
+            if matches!(
+                state.control_stack.last(),
+                Some(ControlStackFrame::Try { .. })
+            ) =>
+        {
+            translate_catch(None, builder, state, environ)?;
+        }
+        Operator::End | Operator::Delegate { .. }
+            if matches!(
+                state.control_stack.last(),
+                Some(ControlStackFrame::Try { .. })
+            ) =>
+        {
+            let delegate = match *op {
+                Operator::Delegate { relative_depth } => Some(relative_depth),
+                _ => None,
+            };
+            translate_try_end(delegate, builder, state, environ)?;
+        }
+        Operator::End | Operator::Delegate { .. } => {
             let stack = &mut state.stack;
             let control_stack = &mut state.control_stack;
             let frame = control_stack.pop().unwrap();
@@ -2195,11 +2509,24 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
 fn get_heap_addr(
     heap: ir::Heap,
     addr32: ir::Value,
-    offset: u32,
+    offset: u64,
     width: u32,
     addr_ty: Type,
     builder: &mut FunctionBuilder,
 ) -> (ir::Value, i32) {
+    // Only 64-bit memories can have offsets which don't fit in 32 bits. Those
+    // are added to the address up front, trapping if that overflows.
+    let (addr32, offset) = match u32::try_from(offset) {
+        Ok(offset) => (addr32, offset),
+        Err(_) => {
+            let addr = builder.ins().iadd_imm(addr32, offset as i64);
+            let overflowed = builder.ins().icmp(IntCC::UnsignedLessThan, addr, addr32);
+            builder
+                .ins()
+                .trapnz(overflowed, ir::TrapCode::HeapOutOfBounds);
+            (addr, 0)
+        }
+    };
     let offset_guard_size: u64 = builder.func.heaps[heap].offset_guard_size.into();
 
     // How exactly the bounds check is performed here and what it's performed
@@ -2289,7 +2616,7 @@ fn prepare_load<FE: FuncEnvironment + ?Sized>(
     let (base, offset) = get_heap_addr(
         heap,
         addr32,
-        memarg.offset as u32,
+        memarg.offset,
         loaded_bytes,
         environ.pointer_type(),
         builder,
@@ -2341,7 +2668,7 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
     let (base, offset) = get_heap_addr(
         heap,
         addr32,
-        memarg.offset as u32,
+        memarg.offset,
         mem_op_size(opcode, val_ty),
         environ.pointer_type(),
         builder,
@@ -2371,12 +2698,21 @@ fn translate_icmp(cc: IntCC, builder: &mut FunctionBuilder, state: &mut FuncTran
     state.push1(builder.ins().uextend(I32, val));
 }
 
+/// Atomic operations don't support 64-bit memories yet.
+fn ensure_atomic_addr32(linear_mem_addr: Value, builder: &FunctionBuilder) -> WasmResult<()> {
+    if builder.func.dfg.value_type(linear_mem_addr) != I32 {
+        return Err(wasm_unsupported!("atomic operations on 64-bit memories"));
+    }
+    Ok(())
+}
+
 fn fold_atomic_mem_addr(
     linear_mem_addr: Value,
     memarg: &MemArg,
     access_ty: Type,
     builder: &mut FunctionBuilder,
-) -> Value {
+) -> WasmResult<Value> {
+    ensure_atomic_addr32(linear_mem_addr, builder)?;
     let access_ty_bytes = access_ty.bytes();
     let final_lma = if memarg.offset > 0 {
         assert!(builder.func.dfg.value_type(linear_mem_addr) == I32);
@@ -2400,7 +2736,7 @@ fn fold_atomic_mem_addr(
         .ins()
         .icmp_imm(IntCC::Equal, final_lma_misalignment, i64::from(0));
     builder.ins().trapz(f, ir::TrapCode::HeapMisaligned);
-    final_lma
+    Ok(final_lma)
 }
 
 // For an atomic memory operation, emit an alignment check for the linear memory address,
@@ -2413,6 +2749,7 @@ fn finalise_atomic_mem_addr<FE: FuncEnvironment + ?Sized>(
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<Value> {
+    ensure_atomic_addr32(linear_mem_addr, builder)?;
     let access_ty_bytes = access_ty.bytes();
     let final_lma = if memarg.offset > 0 {
         assert!(builder.func.dfg.value_type(linear_mem_addr) == I32);
@@ -2663,12 +3000,30 @@ fn translate_vector_fcmp(
     state.push1(builder.ins().fcmp(cc, bitcast_a, bitcast_b))
 }
 
-fn translate_br_if(
+fn translate_br_if<FE: FuncEnvironment + ?Sized>(
     relative_depth: u32,
     builder: &mut FunctionBuilder,
     state: &mut FuncTranslationState,
-) {
+    environ: &mut FE,
+) -> WasmResult<()> {
     let val = state.pop1();
+    if !state.caught_exceptions(relative_depth).is_empty() {
+        // Free the exceptions of the `catch` clauses the branch leaves on the
+        // way to its destination
+        let branch_block = builder.create_block();
+        canonicalise_then_brnz(builder, val, branch_block, &[]);
+        let next_block = builder.create_block();
+        canonicalise_then_jump(builder, next_block, &[]);
+        builder.seal_block(next_block); // The only predecessor is the current block.
+        builder.seal_block(branch_block); // Ditto.
+
+        builder.switch_to_block(branch_block);
+        translate_caught_exceptions_drop(relative_depth, builder, state, environ)?;
+        let (br_destination, inputs) = translate_br_if_args(relative_depth, state);
+        canonicalise_then_jump(builder, br_destination, inputs);
+        builder.switch_to_block(next_block);
+        return Ok(());
+    }
     let (br_destination, inputs) = translate_br_if_args(relative_depth, state);
     canonicalise_then_brnz(builder, val, br_destination, inputs);
 
@@ -2676,6 +3031,7 @@ fn translate_br_if(
     canonicalise_then_jump(builder, next_block, &[]);
     builder.seal_block(next_block); // The only predecessor is the current block.
     builder.switch_to_block(next_block);
+    Ok(())
 }
 
 fn translate_br_if_args(
@@ -2906,6 +3262,71 @@ fn type_of(operator: &Operator) -> Type {
     }
 }
 
+/// Replace `value`, or each lane of it, with the canonical NaN if it's a NaN
+/// and NaNs are canonicalized.
+///
+/// Cranelift's `enable_nan_canonicalization` only covers arithmetic, so this
+/// is needed after the conversions between `f32` and `f64`, which keep the
+/// payload of NaNs.
+fn canonicalize_nan<FE: FuncEnvironment + ?Sized>(
+    value: Value,
+    environ: &FE,
+    builder: &mut FunctionBuilder,
+) -> Value {
+    if !environ.canonicalize_nans() {
+        return value;
+    }
+
+    let ty = builder.func.dfg.value_type(value);
+    let canonical = match ty.lane_type() {
+        F32 => builder
+            .ins()
+            .f32const(ir::immediates::Ieee32::with_bits(0x7fc0_0000)),
+        F64 => builder
+            .ins()
+            .f64const(ir::immediates::Ieee64::with_bits(0x7ff8_0000_0000_0000)),
+        _ => return value,
+    };
+    let is_nan = builder.ins().fcmp(FloatCC::Unordered, value, value);
+    if ty.is_vector() {
+        let canonical = builder.ins().splat(ty, canonical);
+        // The comparison gives an integer mask, but all the operands of
+        // `bitselect` need to have the same type
+        let is_nan = optionally_bitcast_vector(is_nan, ty, builder);
+        builder.ins().bitselect(is_nan, canonical, value)
+    } else {
+        builder.ins().select(is_nan, canonical, value)
+    }
+}
+
+/// Split each 32-bit lane of `value`, a vector of `bf16`s, into the `f32`s
+/// of its low and high halves: a `bf16` is the top half of an `f32`.
+fn bf16x8_to_f32x4_pairs(value: Value, builder: &mut FunctionBuilder) -> (Value, Value) {
+    let even = builder.ins().ishl_imm(value, 16);
+    let odd = builder.ins().ushr_imm(value, 16);
+    let odd = builder.ins().ishl_imm(odd, 16);
+    (
+        optionally_bitcast_vector(even, F32X4, builder),
+        optionally_bitcast_vector(odd, F32X4, builder),
+    )
+}
+
+/// Multiply the signed bytes of `a` and `b`, seen as `I16X8`s, and add up
+/// each pair of products into a 16-bit lane.
+fn i8x16_pairwise_dot(a: Value, b: Value, builder: &mut FunctionBuilder) -> Value {
+    // The high byte of each lane, and the low one, sign-extended
+    let a_odd = builder.ins().sshr_imm(a, 8);
+    let a_even = builder.ins().ishl_imm(a, 8);
+    let a_even = builder.ins().sshr_imm(a_even, 8);
+    let b_odd = builder.ins().sshr_imm(b, 8);
+    let b_even = builder.ins().ishl_imm(b, 8);
+    let b_even = builder.ins().sshr_imm(b_even, 8);
+
+    let odd = builder.ins().imul(a_odd, b_odd);
+    let even = builder.ins().imul(a_even, b_even);
+    builder.ins().iadd(even, odd)
+}
+
 /// Some SIMD operations only operate on I8X16 in CLIF; this will convert them to that type by
 /// adding a raw_bitcast if necessary.
 fn optionally_bitcast_vector(
@@ -3049,6 +3470,315 @@ pub fn bitcast_arguments(
     }
 }
 
+/// Translate a tail call to a function whose body is in the function being
+/// translated, which passes `args` as the new values of its parameters,
+/// resets its other locals to zero and jumps to its body.
+fn translate_tail_call_jump<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    environ: &mut FE,
+    target: &TailCallTarget,
+    args: &[Value],
+) {
+    let local_base = target.local_base as usize;
+    let local_types =
+        environ.get_local_types()[local_base..local_base + target.num_locals].to_vec();
+    for (i, ty) in local_types.into_iter().enumerate() {
+        let value = match ty {
+            // Vector locals have the default type, I8X16
+            WasmerType::V128 if i < target.num_params => {
+                optionally_bitcast_vector(args[i], I8X16, builder)
+            }
+            _ if i < target.num_params => args[i],
+            WasmerType::I32 => builder.ins().iconst(I32, 0),
+            WasmerType::I64 => builder.ins().iconst(I64, 0),
+            WasmerType::F32 => builder.ins().f32const(ir::immediates::Ieee32::with_bits(0)),
+            WasmerType::F64 => builder.ins().f64const(ir::immediates::Ieee64::with_bits(0)),
+            WasmerType::V128 => {
+                let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+                builder.ins().vconst(I8X16, constant_handle)
+            }
+            WasmerType::ExternRef | WasmerType::FuncRef => {
+                builder.ins().null(environ.reference_type())
+            }
+        };
+        builder.def_var(Variable::from_u32((local_base + i) as u32), value);
+    }
+
+    builder.ins().jump(target.body_block, &[]);
+}
+
+/// Translate the body of a function inlined into its callers instead of a
+/// call to it, with its parameters the arguments on the stack.
+fn translate_inlined_call<FE: FuncEnvironment + ?Sized>(
+    module_translation_state: &ModuleTranslationState,
+    body: &InlinedBody,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let args = state.peekn(body.num_params).to_vec();
+    state.popn(body.num_params);
+
+    // The body doesn't have any control flow, so it ends at the first `end`
+    let mut reader = body.reader()?;
+    loop {
+        match reader.read_operator()? {
+            Operator::End => return Ok(()),
+            Operator::LocalGet { local_index } => state.push1(args[local_index as usize]),
+            op => translate_operator(module_translation_state, &op, builder, state, environ)?,
+        }
+    }
+}
+
+/// Branch to the innermost handler if the call just translated returned with
+/// a pending exception.
+fn translate_pending_exception_check<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    if !state.exceptions {
+        return Ok(());
+    }
+    let exception = environ.translate_load_pending_exception(builder.cursor())?;
+    let handler = state.exception_handler(builder, state.control_stack.len());
+    builder.ins().brnz(exception, handler, &[]);
+
+    let next_block = builder.create_block();
+    builder.ins().jump(next_block, &[]);
+    builder.seal_block(next_block); // The only predecessor is the current block.
+    builder.switch_to_block(next_block);
+    Ok(())
+}
+
+/// Free the exceptions caught by the `catch` clauses a branch to the label
+/// `relative_depth` leaves.
+fn translate_caught_exceptions_drop<FE: FuncEnvironment + ?Sized>(
+    relative_depth: u32,
+    builder: &mut FunctionBuilder,
+    state: &FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    for exception in state.caught_exceptions(relative_depth) {
+        environ.translate_exception_drop(builder.cursor(), exception)?;
+    }
+    Ok(())
+}
+
+/// Translate the end of the body or of the current clause of the `try` on top
+/// of the control stack, when it falls through to the code after the `try`.
+fn translate_try_exit<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let frame = state.control_stack.last_mut().unwrap();
+    if let ControlStackFrame::Try {
+        catch: Some(ref clause),
+        ..
+    } = *frame
+    {
+        environ.translate_exception_drop(builder.cursor(), clause.exception)?;
+    }
+    frame.set_branched_to_exit();
+    let destination = frame.following_code();
+    let num_return_values = frame.num_return_values();
+    canonicalise_then_jump(builder, destination, state.peekn(num_return_values));
+    Ok(())
+}
+
+/// Fill in the cleanup block of the current clause of the `try` on top of the
+/// control stack, if anything in the clause throws: it frees the caught
+/// exception and goes to the outer handler.
+fn translate_catch_cleanup<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let depth = state.control_stack.len() - 1;
+    let (exception, cleanup) = match state.control_stack[depth] {
+        ControlStackFrame::Try {
+            catch: Some(ref mut clause),
+            ..
+        } => match clause.cleanup.take() {
+            Some(cleanup) => (clause.exception, cleanup),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    builder.switch_to_block(cleanup);
+    builder.seal_block(cleanup);
+    environ.translate_exception_drop(builder.cursor(), exception)?;
+    let handler = state.exception_handler(builder, depth);
+    builder.ins().jump(handler, &[]);
+    Ok(())
+}
+
+/// Translate a `catch` clause of the `try` on top of the control stack, or a
+/// `catch_all` clause without `tag_index`, ending its body or its previous
+/// clause.
+fn translate_catch<FE: FuncEnvironment + ?Sized>(
+    tag_index: Option<TagIndex>,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    if state.reachable {
+        translate_try_exit(builder, state, environ)?;
+    }
+    translate_catch_cleanup(builder, state, environ)?;
+
+    let frame = state.control_stack.last_mut().unwrap();
+    frame.truncate_value_stack_to_original_size(&mut state.stack);
+    let (landing, landing_is_used, previous) = match *frame {
+        ControlStackFrame::Try {
+            landing,
+            landing_is_used,
+            ref mut catch,
+            ..
+        } => (landing, landing_is_used, catch.take()),
+        _ => unreachable!(),
+    };
+    let exception = match previous {
+        // Test the exceptions the previous clauses didn't match
+        Some(clause) => {
+            let no_match = clause.no_match.expect("clause after a `catch_all`");
+            builder.switch_to_block(no_match);
+            builder.seal_block(no_match);
+            clause.exception
+        }
+        None if landing_is_used => {
+            // Take the exception, so the clause can throw others
+            builder.switch_to_block(landing);
+            builder.seal_block(landing);
+            let exception = environ.translate_load_pending_exception(builder.cursor())?;
+            let null = builder.ins().iconst(environ.pointer_type(), 0);
+            environ.translate_store_pending_exception(builder.cursor(), null)?;
+            exception
+        }
+        None => {
+            // Nothing in the body throws
+            state.reachable = false;
+            return Ok(());
+        }
+    };
+
+    let no_match = match tag_index {
+        Some(tag_index) => {
+            let matches = environ.translate_exception_is(builder.cursor(), exception, tag_index)?;
+            let catch_block = builder.create_block();
+            let no_match = builder.create_block();
+            builder.ins().brnz(matches, catch_block, &[]);
+            builder.ins().jump(no_match, &[]);
+            builder.seal_block(catch_block); // The only predecessor is the current block.
+            builder.switch_to_block(catch_block);
+
+            let payload =
+                environ.translate_exception_payload(builder.cursor(), exception, tag_index)?;
+            state.pushn(&payload);
+            Some(no_match)
+        }
+        None => None,
+    };
+    if let Some(ControlStackFrame::Try { catch, .. }) = state.control_stack.last_mut() {
+        *catch = Some(CatchClause {
+            exception,
+            no_match,
+            cleanup: None,
+        });
+    }
+    state.reachable = true;
+    Ok(())
+}
+
+/// Translate the `end` of the `try` on top of the control stack, or its
+/// `delegate` to the label `delegate`, ending its body or its last clause.
+fn translate_try_end<FE: FuncEnvironment + ?Sized>(
+    delegate: Option<u32>,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    if state.reachable {
+        translate_try_exit(builder, state, environ)?;
+    }
+    translate_catch_cleanup(builder, state, environ)?;
+
+    let depth = state.control_stack.len() - 1;
+    let (landing, landing_is_used, catch) = match state.control_stack[depth] {
+        ControlStackFrame::Try {
+            landing,
+            landing_is_used,
+            ref mut catch,
+            ..
+        } => (landing, landing_is_used, catch.take()),
+        _ => unreachable!(),
+    };
+    match catch {
+        // The exceptions no clause matches go to the outer handler
+        Some(CatchClause {
+            exception,
+            no_match: Some(no_match),
+            ..
+        }) => {
+            builder.switch_to_block(no_match);
+            builder.seal_block(no_match);
+            environ.translate_store_pending_exception(builder.cursor(), exception)?;
+            let handler = state.exception_handler(builder, depth);
+            builder.ins().jump(handler, &[]);
+        }
+        // So do all the exceptions of a `try` without clauses, or to the
+        // handler of the label it delegates them to
+        None if landing_is_used => {
+            builder.switch_to_block(landing);
+            builder.seal_block(landing);
+            let depth = depth - delegate.unwrap_or(0) as usize;
+            let handler = state.exception_handler(builder, depth);
+            builder.ins().jump(handler, &[]);
+        }
+        _ => {}
+    }
+
+    let frame = state.control_stack.pop().unwrap();
+    frame.truncate_value_stack_to_original_size(&mut state.stack);
+    if frame.exit_is_branched_to() {
+        let next_block = frame.following_code();
+        builder.switch_to_block(next_block);
+        builder.seal_block(next_block);
+        state
+            .stack
+            .extend_from_slice(builder.block_params(next_block));
+        state.reachable = true;
+    } else {
+        state.reachable = false;
+    }
+    Ok(())
+}
+
+/// The tail call targets a `return_call_indirect` of the type `type_index`
+/// can jump to.
+fn indirect_tail_call_targets<FE: FuncEnvironment + ?Sized>(
+    state: &FuncTranslationState,
+    environ: &FE,
+    type_index: u32,
+) -> Vec<FunctionIndex> {
+    if !state.indirect_tail_call_jumps {
+        return Vec::new();
+    }
+    let signature = match environ.get_function_sig(SignatureIndex::from_u32(type_index)) {
+        Some(signature) => signature,
+        None => return Vec::new(),
+    };
+    let mut targets = state
+        .tail_call_targets
+        .keys()
+        .filter(|index| environ.get_function_type(**index) == Some(signature))
+        .copied()
+        .collect::<Vec<_>>();
+    targets.sort();
+    targets
+}
+
 /// A helper to extract all the `Type` listings of each variable in `params`
 /// for only parameters the return true for `is_wasm`, typically paired with
 /// `is_wasm_return` or `is_wasm_parameter`.
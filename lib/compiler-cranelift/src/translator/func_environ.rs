@@ -15,7 +15,7 @@ use cranelift_frontend::FunctionBuilder;
 use wasmer_compiler::wasmparser::{Operator, ValType};
 use wasmer_types::{
     FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex, SignatureIndex,
-    TableIndex, Type as WasmerType, WasmResult,
+    TableIndex, TagIndex, Type as WasmerType, WasmResult,
 };
 
 /// The value of a WebAssembly global variable.
@@ -91,6 +91,19 @@ pub trait FuncEnvironment: TargetEnvironment {
         signature.returns[index].purpose == ir::ArgumentPurpose::Normal
     }
 
+    /// Whether the translator needs to canonicalize the NaNs produced by the
+    /// operations Cranelift's own NaN canonicalization doesn't cover.
+    fn canonicalize_nans(&self) -> bool {
+        false
+    }
+
+    /// Whether the target has fused multiply-add instructions, which the
+    /// relaxed SIMD proposal's `relaxed_madd` and `relaxed_nmadd` can use
+    /// instead of a separate multiply and add.
+    fn has_native_fma(&self) -> bool {
+        false
+    }
+
     /// Should the code be structured to use a single `fallthrough_return` instruction at the end
     /// of the function body, rather than `return` instructions as needed? This is used by VMs
     /// to append custom epilogues.
@@ -176,6 +189,20 @@ pub trait FuncEnvironment: TargetEnvironment {
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst>;
 
+    /// Translate the lookup of the function a `return_call_indirect` calls at
+    /// `pos`, with the same checks as `translate_call_indirect` but without
+    /// the call.
+    ///
+    /// Return the address of the function's code and its `vmctx`.
+    fn translate_indirect_callee(
+        &mut self,
+        pos: FuncCursor,
+        table_index: TableIndex,
+        table: ir::Table,
+        sig_index: SignatureIndex,
+        callee: ir::Value,
+    ) -> WasmResult<(ir::Value, ir::Value)>;
+
     /// Translate a `call` WebAssembly instruction at `pos`.
     ///
     /// Insert instructions at `pos` for a direct call to the function `callee_index`.
@@ -438,6 +465,53 @@ pub trait FuncEnvironment: TargetEnvironment {
         count: ir::Value,
     ) -> WasmResult<ir::Value>;
 
+    /// Load the exception being thrown, a null pointer if there is none.
+    fn translate_load_pending_exception(&mut self, pos: FuncCursor) -> WasmResult<ir::Value>;
+
+    /// Store `exception` as the exception being thrown, or stop throwing
+    /// one if it is a null pointer.
+    fn translate_store_pending_exception(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+    ) -> WasmResult<()>;
+
+    /// Translate the test of whether `exception` was thrown with the tag
+    /// `tag_index`, returning a boolean.
+    fn translate_exception_is(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+        tag_index: TagIndex,
+    ) -> WasmResult<ir::Value>;
+
+    /// Load the values thrown with `exception`, which was thrown with the
+    /// tag `tag_index`.
+    fn translate_exception_payload(
+        &mut self,
+        pos: FuncCursor,
+        exception: ir::Value,
+        tag_index: TagIndex,
+    ) -> WasmResult<Vec<ir::Value>>;
+
+    /// Translate a `throw` WebAssembly instruction, making the exception
+    /// with the tag `tag_index` and `args` the one being thrown.
+    fn translate_throw(
+        &mut self,
+        pos: FuncCursor,
+        tag_index: TagIndex,
+        args: &[ir::Value],
+    ) -> WasmResult<()>;
+
+    /// Translate a `rethrow` WebAssembly instruction, making a copy of the
+    /// caught `exception` the one being thrown.
+    fn translate_rethrow(&mut self, pos: FuncCursor, exception: ir::Value) -> WasmResult<()>;
+
+    /// Free a caught exception, when leaving the `catch` clause it was
+    /// caught by.
+    fn translate_exception_drop(&mut self, pos: FuncCursor, exception: ir::Value)
+        -> WasmResult<()>;
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
@@ -478,6 +552,9 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Push locals for a the params of a function on to the stack.
     fn push_params_on_stack(&mut self, function_index: LocalFunctionIndex);
 
+    /// Get the index of a local function in the module.
+    fn func_index(&self, function_index: LocalFunctionIndex) -> FunctionIndex;
+
     /// Get the type of the local at the given index.
     fn get_local_type(&self, local_index: u32) -> Option<WasmerType>;
 
@@ -489,4 +566,7 @@ pub trait FuncEnvironment: TargetEnvironment {
 
     /// Get the type of a function with the given signature index.
     fn get_function_sig(&self, sig_index: SignatureIndex) -> Option<&FunctionType>;
+
+    /// Get the types of the values thrown with the tag at the given index.
+    fn get_tag_params(&self, tag_index: TagIndex) -> &[WasmerType];
 }
@@ -10,8 +10,11 @@
 //! value and control stacks during the translation of a single function.
 
 use super::func_environ::{FuncEnvironment, GlobalVariable};
+use crate::inlining::InlinedFunctions;
 use crate::{HashMap, Occupied, Vacant};
 use cranelift_codegen::ir::{self, Block, Inst, Value};
+use cranelift_frontend::FunctionBuilder;
+use std::sync::Arc;
 use std::vec::Vec;
 use wasmer_types::{
     FunctionIndex, GlobalIndex, MemoryIndex, SignatureIndex, TableIndex, WasmResult,
@@ -53,7 +56,9 @@ pub enum ElseData {
 ///
 /// Moreover, the `if` frame has the `branch_inst` field that points to the `brz` instruction
 /// separating the `true` and `false` branch. The `loop` frame has a `header` field that references
-/// the `Block` that contains the beginning of the body of the loop.
+/// the `Block` that contains the beginning of the body of the loop. The `try` frame has a `landing`
+/// field that references the `Block` the exceptions thrown in its body go to, and the `catch`
+/// clause being translated, if any.
 #[derive(Debug)]
 pub enum ControlStackFrame {
     If {
@@ -90,6 +95,34 @@ pub enum ControlStackFrame {
         num_return_values: usize,
         original_stack_size: usize,
     },
+    Try {
+        destination: Block,
+        landing: Block,
+        num_param_values: usize,
+        num_return_values: usize,
+        original_stack_size: usize,
+        exit_is_branched_to: bool,
+        /// Can anything in the body throw, so the landing is reachable?
+        landing_is_used: bool,
+        /// The `catch` or `catch_all` clause being translated.
+        ///
+        /// This is `None` while translating the body, and in the clauses
+        /// when the landing isn't reachable.
+        catch: Option<CatchClause>,
+    },
+}
+
+/// The reachable `catch` or `catch_all` clause of a `try` being translated.
+#[derive(Debug)]
+pub struct CatchClause {
+    /// The caught exception, which has to be freed when leaving the clause.
+    pub exception: Value,
+    /// Where the exception goes when it doesn't match the tags of the
+    /// clauses so far, `None` after a `catch_all`.
+    pub no_match: Option<Block>,
+    /// Where the exceptions thrown in the clause go, to free the caught one
+    /// before going to the outer handler, if anything can throw.
+    pub cleanup: Option<Block>,
 }
 
 /// Helper methods for the control stack objects.
@@ -104,6 +137,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_return_values, ..
+            }
+            | Self::Try {
+                num_return_values, ..
             } => num_return_values,
         }
     }
@@ -117,6 +153,9 @@ impl ControlStackFrame {
             }
             | Self::Loop {
                 num_param_values, ..
+            }
+            | Self::Try {
+                num_param_values, ..
             } => num_param_values,
         }
     }
@@ -124,12 +163,15 @@ impl ControlStackFrame {
         match *self {
             Self::If { destination, .. }
             | Self::Block { destination, .. }
-            | Self::Loop { destination, .. } => destination,
+            | Self::Loop { destination, .. }
+            | Self::Try { destination, .. } => destination,
         }
     }
     pub fn br_destination(&self) -> Block {
         match *self {
-            Self::If { destination, .. } | Self::Block { destination, .. } => destination,
+            Self::If { destination, .. }
+            | Self::Block { destination, .. }
+            | Self::Try { destination, .. } => destination,
             Self::Loop { header, .. } => header,
         }
     }
@@ -148,12 +190,16 @@ impl ControlStackFrame {
             | Self::Loop {
                 original_stack_size,
                 ..
+            }
+            | Self::Try {
+                original_stack_size,
+                ..
             } => original_stack_size,
         }
     }
     pub fn is_loop(&self) -> bool {
         match *self {
-            Self::If { .. } | Self::Block { .. } => false,
+            Self::If { .. } | Self::Block { .. } | Self::Try { .. } => false,
             Self::Loop { .. } => true,
         }
     }
@@ -167,6 +213,10 @@ impl ControlStackFrame {
             | Self::Block {
                 exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                exit_is_branched_to,
+                ..
             } => exit_is_branched_to,
             Self::Loop { .. } => false,
         }
@@ -181,6 +231,10 @@ impl ControlStackFrame {
             | Self::Block {
                 ref mut exit_is_branched_to,
                 ..
+            }
+            | Self::Try {
+                ref mut exit_is_branched_to,
+                ..
             } => *exit_is_branched_to = true,
             Self::Loop { .. } => {}
         }
@@ -247,6 +301,47 @@ pub struct FuncTranslationState {
     // `FuncEnvironment::make_direct_func()`.
     // Stores both the function reference and the number of WebAssembly arguments
     functions: HashMap<FunctionIndex, (ir::FuncRef, usize)>,
+
+    /// The functions whose body is in the function being translated, which
+    /// `return_call`s jump to instead of growing the stack.
+    pub(crate) tail_call_targets: HashMap<FunctionIndex, TailCallTarget>,
+
+    /// Whether a `return_call_indirect` can jump to the tail call targets
+    /// too, checking if it calls one of them by the address of its code.
+    pub(crate) indirect_tail_call_jumps: bool,
+
+    /// The first local of the function whose body is being translated, which
+    /// isn't 0 for the functions compiled into another one for tail calls.
+    pub(crate) local_base: u32,
+
+    /// Whether calls can throw exceptions, so they're followed by a check
+    /// for the pending exception.
+    pub(crate) exceptions: bool,
+
+    /// The tiny functions whose body is translated instead of the direct
+    /// calls to them.
+    pub(crate) inlined: Option<Arc<InlinedFunctions>>,
+
+    /// The block returning from the function with the exceptions it doesn't
+    /// catch, if anything jumps to it yet.
+    pub(crate) unwind_block: Option<Block>,
+}
+
+/// A function whose body is in the function being translated, so tail calls
+/// to it can be jumps.
+#[derive(Debug, Clone)]
+pub(crate) struct TailCallTarget {
+    /// The block following the initialization of the function's locals.
+    pub(crate) body_block: Block,
+    /// The function's first local.
+    pub(crate) local_base: u32,
+    /// The number of the function's parameters.
+    pub(crate) num_params: usize,
+    /// The number of the function's locals, including its parameters.
+    pub(crate) num_locals: usize,
+    /// Whether anything jumps to the function's body yet, so it needs to be
+    /// translated.
+    pub(crate) used: bool,
 }
 
 // Public methods that are exposed to non-`cranelift_wasm` API consumers.
@@ -273,6 +368,12 @@ impl FuncTranslationState {
             tables: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
+            tail_call_targets: HashMap::new(),
+            indirect_tail_call_jumps: false,
+            local_base: 0,
+            exceptions: false,
+            inlined: None,
+            unwind_block: None,
         }
     }
 
@@ -285,6 +386,12 @@ impl FuncTranslationState {
         self.tables.clear();
         self.signatures.clear();
         self.functions.clear();
+        self.tail_call_targets.clear();
+        self.indirect_tail_call_jumps = false;
+        self.local_base = 0;
+        self.exceptions = false;
+        self.inlined = None;
+        self.unwind_block = None;
     }
 
     /// Initialize the state for compiling a function with the given signature.
@@ -303,6 +410,32 @@ impl FuncTranslationState {
         );
     }
 
+    /// Initialize the state for translating the body of a function compiled
+    /// into the current one for tail calls, whose locals start at
+    /// `local_base`.
+    ///
+    /// Unlike `initialize`, this keeps the entities already created in the
+    /// function.
+    pub(crate) fn initialize_tail_call_target(
+        &mut self,
+        sig: &ir::Signature,
+        exit_block: Block,
+        local_base: u32,
+    ) {
+        debug_assert!(self.stack.is_empty());
+        debug_assert!(self.control_stack.is_empty());
+        self.reachable = true;
+        self.local_base = local_base;
+        self.push_block(
+            exit_block,
+            0,
+            sig.returns
+                .iter()
+                .filter(|arg| arg.purpose == ir::ArgumentPurpose::Normal)
+                .count(),
+        );
+    }
+
     /// Push a value.
     pub(crate) fn push1(&mut self, val: Value) {
         self.stack.push(val);
@@ -446,6 +579,74 @@ impl FuncTranslationState {
             blocktype,
         });
     }
+
+    /// Push a try on the control stack.
+    pub(crate) fn push_try(
+        &mut self,
+        following_code: Block,
+        landing: Block,
+        num_param_types: usize,
+        num_result_types: usize,
+    ) {
+        debug_assert!(num_param_types <= self.stack.len());
+        self.control_stack.push(ControlStackFrame::Try {
+            destination: following_code,
+            landing,
+            original_stack_size: self.stack.len() - num_param_types,
+            num_param_values: num_param_types,
+            num_return_values: num_result_types,
+            exit_is_branched_to: false,
+            landing_is_used: false,
+            catch: None,
+        });
+    }
+
+    /// The block an exception thrown inside the control stack frames below
+    /// `depth` goes to: the landing of the innermost `try` whose body it is
+    /// thrown in, the cleanup of the innermost `catch` clause, or the unwind
+    /// block if nothing catches it.
+    pub(crate) fn exception_handler(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        depth: usize,
+    ) -> Block {
+        for frame in self.control_stack[..depth].iter_mut().rev() {
+            if let ControlStackFrame::Try {
+                landing,
+                landing_is_used,
+                catch,
+                ..
+            } = frame
+            {
+                return match catch {
+                    Some(clause) => *clause.cleanup.get_or_insert_with(|| builder.create_block()),
+                    None => {
+                        *landing_is_used = true;
+                        *landing
+                    }
+                };
+            }
+        }
+        *self
+            .unwind_block
+            .get_or_insert_with(|| builder.create_block())
+    }
+
+    /// The exceptions caught by the `catch` clauses a branch to the label
+    /// `relative_depth` leaves, which it has to free first.
+    pub(crate) fn caught_exceptions(&self, relative_depth: u32) -> Vec<Value> {
+        let depth = self.control_stack.len() - 1 - relative_depth as usize;
+        self.control_stack[depth..]
+            .iter()
+            .filter_map(|frame| match frame {
+                ControlStackFrame::Try {
+                    catch: Some(clause),
+                    ..
+                } => Some(clause.exception),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 /// Methods for handling entity references.
@@ -9,15 +9,18 @@
 
 use super::code_translator::{bitcast_arguments, translate_operator, wasm_param_types};
 use super::func_environ::{FuncEnvironment, ReturnMode};
-use super::func_state::FuncTranslationState;
-use super::translation_utils::get_vmctx_value_label;
+use super::func_state::{FuncTranslationState, TailCallTarget};
+use super::translation_utils::{get_vmctx_value_label, type_to_irtype};
+use crate::inlining::InlinedFunctions;
 use cranelift_codegen::entity::EntityRef;
 use cranelift_codegen::ir::{self, Block, InstBuilder, ValueLabel};
 use cranelift_codegen::timing;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use std::sync::Arc;
+use std::vec::Vec;
 use wasmer_compiler::wasmparser;
 use wasmer_compiler::{wptype_to_type, FunctionBinaryReader, ModuleTranslationState};
-use wasmer_types::{LocalFunctionIndex, WasmResult};
+use wasmer_types::{FunctionIndex, LocalFunctionIndex, WasmResult};
 
 /// WebAssembly to Cranelift IR function translator.
 ///
@@ -27,6 +30,19 @@ use wasmer_types::{LocalFunctionIndex, WasmResult};
 pub struct FuncTranslator {
     func_ctx: FunctionBuilderContext,
     state: FuncTranslationState,
+    tail_call: bool,
+    indirect_tail_call_jumps: bool,
+    exceptions: bool,
+    inlined: Option<Arc<InlinedFunctions>>,
+}
+
+/// A function compiled into the one being translated, so the tail calls to
+/// it can be jumps (see [`FuncTranslator::translate`]).
+pub struct TailCallee<'r, 'a> {
+    /// The function.
+    pub index: LocalFunctionIndex,
+    /// The function's code, like the one being translated.
+    pub reader: &'r mut dyn FunctionBinaryReader<'a>,
 }
 
 impl FuncTranslator {
@@ -35,9 +51,50 @@ impl FuncTranslator {
         Self {
             func_ctx: FunctionBuilderContext::new(),
             state: FuncTranslationState::new(),
+            tail_call: false,
+            indirect_tail_call_jumps: false,
+            exceptions: false,
+            inlined: None,
         }
     }
 
+    /// Enable the tail call proposal.
+    ///
+    /// Cranelift can't lower tail calls yet, so `return_call`s of a function
+    /// to itself or to the functions compiled into it jump to their body,
+    /// running in constant stack space, and all other tail calls are a call
+    /// followed by a return.
+    pub fn set_tail_call(&mut self, enable: bool) {
+        self.tail_call = enable;
+    }
+
+    /// Let `return_call_indirect`s jump to the functions compiled into the
+    /// one being translated too, when the address of the code they call is
+    /// one of theirs.
+    ///
+    /// This needs the address of their code, which isn't available to
+    /// position independent code or functions compiled on their own.
+    pub fn set_indirect_tail_call_jumps(&mut self, enable: bool) {
+        self.indirect_tail_call_jumps = enable;
+    }
+
+    /// Enable the exception handling proposal.
+    ///
+    /// Cranelift can't unwind yet, so a thrown exception is the store's
+    /// pending exception, which every call is followed by a check for.
+    pub fn set_exceptions(&mut self, enable: bool) {
+        self.exceptions = enable;
+    }
+
+    /// Translate the bodies of `inlined` instead of the direct calls to
+    /// them.
+    ///
+    /// Their operators are translated in the caller, so the traps they raise
+    /// are at the call.
+    pub(crate) fn set_inlined_functions(&mut self, inlined: Option<Arc<InlinedFunctions>>) {
+        self.inlined = inlined;
+    }
+
     /// Translate a binary WebAssembly function.
     ///
     /// The `code` slice contains the binary WebAssembly *function code* as it appears in the code
@@ -56,6 +113,11 @@ impl FuncTranslator {
     /// regarded as WebAssembly local variables. Any signature arguments marked as
     /// `ArgumentPurpose::Normal` are made accessible as WebAssembly local variables.
     ///
+    /// With the tail call proposal, the bodies of the `tail_callees` are
+    /// translated into `func` too, after the function's own body and only if
+    /// something tail calls them, so that tail calls to them are jumps. They
+    /// must return the same types as the function, which the functions it can
+    /// reach through tail calls do. Their locals follow the function's.
     pub fn translate<FE: FuncEnvironment + ?Sized>(
         &mut self,
         module_translation_state: &ModuleTranslationState,
@@ -63,18 +125,28 @@ impl FuncTranslator {
         func: &mut ir::Function,
         environ: &mut FE,
         local_function_index: LocalFunctionIndex,
+        tail_callees: &mut [TailCallee<'_, '_>],
     ) -> WasmResult<()> {
         environ.push_params_on_stack(local_function_index);
-        self.translate_from_reader(module_translation_state, reader, func, environ)
+        let function_index = environ.func_index(local_function_index);
+        self.translate_function(
+            module_translation_state,
+            reader,
+            func,
+            environ,
+            Some(function_index),
+            tail_callees,
+        )
     }
 
-    /// Translate a binary WebAssembly function from a `FunctionBinaryReader`.
-    pub fn translate_from_reader<FE: FuncEnvironment + ?Sized>(
+    fn translate_function<FE: FuncEnvironment + ?Sized>(
         &mut self,
         module_translation_state: &ModuleTranslationState,
         reader: &mut dyn FunctionBinaryReader,
         func: &mut ir::Function,
         environ: &mut FE,
+        function_index: Option<FunctionIndex>,
+        tail_callees: &mut [TailCallee<'_, '_>],
     ) -> WasmResult<()> {
         let _tt = timing::wasm_translate_function();
         tracing::trace!(
@@ -105,8 +177,62 @@ impl FuncTranslator {
         let exit_block = builder.create_block();
         builder.append_block_params_for_function_returns(exit_block);
         self.state.initialize(&builder.func.signature, exit_block);
+        self.state.exceptions = self.exceptions;
+        self.state.inlined = self.inlined.clone();
+
+        let own_num_locals = parse_local_decls(reader, &mut builder, num_params, environ)?;
+
+        // Tail calls re-initialize the locals of the function they call and
+        // jump to its body, so each body needs its own block.
+        let function_index = function_index.filter(|_| self.tail_call);
+        let mut callee_indices = Vec::with_capacity(tail_callees.len());
+        if let Some(function_index) = function_index {
+            self.state.indirect_tail_call_jumps = self.indirect_tail_call_jumps;
+            let mut num_locals = own_num_locals;
+            for callee in tail_callees.iter_mut() {
+                let local_base = num_locals;
+                let num_params = declare_tail_callee_parameters(
+                    &mut builder,
+                    environ,
+                    callee.index,
+                    local_base,
+                )?;
+                num_locals = parse_local_decls(
+                    &mut *callee.reader,
+                    &mut builder,
+                    local_base + num_params,
+                    environ,
+                )?;
+                let callee_index = environ.func_index(callee.index);
+                self.state.tail_call_targets.insert(
+                    callee_index,
+                    TailCallTarget {
+                        body_block: builder.create_block(),
+                        local_base: local_base as u32,
+                        num_params,
+                        num_locals: num_locals - local_base,
+                        used: false,
+                    },
+                );
+                callee_indices.push(callee_index);
+            }
+
+            builder.set_srcloc(cur_srcloc(reader));
+            let body_block = builder.create_block();
+            builder.ins().jump(body_block, &[]);
+            builder.switch_to_block(body_block);
+            self.state.tail_call_targets.insert(
+                function_index,
+                TailCallTarget {
+                    body_block,
+                    local_base: 0,
+                    num_params,
+                    num_locals: own_num_locals,
+                    used: true,
+                },
+            );
+        }
 
-        parse_local_decls(reader, &mut builder, num_params, environ)?;
         parse_function_body(
             module_translation_state,
             reader,
@@ -115,11 +241,76 @@ impl FuncTranslator {
             environ,
         )?;
 
+        // Translate the bodies of the functions the tail calls jump to, which
+        // can make more of them used
+        let mut translated = vec![false; callee_indices.len()];
+        while let Some(i) = (0..callee_indices.len())
+            .find(|i| !translated[*i] && self.state.tail_call_targets[&callee_indices[*i]].used)
+        {
+            translated[i] = true;
+            let target = self.state.tail_call_targets[&callee_indices[i]].clone();
+            builder.switch_to_block(target.body_block);
+            let exit_block = builder.create_block();
+            builder.append_block_params_for_function_returns(exit_block);
+            self.state.initialize_tail_call_target(
+                &builder.func.signature,
+                exit_block,
+                target.local_base,
+            );
+            parse_function_body(
+                module_translation_state,
+                &mut *tail_callees[i].reader,
+                &mut builder,
+                &mut self.state,
+                environ,
+            )?;
+        }
+
+        // All the tail calls which jump to the bodies have been translated
+        for index in function_index.iter().chain(&callee_indices) {
+            let target = &self.state.tail_call_targets[index];
+            if target.used {
+                builder.seal_block(target.body_block);
+            }
+        }
+
+        if let Some(unwind_block) = self.state.unwind_block {
+            translate_unwind_block(&mut builder, unwind_block);
+        }
         builder.finalize();
         Ok(())
     }
 }
 
+/// Fill in the block returning from the function with the exceptions it
+/// doesn't catch. The caller only looks at the pending exception then, so
+/// the function returns zeros.
+fn translate_unwind_block(builder: &mut FunctionBuilder, unwind_block: Block) {
+    builder.switch_to_block(unwind_block);
+    builder.seal_block(unwind_block);
+    let return_types = builder
+        .func
+        .signature
+        .returns
+        .iter()
+        .map(|param| param.value_type)
+        .collect::<Vec<_>>();
+    let return_values = return_types
+        .into_iter()
+        .map(|ty| match ty {
+            ir::types::F32 => builder.ins().f32const(ir::immediates::Ieee32::with_bits(0)),
+            ir::types::F64 => builder.ins().f64const(ir::immediates::Ieee64::with_bits(0)),
+            ty if ty.is_vector() => {
+                let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
+                builder.ins().vconst(ty, constant_handle)
+            }
+            ty if ty.is_ref() => builder.ins().null(ty),
+            ty => builder.ins().iconst(ty, 0),
+        })
+        .collect::<Vec<_>>();
+    builder.ins().return_(&return_values);
+}
+
 /// Declare local variables for the signature parameters that correspond to WebAssembly locals.
 ///
 /// Return the number of local variables declared.
@@ -152,15 +343,36 @@ fn declare_wasm_parameters<FE: FuncEnvironment + ?Sized>(
     next_local
 }
 
+/// Declare local variables for the parameters of a function compiled into
+/// the one being translated for tail calls, starting from `local_base`.
+///
+/// Return the number of parameters.
+fn declare_tail_callee_parameters<FE: FuncEnvironment + ?Sized>(
+    builder: &mut FunctionBuilder,
+    environ: &mut FE,
+    index: LocalFunctionIndex,
+    local_base: usize,
+) -> WasmResult<usize> {
+    debug_assert_eq!(environ.get_local_types().len(), local_base);
+    environ.push_params_on_stack(index);
+    let param_types = environ.get_local_types()[local_base..].to_vec();
+    for (i, ty) in param_types.iter().enumerate() {
+        let ty = type_to_irtype(*ty, environ.target_config())?;
+        builder.declare_var(Variable::new(local_base + i), ty);
+    }
+    Ok(param_types.len())
+}
+
 /// Parse the local variable declarations that precede the function body.
 ///
-/// Declare local variables, starting from `num_params`.
+/// Declare local variables, starting from `num_params`. Return the number of
+/// local variables declared so far, including the parameters.
 fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
     reader: &mut dyn FunctionBinaryReader,
     builder: &mut FunctionBuilder,
     num_params: usize,
     environ: &mut FE,
-) -> WasmResult<()> {
+) -> WasmResult<usize> {
     let mut next_local = num_params;
     let local_count = reader.read_local_count()?;
 
@@ -170,7 +382,7 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
         declare_locals(builder, count, ty, &mut next_local, environ)?;
     }
 
-    Ok(())
+    Ok(next_local)
 }
 
 /// Declare `count` local variables of the same type, starting from `next_local`.
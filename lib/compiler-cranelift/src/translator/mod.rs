@@ -9,7 +9,7 @@ mod unwind;
 
 pub use self::func_environ::{FuncEnvironment, GlobalVariable, ReturnMode, TargetEnvironment};
 pub use self::func_state::FuncTranslationState;
-pub use self::func_translator::FuncTranslator;
+pub use self::func_translator::{FuncTranslator, TailCallee};
 pub use self::translation_utils::{
     get_vmctx_value_label, irlibcall_to_libcall, irreloc_to_relocationkind,
     signature_to_cranelift_ir, type_to_irtype,
@@ -7,15 +7,13 @@ use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::{Linkage, Module};
 use inkwell::targets::FileType;
 use inkwell::DLLStorageClass;
-use rayon::iter::ParallelBridge;
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::{Compiler, FunctionBodyData, ModuleMiddleware, ModuleTranslationState};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     Compilation, CompileError, CompileModuleInfo, CustomSection, CustomSectionProtection, Dwarf,
-    FunctionIndex, LocalFunctionIndex, RelocationTarget, SectionBody, SectionIndex, SignatureIndex,
-    Symbol, SymbolRegistry, Target,
+    FunctionIndex, FunctionLocation, LocalFunctionIndex, RelocationTarget, SectionBody,
+    SectionIndex, SignatureIndex, Symbol, SymbolRegistry, Target,
 };
 
 //use std::sync::Mutex;
@@ -88,77 +86,86 @@ impl LLVMCompiler {
         let target_machine = self.config().target_machine(target);
         let ctx = Context::create();
 
-        // TODO: https:/github.com/rayon-rs/rayon/issues/822
-
-        let merged_bitcode = function_body_inputs.into_iter().par_bridge().map_init(
+        let config = self.config().for_module(function_body_inputs);
+        let threads = &self.config().threads;
+        let functions_bitcode = threads.map_init(
+            &function_body_inputs.iter().collect::<Vec<_>>(),
             || {
                 let target_machine = self.config().target_machine(target);
                 FuncTranslator::new(target_machine)
             },
-            |func_translator, (i, input)| {
-                let module = func_translator.translate_to_module(
-                    &compile_info.module,
-                    module_translation,
-                    &i,
-                    input,
-                    self.config(),
-                    &compile_info.memory_styles,
-                    &compile_info.table_styles,
-                    symbol_registry,
-                )?;
+            |func_translator, (i, input)| -> Result<_, CompileError> {
+                let module = func_translator
+                    .translate_to_module(
+                        &compile_info.module,
+                        module_translation,
+                        i,
+                        input,
+                        &config,
+                        &compile_info.memory_styles,
+                        &compile_info.table_styles,
+                        symbol_registry,
+                    )
+                    .map_err(|error| {
+                        error.in_function(FunctionLocation::new(
+                            &compile_info.module,
+                            *i,
+                            input.module_offset,
+                        ))
+                    })?;
                 Ok(module.write_bitcode_to_memory().as_slice().to_vec())
             },
-        );
+        )?;
 
-        let trampolines_bitcode = compile_info.module.signatures.iter().par_bridge().map_init(
+        let trampolines_bitcode = threads.map_init(
+            &compile_info.module.signatures.iter().collect::<Vec<_>>(),
             || {
                 let target_machine = self.config().target_machine(target);
                 FuncTrampoline::new(target_machine)
             },
-            |func_trampoline, (i, sig)| {
+            |func_trampoline, &(i, sig)| -> Result<_, CompileError> {
                 let name = symbol_registry.symbol_to_name(Symbol::FunctionCallTrampoline(i));
                 let module = func_trampoline.trampoline_to_module(sig, self.config(), &name)?;
                 Ok(module.write_bitcode_to_memory().as_slice().to_vec())
             },
-        );
+        )?;
 
-        let dynamic_trampolines_bitcode =
-            compile_info.module.functions.iter().par_bridge().map_init(
-                || {
-                    let target_machine = self.config().target_machine(target);
-                    (
-                        FuncTrampoline::new(target_machine),
-                        &compile_info.module.signatures,
-                    )
-                },
-                |(func_trampoline, signatures), (i, sig)| {
-                    let sig = &signatures[*sig];
-                    let name = symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(i));
-                    let module =
-                        func_trampoline.dynamic_trampoline_to_module(sig, self.config(), &name)?;
-                    Ok(module.write_bitcode_to_memory().as_slice().to_vec())
-                },
-            );
+        let dynamic_trampolines_bitcode = threads.map_init(
+            &compile_info.module.functions.iter().collect::<Vec<_>>(),
+            || {
+                let target_machine = self.config().target_machine(target);
+                FuncTrampoline::new(target_machine)
+            },
+            |func_trampoline, &(i, sig)| -> Result<_, CompileError> {
+                let sig = &compile_info.module.signatures[*sig];
+                let name = symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(i));
+                let module =
+                    func_trampoline.dynamic_trampoline_to_module(sig, self.config(), &name)?;
+                Ok(module.write_bitcode_to_memory().as_slice().to_vec())
+            },
+        )?;
 
-        let merged_bitcode = merged_bitcode
-            .chain(trampolines_bitcode)
-            .chain(dynamic_trampolines_bitcode)
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_par_iter()
-            .reduce_with(|bc1, bc2| {
-                let ctx = Context::create();
-                let membuf = MemoryBuffer::create_from_memory_range(&bc1, "");
-                let m1 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
-                let membuf = MemoryBuffer::create_from_memory_range(&bc2, "");
-                let m2 = Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap();
-                m1.link_in_module(m2).unwrap();
-                m1.write_bitcode_to_memory().as_slice().to_vec()
-            });
-        let merged_module = if let Some(bc) = merged_bitcode {
-            let membuf = MemoryBuffer::create_from_memory_range(&bc, "");
+        let bitcode = CompileError::collect(
+            functions_bitcode
+                .into_iter()
+                .chain(trampolines_bitcode)
+                .chain(dynamic_trampolines_bitcode),
+        )?;
+        let parse = |bitcode: &[u8]| {
+            let membuf = MemoryBuffer::create_from_memory_range(bitcode, "");
             Module::parse_bitcode_from_buffer(&membuf, &ctx).unwrap()
-        } else {
-            ctx.create_module("")
+        };
+        // The modules are linked in order, so the object file doesn't depend
+        // on the threads they were compiled on
+        let merged_module = match bitcode.split_first() {
+            Some((first, rest)) => {
+                let merged_module = parse(first);
+                for bitcode in rest {
+                    merged_module.link_in_module(parse(bitcode)).unwrap();
+                }
+                merged_module
+            }
+            None => ctx.create_module(""),
         };
 
         let i8_ty = ctx.i8_type();
@@ -199,6 +206,18 @@ impl Compiler for LLVMCompiler {
         "llvm"
     }
 
+    fn deterministic_id(&self) -> String {
+        self.config.settings_id()
+    }
+
+    fn is_pic(&self) -> bool {
+        self.config.is_pic
+    }
+
+    fn text_section_size(&self) -> Option<u64> {
+        self.config.text_section_size
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
@@ -234,6 +253,27 @@ impl Compiler for LLVMCompiler {
         module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError> {
+        if compile_info.module.memories.values().any(|m| m.memory64) {
+            return Err(CompileError::UnsupportedFeature(
+                "memory64 in the LLVM compiler".to_string(),
+            ));
+        }
+        if compile_info.features.tail_call {
+            return Err(CompileError::UnsupportedFeature(
+                "tail calls in the LLVM compiler".to_string(),
+            ));
+        }
+        if compile_info.features.exceptions {
+            return Err(CompileError::UnsupportedFeature(
+                "exceptions in the LLVM compiler".to_string(),
+            ));
+        }
+        if compile_info.features.relaxed_simd {
+            return Err(CompileError::UnsupportedFeature(
+                "relaxed SIMD in the LLVM compiler".to_string(),
+            ));
+        }
+
         //let data = Arc::new(Mutex::new(0));
         let memory_styles = &compile_info.memory_styles;
         let table_styles = &compile_info.table_styles;
@@ -245,31 +285,39 @@ impl Compiler for LLVMCompiler {
         let mut module_custom_sections = PrimaryMap::new();
         let mut frame_section_bytes = vec![];
         let mut frame_section_relocations = vec![];
-        let functions = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .par_iter()
-            .map_init(
-                || {
-                    let target_machine = self.config().target_machine(target);
-                    FuncTranslator::new(target_machine)
-                },
-                |func_translator, (i, input)| {
-                    // TODO: remove (to serialize)
-                    //let _data = data.lock().unwrap();
-                    func_translator.translate(
+        let config = self.config().for_module(&function_body_inputs);
+        let threads = &self.config().threads;
+        // Each function gets an LLVM module of its own, so its code doesn't
+        // depend on the functions compiled before it on the same thread.
+        // LLVM is never given command-line options either, so it keeps its
+        // fixed default `-rng-seed` and nothing time-based is enabled.
+        let functions = threads.map_init(
+            &function_body_inputs.iter().collect::<Vec<_>>(),
+            || {
+                let target_machine = self.config().target_machine(target);
+                FuncTranslator::new(target_machine)
+            },
+            |func_translator, (i, input)| {
+                // TODO: remove (to serialize)
+                //let _data = data.lock().unwrap();
+                func_translator
+                    .translate(
                         module,
                         module_translation,
                         i,
                         input,
-                        self.config(),
+                        &config,
                         memory_styles,
                         table_styles,
                         &ShortNames {},
+                        target,
                     )
-                },
-            )
-            .collect::<Result<Vec<_>, CompileError>>()?
+                    .map_err(|error| {
+                        error.in_function(FunctionLocation::new(module, *i, input.module_offset))
+                    })
+            },
+        )?;
+        let functions = CompileError::collect(functions)?
             .into_iter()
             .map(|mut compiled_function| {
                 let first_section = module_custom_sections.len() as u32;
@@ -314,6 +362,11 @@ impl Compiler for LLVMCompiler {
             })
             .collect::<PrimaryMap<LocalFunctionIndex, _>>();
 
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &self.config().dump {
+            dump.write_index(module, self.name(), function_body_inputs.keys(), Some("ll"))?;
+        }
+
         let dwarf = if !frame_section_bytes.is_empty() {
             let dwarf = Some(Dwarf::new(SectionIndex::from_u32(
                 module_custom_sections.len() as u32,
@@ -331,27 +384,21 @@ impl Compiler for LLVMCompiler {
             None
         };
 
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .par_iter()
+        let function_call_trampolines = threads
             .map_init(
+                &module.signatures.values().collect::<Vec<_>>(),
                 || {
                     let target_machine = self.config().target_machine(target);
                     FuncTrampoline::new(target_machine)
                 },
                 |func_trampoline, sig| func_trampoline.trampoline(sig, self.config(), ""),
-            )
-            .collect::<Vec<_>>()
+            )?
             .into_iter()
             .collect::<Result<PrimaryMap<_, _>, CompileError>>()?;
 
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .par_iter()
+        let dynamic_function_trampolines = threads
             .map_init(
+                &module.imported_function_types().collect::<Vec<_>>(),
                 || {
                     let target_machine = self.config().target_machine(target);
                     FuncTrampoline::new(target_machine)
@@ -359,7 +406,8 @@ impl Compiler for LLVMCompiler {
                 |func_trampoline, func_type| {
                     func_trampoline.dynamic_trampoline(func_type, self.config(), "")
                 },
-            )
+            )?
+            .into_iter()
             .collect::<Result<Vec<_>, CompileError>>()?
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
@@ -1,15 +1,23 @@
 use crate::compiler::LLVMCompiler;
+use inkwell::passes::{PassManager, PassManagerSubType};
 use inkwell::targets::{
     CodeModel, InitializationConfig, RelocMode, Target as InkwellTarget, TargetMachine,
     TargetTriple,
 };
-pub use inkwell::OptimizationLevel as LLVMOptLevel;
+use inkwell::OptimizationLevel;
 use itertools::Itertools;
-use std::fmt::Debug;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
 use std::sync::Arc;
 use target_lexicon::Architecture;
-use wasmer_compiler::{Compiler, CompilerConfig, Engine, EngineBuilder, ModuleMiddleware};
-use wasmer_types::{FunctionType, LocalFunctionIndex, Target, Triple};
+#[cfg(feature = "dump")]
+use wasmer_compiler::CompilerDump;
+use wasmer_compiler::{
+    CompileThreads, Compiler, CompilerConfig, Engine, EngineBuilder, FunctionBodyData,
+    ModuleMiddleware,
+};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{FeatureSupport, Features, FunctionType, LocalFunctionIndex, Target, Triple};
 
 /// The InkWell ModuleInfo type
 pub type InkwellModule<'ctx> = inkwell::module::Module<'ctx>;
@@ -37,13 +45,77 @@ pub trait LLVMCallbacks: Debug + Send + Sync {
     fn obj_memory_buffer(&self, function: &CompiledKind, memory_buffer: &InkwellMemoryBuffer);
 }
 
+/// How much the LLVM backend optimizes the code it generates, trading
+/// compile time for faster code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LLVMOptLevel {
+    /// No optimizations, for the fastest compile times.
+    O0,
+    /// A few cheap optimizations, like promoting locals to registers and
+    /// simplifying the control flow.
+    O1,
+    /// All of the optimizations, with the default amount of effort spent
+    /// generating machine code.
+    O2,
+    /// All of the optimizations, with as much effort as possible spent
+    /// generating machine code.
+    #[default]
+    O3,
+    /// Like `O2`, but without the loop transformations which mostly make
+    /// code bigger (loop unswitching and vectorization).
+    Os,
+}
+
+impl LLVMOptLevel {
+    /// How much effort LLVM spends generating machine code.
+    pub(crate) fn codegen_level(self) -> OptimizationLevel {
+        match self {
+            Self::O0 => OptimizationLevel::None,
+            Self::O1 => OptimizationLevel::Less,
+            Self::O2 | Self::Os => OptimizationLevel::Default,
+            Self::O3 => OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+impl From<OptimizationLevel> for LLVMOptLevel {
+    fn from(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::None => Self::O0,
+            OptimizationLevel::Less => Self::O1,
+            OptimizationLevel::Default => Self::O2,
+            OptimizationLevel::Aggressive => Self::O3,
+        }
+    }
+}
+
+impl fmt::Display for LLVMOptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::O0 => "O0",
+            Self::O1 => "O1",
+            Self::O2 => "O2",
+            Self::O3 => "O3",
+            Self::Os => "Os",
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LLVM {
     pub(crate) enable_nan_canonicalization: bool,
     pub(crate) enable_verifier: bool,
     pub(crate) opt_level: LLVMOptLevel,
-    is_pic: bool,
+    enable_vectorization: bool,
+    enable_licm: bool,
+    pub(crate) is_pic: bool,
+    pub(crate) text_section_size: Option<u64>,
+    code_size_limit: Option<u64>,
     pub(crate) callbacks: Option<Arc<dyn LLVMCallbacks>>,
+    #[cfg(feature = "dump")]
+    pub(crate) dump: Option<CompilerDump>,
+    pub(crate) threads: CompileThreads,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -55,19 +127,169 @@ impl LLVM {
         Self {
             enable_nan_canonicalization: false,
             enable_verifier: false,
-            opt_level: LLVMOptLevel::Aggressive,
+            opt_level: LLVMOptLevel::O3,
+            enable_vectorization: true,
+            enable_licm: true,
             is_pic: false,
+            text_section_size: None,
+            code_size_limit: None,
             callbacks: None,
+            #[cfg(feature = "dump")]
+            dump: None,
+            threads: CompileThreads::default(),
             middlewares: vec![],
         }
     }
 
-    /// The optimization levels when optimizing the IR.
-    pub fn opt_level(&mut self, opt_level: LLVMOptLevel) -> &mut Self {
-        self.opt_level = opt_level;
+    /// The optimization level, which decides both the optimizations run on
+    /// the IR and the effort spent generating machine code (`O3` by default).
+    pub fn opt_level(&mut self, opt_level: impl Into<LLVMOptLevel>) -> &mut Self {
+        self.opt_level = opt_level.into();
         self
     }
 
+    /// Whether to vectorize loops and straight-line code, at `O2` and above
+    /// (enabled by default).
+    ///
+    /// Vectorization is one of the most expensive passes, and mostly pays
+    /// off for numeric code.
+    pub fn vectorize(&mut self, enable: bool) -> &mut Self {
+        self.enable_vectorization = enable;
+        self
+    }
+
+    /// Whether to hoist loop-invariant code out of loops, at `O2` and above
+    /// (enabled by default).
+    pub fn licm(&mut self, enable: bool) -> &mut Self {
+        self.enable_licm = enable;
+        self
+    }
+
+    /// Optimize modules whose native code would otherwise be estimated to
+    /// take more than `max_size` bytes like at `Os`, without the loop
+    /// transformations which make code bigger.
+    ///
+    /// Functions are compiled one at a time, so LLVM never inlines them into
+    /// each other, and these transformations are what grows the code
+    /// instead. See [`CompilerConfig::set_code_size_limit`].
+    pub fn code_size_limit(&mut self, max_size: Option<u64>) -> &mut Self {
+        self.code_size_limit = max_size;
+        self
+    }
+
+    /// The configuration to compile the functions of a module with: this
+    /// one, or one optimizing for size if the module's native code is
+    /// estimated to take more than the code size limit.
+    pub(crate) fn for_module(
+        &self,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Cow<'_, Self> {
+        /// A rough estimate of the bytes of native code LLVM generates for
+        /// each byte of WebAssembly code.
+        const NATIVE_BYTES_PER_WASM_BYTE: u64 = 4;
+
+        let max_size = match self.code_size_limit {
+            Some(max_size) if matches!(self.opt_level, LLVMOptLevel::O2 | LLVMOptLevel::O3) => {
+                max_size
+            }
+            _ => return Cow::Borrowed(self),
+        };
+        let size = bodies
+            .values()
+            .map(|body| body.data.len() as u64)
+            .sum::<u64>()
+            .saturating_mul(NATIVE_BYTES_PER_WASM_BYTE);
+        if size <= max_size {
+            return Cow::Borrowed(self);
+        }
+
+        let mut config = self.clone();
+        config.opt_level = LLVMOptLevel::Os;
+        Cow::Owned(config)
+    }
+
+    /// Identifies the settings which change the generated code, so
+    /// artifacts compiled with different settings can be told apart.
+    pub(crate) fn settings_id(&self) -> String {
+        let mut id = format!("llvm-{}", self.opt_level);
+        if !self.enable_vectorization {
+            id.push_str("-novectorize");
+        }
+        if !self.enable_licm {
+            id.push_str("-nolicm");
+        }
+        if let Some(limit) = self.code_size_limit {
+            id.push_str(&format!("-code-size-limit-{}", limit));
+        }
+        if self.is_pic {
+            id.push_str("-pic");
+        }
+        if self.enable_nan_canonicalization {
+            id.push_str("-canonicalize-nans");
+        }
+        id
+    }
+
+    /// Add the IR optimizations for the optimization level to `pass_manager`.
+    pub(crate) fn add_optimization_passes<T: PassManagerSubType>(
+        &self,
+        pass_manager: &PassManager<T>,
+    ) {
+        let full = match self.opt_level {
+            LLVMOptLevel::O0 => return,
+            LLVMOptLevel::O1 => {
+                pass_manager.add_type_based_alias_analysis_pass();
+                pass_manager.add_sccp_pass();
+                pass_manager.add_scalar_repl_aggregates_pass();
+                pass_manager.add_instruction_combining_pass();
+                pass_manager.add_cfg_simplification_pass();
+                pass_manager.add_early_cse_pass();
+                return;
+            }
+            LLVMOptLevel::O2 | LLVMOptLevel::O3 => true,
+            LLVMOptLevel::Os => false,
+        };
+        let vectorize = full && self.enable_vectorization;
+
+        pass_manager.add_type_based_alias_analysis_pass();
+        pass_manager.add_sccp_pass();
+        pass_manager.add_prune_eh_pass();
+        pass_manager.add_dead_arg_elimination_pass();
+        pass_manager.add_lower_expect_intrinsic_pass();
+        pass_manager.add_scalar_repl_aggregates_pass();
+        pass_manager.add_instruction_combining_pass();
+        pass_manager.add_jump_threading_pass();
+        pass_manager.add_correlated_value_propagation_pass();
+        pass_manager.add_cfg_simplification_pass();
+        pass_manager.add_reassociate_pass();
+        pass_manager.add_loop_rotate_pass();
+        if full {
+            pass_manager.add_loop_unswitch_pass();
+        }
+        pass_manager.add_ind_var_simplify_pass();
+        if self.enable_licm {
+            pass_manager.add_licm_pass();
+        }
+        if vectorize {
+            pass_manager.add_loop_vectorize_pass();
+        }
+        pass_manager.add_instruction_combining_pass();
+        pass_manager.add_sccp_pass();
+        pass_manager.add_reassociate_pass();
+        pass_manager.add_cfg_simplification_pass();
+        pass_manager.add_gvn_pass();
+        pass_manager.add_memcpy_optimize_pass();
+        pass_manager.add_dead_store_elimination_pass();
+        pass_manager.add_bit_tracking_dce_pass();
+        pass_manager.add_instruction_combining_pass();
+        pass_manager.add_reassociate_pass();
+        pass_manager.add_cfg_simplification_pass();
+        if vectorize {
+            pass_manager.add_slp_vectorize_pass();
+        }
+        pass_manager.add_early_cse_pass();
+    }
+
     /// Callbacks that will triggered in the different compilation
     /// phases in LLVM.
     pub fn callbacks(&mut self, callbacks: Option<Arc<dyn LLVMCallbacks>>) -> &mut Self {
@@ -203,7 +425,7 @@ impl LLVM {
                     Architecture::Riscv64(_) => "+m,+a,+c,+d,+f",
                     _ => &llvm_cpu_features,
                 },
-                self.opt_level,
+                self.opt_level.codegen_level(),
                 self.reloc_mode(),
                 match triple.architecture {
                     Architecture::Riscv64(_) => CodeModel::Medium,
@@ -249,8 +471,8 @@ impl LLVM {
 impl CompilerConfig for LLVM {
     /// Emit code suitable for dlopen.
     fn enable_pic(&mut self) {
-        // TODO: although we can emit PIC, the object file parser does not yet
-        // support all the relocations.
+        // The object file parser understands the relocations PIC uses on
+        // x86_64 and aarch64 ELF, but not those of the other targets yet.
         self.is_pic = true;
     }
 
@@ -263,6 +485,23 @@ impl CompilerConfig for LLVM {
         self.enable_nan_canonicalization = enable;
     }
 
+    #[cfg(feature = "dump")]
+    fn enable_dump(&mut self, dump: CompilerDump) {
+        self.dump = Some(dump);
+    }
+
+    fn set_compile_threads(&mut self, threads: CompileThreads) {
+        self.threads = threads;
+    }
+
+    fn set_text_section_size(&mut self, max_size: Option<u64>) {
+        self.text_section_size = max_size;
+    }
+
+    fn set_code_size_limit(&mut self, max_size: Option<u64>) {
+        self.code_size_limit = max_size;
+    }
+
     /// Transform it into the compiler.
     fn compiler(self: Box<Self>) -> Box<dyn Compiler> {
         Box::new(LLVMCompiler::new(*self))
@@ -274,6 +513,25 @@ impl CompilerConfig for LLVM {
     }
 }
 
+impl FeatureSupport for LLVM {
+    fn supported_features_for_target(&self, _target: &Target) -> Features {
+        Features {
+            threads: true,
+            reference_types: true,
+            simd: true,
+            bulk_memory: true,
+            multi_value: true,
+            tail_call: false,
+            module_linking: false,
+            multi_memory: true,
+            memory64: false,
+            exceptions: false,
+            relaxed_simd: false,
+            extended_const: true,
+        }
+    }
+}
+
 impl Default for LLVM {
     fn default() -> LLVM {
         Self::new()
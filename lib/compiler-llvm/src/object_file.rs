@@ -147,8 +147,9 @@ where
         })
     };
 
-    // From elf section index to list of Relocations. Although we use a Vec,
-    // the order of relocations is not important.
+    // From elf section index to list of Relocations. The relocations are
+    // sorted by offset before being handed out, so the artifact doesn't
+    // depend on the order the object file lists them in.
     let mut relocations: HashMap<object::read::SectionIndex, Vec<Relocation>> = HashMap::new();
 
     // Each iteration of this loop pulls a section and the relocations
@@ -216,6 +217,55 @@ where
                     object::RelocationKind::Elf(object::elf::R_AARCH64_MOVW_UABS_G3),
                     0,
                 ) => RelocationKind::Arm64Movw3,
+                (object::Architecture::X86_64, object::RelocationKind::Relative, 32) => {
+                    RelocationKind::X86PCRel4
+                }
+                (object::Architecture::X86_64, object::RelocationKind::PltRelative, 32) => {
+                    RelocationKind::X86CallPLTRel4
+                }
+                (object::Architecture::X86_64, object::RelocationKind::GotRelative, 32)
+                | (
+                    object::Architecture::X86_64,
+                    object::RelocationKind::Elf(
+                        object::elf::R_X86_64_GOTPCRELX | object::elf::R_X86_64_REX_GOTPCRELX,
+                    ),
+                    _,
+                ) => RelocationKind::X86GOTPCRel4,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_ADR_PREL_PG_HI21),
+                    0,
+                ) => RelocationKind::Arm64AdrPrelPgHi21,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_ADD_ABS_LO12_NC),
+                    0,
+                ) => RelocationKind::Arm64AddAbsLo12Nc,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_LDST32_ABS_LO12_NC),
+                    0,
+                ) => RelocationKind::Arm64Ldst32AbsLo12Nc,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_LDST64_ABS_LO12_NC),
+                    0,
+                ) => RelocationKind::Arm64Ldst64AbsLo12Nc,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_LDST128_ABS_LO12_NC),
+                    0,
+                ) => RelocationKind::Arm64Ldst128AbsLo12Nc,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_ADR_GOT_PAGE),
+                    0,
+                ) => RelocationKind::Arm64AdrGotPage21,
+                (
+                    object::Architecture::Aarch64,
+                    object::RelocationKind::Elf(object::elf::R_AARCH64_LD64_GOT_LO12_NC),
+                    0,
+                ) => RelocationKind::Arm64Ld64GotLo12Nc,
                 (
                     object::Architecture::Riscv64,
                     object::RelocationKind::Elf(object::elf::R_RISCV_CALL_PLT),
@@ -336,6 +386,10 @@ where
         }
     }
 
+    for section_relocations in relocations.values_mut() {
+        section_relocations.sort_by_key(|relocation| relocation.offset);
+    }
+
     let eh_frame_section_indices = eh_frame_section_indices
         .iter()
         .map(|index| {
@@ -25,6 +25,8 @@ use crate::abi::{get_abi, Abi};
 use crate::config::{CompiledKind, LLVM};
 use crate::object_file::{load_object_file, CompiledFunction};
 use std::convert::TryFrom;
+#[cfg(feature = "dump")]
+use wasmer_compiler::disassemble;
 use wasmer_compiler::wasmparser::{MemArg, Operator};
 use wasmer_compiler::{
     from_binaryreadererror_wasmerror, wptype_to_type, FunctionBinaryReader, FunctionBodyData,
@@ -32,8 +34,9 @@ use wasmer_compiler::{
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
-    CompileError, FunctionIndex, FunctionType, GlobalIndex, LocalFunctionIndex, MemoryIndex,
-    ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex, Type,
+    CompileError, FunctionIndex, FunctionLocation, FunctionType, GlobalIndex, LocalFunctionIndex,
+    MemoryIndex, ModuleInfo, RelocationTarget, SignatureIndex, Symbol, SymbolRegistry, TableIndex,
+    Target, Type,
 };
 use wasmer_vm::{MemoryStyle, TableStyle, VMOffsets};
 
@@ -215,8 +218,18 @@ impl FuncTranslator {
 
         while fcg.state.has_control_frames() {
             let pos = reader.current_position() as u32;
-            let op = reader.read_operator()?;
-            fcg.translate_operator(op, pos)?;
+            let offset = reader.original_position();
+            reader
+                .read_operator()
+                .map_err(CompileError::from)
+                .and_then(|op| fcg.translate_operator(op, pos))
+                .map_err(|error| {
+                    error.in_function(FunctionLocation::new(
+                        wasm_module,
+                        *local_func_index,
+                        offset,
+                    ))
+                })?;
         }
 
         fcg.finalize(wasm_fn_type)?;
@@ -231,35 +244,7 @@ impl FuncTranslator {
             pass_manager.add_verifier_pass();
         }
 
-        pass_manager.add_type_based_alias_analysis_pass();
-        pass_manager.add_sccp_pass();
-        pass_manager.add_prune_eh_pass();
-        pass_manager.add_dead_arg_elimination_pass();
-        pass_manager.add_lower_expect_intrinsic_pass();
-        pass_manager.add_scalar_repl_aggregates_pass();
-        pass_manager.add_instruction_combining_pass();
-        pass_manager.add_jump_threading_pass();
-        pass_manager.add_correlated_value_propagation_pass();
-        pass_manager.add_cfg_simplification_pass();
-        pass_manager.add_reassociate_pass();
-        pass_manager.add_loop_rotate_pass();
-        pass_manager.add_loop_unswitch_pass();
-        pass_manager.add_ind_var_simplify_pass();
-        pass_manager.add_licm_pass();
-        pass_manager.add_loop_vectorize_pass();
-        pass_manager.add_instruction_combining_pass();
-        pass_manager.add_sccp_pass();
-        pass_manager.add_reassociate_pass();
-        pass_manager.add_cfg_simplification_pass();
-        pass_manager.add_gvn_pass();
-        pass_manager.add_memcpy_optimize_pass();
-        pass_manager.add_dead_store_elimination_pass();
-        pass_manager.add_bit_tracking_dce_pass();
-        pass_manager.add_instruction_combining_pass();
-        pass_manager.add_reassociate_pass();
-        pass_manager.add_cfg_simplification_pass();
-        pass_manager.add_slp_vectorize_pass();
-        pass_manager.add_early_cse_pass();
+        config.add_optimization_passes(&pass_manager);
 
         pass_manager.run_on(&module);
 
@@ -281,6 +266,8 @@ impl FuncTranslator {
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &PrimaryMap<TableIndex, TableStyle>,
         symbol_registry: &dyn SymbolRegistry,
+        // Only used to disassemble the functions being dumped
+        #[cfg_attr(not(feature = "dump"), allow(unused_variables))] target: &Target,
     ) -> Result<CompiledFunction, CompileError> {
         let module = self.translate_to_module(
             wasm_module,
@@ -303,7 +290,7 @@ impl FuncTranslator {
         }
 
         let mem_buf_slice = memory_buffer.as_slice();
-        load_object_file(
+        let compiled = load_object_file(
             mem_buf_slice,
             FUNCTION_SECTION,
             RelocationTarget::LocalFunc(*local_func_index),
@@ -318,7 +305,24 @@ impl FuncTranslator {
                     },
                 )
             },
-        )
+        )?;
+
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &config.dump {
+            if dump.should_dump(wasm_module, *local_func_index) {
+                let ir = module.print_to_string().to_string();
+                let code = &compiled.compiled_function.body.body;
+                dump.dump_function(
+                    wasm_module,
+                    *local_func_index,
+                    function_body,
+                    Some(("ll", &ir)),
+                    &disassemble(target, code),
+                )?;
+            }
+        }
+
+        Ok(compiled)
     }
 }
 
@@ -11062,17 +11066,16 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 self.state.push1(value);
             }
             Operator::TableGet { table } => {
-                let table_index = self.intrinsics.i32_ty.const_int(table.into(), false);
                 let elem = self.state.pop1()?;
-                let table_get = if self
+                let (table_get, table_index) = if let Some(local_table_index) = self
                     .wasm_module
                     .local_table_index(TableIndex::from_u32(table))
-                    .is_some()
                 {
-                    self.intrinsics.table_get
+                    (self.intrinsics.table_get, local_table_index.as_u32())
                 } else {
-                    self.intrinsics.imported_table_get
+                    (self.intrinsics.imported_table_get, table)
                 };
+                let table_index = self.intrinsics.i32_ty.const_int(table_index as u64, false);
                 let value = self
                     .builder
                     .build_call(
@@ -11098,20 +11101,19 @@ impl<'ctx, 'a> LLVMFunctionCodeGenerator<'ctx, 'a> {
                 self.state.push1(value);
             }
             Operator::TableSet { table } => {
-                let table_index = self.intrinsics.i32_ty.const_int(table.into(), false);
                 let (elem, value) = self.state.pop2()?;
                 let value = self
                     .builder
                     .build_bitcast(value, self.intrinsics.anyref_ty, "");
-                let table_set = if self
+                let (table_set, table_index) = if let Some(local_table_index) = self
                     .wasm_module
                     .local_table_index(TableIndex::from_u32(table))
-                    .is_some()
                 {
-                    self.intrinsics.table_set
+                    (self.intrinsics.table_set, local_table_index.as_u32())
                 } else {
-                    self.intrinsics.imported_table_set
+                    (self.intrinsics.imported_table_set, table)
                 };
+                let table_index = self.intrinsics.i32_ty.const_int(table_index as u64, false);
                 self.builder.build_call(
                     table_set,
                     &[
@@ -3,7 +3,7 @@ use crate::codegen_error;
 #[cfg(feature = "unwind")]
 use crate::dwarf::WriterRelocate;
 use crate::location::{Location, Reg};
-use crate::machine::{Label, Machine, MachineStackOffset, NATIVE_PAGE_SIZE};
+use crate::machine::{Label, Machine, MachineStackOffset};
 use crate::unwind::UnwindFrame;
 use crate::{common_decl::*, config::Singlepass};
 #[cfg(feature = "unwind")]
@@ -129,12 +129,13 @@ impl FloatValue {
         }
     }
 
+    // Converting keeps the payload of a NaN, so the result needs to be
+    // canonicalized even if the operand didn't
     fn promote(self, depth: usize) -> Result<FloatValue, CompileError> {
         let ret = FloatValue {
             canonicalization: match self.canonicalization {
-                Some(CanonicalizeType::F32) => Some(CanonicalizeType::F64),
+                Some(CanonicalizeType::F32) | None => Some(CanonicalizeType::F64),
                 Some(CanonicalizeType::F64) => codegen_error!("cannot promote F64"),
-                None => None,
             },
             depth,
         };
@@ -144,9 +145,8 @@ impl FloatValue {
     fn demote(self, depth: usize) -> Result<FloatValue, CompileError> {
         let ret = FloatValue {
             canonicalization: match self.canonicalization {
-                Some(CanonicalizeType::F64) => Some(CanonicalizeType::F32),
+                Some(CanonicalizeType::F64) | None => Some(CanonicalizeType::F32),
                 Some(CanonicalizeType::F32) => codegen_error!("cannot demote F32"),
-                None => None,
             },
             depth,
         };
@@ -554,12 +554,14 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         // Stack probe.
         //
         // `rep stosq` writes data from low address to high address and may skip the stack guard page.
-        // so here we probe it explicitly when needed.
-        for i in (sig.params().len()..n)
-            .step_by(NATIVE_PAGE_SIZE / 8)
-            .skip(1)
-        {
-            self.machine.zero_location(Size::S64, locations[i])?;
+        // so here we probe it explicitly when needed, from the top down.
+        if let Some(interval) = self.config.stack_probes.interval() {
+            for i in (sig.params().len()..n)
+                .step_by(interval as usize / 8)
+                .skip(1)
+            {
+                self.machine.zero_location(Size::S64, locations[i])?;
+            }
         }
 
         self.machine.adjust_stack(static_area_size as _)?;
@@ -754,6 +756,21 @@ impl<'a, M: Machine> FuncGen<'a, M> {
         );
     }
 
+    /// The builtin to call for an operation on `table_index`, and the index
+    /// to pass it: the builtins for locally-defined tables take a
+    /// `LocalTableIndex`, the ones for imported tables a `TableIndex`.
+    fn table_builtin(
+        &self,
+        table_index: TableIndex,
+        local: VMBuiltinFunctionIndex,
+        imported: VMBuiltinFunctionIndex,
+    ) -> (u32, VMBuiltinFunctionIndex) {
+        match self.module.local_table_index(table_index) {
+            Some(local_table_index) => (local_table_index.index() as u32, local),
+            None => (table_index.index() as u32, imported),
+        }
+    }
+
     /// Emits a Native ABI call sequence.
     ///
     /// The caller MUST NOT hold any temporary registers allocated by `acquire_temp_gpr` when calling
@@ -6089,7 +6106,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 self.value_stack.push(ret);
             }
             Operator::TableSet { table: index } => {
-                let table_index = TableIndex::new(index as _);
+                let (table_arg, builtin) = self.table_builtin(
+                    TableIndex::new(index as _),
+                    VMBuiltinFunctionIndex::get_table_set_index(),
+                    VMBuiltinFunctionIndex::get_imported_table_set_index(),
+                );
                 let value = self.value_stack.pop().unwrap();
                 let index = self.value_stack.pop().unwrap();
                 // double check this does what I think it does
@@ -6099,13 +6120,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_table_index(table_index).is_some() {
-                                VMBuiltinFunctionIndex::get_table_set_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_table_set_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(builtin) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 )?;
@@ -6118,9 +6133,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call())
                     },
                     // [vmctx, table_index, elem_index, reftype]
-                    [Location::Imm32(table_index.index() as u32), index, value]
-                        .iter()
-                        .cloned(),
+                    [Location::Imm32(table_arg), index, value].iter().cloned(),
                     [WpType::I32, WpType::I64, WpType::I64].iter().cloned(),
                 )?;
 
@@ -6128,6 +6141,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
             }
             Operator::TableGet { table: index } => {
                 let table_index = TableIndex::new(index as _);
+                let (table_arg, builtin) = self.table_builtin(
+                    table_index,
+                    VMBuiltinFunctionIndex::get_table_get_index(),
+                    VMBuiltinFunctionIndex::get_imported_table_get_index(),
+                );
                 let index = self.value_stack.pop().unwrap();
                 self.release_locations_only_regs(&[index])?;
 
@@ -6135,13 +6153,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_table_index(table_index).is_some() {
-                                VMBuiltinFunctionIndex::get_table_get_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_table_get_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(builtin) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 )?;
@@ -6153,9 +6165,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call())
                     },
                     // [vmctx, table_index, elem_index] -> reftype
-                    [Location::Imm32(table_index.index() as u32), index]
-                        .iter()
-                        .cloned(),
+                    [Location::Imm32(table_arg), index].iter().cloned(),
                     [WpType::I32, WpType::I64].iter().cloned(),
                 )?;
 
@@ -6163,7 +6173,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
 
                 let ret = self.acquire_locations(
                     &[(
-                        WpType::FuncRef,
+                        type_to_wp_type(self.module.tables[table_index].ty),
                         MachineValue::WasmStack(self.value_stack.len()),
                     )],
                     false,
@@ -6176,19 +6186,17 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?;
             }
             Operator::TableSize { table: index } => {
-                let table_index = TableIndex::new(index as _);
+                let (table_arg, builtin) = self.table_builtin(
+                    TableIndex::new(index as _),
+                    VMBuiltinFunctionIndex::get_table_size_index(),
+                    VMBuiltinFunctionIndex::get_imported_table_size_index(),
+                );
 
                 self.machine.move_location(
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_table_index(table_index).is_some() {
-                                VMBuiltinFunctionIndex::get_table_size_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_table_size_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(builtin) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 )?;
@@ -6199,7 +6207,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call())
                     },
                     // [vmctx, table_index] -> i32
-                    iter::once(Location::Imm32(table_index.index() as u32)),
+                    iter::once(Location::Imm32(table_arg)),
                     iter::once(WpType::I32),
                 )?;
 
@@ -6215,7 +6223,11 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                 )?;
             }
             Operator::TableGrow { table: index } => {
-                let table_index = TableIndex::new(index as _);
+                let (table_arg, builtin) = self.table_builtin(
+                    TableIndex::new(index as _),
+                    VMBuiltinFunctionIndex::get_table_grow_index(),
+                    VMBuiltinFunctionIndex::get_imported_table_grow_index(),
+                );
                 let delta = self.value_stack.pop().unwrap();
                 let init_value = self.value_stack.pop().unwrap();
                 self.release_locations_only_regs(&[delta, init_value])?;
@@ -6224,13 +6236,7 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                     Size::S64,
                     Location::Memory(
                         self.machine.get_vmctx_reg(),
-                        self.vmoffsets.vmctx_builtin_function(
-                            if self.module.local_table_index(table_index).is_some() {
-                                VMBuiltinFunctionIndex::get_table_grow_index()
-                            } else {
-                                VMBuiltinFunctionIndex::get_imported_table_get_index()
-                            },
-                        ) as i32,
+                        self.vmoffsets.vmctx_builtin_function(builtin) as i32,
                     ),
                     Location::GPR(self.machine.get_grp_for_call()),
                 )?;
@@ -6243,13 +6249,9 @@ impl<'a, M: Machine> FuncGen<'a, M> {
                             .emit_call_register(this.machine.get_grp_for_call())
                     },
                     // [vmctx, init_value, delta, table_index] -> u32
-                    [
-                        init_value,
-                        delta,
-                        Location::Imm32(table_index.index() as u32),
-                    ]
-                    .iter()
-                    .cloned(),
+                    [init_value, delta, Location::Imm32(table_arg)]
+                        .iter()
+                        .cloned(),
                     [WpType::I64, WpType::I64, WpType::I64].iter().cloned(),
                 )?;
 
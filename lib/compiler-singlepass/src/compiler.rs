@@ -17,9 +17,9 @@ use crate::unwind::{create_systemv_cie, UnwindFrame};
 use enumset::EnumSet;
 #[cfg(feature = "unwind")]
 use gimli::write::{EhFrame, FrameTable};
-#[cfg(feature = "rayon")]
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
+#[cfg(feature = "dump")]
+use wasmer_compiler::disassemble;
 use wasmer_compiler::{
     Compiler, CompilerConfig, FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader,
     ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
@@ -27,9 +27,9 @@ use wasmer_compiler::{
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{
     Architecture, CallingConvention, Compilation, CompileError, CompileModuleInfo,
-    CompiledFunction, CpuFeature, Dwarf, FunctionBody, FunctionIndex, FunctionType,
-    LocalFunctionIndex, MemoryIndex, ModuleInfo, OperatingSystem, SectionIndex, TableIndex, Target,
-    TrapCode, TrapInformation, VMOffsets,
+    CompiledFunction, CpuFeature, Dwarf, FunctionBody, FunctionIndex, FunctionLocation,
+    FunctionType, LocalFunctionIndex, MemoryIndex, ModuleInfo, OperatingSystem, SectionIndex,
+    TableIndex, Target, TrapCode, TrapInformation, VMOffsets,
 };
 
 /// A compiler that compiles a WebAssembly module with Singlepass.
@@ -55,6 +55,14 @@ impl Compiler for SinglepassCompiler {
         "singlepass"
     }
 
+    fn deterministic_id(&self) -> String {
+        self.config.settings_id()
+    }
+
+    fn is_pic(&self) -> bool {
+        self.config.enable_pic
+    }
+
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>] {
         &self.config.middlewares
@@ -69,6 +77,34 @@ impl Compiler for SinglepassCompiler {
         _module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError> {
+        if compile_info.module.memories.values().any(|m| m.memory64) {
+            return Err(CompileError::UnsupportedFeature(
+                "memory64 in the singlepass compiler".to_string(),
+            ));
+        }
+        // Every memory access goes to the first memory, so any others (like
+        // the one the coverage middleware adds) would be silently ignored
+        if compile_info.module.memories.len() > 1 {
+            return Err(CompileError::UnsupportedFeature(
+                "multiple memories in the singlepass compiler".to_string(),
+            ));
+        }
+        if compile_info.features.tail_call {
+            return Err(CompileError::UnsupportedFeature(
+                "tail calls in the singlepass compiler".to_string(),
+            ));
+        }
+        if compile_info.features.exceptions {
+            return Err(CompileError::UnsupportedFeature(
+                "exceptions in the singlepass compiler".to_string(),
+            ));
+        }
+        if compile_info.features.relaxed_simd {
+            return Err(CompileError::UnsupportedFeature(
+                "relaxed SIMD in the singlepass compiler".to_string(),
+            ));
+        }
+
         match target.triple().architecture {
             Architecture::X86_64 => {}
             Architecture::Aarch64(_) => {}
@@ -117,28 +153,28 @@ impl Compiler for SinglepassCompiler {
         let table_styles = &compile_info.table_styles;
         let vmoffsets = VMOffsets::new(8, &compile_info.module);
         let module = &compile_info.module;
-        let mut custom_sections: PrimaryMap<SectionIndex, _> = (0..module.num_imported_functions)
-            .map(FunctionIndex::new)
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|i| {
-                gen_import_call_trampoline(
-                    &vmoffsets,
-                    i,
-                    &module.signatures[module.functions[i]],
-                    target,
-                    calling_convention,
-                )
-                .unwrap()
-            })
-            .collect::<Vec<_>>()
+        let threads = &self.config.threads;
+        let mut custom_sections: PrimaryMap<SectionIndex, _> = threads
+            .map_init(
+                &(0..module.num_imported_functions)
+                    .map(FunctionIndex::new)
+                    .collect::<Vec<_>>(),
+                || (),
+                |_, &i| {
+                    gen_import_call_trampoline(
+                        &vmoffsets,
+                        i,
+                        &module.signatures[module.functions[i]],
+                        target,
+                        calling_convention,
+                    )
+                    .unwrap()
+                },
+            )?
             .into_iter()
             .collect();
-        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) = function_body_inputs
-            .iter()
-            .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
-            .into_par_iter_if_rayon()
-            .map(|(i, input)| {
+        let compile_function =
+            |i: LocalFunctionIndex, input: &FunctionBodyData<'_>| -> Result<_, CompileError> {
                 let middleware_chain = self
                     .config
                     .middlewares
@@ -157,7 +193,7 @@ impl Compiler for SinglepassCompiler {
                     }
                 }
 
-                match target.triple().architecture {
+                let (compiled, fde) = match target.triple().architecture {
                     Architecture::X86_64 => {
                         let machine = MachineX86_64::new(Some(target.clone()))?;
                         let mut generator = FuncGen::new(
@@ -172,9 +208,15 @@ impl Compiler for SinglepassCompiler {
                             calling_convention,
                         )?;
                         while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
-                            let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
+                            let offset = reader.original_position();
+                            generator.set_srcloc(offset as u32);
+                            reader
+                                .read_operator()
+                                .map_err(CompileError::from)
+                                .and_then(|op| generator.feed_operator(op))
+                                .map_err(|error| {
+                                    error.in_function(FunctionLocation::new(module, i, offset))
+                                })?;
                         }
 
                         generator.finalize(input)
@@ -193,44 +235,72 @@ impl Compiler for SinglepassCompiler {
                             calling_convention,
                         )?;
                         while generator.has_control_frames() {
-                            generator.set_srcloc(reader.original_position() as u32);
-                            let op = reader.read_operator()?;
-                            generator.feed_operator(op)?;
+                            let offset = reader.original_position();
+                            generator.set_srcloc(offset as u32);
+                            reader
+                                .read_operator()
+                                .map_err(CompileError::from)
+                                .and_then(|op| generator.feed_operator(op))
+                                .map_err(|error| {
+                                    error.in_function(FunctionLocation::new(module, i, offset))
+                                })?;
                         }
 
                         generator.finalize(input)
                     }
                     _ => unimplemented!(),
+                }?;
+
+                #[cfg(feature = "dump")]
+                if let Some(dump) = &self.config.dump {
+                    if dump.should_dump(module, i) {
+                        let disassembly = disassemble(target, &compiled.body.body);
+                        dump.dump_function(module, i, input, None, &disassembly)?;
+                    }
                 }
-            })
-            .collect::<Result<Vec<_>, CompileError>>()?
-            .into_iter()
-            .unzip();
-
-        let function_call_trampolines = module
-            .signatures
-            .values()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| gen_std_trampoline(func_type, target, calling_convention).unwrap())
-            .collect::<Vec<_>>()
+
+                Ok((compiled, fde))
+            };
+        let results = threads.map_init(
+            &function_body_inputs.iter().collect::<Vec<_>>(),
+            || (),
+            |_, &(i, input)| {
+                compile_function(i, input).map_err(|error| {
+                    error.in_function(FunctionLocation::new(module, i, input.module_offset))
+                })
+            },
+        )?;
+        let (functions, fdes): (Vec<CompiledFunction>, Vec<_>) =
+            CompileError::collect(results)?.into_iter().unzip();
+
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &self.config.dump {
+            dump.write_index(module, self.name(), function_body_inputs.keys(), None)?;
+        }
+
+        let function_call_trampolines = threads
+            .map_init(
+                &module.signatures.values().collect::<Vec<_>>(),
+                || (),
+                |_, func_type| gen_std_trampoline(func_type, target, calling_convention).unwrap(),
+            )?
             .into_iter()
             .collect::<PrimaryMap<_, _>>();
 
-        let dynamic_function_trampolines = module
-            .imported_function_types()
-            .collect::<Vec<_>>()
-            .into_par_iter_if_rayon()
-            .map(|func_type| {
-                gen_std_dynamic_import_trampoline(
-                    &vmoffsets,
-                    &func_type,
-                    target,
-                    calling_convention,
-                )
-                .unwrap()
-            })
-            .collect::<Vec<_>>()
+        let dynamic_function_trampolines = threads
+            .map_init(
+                &module.imported_function_types().collect::<Vec<_>>(),
+                || (),
+                |_, func_type| {
+                    gen_std_dynamic_import_trampoline(
+                        &vmoffsets,
+                        func_type,
+                        target,
+                        calling_convention,
+                    )
+                    .unwrap()
+                },
+            )?
             .into_iter()
             .collect::<PrimaryMap<FunctionIndex, FunctionBody>>();
 
@@ -268,25 +338,6 @@ impl Compiler for SinglepassCompiler {
     }
 }
 
-trait IntoParIterIfRayon {
-    type Output;
-    fn into_par_iter_if_rayon(self) -> Self::Output;
-}
-
-impl<T: Send> IntoParIterIfRayon for Vec<T> {
-    #[cfg(not(feature = "rayon"))]
-    type Output = std::vec::IntoIter<T>;
-    #[cfg(feature = "rayon")]
-    type Output = rayon::vec::IntoIter<T>;
-
-    fn into_par_iter_if_rayon(self) -> Self::Output {
-        #[cfg(not(feature = "rayon"))]
-        return self.into_iter();
-        #[cfg(feature = "rayon")]
-        return self.into_par_iter();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3,12 +3,21 @@
 
 use crate::compiler::SinglepassCompiler;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, Engine, EngineBuilder, ModuleMiddleware};
-use wasmer_types::{CpuFeature, Features, Target};
+#[cfg(feature = "dump")]
+use wasmer_compiler::CompilerDump;
+use wasmer_compiler::{
+    CompileThreads, Compiler, CompilerConfig, Engine, EngineBuilder, ModuleMiddleware, StackProbes,
+};
+use wasmer_types::{CpuFeature, FeatureSupport, Features, Target};
 
 #[derive(Debug, Clone)]
 pub struct Singlepass {
     pub(crate) enable_nan_canonicalization: bool,
+    pub(crate) enable_pic: bool,
+    pub(crate) stack_probes: StackProbes,
+    #[cfg(feature = "dump")]
+    pub(crate) dump: Option<CompilerDump>,
+    pub(crate) threads: CompileThreads,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
 }
@@ -18,21 +27,72 @@ impl Singlepass {
     /// specified.
     pub fn new() -> Self {
         Self {
-            enable_nan_canonicalization: true,
+            enable_nan_canonicalization: false,
+            enable_pic: false,
+            stack_probes: StackProbes::default(),
+            #[cfg(feature = "dump")]
+            dump: None,
+            threads: CompileThreads::default(),
             middlewares: vec![],
         }
     }
 
+    /// Enable NaN canonicalization.
+    ///
+    /// See [`CompilerConfig::canonicalize_nans`].
     pub fn canonicalize_nans(&mut self, enable: bool) -> &mut Self {
         self.enable_nan_canonicalization = enable;
         self
     }
+
+    /// Identifies the settings which change the generated code, so
+    /// artifacts compiled with different settings can be told apart.
+    pub(crate) fn settings_id(&self) -> String {
+        let mut id = "singlepass".to_string();
+        if self.enable_nan_canonicalization {
+            id.push_str("-canonicalize-nans");
+        }
+        if self.stack_probes != StackProbes::default() {
+            match self.stack_probes.interval() {
+                Some(interval) => id.push_str(&format!("-probes-{}", interval)),
+                None => id.push_str("-no-probes"),
+            }
+        }
+        id
+    }
+
+    /// How functions with big stack frames probe them.
+    ///
+    /// See [`CompilerConfig::set_stack_probes`].
+    pub fn stack_probes(&mut self, probes: StackProbes) -> &mut Self {
+        self.stack_probes = probes;
+        self
+    }
 }
 
 impl CompilerConfig for Singlepass {
     fn enable_pic(&mut self) {
-        // Do nothing, since singlepass already emits
-        // PIC code.
+        // Singlepass already emits PIC code, this only changes how the
+        // objects it's emitted into link.
+        self.enable_pic = true;
+    }
+
+    fn canonicalize_nans(&mut self, enable: bool) {
+        self.enable_nan_canonicalization = enable;
+    }
+
+    /// Singlepass has no IR, so only the disassembly is dumped.
+    #[cfg(feature = "dump")]
+    fn enable_dump(&mut self, dump: CompilerDump) {
+        self.dump = Some(dump);
+    }
+
+    fn set_compile_threads(&mut self, threads: CompileThreads) {
+        self.threads = threads;
+    }
+
+    fn set_stack_probes(&mut self, probes: StackProbes) {
+        self.stack_probes = probes;
     }
 
     /// Transform it into the compiler
@@ -53,6 +113,25 @@ impl CompilerConfig for Singlepass {
     }
 }
 
+impl FeatureSupport for Singlepass {
+    fn supported_features_for_target(&self, _target: &Target) -> Features {
+        Features {
+            threads: true,
+            reference_types: true,
+            simd: false,
+            bulk_memory: true,
+            multi_value: false,
+            tail_call: false,
+            module_linking: false,
+            multi_memory: true,
+            memory64: false,
+            exceptions: false,
+            relaxed_simd: false,
+            extended_const: true,
+        }
+    }
+}
+
 impl Default for Singlepass {
     fn default() -> Singlepass {
         Self::new()
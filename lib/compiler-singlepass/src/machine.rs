@@ -46,9 +46,6 @@ pub struct TrapTable {
     pub offset_to_code: BTreeMap<usize, TrapCode>,
 }
 
-// all machine seems to have a page this size, so not per arch for now
-pub const NATIVE_PAGE_SIZE: usize = 4096;
-
 pub struct MachineStackOffset(pub usize);
 
 pub trait Machine {
@@ -6,7 +6,7 @@ use crate::location::Reg;
 use crate::machine::*;
 use crate::unwind::{UnwindInstructions, UnwindOps};
 #[cfg(feature = "unwind")]
-use crate::unwind_winx64::create_unwind_info_from_insts;
+use crate::unwind_winx64::{create_trampoline_unwind_info, create_unwind_info_from_insts};
 use crate::x64_decl::new_machine_state;
 use crate::x64_decl::{ArgumentRegisterAllocator, X64Register, GPR, XMM};
 use dynasmrt::{x64::X64Relocation, DynasmError, VecAssembler};
@@ -15,8 +15,8 @@ use gimli::{write::CallFrameInstruction, X86_64};
 use std::ops::{Deref, DerefMut};
 use wasmer_compiler::wasmparser::ValType as WpType;
 use wasmer_types::{
-    CallingConvention, CompileError, CpuFeature, CustomSection, CustomSectionProtection,
-    Relocation, RelocationKind, RelocationTarget, SectionBody, Target,
+    CallingConvention, CompileError, CompiledFunctionUnwindInfo, CpuFeature, CustomSection,
+    CustomSectionProtection, Relocation, RelocationKind, RelocationTarget, SectionBody, Target,
 };
 use wasmer_types::{FunctionBody, InstructionAddressMap, SourceLoc, TrapInformation};
 use wasmer_types::{FunctionIndex, FunctionType, TrapCode, Type, VMOffsets};
@@ -394,14 +394,57 @@ impl MachineX86_64 {
         loc_b: Location,
         ret: Location,
     ) -> Result<(), CompileError> {
-        self.assembler
-            .emit_mov(Size::S64, loc_b, Location::GPR(GPR::RCX))?;
+        self.emit_shift(f, Size::S64, loc_a, loc_b, ret)
+    }
+    /// Shift or rotate `loc_a` by `loc_b` into `ret`.
+    ///
+    /// Constant amounts are encoded in the instruction, modulo the operand's
+    /// width like WebAssembly does. Other amounts go through `cl`, which is
+    /// reserved until the shift is emitted so that moving `loc_a` to a
+    /// spilled `ret` can't pick it as its temporary and overwrite the amount.
+    fn emit_shift(
+        &mut self,
+        f: fn(&mut AssemblerX64, Size, Location, Location) -> Result<(), CompileError>,
+        sz: Size,
+        loc_a: Location,
+        loc_b: Location,
+        ret: Location,
+    ) -> Result<(), CompileError> {
+        let bits = match sz {
+            Size::S32 => 32,
+            _ => 64,
+        };
+        let amount = match loc_b {
+            Location::Imm8(imm) => Some(imm as u64),
+            Location::Imm32(imm) => Some(imm as u64),
+            Location::Imm64(imm) => Some(imm),
+            _ => None,
+        };
 
-        if loc_a != ret {
-            self.emit_relaxed_mov(Size::S64, loc_a, ret)?;
+        match amount {
+            Some(amount) => {
+                if loc_a != ret {
+                    self.emit_relaxed_mov(sz, loc_a, ret)?;
+                }
+                f(
+                    &mut self.assembler,
+                    sz,
+                    Location::Imm8((amount % bits) as u8),
+                    ret,
+                )
+            }
+            None => {
+                // `ret` may be where `loc_b` was, so the amount is read first
+                let count = self.reserve_unused_temp_gpr(GPR::RCX);
+                self.assembler.emit_mov(sz, loc_b, Location::GPR(count))?;
+                if loc_a != ret {
+                    self.emit_relaxed_mov(sz, loc_a, ret)?;
+                }
+                f(&mut self.assembler, sz, Location::GPR(count), ret)?;
+                self.release_gpr(count);
+                Ok(())
+            }
         }
-
-        f(&mut self.assembler, Size::S64, Location::GPR(GPR::RCX), ret)
     }
     /// Moves `loc` to a valid location for `div`/`idiv`.
     fn emit_relaxed_xdiv(
@@ -471,14 +514,7 @@ impl MachineX86_64 {
         loc_b: Location,
         ret: Location,
     ) -> Result<(), CompileError> {
-        self.assembler
-            .emit_mov(Size::S32, loc_b, Location::GPR(GPR::RCX))?;
-
-        if loc_a != ret {
-            self.emit_relaxed_mov(Size::S32, loc_a, ret)?;
-        }
-
-        f(&mut self.assembler, Size::S32, Location::GPR(GPR::RCX), ret)
+        self.emit_shift(f, Size::S32, loc_a, loc_b, ret)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -7854,7 +7890,9 @@ impl Machine for MachineX86_64 {
 
         // Used callee-saved registers
         a.emit_push(Size::S64, Location::GPR(GPR::R15))?;
+        let r15_pushed = a.get_offset().0;
         a.emit_push(Size::S64, Location::GPR(GPR::R14))?;
+        let r14_pushed = a.get_offset().0;
 
         // Prepare stack space.
         a.emit_sub(
@@ -7862,6 +7900,11 @@ impl Machine for MachineX86_64 {
             Location::Imm32(stack_offset + stack_padding),
             Location::GPR(GPR::RSP),
         )?;
+        let unwind_info = trampoline_unwind_info(
+            calling_convention,
+            &[(r15_pushed, GPR::R15), (r14_pushed, GPR::R14)],
+            (a.get_offset().0, stack_offset + stack_padding),
+        );
 
         // Arguments
         a.emit_mov(
@@ -7933,10 +7976,7 @@ impl Machine for MachineX86_64 {
 
         let mut body = a.finalize().unwrap();
         body.shrink_to_fit();
-        Ok(FunctionBody {
-            body,
-            unwind_info: None,
-        })
+        Ok(FunctionBody { body, unwind_info })
     }
     // Generates dynamic import function call trampoline for a function type.
     fn gen_std_dynamic_import_trampoline(
@@ -7959,6 +7999,11 @@ impl Machine for MachineX86_64 {
             Location::Imm32((stack_offset + stack_padding) as _),
             Location::GPR(GPR::RSP),
         )?;
+        let unwind_info = trampoline_unwind_info(
+            calling_convention,
+            &[],
+            (a.get_offset().0, (stack_offset + stack_padding) as u32),
+        );
 
         // Copy arguments.
         if !sig.params().is_empty() {
@@ -8057,10 +8102,7 @@ impl Machine for MachineX86_64 {
 
         let mut body = a.finalize().unwrap();
         body.shrink_to_fit();
-        Ok(FunctionBody {
-            body,
-            unwind_info: None,
-        })
+        Ok(FunctionBody { body, unwind_info })
     }
     // Singlepass calls import functions through a trampoline.
     fn gen_import_call_trampoline(
@@ -8291,6 +8333,35 @@ impl Machine for MachineX86_64 {
     }
 }
 
+/// The unwind information of a trampoline, which pushes the `pushed`
+/// registers and then allocates stack space. Only Windows needs it: the
+/// trampolines don't have any DWARF unwind information.
+#[cfg(feature = "unwind")]
+fn trampoline_unwind_info(
+    calling_convention: CallingConvention,
+    pushed: &[(usize, GPR)],
+    stack_alloc: (usize, u32),
+) -> Option<CompiledFunctionUnwindInfo> {
+    match calling_convention {
+        CallingConvention::WindowsFastcall => {
+            let unwind = create_trampoline_unwind_info(pushed, stack_alloc);
+            let mut tbl = vec![0; unwind.emit_size()];
+            unwind.emit(&mut tbl);
+            Some(CompiledFunctionUnwindInfo::WindowsX64(tbl))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "unwind"))]
+fn trampoline_unwind_info(
+    _calling_convention: CallingConvention,
+    _pushed: &[(usize, GPR)],
+    _stack_alloc: (usize, u32),
+) -> Option<CompiledFunctionUnwindInfo> {
+    None
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1,6 +1,7 @@
 //! Windows x64 ABI unwind information.
 
 use crate::unwind::UnwindOps;
+use crate::x64_decl::GPR;
 
 /// Maximum (inclusive) size of a "small" stack allocation
 const SMALL_ALLOC_MAX_SIZE: u32 = 128;
@@ -105,14 +106,16 @@ impl UnwindCode {
                     )
                 };
                 writer.write_u8(*instruction_offset);
-                let scaled_stack_offset = stack_offset / 16;
-                if scaled_stack_offset <= core::u16::MAX as u32 {
-                    writer.write_u8((*reg << 4) | (op_small as u8));
-                    writer.write_u16_le(scaled_stack_offset as u16);
-                } else {
-                    writer.write_u8((*reg << 4) | (op_large as u8));
-                    writer.write_u16_le(*stack_offset as u16);
-                    writer.write_u16_le((stack_offset >> 16) as u16);
+                match self.scaled_stack_offset() {
+                    Some(scaled_stack_offset) => {
+                        writer.write_u8((*reg << 4) | (op_small as u8));
+                        writer.write_u16_le(scaled_stack_offset);
+                    }
+                    None => {
+                        writer.write_u8((*reg << 4) | (op_large as u8));
+                        writer.write_u16_le(*stack_offset as u16);
+                        writer.write_u16_le((stack_offset >> 16) as u16);
+                    }
                 }
             }
             Self::StackAlloc {
@@ -154,8 +157,8 @@ impl UnwindCode {
                     3
                 }
             }
-            Self::SaveXmm { stack_offset, .. } | Self::SaveReg { stack_offset, .. } => {
-                if *stack_offset <= core::u16::MAX as u32 {
+            Self::SaveXmm { .. } | Self::SaveReg { .. } => {
+                if self.scaled_stack_offset().is_some() {
                     2
                 } else {
                     3
@@ -164,6 +167,21 @@ impl UnwindCode {
             _ => 1,
         }
     }
+
+    /// The offset of a saved register in the units of the short form of
+    /// its code: 8 bytes for GPRs and 16 for XMM registers. Offsets that
+    /// aren't a multiple of that, or are too far, need the far form.
+    fn scaled_stack_offset(&self) -> Option<u16> {
+        let (stack_offset, scale) = match self {
+            Self::SaveReg { stack_offset, .. } => (*stack_offset, 8),
+            Self::SaveXmm { stack_offset, .. } => (*stack_offset, 16),
+            _ => return None,
+        };
+        if stack_offset % scale != 0 {
+            return None;
+        }
+        u16::try_from(stack_offset / scale).ok()
+    }
 }
 
 /// Represents Windows x64 unwind information.
@@ -240,9 +258,38 @@ impl UnwindInfo {
 
 const UNWIND_RBP_REG: u8 = 5;
 
+/// Largest offset of the frame pointer from the establisher frame, which
+/// is stored in 4 bits, in units of 16 bytes.
+const MAX_FRAME_REGISTER_OFFSET: i32 = 15 * 16;
+
+/// Creates the unwind information of a function, from the unwind ops its
+/// prologue emitted.
+///
+/// The prologue is `push rbp; mov rbp, rsp`, followed by the registers
+/// being saved below `rbp`. The offsets of saved registers are relative to
+/// the establisher frame, and can't be negative, so the frame is described
+/// as if `mov rbp, rsp` also allocated the save area, with `rbp` pointing
+/// at its top. Unwinding then sets `rsp` back to `rbp` as it should. The
+/// stack adjustments made after the prologue, for the locals or for calls,
+/// don't need unwind codes, as the frame is found from `rbp`.
 pub(crate) fn create_unwind_info_from_insts(insts: &[(usize, UnwindOps)]) -> Option<UnwindInfo> {
+    let save_area_size = insts
+        .iter()
+        .filter_map(|(_, inst)| match *inst {
+            UnwindOps::SaveRegister { bp_neg_offset, .. } => Some(bp_neg_offset),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    assert!(
+        save_area_size <= MAX_FRAME_REGISTER_OFFSET,
+        "registers can't be saved more than {} bytes below the frame pointer for Windows x64",
+        MAX_FRAME_REGISTER_OFFSET
+    );
+    let frame_register_offset = ((save_area_size + 15) / 16) as u8;
+    let establisher_frame_offset = frame_register_offset as i32 * 16;
+
     let mut unwind_codes = vec![];
-    let mut frame_register_offset = 0;
     let mut max_unwind_offset = 0;
     for &(instruction_offset, ref inst) in insts {
         let instruction_offset = ensure_unwind_offset(instruction_offset as u32)?;
@@ -254,31 +301,39 @@ pub(crate) fn create_unwind_info_from_insts(insts: &[(usize, UnwindOps)]) -> Opt
                 });
             }
             UnwindOps::DefineNewFrame => {
-                frame_register_offset = ensure_unwind_offset(32)?;
-                unwind_codes.push(UnwindCode::SetFPReg { instruction_offset });
-            }
-            UnwindOps::SaveRegister { reg, bp_neg_offset } => match reg {
-                0..=15 => {
-                    // GPR reg
-                    static FROM_DWARF: [u8; 16] =
-                        [0, 2, 1, 3, 6, 7, 5, 4, 8, 9, 10, 11, 12, 13, 14, 15];
-                    unwind_codes.push(UnwindCode::SaveReg {
+                if establisher_frame_offset > 0 {
+                    unwind_codes.push(UnwindCode::StackAlloc {
                         instruction_offset,
-                        reg: FROM_DWARF[reg as usize],
-                        stack_offset: bp_neg_offset as u32,
+                        size: establisher_frame_offset as u32,
                     });
                 }
-                17..=32 => {
-                    unwind_codes.push(UnwindCode::SaveXmm {
-                        instruction_offset,
-                        reg: reg as u8 - 17,
-                        stack_offset: bp_neg_offset as u32,
-                    });
-                }
-                _ => {
-                    unreachable!("unknown register index {}", reg);
+                unwind_codes.push(UnwindCode::SetFPReg { instruction_offset });
+            }
+            UnwindOps::SaveRegister { reg, bp_neg_offset } => {
+                let stack_offset = (establisher_frame_offset - bp_neg_offset) as u32;
+                match reg {
+                    0..=15 => {
+                        // GPR reg
+                        static FROM_DWARF: [u8; 16] =
+                            [0, 2, 1, 3, 6, 7, 5, 4, 8, 9, 10, 11, 12, 13, 14, 15];
+                        unwind_codes.push(UnwindCode::SaveReg {
+                            instruction_offset,
+                            reg: FROM_DWARF[reg as usize],
+                            stack_offset,
+                        });
+                    }
+                    17..=32 => {
+                        unwind_codes.push(UnwindCode::SaveXmm {
+                            instruction_offset,
+                            reg: reg as u8 - 17,
+                            stack_offset,
+                        });
+                    }
+                    _ => {
+                        unreachable!("unknown register index {}", reg);
+                    }
                 }
-            },
+            }
             UnwindOps::Push2Regs { .. } => {
                 unreachable!("no aarch64 on x64");
             }
@@ -295,9 +350,153 @@ pub(crate) fn create_unwind_info_from_insts(insts: &[(usize, UnwindOps)]) -> Opt
     })
 }
 
+/// Creates the unwind information of a trampoline, which doesn't have a
+/// frame pointer: its prologue pushes the `pushed` registers, then
+/// allocates `stack_size` bytes, each instruction ending at the offset
+/// given with it.
+pub(crate) fn create_trampoline_unwind_info(
+    pushed: &[(usize, GPR)],
+    (alloc_offset, stack_size): (usize, u32),
+) -> UnwindInfo {
+    let mut unwind_codes = pushed
+        .iter()
+        .map(|&(instruction_offset, reg)| UnwindCode::PushRegister {
+            instruction_offset: instruction_offset as u8,
+            // `GPR` is numbered like the registers of the unwind codes
+            reg: reg as u8,
+        })
+        .collect::<Vec<_>>();
+    let prologue_size = ensure_unwind_offset(alloc_offset as u32).unwrap();
+    unwind_codes.push(UnwindCode::StackAlloc {
+        instruction_offset: prologue_size,
+        size: stack_size,
+    });
+
+    UnwindInfo {
+        flags: 0,
+        prologue_size,
+        frame_register: None,
+        frame_register_offset: 0,
+        unwind_codes,
+    }
+}
+
 fn ensure_unwind_offset(offset: u32) -> Option<u8> {
     if offset > 255 {
         panic!("function prologues cannot exceed 255 bytes in size for Windows x64");
     }
     Some(offset as u8)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emit(info: &UnwindInfo) -> Vec<u8> {
+        let mut buf = vec![0; info.emit_size()];
+        info.emit(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn saves_are_relative_to_the_establisher_frame() {
+        let info = create_unwind_info_from_insts(&[
+            (1, UnwindOps::PushFP { up_to_sp: 16 }),
+            (4, UnwindOps::DefineNewFrame),
+            // r15, r14 and xmm6, in DWARF numbering
+            (
+                15,
+                UnwindOps::SaveRegister {
+                    reg: 15,
+                    bp_neg_offset: 8,
+                },
+            ),
+            (
+                23,
+                UnwindOps::SaveRegister {
+                    reg: 14,
+                    bp_neg_offset: 16,
+                },
+            ),
+            (
+                30,
+                UnwindOps::SaveRegister {
+                    reg: 23,
+                    bp_neg_offset: 32,
+                },
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(info.frame_register_offset, 2);
+        #[rustfmt::skip]
+        assert_eq!(
+            emit(&info),
+            [
+                // version 1, 30 bytes of prologue, 9 nodes, rbp at 2 * 16
+                0x01, 30, 9, 0x25,
+                // xmm6 at 0 * 16
+                30, 0x68, 0, 0,
+                // r14 at 2 * 8
+                23, 0xe4, 2, 0,
+                // r15 at 3 * 8
+                15, 0xf4, 3, 0,
+                // rbp is the frame pointer, 32 bytes above the frame
+                4, 0x03,
+                4, 0x32,
+                // push rbp
+                1, 0x50,
+                // padding
+                0, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn unaligned_xmm_saves_use_the_far_form() {
+        let info = create_unwind_info_from_insts(&[
+            (1, UnwindOps::PushFP { up_to_sp: 16 }),
+            (4, UnwindOps::DefineNewFrame),
+            (
+                12,
+                UnwindOps::SaveRegister {
+                    reg: 24,
+                    bp_neg_offset: 8,
+                },
+            ),
+        ])
+        .unwrap();
+
+        #[rustfmt::skip]
+        assert_eq!(
+            emit(&info),
+            [
+                0x01, 12, 6, 0x15,
+                // xmm7 at 8 bytes, unscaled
+                12, 0x79, 8, 0, 0, 0,
+                4, 0x03,
+                4, 0x12,
+                1, 0x50,
+            ]
+        );
+    }
+
+    #[test]
+    fn trampolines_have_no_frame_pointer() {
+        let info = create_trampoline_unwind_info(&[(2, GPR::R15), (4, GPR::R14)], (11, 40));
+
+        #[rustfmt::skip]
+        assert_eq!(
+            emit(&info),
+            [
+                0x01, 11, 3, 0x00,
+                // sub rsp, 40
+                11, 0x42,
+                // push r14, push r15
+                4, 0xe0,
+                2, 0xf0,
+                0, 0,
+            ]
+        );
+    }
+}
@@ -12,19 +12,24 @@ use std::sync::Arc;
 use wasmer_types::entity::PrimaryMap;
 #[cfg(feature = "compiler")]
 use wasmer_types::CompileModuleInfo;
+use wasmer_types::{ArtifactMetadata, MetadataHeader, SerializeError};
 use wasmer_types::{
     CompileError, CpuFeature, CustomSection, Dwarf, FunctionIndex, LocalFunctionIndex, MemoryIndex,
-    MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex, SignatureIndex,
-    TableIndex, TableStyle, Target,
+    MemoryStyle, MemoryType, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex,
+    SignatureIndex, TableIndex, TableStyle, TableType, Target, Triple, WatSourceMap,
 };
 use wasmer_types::{
     CompiledFunctionFrameInfo, FunctionBody, SerializableCompilation, SerializableModule,
 };
-use wasmer_types::{MetadataHeader, SerializeError};
 
 /// A compiled wasm module, ready to be instantiated.
 pub struct ArtifactBuild {
     serializable: SerializableModule,
+    /// The target triple the module was compiled for.
+    triple: Triple,
+    /// The compiler and settings the module was compiled with, if known
+    /// (see [`crate::Compiler::deterministic_id`]).
+    compiler: String,
 }
 
 impl ArtifactBuild {
@@ -37,13 +42,46 @@ impl ArtifactBuild {
     }
 
     /// Compile a data buffer into a `ArtifactBuild`, which may then be instantiated.
+    ///
+    /// The styles of the memories and tables, including the ones the
+    /// middlewares add, are picked by `memory_style` and `table_style`
+    /// (usually the engine's [`crate::Tunables`]).
     #[cfg(feature = "compiler")]
     pub fn new(
         inner_engine: &mut EngineInner,
         data: &[u8],
         target: &Target,
-        memory_styles: PrimaryMap<MemoryIndex, MemoryStyle>,
-        table_styles: PrimaryMap<TableIndex, TableStyle>,
+        memory_style: &dyn Fn(&MemoryType) -> MemoryStyle,
+        table_style: &dyn Fn(&TableType) -> TableStyle,
+    ) -> Result<Self, CompileError> {
+        Self::compile(inner_engine, data, target, memory_style, table_style, false)
+    }
+
+    /// Compile a data buffer into a `ArtifactBuild` whose functions are
+    /// stubs, which compile the real functions the first time they're
+    /// called (see [`crate::Compiler::compile_module_lazily`]).
+    ///
+    /// The result can't be serialized, since the stubs only work together
+    /// with a [`wasmer_vm::LazyFunctionCompiler`].
+    #[cfg(feature = "compiler")]
+    pub fn new_lazy(
+        inner_engine: &mut EngineInner,
+        data: &[u8],
+        target: &Target,
+        memory_style: &dyn Fn(&MemoryType) -> MemoryStyle,
+        table_style: &dyn Fn(&TableType) -> TableStyle,
+    ) -> Result<Self, CompileError> {
+        Self::compile(inner_engine, data, target, memory_style, table_style, true)
+    }
+
+    #[cfg(feature = "compiler")]
+    fn compile(
+        inner_engine: &mut EngineInner,
+        data: &[u8],
+        target: &Target,
+        memory_style: &dyn Fn(&MemoryType) -> MemoryStyle,
+        table_style: &dyn Fn(&TableType) -> TableStyle,
+        lazy: bool,
     ) -> Result<Self, CompileError> {
         let environ = ModuleEnvironment::new();
         let features = inner_engine.features().clone();
@@ -55,7 +93,15 @@ impl ArtifactBuild {
         // We try to apply the middleware first
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
-        middlewares.apply_on_module_info(&mut module);
+        middlewares
+            .apply_on_module_info_with_bodies(&mut module, &translation.function_body_inputs);
+
+        // The middlewares may have added memories and tables, so the styles
+        // are only picked now
+        let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> =
+            module.memories.values().map(memory_style).collect();
+        let table_styles: PrimaryMap<TableIndex, TableStyle> =
+            module.tables.values().map(table_style).collect();
 
         let compile_info = CompileModuleInfo {
             module: Arc::new(module),
@@ -64,16 +110,21 @@ impl ArtifactBuild {
             table_styles,
         };
 
+        // SAFETY: Calling `unwrap` is correct since `environ.translate()`
+        // above will write some data into `module_translation_state`.
+        let module_translation_state = translation.module_translation_state.as_ref().unwrap();
+
         // Compile the Module
-        let compilation = compiler.compile_module(
-            target,
-            &compile_info,
-            // SAFETY: Calling `unwrap` is correct since
-            // `environ.translate()` above will write some data into
-            // `module_translation_state`.
-            translation.module_translation_state.as_ref().unwrap(),
-            translation.function_body_inputs,
-        )?;
+        let compilation = if lazy {
+            compiler.compile_module_lazily(target, &compile_info, module_translation_state)?
+        } else {
+            compiler.compile_module(
+                target,
+                &compile_info,
+                module_translation_state,
+                translation.function_body_inputs,
+            )?
+        };
 
         let data_initializers = translation
             .data_initializers
@@ -121,7 +172,15 @@ impl ArtifactBuild {
             data_initializers,
             cpu_features: cpu_features.as_u64(),
         };
-        Ok(Self { serializable })
+        Ok(Self {
+            serializable,
+            triple: target.triple().clone(),
+            compiler: format!(
+                "{}{}",
+                compiler.deterministic_id(),
+                compiler.get_middlewares().deterministic_id_suffix()
+            ),
+        })
     }
 
     /// Compile a data buffer into a `ArtifactBuild`, which may then be instantiated.
@@ -139,9 +198,32 @@ impl ArtifactBuild {
         ))
     }
 
-    /// Create a new ArtifactBuild from a SerializableModule
-    pub fn from_serializable(serializable: SerializableModule) -> Self {
-        Self { serializable }
+    /// Create a new ArtifactBuild from a SerializableModule which was compiled
+    /// for `triple`
+    pub fn from_serializable(serializable: SerializableModule, triple: Triple) -> Self {
+        Self {
+            serializable,
+            triple,
+            compiler: String::new(),
+        }
+    }
+
+    /// Record the compiler and settings the module was compiled with, as
+    /// read from a serialized artifact's metadata.
+    pub fn with_compiler(mut self, compiler: String) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Get the target triple the module was compiled for
+    pub fn triple(&self) -> &Triple {
+        &self.triple
+    }
+
+    /// Get the compiler and settings the module was compiled with (eg.
+    /// `llvm-O2`), or an empty string if they aren't known.
+    pub fn compiler(&self) -> &str {
+        &self.compiler
     }
 
     /// Get Functions Bodies ref
@@ -201,10 +283,15 @@ impl ArtifactCreate for ArtifactBuild {
     }
 
     fn set_module_info_name(&mut self, name: String) -> bool {
-        Arc::get_mut(&mut self.serializable.compile_info.module).map_or(false, |mut module_info| {
-            module_info.name = Some(name.to_string());
-            true
-        })
+        // Frame info registrations and instances keep their own reference
+        // to the `ModuleInfo`, so copy it rather than failing if it's shared
+        Arc::make_mut(&mut self.serializable.compile_info.module).name = Some(name);
+        true
+    }
+
+    fn set_module_info_source_map(&mut self, source_map: Arc<WatSourceMap>) -> bool {
+        Arc::make_mut(&mut self.serializable.compile_info.module).source_map = Some(source_map);
+        true
     }
 
     fn module_info(&self) -> &ModuleInfo {
@@ -235,10 +322,17 @@ impl ArtifactCreate for ArtifactBuild {
         let serialized_data = self.serializable.serialize()?;
         assert!(std::mem::align_of::<SerializableModule>() <= MetadataHeader::ALIGN);
 
+        let metadata = ArtifactMetadata {
+            wasmer_version: env!("CARGO_PKG_VERSION").to_string(),
+            triple: self.triple.to_string(),
+            compiler: self.compiler.clone(),
+        };
+
         let mut metadata_binary = vec![];
         metadata_binary.extend(Self::MAGIC_HEADER);
         metadata_binary.extend(MetadataHeader::new(serialized_data.len()).into_bytes());
-        metadata_binary.extend(serialized_data);
+        metadata_binary.extend(&serialized_data);
+        metadata.write(&serialized_data, &mut metadata_binary);
         Ok(metadata_binary)
     }
 }
@@ -4,20 +4,28 @@
 use crate::lib::std::boxed::Box;
 use crate::lib::std::sync::Arc;
 use crate::translator::ModuleMiddleware;
+use crate::validation::wasm_features;
+use crate::CompileThreads;
+#[cfg(feature = "dump")]
+use crate::CompilerDump;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
 use enumset::EnumSet;
-use wasmer_types::compilation::function::Compilation;
+use wasmer_types::compilation::function::{Compilation, LazyCompiledFunction};
 use wasmer_types::compilation::module::CompileModuleInfo;
 use wasmer_types::compilation::symbols::SymbolRegistry;
 use wasmer_types::compilation::target::Target;
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::error::CompileError;
-use wasmer_types::{CpuFeature, Features, LocalFunctionIndex};
-use wasmparser::{Validator, WasmFeatures};
+use wasmer_types::{CpuFeature, FeatureSupport, Features, LocalFunctionIndex};
+use wasmparser::Validator;
 
 /// The compiler configuration options.
-pub trait CompilerConfig {
+///
+/// The [`FeatureSupport`] implementation says which WebAssembly proposals
+/// the compiler can handle, which [`Features::builder()`] uses to reject
+/// the unsupported ones up front.
+pub trait CompilerConfig: FeatureSupport {
     /// Enable Position Independent Code (PIC).
     ///
     /// This is required for shared object generation (Native Engine),
@@ -39,11 +47,74 @@ pub trait CompilerConfig {
 
     /// Enable NaN canonicalization.
     ///
-    /// NaN canonicalization is useful when trying to run WebAssembly
-    /// deterministically across different architectures.
+    /// Every floating point operation which can produce a NaN (arithmetic,
+    /// including on each lane of SIMD vectors, and conversions between
+    /// `f32` and `f64`) then produces the canonical NaN, with only the
+    /// most significant bit of the payload set, instead of whichever NaN the
+    /// hardware came up with. This is useful when trying to run WebAssembly
+    /// deterministically across different compilers and architectures.
+    ///
+    /// It's disabled by default, because of the extra code it takes. The
+    /// compilers include it in their [`Compiler::deterministic_id`], so
+    /// artifacts compiled with and without it are told apart.
     fn canonicalize_nans(&mut self, _enable: bool) {
         // By default we do nothing, each backend will need to customize this
-        // in case they create an IR that they can verify.
+        // in case they can canonicalize NaNs.
+    }
+
+    /// Enable lazy compilation.
+    ///
+    /// Each function is only compiled the first time it's called, which
+    /// makes loading large modules much faster when most of their code is
+    /// never run.
+    fn enable_lazy_compilation(&mut self, _enable: bool) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they can compile functions one at a time.
+    }
+
+    /// Dump the WebAssembly, IR and machine code of the functions selected
+    /// by `dump` as they're compiled (see [`CompilerDump`]).
+    #[cfg(feature = "dump")]
+    fn enable_dump(&mut self, _dump: CompilerDump) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they can dump what they generate.
+    }
+
+    /// Set the threads modules are compiled on (see [`CompileThreads`]).
+    ///
+    /// The compiled artifacts are the same whichever threads compiled them.
+    fn set_compile_threads(&mut self, _threads: CompileThreads) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they compile functions in parallel.
+    }
+
+    /// Set how functions with big stack frames probe them (see
+    /// [`StackProbes`]).
+    fn set_stack_probes(&mut self, _probes: StackProbes) {
+        // By default we do nothing, each backend will need to customize this
+        // in case they can probe the stack.
+    }
+
+    /// Limit the size of the text sections of the objects the compiled
+    /// code is emitted into, splitting it into several sections past
+    /// `max_size` bytes.
+    ///
+    /// This only affects objects, which linkers may not be able to link
+    /// with all the code of a big module in one section, and not the
+    /// artifacts loaded by the engine.
+    fn set_text_section_size(&mut self, _max_size: Option<u64>) {
+        // By default we do nothing, each backend will need to customize this
+        // in case their code can be emitted into objects.
+    }
+
+    /// Optimize modules whose native code is estimated to take more than
+    /// `max_size` bytes less aggressively, so that they generate less code.
+    ///
+    /// Compiling such a module still succeeds, and the code is just as
+    /// correct, but it may be slower.
+    fn set_code_size_limit(&mut self, _max_size: Option<u64>) {
+        // By default we do nothing, each backend will need to customize this
+        // in case it has optimizations which make code bigger.
     }
 
     /// Gets the custom compiler config
@@ -67,6 +138,49 @@ where
     }
 }
 
+/// How functions whose stack frame is bigger than a page make sure the
+/// stack has room for it.
+///
+/// The stacks wasm runs on have guard pages below them, which trap with
+/// [`TrapCode::StackOverflow`] when touched. A function allocating a frame
+/// bigger than the guard could move the stack pointer past it without ever
+/// touching it, and write to whatever is below. Probing touches the frame a
+/// page at a time from the top down, so it hits the guard first.
+///
+/// [`TrapCode::StackOverflow`]: wasmer_types::TrapCode::StackOverflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StackProbes {
+    /// Frames are allocated without probing them.
+    Disabled,
+    /// Frames bigger than this many bytes are probed every this many bytes.
+    ///
+    /// It's rounded up to a power of two, of at least 4096 bytes, and should
+    /// be no bigger than the guard below the stacks.
+    Every(u32),
+}
+
+impl StackProbes {
+    /// The interval frames are probed at, or `None` if they aren't.
+    pub fn interval(&self) -> Option<u32> {
+        match *self {
+            Self::Disabled => None,
+            Self::Every(bytes) => Some(
+                bytes
+                    .max(4096)
+                    .checked_next_power_of_two()
+                    .unwrap_or(1 << 31),
+            ),
+        }
+    }
+}
+
+impl Default for StackProbes {
+    /// Frames are probed a page at a time.
+    fn default() -> Self {
+        Self::Every(4096)
+    }
+}
+
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
 pub trait Compiler: Send {
     /// Returns a descriptive name for this compiler.
@@ -74,28 +188,21 @@ pub trait Compiler: Send {
     /// Note that this is an API breaking change since 3.0
     fn name(&self) -> &str;
 
+    /// Returns an id for this compiler and the settings it was configured
+    /// with that affect the generated code.
+    ///
+    /// Two compilers with the same id must produce compatible artifacts.
+    /// It's used as part of [`crate::Engine::deterministic_id`], and is
+    /// recorded in serialized artifacts.
+    fn deterministic_id(&self) -> String {
+        self.name().to_string()
+    }
+
     /// Validates a module.
     ///
     /// It returns the a succesful Result in case is valid, `CompileError` in case is not.
     fn validate_module(&self, features: &Features, data: &[u8]) -> Result<(), CompileError> {
-        let wasm_features = WasmFeatures {
-            bulk_memory: features.bulk_memory,
-            threads: features.threads,
-            reference_types: features.reference_types,
-            multi_value: features.multi_value,
-            simd: features.simd,
-            tail_call: features.tail_call,
-            multi_memory: features.multi_memory,
-            memory64: features.memory64,
-            exceptions: features.exceptions,
-            deterministic_only: false,
-            extended_const: features.extended_const,
-            relaxed_simd: features.relaxed_simd,
-            mutable_global: true,
-            saturating_float_to_int: true,
-            sign_extension: true,
-            component_model: false,
-        };
+        let wasm_features = wasm_features(features);
         let mut validator = Validator::new_with_features(wasm_features);
         validator
             .validate_all(data)
@@ -115,6 +222,65 @@ pub trait Compiler: Send {
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
     ) -> Result<Compilation, CompileError>;
 
+    /// Whether the compiler emits position-independent code (see
+    /// [`CompilerConfig::enable_pic`]).
+    fn is_pic(&self) -> bool {
+        false
+    }
+
+    /// The size the text sections of objects are limited to, if any (see
+    /// [`CompilerConfig::set_text_section_size`]).
+    fn text_section_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether modules should be compiled lazily (see
+    /// [`CompilerConfig::enable_lazy_compilation`]).
+    fn lazy_compilation(&self) -> bool {
+        false
+    }
+
+    /// Compiles a parsed module lazily.
+    ///
+    /// Instead of the functions themselves, the [`Compilation`] contains a
+    /// stub for each of them which calls the
+    /// [`VMBuiltinFunctionIndex::get_lazy_compile_index`] builtin the first
+    /// time it's run. The functions are compiled with
+    /// [`Compiler::compile_function`] from then on.
+    ///
+    /// [`VMBuiltinFunctionIndex::get_lazy_compile_index`]: wasmer_types::VMBuiltinFunctionIndex::get_lazy_compile_index
+    fn compile_module_lazily(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+    ) -> Result<Compilation, CompileError> {
+        Err(CompileError::UnsupportedFeature(format!(
+            "lazy compilation with the {} compiler",
+            self.name()
+        )))
+    }
+
+    /// Compiles a single function of a module that was compiled with
+    /// [`Compiler::compile_module_lazily`].
+    ///
+    /// The function's code is allocated separately from the rest of the
+    /// module, so it mustn't assume the functions it calls are nearby, and
+    /// it comes with its own unwind information.
+    fn compile_function(
+        &self,
+        _target: &Target,
+        _module: &CompileModuleInfo,
+        _module_translation: &ModuleTranslationState,
+        _index: LocalFunctionIndex,
+        _function_body: &FunctionBodyData<'_>,
+    ) -> Result<LazyCompiledFunction, CompileError> {
+        Err(CompileError::UnsupportedFeature(format!(
+            "lazy compilation with the {} compiler",
+            self.name()
+        )))
+    }
+
     /// Compiles a module into a native object file.
     ///
     /// It returns the bytes as a `&[u8]` or a [`CompileError`].
@@ -0,0 +1,151 @@
+//! Disassembling the machine code the compilers generate, for
+//! `wasmer inspect --disassemble` and the compiler dumps.
+
+use crate::{ArtifactBuild, ArtifactCreate};
+use std::fmt::Write as _;
+use std::str::FromStr;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{Architecture, InstructionAddressMap, LocalFunctionIndex, ModuleInfo, Target};
+
+/// Selects functions to dump or disassemble.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DumpFilter {
+    /// The function with this index in the module, counting the imported
+    /// functions like the WebAssembly does.
+    Index(u32),
+    /// The function with this name in the module's name section.
+    Name(String),
+}
+
+impl FromStr for DumpFilter {
+    type Err = std::convert::Infallible;
+
+    /// Numbers are parsed as indices and anything else as a name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::Name(s.to_string()),
+        })
+    }
+}
+
+/// Disassemble the machine code of a function compiled for `target`.
+///
+/// Only x86_64 is supported, and only with the `disassemble` feature.
+/// Otherwise the machine code is printed in hex.
+pub fn disassemble(target: &Target, code: &[u8]) -> String {
+    disassemble_annotated(target.triple().architecture, code, &[])
+}
+
+/// Disassemble the functions of a compiled module matching any of
+/// `filters`, or all of them if there are no filters.
+///
+/// Each function starts with a `;; function <index> <name>` line, and the
+/// instructions generated for each WebAssembly instruction are preceded by
+/// a `;; @<offset>` line with its offset in the module, for the compilers
+/// which record it. Addresses are relative to the start of the function,
+/// so the output of two compilers, or two versions of one, can be diffed.
+pub fn disassemble_artifact(artifact: &ArtifactBuild, filters: &[DumpFilter]) -> String {
+    let module = artifact.module_info();
+    let architecture = artifact.triple().architecture;
+    let frame_info = artifact.get_frame_info_ref();
+
+    let mut out = String::new();
+    for (local_index, body) in artifact.get_function_bodies_ref().iter() {
+        if !matches_filters(filters, module, local_index) {
+            continue;
+        }
+        let index = module.func_index(local_index);
+        let name = module
+            .function_names
+            .get(&index)
+            .map(String::as_str)
+            .unwrap_or("<unnamed>");
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, ";; function {} {name}", index.index());
+        let address_map = frame_info
+            .get(local_index)
+            .map(|info| info.address_map.instructions.as_slice())
+            .unwrap_or_default();
+        out.push_str(&disassemble_annotated(
+            architecture,
+            &body.body,
+            address_map,
+        ));
+    }
+    out
+}
+
+/// Disassemble `code`, adding a line with the WebAssembly offset before
+/// the instructions each entry of `address_map` covers.
+fn disassemble_annotated(
+    #[cfg_attr(not(feature = "disassemble"), allow(unused_variables))] architecture: Architecture,
+    code: &[u8],
+    address_map: &[InstructionAddressMap],
+) -> String {
+    let mut out = String::new();
+    let mut annotated = None;
+    let mut annotate = |out: &mut String, offset: usize| {
+        let entry = address_map.iter().rev().find(|entry| {
+            entry.code_offset <= offset && offset < entry.code_offset + entry.code_len.max(1)
+        });
+        if let Some(entry) = entry {
+            if annotated != Some(entry.srcloc) && !entry.srcloc.is_default() {
+                let _ = writeln!(out, ";; @{:06x}", entry.srcloc.bits());
+            }
+            annotated = Some(entry.srcloc);
+        }
+    };
+
+    #[cfg(feature = "disassemble")]
+    if architecture == Architecture::X86_64 {
+        use iced_x86::{Decoder, DecoderOptions, Formatter, IntelFormatter};
+
+        let mut decoder = Decoder::with_ip(64, code, 0, DecoderOptions::NONE);
+        let mut formatter = IntelFormatter::new();
+        let mut text = String::new();
+        for instruction in &mut decoder {
+            text.clear();
+            formatter.format(&instruction, &mut text);
+            let start = instruction.ip() as usize;
+            annotate(&mut out, start);
+            let bytes = hex(&code[start..start + instruction.len()]);
+            let _ = writeln!(out, "{start:8x}:  {bytes:<30} {text}");
+        }
+        return out;
+    }
+
+    for (line, bytes) in code.chunks(16).enumerate() {
+        annotate(&mut out, line * 16);
+        let _ = writeln!(out, "{:8x}:  {}", line * 16, hex(bytes));
+    }
+    out
+}
+
+/// Whether the function at `local_index` matches any of `filters`, or
+/// there aren't any.
+pub(crate) fn matches_filters(
+    filters: &[DumpFilter],
+    module: &ModuleInfo,
+    local_index: LocalFunctionIndex,
+) -> bool {
+    if filters.is_empty() {
+        return true;
+    }
+    let index = module.func_index(local_index);
+    let name = module.function_names.get(&index);
+    filters.iter().any(|filter| match filter {
+        DumpFilter::Index(wanted) => index.index() == *wanted as usize,
+        DumpFilter::Name(wanted) => name == Some(wanted),
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
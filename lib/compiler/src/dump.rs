@@ -0,0 +1,254 @@
+//! Dumping what the compilers generate for each function, to help debug
+//! miscompilations (see [`CompilerConfig::enable_dump`]).
+//!
+//! Each dumped function gets these files in the dump directory, named
+//! after its index in the module and its name:
+//!
+//! * `<index>-<name>.wat`, the function's WebAssembly, as a module of its
+//!   own with the original module's types (its calls, globals and memory
+//!   accesses still use the original module's indices).
+//! * `<index>-<name>.<ir>`, the compiler's IR after optimization, like
+//!   `.clif` for Cranelift or `.ll` for LLVM. Singlepass doesn't have one.
+//! * `<index>-<name>.s`, the disassembled machine code.
+//!
+//! `index.json` lists the files of every function dumped so far.
+//!
+//! [`CompilerConfig::enable_dump`]: crate::CompilerConfig::enable_dump
+
+use crate::disassemble::matches_filters;
+use crate::{DumpFilter, FunctionBodyData};
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{CompileError, LocalFunctionIndex, ModuleInfo, Type};
+
+/// The name of the file listing the dumped functions.
+pub const DUMP_INDEX_FILE: &str = "index.json";
+
+// `index.json` is rewritten every time more functions are dumped, which can
+// happen on several threads at once when functions are compiled lazily
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Where the compiler dumps each function it compiles, and which ones.
+///
+/// Modules write to the same file names, so use a directory per module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerDump {
+    dir: PathBuf,
+    filters: Vec<DumpFilter>,
+}
+
+impl CompilerDump {
+    /// Dump the functions matching any of `filters` into `dir`, or all of
+    /// them if there are no filters.
+    pub fn new(dir: impl Into<PathBuf>, filters: Vec<DumpFilter>) -> Self {
+        Self {
+            dir: dir.into(),
+            filters,
+        }
+    }
+
+    /// The directory the functions are dumped into.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the function at `local_index` should be dumped.
+    pub fn should_dump(&self, module: &ModuleInfo, local_index: LocalFunctionIndex) -> bool {
+        matches_filters(&self.filters, module, local_index)
+    }
+
+    /// Write the files of the function at `local_index`: its WebAssembly,
+    /// the compiler's IR as `(extension, text)` if it has one, and the
+    /// disassembled machine code.
+    pub fn dump_function(
+        &self,
+        module: &ModuleInfo,
+        local_index: LocalFunctionIndex,
+        body: &FunctionBodyData<'_>,
+        ir: Option<(&str, &str)>,
+        disassembly: &str,
+    ) -> Result<(), CompileError> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| self.io_error(&self.dir, e))?;
+        let stem = file_stem(module, local_index);
+
+        let wat = function_wat(module, local_index, body)?;
+        self.write(&format!("{stem}.wat"), &wat)?;
+        if let Some((extension, text)) = ir {
+            self.write(&format!("{stem}.{extension}"), text)?;
+        }
+        self.write(&format!("{stem}.s"), disassembly)
+    }
+
+    /// Add the dumped functions among `functions` to `index.json`, keeping
+    /// the ones already in there (like other lazily compiled functions).
+    pub fn write_index(
+        &self,
+        module: &ModuleInfo,
+        compiler: &str,
+        functions: impl IntoIterator<Item = LocalFunctionIndex>,
+        ir_extension: Option<&str>,
+    ) -> Result<(), CompileError> {
+        let entries = functions
+            .into_iter()
+            .filter(|local_index| self.should_dump(module, *local_index))
+            .map(|local_index| {
+                let index = module.func_index(local_index);
+                let stem = file_stem(module, local_index);
+                json!({
+                    "index": index.index(),
+                    "name": module.function_names.get(&index),
+                    "wat": format!("{stem}.wat"),
+                    "ir": ir_extension.map(|extension| format!("{stem}.{extension}")),
+                    "disassembly": format!("{stem}.s"),
+                })
+            })
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = INDEX_LOCK.lock().unwrap();
+        let path = self.dir.join(DUMP_INDEX_FILE);
+        let mut functions = match std::fs::read(&path) {
+            Ok(existing) => match serde_json::from_slice::<Value>(&existing) {
+                Ok(Value::Object(mut index)) => match index.remove("functions") {
+                    Some(Value::Array(functions)) => functions,
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            },
+            Err(_) => Vec::new(),
+        };
+        functions.retain(|existing| {
+            !entries
+                .iter()
+                .any(|entry| entry["index"] == existing["index"])
+        });
+        functions.extend(entries);
+        functions.sort_by_key(|entry| entry["index"].as_u64());
+
+        let index = json!({
+            "module": module.name(),
+            "compiler": compiler,
+            "functions": functions,
+        });
+        let contents = serde_json::to_string_pretty(&index).map_err(|e| {
+            CompileError::Codegen(format!("Unable to serialize the dump index: {e}"))
+        })?;
+        self.write(DUMP_INDEX_FILE, &contents)
+    }
+
+    fn write(&self, file_name: &str, contents: &str) -> Result<(), CompileError> {
+        let path = self.dir.join(file_name);
+        std::fs::write(&path, contents).map_err(|e| self.io_error(&path, e))
+    }
+
+    fn io_error(&self, path: &Path, error: std::io::Error) -> CompileError {
+        CompileError::Codegen(format!(
+            "Unable to dump the compiled functions to \"{}\": {error}",
+            path.display()
+        ))
+    }
+}
+
+/// The WAT of `wasm` around the instruction at `offset`, `context` lines
+/// either side of it, with its line marked by a `>`.
+///
+/// Returns `None` if `wasm` can't be printed, or `offset` isn't in it.
+pub fn wat_around(wasm: &[u8], offset: usize, context: usize) -> Option<String> {
+    let mut printer = wasmprinter::Printer::new();
+    let lines: Vec<(Option<usize>, &str)> = printer
+        .offsets_and_lines(wasm)
+        .ok()?
+        .collect();
+    let line = lines
+        .iter()
+        .rposition(|(start, _)| matches!(start, Some(start) if *start <= offset))?;
+
+    let mut snippet = String::new();
+    let start = line.saturating_sub(context);
+    let end = (line + context + 1).min(lines.len());
+    for (i, (_, text)) in lines.iter().enumerate().take(end).skip(start) {
+        let marker = if i == line { '>' } else { ' ' };
+        let _ = writeln!(snippet, "{marker} {}", text.trim_end());
+    }
+    Some(snippet)
+}
+
+/// `<index>-<name>`, with the name made safe to use in a file name.
+fn file_stem(module: &ModuleInfo, local_index: LocalFunctionIndex) -> String {
+    let index = module.func_index(local_index);
+    let name = match module.function_names.get(&index) {
+        Some(name) => name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+        None => "function".to_string(),
+    };
+    format!("{:05}-{name}", index.index())
+}
+
+/// Print the function as a module with just it and the original module's
+/// types.
+fn function_wat(
+    module: &ModuleInfo,
+    local_index: LocalFunctionIndex,
+    body: &FunctionBodyData<'_>,
+) -> Result<String, CompileError> {
+    let mut types = Vec::new();
+    write_u32(&mut types, module.signatures.len());
+    for signature in module.signatures.values() {
+        types.push(0x60);
+        for list in [signature.params(), signature.results()] {
+            write_u32(&mut types, list.len());
+            types.extend(list.iter().map(|ty| value_type(*ty)));
+        }
+    }
+
+    let mut functions = Vec::new();
+    write_u32(&mut functions, 1);
+    write_u32(
+        &mut functions,
+        module.functions[module.func_index(local_index)].index(),
+    );
+
+    let mut code = Vec::new();
+    write_u32(&mut code, 1);
+    write_u32(&mut code, body.data.len());
+    code.extend_from_slice(body.data);
+
+    let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+    for (id, section) in [(1, types), (3, functions), (10, code)] {
+        wasm.push(id);
+        write_u32(&mut wasm, section.len());
+        wasm.extend(section);
+    }
+
+    wasmprinter::print_bytes(&wasm)
+        .map_err(|e| CompileError::Codegen(format!("Unable to print the function as WAT: {e}")))
+}
+
+fn write_u32(out: &mut Vec<u8>, value: usize) {
+    leb128::write::unsigned(out, value as u64).unwrap();
+}
+
+fn value_type(ty: Type) -> u8 {
+    match ty {
+        Type::I32 => 0x7f,
+        Type::I64 => 0x7e,
+        Type::F32 => 0x7d,
+        Type::F64 => 0x7c,
+        Type::V128 => 0x7b,
+        Type::FuncRef => 0x70,
+        Type::ExternRef => 0x6f,
+    }
+}
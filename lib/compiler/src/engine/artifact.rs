@@ -1,14 +1,21 @@
 //! Define `Artifact`, based on `ArtifactBuild`
 //! to allow compiling and instantiating to be done as separate steps.
 
+#[cfg(feature = "compiler")]
+use crate::engine::lazy::LazyFunctions;
 use crate::engine::link::link_module;
+#[cfg(feature = "compiler")]
+use crate::engine::trap::register_lazy_stubs;
 use crate::ArtifactBuild;
 use crate::ArtifactCreate;
 use crate::Features;
+#[cfg(target_os = "linux")]
+use crate::JitDump;
+#[cfg(feature = "static-artifact-create")]
 use crate::ModuleEnvironment;
 use crate::{
     register_frame_info, resolve_imports, FunctionExtent, GlobalFrameInfoRegistration,
-    InstantiationError, Tunables,
+    InstantiationError, PerfMap, Tunables, FRAME_INFO,
 };
 #[cfg(feature = "static-artifact-create")]
 use crate::{Compiler, FunctionBodyData, ModuleTranslationState};
@@ -16,23 +23,33 @@ use crate::{Engine, EngineInner};
 use enumset::EnumSet;
 #[cfg(any(feature = "static-artifact-create", feature = "static-artifact-load"))]
 use std::mem;
-use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 #[cfg(feature = "static-artifact-create")]
-use wasmer_object::{emit_compilation, emit_data, get_object_for_target, Object};
+use wasmer_object::{
+    emit_compilation_with_options, emit_data, get_object_for_target, EmitOptions, Object,
+    RelocationModel,
+};
 #[cfg(any(feature = "static-artifact-create", feature = "static-artifact-load"))]
 use wasmer_types::compilation::symbols::ModuleMetadata;
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 #[cfg(feature = "static-artifact-create")]
 use wasmer_types::CompileModuleInfo;
-use wasmer_types::MetadataHeader;
+#[cfg(feature = "compiler")]
+use wasmer_types::SectionIndex;
 #[cfg(feature = "static-artifact-load")]
 use wasmer_types::SerializableCompilation;
+use wasmer_types::{ArtifactMetadata, MetadataHeader};
 use wasmer_types::{
     CompileError, CpuFeature, DataInitializer, DeserializeError, FunctionIndex, LocalFunctionIndex,
     MemoryIndex, ModuleInfo, OwnedDataInitializer, SignatureIndex, TableIndex, Target,
+    WatSourceMap,
 };
+#[cfg(feature = "compiler")]
+use wasmer_types::{MemoryType, TableType};
 use wasmer_types::{SerializableModule, SerializeError};
+#[cfg(feature = "compiler")]
+use wasmer_vm::SectionBodyPtr;
 use wasmer_vm::{FunctionBodyPtr, MemoryStyle, TableStyle, VMSharedSignatureIndex, VMTrampoline};
 use wasmer_vm::{InstanceAllocator, StoreObjects, TrapHandlerFn, VMConfig, VMExtern, VMInstance};
 
@@ -51,6 +68,20 @@ pub struct AllocatedArtifact {
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    // The engine's perf map and jitdump, which the functions are written to
+    // on the first instantiation (so the module has its final name by then)
+    perfmap: Option<Arc<PerfMap>>,
+    #[cfg(target_os = "linux")]
+    jitdump: Option<Arc<JitDump>>,
+    symbols_registered: AtomicBool,
+    // Where the custom sections were allocated, which lazily compiled
+    // functions get linked against
+    #[cfg(feature = "compiler")]
+    custom_sections: PrimaryMap<SectionIndex, SectionBodyPtr>,
+    // Compiles the functions on their first call, if the module was
+    // compiled lazily
+    #[cfg(feature = "compiler")]
+    lazy: Option<Arc<LazyFunctions>>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -100,29 +131,49 @@ impl Artifact {
         tunables: &dyn Tunables,
     ) -> Result<Self, CompileError> {
         let mut inner_engine = engine.inner_mut();
-        let environ = ModuleEnvironment::new();
-        let translation = environ.translate(data).map_err(CompileError::Wasm)?;
-        let module = translation.module;
-        let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
-            .memories
-            .values()
-            .map(|memory_type| tunables.memory_style(memory_type))
-            .collect();
-        let table_styles: PrimaryMap<TableIndex, TableStyle> = module
-            .tables
-            .values()
-            .map(|table_type| tunables.table_style(table_type))
-            .collect();
 
-        let artifact = ArtifactBuild::new(
-            &mut inner_engine,
-            data,
-            engine.target(),
-            memory_styles,
-            table_styles,
-        )?;
+        // Stubs can only be used on the host
+        let lazy = inner_engine.compiler()?.lazy_compilation() && engine.target().is_native();
+
+        let memory_style = |memory_type: &MemoryType| tunables.memory_style(memory_type);
+        let table_style = |table_type: &TableType| tunables.table_style(table_type);
+        let artifact = if lazy {
+            ArtifactBuild::new_lazy(
+                &mut inner_engine,
+                data,
+                engine.target(),
+                &memory_style,
+                &table_style,
+            )?
+        } else {
+            ArtifactBuild::new(
+                &mut inner_engine,
+                data,
+                engine.target(),
+                &memory_style,
+                &table_style,
+            )?
+        };
 
-        Self::from_parts(&mut inner_engine, artifact, engine.target())
+        // The stubs are registered once the artifact knows it's lazy
+        let mut artifact =
+            Self::from_parts_unregistered(&mut inner_engine, artifact, engine.target())?;
+        drop(inner_engine);
+
+        if lazy {
+            let allocated = artifact.allocated.as_mut().expect("It must be allocated");
+            let lazy_functions = LazyFunctions::new(
+                engine,
+                data,
+                &artifact.artifact,
+                &allocated.finished_functions,
+                std::mem::take(&mut allocated.custom_sections),
+            )?;
+            allocated.lazy = Some(Arc::new(lazy_functions));
+        }
+        artifact.register_frame_info_in(&mut engine.inner_mut());
+
+        Ok(artifact)
     }
 
     /// This indicates if the Artifact is allocated and can be run by the current
@@ -141,6 +192,19 @@ impl Artifact {
         &self.id
     }
 
+    /// The compiler and settings the artifact was compiled with (eg.
+    /// `llvm-O2`), or an empty string if they aren't known, like for
+    /// artifacts not loaded with [`Self::deserialize_checked`].
+    pub fn compiler(&self) -> &str {
+        self.artifact.compiler()
+    }
+
+    /// The module as it was compiled, with the machine code of each
+    /// function before it's loaded in memory.
+    pub fn build(&self) -> &ArtifactBuild {
+        &self.artifact
+    }
+
     /// Compile a data buffer into a `ArtifactBuild`, which may then be instantiated.
     #[cfg(not(feature = "compiler"))]
     pub fn new(_engine: &Engine, _data: &[u8]) -> Result<Self, CompileError> {
@@ -179,12 +243,97 @@ impl Artifact {
         let metadata_slice = Self::get_byte_slice(metadata_slice, 0, metadata_len)?;
 
         let serializable = SerializableModule::deserialize(metadata_slice)?;
-        let artifact = ArtifactBuild::from_serializable(serializable);
+        let artifact =
+            ArtifactBuild::from_serializable(serializable, engine.target().triple().clone());
         let mut inner_engine = engine.inner_mut();
         Self::from_parts(&mut inner_engine, artifact, engine.target())
             .map_err(DeserializeError::Compiler)
     }
 
+    /// Deserialize a serialized artifact, making sure it can be loaded by
+    /// this engine first.
+    ///
+    /// On top of the layout validation done by [`Self::deserialize`], this
+    /// verifies the artifact's checksum, checks that it was serialized by this
+    /// version of Wasmer for the engine's target and CPU features, and
+    /// bounds-checks its internal offsets. All of this happens before any
+    /// executable memory is allocated.
+    ///
+    /// Static artifacts, and artifacts serialized before checksums were
+    /// introduced, are rejected.
+    ///
+    /// # Safety
+    /// This function loads executable code into memory.
+    /// The checks protect against corrupted and incompatible artifacts, but
+    /// the code itself must still be trusted to come from a Wasmer compiler.
+    pub unsafe fn deserialize_checked(
+        engine: &Engine,
+        bytes: &[u8],
+    ) -> Result<Self, DeserializeError> {
+        if !ArtifactBuild::is_deserializable(bytes) {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes are not wasmer-universal".to_string(),
+            ));
+        }
+
+        let bytes = Self::get_byte_slice(bytes, ArtifactBuild::MAGIC_HEADER.len(), bytes.len())?;
+
+        let (version, metadata_len) = MetadataHeader::parse_versioned(bytes)?;
+        if version < MetadataHeader::CURRENT_VERSION {
+            return Err(DeserializeError::Incompatible(
+                "The provided bytes don't have a checksum. They need to be serialized again by a newer version of Wasmer".to_string(),
+            ));
+        }
+
+        let body = &bytes[MetadataHeader::LEN..];
+        if metadata_len > body.len() {
+            return Err(DeserializeError::CorruptSection {
+                section: "module data",
+                reason: format!("expected {metadata_len} bytes, found {}", body.len()),
+            });
+        }
+        let (metadata_slice, trailer) = body.split_at(metadata_len);
+        let metadata = ArtifactMetadata::read(metadata_slice, trailer)?;
+
+        let wasmer_version = env!("CARGO_PKG_VERSION");
+        if metadata.wasmer_version != wasmer_version {
+            return Err(DeserializeError::Incompatible(format!(
+                "The provided bytes were serialized by Wasmer {}, but this is Wasmer {wasmer_version}",
+                metadata.wasmer_version,
+            )));
+        }
+
+        let target = engine.target();
+        let triple = target.triple().to_string();
+        if metadata.triple != triple {
+            return Err(DeserializeError::IncompatibleTarget {
+                artifact: metadata.triple,
+                engine: triple,
+            });
+        }
+
+        let serializable = SerializableModule::deserialize(metadata_slice)?;
+
+        let missing_cpu_features = serializable.cpu_features() - *target.cpu_features();
+        if !missing_cpu_features.is_empty() {
+            let names: Vec<String> = missing_cpu_features
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect();
+            return Err(DeserializeError::IncompatibleTarget {
+                artifact: format!("the {} CPU features", names.join(", ")),
+                engine: format!("{triple} without them"),
+            });
+        }
+
+        serializable.check_bounds()?;
+
+        let artifact = ArtifactBuild::from_serializable(serializable, target.triple().clone())
+            .with_compiler(metadata.compiler);
+        let mut inner_engine = engine.inner_mut();
+        Self::from_parts(&mut inner_engine, artifact, target).map_err(DeserializeError::Compiler)
+    }
+
     /// Deserialize a serialized artifact.
     ///
     /// NOTE: You should prefer [`Self::deserialize`].
@@ -218,7 +367,8 @@ impl Artifact {
         let metadata_slice = Self::get_byte_slice(metadata_slice, 0, metadata_len)?;
 
         let serializable = SerializableModule::deserialize_unchecked(metadata_slice)?;
-        let artifact = ArtifactBuild::from_serializable(serializable);
+        let artifact =
+            ArtifactBuild::from_serializable(serializable, engine.target().triple().clone());
         let mut inner_engine = engine.inner_mut();
         Self::from_parts(&mut inner_engine, artifact, engine.target())
             .map_err(DeserializeError::Compiler)
@@ -229,6 +379,18 @@ impl Artifact {
         engine_inner: &mut EngineInner,
         artifact: ArtifactBuild,
         target: &Target,
+    ) -> Result<Self, CompileError> {
+        let mut artifact = Self::from_parts_unregistered(engine_inner, artifact, target)?;
+        artifact.register_frame_info_in(engine_inner);
+        Ok(artifact)
+    }
+
+    /// Like [`Self::from_parts`], but leaves registering the frame info to
+    /// the caller.
+    fn from_parts_unregistered(
+        engine_inner: &mut EngineInner,
+        artifact: ArtifactBuild,
+        target: &Target,
     ) -> Result<Self, CompileError> {
         if !target.is_native() {
             return Ok(Self {
@@ -305,7 +467,7 @@ impl Artifact {
             finished_dynamic_function_trampolines.into_boxed_slice();
         let signatures = signatures.into_boxed_slice();
 
-        let mut artifact = Self {
+        Ok(Self {
             id: Default::default(),
             artifact,
             allocated: Some(AllocatedArtifact {
@@ -316,21 +478,49 @@ impl Artifact {
                 finished_dynamic_function_trampolines,
                 signatures,
                 finished_function_lengths,
+                perfmap: engine_inner.perfmap().cloned(),
+                #[cfg(target_os = "linux")]
+                jitdump: engine_inner.jitdump().cloned(),
+                symbols_registered: AtomicBool::new(false),
+                #[cfg(feature = "compiler")]
+                custom_sections,
+                #[cfg(feature = "compiler")]
+                lazy: None,
             }),
-        };
+        })
+    }
 
-        artifact.internal_register_frame_info();
-        if let Some(frame_info) = artifact.internal_take_frame_info_registration() {
+    fn register_frame_info_in(&mut self, engine_inner: &mut EngineInner) {
+        if self.allocated.is_none() {
+            return;
+        }
+        self.internal_register_frame_info();
+        if let Some(frame_info) = self.internal_take_frame_info_registration() {
             engine_inner.register_frame_info(frame_info);
         }
-
-        Ok(artifact)
     }
 
     /// Check if the provided bytes look like a serialized `ArtifactBuild`.
     pub fn is_deserializable(bytes: &[u8]) -> bool {
         ArtifactBuild::is_deserializable(bytes)
     }
+
+    /// Make sure frames in future traps, and functions compiled lazily, see
+    /// changes to the `ModuleInfo`.
+    fn refresh_module_info(&self) {
+        if let Some(allocated) = &self.allocated {
+            if let Some(ptr) = allocated.finished_functions.values().next() {
+                FRAME_INFO
+                    .write()
+                    .unwrap()
+                    .update_module_info(**ptr as usize, self.artifact.create_module_info());
+            }
+            #[cfg(feature = "compiler")]
+            if let Some(lazy) = &allocated.lazy {
+                lazy.set_module_info(self.artifact.create_module_info());
+            }
+        }
+    }
 }
 
 impl PartialEq for Artifact {
@@ -351,7 +541,19 @@ impl std::fmt::Debug for Artifact {
 
 impl ArtifactCreate for Artifact {
     fn set_module_info_name(&mut self, name: String) -> bool {
-        self.artifact.set_module_info_name(name)
+        if !self.artifact.set_module_info_name(name) {
+            return false;
+        }
+        self.refresh_module_info();
+        true
+    }
+
+    fn set_module_info_source_map(&mut self, source_map: Arc<WatSourceMap>) -> bool {
+        if !self.artifact.set_module_info_source_map(source_map) {
+            return false;
+        }
+        self.refresh_module_info();
+        true
     }
 
     fn create_module_info(&self) -> Arc<ModuleInfo> {
@@ -383,6 +585,11 @@ impl ArtifactCreate for Artifact {
     }
 
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        #[cfg(feature = "compiler")]
+        if let Some(lazy) = self.allocated.as_ref().and_then(|a| a.lazy.as_ref()) {
+            return lazy.serialize();
+        }
+
         self.artifact.serialize()
     }
 }
@@ -428,6 +635,22 @@ impl Artifact {
             .collect::<PrimaryMap<LocalFunctionIndex, _>>()
             .into_boxed_slice();
 
+        // The finished functions of a lazily compiled module are its stubs
+        #[cfg(feature = "compiler")]
+        let register = if self
+            .allocated
+            .as_ref()
+            .expect("It must be allocated")
+            .lazy
+            .is_some()
+        {
+            register_lazy_stubs
+        } else {
+            register_frame_info
+        };
+        #[cfg(not(feature = "compiler"))]
+        let register = register_frame_info;
+
         let frame_info_registration = &mut self
             .allocated
             .as_mut()
@@ -436,7 +659,7 @@ impl Artifact {
 
         let frame_infos = self.artifact.get_frame_info_ref();
 
-        *frame_info_registration = register_frame_info(
+        *frame_info_registration = register(
             self.artifact.create_module_info(),
             &finished_function_extents,
             frame_infos.clone(),
@@ -475,6 +698,19 @@ impl Artifact {
             .finished_functions
     }
 
+    /// Returns the length in bytes of each function in
+    /// [`Artifact::finished_functions`].
+    ///
+    /// They're 0 for artifacts loaded from a static object, where they
+    /// aren't known.
+    pub fn finished_function_lengths(&self) -> &BoxedSlice<LocalFunctionIndex, usize> {
+        &self
+            .allocated
+            .as_ref()
+            .expect("It must be allocated")
+            .finished_function_lengths
+    }
+
     /// Returns the function call trampolines allocated in memory of this
     /// `Artifact`, ready to be run.
     pub fn finished_function_call_trampolines(&self) -> &BoxedSlice<SignatureIndex, VMTrampoline> {
@@ -512,6 +748,34 @@ impl Artifact {
         Ok(())
     }
 
+    /// Write this artifact's functions to the engine's perf map and
+    /// jitdump, if it has them and they haven't been written yet.
+    fn register_symbols(&self) {
+        let allocated = match &self.allocated {
+            Some(allocated) => allocated,
+            None => return,
+        };
+        if allocated.symbols_registered.swap(true, SeqCst) {
+            return;
+        }
+        // Profiling is best-effort, so don't fail the instantiation
+        if let Some(perfmap) = &allocated.perfmap {
+            let _ = perfmap.register(
+                self.module_info(),
+                &allocated.finished_functions,
+                &allocated.finished_function_lengths,
+            );
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(jitdump) = &allocated.jitdump {
+            let _ = jitdump.register(
+                self.module_info(),
+                &allocated.finished_functions,
+                &allocated.finished_function_lengths,
+            );
+        }
+    }
+
     /// Crate an `Instance` from this `Artifact`.
     ///
     /// # Safety
@@ -528,13 +792,17 @@ impl Artifact {
         // host CPU features.
         let host_cpu_features = CpuFeature::for_host();
         if !host_cpu_features.is_superset(self.cpu_features()) {
-            return Err(InstantiationError::CpuFeature(format!(
-                "{:?}",
-                self.cpu_features().difference(host_cpu_features)
-            )));
+            let names: Vec<String> = self
+                .cpu_features()
+                .difference(host_cpu_features)
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect();
+            return Err(InstantiationError::CpuFeature(names.join(", ")));
         }
 
         self.preinstantiate()?;
+        self.register_symbols();
 
         let module = self.create_module_info();
         let imports = resolve_imports(
@@ -575,7 +843,8 @@ impl Artifact {
             .map_err(InstantiationError::Link)?
             .into_boxed_slice();
 
-        let handle = VMInstance::new(
+        #[allow(unused_mut)]
+        let mut handle = VMInstance::new(
             allocator,
             module,
             context,
@@ -588,6 +857,12 @@ impl Artifact {
             self.signatures().clone(),
         )
         .map_err(InstantiationError::Start)?;
+
+        #[cfg(feature = "compiler")]
+        if let Some(lazy) = self.allocated.as_ref().and_then(|a| a.lazy.as_ref()) {
+            handle.set_lazy_compiler(lazy.clone());
+        }
+
         Ok(handle)
     }
 
@@ -640,7 +915,8 @@ impl Artifact {
         use crate::translator::ModuleMiddlewareChain;
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
-        middlewares.apply_on_module_info(&mut module);
+        middlewares
+            .apply_on_module_info_with_bodies(&mut module, &translation.function_body_inputs);
 
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
@@ -721,6 +997,10 @@ impl Artifact {
     /// be `wasmer_function_{prefix}_{id}` and the object metadata will be addressable
     /// using `WASMER_METADATA_{prefix}_LENGTH` and `WASMER_METADATA_{prefix}_DATA`.
     ///
+    /// When the compiler emits position-independent code (see
+    /// [`Compiler::is_pic`]), so does the object, which can then be linked
+    /// into shared libraries. The code is split into text sections of at
+    /// most [`Compiler::text_section_size`] bytes.
     #[cfg(feature = "static-artifact-create")]
     pub fn generate_object<'data>(
         compiler: &dyn Compiler,
@@ -790,8 +1070,23 @@ impl Artifact {
         emit_data(&mut obj, object_name.as_bytes(), &metadata_binary, 1)
             .map_err(to_compile_error)?;
 
-        emit_compilation(&mut obj, compilation, &symbol_registry, target_triple)
-            .map_err(to_compile_error)?;
+        let model = if compiler.is_pic() {
+            RelocationModel::Pic
+        } else {
+            RelocationModel::Static
+        };
+        let options = EmitOptions {
+            model,
+            max_text_section_size: compiler.text_section_size(),
+        };
+        emit_compilation_with_options(
+            &mut obj,
+            compilation,
+            &symbol_registry,
+            target_triple,
+            &options,
+        )
+        .map_err(to_compile_error)?;
         Ok((
             Arc::try_unwrap(metadata.compile_info.module).unwrap(),
             obj,
@@ -916,12 +1211,15 @@ impl Artifact {
             finished_dynamic_function_trampolines.push(fp);
         }
 
-        let artifact = ArtifactBuild::from_serializable(SerializableModule {
-            compilation: SerializableCompilation::default(),
-            compile_info: metadata.compile_info,
-            data_initializers: metadata.data_initializers,
-            cpu_features: metadata.cpu_features,
-        });
+        let artifact = ArtifactBuild::from_serializable(
+            SerializableModule {
+                compilation: SerializableCompilation::default(),
+                compile_info: metadata.compile_info,
+                data_initializers: metadata.data_initializers,
+                cpu_features: metadata.cpu_features,
+            },
+            engine.target().triple().clone(),
+        );
 
         let finished_function_lengths = finished_functions
             .values()
@@ -942,6 +1240,16 @@ impl Artifact {
                     .into_boxed_slice(),
                 signatures: signatures.into_boxed_slice(),
                 finished_function_lengths,
+                // The function lengths aren't known, so perf map entries
+                // would be useless
+                perfmap: None,
+                #[cfg(target_os = "linux")]
+                jitdump: None,
+                symbols_registered: AtomicBool::new(false),
+                #[cfg(feature = "compiler")]
+                custom_sections: PrimaryMap::new(),
+                #[cfg(feature = "compiler")]
+                lazy: None,
             }),
         })
     }
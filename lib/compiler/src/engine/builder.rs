@@ -1,5 +1,5 @@
 use super::Engine;
-use crate::CompilerConfig;
+use crate::{CompileThreads, CompilerConfig};
 use wasmer_types::{Features, Target};
 
 /// The Builder contents of `Engine`
@@ -46,6 +46,17 @@ impl EngineBuilder {
         self
     }
 
+    /// Set the threads modules are compiled on.
+    ///
+    /// See [`CompilerConfig::set_compile_threads`]. It does nothing for
+    /// headless engines, which don't compile.
+    pub fn set_compile_threads(mut self, threads: CompileThreads) -> Self {
+        if let Some(compiler_config) = &mut self.compiler_config {
+            compiler_config.set_compile_threads(threads);
+        }
+        self
+    }
+
     /// Build the `Engine` for this configuration
     #[cfg(feature = "compiler")]
     pub fn engine(self) -> Engine {
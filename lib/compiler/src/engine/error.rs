@@ -42,7 +42,7 @@ pub enum InstantiationError {
 
     /// The module was compiled with a CPU feature that is not available on
     /// the current host.
-    #[error("module compiled with CPU feature that is missing from host")]
+    #[error("module compiled with CPU features that are missing from the host: {0}")]
     CpuFeature(String),
 
     /// A runtime error occured while invoking the start function
@@ -9,8 +9,12 @@ use crate::BaseTunables;
 use crate::CodeMemory;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::GlobalFrameInfoRegistration;
+#[cfg(target_os = "linux")]
+use crate::JitDump;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::PerfMap;
 #[cfg(feature = "compiler")]
-use crate::{Compiler, CompilerConfig};
+use crate::{Compiler, CompilerConfig, ModuleMiddlewareChain};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::{FunctionExtent, Tunables};
 #[cfg(not(target_arch = "wasm32"))]
@@ -42,7 +46,12 @@ pub struct Engine {
     engine_id: EngineId,
     #[cfg(not(target_arch = "wasm32"))]
     tunables: Arc<dyn Tunables + Send + Sync>,
+    #[cfg(not(target_arch = "wasm32"))]
+    default_stack_limit: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    stack_guard_size: Option<usize>,
     name: String,
+    deterministic_id: String,
 }
 
 impl Engine {
@@ -57,6 +66,11 @@ impl Engine {
         let tunables = BaseTunables::for_target(&target);
         let compiler = compiler_config.compiler();
         let name = format!("engine-{}", compiler.name());
+        let deterministic_id = format!(
+            "engine-{}{}",
+            compiler.deterministic_id(),
+            compiler.get_middlewares().deterministic_id_suffix()
+        );
         Self {
             inner: Arc::new(Mutex::new(EngineInner {
                 compiler: Some(compiler),
@@ -65,12 +79,21 @@ impl Engine {
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                perfmap: None,
+                #[cfg(target_os = "linux")]
+                jitdump: None,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(not(target_arch = "wasm32"))]
+            default_stack_limit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            stack_guard_size: None,
             name,
+            deterministic_id,
         }
     }
 
@@ -89,12 +112,13 @@ impl Engine {
     }
 
     /// Returns the deterministic id of this engine
+    ///
+    /// Unlike the name, it also changes with the compiler settings which
+    /// affect the generated code (eg. LLVM with optimizations vs LLVM
+    /// without optimizations), so it can be used to key caches of
+    /// compiled modules.
     pub fn deterministic_id(&self) -> &str {
-        // TODO: add a `deterministic_id` to the Compiler, so two
-        // compilers can actually serialize into a different deterministic_id
-        // if their configuration is different (eg. LLVM with optimizations vs LLVM
-        // without optimizations)
-        self.name.as_str()
+        self.deterministic_id.as_str()
     }
 
     /// Create a headless `Engine`
@@ -124,12 +148,21 @@ impl Engine {
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
                 signatures: SignatureRegistry::new(),
+                #[cfg(not(target_arch = "wasm32"))]
+                perfmap: None,
+                #[cfg(target_os = "linux")]
+                jitdump: None,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
             #[cfg(not(target_arch = "wasm32"))]
             tunables: Arc::new(tunables),
+            #[cfg(not(target_arch = "wasm32"))]
+            default_stack_limit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            stack_guard_size: None,
             name: "engine-headless".to_string(),
+            deterministic_id: "engine-headless".to_string(),
         }
     }
 
@@ -162,6 +195,37 @@ impl Engine {
         compiler.signatures().lookup(sig)
     }
 
+    /// The WebAssembly proposals modules are compiled with.
+    ///
+    /// Headless engines can't compile anything, so they report the default
+    /// set of features.
+    pub fn features(&self) -> Features {
+        #[cfg(feature = "compiler")]
+        {
+            self.inner().features().clone()
+        }
+        #[cfg(not(feature = "compiler"))]
+        {
+            Features::default()
+        }
+    }
+
+    /// Whether modules are compiled lazily, one function at a time as
+    /// they're first called (see [`CompilerConfig::enable_lazy_compilation`]).
+    pub fn lazy_compilation(&self) -> bool {
+        #[cfg(feature = "compiler")]
+        {
+            self.inner()
+                .compiler()
+                .map(|compiler| compiler.lazy_compilation())
+                .unwrap_or(false)
+        }
+        #[cfg(not(feature = "compiler"))]
+        {
+            false
+        }
+    }
+
     /// Validates a WebAssembly module
     #[cfg(feature = "compiler")]
     pub fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
@@ -217,6 +281,20 @@ impl Engine {
         Ok(Arc::new(Artifact::deserialize(self, bytes)?))
     }
 
+    /// Deserializes a WebAssembly module which was previously serialized with
+    /// [`Module::serialize`], checking its integrity and compatibility first.
+    ///
+    /// # Safety
+    ///
+    /// See [`Artifact::deserialize_checked`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn deserialize_checked(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Arc<Artifact>, DeserializeError> {
+        Ok(Arc::new(Artifact::deserialize_checked(self, bytes)?))
+    }
+
     /// Deserializes a WebAssembly module from a path.
     ///
     /// # Safety
@@ -270,6 +348,68 @@ impl Engine {
     pub fn tunables(&self) -> &dyn Tunables {
         self.tunables.as_ref()
     }
+
+    /// Set the stack size, in bytes, that calls into stores using this
+    /// engine get unless the store sets its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_default_stack_limit(&mut self, bytes: usize) {
+        self.default_stack_limit = Some(bytes);
+    }
+
+    /// The stack size set with [`Engine::set_default_stack_limit`], if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn default_stack_limit(&self) -> Option<usize> {
+        self.default_stack_limit
+    }
+
+    /// Set the size, in bytes, of the extra guard below the stacks calls
+    /// into stores using this engine run on, instead of the process-wide
+    /// one set with [`wasmer_vm::set_stack_guard_size`].
+    ///
+    /// It's rounded up to whole pages. Functions whose frames are bigger
+    /// than the guard only overflow into it if they probe their frames (see
+    /// [`crate::StackProbes`]) in steps no bigger than it. It has no effect
+    /// on platforms without `mprotect`, where stacks only have the guard
+    /// page they always have.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_stack_guard_size(&mut self, bytes: usize) {
+        self.stack_guard_size = Some(bytes);
+    }
+
+    /// The guard size set with [`Engine::set_stack_guard_size`], if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stack_guard_size(&self) -> Option<usize> {
+        self.stack_guard_size
+    }
+
+    /// Write the symbols of every function compiled by this engine to the
+    /// [perf map][PerfMap] at `path` (usually [`PerfMap::default_path()`]),
+    /// as `<module>::<function>`.
+    ///
+    /// A module's entries are written when it's first instantiated, so they
+    /// use the name given to it with `Module::set_name` before then. Lazily
+    /// compiled functions get another entry once they're compiled.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enable_perfmap(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let perfmap = PerfMap::create(path)?;
+        self.inner_mut().perfmap = Some(Arc::new(perfmap));
+        Ok(())
+    }
+
+    /// Write every function compiled by this engine, along with its machine
+    /// code, to the [jitdump][JitDump] at `path` (usually
+    /// [`JitDump::default_path()`]), as `<module>::<function>`.
+    ///
+    /// Like with [`Engine::enable_perfmap`], a module's functions are written
+    /// when it's first instantiated, and lazily compiled functions when
+    /// they're compiled. The dump is finished off once the engine and all
+    /// of its modules are dropped.
+    #[cfg(target_os = "linux")]
+    pub fn enable_jitdump(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let jitdump = JitDump::create(path)?;
+        self.inner_mut().jitdump = Some(Arc::new(jitdump));
+        Ok(())
+    }
 }
 
 impl std::fmt::Debug for Engine {
@@ -298,6 +438,13 @@ pub struct EngineInner {
     /// performantly.
     #[cfg(not(target_arch = "wasm32"))]
     signatures: SignatureRegistry,
+    /// Where to write the symbols of compiled functions for profilers, if
+    /// anywhere.
+    #[cfg(not(target_arch = "wasm32"))]
+    perfmap: Option<Arc<PerfMap>>,
+    /// Where to write compiled functions for `perf inject --jit`, if anywhere.
+    #[cfg(target_os = "linux")]
+    jitdump: Option<Arc<JitDump>>,
 }
 
 impl EngineInner {
@@ -445,6 +592,18 @@ impl EngineInner {
         &self.signatures
     }
 
+    /// The perf map set with [`Engine::enable_perfmap`], if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn perfmap(&self) -> Option<&Arc<PerfMap>> {
+        self.perfmap.as_ref()
+    }
+
+    /// The jitdump set with [`Engine::enable_jitdump`], if any.
+    #[cfg(target_os = "linux")]
+    pub fn jitdump(&self) -> Option<&Arc<JitDump>> {
+        self.jitdump.as_ref()
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     /// Register the frame info for the code memory
     pub(crate) fn register_frame_info(&mut self, frame_info: GlobalFrameInfoRegistration) {
@@ -0,0 +1,196 @@
+//! Writing [jitdump][jitdump] files, so Linux `perf` can annotate the
+//! instructions of JIT-compiled WebAssembly code and not just name its
+//! functions like a [`PerfMap`][crate::PerfMap] does.
+//!
+//! Record with `perf record -k mono`, then merge the dump into the profile
+//! with `perf inject --jit` before running `perf report`.
+//!
+//! [jitdump]: https://github.com/torvalds/linux/blob/master/tools/perf/Documentation/jitdump-specification.txt
+
+use crate::engine::perfmap::symbol_name;
+use memmap2::{Mmap, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasmer_types::entity::BoxedSlice;
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::FunctionBodyPtr;
+
+/// "JiTD", written in the native byte order so readers can tell it apart.
+const MAGIC: u32 = 0x4A69_5444;
+const VERSION: u32 = 1;
+const HEADER_SIZE: u32 = 40;
+const RECORD_HEADER_SIZE: u32 = 16;
+
+const JIT_CODE_LOAD: u32 = 0;
+const JIT_CODE_CLOSE: u32 = 3;
+
+/// The ELF machine the dumped code is for.
+const ELF_MACHINE: u32 = if cfg!(target_arch = "x86_64") {
+    62
+} else if cfg!(target_arch = "aarch64") {
+    183
+} else if cfg!(target_arch = "riscv64") {
+    243
+} else {
+    0
+};
+
+/// A jitdump file which compiled functions, including their machine code,
+/// get appended to.
+#[derive(Debug)]
+pub struct JitDump {
+    state: Mutex<State>,
+    path: PathBuf,
+    // `perf` finds the dump through the executable mapping of it showing
+    // up in the profile
+    _marker: Mmap,
+}
+
+#[derive(Debug)]
+struct State {
+    file: File,
+    /// A unique index for each function written.
+    next_code_index: u64,
+}
+
+impl JitDump {
+    /// The path `perf inject --jit` expects the current process' dump at,
+    /// `/tmp/jit-<pid>.dump`.
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(format!("/tmp/jit-{}.dump", std::process::id()))
+    }
+
+    /// Create a jitdump at `path`, replacing whatever was there.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(&MAGIC.to_ne_bytes());
+        header.extend_from_slice(&VERSION.to_ne_bytes());
+        header.extend_from_slice(&HEADER_SIZE.to_ne_bytes());
+        header.extend_from_slice(&ELF_MACHINE.to_ne_bytes());
+        header.extend_from_slice(&0_u32.to_ne_bytes());
+        header.extend_from_slice(&std::process::id().to_ne_bytes());
+        header.extend_from_slice(&timestamp().to_ne_bytes());
+        // No flags
+        header.extend_from_slice(&0_u64.to_ne_bytes());
+        file.write_all(&header)?;
+        file.flush()?;
+
+        let marker = unsafe {
+            MmapOptions::new()
+                .len(region::page::size())
+                .map_exec(&file)?
+        };
+
+        Ok(Self {
+            state: Mutex::new(State {
+                file,
+                next_code_index: 0,
+            }),
+            path: path.to_path_buf(),
+            _marker: marker,
+        })
+    }
+
+    /// The file this jitdump is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write a record for each function in `finished_functions`, with their
+    /// code, naming them `<module>::<function>`.
+    pub(crate) fn register(
+        &self,
+        module: &ModuleInfo,
+        finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
+        finished_function_lengths: &BoxedSlice<LocalFunctionIndex, usize>,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for (local_index, ptr) in finished_functions.iter() {
+            let name = symbol_name(module, local_index);
+            state.write_code_load(
+                &name,
+                **ptr as usize,
+                finished_function_lengths[local_index],
+            )?;
+        }
+        state.file.flush()
+    }
+
+    /// Write a record for a single function, compiled on its own.
+    pub(crate) fn register_function(
+        &self,
+        name: &str,
+        address: usize,
+        length: usize,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.write_code_load(name, address, length)?;
+        state.file.flush()
+    }
+}
+
+impl State {
+    fn write_code_load(&mut self, name: &str, address: usize, length: usize) -> io::Result<()> {
+        // SAFETY: the code has been published, so it's mapped and readable
+        let code = unsafe { std::slice::from_raw_parts(address as *const u8, length) };
+
+        let total_size = RECORD_HEADER_SIZE as usize + 8 + 4 * 8 + name.len() + 1 + code.len();
+        let total_size = u32::try_from(total_size)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "function too big"))?;
+
+        let mut record = Vec::with_capacity(total_size as usize);
+        record.extend_from_slice(&JIT_CODE_LOAD.to_ne_bytes());
+        record.extend_from_slice(&total_size.to_ne_bytes());
+        record.extend_from_slice(&timestamp().to_ne_bytes());
+        record.extend_from_slice(&std::process::id().to_ne_bytes());
+        record.extend_from_slice(&thread_id().to_ne_bytes());
+        // The code is never relocated after being dumped, so its virtual
+        // address and the address of the code are the same
+        record.extend_from_slice(&(address as u64).to_ne_bytes());
+        record.extend_from_slice(&(address as u64).to_ne_bytes());
+        record.extend_from_slice(&(length as u64).to_ne_bytes());
+        record.extend_from_slice(&self.next_code_index.to_ne_bytes());
+        record.extend_from_slice(name.as_bytes());
+        record.push(0);
+        record.extend_from_slice(code);
+
+        self.next_code_index += 1;
+        self.file.write_all(&record)
+    }
+}
+
+impl Drop for JitDump {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE as usize);
+        record.extend_from_slice(&JIT_CODE_CLOSE.to_ne_bytes());
+        record.extend_from_slice(&RECORD_HEADER_SIZE.to_ne_bytes());
+        record.extend_from_slice(&timestamp().to_ne_bytes());
+        let _ = state.file.write_all(&record);
+        let _ = state.file.flush();
+    }
+}
+
+/// The time on the clock `perf record -k mono` uses, in nanoseconds.
+fn timestamp() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+fn thread_id() -> u32 {
+    unsafe { libc::syscall(libc::SYS_gettid) as u32 }
+}
@@ -0,0 +1,250 @@
+//! Lazy compilation, where each function of a module is only compiled the
+//! first time it's called (see [`crate::CompilerConfig::enable_lazy_compilation`]).
+//!
+//! The module is compiled with [`crate::Compiler::compile_module_lazily`],
+//! so every function starts out as a stub. [`LazyFunctions`] keeps what's
+//! needed to compile the real functions, and is handed to each instance as
+//! its [`LazyFunctionCompiler`].
+//!
+//! Lazily compiled code is allocated in its own [`crate::CodeMemory`], with
+//! its own unwind information, and can be far away from the rest of the
+//! module, so it calls other functions and libcalls through absolute
+//! addresses. Direct calls to a function keep
+//! going through its stub, which jumps straight to the compiled body once
+//! there is one, while calls through tables and exports skip the stub
+//! entirely.
+
+use super::link::link_function;
+use super::perfmap::symbol_name;
+use super::trap::register_lazy_function;
+use crate::{
+    ArtifactBuild, ArtifactCreate, Engine, FunctionBodyData, FunctionExtent, ModuleEnvironment,
+    ModuleTranslationState,
+};
+use std::ops::Range;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
+use wasmer_types::{
+    CompileError, CompileModuleInfo, LazyCompiledFunction, LocalFunctionIndex, ModuleInfo,
+    SectionIndex, SerializeError,
+};
+use wasmer_vm::{FunctionBodyPtr, LazyFunctionCompiler, SectionBodyPtr, VMFunctionBody};
+
+/// Compiles the functions of a lazily compiled module when they're first
+/// called.
+pub(crate) struct LazyFunctions {
+    engine: Engine,
+    /// The original WebAssembly module.
+    wasm: Vec<u8>,
+    /// Where each function's body is in `wasm`, and its offset for
+    /// [`FunctionBodyData::module_offset`].
+    function_bodies: PrimaryMap<LocalFunctionIndex, (Range<usize>, usize)>,
+    module_translation_state: ModuleTranslationState,
+    compile_info: CompileModuleInfo,
+    /// The module used for frame info, which is kept up to date with
+    /// [`ArtifactCreate::set_module_info_name`].
+    module_info: Mutex<Arc<ModuleInfo>>,
+    stubs: PrimaryMap<LocalFunctionIndex, FunctionBodyPtr>,
+    custom_sections: PrimaryMap<SectionIndex, SectionBodyPtr>,
+    libcall_trampolines: SectionIndex,
+    libcall_trampoline_len: usize,
+    /// The body of each function, or null if it hasn't been compiled yet.
+    compiled: PrimaryMap<LocalFunctionIndex, AtomicPtr<VMFunctionBody>>,
+    /// Held while a function is compiled, so it only happens once.
+    locks: PrimaryMap<LocalFunctionIndex, Mutex<()>>,
+}
+
+impl LazyFunctions {
+    /// Prepare to lazily compile the functions of `artifact`, which was
+    /// compiled from `wasm` by [`ArtifactBuild::new_lazy`]. `stubs` and
+    /// `custom_sections` are where its stubs and sections were allocated.
+    pub(crate) fn new(
+        engine: &Engine,
+        wasm: &[u8],
+        artifact: &ArtifactBuild,
+        stubs: &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
+        custom_sections: PrimaryMap<SectionIndex, SectionBodyPtr>,
+    ) -> Result<Self, CompileError> {
+        let translation = ModuleEnvironment::new()
+            .translate(wasm)
+            .map_err(CompileError::Wasm)?;
+        let function_bodies = translation
+            .function_body_inputs
+            .values()
+            .map(|body| {
+                let start = body.data.as_ptr() as usize - wasm.as_ptr() as usize;
+                (start..start + body.data.len(), body.module_offset)
+            })
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+        let module_translation_state = translation
+            .module_translation_state
+            .expect("always set by a successful translation");
+
+        let compile_info = CompileModuleInfo {
+            module: artifact.create_module_info(),
+            features: artifact.features().clone(),
+            memory_styles: artifact.memory_styles().clone(),
+            table_styles: artifact.table_styles().clone(),
+        };
+        let stubs = stubs
+            .values()
+            .copied()
+            .collect::<PrimaryMap<LocalFunctionIndex, _>>();
+
+        Ok(Self {
+            engine: engine.cloned(),
+            wasm: wasm.to_vec(),
+            compiled: function_bodies
+                .keys()
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+            locks: function_bodies.keys().map(|_| Mutex::new(())).collect(),
+            function_bodies,
+            module_translation_state,
+            module_info: Mutex::new(compile_info.module.clone()),
+            compile_info,
+            stubs,
+            custom_sections,
+            libcall_trampolines: artifact.get_libcall_trampolines(),
+            libcall_trampoline_len: artifact.get_libcall_trampoline_len(),
+        })
+    }
+
+    /// Use `module` for the frames of functions compiled from now on.
+    pub(crate) fn set_module_info(&self, module: Arc<ModuleInfo>) {
+        *self.module_info.lock().unwrap() = module;
+    }
+
+    /// Serialize the module.
+    ///
+    /// Stubs are useless without a [`LazyFunctions`], so the whole module is
+    /// compiled up front instead.
+    pub(crate) fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        let mut artifact = ArtifactBuild::new(
+            &mut self.engine.inner_mut(),
+            &self.wasm,
+            self.engine.target(),
+            &|memory_type| self.engine.tunables().memory_style(memory_type),
+            &|table_type| self.engine.tunables().table_style(table_type),
+        )
+        .map_err(|e| SerializeError::Generic(e.to_string()))?;
+
+        if let Some(name) = &self.module_info.lock().unwrap().name {
+            artifact.set_module_info_name(name.clone());
+        }
+
+        artifact.serialize()
+    }
+
+    /// Where calls to each function should go: the compiled body if there is
+    /// one, otherwise the stub.
+    fn call_targets(&self) -> PrimaryMap<LocalFunctionIndex, FunctionExtent> {
+        self.stubs
+            .iter()
+            .map(|(index, stub)| FunctionExtent {
+                ptr: self.compiled(index).unwrap_or(*stub),
+                // Only the addresses are needed for linking
+                length: 0,
+            })
+            .collect()
+    }
+}
+
+impl LazyFunctionCompiler for LazyFunctions {
+    fn compiled(&self, index: LocalFunctionIndex) -> Option<FunctionBodyPtr> {
+        let body = self.compiled[index].load(Ordering::Acquire);
+        if body.is_null() {
+            None
+        } else {
+            Some(FunctionBodyPtr(body))
+        }
+    }
+
+    fn compile(&self, index: LocalFunctionIndex) -> Result<FunctionBodyPtr, CompileError> {
+        let _guard = self.locks[index].lock().unwrap();
+        // Another thread may have compiled it while we were waiting
+        if let Some(body) = self.compiled(index) {
+            return Ok(body);
+        }
+
+        let (range, module_offset) = self.function_bodies[index].clone();
+        let function_body = FunctionBodyData {
+            data: &self.wasm[range],
+            module_offset,
+        };
+
+        let mut engine = self.engine.inner_mut();
+        let LazyCompiledFunction { function, eh_frame } = engine.compiler()?.compile_function(
+            self.engine.target(),
+            &self.compile_info,
+            &self.module_translation_state,
+            index,
+            &function_body,
+        )?;
+
+        let mut bodies = PrimaryMap::with_capacity(1);
+        bodies.push(function.body);
+        let mut sections = PrimaryMap::new();
+        let eh_frame_index = eh_frame.map(|eh_frame| sections.push(eh_frame));
+        let (allocated, _, _, allocated_sections) = engine.allocate(
+            &self.compile_info.module,
+            &bodies,
+            &PrimaryMap::new(),
+            &PrimaryMap::new(),
+            &sections,
+        )?;
+        let extent = &allocated[LocalFunctionIndex::new(0)];
+
+        let mut targets = self.call_targets();
+        // Recursive calls can go straight to the new body
+        targets[index] = FunctionExtent {
+            ptr: extent.ptr,
+            length: extent.length,
+        };
+        link_function(
+            *extent.ptr as usize,
+            &function.relocations,
+            &targets,
+            &self.custom_sections,
+            self.libcall_trampolines,
+            self.libcall_trampoline_len,
+        );
+
+        // The unwind info points at the function, like its own calls do
+        let eh_frame = eh_frame_index.map(|section_index| {
+            let eh_frame = &sections[section_index];
+            let body = allocated_sections[section_index];
+            link_function(
+                *body as usize,
+                &eh_frame.relocations,
+                &targets,
+                &self.custom_sections,
+                self.libcall_trampolines,
+                self.libcall_trampoline_len,
+            );
+            unsafe { std::slice::from_raw_parts(*body, eh_frame.bytes.len()) }
+        });
+
+        engine.publish_compiled_code();
+        engine.publish_eh_frame(eh_frame)?;
+
+        let module = self.module_info.lock().unwrap().clone();
+        // Profiling is best-effort, so don't fail the call
+        if let Some(perfmap) = engine.perfmap() {
+            let name = symbol_name(&module, index);
+            let _ = perfmap.register_function(&name, *extent.ptr as usize, extent.length);
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(jitdump) = engine.jitdump() {
+            let name = symbol_name(&module, index);
+            let _ = jitdump.register_function(&name, *extent.ptr as usize, extent.length);
+        }
+        let registration = register_lazy_function(module, index, extent, function.frame_info);
+        engine.register_frame_info(registration);
+
+        self.compiled[index].store(extent.ptr.0 as *mut VMFunctionBody, Ordering::Release);
+        Ok(extent.ptr)
+    }
+}
@@ -121,6 +121,41 @@ fn apply_relocation(
                 | read_unaligned(reloc_address as *mut u64);
             write_unaligned(reloc_address as *mut u64, reloc_delta);
         },
+        RelocationKind::Arm64AdrPrelPgHi21 => unsafe {
+            let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
+            if (reloc_delta as i64).abs() >= 0x1_0000_0000 {
+                panic!(
+                    "Relocation to big for {:?} for {:?} with {:x}, current val {:x}",
+                    r.kind,
+                    r.reloc_target,
+                    reloc_delta,
+                    read_unaligned(reloc_address as *mut u32)
+                )
+            }
+            // The page delta goes in immlo (bits 29-30) and immhi (bits 5-23)
+            let pages = (reloc_delta >> 12) as u32;
+            let immlo = (pages & 0x3) << 29;
+            let immhi = ((pages >> 2) & 0x7_ffff) << 5;
+            let reloc_delta =
+                (read_unaligned(reloc_address as *mut u32) & 0x9f00_001f) | immlo | immhi;
+            write_unaligned(reloc_address as *mut u32, reloc_delta);
+        },
+        RelocationKind::Arm64AddAbsLo12Nc
+        | RelocationKind::Arm64Ldst32AbsLo12Nc
+        | RelocationKind::Arm64Ldst64AbsLo12Nc
+        | RelocationKind::Arm64Ldst128AbsLo12Nc => unsafe {
+            let (reloc_address, reloc_abs) = r.for_address(body, target_func_address as u64);
+            // Loads and stores scale their offset by the size of the access
+            let shift = match r.kind {
+                RelocationKind::Arm64Ldst32AbsLo12Nc => 2,
+                RelocationKind::Arm64Ldst64AbsLo12Nc => 3,
+                RelocationKind::Arm64Ldst128AbsLo12Nc => 4,
+                _ => 0,
+            };
+            let imm12 = (((reloc_abs & 0xfff) >> shift) as u32) << 10;
+            let reloc_delta = (read_unaligned(reloc_address as *mut u32) & 0xffc0_03ff) | imm12;
+            write_unaligned(reloc_address as *mut u32, reloc_delta);
+        },
         kind => panic!(
             "Relocation kind unsupported in the current architecture {}",
             kind
@@ -170,3 +205,29 @@ pub fn link_module(
         }
     }
 }
+
+/// Links a single function that was compiled lazily, after the rest of its
+/// module was linked by [`link_module`].
+#[cfg(feature = "compiler")]
+pub(crate) fn link_function(
+    body: usize,
+    relocations: &[Relocation],
+    allocated_functions: &PrimaryMap<LocalFunctionIndex, FunctionExtent>,
+    allocated_sections: &PrimaryMap<SectionIndex, SectionBodyPtr>,
+    libcall_trampolines: SectionIndex,
+    trampoline_len: usize,
+) {
+    let mut riscv_pcrel_hi20s: HashMap<usize, u32> = HashMap::new();
+
+    for r in relocations {
+        apply_relocation(
+            body,
+            r,
+            allocated_functions,
+            allocated_sections,
+            libcall_trampolines,
+            trampoline_len,
+            &mut riscv_pcrel_hi20s,
+        );
+    }
+}
@@ -19,10 +19,19 @@ mod code_memory;
 #[cfg(feature = "translator")]
 mod inner;
 #[cfg(feature = "translator")]
+#[cfg(target_os = "linux")]
+mod jitdump;
+#[cfg(feature = "compiler")]
+#[cfg(not(target_arch = "wasm32"))]
+mod lazy;
+#[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 mod link;
 #[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
+mod perfmap;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
 mod unwind;
 
 pub use self::error::{InstantiationError, LinkError};
@@ -44,5 +53,11 @@ pub use self::code_memory::CodeMemory;
 #[cfg(feature = "translator")]
 pub use self::inner::{Engine, EngineInner};
 #[cfg(feature = "translator")]
+#[cfg(target_os = "linux")]
+pub use self::jitdump::JitDump;
+#[cfg(feature = "translator")]
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::link::link_module;
+#[cfg(feature = "translator")]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::perfmap::PerfMap;
@@ -0,0 +1,93 @@
+//! Writing [perf map][perf-map] files, so Linux `perf` (and other profilers
+//! understanding the format) can symbolicate JIT-compiled WebAssembly code.
+//!
+//! Each compiled function is written on its own line as
+//! `<start address> <size> <module>::<function>`, with the addresses in hex.
+//!
+//! [perf-map]: https://github.com/torvalds/linux/blob/master/tools/perf/Documentation/jit-interface.txt
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasmer_types::entity::{BoxedSlice, EntityRef};
+use wasmer_types::{LocalFunctionIndex, ModuleInfo};
+use wasmer_vm::FunctionBodyPtr;
+
+/// A perf map file which compiled functions get appended to.
+#[derive(Debug)]
+pub struct PerfMap {
+    file: Mutex<File>,
+    path: PathBuf,
+}
+
+impl PerfMap {
+    /// The path `perf` looks for the current process' map at,
+    /// `/tmp/perf-<pid>.map`.
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(format!("/tmp/perf-{}.map", std::process::id()))
+    }
+
+    /// Open the perf map at `path`, creating it if needed. New entries are
+    /// appended to any existing contents.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The file this perf map is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write an entry for each function in `finished_functions`, naming them
+    /// `<module>::<function>`.
+    pub(crate) fn register(
+        &self,
+        module: &ModuleInfo,
+        finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
+        finished_function_lengths: &BoxedSlice<LocalFunctionIndex, usize>,
+    ) -> io::Result<()> {
+        // Format everything up front so entries from different modules never
+        // get interleaved
+        let mut entries = String::new();
+        for (local_index, ptr) in finished_functions.iter() {
+            entries.push_str(&format!(
+                "{:x} {:x} {}\n",
+                **ptr as usize,
+                finished_function_lengths[local_index],
+                symbol_name(module, local_index)
+            ));
+        }
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(entries.as_bytes())?;
+        file.flush()
+    }
+
+    /// Write an entry for a single function, compiled on its own.
+    pub(crate) fn register_function(
+        &self,
+        name: &str,
+        address: usize,
+        length: usize,
+    ) -> io::Result<()> {
+        let entry = format!("{address:x} {length:x} {name}\n");
+        let mut file = self.file.lock().unwrap();
+        file.write_all(entry.as_bytes())?;
+        file.flush()
+    }
+}
+
+/// The name profilers show for a compiled function, `<module>::<function>`.
+pub(crate) fn symbol_name(module: &ModuleInfo, local_index: LocalFunctionIndex) -> String {
+    let func_index = module.func_index(local_index);
+    match module.function_names.get(&func_index) {
+        Some(name) => format!("{}::{}", module.name(), name),
+        None => format!("{}::function[{}]", module.name(), func_index.index()),
+    }
+}
@@ -32,6 +32,7 @@ fn get_extern_from_import(module: &ModuleInfo, import_index: &ImportIndex) -> Ex
             let global = module.globals[*index];
             ExternType::Global(global)
         }
+        ImportIndex::Tag(index) => ExternType::Tag(module.tag_type(*index)),
     }
 }
 
@@ -45,6 +46,7 @@ fn get_extern_type(context: &StoreObjects, extern_: &VMExtern) -> ExternType {
             let global = g.get(context).ty();
             ExternType::Global(*global)
         }
+        VMExtern::Tag(t) => ExternType::Tag(t.get(context).ty().clone()),
     }
 }
 
@@ -73,6 +75,7 @@ pub fn resolve_imports(
     let mut table_imports = PrimaryMap::with_capacity(module.num_imported_tables);
     let mut memory_imports = PrimaryMap::with_capacity(module.num_imported_memories);
     let mut global_imports = PrimaryMap::with_capacity(module.num_imported_globals);
+    let mut tag_imports = PrimaryMap::with_capacity(module.num_imported_tags);
 
     for (
         wasmer_types::ImportKey {
@@ -189,6 +192,10 @@ pub fn resolve_imports(
                     handle,
                 });
             }
+
+            VMExtern::Tag(handle) => {
+                tag_imports.push(handle);
+            }
         }
     }
 
@@ -197,5 +204,6 @@ pub fn resolve_imports(
         table_imports,
         memory_imports,
         global_imports,
+        tag_imports,
     ))
 }
@@ -13,10 +13,11 @@
 //! ```
 use std::cmp;
 use std::collections::BTreeMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Once, RwLock};
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
-    CompiledFunctionFrameInfo, FrameInfo, LocalFunctionIndex, ModuleInfo, TrapInformation,
+    CompiledFunctionFrameInfo, FrameInfo, LocalFunctionIndex, ModuleInfo, SourceLoc,
+    TrapInformation,
 };
 use wasmer_vm::FunctionBodyPtr;
 
@@ -60,8 +61,22 @@ struct ModuleInfoFrameInfo {
 }
 
 impl ModuleInfoFrameInfo {
-    fn function_debug_info(&self, local_index: LocalFunctionIndex) -> &CompiledFunctionFrameInfo {
-        self.frame_infos.get(local_index).unwrap()
+    fn function_debug_info(&self, func: &FunctionInfo) -> &CompiledFunctionFrameInfo {
+        self.frame_infos.get(func.frame_info_index).unwrap()
+    }
+
+    /// Gets the function whose code contains `srcloc`, with where its code
+    /// starts.
+    fn function_at_srcloc(&self, srcloc: SourceLoc) -> Option<(LocalFunctionIndex, SourceLoc)> {
+        self.functions.values().find_map(|func| {
+            let address_map = &self.function_debug_info(func).address_map;
+            let range = address_map.start_srcloc.bits()..=address_map.end_srcloc.bits();
+            if range.contains(&srcloc.bits()) {
+                Some((func.local_index, address_map.start_srcloc))
+            } else {
+                None
+            }
+        })
     }
 
     /// Gets a function given a pc
@@ -79,6 +94,12 @@ impl ModuleInfoFrameInfo {
 struct FunctionInfo {
     start: usize,
     local_index: LocalFunctionIndex,
+    /// Where the function's entry is in `frame_infos`. This is the same as
+    /// `local_index`, except for functions that were compiled lazily.
+    frame_info_index: LocalFunctionIndex,
+    /// Whether this is the stub of a lazily compiled function. Stubs stay on
+    /// the stack under the function they call, but aren't frames of their own.
+    stub: bool,
 }
 
 impl GlobalFrameInfo {
@@ -89,12 +110,15 @@ impl GlobalFrameInfo {
     pub fn lookup_frame_info(&self, pc: usize) -> Option<FrameInfo> {
         let module = self.module_info(pc)?;
         let func = module.function_info(pc)?;
+        if func.stub {
+            return None;
+        }
 
         // Use our relative position from the start of the function to find the
         // machine instruction that corresponds to `pc`, which then allows us to
         // map that to a wasm original source location.
         let rel_pos = pc - func.start;
-        let instr_map = &module.function_debug_info(func.local_index).address_map;
+        let instr_map = &module.function_debug_info(func).address_map;
         let pos = match instr_map
             .instructions
             .binary_search_by_key(&rel_pos, |map| map.code_offset)
@@ -129,27 +153,69 @@ impl GlobalFrameInfo {
             // start offset of the function.
             None => instr_map.start_srcloc,
         };
-        let func_index = module.module.func_index(func.local_index);
-        Some(FrameInfo::new(
+        // Functions can be compiled into the ones tail calling them, so the
+        // instruction is in the function with its source location
+        let (local_index, start_srcloc) = if instr.bits() < instr_map.start_srcloc.bits()
+            || instr.bits() > instr_map.end_srcloc.bits()
+        {
+            module
+                .function_at_srcloc(instr)
+                .unwrap_or((func.local_index, instr_map.start_srcloc))
+        } else {
+            (func.local_index, instr_map.start_srcloc)
+        };
+        let func_index = module.module.func_index(local_index);
+        let wat_location = module
+            .module
+            .source_map
+            .as_ref()
+            .and_then(|map| map.lookup(instr.bits() as usize));
+        let frame = FrameInfo::new(
             module.module.name(),
             func_index.index() as u32,
-            module.module.function_names.get(&func_index).cloned(),
-            instr_map.start_srcloc,
+            module
+                .module
+                .function_names
+                .get(&func_index)
+                .map(|name| demangle(name)),
+            start_srcloc,
             instr,
-        ))
+        );
+        Some(frame.with_wat_location(wat_location))
     }
 
     /// Fetches trap information about a program counter in a backtrace.
     pub fn lookup_trap_info(&self, pc: usize) -> Option<&TrapInformation> {
         let module = self.module_info(pc)?;
         let func = module.function_info(pc)?;
-        let traps = &module.function_debug_info(func.local_index).traps;
+        let traps = &module.function_debug_info(func).traps;
         let idx = traps
             .binary_search_by_key(&((pc - func.start) as u32), |info| info.code_offset)
             .ok()?;
         Some(&traps[idx])
     }
 
+    /// Replaces the [`ModuleInfo`] of the module containing `pc`, so frames
+    /// pick up changes made after it was registered (e.g. a new name).
+    ///
+    /// Returns `false` if no registered module contains `pc`.
+    pub fn update_module_info(&mut self, pc: usize, module: Arc<ModuleInfo>) -> bool {
+        match self.ranges.range_mut(pc..).next() {
+            Some((end, module_info)) if module_info.start <= pc && pc <= *end => {
+                module_info.module = module;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `pc` is in the code of a registered function.
+    fn is_wasm_pc(&self, pc: usize) -> bool {
+        self.module_info(pc)
+            .and_then(|module| module.function_info(pc))
+            .is_some()
+    }
+
     /// Gets a module given a pc
     fn module_info(&self, pc: usize) -> Option<&ModuleInfoFrameInfo> {
         let (end, module_info) = self.ranges.range(pc..).next()?;
@@ -191,6 +257,26 @@ pub fn register(
     module: Arc<ModuleInfo>,
     finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionExtent>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<GlobalFrameInfoRegistration> {
+    register_functions(module, finished_functions, frame_infos, false)
+}
+
+/// Registers the stubs of a lazily compiled module, which are left out of
+/// backtraces.
+#[cfg(feature = "compiler")]
+pub(crate) fn register_lazy_stubs(
+    module: Arc<ModuleInfo>,
+    finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionExtent>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> Option<GlobalFrameInfoRegistration> {
+    register_functions(module, finished_functions, frame_infos, true)
+}
+
+fn register_functions(
+    module: Arc<ModuleInfo>,
+    finished_functions: &BoxedSlice<LocalFunctionIndex, FunctionExtent>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    stub: bool,
 ) -> Option<GlobalFrameInfoRegistration> {
     let mut min = usize::max_value();
     let mut max = 0;
@@ -211,6 +297,8 @@ pub fn register(
         let func = FunctionInfo {
             start,
             local_index: i,
+            frame_info_index: i,
+            stub,
         };
         assert!(functions.insert(end, func).is_none());
     }
@@ -218,6 +306,46 @@ pub fn register(
         return None;
     }
 
+    Some(insert(min, max, functions, module, frame_infos))
+}
+
+/// Registers the frame information for a single function that was compiled
+/// lazily, after the rest of its module.
+#[cfg(feature = "compiler")]
+pub(crate) fn register_lazy_function(
+    module: Arc<ModuleInfo>,
+    local_index: LocalFunctionIndex,
+    extent: &FunctionExtent,
+    frame_info: CompiledFunctionFrameInfo,
+) -> GlobalFrameInfoRegistration {
+    let start = *extent.ptr as usize;
+    let end = start + extent.length - 1;
+    let mut frame_infos = PrimaryMap::with_capacity(1);
+    let frame_info_index = frame_infos.push(frame_info);
+    let mut functions = BTreeMap::new();
+    functions.insert(
+        end,
+        FunctionInfo {
+            start,
+            local_index,
+            frame_info_index,
+            stub: false,
+        },
+    );
+
+    insert(start, end, functions, module, frame_infos)
+}
+
+fn insert(
+    min: usize,
+    max: usize,
+    functions: BTreeMap<usize, FunctionInfo>,
+    module: Arc<ModuleInfo>,
+    frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+) -> GlobalFrameInfoRegistration {
+    static SET_WASM_PC_CHECKER: Once = Once::new();
+    SET_WASM_PC_CHECKER.call_once(|| wasmer_vm::set_wasm_pc_checker(is_wasm_pc));
+
     let mut info = FRAME_INFO.write().unwrap();
     // First up assert that our chunk of jit functions doesn't collide with
     // any other known chunks of jit functions...
@@ -239,5 +367,49 @@ pub fn register(
         },
     );
     assert!(prev.is_none());
-    Some(GlobalFrameInfoRegistration { key: max })
+    GlobalFrameInfoRegistration { key: max }
+}
+
+/// Checks whether `pc` is in wasm code for interrupt signals, which can't
+/// wait for the lock.
+fn is_wasm_pc(pc: usize) -> bool {
+    match FRAME_INFO.try_read() {
+        Ok(info) => info.is_wasm_pc(pc),
+        Err(_) => false,
+    }
+}
+
+/// Demangle a Rust or C++ symbol name, leaving any other names as-is.
+fn demangle(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        // The alternate format leaves off the trailing hash
+        return format!("{:#}", demangled);
+    }
+
+    if name.starts_with("_Z") {
+        let demangled = cpp_demangle::Symbol::new(name)
+            .ok()
+            .and_then(|symbol| symbol.demangle(&Default::default()).ok());
+        if let Some(demangled) = demangled {
+            return demangled;
+        }
+    }
+
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangle_symbol_names() {
+        assert_eq!(
+            demangle("_ZN4core9panicking5panic17h5b8b5b6b0b8d1e6aE"),
+            "core::panicking::panic"
+        );
+        assert_eq!(demangle("_ZN5space3fooEii"), "space::foo(int, int)");
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_Znot a symbol"), "_Znot a symbol");
+    }
 }
@@ -3,4 +3,6 @@ mod stack;
 pub use frame_info::{
     register as register_frame_info, FunctionExtent, GlobalFrameInfoRegistration, FRAME_INFO,
 };
+#[cfg(feature = "compiler")]
+pub(crate) use frame_info::{register_lazy_function, register_lazy_stubs};
 pub use stack::get_trace_and_trapcode;
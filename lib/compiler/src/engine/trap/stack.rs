@@ -17,11 +17,16 @@ pub fn get_trace_and_trapcode(trap: &Trap) -> (Vec<FrameInfo>, Option<TrapCode>)
             signal_trap,
             backtrace,
         } => {
-            let trap_code = info
-                .lookup_trap_info(*pc)
-                .map_or(signal_trap.unwrap_or(TrapCode::StackOverflow), |info| {
-                    info.trap_code
-                });
+            let trap_code = match signal_trap {
+                // Interrupts can land on any instruction, including ones which
+                // could have trapped themselves
+                Some(TrapCode::Interrupted) => TrapCode::Interrupted,
+                _ => info
+                    .lookup_trap_info(*pc)
+                    .map_or(signal_trap.unwrap_or(TrapCode::StackOverflow), |info| {
+                        info.trap_code
+                    }),
+            };
 
             (wasm_trace(&info, Some(*pc), backtrace), Some(trap_code))
         }
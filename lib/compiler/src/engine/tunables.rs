@@ -71,13 +71,13 @@ pub trait Tunables {
         let num_imports = module.num_imported_memories;
         let mut memories: PrimaryMap<LocalMemoryIndex, _> =
             PrimaryMap::with_capacity(module.memories.len() - num_imports);
+        // The locations are only those of the locally-defined memories
         for (index, mdl) in memory_definition_locations
             .iter()
             .enumerate()
-            .take(module.memories.len())
-            .skip(num_imports)
+            .take(module.memories.len() - num_imports)
         {
-            let mi = MemoryIndex::new(index);
+            let mi = MemoryIndex::new(num_imports + index);
             let ty = &module.memories[mi];
             let style = &memory_styles[mi];
             memories.push(InternalStoreHandle::new(
@@ -105,13 +105,13 @@ pub trait Tunables {
         let num_imports = module.num_imported_tables;
         let mut tables: PrimaryMap<LocalTableIndex, _> =
             PrimaryMap::with_capacity(module.tables.len() - num_imports);
+        // The locations are only those of the locally-defined tables
         for (index, tdl) in table_definition_locations
             .iter()
             .enumerate()
-            .take(module.tables.len())
-            .skip(num_imports)
+            .take(module.tables.len() - num_imports)
         {
-            let ti = TableIndex::new(index);
+            let ti = TableIndex::new(num_imports + index);
             let ty = &module.tables[ti];
             let style = &table_styles[ti];
             tables.push(InternalStoreHandle::new(
@@ -154,6 +154,7 @@ pub trait Tunables {
     fn vmconfig(&self) -> &VMConfig {
         &VMConfig {
             wasm_stack_size: None,
+            wasm_stack_guard_size: None,
         }
     }
 }
@@ -221,8 +222,9 @@ impl Tunables for BaseTunables {
         // tunables make it static.
         //
         // If the module doesn't declare an explicit maximum treat it as 4GiB.
+        // 64-bit memories can always exceed that, so they are dynamic.
         let maximum = memory.maximum.unwrap_or_else(Pages::max_value);
-        if maximum <= self.static_memory_bound {
+        if maximum <= self.static_memory_bound && !memory.memory64 {
             MemoryStyle::Static {
                 // Bound can be larger than the maximum for performance reasons
                 bound: self.static_memory_bound,
@@ -65,20 +65,40 @@ pub use self::artifact_builders::*;
 
 #[cfg(feature = "translator")]
 mod compiler;
+#[cfg(feature = "translator")]
+mod disassemble;
+#[cfg(feature = "dump")]
+mod dump;
 
 #[cfg(feature = "translator")]
 #[macro_use]
 mod translator;
 #[cfg(feature = "translator")]
-pub use crate::compiler::{Compiler, CompilerConfig};
+pub use crate::compiler::{Compiler, CompilerConfig, StackProbes};
+#[cfg(feature = "translator")]
+pub use crate::disassemble::{disassemble, disassemble_artifact, DumpFilter};
+#[cfg(feature = "dump")]
+pub use crate::dump::{wat_around, CompilerDump, DUMP_INDEX_FILE};
+#[cfg(feature = "translator")]
+mod threads;
+#[cfg(feature = "translator")]
+pub use crate::threads::{CompileThreads, ThreadStartHook};
+#[cfg(feature = "translator")]
+mod validation;
 #[cfg(feature = "translator")]
 pub use crate::translator::{
     from_binaryreadererror_wasmerror, translate_module, wptype_to_type, FunctionBinaryReader,
     FunctionBodyData, FunctionMiddleware, MiddlewareBinaryReader, MiddlewareReaderState,
     ModuleEnvironment, ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
 };
+#[cfg(feature = "translator")]
+pub use crate::validation::{
+    required_features, validate_all_with_features, validate_with_features,
+};
 
-pub use wasmer_types::{Addend, CodeOffset, Features};
+pub use wasmer_types::{
+    Addend, CodeOffset, FeatureSupport, Features, FeaturesBuilder, UnsupportedFeaturesError,
+};
 
 #[cfg(feature = "translator")]
 /// wasmparser is exported as a module to slim compiler dependencies
@@ -0,0 +1,246 @@
+//! The threads the compilers spread the work of compiling a module over
+//! (see [`CompilerConfig::set_compile_threads`]).
+//!
+//! The work is split in a way that doesn't depend on the number of threads,
+//! and the results are put back in order, so the artifacts are the same
+//! whichever threads compiled them.
+//!
+//! [`CompilerConfig::set_compile_threads`]: crate::CompilerConfig::set_compile_threads
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::fmt;
+use std::sync::Arc;
+#[cfg(feature = "rayon")]
+use std::sync::Mutex;
+use wasmer_types::CompileError;
+
+/// A hook the threads spawned for compiling run as they start, with the
+/// index of the thread in its pool, to set their priority or CPU affinity
+/// for instance.
+pub type ThreadStartHook = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// Which threads a module is compiled on.
+///
+/// By default, the work is spread over rayon's global thread pool, which
+/// has a thread per CPU core. Without the `rayon` feature, modules are
+/// always compiled in the calling thread.
+#[derive(Clone, Default)]
+pub struct CompileThreads {
+    /// The number of threads, or `None` for one per CPU core.
+    jobs: Option<usize>,
+    on_thread_start: Option<ThreadStartHook>,
+    /// The pool the work is spread over, once it exists. It's shared by the
+    /// clones, so a compiler config cloned for each engine doesn't spawn a
+    /// pool per engine.
+    #[cfg(feature = "rayon")]
+    pool: Arc<Mutex<Option<Arc<rayon::ThreadPool>>>>,
+    /// Whether `pool` was given, rather than created as needed.
+    #[cfg(feature = "rayon")]
+    injected: bool,
+}
+
+impl CompileThreads {
+    /// Compiles on `jobs` threads, spawned the first time a module is
+    /// compiled.
+    ///
+    /// With `0`, modules are compiled in the calling thread, without
+    /// spawning any, for embedders which can't spawn threads.
+    pub fn new(jobs: usize) -> Self {
+        Self {
+            jobs: Some(jobs),
+            ..Self::default()
+        }
+    }
+
+    /// Compiles in the calling thread, like `CompileThreads::new(0)`.
+    pub fn sequential() -> Self {
+        Self::new(0)
+    }
+
+    /// Compiles on the threads of `pool`, which can be shared with other
+    /// engines, or with the rest of the application.
+    #[cfg(feature = "rayon")]
+    pub fn with_pool(pool: Arc<rayon::ThreadPool>) -> Self {
+        Self {
+            jobs: Some(pool.current_num_threads()),
+            pool: Arc::new(Mutex::new(Some(pool))),
+            injected: true,
+            ..Self::default()
+        }
+    }
+
+    /// Runs `hook` on each thread spawned for compiling, as it starts.
+    ///
+    /// The default number of threads then gets a pool of its own rather
+    /// than rayon's global one. The threads of a pool given to
+    /// [`CompileThreads::with_pool`] aren't spawned for compiling, so they
+    /// don't run it.
+    pub fn on_thread_start(mut self, hook: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.on_thread_start = Some(Arc::new(hook));
+        self
+    }
+
+    /// The number of threads, or `None` for one per CPU core.
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    /// Whether modules are compiled in the calling thread.
+    pub fn is_sequential(&self) -> bool {
+        self.jobs == Some(0) || cfg!(not(feature = "rayon"))
+    }
+
+    /// Maps each of `items` with `map`, on the threads.
+    ///
+    /// Like rayon's `map_init`, `init` creates the state `map` gets, once
+    /// for each batch of items a thread works on. The results are in the
+    /// order of the items.
+    pub fn map_init<T, S, R>(
+        &self,
+        items: &[T],
+        init: impl Fn() -> S + Send + Sync,
+        map: impl Fn(&mut S, &T) -> R + Send + Sync,
+    ) -> Result<Vec<R>, CompileError>
+    where
+        T: Sync,
+        R: Send,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            if !self.is_sequential() {
+                let parallel = || items.par_iter().map_init(&init, &map).collect();
+                return Ok(match self.pool()? {
+                    Some(pool) => pool.install(parallel),
+                    None => parallel(),
+                });
+            }
+        }
+
+        let mut state = init();
+        Ok(items.iter().map(|item| map(&mut state, item)).collect())
+    }
+
+    /// The pool to compile on, or `None` for rayon's global pool.
+    #[cfg(feature = "rayon")]
+    fn pool(&self) -> Result<Option<Arc<rayon::ThreadPool>>, CompileError> {
+        if self.jobs.is_none() && self.on_thread_start.is_none() {
+            return Ok(None);
+        }
+        let mut pool = self.pool.lock().unwrap();
+        if let Some(pool) = &*pool {
+            return Ok(Some(Arc::clone(pool)));
+        }
+
+        let mut builder = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs.unwrap_or(0))
+            .thread_name(|index| format!("wasmer-compile-{}", index));
+        if let Some(hook) = &self.on_thread_start {
+            let hook = Arc::clone(hook);
+            builder = builder.start_handler(move |index| hook(index));
+        }
+        let new_pool = Arc::new(builder.build().map_err(|e| {
+            CompileError::Resource(format!("Unable to spawn the compilation threads: {}", e))
+        })?);
+        *pool = Some(Arc::clone(&new_pool));
+        Ok(Some(new_pool))
+    }
+}
+
+impl fmt::Debug for CompileThreads {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("CompileThreads");
+        debug.field("jobs", &self.jobs);
+        debug.field("on_thread_start", &self.on_thread_start.is_some());
+        #[cfg(feature = "rayon")]
+        debug.field("injected", &self.injected);
+        debug.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn results_are_in_order() {
+        let items: Vec<u32> = (0..1000).collect();
+        for threads in [
+            CompileThreads::default(),
+            CompileThreads::sequential(),
+            CompileThreads::new(1),
+            CompileThreads::new(8),
+        ] {
+            let squares = threads
+                .map_init(&items, || (), |_, item| item * item)
+                .unwrap();
+            assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn sequential_stays_on_the_calling_thread() {
+        let caller = thread::current().id();
+        let items = [0; 100];
+
+        let threads = CompileThreads::sequential()
+            .map_init(&items, || (), |_, _| thread::current().id())
+            .unwrap();
+
+        assert!(threads.iter().all(|id| *id == caller));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn spawned_threads_run_the_hook() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::{Duration, Instant};
+
+        let started = Arc::new(AtomicUsize::new(0));
+        let threads = {
+            let started = Arc::clone(&started);
+            CompileThreads::new(3).on_thread_start(move |_| {
+                started.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        threads.map_init(&[0; 100], || (), |_, _| ()).unwrap();
+        // The threads are spawned with the pool, but start in their own time
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while started.load(Ordering::SeqCst) < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        // The clones share the pool
+        threads
+            .clone()
+            .map_init(&[0; 100], || (), |_, _| ())
+            .unwrap();
+
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn injected_pools_are_used() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .thread_name(|index| format!("injected-{}", index))
+            .build()
+            .unwrap();
+        let threads = CompileThreads::with_pool(Arc::new(pool));
+        assert_eq!(threads.jobs(), Some(2));
+
+        let names = threads
+            .map_init(
+                &[0; 100],
+                || (),
+                |_, _| thread::current().name().map(str::to_string),
+            )
+            .unwrap();
+
+        assert!(names.iter().all(|name| name
+            .as_deref()
+            .map_or(false, |n| n.starts_with("injected-"))));
+    }
+}
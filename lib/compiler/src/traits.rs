@@ -8,6 +8,7 @@ use wasmer_types::entity::PrimaryMap;
 use wasmer_types::SerializeError;
 use wasmer_types::{
     CpuFeature, MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, TableIndex, TableStyle,
+    WatSourceMap,
 };
 
 /// An `Artifact` is the product that the `Engine`
@@ -23,6 +24,10 @@ pub trait ArtifactCreate: Send + Sync + Upcastable {
     /// Sets the `ModuleInfo` name
     fn set_module_info_name(&mut self, name: String) -> bool;
 
+    /// Sets the source map of a module compiled from the WebAssembly text
+    /// format
+    fn set_module_info_source_map(&mut self, source_map: Arc<WatSourceMap>) -> bool;
+
     /// Returns the `ModuleInfo` for instantiation
     fn module_info(&self) -> &ModuleInfo;
 
@@ -0,0 +1,212 @@
+//! Recover function names from a module's DWARF debug info.
+//!
+//! Toolchains like `clang` and `rustc` only emit a `name` section when asked
+//! to, but a module compiled with `-g` will always describe its functions in
+//! `.debug_info`. We use that as a fallback so traps can still be symbolicated.
+
+use super::environ::ModuleEnvironment;
+use crate::lib::std::collections::HashMap;
+use crate::lib::std::str;
+use crate::lib::std::{string::String, vec::Vec};
+use gimli::{AttributeValue, Dwarf, EndianSlice, LittleEndian, SectionId};
+use wasmer_types::LocalFunctionIndex;
+
+/// Name every function described by a `DW_TAG_subprogram` entry which wasn't
+/// already named by the `name` section.
+///
+/// `code_section_offset` is the offset of the code section's contents within
+/// the module, which is what DWARF addresses are relative to.
+///
+/// Debug info is purely informational, so malformed DWARF is ignored rather
+/// than failing the compilation.
+pub fn parse_dwarf_function_names(environ: &mut ModuleEnvironment<'_>, code_section_offset: u64) {
+    if !environ.module.custom_sections.contains_key(".debug_info") {
+        return;
+    }
+
+    let names = match read_subprogram_names(environ, code_section_offset) {
+        Ok(names) => names,
+        Err(_) => return,
+    };
+
+    for (local_index, name) in names {
+        let func_index = environ.module.func_index(local_index);
+        environ
+            .module
+            .function_names
+            .entry(func_index)
+            .or_insert(name);
+    }
+}
+
+fn read_subprogram_names(
+    environ: &ModuleEnvironment<'_>,
+    code_section_offset: u64,
+) -> Result<Vec<(LocalFunctionIndex, String)>, gimli::Error> {
+    let function_starts: HashMap<u64, LocalFunctionIndex> = environ
+        .function_body_inputs
+        .iter()
+        .map(|(index, body)| (body.module_offset as u64, index))
+        .collect();
+
+    let module = &environ.module;
+    let dwarf = Dwarf::load(|id: SectionId| -> Result<_, gimli::Error> {
+        let data = module
+            .custom_sections
+            .get(id.name())
+            .map(|&index| &*module.custom_sections_data[index])
+            .unwrap_or_default();
+        Ok(EndianSlice::new(data, LittleEndian))
+    })?;
+
+    let mut names = Vec::new();
+    let mut units = dwarf.units();
+
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let mut entries = unit.entries();
+
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+
+            let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+                Some(value) => dwarf.attr_address(&unit, value)?,
+                None => None,
+            };
+            let local_index = match low_pc
+                .and_then(|pc| pc.checked_add(code_section_offset))
+                .and_then(|offset| function_starts.get(&offset))
+            {
+                Some(&index) => index,
+                None => continue,
+            };
+
+            // Prefer the mangled name because it can be demangled into
+            // something more descriptive (e.g. including the namespace)
+            let name = match entry.attr_value(gimli::DW_AT_linkage_name)? {
+                Some(name) => Some(name),
+                None => entry.attr_value(gimli::DW_AT_name)?,
+            };
+            let name = match name {
+                Some(name) => attr_str(&dwarf, &unit, name)?,
+                None => None,
+            };
+            if let Some(name) = name {
+                names.push((local_index, name));
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+fn attr_str(
+    dwarf: &Dwarf<EndianSlice<'_, LittleEndian>>,
+    unit: &gimli::Unit<EndianSlice<'_, LittleEndian>>,
+    value: AttributeValue<EndianSlice<'_, LittleEndian>>,
+) -> Result<Option<String>, gimli::Error> {
+    let name = dwarf.attr_string(unit, value)?;
+    Ok(str::from_utf8(name.slice()).ok().map(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::write::{Address, AttributeValue, DwarfUnit, EndianVec, Sections};
+    use wasmer_types::FunctionIndex;
+
+    /// A module with a single function, `(func unreachable)`, whose body
+    /// starts 2 bytes into the code section.
+    const MODULE: &[u8] =
+        b"\0asm\x01\0\0\0\x01\x04\x01\x60\0\0\x03\x02\x01\0\x0a\x05\x01\x03\0\0\x0b";
+
+    fn push_custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut contents = Vec::new();
+        leb128::write::unsigned(&mut contents, name.len() as u64).unwrap();
+        contents.extend_from_slice(name.as_bytes());
+        contents.extend_from_slice(data);
+
+        wasm.push(0);
+        leb128::write::unsigned(wasm, contents.len() as u64).unwrap();
+        wasm.extend(contents);
+    }
+
+    /// Add DWARF debug info naming the function at `low_pc`.
+    fn with_debug_info(name: &str, low_pc: u64) -> Vec<u8> {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let root = dwarf.unit.root();
+        let subprogram = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = dwarf.unit.get_mut(subprogram);
+        entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(name.as_bytes().to_vec()),
+        );
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(low_pc)),
+        );
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+
+        let mut wasm = MODULE.to_vec();
+        sections
+            .for_each(|id, data| -> Result<(), ()> {
+                if !data.slice().is_empty() {
+                    push_custom_section(&mut wasm, id.name(), data.slice());
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        wasm
+    }
+
+    #[test]
+    fn function_names_from_dwarf() {
+        let wasm = with_debug_info("my_function", 2);
+
+        let environ = ModuleEnvironment::new().translate(&wasm).unwrap();
+
+        assert_eq!(
+            environ
+                .module
+                .function_names
+                .get(&FunctionIndex::from_u32(0)),
+            Some(&String::from("my_function"))
+        );
+    }
+
+    #[test]
+    fn unknown_addresses_are_ignored() {
+        let wasm = with_debug_info("my_function", 3);
+
+        let environ = ModuleEnvironment::new().translate(&wasm).unwrap();
+
+        assert!(environ.module.function_names.is_empty());
+    }
+
+    #[test]
+    fn the_name_section_takes_precedence() {
+        let mut wasm = with_debug_info("from_dwarf", 2);
+        // A "name" section where function 0 is called "f"
+        push_custom_section(&mut wasm, "name", b"\x01\x04\x01\0\x01f");
+
+        let environ = ModuleEnvironment::new().translate(&wasm).unwrap();
+
+        assert_eq!(
+            environ
+                .module
+                .function_names
+                .get(&FunctionIndex::from_u32(0)),
+            Some(&String::from("f"))
+        );
+    }
+}
@@ -14,7 +14,7 @@ use wasmer_types::{
     CustomSectionIndex, DataIndex, DataInitializer, DataInitializerLocation, ElemIndex,
     ExportIndex, FunctionIndex, GlobalIndex, GlobalInit, GlobalType, ImportIndex,
     LocalFunctionIndex, MemoryIndex, MemoryType, ModuleInfo, SignatureIndex, TableIndex,
-    TableInitializer, TableType,
+    TableInitializer, TableType, TagIndex,
 };
 
 /// Contains function data: bytecode and its offset in the module.
@@ -216,6 +216,27 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn declare_tag_import(
+        &mut self,
+        sig_index: SignatureIndex,
+        module: &str,
+        field: &str,
+    ) -> WasmResult<()> {
+        debug_assert_eq!(
+            self.module.tags.len(),
+            self.module.num_imported_tags,
+            "Imported tags must be declared first"
+        );
+        self.declare_import(
+            ImportIndex::Tag(TagIndex::from_u32(self.module.num_imported_tags as _)),
+            module,
+            field,
+        )?;
+        self.module.tags.push(sig_index);
+        self.module.num_imported_tags += 1;
+        Ok(())
+    }
+
     pub(crate) fn finish_imports(&mut self) -> WasmResult<()> {
         Ok(())
     }
@@ -275,6 +296,18 @@ impl<'data> ModuleEnvironment<'data> {
         Ok(())
     }
 
+    pub(crate) fn reserve_tags(&mut self, num: u32) -> WasmResult<()> {
+        self.module
+            .tags
+            .reserve_exact(usize::try_from(num).unwrap());
+        Ok(())
+    }
+
+    pub(crate) fn declare_tag(&mut self, sig_index: SignatureIndex) -> WasmResult<()> {
+        self.module.tags.push(sig_index);
+        Ok(())
+    }
+
     pub(crate) fn reserve_exports(&mut self, num: u32) -> WasmResult<()> {
         self.module.exports.reserve(usize::try_from(num).unwrap());
         Ok(())
@@ -312,6 +345,10 @@ impl<'data> ModuleEnvironment<'data> {
         self.declare_export(ExportIndex::Global(global_index), name)
     }
 
+    pub(crate) fn declare_tag_export(&mut self, tag_index: TagIndex, name: &str) -> WasmResult<()> {
+        self.declare_export(ExportIndex::Tag(tag_index), name)
+    }
+
     pub(crate) fn declare_start_function(&mut self, func_index: FunctionIndex) -> WasmResult<()> {
         debug_assert!(self.module.start_function.is_none());
         self.module.start_function = Some(func_index);
@@ -447,6 +484,7 @@ impl<'data> ModuleEnvironment<'data> {
             .custom_sections
             .insert(String::from(name), custom_section);
         self.module.custom_sections_data.push(Box::from(data));
+        self.module.custom_section_names.push(String::from(name));
         Ok(())
     }
 }
@@ -5,11 +5,12 @@ use smallvec::SmallVec;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::ops::{Deref, Range};
+use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{LocalFunctionIndex, MiddlewareError, ModuleInfo, WasmResult};
 use wasmparser::{BinaryReader, Operator, ValType};
 
 use super::error::from_binaryreadererror_wasmerror;
-use crate::translator::environ::FunctionBinaryReader;
+use crate::translator::environ::{FunctionBinaryReader, FunctionBodyData};
 
 /// A shared builder for function middlewares.
 pub trait ModuleMiddleware: Debug + Send + Sync {
@@ -25,6 +26,28 @@ pub trait ModuleMiddleware: Debug + Send + Sync {
 
     /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
     fn transform_module_info(&self, _: &mut ModuleInfo) {}
+
+    /// Like [`ModuleMiddleware::transform_module_info`], with the bodies of
+    /// the module's local functions, for the middlewares which add
+    /// something to the module for each place they instrument. By default,
+    /// it calls `transform_module_info`.
+    fn transform_module_info_with_bodies(
+        &self,
+        module_info: &mut ModuleInfo,
+        _bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        self.transform_module_info(module_info);
+    }
+
+    /// Returns an id for the middleware and the settings that affect the
+    /// code it generates, or `None` if it doesn't have any.
+    ///
+    /// It's appended to the [`crate::Compiler::deterministic_id`] of the
+    /// compiler the middleware is used with, so that artifacts compiled
+    /// with different settings are told apart.
+    fn deterministic_id(&self) -> Option<String> {
+        None
+    }
 }
 
 /// A function middleware specialized for a single function.
@@ -58,6 +81,9 @@ pub struct MiddlewareReaderState<'a> {
 
     /// The pending operations added by the middleware.
     pending_operations: VecDeque<Operator<'a>>,
+
+    /// The offset of the last operator read from the binary.
+    operator_offset: Option<usize>,
 }
 
 /// Trait for generating middleware chains from "prototype" (generator) chains.
@@ -70,6 +96,18 @@ pub trait ModuleMiddlewareChain {
 
     /// Applies the chain on a `ModuleInfo` struct.
     fn apply_on_module_info(&self, module_info: &mut ModuleInfo);
+
+    /// Applies the chain on a `ModuleInfo` struct, with the bodies of the
+    /// module's local functions.
+    fn apply_on_module_info_with_bodies(
+        &self,
+        module_info: &mut ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    );
+
+    /// The ids of the middlewares in the chain which have one (see
+    /// [`ModuleMiddleware::deterministic_id`]), each preceded by a `-`.
+    fn deterministic_id_suffix(&self) -> String;
 }
 
 impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
@@ -89,6 +127,23 @@ impl<T: Deref<Target = dyn ModuleMiddleware>> ModuleMiddlewareChain for [T] {
             item.transform_module_info(module_info);
         }
     }
+
+    fn apply_on_module_info_with_bodies(
+        &self,
+        module_info: &mut ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        for item in self {
+            item.transform_module_info_with_bodies(module_info, bodies);
+        }
+    }
+
+    fn deterministic_id_suffix(&self) -> String {
+        self.iter()
+            .filter_map(|x| x.deterministic_id())
+            .map(|id| format!("-{}", id))
+            .collect()
+    }
 }
 
 impl<'a> MiddlewareReaderState<'a> {
@@ -96,6 +151,12 @@ impl<'a> MiddlewareReaderState<'a> {
     pub fn push_operator(&mut self, operator: Operator<'a>) {
         self.pending_operations.push_back(operator);
     }
+
+    /// The offset from the start of the module of the operator read from
+    /// the binary which is being fed through the middlewares.
+    pub fn operator_offset(&self) -> usize {
+        self.operator_offset.unwrap_or_default()
+    }
 }
 
 impl<'a> Extend<Operator<'a>> for MiddlewareReaderState<'a> {
@@ -113,11 +174,15 @@ impl<'a: 'b, 'b> Extend<&'b Operator<'a>> for MiddlewareReaderState<'a> {
 impl<'a> MiddlewareBinaryReader<'a> {
     /// Constructs a `MiddlewareBinaryReader` with an explicit starting offset.
     pub fn new_with_offset(data: &'a [u8], original_offset: usize) -> Self {
-        let inner = BinaryReader::new_with_offset(data, original_offset);
+        let mut inner = BinaryReader::new_with_offset(data, original_offset);
+        // Function bodies have already been validated, so offsets which
+        // don't fit in 32 bits can only be there for 64-bit memories
+        inner.allow_memarg64(true);
         Self {
             state: MiddlewareReaderState {
                 inner,
                 pending_operations: VecDeque::new(),
+                operator_offset: None,
             },
             chain: vec![],
         }
@@ -127,6 +192,15 @@ impl<'a> MiddlewareBinaryReader<'a> {
     pub fn set_middleware_chain(&mut self, stages: Vec<Box<dyn FunctionMiddleware>>) {
         self.chain = stages;
     }
+
+    /// The offset from the start of the module of the last operator read
+    /// from the binary, or `None` if none were read yet.
+    ///
+    /// The operators middlewares insert are at the offset of the operator
+    /// they were inserted for.
+    pub fn operator_offset(&self) -> Option<usize> {
+        self.state.operator_offset
+    }
 }
 
 impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
@@ -154,6 +228,7 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
     fn read_operator(&mut self) -> WasmResult<Operator<'a>> {
         if self.chain.is_empty() {
             // We short-circuit in case no chain is used
+            self.state.operator_offset = Some(self.state.inner.original_position());
             return self
                 .state
                 .inner
@@ -163,6 +238,7 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
 
         // Try to fill the `self.pending_operations` buffer, until it is non-empty.
         while self.state.pending_operations.is_empty() {
+            self.state.operator_offset = Some(self.state.inner.original_position());
             let raw_op = self
                 .state
                 .inner
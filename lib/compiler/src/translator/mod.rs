@@ -5,6 +5,8 @@
 //! compilers rather than just Cranelift.
 //!
 //! [cranelift-wasm]: https://crates.io/crates/cranelift-wasm/
+#[cfg(feature = "symbolication")]
+mod dwarf;
 mod environ;
 mod middleware;
 mod module;
@@ -3,12 +3,14 @@
 
 //! Translation skeleton that traverses the whole WebAssembly module and call helper functions
 //! to deal with each part of it.
+#[cfg(feature = "symbolication")]
+use super::dwarf::parse_dwarf_function_names;
 use super::environ::ModuleEnvironment;
 use super::error::from_binaryreadererror_wasmerror;
 use super::sections::{
     parse_data_section, parse_element_section, parse_export_section, parse_function_section,
     parse_global_section, parse_import_section, parse_memory_section, parse_name_section,
-    parse_start_section, parse_table_section, parse_type_section,
+    parse_start_section, parse_table_section, parse_tag_section, parse_type_section,
 };
 use super::state::ModuleTranslationState;
 use wasmer_types::WasmResult;
@@ -21,6 +23,8 @@ pub fn translate_module<'data>(
     environ: &mut ModuleEnvironment<'data>,
 ) -> WasmResult<ModuleTranslationState> {
     let mut module_translation_state = ModuleTranslationState::new();
+    #[cfg(feature = "symbolication")]
+    let mut code_section_offset = None;
 
     for payload in Parser::new(0).parse_all(data) {
         match payload.map_err(from_binaryreadererror_wasmerror)? {
@@ -62,6 +66,11 @@ pub fn translate_module<'data>(
                 parse_element_section(elements, environ)?;
             }
 
+            #[cfg(feature = "symbolication")]
+            Payload::CodeSectionStart { range, .. } => {
+                code_section_offset = Some(range.start as u64);
+            }
+            #[cfg(not(feature = "symbolication"))]
             Payload::CodeSectionStart { .. } => {}
             Payload::CodeSectionEntry(code) => {
                 let mut code = code.get_binary_reader();
@@ -97,8 +106,8 @@ pub fn translate_module<'data>(
                 unimplemented!("module linking not implemented. It will only be implemented if/when browsers support it")
             }
 
-            Payload::TagSection(_) => {
-                unimplemented!("exception handling not implemented yet")
+            Payload::TagSection(tags) => {
+                parse_tag_section(tags, environ)?;
             }
 
             Payload::CustomSection(sectionreader) => {
@@ -118,5 +127,10 @@ pub fn translate_module<'data>(
         }
     }
 
+    #[cfg(feature = "symbolication")]
+    if let Some(code_section_offset) = code_section_offset {
+        parse_dwarf_function_names(environ, code_section_offset);
+    }
+
     Ok(module_translation_state)
 }
@@ -21,15 +21,16 @@ use wasmer_types::entity::packed_option::ReservedValue;
 use wasmer_types::entity::EntityRef;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, FunctionType, GlobalIndex, GlobalInit, GlobalType,
-    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, Type, V128,
+    MemoryIndex, MemoryType, Pages, SignatureIndex, TableIndex, TableType, TagIndex, Type, V128,
+    WASM64_MAX_PAGES,
 };
 use wasmer_types::{WasmError, WasmResult};
 use wasmparser::{
     self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementItems, ElementKind,
     ElementSectionReader, Export, ExportSectionReader, ExternalKind, FunctionSectionReader,
     GlobalSectionReader, GlobalType as WPGlobalType, ImportSectionReader, MemorySectionReader,
-    MemoryType as WPMemoryType, NameSectionReader, Operator, TableSectionReader, TypeRef,
-    TypeSectionReader,
+    MemoryType as WPMemoryType, NameSectionReader, Operator, TableSectionReader, TagSectionReader,
+    TypeRef, TypeSectionReader,
 };
 
 /// Helper function translating wasmparser types to Wasm Type.
@@ -105,28 +106,16 @@ pub fn parse_import_section<'data>(
                     field_name,
                 )?;
             }
-            TypeRef::Tag(_) => {
-                unimplemented!("exception handling not implemented yet")
-            }
-            TypeRef::Memory(WPMemoryType {
-                shared,
-                memory64,
-                initial,
-                maximum,
-            }) => {
-                if memory64 {
-                    unimplemented!("64bit memory not implemented yet");
-                }
-                environ.declare_memory_import(
-                    MemoryType {
-                        minimum: Pages(initial as u32),
-                        maximum: maximum.map(|p| Pages(p as u32)),
-                        shared,
-                    },
+            TypeRef::Tag(tag) => {
+                environ.declare_tag_import(
+                    SignatureIndex::from_u32(tag.func_type_idx),
                     module_name,
                     field_name,
                 )?;
             }
+            TypeRef::Memory(ty) => {
+                environ.declare_memory_import(memory_type(ty)?, module_name, field_name)?;
+            }
             TypeRef::Global(ref ty) => {
                 environ.declare_global_import(
                     GlobalType {
@@ -203,25 +192,32 @@ pub fn parse_memory_section(
     environ.reserve_memories(memories.get_count())?;
 
     for entry in memories {
-        let WPMemoryType {
-            shared,
-            memory64,
-            initial,
-            maximum,
-        } = entry.map_err(from_binaryreadererror_wasmerror)?;
-        if memory64 {
-            unimplemented!("64bit memory not implemented yet");
-        }
-        environ.declare_memory(MemoryType {
-            minimum: Pages(initial as u32),
-            maximum: maximum.map(|p| Pages(p as u32)),
-            shared,
-        })?;
+        let ty = entry.map_err(from_binaryreadererror_wasmerror)?;
+        environ.declare_memory(memory_type(ty)?)?;
     }
 
     Ok(())
 }
 
+/// Convert a memory's limits, which are 64-bit for 64-bit memories, to
+/// `Pages`.
+fn memory_type(ty: WPMemoryType) -> WasmResult<MemoryType> {
+    let pages = |count: u64| {
+        Pages::try_from(count).map_err(|_| {
+            WasmError::Unsupported(format!(
+                "memories with more than {} pages",
+                WASM64_MAX_PAGES
+            ))
+        })
+    };
+    Ok(MemoryType {
+        minimum: pages(ty.initial)?,
+        maximum: ty.maximum.map(pages).transpose()?,
+        shared: ty.shared,
+        memory64: ty.memory64,
+    })
+}
+
 /// Parses the Global section of the wasm module.
 pub fn parse_global_section(
     globals: GlobalSectionReader,
@@ -242,8 +238,16 @@ pub fn parse_global_section(
             .read_operator()
             .map_err(from_binaryreadererror_wasmerror)?
         {
-            Operator::I32Const { value } => GlobalInit::I32Const(value),
-            Operator::I64Const { value } => GlobalInit::I64Const(value),
+            Operator::I32Const { .. } | Operator::I64Const { .. } | Operator::GlobalGet { .. } => {
+                match read_integer_const_expr(&init_expr, "global")? {
+                    (None, value) if content_type == wasmparser::ValType::I64 => {
+                        GlobalInit::I64Const(value)
+                    }
+                    (None, value) => GlobalInit::I32Const(value as i32),
+                    (Some(global), 0) => GlobalInit::GetGlobal(global),
+                    (Some(global), offset) => GlobalInit::GetGlobalOffset(global, offset),
+                }
+            }
             Operator::F32Const { value } => GlobalInit::F32Const(f32::from_bits(value.bits())),
             Operator::F64Const { value } => GlobalInit::F64Const(f64::from_bits(value.bits())),
             Operator::V128Const { value } => GlobalInit::V128Const(V128::from(*value.bytes())),
@@ -251,9 +255,6 @@ pub fn parse_global_section(
             Operator::RefFunc { function_index } => {
                 GlobalInit::RefFunc(FunctionIndex::from_u32(function_index))
             }
-            Operator::GlobalGet { global_index } => {
-                GlobalInit::GetGlobal(GlobalIndex::from_u32(global_index))
-            }
             ref s => {
                 return Err(wasm_unsupported!(
                     "unsupported init expr in global section: {:?}",
@@ -271,6 +272,105 @@ pub fn parse_global_section(
     Ok(())
 }
 
+/// Evaluate an integer constant expression as far as it can be before
+/// instantiation: to the global it reads, if any, and a constant to add to
+/// it. `i32` constants are zero-extended.
+///
+/// Besides a single `i32.const`, `i64.const` or `global.get`, this supports
+/// the `add`, `sub` and `mul` of the extended constant expressions proposal,
+/// as long as they don't combine globals together.
+fn read_integer_const_expr(
+    expr: &wasmparser::ConstExpr<'_>,
+    section: &str,
+) -> WasmResult<(Option<GlobalIndex>, i64)> {
+    let mut reader = expr.get_binary_reader();
+    let mut stack: Vec<(Option<GlobalIndex>, i64)> = Vec::new();
+
+    loop {
+        let op = reader
+            .read_operator()
+            .map_err(from_binaryreadererror_wasmerror)?;
+        let value = match op {
+            Operator::End => break,
+            Operator::I32Const { value } => (None, value as u32 as i64),
+            Operator::I64Const { value } => (None, value),
+            Operator::GlobalGet { global_index } => (Some(GlobalIndex::from_u32(global_index)), 0),
+            Operator::I32Add
+            | Operator::I32Sub
+            | Operator::I32Mul
+            | Operator::I64Add
+            | Operator::I64Sub
+            | Operator::I64Mul => {
+                let (b, a) = match (stack.pop(), stack.pop()) {
+                    (Some(b), Some(a)) => (b, a),
+                    _ => return Err(invalid_const_expr(&reader)),
+                };
+                let value = match (&op, a, b) {
+                    (Operator::I32Add | Operator::I64Add, (global, a), (None, b))
+                    | (Operator::I32Add | Operator::I64Add, (None, a), (global, b)) => {
+                        (global, a.wrapping_add(b))
+                    }
+                    (Operator::I32Sub | Operator::I64Sub, (global, a), (None, b)) => {
+                        (global, a.wrapping_sub(b))
+                    }
+                    (Operator::I32Mul | Operator::I64Mul, (None, a), (None, b)) => {
+                        (None, a.wrapping_mul(b))
+                    }
+                    _ => {
+                        return Err(wasm_unsupported!(
+                            "unsupported init expr in {} section: {:?} with a global operand",
+                            section,
+                            op
+                        ))
+                    }
+                };
+                match op {
+                    Operator::I32Add | Operator::I32Sub | Operator::I32Mul => {
+                        (value.0, value.1 as u32 as i64)
+                    }
+                    _ => value,
+                }
+            }
+            ref s => {
+                return Err(wasm_unsupported!(
+                    "unsupported init expr in {} section: {:?}",
+                    section,
+                    s
+                ))
+            }
+        };
+        stack.push(value);
+    }
+
+    match stack.pop() {
+        Some(value) if stack.is_empty() => Ok(value),
+        _ => Err(invalid_const_expr(&reader)),
+    }
+}
+
+/// The validator rejects these, but modules can be translated unvalidated.
+fn invalid_const_expr(reader: &wasmparser::BinaryReader<'_>) -> WasmError {
+    WasmError::InvalidWebAssembly {
+        message: "type mismatch in constant expression".to_string(),
+        offset: reader.original_position(),
+    }
+}
+
+/// Parses the Tag section of the wasm module.
+pub fn parse_tag_section(
+    tags: TagSectionReader,
+    environ: &mut ModuleEnvironment,
+) -> WasmResult<()> {
+    environ.reserve_tags(tags.get_count())?;
+
+    for entry in tags {
+        let tag = entry.map_err(from_binaryreadererror_wasmerror)?;
+        environ.declare_tag(SignatureIndex::from_u32(tag.func_type_idx))?;
+    }
+
+    Ok(())
+}
+
 /// Parses the Export section of the wasm module.
 pub fn parse_export_section<'data>(
     exports: ExportSectionReader<'data>,
@@ -298,9 +398,7 @@ pub fn parse_export_section<'data>(
             ExternalKind::Global => {
                 environ.declare_global_export(GlobalIndex::new(index), field)?
             }
-            ExternalKind::Tag => {
-                unimplemented!("exception handling not implemented yet")
-            }
+            ExternalKind::Tag => environ.declare_tag_export(TagIndex::new(index), field)?,
         }
     }
 
@@ -356,7 +454,9 @@ pub fn parse_element_section(
             ty,
             range: _,
         } = entry.map_err(from_binaryreadererror_wasmerror)?;
-        if ty != wasmparser::ValType::FuncRef {
+        // Segments of externrefs can only hold nulls (validation rejects
+        // `ref.func` in them), which are stored like null funcrefs
+        if ty != wasmparser::ValType::FuncRef && ty != wasmparser::ValType::ExternRef {
             return Err(wasm_unsupported!(
                 "unsupported table element type: {:?}",
                 ty
@@ -368,26 +468,11 @@ pub fn parse_element_section(
                 table_index,
                 offset_expr,
             } => {
-                let mut init_expr_reader = offset_expr.get_binary_reader();
-                let (base, offset) = match init_expr_reader
-                    .read_operator()
-                    .map_err(from_binaryreadererror_wasmerror)?
-                {
-                    Operator::I32Const { value } => (None, value as u32 as usize),
-                    Operator::GlobalGet { global_index } => {
-                        (Some(GlobalIndex::from_u32(global_index)), 0)
-                    }
-                    ref s => {
-                        return Err(wasm_unsupported!(
-                            "unsupported init expr in element section: {:?}",
-                            s
-                        ));
-                    }
-                };
+                let (base, offset) = read_integer_const_expr(&offset_expr, "element")?;
                 environ.declare_table_initializers(
                     TableIndex::from_u32(table_index),
                     base,
-                    offset,
+                    offset as u64 as usize,
                     segments,
                 )?
             }
@@ -419,26 +504,12 @@ pub fn parse_data_section<'data>(
                 memory_index,
                 offset_expr,
             } => {
-                let mut init_expr_reader = offset_expr.get_binary_reader();
-                let (base, offset) = match init_expr_reader
-                    .read_operator()
-                    .map_err(from_binaryreadererror_wasmerror)?
-                {
-                    Operator::I32Const { value } => (None, value as u32 as usize),
-                    Operator::GlobalGet { global_index } => {
-                        (Some(GlobalIndex::from_u32(global_index)), 0)
-                    }
-                    ref s => {
-                        return Err(wasm_unsupported!(
-                            "unsupported init expr in data section: {:?}",
-                            s
-                        ))
-                    }
-                };
+                // Data segments of 64-bit memories have 64-bit offsets
+                let (base, offset) = read_integer_const_expr(&offset_expr, "data")?;
                 environ.declare_data_initialization(
                     MemoryIndex::from_u32(memory_index),
                     base,
-                    offset,
+                    offset as u64 as usize,
                     data,
                 )?;
             }
@@ -0,0 +1,230 @@
+//! Validate WebAssembly modules against an explicit set of features, with
+//! structured errors.
+
+use crate::lib::std::vec::Vec;
+use wasmer_types::{
+    Features, FunctionIndex, PointerWidth, Triple, ValidationError, ValidationErrorKind,
+};
+use wasmparser::{BinaryReaderError, Parser, ValidPayload, Validator, WasmFeatures};
+
+/// Translate wasmer's [`Features`] into the equivalent [`WasmFeatures`].
+pub(crate) fn wasm_features(features: &Features) -> WasmFeatures {
+    WasmFeatures {
+        bulk_memory: features.bulk_memory,
+        threads: features.threads,
+        reference_types: features.reference_types,
+        multi_value: features.multi_value,
+        simd: features.simd,
+        tail_call: features.tail_call,
+        multi_memory: features.multi_memory,
+        memory64: features.memory64,
+        exceptions: features.exceptions,
+        deterministic_only: false,
+        extended_const: features.extended_const,
+        relaxed_simd: features.relaxed_simd,
+        mutable_global: true,
+        saturating_float_to_int: true,
+        sign_extension: true,
+        component_model: false,
+    }
+}
+
+/// Validate a module using the given features, stopping at the first error.
+///
+/// Proposals which can't be supported on `target` (e.g. `memory64` on a 32-bit
+/// target) are rejected even if they are enabled in `features`.
+pub fn validate_with_features(
+    data: &[u8],
+    features: &Features,
+    target: &Triple,
+) -> Result<(), ValidationError> {
+    match validate(data, features, target, false).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Validate a module using the given features, reporting every invalid
+/// function instead of stopping at the first error.
+///
+/// Errors outside the code section still stop validation, because the rest of
+/// the module can't be checked without them.
+pub fn validate_all_with_features(
+    data: &[u8],
+    features: &Features,
+    target: &Triple,
+) -> Result<(), Vec<ValidationError>> {
+    let errors = validate(data, features, target, true);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Work out which proposals a module uses, by checking which ones it can't be
+/// validated without.
+///
+/// Only the proposals a module needs are enabled in the result, so it can be
+/// compared against [`Engine::features()`][crate::Engine::features] before
+/// compiling.
+///
+/// # Errors
+///
+/// Fails if the module is invalid even with every proposal enabled.
+pub fn required_features(data: &[u8]) -> Result<Features, ValidationError> {
+    if let Some(error) = validate_wasm_features(data, wasm_features(&Features::all()), false)
+        .into_iter()
+        .next()
+    {
+        return Err(error);
+    }
+
+    let mut required = Features::none();
+    for proposal in Features::PROPOSALS {
+        let mut features = Features::all();
+        features.set_enabled(proposal, false);
+        if !validate_wasm_features(data, wasm_features(&features), false).is_empty() {
+            required.set_enabled(proposal, true);
+        }
+    }
+
+    Ok(required)
+}
+
+fn validate(
+    data: &[u8],
+    features: &Features,
+    target: &Triple,
+    collect_all: bool,
+) -> Vec<ValidationError> {
+    let mut wasm_features = wasm_features(features);
+    if !matches!(target.pointer_width(), Ok(PointerWidth::U64)) {
+        wasm_features.memory64 = false;
+    }
+
+    validate_wasm_features(data, wasm_features, collect_all)
+}
+
+fn validate_wasm_features(
+    data: &[u8],
+    wasm_features: WasmFeatures,
+    collect_all: bool,
+) -> Vec<ValidationError> {
+    let mut validator = Validator::new_with_features(wasm_features);
+    let mut errors = Vec::new();
+
+    for payload in Parser::new(0).parse_all(data) {
+        let payload = match payload.and_then(|payload| validator.payload(&payload)) {
+            Ok(payload) => payload,
+            Err(e) => {
+                errors.push(validation_error(&e, None));
+                break;
+            }
+        };
+
+        if let ValidPayload::Func(func, body) = payload {
+            let mut func_validator = func.into_validator(Default::default());
+            let function_index = FunctionIndex::from_u32(func_validator.index());
+
+            if let Err(e) = func_validator.validate(&body) {
+                errors.push(validation_error(&e, Some(function_index)));
+                if !collect_all {
+                    break;
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+fn validation_error(
+    error: &BinaryReaderError,
+    function_index: Option<FunctionIndex>,
+) -> ValidationError {
+    ValidationError {
+        kind: classify(error.message()),
+        message: error.message().into(),
+        offset: error.offset(),
+        function_index,
+    }
+}
+
+/// Work out what kind of error wasmparser reported, based on its message.
+fn classify(message: &str) -> ValidationErrorKind {
+    /// Keywords used in wasmparser's messages, and the proposal they refer
+    /// to. More specific keywords need to come first.
+    const PROPOSALS: &[(&str, &str)] = &[
+        ("relaxed simd", "relaxed-simd"),
+        ("simd", "simd"),
+        ("threads", "threads"),
+        ("shared memor", "threads"),
+        ("atomic", "threads"),
+        ("bulk memory", "bulk-memory"),
+        ("reference types", "reference-types"),
+        ("multi-value", "multi-value"),
+        ("multi value", "multi-value"),
+        ("tail call", "tail-call"),
+        ("multi-memory", "multi-memory"),
+        ("multiple memories", "multi-memory"),
+        ("memory64", "memory64"),
+        ("exceptions", "exceptions"),
+        ("extended const", "extended-const"),
+    ];
+
+    let message = message.to_lowercase();
+
+    if message.starts_with("type mismatch") {
+        return ValidationErrorKind::TypeMismatch;
+    }
+
+    let is_unsupported = ["not enabled", "must be enabled", "proposal"]
+        .iter()
+        .any(|needle| message.contains(needle));
+    if is_unsupported {
+        if let Some((_, proposal)) = PROPOSALS
+            .iter()
+            .find(|(keyword, _)| message.contains(keyword))
+        {
+            return ValidationErrorKind::UnsupportedProposal {
+                proposal: *proposal,
+            };
+        }
+    }
+
+    ValidationErrorKind::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_messages() {
+        assert_eq!(
+            classify("SIMD support is not enabled"),
+            ValidationErrorKind::UnsupportedProposal { proposal: "simd" }
+        );
+        assert_eq!(
+            classify("Relaxed SIMD support is not enabled"),
+            ValidationErrorKind::UnsupportedProposal {
+                proposal: "relaxed-simd"
+            }
+        );
+        assert_eq!(
+            classify("memory64 must be enabled for 64-bit memories"),
+            ValidationErrorKind::UnsupportedProposal {
+                proposal: "memory64"
+            }
+        );
+        assert_eq!(
+            classify("type mismatch: expected i32, found i64"),
+            ValidationErrorKind::TypeMismatch
+        );
+        assert_eq!(
+            classify("unknown function 42: function index out of bounds"),
+            ValidationErrorKind::Other
+        );
+    }
+}
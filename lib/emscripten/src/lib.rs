@@ -81,6 +81,9 @@ pub struct EmscriptenState {
     pub env_vars: HashMap<String, String>,
     /// Command line arguments that this module received
     pub cli_args: Vec<String>,
+    /// Whether the module sees itself as PID 1 with a parent of 0, rather
+    /// than getting the host's PIDs from `getpid` and `getppid`
+    pub pid_namespace: bool,
 }
 
 impl Default for EmscriptenState {
@@ -90,6 +93,7 @@ impl Default for EmscriptenState {
                 .filter_map(|(k, v)| Some((k.to_str()?.to_string(), v.to_str()?.to_string())))
                 .collect(),
             cli_args: Vec::new(),
+            pid_namespace: false,
         }
     }
 }
@@ -172,6 +176,12 @@ impl EmEnv {
         w.env_vars.remove(key)
     }
 
+    /// Whether `getpid` and `getppid` return virtual PIDs (see
+    /// [`EmscriptenState::pid_namespace`]).
+    pub fn pid_namespace(&self) -> bool {
+        self.state.lock().unwrap().pid_namespace
+    }
+
     pub fn get_args_size(&self) -> usize {
         let w = self.state.lock().unwrap();
         w.cli_args.len()
@@ -129,8 +129,11 @@ pub fn ___syscall15(_ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
 }
 
 // getpid
-pub fn ___syscall20(_ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
+pub fn ___syscall20(ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
     debug!("emscripten::___syscall20 (getpid)");
+    if ctx.data().pid_namespace() {
+        return 1;
+    }
     unsafe { getpid() }
 }
 
@@ -264,9 +267,17 @@ pub fn ___syscall63(ctx: FunctionEnvMut<EmEnv>, _which: c_int, mut varargs: VarA
 }
 
 // getppid
-pub fn ___syscall64(_ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
+pub fn ___syscall64(ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
     debug!("emscripten::___syscall64 (getppid)");
-    unsafe { getpid() }
+    if ctx.data().pid_namespace() {
+        return 0;
+    }
+    // Windows has no notion of a parent process to ask for
+    #[cfg(target_os = "windows")]
+    let ppid = unsafe { getpid() };
+    #[cfg(not(target_os = "windows"))]
+    let ppid = unsafe { libc::getppid() };
+    ppid
 }
 
 pub fn ___syscall66(_ctx: FunctionEnvMut<EmEnv>, _one: i32, _two: i32) -> i32 {
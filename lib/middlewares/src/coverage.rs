@@ -0,0 +1,552 @@
+//! `coverage` is a middleware for finding out which parts of a module's
+//! code run, to measure how much of it a test suite covers.
+//!
+//! The code of each function is split into blocks, which start at the
+//! function's entry and after every operator which branches, calls or
+//! starts or ends a structured block. Running a block stores a byte into
+//! a memory added to the module, one byte per block, so this works in
+//! modules with their own memory as well as without.
+//!
+//! [`CoverageReport::add_instance`] reads which blocks of an instance ran,
+//! adding them up over as many instances of the module as it is given.
+//! [`CoverageReport::write_lcov`] writes them in the LCOV format, given a
+//! way to map the offsets of the blocks in the module to source lines,
+//! e.g. with the module's DWARF debug info. Functions without any are
+//! reported by name only.
+//!
+//! The compiler must support the multi-memory proposal, which Cranelift
+//! and LLVM do. Singlepass only supports a single memory, so it refuses to
+//! compile modules which have a memory of their own with this middleware.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::CompilerConfig;
+//! use wasmer_middlewares::Coverage;
+//!
+//! fn enable_coverage(compiler_config: &mut dyn CompilerConfig) {
+//!     compiler_config.push_middleware(Arc::new(Coverage::new()));
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use wasmer::wasmparser::{BinaryReader, BinaryReaderError, MemArg, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, FunctionBodyData, FunctionMiddleware, Instance, LocalFunctionIndex,
+    MemoryType, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, WASM_PAGE_SIZE,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{CustomSectionIndex, FunctionIndex, MemoryIndex, ModuleInfo};
+
+/// The name the memory holding a byte for each block is exported with.
+const FLAGS_EXPORT: &str = "wasmer_coverage_flags";
+
+/// The name of the custom section giving the function and the range of
+/// offsets of each block, as three little-endian `u32`s per block, in the
+/// order of their bytes in the memory.
+const BLOCKS_SECTION: &str = "wasmer_coverage_blocks";
+
+/// The blocks of a function, with the offset in the module they start at
+/// and the index of their byte in the memory.
+type FunctionBlocks = Vec<(usize, u32)>;
+
+/// The module-level coverage middleware.
+///
+/// # Panic
+///
+/// An instance of `Coverage` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the index
+/// of the memory the blocks are recorded in. Attempts to use a `Coverage`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, EngineBuilder, Instance, Module, Store};
+/// use wasmer_middlewares::{coverage::CoverageReport, Coverage};
+///
+/// let mut compiler_config = Cranelift::default();
+/// compiler_config.push_middleware(Arc::new(Coverage::new()));
+/// let mut store = Store::new(EngineBuilder::new(compiler_config));
+///
+/// let wasm = wat2wasm(br#"(module (func (export "run")) (func (export "skip")))"#).unwrap();
+/// let module = Module::new(&store, wasm).unwrap();
+/// let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+/// instance.exports.get_function("run").unwrap().call(&mut store, &[]).unwrap();
+///
+/// let mut report = CoverageReport::new();
+/// report.add_instance(&mut store, &instance);
+/// assert!(report.functions[&0].blocks.values().all(|block| block.hits == 1));
+/// assert!(report.functions[&1].blocks.values().all(|block| block.hits == 0));
+/// ```
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// The memory the blocks are recorded in and the blocks of each local
+    /// function, once the module is known.
+    layout: Mutex<Option<(MemoryIndex, HashMap<LocalFunctionIndex, FunctionBlocks>)>>,
+}
+
+/// The function-level coverage middleware.
+#[derive(Debug)]
+pub struct FunctionCoverage {
+    /// The memory the blocks are recorded in.
+    memory: MemoryIndex,
+
+    /// The blocks of the function being instrumented.
+    blocks: FunctionBlocks,
+
+    /// The index in `blocks` of the next block to instrument.
+    next_block: usize,
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let layout = self.layout.lock().unwrap();
+        let (memory, blocks) = layout
+            .as_ref()
+            .expect("Coverage::generate_function_middleware: The module wasn't transformed yet");
+        Box::new(FunctionCoverage {
+            memory: *memory,
+            blocks: blocks.get(&function_index).cloned().unwrap_or_default(),
+            next_block: 0,
+        })
+    }
+
+    /// Without the bodies of the functions, there are no blocks to record.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        self.transform_module_info_with_bodies(module_info, &PrimaryMap::new());
+    }
+
+    /// Appends the memory the blocks are recorded in, and the custom
+    /// section giving where they are.
+    fn transform_module_info_with_bodies(
+        &self,
+        module_info: &mut ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        let mut layout = self.layout.lock().unwrap();
+
+        if layout.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+
+        let mut section = vec![];
+        let mut all_blocks = HashMap::new();
+        let mut next_slot = 0_u32;
+        for (local_index, body) in bodies.iter() {
+            let function_index = module_info.func_index(local_index).as_u32();
+            // A body which can't be read doesn't compile either
+            let offsets = block_offsets(body).unwrap_or_default();
+            let body_end = body.module_offset + body.data.len();
+
+            let mut blocks = vec![];
+            for (i, &start) in offsets.iter().enumerate() {
+                let end = offsets.get(i + 1).copied().unwrap_or(body_end);
+                for value in [function_index, start as u32, end as u32] {
+                    section.extend_from_slice(&value.to_le_bytes());
+                }
+                blocks.push((start, next_slot));
+                next_slot += 1;
+            }
+            all_blocks.insert(local_index, blocks);
+        }
+
+        let pages = (next_slot as usize + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+        let memory =
+            module_info
+                .memories
+                .push(MemoryType::new(pages as u32, Some(pages as u32), false));
+        module_info
+            .exports
+            .insert(FLAGS_EXPORT.to_string(), ExportIndex::Memory(memory));
+
+        let section_index = CustomSectionIndex::new(module_info.custom_sections_data.len());
+        module_info
+            .custom_sections
+            .insert(BLOCKS_SECTION.to_string(), section_index);
+        module_info
+            .custom_sections_data
+            .push(section.into_boxed_slice());
+        module_info
+            .custom_section_names
+            .push(BLOCKS_SECTION.to_string());
+
+        *layout = Some((memory, all_blocks));
+    }
+
+    fn deterministic_id(&self) -> Option<String> {
+        Some("coverage".to_string())
+    }
+}
+
+/// The offsets in the module of the first operator of each block of a
+/// function's body.
+fn block_offsets(body: &FunctionBodyData<'_>) -> Result<Vec<usize>, BinaryReaderError> {
+    let mut reader = BinaryReader::new_with_offset(body.data, body.module_offset);
+    for _ in 0..reader.read_var_u32()? {
+        reader.read_var_u32()?;
+        reader.read_val_type()?;
+    }
+
+    let mut offsets = vec![];
+    let mut starts_block = true;
+    while !reader.eof() {
+        let offset = reader.original_position();
+        let operator = reader.read_operator()?;
+        if starts_block {
+            offsets.push(offset);
+        }
+        starts_block = ends_block(&operator);
+    }
+    Ok(offsets)
+}
+
+/// Whether the operator after `operator` may run a different number of
+/// times than `operator`.
+fn ends_block(operator: &Operator<'_>) -> bool {
+    matches!(
+        operator,
+        Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Unreachable
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+    )
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // Operators added by an earlier middleware have the offset of the
+        // operator they were added for, so the first of them at the start
+        // of a block records it.
+        if let Some(&(offset, slot)) = self.blocks.get(self.next_block) {
+            if offset == state.operator_offset() {
+                // flags[slot] = 1;
+                state.extend(&[
+                    Operator::I32Const { value: slot as i32 },
+                    Operator::I32Const { value: 1 },
+                    Operator::I32Store8 {
+                        memarg: MemArg {
+                            align: 0,
+                            max_align: 0,
+                            offset: 0,
+                            memory: self.memory.as_u32(),
+                        },
+                    },
+                ]);
+                self.next_block += 1;
+            }
+        }
+
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+/// Which blocks of a module ran, over one or more of its instances.
+///
+/// The memory of an instance only records whether each block ran, not
+/// how many times, so the number of hits of a block is the number of
+/// instances it ran in.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::File;
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::coverage::CoverageReport;
+///
+/// /// Write which functions of the instances ran to `coverage.info`.
+/// fn save_coverage(store: &mut impl AsStoreMut, instances: &[Instance]) -> std::io::Result<()> {
+///     let mut report = CoverageReport::new();
+///     for instance in instances {
+///         report.add_instance(store, instance);
+///     }
+///
+///     let mut file = File::create("coverage.info")?;
+///     report.write_lcov(&mut file, "module.wasm", |_| vec![])
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The blocks of each of the module's local functions, keyed by the
+    /// index of the function in the module.
+    pub functions: BTreeMap<u32, CoveredFunction>,
+}
+
+/// The blocks of a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoveredFunction {
+    /// The name of the function, if the module's name section has one.
+    pub name: Option<String>,
+
+    /// The function's blocks, keyed by the offset in the module they
+    /// start at.
+    pub blocks: BTreeMap<usize, CoveredBlock>,
+}
+
+/// A block of a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoveredBlock {
+    /// The offset in the module the block ends at.
+    pub end: usize,
+
+    /// The number of instances the block ran in.
+    pub hits: u64,
+}
+
+impl CoverageReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the blocks which ran in an [`Instance`][wasmer::Instance] to
+    /// the report. All the instances added to a report should be of the
+    /// same module.
+    ///
+    /// Note: This can be used in a headless engine after an ahead-of-time
+    /// compilation as all required state lives in the instance.
+    ///
+    /// # Panic
+    ///
+    /// The given [`Instance`][wasmer::Instance] must have been processed
+    /// with the [`Coverage`] middleware at compile time, otherwise this
+    /// will panic.
+    pub fn add_instance(&mut self, ctx: &mut impl AsStoreMut, instance: &Instance) {
+        let module = instance.module();
+        let section = module
+            .custom_sections(BLOCKS_SECTION)
+            .next()
+            .expect("Instance wasn't processed with the `Coverage` middleware");
+        let flags = instance
+            .exports
+            .get_memory(FLAGS_EXPORT)
+            .expect("Can't get `wasmer_coverage_flags` from Instance")
+            .view(&*ctx)
+            .copy_to_vec()
+            .expect("Can't read `wasmer_coverage_flags` from Instance");
+
+        let function_names = &module.info().function_names;
+        for (slot, block) in section.chunks_exact(12).enumerate() {
+            let field = |i: usize| u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+            let (function_index, start, end) = (field(0), field(1) as usize, field(2) as usize);
+
+            let function =
+                self.functions
+                    .entry(function_index)
+                    .or_insert_with(|| CoveredFunction {
+                        name: function_names
+                            .get(&FunctionIndex::from_u32(function_index))
+                            .cloned(),
+                        blocks: BTreeMap::new(),
+                    });
+            let block = function
+                .blocks
+                .entry(start)
+                .or_insert(CoveredBlock { end, hits: 0 });
+            if flags[slot] != 0 {
+                block.hits += 1;
+            }
+        }
+    }
+
+    /// Write the report in the LCOV format, which `genhtml` and most
+    /// coverage tools can read.
+    ///
+    /// `source_lines` gives the source lines the code of a block comes
+    /// from, as the path of their file and their line number, given the
+    /// range of offsets of the block in the module. Lines get the hits of
+    /// the block with the most hits among those they come from, and
+    /// functions the hits of their first block, at the first line of that
+    /// block.
+    ///
+    /// Functions without any source line are written at line 0 of a file
+    /// called `module_name`, and are called `wasm-function[<index>]` if
+    /// they don't have a name.
+    pub fn write_lcov<F>(
+        &self,
+        out: &mut impl Write,
+        module_name: &str,
+        mut source_lines: F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(Range<usize>) -> Vec<(PathBuf, u64)>,
+    {
+        let mut files: BTreeMap<PathBuf, LcovFile> = BTreeMap::new();
+        let mut unmapped = LcovFile::default();
+
+        for (function_index, function) in &self.functions {
+            let name = match &function.name {
+                Some(name) => name.clone(),
+                None => format!("wasm-function[{}]", function_index),
+            };
+            let entry_hits = function
+                .blocks
+                .values()
+                .next()
+                .map_or(0, |block| block.hits);
+
+            let mut first_line = None;
+            for (start, block) in &function.blocks {
+                for (path, line) in source_lines(*start..block.end) {
+                    let file = files.entry(path.clone()).or_default();
+                    let hits = file.lines.entry(line).or_default();
+                    *hits = (*hits).max(block.hits);
+                    first_line.get_or_insert((path, line));
+                }
+            }
+
+            match first_line {
+                Some((path, line)) => files
+                    .get_mut(&path)
+                    .unwrap()
+                    .functions
+                    .push((line, name, entry_hits)),
+                None => unmapped.functions.push((0, name, entry_hits)),
+            }
+        }
+
+        for (path, file) in &files {
+            file.write(out, &path.display().to_string())?;
+        }
+        if !unmapped.functions.is_empty() {
+            unmapped.write(out, module_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The records of a source file in an LCOV file.
+#[derive(Debug, Default)]
+struct LcovFile {
+    /// The line, name and hits of each function.
+    functions: Vec<(u64, String, u64)>,
+
+    /// The hits of each line.
+    lines: BTreeMap<u64, u64>,
+}
+
+impl LcovFile {
+    fn write(&self, out: &mut impl Write, path: &str) -> io::Result<()> {
+        writeln!(out, "SF:{}", path)?;
+        for (line, name, _) in &self.functions {
+            writeln!(out, "FN:{},{}", line, name)?;
+        }
+        for (_, name, hits) in &self.functions {
+            writeln!(out, "FNDA:{},{}", hits, name)?;
+        }
+        writeln!(out, "FNF:{}", self.functions.len())?;
+        let functions_hit = self.functions.iter().filter(|(_, _, hits)| *hits > 0);
+        writeln!(out, "FNH:{}", functions_hit.count())?;
+        for (line, hits) in &self.lines {
+            writeln!(out, "DA:{},{}", line, hits)?;
+        }
+        writeln!(out, "LF:{}", self.lines.len())?;
+        let lines_hit = self.lines.values().filter(|hits| **hits > 0);
+        writeln!(out, "LH:{}", lines_hit.count())?;
+        writeln!(out, "end_of_record")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> CoverageReport {
+        let mut report = CoverageReport::new();
+        report.functions.insert(
+            1,
+            CoveredFunction {
+                name: Some("classify".to_string()),
+                blocks: [
+                    (0x40, CoveredBlock { end: 0x48, hits: 2 }),
+                    (0x48, CoveredBlock { end: 0x50, hits: 2 }),
+                    (0x50, CoveredBlock { end: 0x58, hits: 0 }),
+                ]
+                .into_iter()
+                .collect(),
+            },
+        );
+        report.functions.insert(
+            2,
+            CoveredFunction {
+                name: None,
+                blocks: [(0x60, CoveredBlock { end: 0x70, hits: 1 })]
+                    .into_iter()
+                    .collect(),
+            },
+        );
+        report
+    }
+
+    #[test]
+    fn lcov_format() {
+        let mut out = vec![];
+        report()
+            .write_lcov(&mut out, "module.wasm", |range| match range.start {
+                // The first two blocks share a line
+                0x40 => vec![(PathBuf::from("/src/lib.rs"), 3)],
+                0x48 => vec![
+                    (PathBuf::from("/src/lib.rs"), 3),
+                    (PathBuf::from("/src/lib.rs"), 4),
+                ],
+                0x50 => vec![(PathBuf::from("/src/lib.rs"), 6)],
+                _ => vec![],
+            })
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "SF:/src/lib.rs\n\
+             FN:3,classify\n\
+             FNDA:2,classify\n\
+             FNF:1\n\
+             FNH:1\n\
+             DA:3,2\n\
+             DA:4,2\n\
+             DA:6,0\n\
+             LF:3\n\
+             LH:2\n\
+             end_of_record\n\
+             SF:module.wasm\n\
+             FN:0,wasm-function[2]\n\
+             FNDA:1,wasm-function[2]\n\
+             FNF:1\n\
+             FNH:1\n\
+             LF:0\n\
+             LH:0\n\
+             end_of_record\n"
+        );
+    }
+}
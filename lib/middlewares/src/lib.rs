@@ -1,6 +1,10 @@
+pub mod coverage;
 pub mod metering;
+pub mod profiling;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use coverage::Coverage;
 pub use metering::Metering;
+pub use profiling::Profiling;
@@ -7,19 +7,117 @@
 //!
 //! [See the `metering` detailed and complete
 //! example](https://github.com/wasmerio/wasmer/blob/master/examples/metering.rs).
+//!
+//! # Cost functions
+//!
+//! The cost of each operator is given by a [`CostFunction`]. Closures
+//! taking the operator are cost functions, and so are closures which also
+//! take the [`OperatorContext`] (where the operator is) when wrapped in
+//! [`WithContext`]. [`CostSchedule`] has built-in costs, either the same for
+//! every operator or looked up by [`OperatorGroup`], which can be loaded
+//! from a file.
+//!
+//! # Per-function accounting
+//!
+//! With [`Metering::with_per_function_accounting`], the points spent in
+//! each function are kept apart, and given by [`get_per_function_cost`].
+//! A callback set with [`set_exhaustion_callback`] is then told in which
+//! function the points ran out, before the trap unwinds.
+
+mod schedule;
 
+pub use self::schedule::{CostSchedule, OperatorGroup, ScheduleError};
+
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::{BlockType as WpTypeOrFuncType, Operator};
 use wasmer::{
-    AsStoreMut, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance,
-    LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+    AsStoreMut, ExportIndex, Extern, Function, FunctionEnv, FunctionEnvMut, FunctionMiddleware,
+    FunctionType, Global, GlobalInit, GlobalType, Instance, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, Module, ModuleMiddleware, Mutability, TableType, Type, Value,
 };
-use wasmer_types::{GlobalIndex, ModuleInfo};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::{FunctionIndex, GlobalIndex, ModuleInfo, SignatureIndex, TableIndex};
+
+/// The prefix of the names the points of each function are exported
+/// with, which end with the index of the function.
+const FUNCTION_POINTS_PREFIX: &str = "wasmer_metering_function_points_";
+
+/// The name the table holding the exhaustion handler is exported with.
+const EXHAUSTION_HANDLER: &str = "wasmer_metering_exhaustion_handler";
+
+/// Where an operator is, for the cost functions which depend on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatorContext<'a> {
+    /// The index of the function the operator is in.
+    pub function_index: LocalFunctionIndex,
+
+    /// The name of the function, if the module's name section has one.
+    pub function_name: Option<&'a str>,
+
+    /// The offset of the operator from the start of the module.
+    pub offset: usize,
+}
+
+/// Maps each operator to a cost in "points".
+pub trait CostFunction: Send + Sync {
+    /// The cost of `operator`, which is at `context`.
+    fn cost(&self, operator: &Operator, context: &OperatorContext) -> u64;
+
+    /// A hash of the costs this charges, or `None` if they aren't known
+    /// ahead of time (as for closures).
+    ///
+    /// It's part of the [`Metering`] middleware's
+    /// [`ModuleMiddleware::deterministic_id`], so that artifacts metered
+    /// with different costs are told apart.
+    fn schedule_hash(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<F: Fn(&Operator) -> u64 + Send + Sync> CostFunction for F {
+    fn cost(&self, operator: &Operator, _context: &OperatorContext) -> u64 {
+        self(operator)
+    }
+}
+
+/// Makes a closure which also takes the [`OperatorContext`] a
+/// [`CostFunction`].
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::wasmparser::Operator;
+/// use wasmer_middlewares::metering::{Metering, OperatorContext, WithContext};
+///
+/// // Calls cost more in the functions of the `shim` module
+/// let metering = Metering::new(
+///     1_000,
+///     WithContext(|operator: &Operator, context: &OperatorContext| -> u64 {
+///         match operator {
+///             Operator::Call { .. }
+///                 if context.function_name.map_or(false, |name| name.starts_with("shim::")) =>
+///             {
+///                 10
+///             }
+///             _ => 1,
+///         }
+///     }),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithContext<F>(pub F);
+
+impl<F: Fn(&Operator, &OperatorContext) -> u64 + Send + Sync> CostFunction for WithContext<F> {
+    fn cost(&self, operator: &Operator, context: &OperatorContext) -> u64 {
+        (self.0)(operator, context)
+    }
+}
 
 #[derive(Clone)]
-struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex);
+struct MeteringGlobalIndexes(GlobalIndex, GlobalIndex, Option<GlobalIndex>);
 
 impl MeteringGlobalIndexes {
     /// The global index in the current module for remaining points.
@@ -35,6 +133,13 @@ impl MeteringGlobalIndexes {
     fn points_exhausted(&self) -> GlobalIndex {
         self.1
     }
+
+    /// The global index in the current module which holds the number of
+    /// pages `memory.grow` is called with while it's charged for, as an
+    /// `i64`, if growing memory is charged per page.
+    fn grow_pages(&self) -> Option<GlobalIndex> {
+        self.2
+    }
 }
 
 impl fmt::Debug for MeteringGlobalIndexes {
@@ -42,10 +147,36 @@ impl fmt::Debug for MeteringGlobalIndexes {
         f.debug_struct("MeteringGlobalIndexes")
             .field("remaining_points", &self.remaining_points())
             .field("points_exhausted", &self.points_exhausted())
+            .field("grow_pages", &self.grow_pages())
             .finish()
     }
 }
 
+/// What's added to a module to account for the points of each function.
+#[derive(Clone, Debug)]
+struct FunctionAccounting {
+    /// The global holding the points of the first local function. Those of
+    /// the other local functions follow it.
+    first_points: GlobalIndex,
+
+    /// The table holding the exhaustion handler, if one is set.
+    handler_table: TableIndex,
+
+    /// The signature of the exhaustion handler, which takes the index of
+    /// the function the points ran out in.
+    handler_signature: SignatureIndex,
+
+    /// The number of functions the module imports.
+    num_imported_functions: usize,
+}
+
+impl FunctionAccounting {
+    /// The global holding the points of the function.
+    fn points(&self, function_index: LocalFunctionIndex) -> GlobalIndex {
+        GlobalIndex::new(self.first_points.index() + function_index.index())
+    }
+}
+
 /// The module-level metering middleware.
 ///
 /// # Panic
@@ -80,25 +211,57 @@ impl fmt::Debug for MeteringGlobalIndexes {
 ///     compiler_config.push_middleware(metering);
 /// }
 /// ```
-pub struct Metering<F: Fn(&Operator) -> u64 + Send + Sync> {
+pub struct Metering<F: CostFunction> {
     /// Initial limit of points.
     initial_limit: u64,
 
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// The points charged for each page `memory.grow` is asked for.
+    grow_cost_per_page: u32,
+
+    /// Whether the points of each function are accounted for.
+    per_function_accounting: bool,
+
     /// The global indexes for metering points.
     global_indexes: Mutex<Option<MeteringGlobalIndexes>>,
+
+    /// What's added to the module for per-function accounting, if it's
+    /// enabled.
+    accounting: Mutex<Option<FunctionAccounting>>,
+
+    /// The names of the module's functions, from its name section.
+    function_names: Mutex<HashMap<LocalFunctionIndex, Arc<str>>>,
+
+    /// Whether each of the module's memories is a 64-bit one.
+    memory64: Mutex<Arc<[bool]>>,
 }
 
 /// The function-level metering middleware.
-pub struct FunctionMetering<F: Fn(&Operator) -> u64 + Send + Sync> {
+pub struct FunctionMetering<F: CostFunction> {
     /// Function that maps each operator to a cost in "points".
     cost_function: Arc<F>,
 
+    /// The points charged for each page `memory.grow` is asked for.
+    grow_cost_per_page: u32,
+
     /// The global indexes for metering points.
     global_indexes: MeteringGlobalIndexes,
 
+    /// What's added to the module for per-function accounting, if it's
+    /// enabled.
+    accounting: Option<FunctionAccounting>,
+
+    /// The function being metered.
+    function_index: LocalFunctionIndex,
+
+    /// The name of the function being metered, if it has one.
+    function_name: Option<Arc<str>>,
+
+    /// Whether each of the module's memories is a 64-bit one.
+    memory64: Arc<[bool]>,
+
     /// Accumulated cost of the current basic block.
     accumulated_cost: u64,
 }
@@ -122,33 +285,77 @@ pub enum MeteringPoints {
     Exhausted,
 }
 
-impl<F: Fn(&Operator) -> u64 + Send + Sync> Metering<F> {
+impl<F: CostFunction> Metering<F> {
     /// Creates a `Metering` middleware.
     pub fn new(initial_limit: u64, cost_function: F) -> Self {
         Self {
             initial_limit,
             cost_function: Arc::new(cost_function),
+            grow_cost_per_page: 0,
+            per_function_accounting: false,
             global_indexes: Mutex::new(None),
+            accounting: Mutex::new(None),
+            function_names: Mutex::new(HashMap::new()),
+            memory64: Mutex::new(Arc::from([])),
         }
     }
+
+    /// Charges `points_per_page` for each page `memory.grow` is asked for,
+    /// on top of the cost of the operator itself.
+    ///
+    /// The pages are only known when `memory.grow` runs, so this is
+    /// charged separately from the cost of the rest of the block, just
+    /// before memory grows. Memory doesn't grow when the points run out.
+    pub fn with_memory_grow_cost(mut self, points_per_page: u32) -> Self {
+        self.grow_cost_per_page = points_per_page;
+        self
+    }
+
+    /// Accounts for the points spent in each function, which are then
+    /// given by [`get_per_function_cost`], and allows a callback to be set
+    /// with [`set_exhaustion_callback`].
+    ///
+    /// This adds a global to the module for each of its functions, and
+    /// the points are added to them whenever they are charged, so metered
+    /// code runs a little slower.
+    pub fn with_per_function_accounting(mut self) -> Self {
+        self.per_function_accounting = true;
+        self
+    }
 }
 
-impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for Metering<F> {
+impl<F: CostFunction> fmt::Debug for Metering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Metering")
             .field("initial_limit", &self.initial_limit)
             .field("cost_function", &"<function>")
+            .field("grow_cost_per_page", &self.grow_cost_per_page)
+            .field("per_function_accounting", &self.per_function_accounting)
             .field("global_indexes", &self.global_indexes)
+            .field("accounting", &self.accounting)
             .finish()
     }
 }
 
-impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Metering<F> {
+impl<F: CostFunction + 'static> ModuleMiddleware for Metering<F> {
     /// Generates a `FunctionMiddleware` for a given function.
-    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+    fn generate_function_middleware(
+        &self,
+        function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
         Box::new(FunctionMetering {
             cost_function: self.cost_function.clone(),
+            grow_cost_per_page: self.grow_cost_per_page,
             global_indexes: self.global_indexes.lock().unwrap().clone().unwrap(),
+            accounting: self.accounting.lock().unwrap().clone(),
+            function_index,
+            function_name: self
+                .function_names
+                .lock()
+                .unwrap()
+                .get(&function_index)
+                .cloned(),
+            memory64: self.memory64.lock().unwrap().clone(),
             accumulated_cost: 0,
         })
     }
@@ -189,23 +396,184 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
             ExportIndex::Global(points_exhausted_global_index),
         );
 
+        // Append a global to keep the operand of `memory.grow` in while
+        // it's charged for.
+        let grow_pages_global_index = if self.grow_cost_per_page > 0 {
+            let index = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+            Some(index)
+        } else {
+            None
+        };
+
         *global_indexes = Some(MeteringGlobalIndexes(
             remaining_points_global_index,
             points_exhausted_global_index,
-        ))
+            grow_pages_global_index,
+        ));
+
+        if self.per_function_accounting {
+            // Append a global for the points of each local function.
+            let num_imported_functions = module_info.num_imported_functions;
+            let num_local_functions = module_info.functions.len() - num_imported_functions;
+            let first_points = GlobalIndex::new(module_info.globals.len());
+            for local_index in 0..num_local_functions {
+                let index = module_info
+                    .globals
+                    .push(GlobalType::new(Type::I64, Mutability::Var));
+                module_info
+                    .global_initializers
+                    .push(GlobalInit::I64Const(0));
+                let function_index = num_imported_functions + local_index;
+                module_info.exports.insert(
+                    format!("{}{}", FUNCTION_POINTS_PREFIX, function_index),
+                    ExportIndex::Global(index),
+                );
+            }
+
+            // Append an empty table for the exhaustion handler, which is
+            // grown to hold it when it's set.
+            let handler_table = module_info
+                .tables
+                .push(TableType::new(Type::FuncRef, 0, Some(1)));
+            module_info.exports.insert(
+                EXHAUSTION_HANDLER.to_string(),
+                ExportIndex::Table(handler_table),
+            );
+            let handler_signature = module_info
+                .signatures
+                .push(FunctionType::new([Type::I32], []));
+
+            *self.accounting.lock().unwrap() = Some(FunctionAccounting {
+                first_points,
+                handler_table,
+                handler_signature,
+                num_imported_functions,
+            });
+        }
+
+        *self.function_names.lock().unwrap() = module_info
+            .function_names
+            .iter()
+            .filter_map(|(index, name)| {
+                let index = module_info.local_func_index(*index)?;
+                Some((index, Arc::from(name.as_str())))
+            })
+            .collect();
+        *self.memory64.lock().unwrap() = module_info
+            .memories
+            .values()
+            .map(|memory| memory.memory64)
+            .collect();
+    }
+
+    /// The initial limit, the cost of growing memory, whether functions
+    /// are accounted for and the hash of the costs, when they are known.
+    fn deterministic_id(&self) -> Option<String> {
+        let hash = self.cost_function.schedule_hash()?;
+        let mut id = format!("metering-{}-{:016x}", self.initial_limit, hash);
+        if self.grow_cost_per_page > 0 {
+            id.push_str(&format!("-grow-{}", self.grow_cost_per_page));
+        }
+        if self.per_function_accounting {
+            id.push_str("-per-function");
+        }
+        Some(id)
     }
 }
 
-impl<F: Fn(&Operator) -> u64 + Send + Sync> fmt::Debug for FunctionMetering<F> {
+impl<F: CostFunction> fmt::Debug for FunctionMetering<F> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FunctionMetering")
             .field("cost_function", &"<function>")
+            .field("grow_cost_per_page", &self.grow_cost_per_page)
             .field("global_indexes", &self.global_indexes)
+            .field("accounting", &self.accounting)
+            .field("function_index", &self.function_index)
+            .field("function_name", &self.function_name)
             .finish()
     }
 }
 
-impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMetering<F> {
+impl<F: CostFunction> FunctionMetering<F> {
+    /// Pushes the operators which charge the points `cost` pushes onto the
+    /// stack, or trap if there aren't enough of them left.
+    fn charge<'a>(&self, state: &mut MiddlewareReaderState<'a>, cost: &[Operator<'a>]) {
+        let remaining_points = self.global_indexes.remaining_points().as_u32();
+
+        // if unsigned(globals[remaining_points_index]) < unsigned(cost) { throw(); }
+        state.push_operator(Operator::GlobalGet {
+            global_index: remaining_points,
+        });
+        state.extend(cost);
+        state.extend(&[
+            Operator::I64LtU,
+            Operator::If {
+                blockty: WpTypeOrFuncType::Empty,
+            },
+            Operator::I32Const { value: 1 },
+            Operator::GlobalSet {
+                global_index: self.global_indexes.points_exhausted().as_u32(),
+            },
+        ]);
+        if let Some(accounting) = &self.accounting {
+            // if table.size(handler_table) > 0 { handler_table[0](function_index); }
+            let function_index = accounting.num_imported_functions + self.function_index.index();
+            state.extend(&[
+                Operator::TableSize {
+                    table: accounting.handler_table.as_u32(),
+                },
+                Operator::If {
+                    blockty: WpTypeOrFuncType::Empty,
+                },
+                Operator::I32Const {
+                    value: function_index as i32,
+                },
+                Operator::I32Const { value: 0 },
+                Operator::CallIndirect {
+                    type_index: accounting.handler_signature.as_u32(),
+                    table_index: accounting.handler_table.as_u32(),
+                    table_byte: 0,
+                },
+                Operator::End,
+            ]);
+        }
+        state.extend(&[Operator::Unreachable, Operator::End]);
+
+        // globals[remaining_points_index] -= cost;
+        state.push_operator(Operator::GlobalGet {
+            global_index: remaining_points,
+        });
+        state.extend(cost);
+        state.extend(&[
+            Operator::I64Sub,
+            Operator::GlobalSet {
+                global_index: remaining_points,
+            },
+        ]);
+
+        if let Some(accounting) = &self.accounting {
+            // globals[function_points_index] += cost;
+            let function_points = accounting.points(self.function_index).as_u32();
+            state.push_operator(Operator::GlobalGet {
+                global_index: function_points,
+            });
+            state.extend(cost);
+            state.extend(&[
+                Operator::I64Add,
+                Operator::GlobalSet {
+                    global_index: function_points,
+                },
+            ]);
+        }
+    }
+}
+
+impl<F: CostFunction> FunctionMiddleware for FunctionMetering<F> {
     fn feed<'a>(
         &mut self,
         operator: Operator<'a>,
@@ -214,7 +582,12 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
         // Get the cost of the current operator, and add it to the accumulator.
         // This needs to be done before the metering logic, to prevent operators like `Call` from escaping metering in some
         // corner cases.
-        self.accumulated_cost += (self.cost_function)(&operator);
+        let context = OperatorContext {
+            function_index: self.function_index,
+            function_name: self.function_name.as_deref(),
+            offset: state.operator_offset(),
+        };
+        self.accumulated_cost += self.cost_function.cost(&operator, &context);
 
         // Possible sources and targets of a branch. Finalize the cost of the previous basic block and perform necessary checks.
         match operator {
@@ -229,27 +602,30 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync> FunctionMiddleware for FunctionMeter
             | Operator::Return // end of function - branch source
             => {
                 if self.accumulated_cost > 0 {
-                    state.extend(&[
-                        // if unsigned(globals[remaining_points_index]) < unsigned(self.accumulated_cost) { throw(); }
-                        Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
-                        Operator::I64Const { value: self.accumulated_cost as i64 },
-                        Operator::I64LtU,
-                        Operator::If { blockty: WpTypeOrFuncType::Empty },
-                        Operator::I32Const { value: 1 },
-                        Operator::GlobalSet { global_index: self.global_indexes.points_exhausted().as_u32() },
-                        Operator::Unreachable,
-                        Operator::End,
-
-                        // globals[remaining_points_index] -= self.accumulated_cost;
-                        Operator::GlobalGet { global_index: self.global_indexes.remaining_points().as_u32() },
-                        Operator::I64Const { value: self.accumulated_cost as i64 },
-                        Operator::I64Sub,
-                        Operator::GlobalSet { global_index: self.global_indexes.remaining_points().as_u32() },
-                    ]);
-
+                    self.charge(state, &[Operator::I64Const { value: self.accumulated_cost as i64 }]);
                     self.accumulated_cost = 0;
                 }
             }
+            Operator::MemoryGrow { mem, .. } => {
+                if let Some(grow_pages) = self.global_indexes.grow_pages() {
+                    let memory64 = self.memory64.get(mem as usize).copied().unwrap_or(false);
+                    if !memory64 {
+                        state.push_operator(Operator::I64ExtendI32U);
+                    }
+                    state.push_operator(Operator::GlobalSet { global_index: grow_pages.as_u32() });
+                    self.charge(state, &[
+                        // globals[grow_pages_index] * self.grow_cost_per_page
+                        Operator::GlobalGet { global_index: grow_pages.as_u32() },
+                        Operator::I64Const { value: self.grow_cost_per_page as i64 },
+                        Operator::I64Mul,
+                    ]);
+                    // Put the operand back
+                    state.push_operator(Operator::GlobalGet { global_index: grow_pages.as_u32() });
+                    if !memory64 {
+                        state.push_operator(Operator::I32WrapI64);
+                    }
+                }
+            }
             _ => {}
         }
         state.push_operator(operator);
@@ -348,6 +724,172 @@ pub fn set_remaining_points(ctx: &mut impl AsStoreMut, instance: &Instance, poin
         .expect("Can't set `wasmer_metering_points_exhausted` in Instance");
 }
 
+/// Get the points spent in each function of an
+/// [`Instance`][wasmer::Instance], as pairs of the index of the function in
+/// the module and its points, most first.
+///
+/// Only the functions which have spent points are given. Their names can
+/// be looked up with [`function_name`]. The points of the code which ran
+/// when the points were exhausted aren't included.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Metering`] middleware at compile time, with
+/// [`Metering::with_per_function_accounting`], otherwise this will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::metering::{function_name, get_per_function_cost};
+///
+/// /// Print the functions which spent the most points.
+/// fn print_hot_functions(store: &mut impl AsStoreMut, instance: &Instance) {
+///     for (index, points) in get_per_function_cost(store, instance).iter().take(10) {
+///         let name = function_name(instance.module(), *index).unwrap_or("<unnamed>");
+///         println!("{} (function {}): {} points", name, index, points);
+///     }
+/// }
+/// ```
+pub fn get_per_function_cost(ctx: &mut impl AsStoreMut, instance: &Instance) -> Vec<(u32, u64)> {
+    per_function_cost(ctx, &function_points(instance))
+}
+
+/// The name of the function at `function_index` in the module, from its
+/// name section.
+pub fn function_name(module: &Module, function_index: u32) -> Option<&str> {
+    module
+        .info()
+        .function_names
+        .get(&FunctionIndex::from_u32(function_index))
+        .map(String::as_str)
+}
+
+/// Where the points of an [`Instance`][wasmer::Instance] ran out, as given
+/// to the callback set with [`set_exhaustion_callback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exhaustion {
+    /// The index in the module of the function the points ran out in.
+    pub function_index: u32,
+
+    /// The name of the function, if the module's name section has one.
+    pub function_name: Option<String>,
+
+    /// The points spent in each function, as given by
+    /// [`get_per_function_cost`].
+    pub per_function_cost: Vec<(u32, u64)>,
+}
+
+/// Set a callback to be called when the points of an
+/// [`Instance`][wasmer::Instance] are exhausted, from the code which traps.
+///
+/// The callback is called before the trap unwinds the instance's stack,
+/// and replaces the one set before, if any.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Metering`] middleware at compile time, with
+/// [`Metering::with_per_function_accounting`], otherwise this will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::metering::set_exhaustion_callback;
+///
+/// fn report_exhaustion(store: &mut impl AsStoreMut, instance: &Instance) {
+///     set_exhaustion_callback(store, instance, |exhaustion| {
+///         eprintln!(
+///             "Out of points in {}, with {:?}",
+///             exhaustion.function_name.as_deref().unwrap_or("<unnamed>"),
+///             exhaustion.per_function_cost,
+///         );
+///     });
+/// }
+/// ```
+pub fn set_exhaustion_callback<C>(ctx: &mut impl AsStoreMut, instance: &Instance, callback: C)
+where
+    C: Fn(&Exhaustion) + Send + Sync + 'static,
+{
+    let env = FunctionEnv::new(
+        ctx,
+        ExhaustionEnv {
+            module: instance.module().clone(),
+            function_points: function_points(instance),
+            callback: Box::new(callback),
+        },
+    );
+    let handler = Value::FuncRef(Some(Function::new_typed_with_env(ctx, &env, on_exhaustion)));
+
+    let table = instance
+        .exports
+        .get_table(EXHAUSTION_HANDLER)
+        .expect("Can't get `wasmer_metering_exhaustion_handler` from Instance");
+    let result = if table.size(ctx) == 0 {
+        table.grow(ctx, 1, handler).map(drop)
+    } else {
+        table.set(ctx, 0, handler)
+    };
+    result.expect("Can't set `wasmer_metering_exhaustion_handler` in Instance");
+}
+
+/// The environment of the exhaustion handler.
+struct ExhaustionEnv {
+    module: Module,
+    function_points: Vec<(u32, Global)>,
+    callback: Box<dyn Fn(&Exhaustion) + Send + Sync>,
+}
+
+fn on_exhaustion(mut env: FunctionEnvMut<ExhaustionEnv>, function_index: u32) {
+    let (env, mut store) = env.data_and_store_mut();
+    let exhaustion = Exhaustion {
+        function_index,
+        function_name: function_name(&env.module, function_index).map(str::to_string),
+        per_function_cost: per_function_cost(&mut store, &env.function_points),
+    };
+    (env.callback)(&exhaustion);
+}
+
+/// The globals holding the points of each function of the instance, with
+/// the index of the function.
+fn function_points(instance: &Instance) -> Vec<(u32, Global)> {
+    assert!(
+        instance.exports.get_table(EXHAUSTION_HANDLER).is_ok(),
+        "The points of each function aren't accounted for in Instance"
+    );
+    instance
+        .exports
+        .iter()
+        .filter_map(|(name, export)| {
+            let function_index = name.strip_prefix(FUNCTION_POINTS_PREFIX)?.parse().ok()?;
+            match export {
+                Extern::Global(global) => Some((function_index, global.clone())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn per_function_cost(
+    ctx: &mut impl AsStoreMut,
+    function_points: &[(u32, Global)],
+) -> Vec<(u32, u64)> {
+    let mut costs: Vec<(u32, u64)> = function_points
+        .iter()
+        .filter_map(|(function_index, global)| {
+            let points: u64 = global
+                .get(ctx)
+                .try_into()
+                .expect("The points of a function from Instance have the wrong type");
+            (points > 0).then(|| (*function_index, points))
+        })
+        .collect();
+    costs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    costs
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,465 @@
+//! Built-in cost functions for the [`Metering`](super::Metering)
+//! middleware.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use wasmer::wasmparser::Operator;
+
+use super::{CostFunction, OperatorContext};
+
+/// A group of related operators, which cost the same in a
+/// [`CostSchedule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OperatorGroup {
+    /// Blocks, branches, `return`, `drop`, `select`, `nop` and
+    /// `unreachable`, and the exception handling operators.
+    Control,
+    /// `call`, `call_indirect` and their `return_call` variants.
+    Call,
+    /// `local.get`, `local.set` and `local.tee`.
+    Local,
+    /// `global.get` and `global.set`.
+    Global,
+    /// `i32.const`, `i64.const`, `f32.const` and `f64.const`.
+    Const,
+    /// Integer arithmetic, bitwise operators and comparisons, except
+    /// divisions.
+    Integer,
+    /// Integer divisions and remainders.
+    IntegerDivision,
+    /// Floating point arithmetic and comparisons.
+    Float,
+    /// Conversions between value types.
+    Conversion,
+    /// Loads from memory.
+    Load,
+    /// Stores to memory.
+    Store,
+    /// `memory.size` and `memory.grow`.
+    Memory,
+    /// `memory.init`, `memory.copy`, `memory.fill` and `data.drop`.
+    BulkMemory,
+    /// The table operators and `elem.drop`.
+    Table,
+    /// `ref.null`, `ref.is_null` and `ref.func`.
+    Reference,
+    /// The SIMD operators.
+    Simd,
+    /// The atomic operators of the threads proposal.
+    Atomic,
+}
+
+impl OperatorGroup {
+    /// Every group.
+    pub const ALL: [Self; 17] = [
+        Self::Control,
+        Self::Call,
+        Self::Local,
+        Self::Global,
+        Self::Const,
+        Self::Integer,
+        Self::IntegerDivision,
+        Self::Float,
+        Self::Conversion,
+        Self::Load,
+        Self::Store,
+        Self::Memory,
+        Self::BulkMemory,
+        Self::Table,
+        Self::Reference,
+        Self::Simd,
+        Self::Atomic,
+    ];
+
+    /// The group `operator` belongs to.
+    pub fn of(operator: &Operator) -> Self {
+        match operator {
+            Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. } => return Self::Call,
+            Operator::LocalGet { .. } | Operator::LocalSet { .. } | Operator::LocalTee { .. } => {
+                return Self::Local
+            }
+            Operator::GlobalGet { .. } | Operator::GlobalSet { .. } => return Self::Global,
+            Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::F32Const { .. }
+            | Operator::F64Const { .. } => return Self::Const,
+            Operator::MemorySize { .. } | Operator::MemoryGrow { .. } => return Self::Memory,
+            Operator::MemoryInit { .. }
+            | Operator::MemoryCopy { .. }
+            | Operator::MemoryFill { .. }
+            | Operator::DataDrop { .. } => return Self::BulkMemory,
+            Operator::RefNull { .. } | Operator::RefIsNull | Operator::RefFunc { .. } => {
+                return Self::Reference
+            }
+            _ => {}
+        }
+
+        // The rest are told apart by their names, e.g. `I32TruncF64S`
+        let debug = format!("{:?}", operator);
+        let name = debug
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .next()
+            .unwrap_or_default();
+        let ty = name.get(..3).unwrap_or_default();
+        let rest = name.get(3..).unwrap_or_default();
+        let is_value_type = |s: &str| ["I32", "I64", "F32", "F64"].iter().any(|t| s.contains(t));
+
+        if ["V128", "I8x16", "I16x8", "I32x4", "I64x2", "F32x4", "F64x2"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            Self::Simd
+        } else if name.contains("Atomic") {
+            Self::Atomic
+        } else if name.starts_with("Table") || name == "ElemDrop" {
+            Self::Table
+        } else if name.contains("Load") {
+            Self::Load
+        } else if name.contains("Store") {
+            Self::Store
+        } else if is_value_type(ty) && is_value_type(rest) {
+            Self::Conversion
+        } else if ty == "I32" || ty == "I64" {
+            if rest.starts_with("Div") || rest.starts_with("Rem") {
+                Self::IntegerDivision
+            } else {
+                Self::Integer
+            }
+        } else if ty == "F32" || ty == "F64" {
+            Self::Float
+        } else {
+            Self::Control
+        }
+    }
+
+    /// The group's name in schedule files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Control => "control",
+            Self::Call => "call",
+            Self::Local => "local",
+            Self::Global => "global",
+            Self::Const => "const",
+            Self::Integer => "integer",
+            Self::IntegerDivision => "integer_division",
+            Self::Float => "float",
+            Self::Conversion => "conversion",
+            Self::Load => "load",
+            Self::Store => "store",
+            Self::Memory => "memory",
+            Self::BulkMemory => "bulk_memory",
+            Self::Table => "table",
+            Self::Reference => "reference",
+            Self::Simd => "simd",
+            Self::Atomic => "atomic",
+        }
+    }
+}
+
+impl FromStr for OperatorGroup {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|group| group.name() == s)
+            .ok_or_else(|| ScheduleError::UnknownGroup(s.to_string()))
+    }
+}
+
+impl fmt::Display for OperatorGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A cost function with the same cost for every operator, or a cost for
+/// each [`OperatorGroup`].
+///
+/// Schedules can be loaded from TOML files with a `default` cost, for the
+/// operators whose group isn't listed, and the costs of the groups in a
+/// `[groups]` table:
+///
+/// ```toml
+/// default = 1
+///
+/// [groups]
+/// call = 10
+/// integer_division = 4
+/// memory = 100
+/// ```
+///
+/// Unlike closures, schedules have a [`CostFunction::schedule_hash`], so
+/// artifacts compiled with different schedules are told apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CostSchedule {
+    default: u64,
+    groups: BTreeMap<OperatorGroup, u64>,
+}
+
+impl CostSchedule {
+    /// A schedule where every operator costs `cost`.
+    pub fn flat(cost: u64) -> Self {
+        Self {
+            default: cost,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the cost of the operators in `group`.
+    pub fn with_group_cost(mut self, group: OperatorGroup, cost: u64) -> Self {
+        self.groups.insert(group, cost);
+        self
+    }
+
+    /// The cost of the operators in `group`.
+    pub fn group_cost(&self, group: OperatorGroup) -> u64 {
+        self.groups.get(&group).copied().unwrap_or(self.default)
+    }
+
+    /// Reads a schedule from the TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScheduleError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|error| ScheduleError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        contents.parse()
+    }
+}
+
+/// Parses the contents of a schedule file (see [`CostSchedule`]).
+///
+/// Only the parts of TOML that schedules use are understood: comments, the
+/// `[groups]` table, and keys set to integers.
+impl FromStr for CostSchedule {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut schedule = Self::flat(0);
+        let mut in_groups = false;
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(table) = line.strip_prefix('[') {
+                match table.strip_suffix(']').map(str::trim) {
+                    Some("groups") => in_groups = true,
+                    _ => {
+                        return Err(ScheduleError::Syntax {
+                            line: line_number,
+                            message: format!("unexpected table \"{}\"", line),
+                        })
+                    }
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| ScheduleError::Syntax {
+                line: line_number,
+                message: "expected `key = value`".to_string(),
+            })?;
+            let key = key.trim().trim_matches('"');
+            let value = value.trim();
+            let cost =
+                value
+                    .replace('_', "")
+                    .parse::<u64>()
+                    .map_err(|_| ScheduleError::Syntax {
+                        line: line_number,
+                        message: format!("expected a cost, found \"{}\"", value),
+                    })?;
+
+            match (in_groups, key) {
+                (false, "default") => schedule.default = cost,
+                (false, _) => {
+                    return Err(ScheduleError::Syntax {
+                        line: line_number,
+                        message: format!("unexpected key \"{}\"", key),
+                    })
+                }
+                (true, group) => {
+                    schedule.groups.insert(group.parse()?, cost);
+                }
+            }
+        }
+
+        Ok(schedule)
+    }
+}
+
+impl CostFunction for CostSchedule {
+    fn cost(&self, operator: &Operator, _context: &OperatorContext) -> u64 {
+        self.group_cost(OperatorGroup::of(operator))
+    }
+
+    /// The FNV-1a hash of the cost of every group, which doesn't change
+    /// between builds like the hashes of the standard library may.
+    fn schedule_hash(&self) -> Option<u64> {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for group in OperatorGroup::ALL {
+            for byte in self.group_cost(group).to_le_bytes() {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        Some(hash)
+    }
+}
+
+/// An error loading a [`CostSchedule`].
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// The file couldn't be read.
+    Io {
+        /// The path of the file.
+        path: String,
+        /// Why it couldn't be read.
+        error: std::io::Error,
+    },
+    /// A line of the file isn't understood.
+    Syntax {
+        /// The line number, starting at 1.
+        line: usize,
+        /// What's wrong with it.
+        message: String,
+    },
+    /// A group in the `[groups]` table doesn't exist.
+    UnknownGroup(String),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "unable to read \"{}\": {}", path, error),
+            Self::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            Self::UnknownGroup(group) => write!(
+                f,
+                "unknown operator group \"{}\", expected one of: {}",
+                group,
+                OperatorGroup::ALL.map(OperatorGroup::name).join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::wasmparser::MemArg;
+
+    #[test]
+    fn operator_groups() {
+        let memarg = MemArg {
+            align: 2,
+            max_align: 2,
+            offset: 0,
+            memory: 0,
+        };
+        let cases = [
+            (
+                Operator::Block {
+                    blockty: wasmer::wasmparser::BlockType::Empty,
+                },
+                OperatorGroup::Control,
+            ),
+            (Operator::Select, OperatorGroup::Control),
+            (Operator::Call { function_index: 0 }, OperatorGroup::Call),
+            (Operator::LocalTee { local_index: 0 }, OperatorGroup::Local),
+            (Operator::I64Const { value: 0 }, OperatorGroup::Const),
+            (Operator::I64Add, OperatorGroup::Integer),
+            (Operator::I32Extend8S, OperatorGroup::Integer),
+            (Operator::I32RemU, OperatorGroup::IntegerDivision),
+            (Operator::F32Div, OperatorGroup::Float),
+            (Operator::F32Trunc, OperatorGroup::Float),
+            (Operator::I32TruncF64S, OperatorGroup::Conversion),
+            (Operator::I64ExtendI32U, OperatorGroup::Conversion),
+            (Operator::I32Load8U { memarg }, OperatorGroup::Load),
+            (Operator::I64Store { memarg }, OperatorGroup::Store),
+            (
+                Operator::MemoryGrow {
+                    mem: 0,
+                    mem_byte: 0,
+                },
+                OperatorGroup::Memory,
+            ),
+            (Operator::MemoryFill { mem: 0 }, OperatorGroup::BulkMemory),
+            (Operator::TableGrow { table: 0 }, OperatorGroup::Table),
+            (Operator::RefIsNull, OperatorGroup::Reference),
+            (Operator::I32x4Add, OperatorGroup::Simd),
+            (Operator::V128Load { memarg }, OperatorGroup::Simd),
+            (Operator::I32AtomicRmwAdd { memarg }, OperatorGroup::Atomic),
+        ];
+        for (operator, group) in cases {
+            assert_eq!(OperatorGroup::of(&operator), group, "{:?}", operator);
+        }
+    }
+
+    #[test]
+    fn parse_schedules() {
+        let schedule: CostSchedule = r#"
+            # The cost of everything else
+            default = 2
+
+            [groups]
+            call = 1_000
+            "integer_division" = 7 # Slow
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            schedule,
+            CostSchedule::flat(2)
+                .with_group_cost(OperatorGroup::Call, 1000)
+                .with_group_cost(OperatorGroup::IntegerDivision, 7)
+        );
+
+        let err = "[groups]\ncals = 1".parse::<CostSchedule>().unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("unknown operator group \"cals\""));
+        let err = "default = lots".parse::<CostSchedule>().unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected a cost, found \"lots\"");
+        let err = "call = 1".parse::<CostSchedule>().unwrap_err();
+        assert_eq!(err.to_string(), "line 1: unexpected key \"call\"");
+    }
+
+    #[test]
+    fn schedule_hashes() {
+        let flat = CostSchedule::flat(1);
+        // The same costs hash the same, however they're written down
+        assert_eq!(
+            flat.schedule_hash(),
+            flat.clone()
+                .with_group_cost(OperatorGroup::Call, 1)
+                .schedule_hash()
+        );
+        assert_ne!(flat.schedule_hash(), CostSchedule::flat(2).schedule_hash());
+        assert_ne!(
+            flat.schedule_hash(),
+            flat.clone()
+                .with_group_cost(OperatorGroup::Call, 2)
+                .schedule_hash()
+        );
+    }
+}
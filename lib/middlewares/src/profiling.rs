@@ -0,0 +1,477 @@
+//! `profiling` is a middleware for counting how many times each function
+//! is called and how many times the header of each loop runs, from
+//! production-representative runs and without an external profiler.
+//!
+//! The counts are kept in globals added to the module, one for each
+//! function and for each loop, and are given by [`get_profile`], keyed by
+//! the index of the function and the offset of the loop in the module.
+//! [`Profile::write_callgrind`] writes them in the format of callgrind, so
+//! they can be looked at in KCachegrind, with the names of the functions
+//! from the module's name section.
+//!
+//! Counts saturate at `u64::MAX` rather than wrapping around.
+//!
+//! # Overhead
+//!
+//! Each call to a function and each iteration of a loop runs seven more
+//! operators, which read a global, add one to it unless it's already at
+//! the maximum, and write it back. Tight loops and functions which are
+//! called very often slow down the most, while code which spends its time
+//! in straight-line code barely does. The `profiling` benchmark in
+//! `benches/` compares both kinds of code with and without the middleware.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::CompilerConfig;
+//! use wasmer_middlewares::Profiling;
+//!
+//! fn enable_profiling(compiler_config: &mut dyn CompilerConfig) {
+//!     compiler_config.push_middleware(Arc::new(Profiling::new()));
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use wasmer::wasmparser::{BinaryReader, BinaryReaderError, Operator};
+use wasmer::{
+    AsStoreMut, ExportIndex, Extern, FunctionBodyData, FunctionMiddleware, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, Type,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, GlobalIndex, ModuleInfo};
+
+/// The prefix of the names the call counts are exported with, which end
+/// with the index of the function and the offset of its body.
+const CALLS_PREFIX: &str = "wasmer_profiling_calls_";
+
+/// The prefix of the names the loop counts are exported with, which end
+/// with the index of the function and the offset of the loop.
+const LOOP_PREFIX: &str = "wasmer_profiling_loop_";
+
+/// The globals counting the calls to a function and the iterations of
+/// its loops.
+#[derive(Clone, Debug, Default)]
+struct FunctionCounters {
+    /// The global counting the calls.
+    calls: Option<GlobalIndex>,
+
+    /// The globals counting the iterations of each loop, with the offset
+    /// of the loop in the module, in order.
+    loops: Vec<(usize, GlobalIndex)>,
+}
+
+/// The module-level profiling middleware.
+///
+/// # Panic
+///
+/// An instance of `Profiling` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// global indexes of the counters. Attempts to use a `Profiling`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, EngineBuilder, Instance, Module, Store};
+/// use wasmer_middlewares::{profiling::get_profile, Profiling};
+///
+/// let mut compiler_config = Cranelift::default();
+/// compiler_config.push_middleware(Arc::new(Profiling::new()));
+/// let mut store = Store::new(EngineBuilder::new(compiler_config));
+///
+/// let wasm = wat2wasm(br#"(module (func (export "run")))"#).unwrap();
+/// let module = Module::new(&store, wasm).unwrap();
+/// let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+/// let run = instance.exports.get_function("run").unwrap();
+/// run.call(&mut store, &[]).unwrap();
+/// run.call(&mut store, &[]).unwrap();
+///
+/// assert_eq!(get_profile(&mut store, &instance).functions[&0].calls, 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct Profiling {
+    /// The counters of each local function, once the module is known.
+    counters: Mutex<Option<HashMap<LocalFunctionIndex, FunctionCounters>>>,
+}
+
+/// The function-level profiling middleware.
+#[derive(Debug)]
+pub struct FunctionProfiling {
+    /// The counters of the function being profiled.
+    counters: FunctionCounters,
+
+    /// Whether the function's entry was instrumented yet.
+    entered: bool,
+
+    /// The index in `counters.loops` of the next loop to instrument.
+    next_loop: usize,
+}
+
+impl Profiling {
+    /// Creates a `Profiling` middleware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ModuleMiddleware for Profiling {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let counters = self
+            .counters
+            .lock()
+            .unwrap()
+            .as_ref()
+            .expect("Profiling::generate_function_middleware: The module wasn't transformed yet")
+            .get(&function_index)
+            .cloned()
+            .unwrap_or_default();
+        Box::new(FunctionProfiling {
+            counters,
+            entered: false,
+            next_loop: 0,
+        })
+    }
+
+    /// Without the bodies of the functions, only their calls are counted.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        self.transform_module_info_with_bodies(module_info, &PrimaryMap::new());
+    }
+
+    /// Appends a global for the calls to each function and for each of
+    /// their loops.
+    fn transform_module_info_with_bodies(
+        &self,
+        module_info: &mut ModuleInfo,
+        bodies: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) {
+        let mut counters = self.counters.lock().unwrap();
+
+        if counters.is_some() {
+            panic!("Profiling::transform_module_info: Attempting to use a `Profiling` middleware from multiple modules.");
+        }
+
+        let num_imported_functions = module_info.num_imported_functions;
+        let num_local_functions = module_info.functions.len() - num_imported_functions;
+        let mut all_counters = HashMap::new();
+        for local_index in 0..num_local_functions {
+            let local_index = LocalFunctionIndex::new(local_index);
+            let function_index = num_imported_functions + local_index.index();
+            let (body_offset, loop_offsets) = match bodies.get(local_index) {
+                // A body which can't be read doesn't compile either
+                Some(body) => (body.module_offset, loop_offsets(body).unwrap_or_default()),
+                None => (0, vec![]),
+            };
+
+            let calls = add_counter(
+                module_info,
+                format!("{}{}_{}", CALLS_PREFIX, function_index, body_offset),
+            );
+            let loops = loop_offsets
+                .into_iter()
+                .map(|offset| {
+                    let name = format!("{}{}_{}", LOOP_PREFIX, function_index, offset);
+                    (offset, add_counter(module_info, name))
+                })
+                .collect();
+
+            all_counters.insert(
+                local_index,
+                FunctionCounters {
+                    calls: Some(calls),
+                    loops,
+                },
+            );
+        }
+
+        *counters = Some(all_counters);
+    }
+
+    fn deterministic_id(&self) -> Option<String> {
+        Some("profiling".to_string())
+    }
+}
+
+/// Append an `i64` global starting at 0 to the module, exported as `name`.
+fn add_counter(module_info: &mut ModuleInfo, name: String) -> GlobalIndex {
+    let index = module_info
+        .globals
+        .push(GlobalType::new(Type::I64, Mutability::Var));
+    module_info
+        .global_initializers
+        .push(GlobalInit::I64Const(0));
+    module_info.exports.insert(name, ExportIndex::Global(index));
+    index
+}
+
+/// The offsets of the `loop`s of a function's body in the module.
+fn loop_offsets(body: &FunctionBodyData<'_>) -> Result<Vec<usize>, BinaryReaderError> {
+    let mut reader = BinaryReader::new_with_offset(body.data, body.module_offset);
+    for _ in 0..reader.read_var_u32()? {
+        reader.read_var_u32()?;
+        reader.read_val_type()?;
+    }
+
+    let mut offsets = vec![];
+    while !reader.eof() {
+        let offset = reader.original_position();
+        if let Operator::Loop { .. } = reader.read_operator()? {
+            offsets.push(offset);
+        }
+    }
+    Ok(offsets)
+}
+
+impl FunctionProfiling {
+    /// Pushes the operators which add one to the counter in `global`,
+    /// unless it's already at the maximum.
+    fn increment(state: &mut MiddlewareReaderState<'_>, global: GlobalIndex) {
+        let global_index = global.as_u32();
+
+        // globals[global] += (globals[global] != u64::MAX) as u64;
+        state.extend(&[
+            Operator::GlobalGet { global_index },
+            Operator::GlobalGet { global_index },
+            Operator::I64Const { value: -1 },
+            Operator::I64Ne,
+            Operator::I64ExtendI32U,
+            Operator::I64Add,
+            Operator::GlobalSet { global_index },
+        ]);
+    }
+}
+
+impl FunctionMiddleware for FunctionProfiling {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            if let Some(calls) = self.counters.calls {
+                Self::increment(state, calls);
+            }
+        }
+
+        let is_loop = matches!(operator, Operator::Loop { .. });
+        state.push_operator(operator);
+
+        // The header is counted inside the loop, so each branch back to it
+        // counts too. A loop added by an earlier middleware has the offset
+        // of the operator it was added for, so only the first loop at the
+        // offset of one of the function's is counted.
+        if is_loop {
+            if let Some(&(offset, global)) = self.counters.loops.get(self.next_loop) {
+                if offset == state.operator_offset() {
+                    Self::increment(state, global);
+                    self.next_loop += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The counts of an [`Instance`][wasmer::Instance] processed with the
+/// [`Profiling`] middleware, as given by [`get_profile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// The counts of each of the module's local functions, keyed by the
+    /// index of the function in the module.
+    pub functions: BTreeMap<u32, FunctionProfile>,
+}
+
+/// The counts of a function.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    /// The name of the function, if the module's name section has one.
+    pub name: Option<String>,
+
+    /// The offset of the function's body in the module.
+    pub offset: usize,
+
+    /// How many times the function was called.
+    pub calls: u64,
+
+    /// How many times the header of each of the function's loops ran,
+    /// which is the number of iterations of the loop, keyed by the offset
+    /// of the `loop` in the module.
+    pub loops: BTreeMap<usize, u64>,
+}
+
+/// Get the counts of an [`Instance`][wasmer::Instance].
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The given [`Instance`][wasmer::Instance] must have been processed
+/// with the [`Profiling`] middleware at compile time, otherwise this
+/// will panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::File;
+/// use wasmer::{AsStoreMut, Instance};
+/// use wasmer_middlewares::profiling::get_profile;
+///
+/// /// Write the counts to `callgrind.out`, for KCachegrind.
+/// fn save_profile(store: &mut impl AsStoreMut, instance: &Instance) -> std::io::Result<()> {
+///     let mut file = File::create("callgrind.out")?;
+///     get_profile(store, instance).write_callgrind(&mut file)
+/// }
+/// ```
+pub fn get_profile(ctx: &mut impl AsStoreMut, instance: &Instance) -> Profile {
+    let mut profile = Profile::default();
+    for (name, export) in instance.exports.iter() {
+        let (function_index, offset, is_loop) = match parse_counter_name(name) {
+            Some(counter) => counter,
+            None => continue,
+        };
+        let count: u64 = match export {
+            Extern::Global(global) => global
+                .get(ctx)
+                .try_into()
+                .expect("A profiling counter from Instance has the wrong type"),
+            _ => continue,
+        };
+
+        let function = profile.functions.entry(function_index).or_default();
+        if is_loop {
+            function.loops.insert(offset, count);
+        } else {
+            function.offset = offset;
+            function.calls = count;
+        }
+    }
+
+    let module_info = instance.module().info();
+    assert!(
+        !profile.functions.is_empty()
+            || module_info.functions.len() == module_info.num_imported_functions,
+        "Instance wasn't processed with the `Profiling` middleware"
+    );
+
+    let function_names = &module_info.function_names;
+    for (function_index, function) in profile.functions.iter_mut() {
+        function.name = function_names
+            .get(&FunctionIndex::from_u32(*function_index))
+            .cloned();
+    }
+    profile
+}
+
+/// The index of the function and the offset of a counter exported as
+/// `name`, and whether it counts the iterations of a loop.
+fn parse_counter_name(name: &str) -> Option<(u32, usize, bool)> {
+    let (rest, is_loop) = match name.strip_prefix(CALLS_PREFIX) {
+        Some(rest) => (rest, false),
+        None => (name.strip_prefix(LOOP_PREFIX)?, true),
+    };
+    let (function_index, offset) = rest.split_once('_')?;
+    Some((function_index.parse().ok()?, offset.parse().ok()?, is_loop))
+}
+
+impl Profile {
+    /// Write the counts in the format of callgrind, which KCachegrind can
+    /// open.
+    ///
+    /// Each function's calls are given at the offset of its body, and the
+    /// iterations of its loops at the offset of each `loop`, as a single
+    /// `Executions` event. Functions without a name are called
+    /// `wasm-function[<index>]`.
+    pub fn write_callgrind(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "# callgrind format")?;
+        writeln!(out, "version: 1")?;
+        writeln!(out, "creator: wasmer-middlewares")?;
+        writeln!(out, "positions: instr")?;
+        writeln!(out, "events: Executions")?;
+
+        for (function_index, function) in &self.functions {
+            writeln!(out)?;
+            match &function.name {
+                Some(name) => writeln!(out, "fn={}", name)?,
+                None => writeln!(out, "fn=wasm-function[{}]", function_index)?,
+            }
+            writeln!(out, "{:#x} {}", function.offset, function.calls)?;
+            for (offset, iterations) in &function.loops {
+                writeln!(out, "{:#x} {}", offset, iterations)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_names() {
+        assert_eq!(
+            parse_counter_name("wasmer_profiling_calls_3_120"),
+            Some((3, 120, false))
+        );
+        assert_eq!(
+            parse_counter_name("wasmer_profiling_loop_3_131"),
+            Some((3, 131, true))
+        );
+        assert_eq!(parse_counter_name("wasmer_profiling_loop_3"), None);
+        assert_eq!(parse_counter_name("memory"), None);
+    }
+
+    #[test]
+    fn callgrind_format() {
+        let mut profile = Profile::default();
+        profile.functions.insert(
+            1,
+            FunctionProfile {
+                name: Some("hot".to_string()),
+                offset: 0x40,
+                calls: 2,
+                loops: [(0x45, 2000), (0x52, 20)].into_iter().collect(),
+            },
+        );
+        profile.functions.insert(
+            2,
+            FunctionProfile {
+                name: None,
+                offset: 0x60,
+                calls: 0,
+                loops: BTreeMap::new(),
+            },
+        );
+
+        let mut out = vec![];
+        profile.write_callgrind(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# callgrind format\n\
+             version: 1\n\
+             creator: wasmer-middlewares\n\
+             positions: instr\n\
+             events: Executions\n\
+             \n\
+             fn=hot\n\
+             0x40 2\n\
+             0x45 2000\n\
+             0x52 20\n\
+             \n\
+             fn=wasm-function[2]\n\
+             0x60 0\n"
+        );
+    }
+}
@@ -23,5 +23,8 @@ mod error;
 mod module;
 
 pub use crate::error::ObjectError;
-pub use crate::module::{emit_compilation, emit_data, emit_serialized, get_object_for_target};
+pub use crate::module::{
+    emit_compilation, emit_compilation_with_model, emit_compilation_with_options, emit_data,
+    emit_serialized, get_object_for_target, EmitOptions, RelocationModel, PIC_MARKER_SYMBOL,
+};
 pub use object::{self, write::Object};
@@ -1,6 +1,7 @@
 use crate::error::ObjectError;
 use object::write::{
-    Object, Relocation, StandardSection, StandardSegment, Symbol as ObjSymbol, SymbolSection,
+    Object, Relocation, SectionId, StandardSection, StandardSegment, Symbol as ObjSymbol, SymbolId,
+    SymbolSection,
 };
 use object::{
     elf, macho, FileFlags, RelocationEncoding, RelocationKind, SectionKind, SymbolFlags,
@@ -16,6 +17,52 @@ use wasmer_types::{Symbol, SymbolRegistry};
 
 const DWARF_SECTION_NAME: &[u8] = b".eh_frame";
 
+/// The name of the symbol marking objects emitted with
+/// [`RelocationModel::Pic`].
+pub const PIC_MARKER_SYMBOL: &str = "WASMER_PIC";
+
+/// How the code in an object refers to the functions, trampolines and
+/// sections around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationModel {
+    /// The code may be fixed up with absolute addresses, so the object
+    /// can only be linked into executables loaded at a fixed address.
+    Static,
+    /// Position-independent code, for shared libraries and PIE
+    /// executables.
+    ///
+    /// The symbols defined by the compilation are only visible to the
+    /// library or executable the object is linked into, so the linker can
+    /// resolve references to them directly rather than through the GOT or
+    /// PLT. The object is marked with a local [`PIC_MARKER_SYMBOL`].
+    Pic,
+}
+
+/// How [`emit_compilation_with_options`] lays out the compilation in the
+/// object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmitOptions {
+    /// How the code refers to what's around it.
+    pub model: RelocationModel,
+    /// The size the text section can grow to before the code goes into a
+    /// new one, if it's limited.
+    ///
+    /// Linkers have limits on the size of an input section, and only extend
+    /// the range of calls with veneers between sections, so the code of big
+    /// modules is split into several to link it. A function bigger than the
+    /// limit still gets a section of its own.
+    pub max_text_section_size: Option<u64>,
+}
+
+impl Default for EmitOptions {
+    fn default() -> Self {
+        Self {
+            model: RelocationModel::Static,
+            max_text_section_size: None,
+        }
+    }
+}
+
 /// Create an object for a given target `Triple`.
 ///
 /// # Usage
@@ -137,6 +184,73 @@ pub fn emit_compilation(
     symbol_registry: &impl SymbolRegistry,
     triple: &Triple,
 ) -> Result<(), ObjectError> {
+    emit_compilation_with_model(
+        obj,
+        compilation,
+        symbol_registry,
+        triple,
+        RelocationModel::Static,
+    )
+}
+
+/// Emit the compilation result into an existing object, for the given
+/// [`RelocationModel`].
+///
+/// The compilation must have been compiled for the same model, with
+/// `CompilerConfig::enable_pic` for [`RelocationModel::Pic`].
+pub fn emit_compilation_with_model(
+    obj: &mut Object,
+    compilation: Compilation,
+    symbol_registry: &impl SymbolRegistry,
+    triple: &Triple,
+    model: RelocationModel,
+) -> Result<(), ObjectError> {
+    emit_compilation_with_options(
+        obj,
+        compilation,
+        symbol_registry,
+        triple,
+        &EmitOptions {
+            model,
+            ..Default::default()
+        },
+    )
+}
+
+/// Emit the compilation result into an existing object, laid out as
+/// `options` say.
+///
+/// The compilation must have been compiled for the same model, with
+/// `CompilerConfig::enable_pic` for [`RelocationModel::Pic`].
+pub fn emit_compilation_with_options(
+    obj: &mut Object,
+    compilation: Compilation,
+    symbol_registry: &impl SymbolRegistry,
+    triple: &Triple,
+    options: &EmitOptions,
+) -> Result<(), ObjectError> {
+    let model = options.model;
+    // Position-independent objects keep their symbols to the library or
+    // executable they're linked into
+    let scope = match model {
+        RelocationModel::Static => SymbolScope::Dynamic,
+        RelocationModel::Pic => SymbolScope::Linkage,
+    };
+    if model == RelocationModel::Pic {
+        let section_id = obj.section_id(StandardSection::ReadOnlyData);
+        let symbol_id = obj.add_symbol(ObjSymbol {
+            name: PIC_MARKER_SYMBOL.as_bytes().to_vec(),
+            value: 0,
+            size: 1,
+            kind: SymbolKind::Data,
+            scope: SymbolScope::Compilation,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+        obj.add_symbol_data(symbol_id, section_id, &[1], 1);
+    }
+
     let mut function_bodies = PrimaryMap::with_capacity(compilation.functions.len());
     let mut function_relocations = PrimaryMap::with_capacity(compilation.functions.len());
     for (_, func) in compilation.functions.into_iter() {
@@ -158,6 +272,8 @@ pub fn emit_compilation(
         _ => 1,
     };
 
+    let mut text = TextSections::new(obj, options.max_text_section_size);
+
     // Add sections
     let custom_section_ids = compilation
         .custom_sections
@@ -183,29 +299,35 @@ pub fn emit_compilation(
                 (section_id, symbol_id)
             } else {
                 let section_name = symbol_registry.symbol_to_name(Symbol::Section(section_index));
-                let (section_kind, standard_section) = match custom_section.protection {
-                    CustomSectionProtection::ReadExecute => {
-                        (SymbolKind::Text, StandardSection::Text)
-                    }
-                    CustomSectionProtection::Read => (SymbolKind::Data, StandardSection::Data),
+                let section_kind = match custom_section.protection {
+                    CustomSectionProtection::ReadExecute => SymbolKind::Text,
+                    CustomSectionProtection::Read => SymbolKind::Data,
                 };
-                let section_id = obj.section_id(standard_section);
                 let symbol_id = obj.add_symbol(ObjSymbol {
                     name: section_name.into_bytes(),
                     value: 0,
                     size: custom_section.bytes.len() as _,
                     kind: section_kind,
-                    scope: SymbolScope::Dynamic,
+                    scope,
                     weak: false,
-                    section: SymbolSection::Section(section_id),
+                    section: SymbolSection::Undefined,
                     flags: SymbolFlags::None,
                 });
-                obj.add_symbol_data(
-                    symbol_id,
-                    section_id,
-                    custom_section.bytes.as_slice(),
-                    align,
-                );
+                let section_id = match custom_section.protection {
+                    CustomSectionProtection::ReadExecute => {
+                        text.add_symbol_data(obj, symbol_id, custom_section.bytes.as_slice(), align)
+                    }
+                    CustomSectionProtection::Read => {
+                        let section_id = obj.section_id(StandardSection::Data);
+                        obj.add_symbol_data(
+                            symbol_id,
+                            section_id,
+                            custom_section.bytes.as_slice(),
+                            align,
+                        );
+                        section_id
+                    }
+                };
                 (section_id, symbol_id)
             }
         })
@@ -217,18 +339,17 @@ pub fn emit_compilation(
         .map(|(function_local_index, function)| {
             let function_name =
                 symbol_registry.symbol_to_name(Symbol::LocalFunction(function_local_index));
-            let section_id = obj.section_id(StandardSection::Text);
             let symbol_id = obj.add_symbol(ObjSymbol {
                 name: function_name.into_bytes(),
                 value: 0,
                 size: function.body.len() as _,
                 kind: SymbolKind::Text,
-                scope: SymbolScope::Dynamic,
+                scope,
                 weak: false,
-                section: SymbolSection::Section(section_id),
+                section: SymbolSection::Undefined,
                 flags: SymbolFlags::None,
             });
-            obj.add_symbol_data(symbol_id, section_id, &function.body, align);
+            let section_id = text.add_symbol_data(obj, symbol_id, &function.body, align);
             (section_id, symbol_id)
         })
         .collect::<PrimaryMap<LocalFunctionIndex, _>>();
@@ -237,36 +358,34 @@ pub fn emit_compilation(
     for (signature_index, function) in compilation.function_call_trampolines.into_iter() {
         let function_name =
             symbol_registry.symbol_to_name(Symbol::FunctionCallTrampoline(signature_index));
-        let section_id = obj.section_id(StandardSection::Text);
         let symbol_id = obj.add_symbol(ObjSymbol {
             name: function_name.into_bytes(),
             value: 0,
             size: function.body.len() as _,
             kind: SymbolKind::Text,
-            scope: SymbolScope::Dynamic,
+            scope,
             weak: false,
-            section: SymbolSection::Section(section_id),
+            section: SymbolSection::Undefined,
             flags: SymbolFlags::None,
         });
-        obj.add_symbol_data(symbol_id, section_id, &function.body, align);
+        text.add_symbol_data(obj, symbol_id, &function.body, align);
     }
 
     // Add dynamic function trampolines
     for (func_index, function) in compilation.dynamic_function_trampolines.into_iter() {
         let function_name =
             symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(func_index));
-        let section_id = obj.section_id(StandardSection::Text);
         let symbol_id = obj.add_symbol(ObjSymbol {
             name: function_name.into_bytes(),
             value: 0,
             size: function.body.len() as _,
             kind: SymbolKind::Text,
-            scope: SymbolScope::Dynamic,
+            scope,
             weak: false,
-            section: SymbolSection::Section(section_id),
+            section: SymbolSection::Undefined,
             flags: SymbolFlags::None,
         });
-        obj.add_symbol_data(symbol_id, section_id, &function.body, align);
+        text.add_symbol_data(obj, symbol_id, &function.body, align);
     }
 
     let mut all_relocations = Vec::new();
@@ -294,6 +413,13 @@ pub fn emit_compilation(
                 Reloc::Abs4 => (RelocationKind::Absolute, RelocationEncoding::Generic, 32),
                 Reloc::Abs8 => (RelocationKind::Absolute, RelocationEncoding::Generic, 64),
                 Reloc::X86PCRel4 => (RelocationKind::Relative, RelocationEncoding::Generic, 32),
+                // Calls out of the object go through the PLT when it's
+                // position-independent
+                Reloc::X86CallPCRel4 if model == RelocationModel::Pic => (
+                    RelocationKind::PltRelative,
+                    RelocationEncoding::X86Branch,
+                    32,
+                ),
                 Reloc::X86CallPCRel4 => {
                     (RelocationKind::Relative, RelocationEncoding::X86Branch, 32)
                 }
@@ -317,6 +443,76 @@ pub fn emit_compilation(
                     RelocationEncoding::Generic,
                     32,
                 ),
+                Reloc::Arm64AdrPrelPgHi21 => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_ADR_PREL_PG_HI21,
+                        macho::ARM64_RELOC_PAGE21,
+                        true,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64AddAbsLo12Nc => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_ADD_ABS_LO12_NC,
+                        macho::ARM64_RELOC_PAGEOFF12,
+                        false,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64Ldst32AbsLo12Nc => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_LDST32_ABS_LO12_NC,
+                        macho::ARM64_RELOC_PAGEOFF12,
+                        false,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64Ldst64AbsLo12Nc => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_LDST64_ABS_LO12_NC,
+                        macho::ARM64_RELOC_PAGEOFF12,
+                        false,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64Ldst128AbsLo12Nc => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_LDST128_ABS_LO12_NC,
+                        macho::ARM64_RELOC_PAGEOFF12,
+                        false,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64AdrGotPage21 => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_ADR_GOT_PAGE,
+                        macho::ARM64_RELOC_GOT_LOAD_PAGE21,
+                        true,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
+                Reloc::Arm64Ld64GotLo12Nc => (
+                    aarch64_relocation(
+                        obj.format(),
+                        elf::R_AARCH64_LD64_GOT_LO12_NC,
+                        macho::ARM64_RELOC_GOT_LOAD_PAGEOFF12,
+                        false,
+                    ),
+                    RelocationEncoding::Generic,
+                    32,
+                ),
                 Reloc::ElfX86_64TlsGd => (
                     RelocationKind::Elf(elf::R_X86_64_TLSGD),
                     RelocationEncoding::Generic,
@@ -396,6 +592,85 @@ pub fn emit_compilation(
     Ok(())
 }
 
+/// The text sections of an object, which the code is added to in order,
+/// starting a new section whenever the current one would grow past the
+/// limit.
+struct TextSections {
+    max_size: Option<u64>,
+    current: SectionId,
+    /// The size of the current section.
+    size: u64,
+    /// The number of sections so far.
+    count: usize,
+}
+
+impl TextSections {
+    fn new(obj: &mut Object, max_size: Option<u64>) -> Self {
+        Self {
+            max_size,
+            current: obj.section_id(StandardSection::Text),
+            size: 0,
+            count: 1,
+        }
+    }
+
+    /// Add `data` for `symbol_id`, returning the section it's in.
+    fn add_symbol_data(
+        &mut self,
+        obj: &mut Object,
+        symbol_id: SymbolId,
+        data: &[u8],
+        align: u64,
+    ) -> SectionId {
+        let len = data.len() as u64;
+        let fits = |max_size| self.size == 0 || align_up(self.size, align) + len <= max_size;
+        if !self.max_size.map_or(true, fits) {
+            let segment = obj.segment_name(StandardSegment::Text).to_vec();
+            let name = text_section_name(obj.format(), self.count);
+            self.current = obj.add_section(segment, name, SectionKind::Text);
+            self.size = 0;
+            self.count += 1;
+        }
+        let offset = obj.add_symbol_data(symbol_id, self.current, data, align);
+        self.size = offset + len;
+        self.current
+    }
+}
+
+fn align_up(offset: u64, align: u64) -> u64 {
+    (offset + align - 1) & !(align - 1)
+}
+
+/// The name of the text section after the first `index` ones, which the
+/// linker merges with the standard one.
+fn text_section_name(format: object::BinaryFormat, index: usize) -> Vec<u8> {
+    match format {
+        // At most 16 bytes
+        object::BinaryFormat::MachO => format!("__text{index}"),
+        // Grouped sections, sorted by what follows the `$`
+        object::BinaryFormat::Coff => format!(".text$w{index:06}"),
+        _ => format!(".text.wasmer.{index}"),
+    }
+    .into_bytes()
+}
+
+/// The relocation for an aarch64 instruction, in the object's format.
+fn aarch64_relocation(
+    format: object::BinaryFormat,
+    elf: u32,
+    macho: u8,
+    relative: bool,
+) -> RelocationKind {
+    match format {
+        object::BinaryFormat::Elf => RelocationKind::Elf(elf),
+        object::BinaryFormat::MachO => RelocationKind::MachO {
+            value: macho,
+            relative,
+        },
+        fmt => panic!("unsupported binary format {:?}", fmt),
+    }
+}
+
 /// Emit the compilation result into an existing object.
 ///
 /// # Usage
@@ -64,6 +64,18 @@ pub struct CompiledFunction {
     pub frame_info: CompiledFunctionFrameInfo,
 }
 
+/// A function of a lazily compiled module, compiled on its own.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LazyCompiledFunction {
+    /// The compiled function.
+    pub function: CompiledFunction,
+
+    /// An `.eh_frame` section with the DWARF unwind information of the
+    /// function, on targets that use it. It's relocated against the
+    /// function's own [`LocalFunctionIndex`].
+    pub eh_frame: Option<CustomSection>,
+}
+
 /// The compiled functions map (index in the Wasm -> function)
 pub type Functions = PrimaryMap<LocalFunctionIndex, CompiledFunction>;
 
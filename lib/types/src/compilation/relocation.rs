@@ -63,6 +63,20 @@ pub enum RelocationKind {
     ElfX86_64TlsGd,
     // /// Mach-O x86_64 32 bit signed PC relative offset to a `__thread_vars` entry.
     // MachOX86_64Tlv,
+    /// Arm64 adrp, page of the target relative to the page of the instruction
+    Arm64AdrPrelPgHi21,
+    /// Arm64 add, low 12 bits of the target
+    Arm64AddAbsLo12Nc,
+    /// Arm64 32-bit ldr/str, low 12 bits of the target
+    Arm64Ldst32AbsLo12Nc,
+    /// Arm64 64-bit ldr/str, low 12 bits of the target
+    Arm64Ldst64AbsLo12Nc,
+    /// Arm64 128-bit ldr/str, low 12 bits of the target
+    Arm64Ldst128AbsLo12Nc,
+    /// Arm64 adrp, page of the target's GOT entry
+    Arm64AdrGotPage21,
+    /// Arm64 ldr, low 12 bits of the target's GOT entry
+    Arm64Ld64GotLo12Nc,
 }
 
 impl fmt::Display for RelocationKind {
@@ -85,6 +99,13 @@ impl fmt::Display for RelocationKind {
             Self::ElfX86_64TlsGd => write!(f, "ElfX86_64TlsGd"),
             Self::RiscvPCRelHi20 => write!(f, "RiscvPCRelHi20"),
             Self::RiscvPCRelLo12I => write!(f, "RiscvPCRelLo12I"),
+            Self::Arm64AdrPrelPgHi21 => write!(f, "Arm64AdrPrelPgHi21"),
+            Self::Arm64AddAbsLo12Nc => write!(f, "Arm64AddAbsLo12Nc"),
+            Self::Arm64Ldst32AbsLo12Nc => write!(f, "Arm64Ldst32AbsLo12Nc"),
+            Self::Arm64Ldst64AbsLo12Nc => write!(f, "Arm64Ldst64AbsLo12Nc"),
+            Self::Arm64Ldst128AbsLo12Nc => write!(f, "Arm64Ldst128AbsLo12Nc"),
+            Self::Arm64AdrGotPage21 => write!(f, "Arm64AdrGotPage21"),
+            Self::Arm64Ld64GotLo12Nc => write!(f, "Arm64Ld64GotLo12Nc"),
             // Self::MachOX86_64Tlv => write!(f, "MachOX86_64Tlv"),
         }
     }
@@ -133,7 +154,11 @@ impl Relocation {
             | RelocationKind::Arm64Movw1
             | RelocationKind::Arm64Movw2
             | RelocationKind::Arm64Movw3
-            | RelocationKind::RiscvPCRelLo12I => {
+            | RelocationKind::RiscvPCRelLo12I
+            | RelocationKind::Arm64AddAbsLo12Nc
+            | RelocationKind::Arm64Ldst32AbsLo12Nc
+            | RelocationKind::Arm64Ldst64AbsLo12Nc
+            | RelocationKind::Arm64Ldst128AbsLo12Nc => {
                 let reloc_address = start + self.offset as usize;
                 let reloc_addend = self.addend as isize;
                 let reloc_abs = target_func_address
@@ -177,6 +202,15 @@ impl Relocation {
                     .wrapping_add(reloc_addend as u64);
                 (reloc_address, reloc_delta_u32)
             }
+            RelocationKind::Arm64AdrPrelPgHi21 => {
+                // The delta between the 4KiB pages of the instruction and
+                // the target, rather than between their addresses
+                let reloc_address = start + self.offset as usize;
+                let reloc_addend = self.addend as isize;
+                let target_page = target_func_address.wrapping_add(reloc_addend as u64) & !0xfff;
+                let reloc_page = reloc_address as u64 & !0xfff;
+                (reloc_address, target_page.wrapping_sub(reloc_page))
+            }
             _ => panic!("Relocation kind unsupported"),
         }
     }
@@ -12,6 +12,7 @@ use crate::error::ParseCpuFeatureError;
 use enumset::{EnumSet, EnumSetType};
 use std::str::FromStr;
 use std::string::{String, ToString};
+use std::vec::Vec;
 pub use target_lexicon::{
     Aarch64Architecture, Architecture, BinaryFormat, CallingConvention, Endianness, Environment,
     OperatingSystem, PointerWidth, Triple, Vendor,
@@ -47,7 +48,11 @@ pub enum CpuFeature {
     AVX512VL,
     AVX512F,
     LZCNT,
+    FMA,
     // ARM features
+    NEON,
+    LSE,
+    SVE,
     // Risc-V features
 }
 
@@ -99,9 +104,28 @@ impl CpuFeature {
         if std::is_x86_feature_detected!("lzcnt") {
             features.insert(Self::LZCNT);
         }
+        if std::is_x86_feature_detected!("fma") {
+            features.insert(Self::FMA);
+        }
+        features
+    }
+    #[cfg(target_arch = "aarch64")]
+    /// Retrieves the features for the current Host
+    pub fn for_host() -> EnumSet<Self> {
+        let mut features = EnumSet::new();
+
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            features.insert(Self::NEON);
+        }
+        if std::arch::is_aarch64_feature_detected!("lse") {
+            features.insert(Self::LSE);
+        }
+        if std::arch::is_aarch64_feature_detected!("sve") {
+            features.insert(Self::SVE);
+        }
         features
     }
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
     /// Retrieves the features for the current Host
     pub fn for_host() -> EnumSet<Self> {
         // We default to an empty hash set
@@ -113,6 +137,96 @@ impl CpuFeature {
         // We default to an empty hash set
         EnumSet::new()
     }
+
+    /// The features which exist on `architecture`.
+    pub fn for_architecture(architecture: Architecture) -> EnumSet<Self> {
+        match architecture {
+            Architecture::X86_64 | Architecture::X86_32(_) => {
+                Self::SSE2
+                    | Self::SSE3
+                    | Self::SSSE3
+                    | Self::SSE41
+                    | Self::SSE42
+                    | Self::POPCNT
+                    | Self::AVX
+                    | Self::BMI1
+                    | Self::BMI2
+                    | Self::AVX2
+                    | Self::AVX512DQ
+                    | Self::AVX512VL
+                    | Self::AVX512F
+                    | Self::LZCNT
+                    | Self::FMA
+            }
+            Architecture::Aarch64(_) => Self::NEON | Self::LSE | Self::SVE,
+            _ => EnumSet::new(),
+        }
+    }
+
+    /// Parses the name of a feature of `architecture`.
+    ///
+    /// Unlike [`FromStr`], this rejects the features of other architectures,
+    /// and suggests the closest known feature when the name is misspelled.
+    pub fn from_str_for(
+        name: &str,
+        architecture: Architecture,
+    ) -> Result<Self, ParseCpuFeatureError> {
+        let known = Self::for_architecture(architecture);
+        match name.parse::<Self>() {
+            Ok(feature) if known.contains(feature) => Ok(feature),
+            _ => Err(ParseCpuFeatureError::Unknown {
+                feature: name.to_string(),
+                architecture: architecture.to_string(),
+                suggestion: known
+                    .iter()
+                    .map(|feature| feature.to_string())
+                    .map(|candidate| (edit_distance(name, &candidate), candidate))
+                    .filter(|(distance, candidate)| *distance <= candidate.len() / 2)
+                    .min()
+                    .map(|(_, candidate)| candidate),
+            }),
+        }
+    }
+
+    /// Enables and disables features of `architecture` in `features`, as
+    /// given by a comma-separated list such as `+neon,+lse,-sve`.
+    ///
+    /// Features without a sign are enabled, and later entries take
+    /// precedence over earlier ones.
+    pub fn apply_list(
+        mut features: EnumSet<Self>,
+        list: &str,
+        architecture: Architecture,
+    ) -> Result<EnumSet<Self>, ParseCpuFeatureError> {
+        for entry in list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            if let Some(name) = entry.strip_prefix('-') {
+                features.remove(Self::from_str_for(name, architecture)?);
+            } else {
+                let name = entry.strip_prefix('+').unwrap_or(entry);
+                features.insert(Self::from_str_for(name, architecture)?);
+            }
+        }
+        Ok(features)
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 // This options should map exactly the GCC options indicated
@@ -140,6 +254,10 @@ impl FromStr for CpuFeature {
             "avx512vl" => Ok(Self::AVX512VL),
             "avx512f" => Ok(Self::AVX512F),
             "lzcnt" => Ok(Self::LZCNT),
+            "fma" => Ok(Self::FMA),
+            "neon" => Ok(Self::NEON),
+            "lse" => Ok(Self::LSE),
+            "sve" => Ok(Self::SVE),
             _ => Err(ParseCpuFeatureError::Missing(s.to_string())),
         }
     }
@@ -162,6 +280,10 @@ impl ToString for CpuFeature {
             Self::AVX512VL => "avx512vl",
             Self::AVX512F => "avx512f",
             Self::LZCNT => "lzcnt",
+            Self::FMA => "fma",
+            Self::NEON => "neon",
+            Self::LSE => "lse",
+            Self::SVE => "sve",
         }
         .to_string()
     }
@@ -194,6 +316,16 @@ impl Target {
         &self.cpu_features
     }
 
+    /// Enables and disables the CPU features of the target's architecture
+    /// given by a list such as `+neon,+lse,-sve`.
+    ///
+    /// See [`CpuFeature::apply_list`].
+    pub fn with_cpu_features_str(mut self, list: &str) -> Result<Self, ParseCpuFeatureError> {
+        self.cpu_features =
+            CpuFeature::apply_list(self.cpu_features, list, self.triple.architecture)?;
+        Ok(self)
+    }
+
     /// Check if target is a native (eq to host) or not
     pub fn is_native(&self) -> bool {
         let host = Triple::host();
@@ -211,3 +343,67 @@ impl Default for Target {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aarch64() -> Architecture {
+        Architecture::Aarch64(Aarch64Architecture::Aarch64)
+    }
+
+    #[test]
+    fn parse_feature_lists() {
+        let features = CpuFeature::apply_list(CpuFeature::set(), "+neon,+lse,-sve", aarch64());
+        assert_eq!(features.unwrap(), CpuFeature::NEON | CpuFeature::LSE);
+
+        // Later entries win, and entries without a sign are enabled
+        let features = CpuFeature::apply_list(
+            CpuFeature::SSE2.into(),
+            "avx2, +avx512f,-avx2,bmi",
+            Architecture::X86_64,
+        );
+        assert_eq!(
+            features.unwrap(),
+            CpuFeature::SSE2 | CpuFeature::AVX512F | CpuFeature::BMI1
+        );
+
+        let target = Target::new(
+            "aarch64-unknown-linux-gnu".parse().unwrap(),
+            CpuFeature::set(),
+        )
+        .with_cpu_features_str("+sve")
+        .unwrap()
+        .with_cpu_features_str("+lse,-sve")
+        .unwrap();
+        assert_eq!(*target.cpu_features(), CpuFeature::LSE);
+    }
+
+    #[test]
+    fn reject_unknown_features() {
+        let err = CpuFeature::apply_list(CpuFeature::set(), "+neno", aarch64()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "neno is not a CPU feature of aarch64, did you mean neon?"
+        );
+
+        let err = CpuFeature::from_str_for("avx512fd", Architecture::X86_64).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "avx512fd is not a CPU feature of x86_64, did you mean avx512f?"
+        );
+
+        // Features of other architectures aren't accepted
+        let err = CpuFeature::from_str_for("avx2", aarch64()).unwrap_err();
+        assert_eq!(err.to_string(), "avx2 is not a CPU feature of aarch64");
+        let err = CpuFeature::from_str_for("neon", Architecture::X86_64).unwrap_err();
+        assert_eq!(err.to_string(), "neon is not a CPU feature of x86_64");
+    }
+
+    #[test]
+    fn feature_names_round_trip() {
+        for feature in EnumSet::<CpuFeature>::all() {
+            assert_eq!(feature.to_string().parse::<CpuFeature>().unwrap(), feature);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 //! The WebAssembly possible errors
-use crate::{ExternType, Pages};
+use crate::{ExternType, FunctionIndex, Pages};
 use std::io;
 use thiserror::Error;
 
@@ -43,6 +43,27 @@ pub enum DeserializeError {
         /// How many bytes the artifact contained
         got: usize,
     },
+    /// The artifact's checksum doesn't match its contents
+    #[error("corrupted binary: the checksum doesn't match the artifact's contents")]
+    ChecksumMismatch,
+    /// The artifact was compiled for a target the engine can't run
+    #[error(
+        "incompatible binary: the artifact requires {artifact}, but the engine targets {engine}"
+    )]
+    IncompatibleTarget {
+        /// What the artifact was compiled for
+        artifact: String,
+        /// What the engine can run
+        engine: String,
+    },
+    /// Part of the artifact is truncated or refers to something out of bounds
+    #[error("corrupted binary: invalid {section}: {reason}")]
+    CorruptSection {
+        /// The part of the artifact which is corrupted
+        section: &'static str,
+        /// What is wrong with it
+        reason: String,
+    },
 }
 
 /// Error type describing things that can go wrong when operating on Wasm Memories.
@@ -122,7 +143,11 @@ pub enum PreInstantiationError {
     CpuFeature(String),
 }
 
+use crate::lib::std::boxed::Box;
+use crate::lib::std::fmt;
 use crate::lib::std::string::String;
+use crate::lib::std::vec::Vec;
+use crate::{LocalFunctionIndex, ModuleInfo};
 
 // Compilation Errors
 //
@@ -165,6 +190,114 @@ pub enum CompileError {
     /// Insufficient resources available for execution.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// One of the module's functions failed to compile.
+    #[cfg_attr(feature = "std", error("Unable to compile {location}: {error}"))]
+    Function {
+        /// The function, and the instruction being compiled.
+        location: FunctionLocation,
+        /// Why it failed.
+        error: Box<CompileError>,
+    },
+
+    /// Several of the module's functions failed to compile.
+    #[cfg_attr(feature = "std", error("{}", display_errors(.0)))]
+    Multiple(Vec<CompileError>),
+}
+
+#[cfg(feature = "std")]
+fn display_errors(errors: &[CompileError]) -> String {
+    let mut message = format!("{} functions failed to compile:", errors.len());
+    for error in errors {
+        message.push_str(&format!("\n  {}", error));
+    }
+    message
+}
+
+impl CompileError {
+    /// Say the error happened compiling the function at `location`, unless
+    /// it already says which function it happened in.
+    ///
+    /// Translation errors know the offset of the invalid instruction, which
+    /// is more precise than the one given.
+    pub fn in_function(self, mut location: FunctionLocation) -> Self {
+        match self {
+            Self::Function { .. } | Self::Multiple(_) => self,
+            error => {
+                if let Self::Wasm(WasmError::InvalidWebAssembly { offset, .. }) = &error {
+                    location.offset = *offset;
+                }
+                Self::Function {
+                    location,
+                    error: Box::new(error),
+                }
+            }
+        }
+    }
+
+    /// Where the error happened, if it happened compiling a function.
+    pub fn function_location(&self) -> Option<&FunctionLocation> {
+        match self {
+            Self::Function { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The results of compiling each function, or every error if any
+    /// failed, rather than only the first.
+    pub fn collect<T>(
+        results: impl IntoIterator<Item = Result<T, Self>>,
+    ) -> Result<Vec<T>, Self> {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(Self::Multiple(nested)) => errors.extend(nested),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        match errors.len() {
+            0 => Ok(values),
+            1 => Err(errors.remove(0)),
+            _ => Err(Self::Multiple(errors)),
+        }
+    }
+}
+
+/// Where in the module a [`CompileError`] happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionLocation {
+    /// The function's index, counting imported functions.
+    pub index: FunctionIndex,
+    /// The function's name, from the name section.
+    pub name: Option<String>,
+    /// The offset from the start of the module of the instruction being
+    /// compiled, or of the function's body if it's not known.
+    pub offset: usize,
+}
+
+impl FunctionLocation {
+    /// The location of `offset`, in the local function `index` of `module`.
+    pub fn new(module: &ModuleInfo, index: LocalFunctionIndex, offset: usize) -> Self {
+        let index = module.func_index(index);
+        Self {
+            index,
+            name: module.function_names.get(&index).cloned(),
+            offset,
+        }
+    }
+}
+
+impl fmt::Display for FunctionLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "function {}", self.index.as_u32())?;
+        if let Some(name) = &self.name {
+            write!(f, " (\"{}\")", name)?;
+        }
+        write!(f, " at offset 0x{:x}", self.offset)
+    }
 }
 
 impl From<WasmError> for CompileError {
@@ -173,6 +306,44 @@ impl From<WasmError> for CompileError {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<ValidationError> for CompileError {
+    fn from(original: ValidationError) -> Self {
+        Self::Validate(original.to_string())
+    }
+}
+
+/// A structured error describing why a WebAssembly module failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[cfg_attr(feature = "std", error("{message} (at offset 0x{offset:x})"))]
+pub struct ValidationError {
+    /// A machine-readable description of the error.
+    pub kind: ValidationErrorKind,
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The offset of the invalid item from the start of the module.
+    pub offset: usize,
+    /// The function the error occurred in, if it was in the code section.
+    pub function_index: Option<FunctionIndex>,
+}
+
+/// The kind of [`ValidationError`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    /// The module uses a WebAssembly proposal (e.g. `"simd"` or `"threads"`)
+    /// which isn't enabled, or isn't supported by the target.
+    UnsupportedProposal {
+        /// The proposal's name.
+        proposal: &'static str,
+    },
+    /// An instruction's operands or a function's results have the wrong type.
+    TypeMismatch,
+    /// Any other validation error.
+    Other,
+}
+
 /// A error in the middleware.
 #[derive(Debug)]
 #[cfg_attr(feature = "std", derive(Error))]
@@ -249,6 +420,31 @@ pub enum ParseCpuFeatureError {
     /// The provided string feature doesn't exist
     #[cfg_attr(feature = "std", error("CpuFeature {0} not recognized"))]
     Missing(String),
+
+    /// The feature doesn't exist on the target's architecture
+    #[cfg_attr(
+        feature = "std",
+        error(
+            "{feature} is not a CPU feature of {architecture}{}",
+            did_you_mean(.suggestion)
+        )
+    )]
+    Unknown {
+        /// The name of the feature
+        feature: String,
+        /// The target's architecture
+        architecture: String,
+        /// The known feature with the closest name, if any is close
+        suggestion: Option<String>,
+    },
+}
+
+#[cfg(feature = "std")]
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean {suggestion}?"),
+        None => String::new(),
+    }
 }
 
 /// A convenient alias for a `Result` that uses `WasmError` as the error type.
@@ -277,4 +473,48 @@ mod tests {
             err => panic!("Unexpected error: {:?}", err),
         }
     }
+
+    #[test]
+    fn compile_errors_say_which_function_failed() {
+        let location = |index, name: Option<&str>| FunctionLocation {
+            index: FunctionIndex::from_u32(index),
+            name: name.map(String::from),
+            offset: 0x2a,
+        };
+
+        let error = CompileError::Codegen("oops".to_string()).in_function(location(3, Some("f")));
+        assert_eq!(
+            error.to_string(),
+            "Unable to compile function 3 (\"f\") at offset 0x2a: Compilation error: oops"
+        );
+        // Where it failed is known best where it failed
+        let error = error.in_function(location(4, None));
+        assert_eq!(error.function_location(), Some(&location(3, Some("f"))));
+
+        let error = CompileError::Wasm(WasmError::InvalidWebAssembly {
+            message: "bad".to_string(),
+            offset: 0x30,
+        })
+        .in_function(location(4, None));
+        assert_eq!(error.function_location().unwrap().offset, 0x30);
+    }
+
+    #[test]
+    fn every_compile_error_is_collected() {
+        let codegen = |message: &str| CompileError::Codegen(message.to_string());
+
+        assert_eq!(
+            CompileError::collect(vec![Ok(1), Ok(2)]).unwrap(),
+            vec![1, 2]
+        );
+        let error = CompileError::collect(vec![Ok(1), Err(codegen("a"))]).unwrap_err();
+        assert!(matches!(error, CompileError::Codegen(_)));
+
+        let error =
+            CompileError::collect(vec![Err(codegen("a")), Ok(1), Err(codegen("b"))]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2 functions failed to compile:\n  Compilation error: a\n  Compilation error: b"
+        );
+    }
 }
@@ -1,6 +1,9 @@
+use crate::lib::std::vec::Vec;
+use crate::Target;
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// Controls which experimental features will be enabled.
 /// Features usually have a corresponding [WebAssembly proposal].
@@ -38,6 +41,23 @@ pub struct Features {
 }
 
 impl Features {
+    /// The names of every proposal, in the order they appear in
+    /// [`Features`].
+    pub const PROPOSALS: [&'static str; 12] = [
+        "threads",
+        "reference-types",
+        "simd",
+        "bulk-memory",
+        "multi-value",
+        "tail-call",
+        "module-linking",
+        "multi-memory",
+        "memory64",
+        "exceptions",
+        "relaxed-simd",
+        "extended-const",
+    ];
+
     /// Create a new feature
     pub fn new() -> Self {
         Self {
@@ -232,6 +252,121 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether the WebAssembly exception handling proposal
+    /// will be enabled.
+    ///
+    /// The [WebAssembly exception handling proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature gates tags and the `try`, `catch`, `throw` and
+    /// `rethrow` instructions.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    pub fn exceptions(&mut self, enable: bool) -> &mut Self {
+        self.exceptions = enable;
+        self
+    }
+}
+
+impl Features {
+    /// Start building a set of features, which can be checked against what a
+    /// compiler supports with [`FeaturesBuilder::build_for`].
+    ///
+    /// ```
+    /// # use wasmer_types::Features;
+    /// let features = Features::builder().tail_call(true).threads(false).build();
+    /// assert!(features.tail_call);
+    /// assert!(!features.threads);
+    /// ```
+    pub fn builder() -> FeaturesBuilder {
+        FeaturesBuilder::default()
+    }
+
+    /// Every proposal enabled.
+    pub fn all() -> Self {
+        let mut features = Self::new();
+        for proposal in Self::PROPOSALS {
+            features.set_enabled(proposal, true);
+        }
+        features
+    }
+
+    /// Every proposal disabled.
+    pub fn none() -> Self {
+        let mut features = Self::new();
+        for proposal in Self::PROPOSALS {
+            features.set_enabled(proposal, false);
+        }
+        features
+    }
+
+    /// Check whether a proposal (named as in [`Features::PROPOSALS`]) is
+    /// enabled, returning `None` if there's no such proposal.
+    pub fn is_enabled(&self, proposal: &str) -> Option<bool> {
+        let enabled = match proposal {
+            "threads" => self.threads,
+            "reference-types" => self.reference_types,
+            "simd" => self.simd,
+            "bulk-memory" => self.bulk_memory,
+            "multi-value" => self.multi_value,
+            "tail-call" => self.tail_call,
+            "module-linking" => self.module_linking,
+            "multi-memory" => self.multi_memory,
+            "memory64" => self.memory64,
+            "exceptions" => self.exceptions,
+            "relaxed-simd" => self.relaxed_simd,
+            "extended-const" => self.extended_const,
+            _ => return None,
+        };
+        Some(enabled)
+    }
+
+    /// Enable or disable a proposal (named as in [`Features::PROPOSALS`]),
+    /// returning `false` if there's no such proposal.
+    ///
+    /// Unlike the dedicated methods (e.g. [`Features::reference_types`]),
+    /// this doesn't touch any other proposals.
+    pub fn set_enabled(&mut self, proposal: &str, enable: bool) -> bool {
+        match self.flag_mut(proposal) {
+            Some(enabled) => {
+                *enabled = enable;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The names of the enabled proposals.
+    pub fn enabled(&self) -> Vec<&'static str> {
+        Self::PROPOSALS
+            .into_iter()
+            .filter(|proposal| self.is_enabled(proposal) == Some(true))
+            .collect()
+    }
+
+    fn flag_mut(&mut self, proposal: &str) -> Option<&mut bool> {
+        let enabled = match proposal {
+            "threads" => &mut self.threads,
+            "reference-types" => &mut self.reference_types,
+            "simd" => &mut self.simd,
+            "bulk-memory" => &mut self.bulk_memory,
+            "multi-value" => &mut self.multi_value,
+            "tail-call" => &mut self.tail_call,
+            "module-linking" => &mut self.module_linking,
+            "multi-memory" => &mut self.multi_memory,
+            "memory64" => &mut self.memory64,
+            "exceptions" => &mut self.exceptions,
+            "relaxed-simd" => &mut self.relaxed_simd,
+            "extended-const" => &mut self.extended_const,
+            _ => return None,
+        };
+        Some(enabled)
+    }
 }
 
 impl Default for Features {
@@ -240,6 +375,180 @@ impl Default for Features {
     }
 }
 
+/// Something which can only handle some WebAssembly proposals, like a
+/// compiler.
+pub trait FeatureSupport {
+    /// The proposals which can be used when compiling for `target`.
+    fn supported_features_for_target(&self, _target: &Target) -> Features {
+        Features::all()
+    }
+}
+
+/// Some of the WebAssembly proposals requested from a [`FeaturesBuilder`]
+/// aren't supported.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("these WebAssembly proposals aren't supported: {}", .proposals.join(", "))]
+pub struct UnsupportedFeaturesError {
+    /// The unsupported proposals, named as in [`Features::PROPOSALS`].
+    pub proposals: Vec<&'static str>,
+}
+
+/// A builder for [`Features`], created with [`Features::builder()`].
+///
+/// It keeps track of which proposals were asked for explicitly, so
+/// [`FeaturesBuilder::build_for`] can reject the ones a compiler doesn't
+/// support and quietly turn off the other unsupported ones which are
+/// only enabled by default.
+///
+/// Converting a [`Features`] into a builder uses it as the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct FeaturesBuilder {
+    features: Features,
+    requested: Vec<&'static str>,
+}
+
+impl From<Features> for FeaturesBuilder {
+    fn from(features: Features) -> Self {
+        Self {
+            features,
+            requested: Vec::new(),
+        }
+    }
+}
+
+impl FeaturesBuilder {
+    fn set(mut self, proposal: &'static str, enable: bool) -> Self {
+        self.features.set_enabled(proposal, enable);
+        if !self.requested.contains(&proposal) {
+            self.requested.push(proposal);
+        }
+        self
+    }
+
+    /// Request the threads proposal. See [`Features::threads`].
+    pub fn threads(self, enable: bool) -> Self {
+        self.set("threads", enable)
+    }
+
+    /// Request the reference types proposal, which also enables bulk
+    /// memory. See [`Features::reference_types`].
+    pub fn reference_types(self, enable: bool) -> Self {
+        let builder = self.set("reference-types", enable);
+        if enable {
+            builder.set("bulk-memory", true)
+        } else {
+            builder
+        }
+    }
+
+    /// Request the SIMD proposal. See [`Features::simd`].
+    pub fn simd(self, enable: bool) -> Self {
+        self.set("simd", enable)
+    }
+
+    /// Request the bulk memory proposal, which is needed by reference
+    /// types. See [`Features::bulk_memory`].
+    pub fn bulk_memory(self, enable: bool) -> Self {
+        let builder = self.set("bulk-memory", enable);
+        if enable {
+            builder
+        } else {
+            builder.set("reference-types", false)
+        }
+    }
+
+    /// Request the multi-value proposal. See [`Features::multi_value`].
+    pub fn multi_value(self, enable: bool) -> Self {
+        self.set("multi-value", enable)
+    }
+
+    /// Request the tail call proposal. See [`Features::tail_call`].
+    pub fn tail_call(self, enable: bool) -> Self {
+        self.set("tail-call", enable)
+    }
+
+    /// Request the module linking proposal. See
+    /// [`Features::module_linking`].
+    pub fn module_linking(self, enable: bool) -> Self {
+        self.set("module-linking", enable)
+    }
+
+    /// Request the multi-memory proposal. See [`Features::multi_memory`].
+    pub fn multi_memory(self, enable: bool) -> Self {
+        self.set("multi-memory", enable)
+    }
+
+    /// Request the 64-bit memory proposal. See [`Features::memory64`].
+    pub fn memory64(self, enable: bool) -> Self {
+        self.set("memory64", enable)
+    }
+
+    /// Request the exception handling proposal. See
+    /// [`Features::exceptions`].
+    pub fn exceptions(self, enable: bool) -> Self {
+        self.set("exceptions", enable)
+    }
+
+    /// Request the relaxed SIMD proposal.
+    pub fn relaxed_simd(self, enable: bool) -> Self {
+        self.set("relaxed-simd", enable)
+    }
+
+    /// Request the extended constant expressions proposal.
+    pub fn extended_const(self, enable: bool) -> Self {
+        self.set("extended-const", enable)
+    }
+
+    /// Get the features, without checking them against anything.
+    pub fn build(self) -> Features {
+        self.features
+    }
+
+    /// Get the features, checking them against what `compiler` supports
+    /// for the host.
+    ///
+    /// # Errors
+    ///
+    /// Fails with every explicitly requested proposal `compiler` doesn't
+    /// support.
+    pub fn build_for(
+        self,
+        compiler: &(impl FeatureSupport + ?Sized),
+    ) -> Result<Features, UnsupportedFeaturesError> {
+        self.build_for_target(compiler, &Target::default())
+    }
+
+    /// Like [`FeaturesBuilder::build_for`], but for compiling to `target`.
+    pub fn build_for_target(
+        self,
+        compiler: &(impl FeatureSupport + ?Sized),
+        target: &Target,
+    ) -> Result<Features, UnsupportedFeaturesError> {
+        let supported = compiler.supported_features_for_target(target);
+        let mut features = self.features;
+        let mut unsupported = Vec::new();
+
+        for proposal in features.enabled() {
+            if supported.is_enabled(proposal) == Some(true) {
+                continue;
+            }
+            if self.requested.contains(&proposal) {
+                unsupported.push(proposal);
+            } else {
+                features.set_enabled(proposal, false);
+            }
+        }
+
+        if unsupported.is_empty() {
+            Ok(features)
+        } else {
+            Err(UnsupportedFeaturesError {
+                proposals: unsupported,
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_features {
     use super::*;
@@ -340,4 +649,68 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn all_and_none() {
+        assert_eq!(Features::all().enabled(), Features::PROPOSALS.to_vec());
+        assert!(Features::none().enabled().is_empty());
+    }
+
+    #[test]
+    fn builder_starts_from_the_defaults() {
+        assert_eq!(Features::builder().build(), Features::default());
+    }
+
+    #[test]
+    fn builder_reference_types_implies_bulk_memory() {
+        let features = Features::builder()
+            .bulk_memory(false)
+            .reference_types(true)
+            .build();
+        assert!(features.reference_types);
+        assert!(features.bulk_memory);
+    }
+
+    struct NoSimd;
+
+    impl FeatureSupport for NoSimd {
+        fn supported_features_for_target(&self, _target: &Target) -> Features {
+            let mut features = Features::all();
+            features.simd = false;
+            features.memory64 = false;
+            features
+        }
+    }
+
+    #[test]
+    fn build_for_disables_unsupported_defaults() {
+        let features = Features::builder().build_for(&NoSimd).unwrap();
+        assert!(!features.simd);
+        assert!(features.threads);
+    }
+
+    #[test]
+    fn build_for_rejects_requested_proposals() {
+        let err = Features::builder()
+            .simd(true)
+            .memory64(true)
+            .build_for(&NoSimd)
+            .unwrap_err();
+        assert_eq!(err.proposals, vec!["simd", "memory64"]);
+        assert_eq!(
+            err.to_string(),
+            "these WebAssembly proposals aren't supported: simd, memory64"
+        );
+    }
+
+    #[test]
+    fn build_for_accepts_starting_sets() {
+        let features = FeaturesBuilder::from(Features::all())
+            .threads(false)
+            .build_for(&NoSimd)
+            .unwrap();
+        assert!(!features.threads);
+        assert!(!features.simd);
+        assert!(features.exceptions);
+    }
 }
@@ -137,6 +137,27 @@ entity_impl!(GlobalIndex);
 pub struct MemoryIndex(u32);
 entity_impl!(MemoryIndex);
 
+/// Index type of an exception tag (imported or local) inside the WebAssembly
+/// module.
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Debug,
+    RkyvSerialize,
+    RkyvDeserialize,
+    Archive,
+    rkyv::CheckBytes,
+)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[archive(as = "Self")]
+pub struct TagIndex(u32);
+entity_impl!(TagIndex);
+
 /// Index type of a signature (imported or local) inside the WebAssembly module.
 #[derive(
     Copy,
@@ -244,6 +265,8 @@ pub enum ExportIndex {
     Memory(MemoryIndex),
     /// Global export.
     Global(GlobalIndex),
+    /// Exception tag export.
+    Tag(TagIndex),
 }
 
 /// An entity to import.
@@ -272,4 +295,6 @@ pub enum ImportIndex {
     Memory(MemoryIndex),
     /// Global import.
     Global(GlobalIndex),
+    /// Exception tag import.
+    Tag(TagIndex),
 }
@@ -0,0 +1,291 @@
+//! A serializable description of everything a module imports and exports.
+
+use crate::lib::std::vec::Vec;
+use crate::types::{ExportType, ExternType, ImportType, Type};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "enable-serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// The imports and exports of a module, in the order they appear in the
+/// WebAssembly bytecode.
+///
+/// With the `enable-serde` feature this serializes to a stable schema meant
+/// for tooling, which doesn't change when the internal representation of the
+/// types does:
+///
+/// ```json
+/// {
+///   "imports": [
+///     { "module": "env", "name": "log", "type": { "kind": "function", "params": ["i32"], "results": [] } }
+///   ],
+///   "exports": [
+///     { "name": "memory", "type": { "kind": "memory", "minimum": 1, "maximum": null, "shared": false, "memory64": false } },
+///     { "name": "table", "type": { "kind": "table", "element": "funcref", "minimum": 1, "maximum": 2 } },
+///     { "name": "counter", "type": { "kind": "global", "value": "i64", "mutable": true } }
+///   ]
+/// }
+/// ```
+///
+/// Value types are one of `i32`, `i64`, `f32`, `f64`, `v128`, `externref`
+/// and `funcref`, and memory limits are in pages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ModuleInterface {
+    /// Everything the module imports.
+    pub imports: Vec<ImportType>,
+    /// Everything the module exports.
+    pub exports: Vec<ExportType>,
+}
+
+impl ModuleInterface {
+    /// A SHA-256 digest of the interface.
+    ///
+    /// Two modules with the same digest can be used interchangeably as far
+    /// as linking is concerned: they import and export the same names, in the
+    /// same order, with the same types.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+
+        hasher.update((self.imports.len() as u64).to_le_bytes());
+        for import in &self.imports {
+            hash_str(&mut hasher, import.module());
+            hash_str(&mut hasher, import.name());
+            hash_extern_type(&mut hasher, import.ty());
+        }
+
+        hasher.update((self.exports.len() as u64).to_le_bytes());
+        for export in &self.exports {
+            hash_str(&mut hasher, export.name());
+            hash_extern_type(&mut hasher, export.ty());
+        }
+
+        hasher.finalize().into()
+    }
+}
+
+fn hash_str(hasher: &mut Sha256, s: &str) {
+    // Length-prefixed, so names can't run into each other
+    hasher.update((s.len() as u64).to_le_bytes());
+    hasher.update(s.as_bytes());
+}
+
+fn hash_types(hasher: &mut Sha256, types: &[Type]) {
+    hasher.update((types.len() as u64).to_le_bytes());
+    for ty in types {
+        hash_str(hasher, type_name(*ty));
+    }
+}
+
+fn hash_limits(hasher: &mut Sha256, minimum: u32, maximum: Option<u32>) {
+    hasher.update(minimum.to_le_bytes());
+    match maximum {
+        Some(maximum) => {
+            hasher.update([1]);
+            hasher.update(maximum.to_le_bytes());
+        }
+        None => hasher.update([0]),
+    }
+}
+
+fn hash_extern_type(hasher: &mut Sha256, ty: &ExternType) {
+    hash_str(hasher, extern_kind(ty));
+    match ty {
+        ExternType::Function(f) => {
+            hash_types(hasher, f.params());
+            hash_types(hasher, f.results());
+        }
+        ExternType::Global(g) => {
+            hash_str(hasher, type_name(g.ty));
+            hasher.update([g.mutability.is_mutable() as u8]);
+        }
+        ExternType::Table(t) => {
+            hash_str(hasher, type_name(t.ty));
+            hash_limits(hasher, t.minimum, t.maximum);
+        }
+        ExternType::Memory(m) => {
+            hash_limits(hasher, m.minimum.0, m.maximum.map(|p| p.0));
+            hasher.update([m.shared as u8, m.memory64 as u8]);
+        }
+        ExternType::Tag(t) => hash_types(hasher, t.params()),
+    }
+}
+
+fn extern_kind(ty: &ExternType) -> &'static str {
+    match ty {
+        ExternType::Function(_) => "function",
+        ExternType::Global(_) => "global",
+        ExternType::Table(_) => "table",
+        ExternType::Memory(_) => "memory",
+        ExternType::Tag(_) => "tag",
+    }
+}
+
+/// The name a value type has in the text format.
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::V128 => "v128",
+        Type::ExternRef => "externref",
+        Type::FuncRef => "funcref",
+    }
+}
+
+#[cfg(feature = "enable-serde")]
+impl Serialize for ModuleInterface {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let imports: Vec<_> = self.imports.iter().map(ImportDescriptor).collect();
+        let exports: Vec<_> = self.exports.iter().map(ExportDescriptor).collect();
+
+        let mut s = serializer.serialize_struct("ModuleInterface", 2)?;
+        s.serialize_field("imports", &imports)?;
+        s.serialize_field("exports", &exports)?;
+        s.end()
+    }
+}
+
+/// The schema doesn't follow the derived `Serialize` implementations of
+/// [`ImportType`] and [`ExportType`], so these wrap them.
+#[cfg(feature = "enable-serde")]
+struct ImportDescriptor<'a>(&'a ImportType);
+
+#[cfg(feature = "enable-serde")]
+impl Serialize for ImportDescriptor<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Import", 3)?;
+        s.serialize_field("module", self.0.module())?;
+        s.serialize_field("name", self.0.name())?;
+        s.serialize_field("type", &ExternTypeDescriptor(self.0.ty()))?;
+        s.end()
+    }
+}
+
+#[cfg(feature = "enable-serde")]
+struct ExportDescriptor<'a>(&'a ExportType);
+
+#[cfg(feature = "enable-serde")]
+impl Serialize for ExportDescriptor<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Export", 2)?;
+        s.serialize_field("name", self.0.name())?;
+        s.serialize_field("type", &ExternTypeDescriptor(self.0.ty()))?;
+        s.end()
+    }
+}
+
+#[cfg(feature = "enable-serde")]
+struct ExternTypeDescriptor<'a>(&'a ExternType);
+
+#[cfg(feature = "enable-serde")]
+impl Serialize for ExternTypeDescriptor<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = extern_kind(self.0);
+        match self.0 {
+            ExternType::Function(f) => {
+                let params: Vec<_> = f.params().iter().map(|ty| type_name(*ty)).collect();
+                let results: Vec<_> = f.results().iter().map(|ty| type_name(*ty)).collect();
+                let mut s = serializer.serialize_struct("FunctionType", 3)?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("params", &params)?;
+                s.serialize_field("results", &results)?;
+                s.end()
+            }
+            ExternType::Global(g) => {
+                let mut s = serializer.serialize_struct("GlobalType", 3)?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("value", type_name(g.ty))?;
+                s.serialize_field("mutable", &g.mutability.is_mutable())?;
+                s.end()
+            }
+            ExternType::Table(t) => {
+                let mut s = serializer.serialize_struct("TableType", 4)?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("element", type_name(t.ty))?;
+                s.serialize_field("minimum", &t.minimum)?;
+                s.serialize_field("maximum", &t.maximum)?;
+                s.end()
+            }
+            ExternType::Memory(m) => {
+                let mut s = serializer.serialize_struct("MemoryType", 5)?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("minimum", &m.minimum.0)?;
+                s.serialize_field("maximum", &m.maximum.map(|p| p.0))?;
+                s.serialize_field("shared", &m.shared)?;
+                s.serialize_field("memory64", &m.memory64)?;
+                s.end()
+            }
+            ExternType::Tag(t) => {
+                let params: Vec<_> = t.params().iter().map(|ty| type_name(*ty)).collect();
+                let mut s = serializer.serialize_struct("TagType", 2)?;
+                s.serialize_field("kind", kind)?;
+                s.serialize_field("params", &params)?;
+                s.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionType, GlobalType, MemoryType, Mutability};
+    use crate::units::Pages;
+
+    fn interface() -> ModuleInterface {
+        ModuleInterface {
+            imports: vec![ImportType::new(
+                "env",
+                "log",
+                ExternType::Function(FunctionType::new([Type::I32], [Type::I32])),
+            )],
+            exports: vec![
+                ExportType::new(
+                    "memory",
+                    ExternType::Memory(MemoryType::new(Pages(1), None, false)),
+                ),
+                ExportType::new(
+                    "counter",
+                    ExternType::Global(GlobalType::new(Type::I64, Mutability::Var)),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        assert_eq!(interface().hash(), interface().hash());
+        assert_ne!(interface().hash(), ModuleInterface::default().hash());
+    }
+
+    #[test]
+    fn hash_covers_names_and_types() {
+        let original = interface().hash();
+
+        let mut renamed = interface();
+        renamed.exports[0] = ExportType::new(
+            "mem",
+            ExternType::Memory(MemoryType::new(Pages(1), None, false)),
+        );
+        assert_ne!(renamed.hash(), original);
+
+        let mut limited = interface();
+        limited.exports[0] = ExportType::new(
+            "memory",
+            ExternType::Memory(MemoryType::new(Pages(1), Some(Pages(1)), false)),
+        );
+        assert_ne!(limited.hash(), original);
+
+        let mut immutable = interface();
+        immutable.exports[1] = ExportType::new(
+            "counter",
+            ExternType::Global(GlobalType::new(Type::I64, Mutability::Const)),
+        );
+        assert_ne!(immutable.hash(), original);
+
+        let mut reordered = interface();
+        reordered.exports.reverse();
+        assert_ne!(reordered.hash(), original);
+    }
+}
@@ -58,10 +58,12 @@ pub mod error;
 mod features;
 mod indexes;
 mod initializers;
+mod interface;
 mod libcalls;
 mod memory;
 mod module;
 mod serialize;
+mod source_map;
 mod stack;
 mod store_id;
 mod table;
@@ -76,31 +78,36 @@ pub use crate::compilation::target::{
     Aarch64Architecture, Architecture, BinaryFormat, CallingConvention, CpuFeature, Endianness,
     Environment, OperatingSystem, PointerWidth, Target, Triple, Vendor,
 };
-pub use crate::serialize::{MetadataHeader, SerializableCompilation, SerializableModule};
+pub use crate::serialize::{
+    ArtifactMetadata, MetadataHeader, SerializableCompilation, SerializableModule,
+};
 pub use error::{
-    CompileError, DeserializeError, ImportError, MemoryError, MiddlewareError,
-    ParseCpuFeatureError, PreInstantiationError, SerializeError, WasmError, WasmResult,
+    CompileError, DeserializeError, FunctionLocation, ImportError, MemoryError, MiddlewareError,
+    ParseCpuFeatureError, PreInstantiationError, SerializeError, ValidationError,
+    ValidationErrorKind, WasmError, WasmResult,
 };
 
 /// The entity module, with common helpers for Rust structures
 pub mod entity;
-pub use crate::features::Features;
+pub use crate::features::{FeatureSupport, Features, FeaturesBuilder, UnsupportedFeaturesError};
 pub use crate::indexes::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, ImportIndex,
     LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    SignatureIndex, TableIndex,
+    SignatureIndex, TableIndex, TagIndex,
 };
 pub use crate::initializers::{
     DataInitializer, DataInitializerLocation, OwnedDataInitializer, TableInitializer,
 };
+pub use crate::interface::ModuleInterface;
 pub use crate::memory::{Memory32, Memory64, MemorySize};
 pub use crate::module::{ExportsIterator, ImportKey, ImportsIterator, ModuleInfo};
 pub use crate::units::{
-    Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Bytes, PageCountOutOfRange, Pages, WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_MIN_PAGES,
+    WASM_PAGE_SIZE,
 };
 pub use types::{
     ExportType, ExternType, FunctionType, GlobalInit, GlobalType, ImportType, MemoryType,
-    Mutability, TableType, Type, V128,
+    Mutability, TableType, TagType, Type, V128,
 };
 pub use value::{RawValue, ValueType};
 
@@ -123,12 +130,13 @@ pub use crate::compilation::section::{
 pub use crate::compilation::address_map::{FunctionAddressMap, InstructionAddressMap};
 pub use crate::compilation::function::{
     Compilation, CompiledFunction, CompiledFunctionFrameInfo, CustomSections, Dwarf, FunctionBody,
-    Functions,
+    Functions, LazyCompiledFunction,
 };
 pub use crate::compilation::module::CompileModuleInfo;
 pub use crate::compilation::symbols::{Symbol, SymbolRegistry};
 pub use crate::compilation::unwind::CompiledFunctionUnwindInfo;
 
+pub use crate::source_map::{WatLocation, WatSourceMap};
 pub use crate::stack::{FrameInfo, SourceLoc, TrapInformation};
 pub use crate::store_id::StoreId;
 
@@ -9,7 +9,7 @@ use crate::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex,
     FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, LocalFunctionIndex,
     LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex, MemoryType, SignatureIndex,
-    TableIndex, TableInitializer, TableType,
+    TableIndex, TableInitializer, TableType, TagIndex, TagType, WatSourceMap,
 };
 use indexmap::IndexMap;
 use rkyv::{
@@ -24,6 +24,7 @@ use std::collections::HashMap;
 use std::fmt;
 use std::iter::ExactSizeIterator;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
 #[archive_attr(derive(CheckBytes))]
@@ -158,12 +159,23 @@ pub struct ModuleInfo {
     /// WebAssembly global variables (imported and local).
     pub globals: PrimaryMap<GlobalIndex, GlobalType>,
 
+    /// WebAssembly exception tags (imported and local), with the signature
+    /// giving the types of the values thrown with each.
+    pub tags: PrimaryMap<TagIndex, SignatureIndex>,
+
     /// Custom sections in the module.
+    ///
+    /// A module may contain several custom sections with the same name, in
+    /// which case this refers to the last one. Use
+    /// [`ModuleInfo::custom_sections`] to get all of them.
     pub custom_sections: IndexMap<String, CustomSectionIndex>,
 
     /// The data for each CustomSection in the module.
     pub custom_sections_data: PrimaryMap<CustomSectionIndex, Box<[u8]>>,
 
+    /// The name of each CustomSection in the module.
+    pub custom_section_names: PrimaryMap<CustomSectionIndex, String>,
+
     /// Number of imported functions in the module.
     pub num_imported_functions: usize,
 
@@ -175,6 +187,16 @@ pub struct ModuleInfo {
 
     /// Number of imported globals in the module.
     pub num_imported_globals: usize,
+
+    /// Number of imported exception tags in the module.
+    pub num_imported_tags: usize,
+
+    /// Where the module's code came from, if it was compiled from the
+    /// WebAssembly text format.
+    ///
+    /// This is never serialized.
+    #[cfg_attr(feature = "enable-serde", serde(skip_serializing, skip_deserializing))]
+    pub source_map: Option<Arc<WatSourceMap>>,
 }
 
 /// Mirror version of ModuleInfo that can derive rkyv traits
@@ -195,12 +217,15 @@ pub struct ArchivableModuleInfo {
     tables: PrimaryMap<TableIndex, TableType>,
     memories: PrimaryMap<MemoryIndex, MemoryType>,
     globals: PrimaryMap<GlobalIndex, GlobalType>,
+    tags: PrimaryMap<TagIndex, SignatureIndex>,
     custom_sections: IndexMap<String, CustomSectionIndex>,
     custom_sections_data: PrimaryMap<CustomSectionIndex, Box<[u8]>>,
+    custom_section_names: PrimaryMap<CustomSectionIndex, String>,
     num_imported_functions: usize,
     num_imported_tables: usize,
     num_imported_memories: usize,
     num_imported_globals: usize,
+    num_imported_tags: usize,
 }
 
 impl From<ModuleInfo> for ArchivableModuleInfo {
@@ -220,12 +245,15 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             tables: it.tables,
             memories: it.memories,
             globals: it.globals,
+            tags: it.tags,
             custom_sections: it.custom_sections,
             custom_sections_data: it.custom_sections_data,
+            custom_section_names: it.custom_section_names,
             num_imported_functions: it.num_imported_functions,
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            num_imported_tags: it.num_imported_tags,
         }
     }
 }
@@ -248,12 +276,16 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             tables: it.tables,
             memories: it.memories,
             globals: it.globals,
+            tags: it.tags,
             custom_sections: it.custom_sections,
             custom_sections_data: it.custom_sections_data,
+            custom_section_names: it.custom_section_names,
             num_imported_functions: it.num_imported_functions,
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
             num_imported_globals: it.num_imported_globals,
+            num_imported_tags: it.num_imported_tags,
+            source_map: None,
         }
     }
 }
@@ -308,12 +340,15 @@ impl PartialEq for ModuleInfo {
             && self.tables == other.tables
             && self.memories == other.memories
             && self.globals == other.globals
+            && self.tags == other.tags
             && self.custom_sections == other.custom_sections
             && self.custom_sections_data == other.custom_sections_data
+            && self.custom_section_names == other.custom_section_names
             && self.num_imported_functions == other.num_imported_functions
             && self.num_imported_tables == other.num_imported_tables
             && self.num_imported_memories == other.num_imported_memories
             && self.num_imported_globals == other.num_imported_globals
+            && self.num_imported_tags == other.num_imported_tags
     }
 }
 
@@ -366,6 +401,7 @@ impl ModuleInfo {
                     let global_type = self.globals.get(*i).unwrap();
                     ExternType::Global(*global_type)
                 }
+                ExportIndex::Tag(i) => ExternType::Tag(self.tag_type(*i)),
             };
             ExportType::new(name, extern_type)
         });
@@ -396,24 +432,34 @@ impl ModuleInfo {
                             let global_type = self.globals.get(*i).unwrap();
                             ExternType::Global(*global_type)
                         }
+                        ImportIndex::Tag(i) => ExternType::Tag(self.tag_type(*i)),
                     };
                     ImportType::new(module, field, extern_type)
                 });
         ImportsIterator::new(iter, self.imports.len())
     }
 
-    /// Get the custom sections of the module given a `name`.
+    /// Get the custom sections of the module given a `name`, in the order
+    /// they appear in the module.
     pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Box<[u8]>> + 'a {
-        self.custom_sections
+        self.custom_section_names
             .iter()
-            .filter_map(move |(section_name, section_index)| {
+            .filter_map(move |(section_index, section_name)| {
                 if name != section_name {
                     return None;
                 }
-                Some(self.custom_sections_data[*section_index].clone())
+                Some(self.custom_sections_data[section_index].clone())
             })
     }
 
+    /// Get the names of the module's custom sections.
+    ///
+    /// Each name is only returned once, in the order it first appears in the
+    /// module.
+    pub fn custom_section_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.custom_sections.keys().map(|name| name.as_str())
+    }
+
     /// Convert a `LocalFunctionIndex` into a `FunctionIndex`.
     pub fn func_index(&self, local_func: LocalFunctionIndex) -> FunctionIndex {
         FunctionIndex::new(self.num_imported_functions + local_func.index())
@@ -489,6 +535,16 @@ impl ModuleInfo {
         index.index() < self.num_imported_globals
     }
 
+    /// Test whether the given tag index is for an imported tag.
+    pub fn is_imported_tag(&self, index: TagIndex) -> bool {
+        index.index() < self.num_imported_tags
+    }
+
+    /// Get the type of the exception tag at `index`.
+    pub fn tag_type(&self, index: TagIndex) -> TagType {
+        TagType::new(self.signatures[self.tags[index]].params())
+    }
+
     /// Get the Module name
     pub fn name(&self) -> String {
         match self.name {
@@ -1,9 +1,9 @@
-use crate::entity::PrimaryMap;
+use crate::entity::{EntityRef, PrimaryMap};
 use crate::{
     compilation::target::CpuFeature, CompileModuleInfo, CompiledFunctionFrameInfo, CustomSection,
     DeserializeError, Dwarf, Features, FunctionBody, FunctionIndex, LocalFunctionIndex,
-    MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, SectionIndex,
-    SerializeError, SignatureIndex, TableIndex, TableStyle,
+    MemoryIndex, MemoryStyle, ModuleInfo, OwnedDataInitializer, Relocation, RelocationKind,
+    RelocationTarget, SectionIndex, SerializeError, SignatureIndex, TableIndex, TableStyle,
 };
 use enumset::EnumSet;
 use rkyv::check_archived_value;
@@ -12,6 +12,7 @@ use rkyv::{
     ser::Serializer as RkyvSerializer, Archive, CheckBytes, Deserialize as RkyvDeserialize,
     Serialize as RkyvSerialize,
 };
+use sha2::{Digest, Sha256};
 use std::convert::TryInto;
 use std::mem;
 
@@ -196,6 +197,168 @@ impl SerializableModule {
     pub fn table_styles(&self) -> &PrimaryMap<TableIndex, TableStyle> {
         &self.compile_info.table_styles
     }
+
+    /// Check that every index and offset inside the module is in bounds, so
+    /// linking it won't read or write outside of its code.
+    ///
+    /// `rkyv`'s validation only guarantees the archive is well-formed, not
+    /// that its contents are consistent with each other.
+    pub fn check_bounds(&self) -> Result<(), DeserializeError> {
+        let module = self.module_info();
+        let compilation = &self.compilation;
+        let num_local_functions = module
+            .functions
+            .len()
+            .checked_sub(module.num_imported_functions)
+            .ok_or_else(|| {
+                corrupt_section("module info", "more functions are imported than declared")
+            })?;
+
+        if let Some(signature) = module
+            .functions
+            .values()
+            .find(|signature| signature.index() >= module.signatures.len())
+        {
+            return Err(corrupt_section(
+                "module info",
+                format!("unknown signature {}", signature.index()),
+            ));
+        }
+
+        check_len(
+            "function bodies",
+            num_local_functions,
+            compilation.function_bodies.len(),
+        )?;
+        check_len(
+            "function relocations",
+            num_local_functions,
+            compilation.function_relocations.len(),
+        )?;
+        check_len(
+            "function frame info",
+            num_local_functions,
+            compilation.function_frame_info.len(),
+        )?;
+        check_len(
+            "function call trampolines",
+            module.signatures.len(),
+            compilation.function_call_trampolines.len(),
+        )?;
+        check_len(
+            "dynamic function trampolines",
+            module.num_imported_functions,
+            compilation.dynamic_function_trampolines.len(),
+        )?;
+        check_len(
+            "custom section relocations",
+            compilation.custom_sections.len(),
+            compilation.custom_section_relocations.len(),
+        )?;
+
+        let num_sections = compilation.custom_sections.len();
+        check_section_index(
+            "libcall trampolines",
+            compilation.libcall_trampolines,
+            num_sections,
+        )?;
+        if let Some(debug) = &compilation.debug {
+            check_section_index("debug info", debug.eh_frame, num_sections)?;
+        }
+
+        for (index, relocations) in compilation.function_relocations.iter() {
+            let body_len = compilation.function_bodies[index].body.len();
+            for relocation in relocations {
+                check_relocation(
+                    "function relocations",
+                    relocation,
+                    body_len,
+                    num_local_functions,
+                    num_sections,
+                )?;
+            }
+        }
+
+        for (index, relocations) in compilation.custom_section_relocations.iter() {
+            let section_len = compilation.custom_sections[index].bytes.len();
+            for relocation in relocations {
+                check_relocation(
+                    "custom section relocations",
+                    relocation,
+                    section_len,
+                    num_local_functions,
+                    num_sections,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn corrupt_section(section: &'static str, reason: impl Into<String>) -> DeserializeError {
+    DeserializeError::CorruptSection {
+        section,
+        reason: reason.into(),
+    }
+}
+
+fn check_len(section: &'static str, expected: usize, found: usize) -> Result<(), DeserializeError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(corrupt_section(
+            section,
+            format!("expected {expected} entries, found {found}"),
+        ))
+    }
+}
+
+fn check_section_index(
+    section: &'static str,
+    index: SectionIndex,
+    num_sections: usize,
+) -> Result<(), DeserializeError> {
+    if index.index() < num_sections {
+        Ok(())
+    } else {
+        Err(corrupt_section(
+            section,
+            format!("unknown custom section {}", index.index()),
+        ))
+    }
+}
+
+fn check_relocation(
+    section: &'static str,
+    relocation: &Relocation,
+    body_len: usize,
+    num_local_functions: usize,
+    num_sections: usize,
+) -> Result<(), DeserializeError> {
+    // How many bytes are patched when the relocation is applied
+    let size = match relocation.kind {
+        RelocationKind::Abs8 | RelocationKind::X86PCRel8 | RelocationKind::RiscvCall => 8,
+        _ => 4,
+    };
+    let end = (relocation.offset as usize).checked_add(size);
+    if end.map_or(true, |end| end > body_len) {
+        return Err(corrupt_section(
+            section,
+            format!(
+                "a relocation at offset {} is outside of the {body_len} byte body",
+                relocation.offset
+            ),
+        ));
+    }
+
+    match relocation.reloc_target {
+        RelocationTarget::LocalFunc(index) if index.index() >= num_local_functions => Err(
+            corrupt_section(section, format!("unknown local function {}", index.index())),
+        ),
+        RelocationTarget::CustomSection(index) => check_section_index(section, index, num_sections),
+        _ => Ok(()),
+    }
 }
 
 /// Metadata header which holds an ABI version and the length of the remaining
@@ -211,7 +374,13 @@ pub struct MetadataHeader {
 impl MetadataHeader {
     /// Current ABI version. Increment this any time breaking changes are made
     /// to the format of the serialized data.
-    pub const CURRENT_VERSION: u32 = 4;
+    pub const CURRENT_VERSION: u32 = 8;
+
+    /// The oldest ABI version which can still be read.
+    ///
+    /// Version 8 added exception tags to the module info and to the
+    /// `VMContext` the compiled code was generated for.
+    pub const OLDEST_SUPPORTED_VERSION: u32 = 8;
 
     /// Magic number to identify wasmer metadata.
     const MAGIC: [u8; 8] = *b"WASMER\0\0";
@@ -238,6 +407,12 @@ impl MetadataHeader {
 
     /// Parses the header and returns the length of the metadata following it.
     pub fn parse(bytes: &[u8]) -> Result<usize, DeserializeError> {
+        Self::parse_versioned(bytes).map(|(_, len)| len)
+    }
+
+    /// Parses the header and returns the ABI version the metadata was written
+    /// with, along with its length.
+    pub fn parse_versioned(bytes: &[u8]) -> Result<(u32, usize), DeserializeError> {
         if bytes.as_ptr() as usize % 8 != 0 {
             return Err(DeserializeError::CorruptedBinary(
                 "misaligned metadata".to_string(),
@@ -256,12 +431,176 @@ impl MetadataHeader {
                 "The provided bytes were not serialized by Wasmer".to_string(),
             ));
         }
-        if header.version != Self::CURRENT_VERSION {
+        if !(Self::OLDEST_SUPPORTED_VERSION..=Self::CURRENT_VERSION).contains(&header.version) {
             return Err(DeserializeError::Incompatible(
                 "The provided bytes were serialized by an incompatible version of Wasmer"
                     .to_string(),
             ));
         }
-        Ok(header.len as usize)
+        Ok((header.version, header.len as usize))
+    }
+}
+
+/// Information appended to a module's serialized data (since ABI version 6),
+/// which lets an artifact be checked before it is loaded.
+///
+/// The trailer has the following format:
+/// Wasmer version length (4 bytes) + Wasmer version + target triple length
+/// (4 bytes) + target triple + compiler length (4 bytes) + compiler +
+/// SHA-256 checksum (32 bytes)
+///
+/// The compiler was added later within the same ABI version, so it's
+/// missing from older artifacts.
+///
+/// The checksum covers the length of the serialized data, the serialized data
+/// itself, and the rest of the trailer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactMetadata {
+    /// The version of Wasmer which serialized the artifact.
+    pub wasmer_version: String,
+    /// The target triple the artifact was compiled for.
+    pub triple: String,
+    /// The compiler and settings the artifact was compiled with (see
+    /// `Compiler::deterministic_id`), or an empty string if the artifact
+    /// predates it being recorded.
+    pub compiler: String,
+}
+
+impl ArtifactMetadata {
+    /// Length of the checksum at the end of the trailer.
+    const CHECKSUM_LEN: usize = 32;
+
+    /// Append the trailer for `serialized_data` to `out`.
+    pub fn write(&self, serialized_data: &[u8], out: &mut Vec<u8>) {
+        let start = out.len();
+        for field in [&self.wasmer_version, &self.triple, &self.compiler] {
+            let len: u32 = field
+                .len()
+                .try_into()
+                .expect("metadata exceeds maximum length");
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(field.as_bytes());
+        }
+        let checksum = Self::checksum(serialized_data, &out[start..]);
+        out.extend_from_slice(&checksum);
+    }
+
+    /// Parse the trailer following `serialized_data`, making sure the
+    /// checksum matches.
+    pub fn read(serialized_data: &[u8], trailer: &[u8]) -> Result<Self, DeserializeError> {
+        let fields_len = trailer
+            .len()
+            .checked_sub(Self::CHECKSUM_LEN)
+            .ok_or_else(|| corrupt_section("artifact metadata", "the checksum is missing"))?;
+        let (mut fields, checksum) = trailer.split_at(fields_len);
+
+        if Self::checksum(serialized_data, fields)[..] != *checksum {
+            return Err(DeserializeError::ChecksumMismatch);
+        }
+
+        let wasmer_version = read_string(&mut fields)?;
+        let triple = read_string(&mut fields)?;
+        let compiler = if fields.is_empty() {
+            String::new()
+        } else {
+            read_string(&mut fields)?
+        };
+        if !fields.is_empty() {
+            return Err(corrupt_section(
+                "artifact metadata",
+                format!("found {} unexpected bytes", fields.len()),
+            ));
+        }
+
+        Ok(Self {
+            wasmer_version,
+            triple,
+            compiler,
+        })
+    }
+
+    fn checksum(serialized_data: &[u8], fields: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        // Include the length so bytes can't be moved between the serialized
+        // data and the trailer without changing the checksum
+        hasher.update((serialized_data.len() as u64).to_le_bytes());
+        hasher.update(serialized_data);
+        hasher.update(fields);
+        hasher.finalize().into()
+    }
+}
+
+/// Read a length-prefixed string from the start of `bytes`.
+fn read_string(bytes: &mut &[u8]) -> Result<String, DeserializeError> {
+    let truncated = || corrupt_section("artifact metadata", "a field is truncated");
+
+    let len: [u8; 4] = bytes.get(..4).ok_or_else(truncated)?.try_into().unwrap();
+    let end = 4_usize
+        .checked_add(u32::from_le_bytes(len) as usize)
+        .ok_or_else(truncated)?;
+    let value = bytes.get(4..end).ok_or_else(truncated)?;
+    let value = std::str::from_utf8(value)
+        .map_err(|e| corrupt_section("artifact metadata", e.to_string()))?;
+
+    *bytes = &bytes[end..];
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> ArtifactMetadata {
+        ArtifactMetadata {
+            wasmer_version: "1.2.3".to_string(),
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+            compiler: "llvm-O2".to_string(),
+        }
+    }
+
+    #[test]
+    fn artifact_metadata_round_trip() {
+        let mut trailer = Vec::new();
+        metadata().write(b"data", &mut trailer);
+
+        assert_eq!(
+            ArtifactMetadata::read(b"data", &trailer).unwrap(),
+            metadata()
+        );
+    }
+
+    #[test]
+    fn artifact_metadata_without_compiler() {
+        // Trailers written before the compiler was recorded
+        let mut fields = Vec::new();
+        for field in ["1.2.3", "x86_64-unknown-linux-gnu"] {
+            fields.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            fields.extend_from_slice(field.as_bytes());
+        }
+        let mut trailer = fields.clone();
+        trailer.extend_from_slice(&ArtifactMetadata::checksum(b"data", &fields));
+
+        let metadata = ArtifactMetadata::read(b"data", &trailer).unwrap();
+        assert_eq!(metadata.triple, "x86_64-unknown-linux-gnu");
+        assert_eq!(metadata.compiler, "");
+    }
+
+    #[test]
+    fn artifact_metadata_detects_corruption() {
+        let mut trailer = Vec::new();
+        metadata().write(b"data", &mut trailer);
+
+        assert!(matches!(
+            ArtifactMetadata::read(b"dat4", &trailer),
+            Err(DeserializeError::ChecksumMismatch)
+        ));
+        for len in 0..trailer.len() {
+            assert!(ArtifactMetadata::read(b"data", &trailer[..len]).is_err());
+        }
+        for i in 0..trailer.len() {
+            let mut corrupted = trailer.clone();
+            corrupted[i] ^= 0x10;
+            assert!(ArtifactMetadata::read(b"data", &corrupted).is_err());
+        }
     }
 }
@@ -0,0 +1,79 @@
+//! Mapping offsets in a module that was compiled from the WebAssembly text
+//! format back to the text.
+
+use crate::lib::std::fmt;
+use crate::lib::std::vec::Vec;
+
+/// A position in a WebAssembly text file. Lines and columns start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatLocation {
+    /// The line number.
+    pub line: u32,
+    /// The column, in characters.
+    pub column: u32,
+}
+
+impl fmt::Display for WatLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Maps offsets in a binary module to the WebAssembly text it was
+/// generated from.
+///
+/// A source map only describes the exact binary it was created with, so it
+/// is never serialized along with a module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatSourceMap {
+    /// The offsets where a new location starts, sorted.
+    entries: Vec<(usize, WatLocation)>,
+    /// Offsets from here on aren't covered by the map.
+    end: usize,
+}
+
+impl WatSourceMap {
+    /// Create a source map where each entry's location covers everything
+    /// from its offset up to the next entry, or `end`.
+    pub fn new(mut entries: Vec<(usize, WatLocation)>, end: usize) -> Self {
+        entries.sort_by_key(|(offset, _)| *offset);
+        Self { entries, end }
+    }
+
+    /// Find where the byte at `offset` in the binary came from.
+    pub fn lookup(&self, offset: usize) -> Option<WatLocation> {
+        if offset >= self.end {
+            return None;
+        }
+        let index = self.entries.partition_point(|(start, _)| *start <= offset);
+        let (_, location) = self.entries.get(index.checked_sub(1)?)?;
+        Some(*location)
+    }
+
+    /// Does the map cover anything at all?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: u32, column: u32) -> WatLocation {
+        WatLocation { line, column }
+    }
+
+    #[test]
+    fn lookup_finds_the_closest_preceding_entry() {
+        let map = WatSourceMap::new(vec![(20, at(3, 5)), (10, at(2, 1))], 30);
+
+        assert_eq!(map.lookup(9), None);
+        assert_eq!(map.lookup(10), Some(at(2, 1)));
+        assert_eq!(map.lookup(19), Some(at(2, 1)));
+        assert_eq!(map.lookup(20), Some(at(3, 5)));
+        assert_eq!(map.lookup(29), Some(at(3, 5)));
+        assert_eq!(map.lookup(30), None);
+        assert_eq!(at(3, 5).to_string(), "3:5");
+    }
+}
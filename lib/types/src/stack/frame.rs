@@ -1,4 +1,4 @@
-use crate::SourceLoc;
+use crate::{SourceLoc, WatLocation};
 
 /// Description of a frame in a backtrace.
 ///
@@ -16,6 +16,9 @@ pub struct FrameInfo {
     func_start: SourceLoc,
     /// The source location of the instruction
     instr: SourceLoc,
+    /// Where the instruction is in the WebAssembly text, if the module was
+    /// compiled from it
+    wat_location: Option<WatLocation>,
 }
 
 impl FrameInfo {
@@ -33,9 +36,17 @@ impl FrameInfo {
             function_name,
             func_start,
             instr,
+            wat_location: None,
         }
     }
 
+    /// Attach the position in the WebAssembly text the frame's instruction
+    /// came from.
+    pub fn with_wat_location(mut self, wat_location: Option<WatLocation>) -> Self {
+        self.wat_location = wat_location;
+        self
+    }
+
     /// Returns the WebAssembly function index for this frame.
     ///
     /// This function index is the index in the function index space of the
@@ -93,4 +104,10 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns where this frame's instruction is in the WebAssembly text
+    /// format, if the module was compiled from text.
+    pub fn wat_location(&self) -> Option<WatLocation> {
+        self.wat_location
+    }
 }
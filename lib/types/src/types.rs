@@ -61,6 +61,13 @@ impl fmt::Display for Type {
 }
 
 /// The WebAssembly V128 type
+///
+/// The bytes are stored in the same order the guest sees them in linear
+/// memory (i.e. the order `v128.store` writes them). Lane `n` of an
+/// `iNxM`/`fNxM` interpretation is made up of the bytes `n * N / 8..(n + 1) *
+/// N / 8`, in little-endian order, so lane 0 is always the least significant
+/// part of the equivalent `u128`. This is independent of the host's
+/// endianness.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, CheckBytes)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 #[derive(RkyvSerialize, RkyvDeserialize, Archive)]
@@ -88,6 +95,61 @@ impl V128 {
     }
 }
 
+macro_rules! v128_lanes {
+    ($($from:ident $to:ident [$ty:ty; $lanes:literal])*) => {
+        impl V128 {
+            $(
+                #[doc = concat!("Create a `V128` from ", stringify!($lanes), " `", stringify!($ty), "` lanes, with lane 0 first.")]
+                pub fn $from(lanes: [$ty; $lanes]) -> Self {
+                    const SIZE: usize = 16 / $lanes;
+                    let mut bytes = [0; 16];
+                    for (chunk, lane) in bytes.chunks_exact_mut(SIZE).zip(lanes) {
+                        chunk.copy_from_slice(&lane.to_le_bytes());
+                    }
+                    Self(bytes)
+                }
+
+                #[doc = concat!("Split the `V128` into ", stringify!($lanes), " `", stringify!($ty), "` lanes, with lane 0 first.")]
+                pub fn $to(&self) -> [$ty; $lanes] {
+                    const SIZE: usize = 16 / $lanes;
+                    let mut lanes = [<$ty>::default(); $lanes];
+                    for (lane, chunk) in lanes.iter_mut().zip(self.0.chunks_exact(SIZE)) {
+                        let mut buffer = [0; SIZE];
+                        buffer.copy_from_slice(chunk);
+                        *lane = <$ty>::from_le_bytes(buffer);
+                    }
+                    lanes
+                }
+            )*
+        }
+    };
+}
+
+v128_lanes! {
+    from_i8x16 to_i8x16 [i8; 16]
+    from_u8x16 to_u8x16 [u8; 16]
+    from_i16x8 to_i16x8 [i16; 8]
+    from_u16x8 to_u16x8 [u16; 8]
+    from_i32x4 to_i32x4 [i32; 4]
+    from_u32x4 to_u32x4 [u32; 4]
+    from_i64x2 to_i64x2 [i64; 2]
+    from_u64x2 to_u64x2 [u64; 2]
+    from_f32x4 to_f32x4 [f32; 4]
+    from_f64x2 to_f64x2 [f64; 2]
+}
+
+impl From<u128> for V128 {
+    fn from(value: u128) -> Self {
+        Self(value.to_le_bytes())
+    }
+}
+
+impl From<V128> for u128 {
+    fn from(value: V128) -> Self {
+        Self::from_le_bytes(value.0)
+    }
+}
+
 impl From<[u8; 16]> for V128 {
     fn from(array: [u8; 16]) -> Self {
         Self(array)
@@ -121,6 +183,8 @@ pub enum ExternType {
     Table(TableType),
     /// This external type is the type of a WebAssembly memory.
     Memory(MemoryType),
+    /// This external type is the type of a WebAssembly exception tag.
+    Tag(TagType),
 }
 
 fn is_global_compatible(exported: GlobalType, imported: GlobalType) -> bool {
@@ -175,11 +239,13 @@ fn is_memory_compatible(
         minimum: exported_minimum,
         maximum: exported_maximum,
         shared: exported_shared,
+        memory64: exported_memory64,
     } = exported;
     let MemoryType {
         minimum: imported_minimum,
         maximum: imported_maximum,
         shared: imported_shared,
+        memory64: imported_memory64,
     } = imported;
 
     imported_minimum.0 <= imported_runtime_size.unwrap_or(exported_minimum.0)
@@ -187,6 +253,7 @@ fn is_memory_compatible(
             || (!exported_maximum.is_none()
                 && imported_maximum.unwrap() >= exported_maximum.unwrap()))
         && exported_shared == imported_shared
+        && exported_memory64 == imported_memory64
 }
 
 macro_rules! accessors {
@@ -219,6 +286,7 @@ impl ExternType {
         (Global(GlobalType) global unwrap_global)
         (Table(TableType) table unwrap_table)
         (Memory(MemoryType) memory unwrap_memory)
+        (Tag(TagType) tag unwrap_tag)
     }
     /// Check if two externs are compatible
     pub fn is_compatible_with(&self, other: &Self, runtime_size: Option<u32>) -> bool {
@@ -227,6 +295,7 @@ impl ExternType {
             (Self::Global(a), Self::Global(b)) => is_global_compatible(*a, *b),
             (Self::Table(a), Self::Table(b)) => is_table_compatible(a, b, runtime_size),
             (Self::Memory(a), Self::Memory(b)) => is_memory_compatible(a, b, runtime_size),
+            (Self::Tag(a), Self::Tag(b)) => a == b,
             // The rest of possibilities, are not compatible
             _ => false,
         }
@@ -428,6 +497,10 @@ pub enum GlobalInit {
     V128Const(V128),
     /// A `global.get` of another global.
     GetGlobal(GlobalIndex),
+    /// A `global.get` of another integer global plus a constant, from the
+    /// extended constant expressions proposal. `i32` constants are
+    /// zero-extended.
+    GetGlobalOffset(GlobalIndex, i64),
     // TODO(reftypes): `ref.null func` and `ref.null extern` seem to be 2 different
     // things: we need to handle both. Perhaps this handled in context by the
     // global knowing its own type?
@@ -496,6 +569,10 @@ pub struct MemoryType {
     pub maximum: Option<Pages>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// Whether the memory is indexed with 64-bit addresses, as defined by
+    /// the memory64 proposal.
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub memory64: bool,
 }
 
 impl MemoryType {
@@ -509,6 +586,29 @@ impl MemoryType {
             minimum: minimum.into(),
             maximum: maximum.map(Into::into),
             shared,
+            memory64: false,
+        }
+    }
+
+    /// Creates a new descriptor for a 64-bit WebAssembly memory given the
+    /// specified limits of the memory.
+    pub fn new64<IntoPages>(minimum: IntoPages, maximum: Option<IntoPages>, shared: bool) -> Self
+    where
+        IntoPages: Into<Pages>,
+    {
+        Self {
+            memory64: true,
+            ..Self::new(minimum, maximum, shared)
+        }
+    }
+
+    /// The largest number of pages this memory can ever have, i.e. 4GiB
+    /// for 32-bit memories.
+    pub fn max_pages(&self) -> Pages {
+        if self.memory64 {
+            Pages::max_value_64()
+        } else {
+            Pages::max_value()
         }
     }
 }
@@ -516,12 +616,54 @@ impl MemoryType {
 impl fmt::Display for MemoryType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let shared = if self.shared { "shared" } else { "not shared" };
+        let index = if self.memory64 { "i64, " } else { "" };
         if let Some(maximum) = self.maximum {
-            write!(f, "{} ({:?}..{:?})", shared, self.minimum, maximum)
+            write!(f, "{}{} ({:?}..{:?})", index, shared, self.minimum, maximum)
         } else {
-            write!(f, "{} ({:?}..)", shared, self.minimum)
+            write!(f, "{}{} ({:?}..)", index, shared, self.minimum)
+        }
+    }
+}
+
+// Tag Types
+
+/// The type of a WebAssembly exception tag, from the exception handling
+/// proposal: the types of the values thrown with it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[derive(RkyvSerialize, RkyvDeserialize, Archive)]
+#[archive_attr(derive(CheckBytes))]
+pub struct TagType {
+    params: Box<[Type]>,
+}
+
+impl TagType {
+    /// Creates a new tag type for exceptions carrying values of `params`.
+    pub fn new<Params>(params: Params) -> Self
+    where
+        Params: Into<Box<[Type]>>,
+    {
+        Self {
+            params: params.into(),
         }
     }
+
+    /// The types of the values thrown with the tag.
+    pub fn params(&self) -> &[Type] {
+        &self.params
+    }
+}
+
+impl fmt::Display for TagType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "[{}]", params)
+    }
 }
 
 // Import Types
@@ -639,4 +781,20 @@ mod tests {
         assert_eq!(ty.params().len(), 9);
         assert_eq!(ty.results().len(), 9);
     }
+
+    #[test]
+    fn v128_lanes_are_little_endian() {
+        let v = V128::from_i32x4([1, 2, 3, -1]);
+
+        assert_eq!(
+            v.bytes(),
+            &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(u128::from(v), 0xffff_ffff_0000_0003_0000_0002_0000_0001);
+        assert_eq!(V128::from(u128::from(v)), v);
+        assert_eq!(v.to_i32x4(), [1, 2, 3, -1]);
+        assert_eq!(v.to_u64x2(), [0x0000_0002_0000_0001, 0xffff_ffff_0000_0003]);
+        assert_eq!(v.to_i8x16()[4], 2);
+        assert_eq!(V128::from_f64x2([1.5, -0.25]).to_f64x2(), [1.5, -0.25]);
+    }
 }
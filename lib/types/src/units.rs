@@ -16,6 +16,12 @@ pub const WASM_PAGE_SIZE: usize = 0x10000;
 /// The number of pages we can have before we run out of byte index space.
 pub const WASM_MAX_PAGES: u32 = 0x10000;
 
+/// The number of pages a 64-bit memory can have.
+///
+/// The memory64 proposal allows up to 2^48 pages, but 2^32 pages (256TiB)
+/// is already far more than any host can provide.
+pub const WASM64_MAX_PAGES: u32 = u32::MAX;
+
 /// The minimum number of pages allowed.
 pub const WASM_MIN_PAGES: u32 = 0x100;
 
@@ -46,6 +52,12 @@ impl Pages {
         Self(WASM_MAX_PAGES)
     }
 
+    /// Returns the largest number of pages a 64-bit memory can have.
+    #[inline(always)]
+    pub const fn max_value_64() -> Self {
+        Self(WASM64_MAX_PAGES)
+    }
+
     /// Checked addition. Computes `self + rhs`,
     /// returning `None` if overflow occurred.
     pub fn checked_add(self, rhs: Self) -> Option<Self> {
@@ -75,6 +87,14 @@ impl From<u32> for Pages {
     }
 }
 
+impl TryFrom<u64> for Pages {
+    type Error = PageCountOutOfRange;
+
+    fn try_from(other: u64) -> Result<Self, Self::Error> {
+        u32::try_from(other).map(Self).or(Err(PageCountOutOfRange))
+    }
+}
+
 /// Units of WebAssembly memory in terms of 8-bit bytes.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
@@ -190,4 +210,12 @@ mod tests {
         let result = Pages::try_from(Bytes(usize::MAX));
         assert_eq!(result.unwrap_err(), PageCountOutOfRange);
     }
+
+    #[test]
+    fn convert_u64_to_pages() {
+        assert_eq!(Pages::try_from(0u64).unwrap(), Pages(0));
+        assert_eq!(Pages::try_from(u32::MAX as u64).unwrap(), Pages(u32::MAX));
+        let result = Pages::try_from(u32::MAX as u64 + 1);
+        assert_eq!(result.unwrap_err(), PageCountOutOfRange);
+    }
 }
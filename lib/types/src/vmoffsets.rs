@@ -8,7 +8,7 @@
 
 use crate::{
     FunctionIndex, GlobalIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    ModuleInfo, SignatureIndex, TableIndex,
+    ModuleInfo, SignatureIndex, TableIndex, TagIndex,
 };
 use more_asserts::assert_lt;
 use std::convert::TryFrom;
@@ -139,9 +139,52 @@ impl VMBuiltinFunctionIndex {
     pub const fn get_imported_memory_atomic_notify_index() -> Self {
         Self(29)
     }
+    /// Returns an index for the builtin function lazily compiled functions
+    /// call to get their body.
+    pub const fn get_lazy_compile_index() -> Self {
+        Self(30)
+    }
+    /// Returns an index for wasm's `throw` instruction.
+    pub const fn get_throw_index() -> Self {
+        Self(31)
+    }
+    /// Returns an index for wasm's `rethrow` instruction.
+    pub const fn get_rethrow_index() -> Self {
+        Self(32)
+    }
+    /// Returns an index for the builtin function freeing a caught exception
+    /// when its `catch` clause is left.
+    pub const fn get_exception_drop_index() -> Self {
+        Self(33)
+    }
+    /// Returns an index for wasm's `memory.copy` for locally defined 64-bit
+    /// memories.
+    pub const fn get_memory64_copy_index() -> Self {
+        Self(34)
+    }
+    /// Returns an index for wasm's `memory.copy` for imported 64-bit
+    /// memories.
+    pub const fn get_imported_memory64_copy_index() -> Self {
+        Self(35)
+    }
+    /// Returns an index for wasm's `memory.fill` for locally defined 64-bit
+    /// memories.
+    pub const fn get_memory64_fill_index() -> Self {
+        Self(36)
+    }
+    /// Returns an index for wasm's `memory.fill` for imported 64-bit
+    /// memories.
+    pub const fn get_imported_memory64_fill_index() -> Self {
+        Self(37)
+    }
+    /// Returns an index for wasm's `memory.init` instruction on 64-bit
+    /// memories.
+    pub const fn get_memory64_init_index() -> Self {
+        Self(38)
+    }
     /// Returns the total number of builtin functions.
     pub const fn builtin_functions_total_number() -> u32 {
-        30
+        39
     }
 
     /// Return the index as an u32 number.
@@ -187,6 +230,8 @@ pub struct VMOffsets {
     num_local_memories: u32,
     /// The number of defined globals in the module.
     num_local_globals: u32,
+    /// The number of exception tags (imported and local) in the module.
+    num_tags: u32,
 
     vmctx_signature_ids_begin: u32,
     vmctx_imported_functions_begin: u32,
@@ -196,7 +241,9 @@ pub struct VMOffsets {
     vmctx_tables_begin: u32,
     vmctx_memories_begin: u32,
     vmctx_globals_begin: u32,
+    vmctx_tag_ids_begin: u32,
     vmctx_builtin_functions_begin: u32,
+    vmctx_pending_exception: u32,
     vmctx_trap_handler_begin: u32,
     vmctx_gas_limiter_pointer: u32,
     vmctx_stack_limit_begin: u32,
@@ -217,6 +264,7 @@ impl VMOffsets {
             num_local_tables: cast_to_u32(module.tables.len()),
             num_local_memories: cast_to_u32(module.memories.len()),
             num_local_globals: cast_to_u32(module.globals.len()),
+            num_tags: cast_to_u32(module.tags.len()),
             vmctx_signature_ids_begin: 0,
             vmctx_imported_functions_begin: 0,
             vmctx_imported_tables_begin: 0,
@@ -225,7 +273,9 @@ impl VMOffsets {
             vmctx_tables_begin: 0,
             vmctx_memories_begin: 0,
             vmctx_globals_begin: 0,
+            vmctx_tag_ids_begin: 0,
             vmctx_builtin_functions_begin: 0,
+            vmctx_pending_exception: 0,
             vmctx_trap_handler_begin: 0,
             vmctx_gas_limiter_pointer: 0,
             vmctx_stack_limit_begin: 0,
@@ -251,6 +301,7 @@ impl VMOffsets {
             num_local_tables: 0,
             num_local_memories: 0,
             num_local_globals: 0,
+            num_tags: 0,
             vmctx_signature_ids_begin: 0,
             vmctx_imported_functions_begin: 0,
             vmctx_imported_tables_begin: 0,
@@ -259,7 +310,9 @@ impl VMOffsets {
             vmctx_tables_begin: 0,
             vmctx_memories_begin: 0,
             vmctx_globals_begin: 0,
+            vmctx_tag_ids_begin: 0,
             vmctx_builtin_functions_begin: 0,
+            vmctx_pending_exception: 0,
             vmctx_trap_handler_begin: 0,
             vmctx_gas_limiter_pointer: 0,
             vmctx_stack_limit_begin: 0,
@@ -324,16 +377,29 @@ impl VMOffsets {
             ),
             16,
         );
-        self.vmctx_builtin_functions_begin = offset_by(
+        self.vmctx_tag_ids_begin = offset_by(
             self.vmctx_globals_begin,
             self.num_local_globals,
             u32::from(self.size_of_vmglobal_local()),
         );
-        self.vmctx_trap_handler_begin = offset_by(
+        self.vmctx_builtin_functions_begin = align(
+            offset_by(
+                self.vmctx_tag_ids_begin,
+                self.num_tags,
+                u32::from(self.size_of_vmtag_id()),
+            ),
+            u32::from(self.pointer_size),
+        );
+        self.vmctx_pending_exception = offset_by(
             self.vmctx_builtin_functions_begin,
             VMBuiltinFunctionIndex::builtin_functions_total_number(),
             u32::from(self.pointer_size),
         );
+        self.vmctx_trap_handler_begin = offset_by(
+            self.vmctx_pending_exception,
+            1,
+            u32::from(self.pointer_size),
+        );
         self.vmctx_gas_limiter_pointer = offset_by(
             self.vmctx_trap_handler_begin,
             1,
@@ -533,6 +599,32 @@ impl VMOffsets {
     }
 }
 
+/// Offsets for the ids of exception tags.
+impl VMOffsets {
+    /// Return the size of the store-unique id of a tag.
+    pub const fn size_of_vmtag_id(&self) -> u8 {
+        4
+    }
+}
+
+/// Offsets for `VMException`.
+impl VMOffsets {
+    /// The offset of the `tag` field, the id of the exception's tag.
+    pub const fn vmexception_tag(&self) -> u8 {
+        0
+    }
+
+    /// The offset of the `payload` field.
+    pub const fn vmexception_payload(&self) -> u8 {
+        8
+    }
+
+    /// Return the size of each value in the payload of a `VMException`.
+    pub const fn size_of_vmexception_value(&self) -> u8 {
+        16
+    }
+}
+
 /// Offsets for `VMCallerCheckedAnyfunc`.
 impl VMOffsets {
     /// The offset of the `func_ptr` field.
@@ -627,6 +719,12 @@ impl VMOffsets {
         self.vmctx_builtin_functions_begin
     }
 
+    /// The offset of the pointer to the store's pending exception, a
+    /// `*mut VMException` which is null when no exception is being thrown.
+    pub fn vmctx_pending_exception(&self) -> u32 {
+        self.vmctx_pending_exception
+    }
+
     /// Return the size of the `VMContext` allocation.
     pub fn size_of_vmctx(&self) -> u32 {
         self.size_of_vmctx
@@ -639,6 +737,12 @@ impl VMOffsets {
             + index.as_u32() * u32::from(self.size_of_vmshared_signature_index())
     }
 
+    /// Return the offset to the store-unique id of the tag `index`.
+    pub fn vmctx_vmtag_id(&self, index: TagIndex) -> u32 {
+        assert_lt!(index.as_u32(), self.num_tags);
+        self.vmctx_tag_ids_begin + index.as_u32() * u32::from(self.size_of_vmtag_id())
+    }
+
     /// Return the offset to `VMFunctionImport` index `index`.
     pub fn vmctx_vmfunction_import(&self, index: FunctionIndex) -> u32 {
         assert_lt!(index.as_u32(), self.num_imported_functions);
@@ -25,11 +25,13 @@ pub mod dual_write_file;
 pub mod empty_fs;
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
+pub mod limited_write_file;
 pub mod mem_fs;
 pub mod null_file;
 pub mod passthru_fs;
 pub mod random_file;
 pub mod special_file;
+pub mod tee_file;
 pub mod tmp_fs;
 pub mod union_fs;
 pub mod zero_file;
@@ -58,11 +60,13 @@ pub use cow_file::*;
 pub use dual_write_file::*;
 pub use empty_fs::*;
 pub use filesystems::FileSystems;
+pub use limited_write_file::*;
 pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
 pub use pipe::*;
 pub use special_file::*;
+pub use tee_file::*;
 pub use tmp_fs::*;
 pub use trace_fs::TraceFileSystem;
 pub use union_fs::*;
@@ -0,0 +1,141 @@
+use super::*;
+
+use crate::VirtualFile;
+
+/// What a [`LimitedWriteFile`] does with writes once its limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteLimitMode {
+    /// Fail the writes with [`io::ErrorKind::WriteZero`], like a full disk.
+    Error,
+    /// Pretend the writes succeeded, discarding the bytes.
+    Truncate,
+}
+
+/// Wraps a [`VirtualFile`], only passing the first `limit` bytes written to
+/// it through.
+///
+/// Useful for stopping runaway programs from flooding stdout.
+#[derive(Debug)]
+pub struct LimitedWriteFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    remaining: u64,
+    mode: WriteLimitMode,
+}
+
+impl LimitedWriteFile {
+    pub fn new(
+        inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+        limit: u64,
+        mode: WriteLimitMode,
+    ) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            mode,
+        }
+    }
+}
+
+impl VirtualFile for LimitedWriteFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> BoxFuture<'static, Result<()>> {
+        let fut = self.inner.unlink();
+        Box::pin(async { fut.await })
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for LimitedWriteFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        if buf.is_empty() {
+            return Pin::new(&mut self.inner).poll_write(cx, buf);
+        }
+        if self.remaining == 0 {
+            return match self.mode {
+                WriteLimitMode::Error => Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "the output limit has been reached",
+                ))),
+                WriteLimitMode::Truncate => Poll::Ready(Ok(buf.len())),
+            };
+        }
+
+        let allowed = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        match Pin::new(&mut self.inner).poll_write(cx, &buf[..allowed]) {
+            Poll::Ready(Ok(amt)) => {
+                self.remaining -= amt as u64;
+                // Everything past the limit is thrown away, so the caller
+                // doesn't need to try writing it again
+                if self.mode == WriteLimitMode::Truncate && amt == allowed {
+                    Poll::Ready(Ok(buf.len()))
+                } else {
+                    Poll::Ready(Ok(amt))
+                }
+            }
+            res => res,
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for LimitedWriteFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for LimitedWriteFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
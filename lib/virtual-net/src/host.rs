@@ -19,15 +19,74 @@ use tracing::{debug, error, info, trace, warn};
 
 #[derive(Debug)]
 pub struct LocalNetworking {
-    // Make struct internals private.
-    // Can be removed once some fields are added (like permissions).
-    _private: (),
+    /// The host network interface outbound sockets are bound to, if any.
+    interface: Option<String>,
 }
 
 impl LocalNetworking {
     pub fn new() -> Self {
-        Self { _private: () }
+        Self { interface: None }
+    }
+
+    /// Create a [`LocalNetworking`] whose outbound TCP connections and UDP
+    /// sockets only use the host network interface called `name` (e.g.
+    /// `eth1`), instead of following the default route.
+    ///
+    /// This uses `SO_BINDTODEVICE`, so it's only supported on Linux.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`std::io::ErrorKind::NotFound`] if the host doesn't have
+    /// an interface called `name`, listing the ones it does have.
+    pub fn with_interface(name: &str) -> std::io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let available = interface_names()?;
+            if !available.iter().any(|interface| interface == name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "there is no network interface called \"{name}\" (available interfaces: {})",
+                        available.join(", ")
+                    ),
+                ));
+            }
+            Ok(Self {
+                interface: Some(name.to_string()),
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "binding to a network interface is only supported on Linux",
+        ))
+    }
+}
+
+/// The names of the host's network interfaces, as reported by
+/// `getifaddrs()`.
+#[cfg(target_os = "linux")]
+fn interface_names() -> std::io::Result<Vec<String>> {
+    let mut addrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // An interface is listed once per address family
+    let mut names = std::collections::BTreeSet::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        let ifaddr = unsafe { &*cursor };
+        if !ifaddr.ifa_name.is_null() {
+            let name = unsafe { std::ffi::CStr::from_ptr(ifaddr.ifa_name) };
+            names.insert(name.to_string_lossy().into_owned());
+        }
+        cursor = ifaddr.ifa_next;
     }
+    unsafe { libc::freeifaddrs(addrs) };
+
+    Ok(names.into_iter().collect())
 }
 
 impl Default for LocalNetworking {
@@ -67,6 +126,13 @@ impl VirtualNetworking for LocalNetworking {
         let socket = tokio::net::UdpSocket::bind(addr)
             .await
             .map_err(io_err_into_net_error)?;
+        // `with_interface()` only succeeds on Linux
+        #[cfg(target_os = "linux")]
+        if let Some(interface) = &self.interface {
+            socket
+                .bind_device(Some(interface.as_bytes()))
+                .map_err(io_err_into_net_error)?;
+        }
         Ok(Box::new(LocalUdpSocket {
             socket,
             addr,
@@ -79,9 +145,23 @@ impl VirtualNetworking for LocalNetworking {
         _addr: SocketAddr,
         peer: SocketAddr,
     ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
-        let stream = tokio::net::TcpStream::connect(peer)
-            .await
-            .map_err(io_err_into_net_error)?;
+        let stream = match &self.interface {
+            None => tokio::net::TcpStream::connect(peer).await,
+            Some(interface) => {
+                let socket = match peer {
+                    SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4(),
+                    SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6(),
+                }
+                .map_err(io_err_into_net_error)?;
+                // `with_interface()` only succeeds on Linux
+                #[cfg(target_os = "linux")]
+                socket
+                    .bind_device(Some(interface.as_bytes()))
+                    .map_err(io_err_into_net_error)?;
+                socket.connect(peer).await
+            }
+        }
+        .map_err(io_err_into_net_error)?;
         let peer = stream.peer_addr().map_err(io_err_into_net_error)?;
         Ok(Box::new(LocalTcpStream::new(stream, peer)))
     }
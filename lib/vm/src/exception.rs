@@ -0,0 +1,175 @@
+//! Exception tags and the exceptions thrown with them, from the exception
+//! handling proposal.
+//!
+//! Compiled code doesn't unwind to throw an exception: `throw` stores it as
+//! the store's pending exception and returns, and every caller checks for a
+//! pending exception after each call, branching to its innermost handler or
+//! returning in turn. Traps keep unwinding the native stack, so guest
+//! handlers never see them.
+
+use crate::store::InternalStoreHandle;
+use std::cell::Cell;
+use std::fmt;
+use std::ptr;
+use wasmer_types::{RawValue, TagType};
+
+/// An exception tag, which exceptions are thrown and caught with.
+#[derive(Debug)]
+pub struct VMTag {
+    ty: TagType,
+}
+
+impl VMTag {
+    /// Creates a new tag for exceptions carrying values of `ty`.
+    pub fn new(ty: TagType) -> Self {
+        Self { ty }
+    }
+
+    /// The type of the tag.
+    pub fn ty(&self) -> &TagType {
+        &self.ty
+    }
+}
+
+/// A thrown exception: its tag and the values thrown with it.
+///
+/// Compiled code reads the tag and the payload directly, see
+/// `VMOffsets::vmexception_tag`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct VMException {
+    /// The store-unique id of the tag, the index of its handle.
+    tag: u32,
+    payload_len: u32,
+    payload: *mut RawValue,
+}
+
+impl VMException {
+    /// Creates an exception thrown with `tag` and `payload`.
+    pub fn new(tag: InternalStoreHandle<VMTag>, payload: Vec<RawValue>) -> Box<Self> {
+        let payload_len = u32::try_from(payload.len()).unwrap();
+        let payload = Box::into_raw(payload.into_boxed_slice());
+        Box::new(Self {
+            tag: u32::try_from(tag.index()).unwrap(),
+            payload_len,
+            payload: payload as *mut RawValue,
+        })
+    }
+
+    /// The tag the exception was thrown with.
+    pub fn tag(&self) -> InternalStoreHandle<VMTag> {
+        InternalStoreHandle::from_index(self.tag as usize).unwrap()
+    }
+
+    /// The values thrown with the exception.
+    pub fn payload(&self) -> &[RawValue] {
+        unsafe { std::slice::from_raw_parts(self.payload, self.payload_len as usize) }
+    }
+}
+
+impl Clone for VMException {
+    fn clone(&self) -> Self {
+        *Self::new(self.tag(), self.payload().to_vec())
+    }
+}
+
+impl Drop for VMException {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                self.payload,
+                self.payload_len as usize,
+            )));
+        }
+    }
+}
+
+// The payload is owned by the exception.
+unsafe impl Send for VMException {}
+unsafe impl Sync for VMException {}
+
+/// The exception being thrown on a store, if any.
+///
+/// Each instance's `VMContext` points to it, so compiled code can check it
+/// after calls. It is boxed so that pointer stays valid as the store moves.
+pub struct PendingException(Box<Cell<*mut VMException>>);
+
+impl PendingException {
+    /// The address compiled code loads the pending exception from.
+    pub fn as_ptr(&self) -> *mut *mut VMException {
+        self.0.as_ptr()
+    }
+
+    /// Whether an exception is being thrown.
+    pub fn is_pending(&self) -> bool {
+        !self.0.get().is_null()
+    }
+
+    /// Takes the exception being thrown, if any.
+    pub fn take(&self) -> Option<Box<VMException>> {
+        let exception = self.0.replace(ptr::null_mut());
+        if exception.is_null() {
+            None
+        } else {
+            Some(unsafe { Box::from_raw(exception) })
+        }
+    }
+
+    /// Starts throwing `exception`, replacing any exception being thrown.
+    pub fn set(&self, exception: Box<VMException>) {
+        drop(self.take());
+        self.0.set(Box::into_raw(exception));
+    }
+}
+
+impl Default for PendingException {
+    fn default() -> Self {
+        Self(Box::new(Cell::new(ptr::null_mut())))
+    }
+}
+
+impl Drop for PendingException {
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+impl fmt::Debug for PendingException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingException")
+            .field("is_pending", &self.is_pending())
+            .finish()
+    }
+}
+
+// Only accessed through the store it belongs to, which isn't shared between
+// threads while code runs on it.
+unsafe impl Send for PendingException {}
+unsafe impl Sync for PendingException {}
+
+#[cfg(test)]
+mod test_vmexception {
+    use super::VMException;
+    use crate::VMOffsets;
+    use memoffset::offset_of;
+    use std::mem::size_of;
+    use wasmer_types::{ModuleInfo, RawValue};
+
+    #[test]
+    fn check_vmexception_offsets() {
+        let module = ModuleInfo::new();
+        let offsets = VMOffsets::new(size_of::<*mut u8>() as u8, &module);
+        assert_eq!(
+            offset_of!(VMException, tag),
+            usize::from(offsets.vmexception_tag())
+        );
+        assert_eq!(
+            offset_of!(VMException, payload),
+            usize::from(offsets.vmexception_payload())
+        );
+        assert_eq!(
+            size_of::<RawValue>(),
+            usize::from(offsets.size_of_vmexception_value())
+        );
+    }
+}
@@ -1,29 +1,102 @@
 use derivative::Derivative;
-use std::any::Any;
+use std::{
+    any::Any,
+    sync::{Arc, Weak},
+};
 use wasmer_types::RawValue;
 
-use crate::store::InternalStoreHandle;
+use crate::store::{InternalStoreHandle, StoreHandle};
 
 /// Underlying object referenced by a `VMExternRef`.
+///
+/// The contents stay alive until the object is collected by
+/// [`StoreObjects::gc_extern_objs()`][crate::StoreObjects::gc_extern_objs] or
+/// the store is dropped, at which point the drop observer (if any) is
+/// triggered.
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct VMExternObj {
     #[derivative(Debug = "ignore")]
-    contents: Box<dyn Any + Send + Sync + 'static>,
+    contents: Option<Box<dyn Any + Send + Sync + 'static>>,
+    /// The handle shared by every host-side reference to this object, which
+    /// lets us know whether the host still holds one.
+    #[derivative(Debug = "ignore")]
+    host_handle: Weak<StoreHandle<VMExternObj>>,
+    #[derivative(Debug = "ignore")]
+    drop_observer: Option<Box<dyn FnOnce() + Send + Sync + 'static>>,
 }
 
 impl VMExternObj {
     /// Wraps the given value to expose it to Wasm code as an externref.
     pub fn new(val: impl Any + Send + Sync + 'static) -> Self {
         Self {
-            contents: Box::new(val),
+            contents: Some(Box::new(val)),
+            host_handle: Weak::new(),
+            drop_observer: None,
         }
     }
 
     #[allow(clippy::should_implement_trait)]
     /// Returns a reference to the underlying value.
+    ///
+    /// Once the object has been collected, this returns a reference to `()`.
     pub fn as_ref(&self) -> &(dyn Any + Send + Sync + 'static) {
-        &*self.contents
+        match &self.contents {
+            Some(contents) => &**contents,
+            None => &(),
+        }
+    }
+
+    /// Get the handle shared by the host's references to this object, which
+    /// keeps it alive for as long as the host holds it. `handle` must point
+    /// to this object, and is used if the host doesn't hold any yet.
+    pub fn host_handle(&mut self, handle: StoreHandle<Self>) -> Arc<StoreHandle<Self>> {
+        if let Some(host_handle) = self.host_handle.upgrade() {
+            return host_handle;
+        }
+        let host_handle = Arc::new(handle);
+        self.host_handle = Arc::downgrade(&host_handle);
+        host_handle
+    }
+
+    /// Does the host hold any references to this object?
+    pub fn is_referenced_by_host(&self) -> bool {
+        self.host_handle.strong_count() > 0
+    }
+
+    /// Has this object's contents already been dropped?
+    pub fn is_collected(&self) -> bool {
+        self.contents.is_none()
+    }
+
+    /// Register a callback that will be invoked once this object's contents
+    /// are dropped, replacing any previous observer.
+    ///
+    /// If the object has already been collected, the callback is invoked
+    /// immediately.
+    pub fn set_drop_observer(&mut self, observer: impl FnOnce() + Send + Sync + 'static) {
+        if self.is_collected() {
+            observer();
+        } else {
+            self.drop_observer = Some(Box::new(observer));
+        }
+    }
+
+    /// Drop the contents of this object and notify the drop observer.
+    pub(crate) fn collect(&mut self) {
+        if let Some(contents) = self.contents.take() {
+            drop(contents);
+
+            if let Some(observer) = self.drop_observer.take() {
+                observer();
+            }
+        }
+    }
+}
+
+impl Drop for VMExternObj {
+    fn drop(&mut self) {
+        self.collect();
     }
 }
 
@@ -1,9 +1,11 @@
 // This file contains code from external sources.
 // Attributions: https://github.com/wasmerio/wasmer/blob/master/ATTRIBUTIONS.md
 
+use crate::exception::VMTag;
+use crate::store::InternalStoreHandle;
 use crate::vmcontext::{VMFunctionImport, VMGlobalImport, VMMemoryImport, VMTableImport};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
-use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex};
+use wasmer_types::{FunctionIndex, GlobalIndex, MemoryIndex, TableIndex, TagIndex};
 
 /// Resolved import pointers.
 #[derive(Clone)]
@@ -19,6 +21,9 @@ pub struct Imports {
 
     /// Resolved addresses for imported globals.
     pub globals: BoxedSlice<GlobalIndex, VMGlobalImport>,
+
+    /// Resolved imported exception tags.
+    pub tags: BoxedSlice<TagIndex, InternalStoreHandle<VMTag>>,
 }
 
 impl Imports {
@@ -28,12 +33,14 @@ impl Imports {
         table_imports: PrimaryMap<TableIndex, VMTableImport>,
         memory_imports: PrimaryMap<MemoryIndex, VMMemoryImport>,
         global_imports: PrimaryMap<GlobalIndex, VMGlobalImport>,
+        tag_imports: PrimaryMap<TagIndex, InternalStoreHandle<VMTag>>,
     ) -> Self {
         Self {
             functions: function_imports.into_boxed_slice(),
             tables: table_imports.into_boxed_slice(),
             memories: memory_imports.into_boxed_slice(),
             globals: global_imports.into_boxed_slice(),
+            tags: tag_imports.into_boxed_slice(),
         }
     }
 
@@ -44,6 +51,7 @@ impl Imports {
             tables: PrimaryMap::new().into_boxed_slice(),
             memories: PrimaryMap::new().into_boxed_slice(),
             globals: PrimaryMap::new().into_boxed_slice(),
+            tags: PrimaryMap::new().into_boxed_slice(),
         }
     }
 }
@@ -8,25 +8,28 @@
 
 mod allocator;
 
+use crate::exception::{VMException, VMTag};
 use crate::export::VMExtern;
 use crate::imports::Imports;
 use crate::store::{InternalStoreHandle, StoreObjects};
 use crate::table::TableElement;
-use crate::trap::{catch_traps, Trap, TrapCode};
+use crate::trap::{catch_traps, on_host_stack, Trap, TrapCode};
 use crate::vmcontext::{
     memory32_atomic_check32, memory32_atomic_check64, memory_copy, memory_fill,
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionContext,
     VMFunctionImport, VMFunctionKind, VMGlobalDefinition, VMGlobalImport, VMMemoryDefinition,
     VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
-use crate::{FunctionBodyPtr, MaybeInstanceOwned, TrapHandlerFn, VMFunctionBody};
+use crate::{
+    FunctionBodyPtr, LazyFunctionCompiler, MaybeInstanceOwned, TrapHandlerFn, VMFunctionBody,
+};
 use crate::{LinearMemory, NotifyLocation};
 use crate::{VMConfig, VMFuncRef, VMFunction, VMGlobal, VMMemory, VMTable};
 pub use allocator::InstanceAllocator;
 use memoffset::offset_of;
 use more_asserts::assert_lt;
 use std::alloc::Layout;
-use std::cell::RefCell;
+use std::cell::{RefCell, UnsafeCell};
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
@@ -36,9 +39,10 @@ use std::slice;
 use std::sync::Arc;
 use wasmer_types::entity::{packed_option::ReservedValue, BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{
-    DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, GlobalInit,
-    LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryError,
-    MemoryIndex, ModuleInfo, Pages, SignatureIndex, TableIndex, TableInitializer, VMOffsets,
+    CompileError, DataIndex, DataInitializer, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex,
+    GlobalInit, LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryError, MemoryIndex, ModuleInfo, Pages, RawValue, SignatureIndex, TableIndex,
+    TableInitializer, TagIndex, Type, VMOffsets,
 };
 
 /// A WebAssembly instance.
@@ -68,6 +72,9 @@ pub(crate) struct Instance {
     /// WebAssembly global data.
     globals: BoxedSlice<LocalGlobalIndex, InternalStoreHandle<VMGlobal>>,
 
+    /// WebAssembly exception tags, the imported ones first.
+    tags: BoxedSlice<TagIndex, InternalStoreHandle<VMTag>>,
+
     /// Pointers to functions in executable memory.
     functions: BoxedSlice<LocalFunctionIndex, FunctionBodyPtr>,
 
@@ -84,12 +91,20 @@ pub(crate) struct Instance {
 
     /// Mapping of function indices to their func ref backing data. `VMFuncRef`s
     /// will point to elements here for functions defined by this instance.
-    funcrefs: BoxedSlice<LocalFunctionIndex, VMCallerCheckedAnyfunc>,
+    ///
+    /// They're in `UnsafeCell`s because lazily compiled functions point
+    /// theirs at their body once it's compiled, from Wasm code that only has
+    /// a shared borrow of the instance.
+    funcrefs: BoxedSlice<LocalFunctionIndex, UnsafeCell<VMCallerCheckedAnyfunc>>,
 
     /// Mapping of function indices to their func ref backing data. `VMFuncRef`s
     /// will point to elements here for functions imported by this instance.
     imported_funcrefs: BoxedSlice<FunctionIndex, NonNull<VMCallerCheckedAnyfunc>>,
 
+    /// Compiles the local functions on their first call, if the module was
+    /// compiled lazily.
+    lazy_compiler: Option<Arc<dyn LazyFunctionCompiler>>,
+
     /// Additional context used by compiled WebAssembly code. This
     /// field is last, and represents a dynamically-sized array that
     /// extends beyond the nominal end of the struct (similar to a
@@ -97,6 +112,13 @@ pub(crate) struct Instance {
     vmctx: VMContext,
 }
 
+/// The passive segments of an [`Instance`] at some point in time.
+#[derive(Clone)]
+pub(crate) struct PassiveSegments {
+    elements: HashMap<ElemIndex, Box<[Option<VMFuncRef>]>>,
+    data: HashMap<DataIndex, Arc<[u8]>>,
+}
+
 impl fmt::Debug for Instance {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.debug_struct("Instance").finish()
@@ -308,6 +330,16 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_globals_begin()) }
     }
 
+    /// Return a pointer to the store-unique id of the tag `index`.
+    fn tag_id_ptr(&self, index: TagIndex) -> *mut u32 {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_vmtag_id(index)) }
+    }
+
+    /// Return a pointer to the pointer to the store's pending exception.
+    fn pending_exception_ptr(&self) -> *mut *mut *mut VMException {
+        unsafe { self.vmctx_plus_offset(self.offsets.vmctx_pending_exception()) }
+    }
+
     /// Return a pointer to the `VMBuiltinFunctionsArray`.
     fn builtin_functions_ptr(&self) -> *mut VMBuiltinFunctionsArray {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_builtin_functions_begin()) }
@@ -411,7 +443,7 @@ impl Instance {
             .memories
             .get(memory_index)
             .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Grow imported memory by the specified amount of pages.
@@ -432,7 +464,7 @@ impl Instance {
     {
         let import = self.imported_memory(memory_index);
         let mem = import.handle;
-        mem.get_mut(self.context_mut()).grow(delta.into())
+        self.context_mut().grow_memory(mem, delta.into())
     }
 
     /// Returns the number of allocated wasm pages.
@@ -567,14 +599,42 @@ impl Instance {
         if function_index == FunctionIndex::reserved_value() {
             None
         } else if let Some(local_function_index) = self.module.local_func_index(function_index) {
-            Some(VMFuncRef(NonNull::from(
-                &self.funcrefs[local_function_index],
-            )))
+            Some(VMFuncRef(
+                NonNull::new(self.funcrefs[local_function_index].get()).unwrap(),
+            ))
         } else {
             Some(VMFuncRef(self.imported_funcrefs[function_index]))
         }
     }
 
+    /// Get the body of a lazily compiled function, compiling it if this is
+    /// the first time it has been called.
+    pub(crate) fn lazy_compile(
+        &self,
+        index: LocalFunctionIndex,
+    ) -> Result<*const VMFunctionBody, CompileError> {
+        let compiler = self
+            .lazy_compiler
+            .as_ref()
+            .expect("the module wasn't compiled lazily");
+
+        let body = match compiler.compiled(index) {
+            Some(body) => body,
+            // Compiling needs a lot more stack than the Wasm stack might
+            // have left
+            None => on_host_stack(|| compiler.compile(index))?,
+        };
+
+        // Calls through tables and exports can go straight to the body from
+        // now on. The funcref is only ever accessed through raw pointers, so
+        // this doesn't alias any reference.
+        unsafe {
+            (*self.funcrefs[index].get()).func_ptr = body.0;
+        }
+
+        Ok(body.0)
+    }
+
     /// The `table.init` operation: initializes a portion of a table with a
     /// passive element.
     ///
@@ -608,14 +668,46 @@ impl Instance {
         }
 
         for (dst, src) in (dst..dst + len).zip(src..src + len) {
+            let item = segment_element(table, elem[src as usize]);
             table
-                .set(dst, TableElement::FuncRef(elem[src as usize]))
+                .set(dst, item)
                 .expect("should never panic because we already did the bounds check above");
         }
 
         Ok(())
     }
 
+    /// The `table.copy` operation: copies `len` elements of a table to
+    /// another one, or within a table.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Trap` error when either range is out of bounds, or the
+    /// tables have different element types.
+    pub(crate) fn table_copy(
+        &mut self,
+        dst_table_index: TableIndex,
+        src_table_index: TableIndex,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> Result<(), Trap> {
+        // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-table-copy
+
+        // Two indices can refer to the same table, when it's imported twice
+        // or both imported and defined by the module
+        let dst_table = self.get_table_handle(dst_table_index);
+        let src_table = self.get_table_handle(src_table_index);
+        if dst_table == src_table {
+            dst_table
+                .get_mut(self.context_mut())
+                .copy_within(dst, src, len)
+        } else {
+            let (dst_table, src_table) = self.context_mut().get_2_mut(dst_table, src_table);
+            dst_table.copy(src_table, dst, src, len)
+        }
+    }
+
     /// The `table.fill` operation: fills a portion of a table with a given value.
     ///
     /// # Errors
@@ -668,9 +760,9 @@ impl Instance {
     pub(crate) fn local_memory_copy(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
 
@@ -683,9 +775,9 @@ impl Instance {
     pub(crate) fn imported_memory_copy(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
-        src: u32,
-        len: u32,
+        dst: u64,
+        src: u64,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         let memory = unsafe { import.definition.as_ref() };
@@ -701,9 +793,9 @@ impl Instance {
     pub(crate) fn local_memory_fill(
         &self,
         memory_index: LocalMemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let memory = self.memory(memory_index);
         // The following memory fill is not synchronized and is not atomic:
@@ -718,9 +810,9 @@ impl Instance {
     pub(crate) fn imported_memory_fill(
         &self,
         memory_index: MemoryIndex,
-        dst: u32,
+        dst: u64,
         val: u32,
-        len: u32,
+        len: u64,
     ) -> Result<(), Trap> {
         let import = self.imported_memory(memory_index);
         let memory = unsafe { import.definition.as_ref() };
@@ -739,7 +831,7 @@ impl Instance {
         &self,
         memory_index: MemoryIndex,
         data_index: DataIndex,
-        dst: u32,
+        dst: u64,
         src: u32,
         len: u32,
     ) -> Result<(), Trap> {
@@ -754,13 +846,13 @@ impl Instance {
             .checked_add(len)
             .map_or(true, |n| n as usize > data.len())
             || dst
-                .checked_add(len)
-                .map_or(true, |m| usize::try_from(m).unwrap() > current_length)
+                .checked_add(u64::from(len))
+                .map_or(true, |m| m > current_length as u64)
         {
             return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
         }
         let src_slice = &data[src as usize..(src + len) as usize];
-        unsafe { memory.initialize_with_data(dst as usize, src_slice) }
+        unsafe { memory.initialize_with_data(usize::try_from(dst).unwrap(), src_slice) }
     }
 
     /// Drop the given data segment, truncating its length to zero.
@@ -769,6 +861,46 @@ impl Instance {
         passive_data.remove(&data_index);
     }
 
+    /// Start throwing an exception with the tag `tag_index` and the values
+    /// at `values`, as many as the tag's parameters.
+    ///
+    /// # Safety
+    ///
+    /// `values` must point to a value for each parameter of the tag. It may
+    /// be null if the tag has none.
+    pub(crate) unsafe fn throw(&self, tag_index: TagIndex, values: *const RawValue) {
+        let signature = &self.module.signatures[self.module.tags[tag_index]];
+        let payload = match signature.params().len() {
+            0 => Vec::new(),
+            len => slice::from_raw_parts(values, len).to_vec(),
+        };
+        let exception = VMException::new(self.tags[tag_index], payload);
+        self.context().pending_exception().set(exception);
+    }
+
+    /// Start throwing `exception` again, from a `rethrow` in the `catch`
+    /// clause which caught it.
+    pub(crate) fn rethrow(&self, exception: &VMException) {
+        let exception = Box::new(exception.clone());
+        self.context().pending_exception().set(exception);
+    }
+
+    /// The passive element and data segments which haven't been dropped yet.
+    pub(crate) fn passive_segments(&self) -> PassiveSegments {
+        PassiveSegments {
+            elements: self.passive_elements.borrow().clone(),
+            data: self.passive_data.borrow().clone(),
+        }
+    }
+
+    /// Bring back the passive segments returned by
+    /// [`Instance::passive_segments`], undoing any `elem.drop`s and
+    /// `data.drop`s since.
+    pub(crate) fn restore_passive_segments(&self, segments: &PassiveSegments) {
+        *self.passive_elements.borrow_mut() = segments.elements.clone();
+        *self.passive_data.borrow_mut() = segments.data.clone();
+    }
+
     /// Get a table by index regardless of whether it is locally-defined or an
     /// imported, foreign table.
     pub(crate) fn get_table(&mut self, table_index: TableIndex) -> &mut VMTable {
@@ -1037,6 +1169,11 @@ impl VMInstance {
                 .map(|(idx, bytes)| (idx, Arc::from(bytes)))
                 .collect::<HashMap<_, _>>(),
         );
+        let mut tags = imports.tags.values().copied().collect::<PrimaryMap<_, _>>();
+        for index in module.tags.keys().skip(module.num_imported_tags) {
+            let tag = VMTag::new(module.tag_type(index));
+            tags.push(InternalStoreHandle::new(context, tag));
+        }
 
         let handle = {
             let offsets = allocator.offsets().clone();
@@ -1051,12 +1188,14 @@ impl VMInstance {
                 memories: finished_memories,
                 tables: finished_tables,
                 globals: finished_globals,
+                tags: tags.into_boxed_slice(),
                 functions: finished_functions,
                 function_call_trampolines: finished_function_call_trampolines,
                 passive_elements: Default::default(),
                 passive_data,
                 funcrefs,
                 imported_funcrefs,
+                lazy_compiler: None,
                 vmctx: VMContext {},
             };
 
@@ -1114,10 +1253,17 @@ impl VMInstance {
             instance.globals_ptr() as *mut NonNull<VMGlobalDefinition>,
             vmctx_globals.len(),
         );
+        for (index, tag) in instance.tags.iter() {
+            ptr::write(instance.tag_id_ptr(index), tag.index() as u32);
+        }
         ptr::write(
             instance.builtin_functions_ptr() as *mut VMBuiltinFunctionsArray,
             VMBuiltinFunctionsArray::initialized(),
         );
+        ptr::write(
+            instance.pending_exception_ptr(),
+            instance.context().pending_exception().as_ptr(),
+        );
 
         // Perform infallible initialization in this constructor, while fallible
         // initialization is deferred to the `initialize` method.
@@ -1137,6 +1283,15 @@ impl VMInstance {
         unsafe { self.instance.as_mut() }
     }
 
+    /// Use `compiler` to compile functions the first time they're called.
+    ///
+    /// This must be done before [`VMInstance::finish_instantiation`] when
+    /// the module was compiled lazily, because its functions are only stubs
+    /// until then.
+    pub fn set_lazy_compiler(&mut self, compiler: Arc<dyn LazyFunctionCompiler>) {
+        self.instance_mut().lazy_compiler = Some(compiler);
+    }
+
     /// Finishes the instantiation process started by `Instance::new`.
     ///
     /// # Safety
@@ -1206,9 +1361,9 @@ impl VMInstance {
                     // exported.
                     let signature = instance.module.signatures[*sig_index].clone();
                     let vm_function = VMFunction {
-                        anyfunc: MaybeInstanceOwned::Instance(NonNull::from(
-                            &instance.funcrefs[def_index],
-                        )),
+                        anyfunc: MaybeInstanceOwned::Instance(
+                            NonNull::new(instance.funcrefs[def_index].get()).unwrap(),
+                        ),
                         signature,
                         // Any function received is already static at this point as:
                         // 1. All locally defined functions in the Wasm have a static signature.
@@ -1252,6 +1407,7 @@ impl VMInstance {
                 };
                 VMExtern::Global(handle)
             }
+            ExportIndex::Tag(index) => VMExtern::Tag(instance.tags[index]),
         }
     }
 
@@ -1333,14 +1489,14 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
     let mut start = init.location.offset;
 
     if let Some(base) = init.location.base {
-        let val = unsafe {
-            if let Some(def_index) = instance.module.local_global_index(base) {
-                instance.global(def_index).val.u32
-            } else {
-                instance.imported_global(base).definition.as_ref().val.u32
-            }
-        };
-        start += usize::try_from(val).unwrap();
+        let val = unsafe { global_definition(instance, base).val };
+        // 64-bit memories are offset by an `i64` global
+        if instance.module.memories[init.location.memory_index].memory64 {
+            start += usize::try_from(unsafe { val.u64 }).unwrap();
+        } else {
+            // The offset is an `i32` expression, which wraps around
+            start = (start as u32).wrapping_add(unsafe { val.u32 }) as usize;
+        }
     }
 
     start
@@ -1370,14 +1526,9 @@ fn get_table_init_start(init: &TableInitializer, instance: &Instance) -> usize {
     let mut start = init.offset;
 
     if let Some(base) = init.base {
-        let val = unsafe {
-            if let Some(def_index) = instance.module.local_global_index(base) {
-                instance.global(def_index).val.u32
-            } else {
-                instance.imported_global(base).definition.as_ref().val.u32
-            }
-        };
-        start += usize::try_from(val).unwrap();
+        let val = unsafe { global_definition(instance, base).val.u32 };
+        // The offset is an `i32` expression, which wraps around
+        start = (start as u32).wrapping_add(val) as usize;
     }
 
     start
@@ -1399,19 +1550,28 @@ fn initialize_tables(instance: &mut Instance) -> Result<(), Trap> {
         }
 
         for (i, func_idx) in init.elements.iter().enumerate() {
-            let anyfunc = instance.func_ref(*func_idx);
-            table
-                .set(
-                    u32::try_from(start + i).unwrap(),
-                    TableElement::FuncRef(anyfunc),
-                )
-                .unwrap();
+            let item = segment_element(table, instance.func_ref(*func_idx));
+            table.set(u32::try_from(start + i).unwrap(), item).unwrap();
         }
     }
 
     Ok(())
 }
 
+/// The table element for an entry of an element segment written to `table`.
+///
+/// Segments of externrefs can only hold null references, which are resolved
+/// to `None` like null funcrefs.
+fn segment_element(table: &VMTable, func_ref: Option<VMFuncRef>) -> TableElement {
+    match table.ty().ty {
+        Type::ExternRef => {
+            debug_assert!(func_ref.is_none());
+            TableElement::ExternRef(None)
+        }
+        _ => TableElement::FuncRef(func_ref),
+    }
+}
+
 /// Initialize the `Instance::passive_elements` map by resolving the
 /// `ModuleInfo::passive_elements`'s `FunctionIndex`s into `VMCallerCheckedAnyfunc`s for
 /// this instance.
@@ -1461,6 +1621,15 @@ fn initialize_memories(
     Ok(())
 }
 
+/// The current value of the global at `index`, local or imported.
+unsafe fn global_definition(instance: &Instance, index: GlobalIndex) -> VMGlobalDefinition {
+    if let Some(def_index) = instance.module.local_global_index(index) {
+        instance.global(def_index)
+    } else {
+        instance.imported_global(index).definition.as_ref().clone()
+    }
+}
+
 fn initialize_globals(instance: &Instance) {
     let module = Arc::clone(&instance.module);
     for (index, initializer) in module.global_initializers.iter() {
@@ -1472,14 +1641,13 @@ fn initialize_globals(instance: &Instance) {
                 GlobalInit::F32Const(x) => (*to).val.f32 = *x,
                 GlobalInit::F64Const(x) => (*to).val.f64 = *x,
                 GlobalInit::V128Const(x) => (*to).val.bytes = *x.bytes(),
-                GlobalInit::GetGlobal(x) => {
-                    let from: VMGlobalDefinition =
-                        if let Some(def_x) = module.local_global_index(*x) {
-                            instance.global(def_x)
-                        } else {
-                            instance.imported_global(*x).definition.as_ref().clone()
-                        };
-                    *to = from;
+                GlobalInit::GetGlobal(x) => *to = global_definition(instance, *x),
+                GlobalInit::GetGlobalOffset(x, offset) => {
+                    *to = global_definition(instance, *x);
+                    match module.globals[module.global_index(index)].ty {
+                        Type::I64 => (*to).val.i64 = (*to).val.i64.wrapping_add(*offset),
+                        _ => (*to).val.i32 = (*to).val.i32.wrapping_add(*offset as i32),
+                    }
                 }
                 GlobalInit::RefNullConst => (*to).val.funcref = 0,
                 GlobalInit::RefFunc(func_idx) => {
@@ -1502,7 +1670,7 @@ fn build_funcrefs(
     function_call_trampolines: &BoxedSlice<SignatureIndex, VMTrampoline>,
     vmctx_ptr: *mut VMContext,
 ) -> (
-    BoxedSlice<LocalFunctionIndex, VMCallerCheckedAnyfunc>,
+    BoxedSlice<LocalFunctionIndex, UnsafeCell<VMCallerCheckedAnyfunc>>,
     BoxedSlice<FunctionIndex, NonNull<VMCallerCheckedAnyfunc>>,
 ) {
     let mut func_refs =
@@ -1526,7 +1694,7 @@ fn build_funcrefs(
             vmctx: VMFunctionContext { vmctx: vmctx_ptr },
             call_trampoline,
         };
-        func_refs.push(anyfunc);
+        func_refs.push(UnsafeCell::new(anyfunc));
     }
     (
         func_refs.into_boxed_slice(),
@@ -0,0 +1,266 @@
+//! Stopping WebAssembly code which is running on another thread.
+//!
+//! Every store has an [`InterruptState`], which calls into WebAssembly
+//! [`enter`][InterruptState::enter] for as long as they run. Once
+//! [`InterruptState::interrupt`] has been called, they trap with
+//! [`TrapCode::Interrupted`][crate::TrapCode::Interrupted] at the next
+//! interruption point:
+//!
+//! - On unix, the threads running the calls are sent `SIGURG`. When it lands
+//!   in compiled WebAssembly code, the trap handler raises the trap right
+//!   away. Otherwise the signal is sent again a little later, until one
+//!   lands or the calls return.
+//! - Host functions check [`is_interrupted`] when they return, and raise the
+//!   trap themselves. This is the only interruption point on Windows.
+//! - Host functions blocked on [`InterruptState::interrupted`] are woken up,
+//!   so they can return early.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+#[cfg(unix)]
+use std::time::Duration;
+
+/// How long to wait before signalling a thread again when the previous
+/// signal didn't stop it.
+#[cfg(unix)]
+const RESIGNAL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(unix)]
+extern "C" {
+    // Not bound by the `libc` crate on every target
+    fn pthread_equal(t1: libc::pthread_t, t2: libc::pthread_t) -> libc::c_int;
+}
+
+thread_local! {
+    /// The state of the innermost call running on this thread. It is read
+    /// from signal handlers, so it must be atomic.
+    static CURRENT: AtomicPtr<InterruptState> = AtomicPtr::new(ptr::null_mut());
+}
+
+/// Checks whether `pc` is in compiled WebAssembly code, as set by
+/// [`set_wasm_pc_checker`]. Zero until then.
+static IS_WASM_PC: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the function used by signal handlers to check whether a program
+/// counter is in compiled WebAssembly code, which is the only place an
+/// interrupt signal can stop a call from.
+///
+/// The function is called from signal handlers, so it must not block.
+pub fn set_wasm_pc_checker(is_wasm_pc: fn(usize) -> bool) {
+    IS_WASM_PC.store(is_wasm_pc as usize, Ordering::Release);
+}
+
+/// Whether `pc` is in compiled WebAssembly code.
+#[cfg(unix)]
+pub(crate) fn is_wasm_pc(pc: usize) -> bool {
+    match IS_WASM_PC.load(Ordering::Acquire) {
+        0 => false,
+        f => unsafe { std::mem::transmute::<usize, fn(usize) -> bool>(f)(pc) },
+    }
+}
+
+/// Whether the innermost call running on this thread has been interrupted.
+pub fn is_interrupted() -> bool {
+    CURRENT.with(|current| {
+        let current = current.load(Ordering::Relaxed);
+        // Safety: the pointer is only set while an `InterruptGuard` keeps the
+        // state alive.
+        !current.is_null() && unsafe { (*current).is_interrupted() }
+    })
+}
+
+/// Whether calls into WebAssembly should stop, shared by everything that
+/// wants to stop them.
+#[derive(Debug, Default)]
+pub struct InterruptState {
+    pending: AtomicBool,
+    running: Mutex<Running>,
+}
+
+#[derive(Debug, Default)]
+struct Running {
+    /// How many calls are running, including nested ones.
+    calls: usize,
+    /// The thread each call is running on.
+    #[cfg(unix)]
+    threads: Vec<libc::pthread_t>,
+    /// Futures waiting for an interrupt.
+    wakers: Vec<Waker>,
+}
+
+impl InterruptState {
+    /// Creates a new `InterruptState` with no calls running.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the calls running right now trap at their next interruption
+    /// point. This does nothing if no call is running, so calls made
+    /// afterwards aren't affected.
+    pub fn interrupt(self: &Arc<Self>) {
+        let mut running = self.running.lock().unwrap();
+        if running.calls == 0 {
+            return;
+        }
+        self.pending.store(true, Ordering::SeqCst);
+        #[cfg(unix)]
+        running.signal_threads();
+        let wakers = std::mem::take(&mut running.wakers);
+        drop(running);
+        for waker in wakers {
+            waker.wake();
+        }
+
+        #[cfg(unix)]
+        {
+            let state = Arc::downgrade(self);
+            std::thread::spawn(move || loop {
+                std::thread::sleep(RESIGNAL_INTERVAL);
+                let state = match state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                let running = state.running.lock().unwrap();
+                if !state.is_interrupted() {
+                    return;
+                }
+                running.signal_threads();
+            });
+        }
+    }
+
+    /// Whether the running calls have been interrupted.
+    pub fn is_interrupted(&self) -> bool {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Returns a future which completes once the running calls have been
+    /// interrupted.
+    pub fn interrupted(self: &Arc<Self>) -> Interrupted {
+        Interrupted {
+            state: self.clone(),
+        }
+    }
+
+    /// Marks a call as running on the current thread, until the returned
+    /// guard is dropped.
+    pub fn enter(self: &Arc<Self>) -> InterruptGuard {
+        let mut running = self.running.lock().unwrap();
+        running.calls += 1;
+        #[cfg(unix)]
+        running.threads.push(unsafe { libc::pthread_self() });
+        drop(running);
+
+        let previous =
+            CURRENT.with(|current| current.swap(Arc::as_ptr(self) as *mut Self, Ordering::Relaxed));
+        InterruptGuard {
+            state: self.clone(),
+            previous,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Running {
+    fn signal_threads(&self) {
+        for thread in &self.threads {
+            unsafe {
+                libc::pthread_kill(*thread, libc::SIGURG);
+            }
+        }
+    }
+}
+
+/// Marks a call into WebAssembly as running, see [`InterruptState::enter`].
+#[derive(Debug)]
+pub struct InterruptGuard {
+    state: Arc<InterruptState>,
+    previous: *mut InterruptState,
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.store(self.previous, Ordering::Relaxed));
+
+        let mut running = self.state.running.lock().unwrap();
+        running.calls -= 1;
+        #[cfg(unix)]
+        {
+            let this_thread = unsafe { libc::pthread_self() };
+            if let Some(index) = running
+                .threads
+                .iter()
+                .rposition(|thread| unsafe { pthread_equal(*thread, this_thread) } != 0)
+            {
+                running.threads.swap_remove(index);
+            }
+        }
+        // Once everything has stopped, new calls can run again
+        if running.calls == 0 {
+            self.state.pending.store(false, Ordering::SeqCst);
+            running.wakers.clear();
+        }
+    }
+}
+
+/// A future which completes once the running calls have been interrupted,
+/// see [`InterruptState::interrupted`].
+#[derive(Debug)]
+pub struct Interrupted {
+    state: Arc<InterruptState>,
+}
+
+impl Future for Interrupted {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.is_interrupted() {
+            return Poll::Ready(());
+        }
+        let mut running = self.state.running.lock().unwrap();
+        // Check again now that `interrupt` can't run
+        if self.state.is_interrupted() {
+            return Poll::Ready(());
+        }
+        if !running
+            .wakers
+            .iter()
+            .any(|waker| waker.will_wake(cx.waker()))
+        {
+            running.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupting_without_running_calls_does_nothing() {
+        let state = Arc::new(InterruptState::new());
+        state.interrupt();
+        assert!(!state.is_interrupted());
+
+        let _guard = state.enter();
+        assert!(!is_interrupted());
+    }
+
+    #[test]
+    fn interrupts_end_with_the_calls() {
+        let state = Arc::new(InterruptState::new());
+        {
+            let _outer = state.enter();
+            let _inner = state.enter();
+            state.interrupt();
+            assert!(is_interrupted());
+        }
+        assert!(!is_interrupted());
+        assert!(!state.is_interrupted());
+    }
+}
@@ -0,0 +1,28 @@
+//! Support for compiling functions lazily, the first time they are called.
+//!
+//! When a module is compiled lazily, each of its functions starts out as a
+//! small stub which calls the `wasmer_vm_lazy_compile` builtin to get the
+//! real body (compiling it if needed) and then jumps to it. The builtin
+//! forwards to the [`LazyFunctionCompiler`] the instance was given, and
+//! points the function's `VMCallerCheckedAnyfunc` at the new body so calls
+//! through tables and exports skip the stub from then on.
+
+use crate::FunctionBodyPtr;
+use wasmer_types::{CompileError, LocalFunctionIndex};
+
+/// Compiles the functions of a lazily compiled module on demand.
+///
+/// Implementations are shared by every instance of the module, so they
+/// must make sure each function is only compiled once, even when several
+/// threads call it for the first time at once.
+pub trait LazyFunctionCompiler: Send + Sync {
+    /// The body of the function, if it has already been compiled.
+    ///
+    /// This is checked on every call through a stub, so it should be cheap.
+    fn compiled(&self, index: LocalFunctionIndex) -> Option<FunctionBodyPtr>;
+
+    /// Compile the function (unless that has already been done), returning
+    /// its body. The body must stay valid for as long as this object is
+    /// alive.
+    fn compile(&self, index: LocalFunctionIndex) -> Result<FunctionBodyPtr, CompileError>;
+}
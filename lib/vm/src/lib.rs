@@ -20,12 +20,15 @@
     )
 )]
 
+mod exception;
 mod export;
 mod extern_ref;
 mod function_env;
 mod global;
 mod imports;
 mod instance;
+mod interrupt;
+mod lazy;
 mod memory;
 mod mmap;
 mod probestack;
@@ -40,20 +43,27 @@ pub mod libcalls;
 
 use std::ptr::NonNull;
 
+pub use crate::exception::{PendingException, VMException, VMTag};
 pub use crate::export::*;
 pub use crate::extern_ref::{VMExternObj, VMExternRef};
 pub use crate::function_env::VMFunctionEnvironment;
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{InstanceAllocator, VMInstance};
+pub use crate::interrupt::{
+    is_interrupted, set_wasm_pc_checker, InterruptGuard, InterruptState, Interrupted,
+};
+pub use crate::lazy::LazyFunctionCompiler;
 pub use crate::memory::{
-    initialize_memory_with_data, LinearMemory, NotifyLocation, VMMemory, VMOwnedMemory,
-    VMSharedMemory,
+    initialize_memory_with_data, LinearMemory, NotifyLocation, OwnedMemoryBuffer, VMMemory,
+    VMOwnedMemory, VMSharedMemory,
 };
 pub use crate::mmap::Mmap;
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
-pub use crate::store::{InternalStoreHandle, MaybeInstanceOwned, StoreHandle, StoreObjects};
+pub use crate::store::{
+    InternalStoreHandle, MaybeInstanceOwned, RestoreError, StoreHandle, StoreObjects, StoreSnapshot,
+};
 pub use crate::table::{TableElement, VMTable};
 #[doc(hidden)]
 pub use crate::threadconditions::ThreadConditions;
@@ -87,6 +97,15 @@ impl std::ops::Deref for SectionBodyPtr {
     }
 }
 
+/// # Safety
+/// Sections are never written to once they have been allocated, so sharing
+/// a pointer to one between threads is fine.
+unsafe impl Send for SectionBodyPtr {}
+/// # Safety
+/// Sections are never written to once they have been allocated, so sharing
+/// a pointer to one between threads is fine.
+unsafe impl Sync for SectionBodyPtr {}
+
 /// A placeholder byte-sized type which is just used to provide some amount of type
 /// safety when dealing with pointers to JIT-compiled function bodies. Note that it's
 /// deliberately not Copy, as we shouldn't be carelessly copying function body bytes
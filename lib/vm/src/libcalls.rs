@@ -37,15 +37,16 @@
 
 #![allow(missing_docs)] // For some reason lint fails saying that `LibCall` is not documented, when it actually is
 
+use crate::exception::VMException;
 use crate::probestack::PROBESTACK;
 use crate::table::{RawTableElement, TableElement};
 use crate::trap::{raise_lib_trap, Trap, TrapCode};
 use crate::vmcontext::VMContext;
-use crate::{on_host_stack, VMFuncRef};
+use crate::{on_host_stack, VMFuncRef, VMFunctionBody};
 pub use wasmer_types::LibCall;
 use wasmer_types::{
-    DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
-    TableIndex, Type,
+    DataIndex, ElemIndex, FunctionIndex, LocalFunctionIndex, LocalMemoryIndex, LocalTableIndex,
+    MemoryIndex, RawValue, TableIndex, TagIndex, Type,
 };
 
 /// Implementation of f32.ceil
@@ -228,14 +229,8 @@ pub unsafe extern "C" fn wasmer_vm_table_copy(
     let result = {
         let dst_table_index = TableIndex::from_u32(dst_table_index);
         let src_table_index = TableIndex::from_u32(src_table_index);
-        if dst_table_index == src_table_index {
-            let table = (*vmctx).instance_mut().get_table(dst_table_index);
-            table.copy_within(dst, src, len)
-        } else {
-            let dst_table = (*vmctx).instance_mut().get_table(dst_table_index);
-            let src_table = (*vmctx).instance_mut().get_table(src_table_index);
-            dst_table.copy(src_table, dst, src, len)
-        }
+        let instance = (*vmctx).instance_mut();
+        instance.table_copy(dst_table_index, src_table_index, dst, src, len)
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -383,11 +378,7 @@ pub unsafe extern "C" fn wasmer_vm_table_set(
     value: RawTableElement,
 ) {
     let instance = (*vmctx).instance_mut();
-    let table_index = TableIndex::from_u32(table_index);
-    let table_index = instance
-        .module_ref()
-        .local_table_index(table_index)
-        .unwrap();
+    let table_index = LocalTableIndex::from_u32(table_index);
 
     let elem = match instance.get_local_table(table_index).ty().ty {
         Type::ExternRef => TableElement::ExternRef(value.extern_ref),
@@ -531,7 +522,7 @@ pub unsafe extern "C" fn wasmer_vm_memory32_copy(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (*vmctx).instance();
-        instance.local_memory_copy(memory_index, dst, src, len)
+        instance.local_memory_copy(memory_index, dst.into(), src.into(), len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -554,7 +545,7 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_copy(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (*vmctx).instance();
-        instance.imported_memory_copy(memory_index, dst, src, len)
+        instance.imported_memory_copy(memory_index, dst.into(), src.into(), len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -577,7 +568,7 @@ pub unsafe extern "C" fn wasmer_vm_memory32_fill(
     let result = {
         let memory_index = LocalMemoryIndex::from_u32(memory_index);
         let instance = (*vmctx).instance();
-        instance.local_memory_fill(memory_index, dst, val, len)
+        instance.local_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -600,7 +591,7 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_fill(
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
         let instance = (*vmctx).instance();
-        instance.imported_memory_fill(memory_index, dst, val, len)
+        instance.imported_memory_fill(memory_index, dst.into(), val, len.into())
     };
     if let Err(trap) = result {
         raise_lib_trap(trap);
@@ -620,6 +611,124 @@ pub unsafe extern "C" fn wasmer_vm_memory32_init(
     dst: u32,
     src: u32,
     len: u32,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let data_index = DataIndex::from_u32(data_index);
+        let instance = (*vmctx).instance();
+        instance.memory_init(memory_index, data_index, dst.into(), src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (*vmctx).instance();
+        instance.local_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.copy` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_copy(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (*vmctx).instance();
+        instance.imported_memory_copy(memory_index, dst, src, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for locally defined 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = LocalMemoryIndex::from_u32(memory_index);
+        let instance = (*vmctx).instance();
+        instance.local_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.fill` for imported 64-bit memories.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_imported_memory64_fill(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    dst: u64,
+    val: u32,
+    len: u64,
+) {
+    let result = {
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        let instance = (*vmctx).instance();
+        instance.imported_memory_fill(memory_index, dst, val, len)
+    };
+    if let Err(trap) = result {
+        raise_lib_trap(trap);
+    }
+}
+
+/// Implementation of `memory.init` for 64-bit memories, which only have a
+/// 64-bit destination address.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_memory64_init(
+    vmctx: *mut VMContext,
+    memory_index: u32,
+    data_index: u32,
+    dst: u64,
+    src: u32,
+    len: u32,
 ) {
     let result = {
         let memory_index = MemoryIndex::from_u32(memory_index);
@@ -815,6 +924,78 @@ pub unsafe extern "C" fn wasmer_vm_imported_memory32_atomic_notify(
     result.unwrap()
 }
 
+/// Gets the body of a lazily compiled function, compiling it first if this
+/// is the first time it has been called.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable and belong to an instance which was given
+/// a [`LazyFunctionCompiler`](crate::LazyFunctionCompiler).
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_lazy_compile(
+    vmctx: *mut VMContext,
+    function_index: u32,
+) -> *const VMFunctionBody {
+    let result = {
+        let instance = (*vmctx).instance();
+        let function_index = LocalFunctionIndex::from_u32(function_index);
+
+        instance.lazy_compile(function_index)
+    };
+    match result {
+        Ok(body) => body,
+        Err(error) => raise_lib_trap(Trap::user(Box::new(error))),
+    }
+}
+
+/// Implementation of `throw`, starting to throw an exception with the tag
+/// `tag_index` and the values at `values`.
+///
+/// The caller then checks for the pending exception like after any call.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable and `values` must point to a value for
+/// each parameter of the tag.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_throw(
+    vmctx: *mut VMContext,
+    tag_index: u32,
+    values: *const RawValue,
+) {
+    on_host_stack(|| {
+        let tag_index = TagIndex::from_u32(tag_index);
+        let instance = (*vmctx).instance();
+        instance.throw(tag_index, values);
+    })
+}
+
+/// Implementation of `rethrow`, throwing the exception caught by a `catch`
+/// clause again.
+///
+/// # Safety
+///
+/// `vmctx` must be dereferenceable and `exception` must be an exception
+/// taken from the pending exception and not dropped yet.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_rethrow(vmctx: *mut VMContext, exception: *mut VMException) {
+    on_host_stack(|| {
+        let instance = (*vmctx).instance();
+        instance.rethrow(&*exception);
+    })
+}
+
+/// Frees the exception caught by a `catch` clause, when leaving it.
+///
+/// # Safety
+///
+/// `exception` must be an exception taken from the pending exception and
+/// not dropped yet.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_vm_exception_drop(exception: *mut VMException) {
+    on_host_stack(|| drop(Box::from_raw(exception)))
+}
+
 /// The function pointer to a libcall
 pub fn function_pointer(libcall: LibCall) -> usize {
     match libcall {
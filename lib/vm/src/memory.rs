@@ -48,10 +48,9 @@ impl WasmMmap {
             return Ok(self.size);
         }
 
-        let new_pages = self
-            .size
-            .checked_add(delta)
-            .ok_or(MemoryError::CouldNotGrow {
+        let new_pages = u32::try_from(u64::from(self.size.0) + u64::from(delta.0))
+            .map(Pages)
+            .map_err(|_| MemoryError::CouldNotGrow {
                 current: self.size,
                 attempted_delta: delta,
             })?;
@@ -69,7 +68,7 @@ impl WasmMmap {
         // Wasm linear memories are never allowed to grow beyond what is
         // indexable. If the memory has no maximum, enforce the greatest
         // limit here.
-        if new_pages >= Pages::max_value() {
+        if new_pages >= conf.memory.max_pages() {
             // Linear memory size would exceed the index range.
             return Err(MemoryError::CouldNotGrow {
                 current: self.size,
@@ -120,6 +119,48 @@ impl WasmMmap {
         Ok(prev_pages)
     }
 
+    /// Zeroes the memory and shrinks (or grows) it to `size` pages, keeping
+    /// the underlying allocation.
+    fn reset(&mut self, size: Pages, conf: VMMemoryConfig) -> Result<(), MemoryError> {
+        if size > self.size {
+            self.grow(size - self.size, conf)?;
+        }
+
+        let keep_bytes = size.bytes().0;
+        let current_bytes = self.size.bytes().0;
+        self.alloc
+            .zero(0, keep_bytes)
+            .map_err(MemoryError::Region)?;
+        self.alloc
+            .make_inaccessible(keep_bytes, current_bytes - keep_bytes)
+            .map_err(MemoryError::Region)?;
+
+        self.size = size;
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition.as_ptr();
+            let md = md_ptr.as_mut();
+            md.current_length = keep_bytes;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the allocation out, leaving an empty memory behind.
+    fn take(&mut self) -> OwnedMemoryBuffer {
+        let len = self.size.bytes().0;
+        let alloc = std::mem::replace(&mut self.alloc, Mmap::new());
+
+        self.size = Pages(0);
+        unsafe {
+            let mut md_ptr = self.vm_memory_definition.as_ptr();
+            let md = md_ptr.as_mut();
+            md.current_length = 0;
+            md.base = self.alloc.as_mut_ptr() as _;
+        }
+
+        OwnedMemoryBuffer { alloc, len }
+    }
+
     /// Copies the memory
     /// (in this case it performs a copy-on-write to save memory)
     pub fn copy(&mut self) -> Result<Self, MemoryError> {
@@ -142,6 +183,68 @@ impl WasmMmap {
     }
 }
 
+/// The contents of a linear memory, detached from the memory itself.
+///
+/// Created by [`LinearMemory::take_buffer`], which hands over the memory's
+/// own allocation without copying it, or [`OwnedMemoryBuffer::copy_from`].
+#[derive(Debug)]
+pub struct OwnedMemoryBuffer {
+    alloc: Mmap,
+    len: usize,
+}
+
+impl OwnedMemoryBuffer {
+    /// Copies the current contents of `memory` into a new buffer.
+    pub fn copy_from(memory: &dyn LinearMemory) -> Result<Self, MemoryError> {
+        unsafe {
+            let md = memory.vmmemory().as_ref();
+            let len = md.current_length;
+            let mut alloc = Mmap::with_at_least(len).map_err(MemoryError::Region)?;
+            alloc.as_mut_slice()[..len].copy_from_slice(slice::from_raw_parts(md.base, len));
+            Ok(Self { alloc, len })
+        }
+    }
+
+    /// The size of the memory in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the memory was empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// A pointer to the start of the buffer.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.alloc.as_ptr()
+    }
+
+    /// The contents of the memory.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.alloc.as_slice()[..self.len]
+    }
+
+    /// The contents of the memory, mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.alloc.as_mut_slice()[..self.len]
+    }
+}
+
+impl std::ops::Deref for OwnedMemoryBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl std::ops::DerefMut for OwnedMemoryBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
 /// A linear memory instance.
 #[derive(Debug, Clone)]
 struct VMMemoryConfig {
@@ -211,18 +314,19 @@ impl VMOwnedMemory {
         style: &MemoryStyle,
         vm_memory_location: Option<NonNull<VMMemoryDefinition>>,
     ) -> Result<Self, MemoryError> {
-        if memory.minimum > Pages::max_value() {
+        if memory.minimum > memory.max_pages() {
             return Err(MemoryError::MinimumMemoryTooLarge {
                 min_requested: memory.minimum,
-                max_allowed: Pages::max_value(),
+                max_allowed: memory.max_pages(),
             });
         }
-        // `maximum` cannot be set to more than `65536` pages.
+        // `maximum` cannot be set to more than `65536` pages (for 32-bit
+        // memories).
         if let Some(max) = memory.maximum {
-            if max > Pages::max_value() {
+            if max > memory.max_pages() {
                 return Err(MemoryError::MaximumMemoryTooLarge {
                     max_requested: max,
-                    max_allowed: Pages::max_value(),
+                    max_allowed: memory.max_pages(),
                 });
             }
             if max < memory.minimum {
@@ -341,6 +445,16 @@ impl LinearMemory for VMOwnedMemory {
         let forked = Self::copy(self)?;
         Ok(Box::new(forked))
     }
+
+    /// Zeroes this memory and resizes it to `size` pages
+    fn reset(&mut self, size: Pages) -> Result<(), MemoryError> {
+        self.mmap.reset(size, self.config.clone())
+    }
+
+    /// Moves the allocation out of this memory
+    unsafe fn take_buffer(&mut self) -> Result<OwnedMemoryBuffer, MemoryError> {
+        Ok(self.mmap.take())
+    }
 }
 
 /// A shared linear memory instance.
@@ -520,6 +634,16 @@ impl LinearMemory for VMMemory {
         self.0.copy()
     }
 
+    /// Zeroes this memory and resizes it to `size` pages
+    fn reset(&mut self, size: Pages) -> Result<(), MemoryError> {
+        self.0.reset(size)
+    }
+
+    /// Moves the allocation out of this memory
+    unsafe fn take_buffer(&mut self) -> Result<OwnedMemoryBuffer, MemoryError> {
+        self.0.take_buffer()
+    }
+
     // Add current thread to waiter list
     fn do_wait(
         &mut self,
@@ -652,6 +776,30 @@ where
     /// Copies this memory to a new memory
     fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError>;
 
+    /// Zeroes this memory and resizes it to `size` pages, so it can be
+    /// reused without being reallocated.
+    ///
+    /// Memories which don't support this return an error.
+    fn reset(&mut self, _size: Pages) -> Result<(), MemoryError> {
+        Err(MemoryError::Generic(
+            "this memory does not support being reset".to_string(),
+        ))
+    }
+
+    /// Moves the memory's allocation into an [`OwnedMemoryBuffer`] without
+    /// copying it, leaving an empty memory behind.
+    ///
+    /// Memories which don't support this return an error.
+    ///
+    /// # Safety
+    /// Code compiled for static memories doesn't bounds check accesses, so
+    /// no WebAssembly code may use this memory afterwards.
+    unsafe fn take_buffer(&mut self) -> Result<OwnedMemoryBuffer, MemoryError> {
+        Err(MemoryError::Generic(
+            "this memory does not support taking its buffer".to_string(),
+        ))
+    }
+
     /// Add current thread to the waiter hash, and wait until notified or timout.
     /// Return 0 if the waiter has been notified, 2 if the timeout occured, or None if en error happened
     fn do_wait(
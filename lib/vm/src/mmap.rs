@@ -233,6 +233,77 @@ impl Mmap {
         Ok(())
     }
 
+    /// Make the memory starting at `start` and extending for `len` bytes inaccessible
+    /// again, discarding its contents. `start` and `len` must be native page-size
+    /// multiples and describe a range within `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn make_inaccessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(start + len, self.total_size);
+
+        if len == 0 {
+            return Ok(());
+        }
+        self.zero(start, len)?;
+        let ptr = self.ptr as *const u8;
+        unsafe { region::protect(ptr.add(start), len, region::Protection::NONE) }
+            .map_err(|e| e.to_string())
+    }
+
+    /// Make the memory starting at `start` and extending for `len` bytes inaccessible
+    /// again, discarding its contents. `start` and `len` must be native page-size
+    /// multiples and describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn make_inaccessible(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(start + len, self.len());
+
+        if len == 0 {
+            return Ok(());
+        }
+        // Decommitted pages come back zeroed when they are committed again.
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Zero the accessible memory starting at `start` and extending for `len` bytes.
+    ///
+    /// On Linux, whole pages are handed back to the kernel instead of being
+    /// written to, so they are only faulted in again once they are used.
+    pub fn zero(&mut self, start: usize, len: usize) -> Result<(), String> {
+        assert_le!(start + len, self.total_size);
+        if len == 0 {
+            return Ok(());
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let page_size = region::page::size();
+            if start & (page_size - 1) == 0 && len & (page_size - 1) == 0 {
+                let ptr = (self.ptr + start) as *mut libc::c_void;
+                // Private anonymous pages read back as zeros after this
+                if unsafe { libc::madvise(ptr, len, libc::MADV_DONTNEED) } != 0 {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                return Ok(());
+            }
+        }
+
+        unsafe { ptr::write_bytes((self.ptr + start) as *mut u8, 0, len) };
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.total_size) }
@@ -1,9 +1,19 @@
+use crate::exception::PendingException;
+use crate::instance::PassiveSegments;
 use crate::{
-    VMExternObj, VMFunction, VMFunctionEnvironment, VMGlobal, VMInstance, VMMemory, VMTable,
+    LinearMemory, TableElement, VMExternObj, VMExternRef, VMFunction, VMFunctionEnvironment,
+    VMGlobal, VMInstance, VMMemory, VMTable, VMTag,
 };
 use core::slice::Iter;
-use std::{cell::UnsafeCell, fmt, marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
-use wasmer_types::StoreId;
+use std::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    ptr::{self, NonNull},
+};
+use thiserror::Error;
+use wasmer_types::{MemoryError, Pages, RawValue, StoreId, Type};
 
 /// Trait to represent an object managed by a context. This is implemented on
 /// the VM types managed by the context.
@@ -33,6 +43,7 @@ impl_context_object! {
     memories => VMMemory,
     extern_objs => VMExternObj,
     function_environments => VMFunctionEnvironment,
+    tags => VMTag,
 }
 
 /// Set of objects managed by a context.
@@ -46,6 +57,27 @@ pub struct StoreObjects {
     instances: Vec<VMInstance>,
     extern_objs: Vec<VMExternObj>,
     function_environments: Vec<VMFunctionEnvironment>,
+    tags: Vec<VMTag>,
+    pending_exception: PendingException,
+    memory_grow_observers: Vec<MemoryGrowObserver>,
+    /// The number of times each memory has grown, indexed by handle.
+    memory_generations: Vec<u64>,
+}
+
+/// A callback registered with [`StoreObjects::subscribe_memory_grow`].
+type MemoryGrowCallback = Box<dyn FnMut(Pages, Pages) + Send + Sync>;
+
+struct MemoryGrowObserver {
+    memory: InternalStoreHandle<VMMemory>,
+    callback: MemoryGrowCallback,
+}
+
+impl fmt::Debug for MemoryGrowObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryGrowObserver")
+            .field("memory", &self.memory)
+            .finish_non_exhaustive()
+    }
 }
 
 impl StoreObjects {
@@ -68,14 +100,132 @@ impl StoreObjects {
         b: InternalStoreHandle<T>,
     ) -> (&mut T, &mut T) {
         assert_ne!(a.index(), b.index());
+        // Handle indices start at 1
+        let (a, b) = (a.index() - 1, b.index() - 1);
         let list = T::list_mut(self);
-        if a.index() < b.index() {
-            let (low, high) = list.split_at_mut(b.index());
-            (&mut low[a.index()], &mut high[0])
+        if a < b {
+            let (low, high) = list.split_at_mut(b);
+            (&mut low[a], &mut high[0])
         } else {
-            let (low, high) = list.split_at_mut(a.index());
-            (&mut high[0], &mut low[a.index()])
+            let (low, high) = list.split_at_mut(a);
+            (&mut high[0], &mut low[b])
+        }
+    }
+
+    /// Drop the contents of every [`VMExternObj`] which is no longer
+    /// referenced by a table, a global, or a handle held by the host,
+    /// returning the number of objects that were collected.
+    ///
+    /// # Safety
+    ///
+    /// Externrefs which only live on the WebAssembly stack aren't tracked, so
+    /// this must not be called while WebAssembly code is executing on this
+    /// store.
+    pub unsafe fn gc_extern_objs(&mut self) -> usize {
+        let mut reachable = vec![false; self.extern_objs.len()];
+        let mut mark = |extern_ref: VMExternRef| {
+            if let Some(slot) = reachable.get_mut(extern_ref.0.index() - 1) {
+                *slot = true;
+            }
+        };
+
+        for table in &self.tables {
+            if table.ty().ty != Type::ExternRef {
+                continue;
+            }
+            for i in 0..table.size() {
+                if let Some(TableElement::ExternRef(Some(extern_ref))) = table.get(i) {
+                    mark(extern_ref);
+                }
+            }
+        }
+
+        for global in &self.globals {
+            if global.ty().ty != Type::ExternRef {
+                continue;
+            }
+            let raw = global.vmglobal().as_ref().val;
+            if let Some(extern_ref) = VMExternRef::from_raw(raw) {
+                mark(extern_ref);
+            }
+        }
+
+        let mut collected = 0;
+        for (obj, reachable) in self.extern_objs.iter_mut().zip(reachable) {
+            if !reachable && !obj.is_collected() && !obj.is_referenced_by_host() {
+                obj.collect();
+                collected += 1;
+            }
         }
+
+        collected
+    }
+
+    /// Grow a memory by `delta` pages, returning its previous size.
+    ///
+    /// Every grow of a memory owned by this store, whether it was requested
+    /// by the guest (`memory.grow`) or by the host, goes through here so
+    /// observers registered with [`StoreObjects::subscribe_memory_grow`] are
+    /// notified once the memory has its new size.
+    pub fn grow_memory(
+        &mut self,
+        memory: InternalStoreHandle<VMMemory>,
+        delta: Pages,
+    ) -> Result<Pages, MemoryError> {
+        let vm_memory = memory.get_mut(self);
+        let old_size = vm_memory.grow(delta)?;
+        let new_size = vm_memory.size();
+
+        // `memory.grow 0` is how the guest asks for the current size
+        if new_size != old_size {
+            let index = memory.index() - 1;
+            if self.memory_generations.len() <= index {
+                self.memory_generations.resize(index + 1, 0);
+            }
+            self.memory_generations[index] += 1;
+
+            // Note: observers don't get access to the store, so they can't
+            // re-enter WebAssembly or grow the memory again.
+            for observer in &mut self.memory_grow_observers {
+                if observer.memory == memory {
+                    (observer.callback)(old_size, new_size);
+                }
+            }
+        }
+
+        Ok(old_size)
+    }
+
+    /// Register a callback which is invoked with the old and new size every
+    /// time `memory` is grown through this store.
+    ///
+    /// Callbacks are invoked in the order they were registered, on the thread
+    /// which performed the grow, before control returns to WebAssembly.
+    pub fn subscribe_memory_grow(
+        &mut self,
+        memory: InternalStoreHandle<VMMemory>,
+        callback: impl FnMut(Pages, Pages) + Send + Sync + 'static,
+    ) {
+        self.memory_grow_observers.push(MemoryGrowObserver {
+            memory,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// The number of times `memory` has been grown through this store.
+    ///
+    /// Pointers into a memory's data may be invalidated when it grows, so
+    /// this can be used to cheaply check whether a cached pointer is stale.
+    pub fn memory_generation(&self, memory: InternalStoreHandle<VMMemory>) -> u64 {
+        self.memory_generations
+            .get(memory.index() - 1)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The exception being thrown on the store, if any.
+    pub fn pending_exception(&self) -> &PendingException {
+        &self.pending_exception
     }
 
     /// Return an immutable iterator over all globals
@@ -99,6 +249,203 @@ impl StoreObjects {
             self.globals[idx].vmglobal().as_mut().val.u128 = val;
         }
     }
+
+    /// Record the contents of every memory, table and global in the store,
+    /// along with the passive segments of its instances, so they can be put
+    /// back with [`StoreObjects::restore`].
+    ///
+    /// The state of host objects, like the data in function environments,
+    /// isn't part of the snapshot.
+    pub fn snapshot(&self) -> StoreSnapshot {
+        StoreSnapshot {
+            memories: self.memories.iter().map(MemorySnapshot::new).collect(),
+            tables: self
+                .tables
+                .iter()
+                .map(|table| (0..table.size()).filter_map(|i| table.get(i)).collect())
+                .collect(),
+            globals: self
+                .globals
+                .iter()
+                .map(|global| unsafe { global.vmglobal().as_ref().val })
+                .collect(),
+            instances: self
+                .instances
+                .iter()
+                .map(|instance| instance.instance().passive_segments())
+                .collect(),
+            functions: self.functions.len(),
+            extern_objs: self.extern_objs.len(),
+            function_environments: self.function_environments.len(),
+        }
+    }
+
+    /// Put the memories, tables, globals and instances of the store back the
+    /// way they were when `snapshot` was taken, without reallocating them.
+    ///
+    /// Functions which only wrap a function of an instance (e.g. ones read
+    /// out of a table) are dropped again. Fails if any other objects were
+    /// added to the store since, or if one of the memories can't be reset.
+    /// The store is left in an unspecified state when this fails and
+    /// shouldn't be used again.
+    ///
+    /// # Safety
+    ///
+    /// `snapshot` must have been taken from this store, and WebAssembly code
+    /// must not be executing on it. Handles to the dropped functions must not
+    /// be used again.
+    pub unsafe fn restore(&mut self, snapshot: &StoreSnapshot) -> Result<(), RestoreError> {
+        let added_functions = self.functions.get(snapshot.functions..).unwrap_or_default();
+        if added_functions
+            .iter()
+            .all(|function| matches!(function.anyfunc, MaybeInstanceOwned::Instance(_)))
+        {
+            self.functions.truncate(snapshot.functions);
+        }
+
+        if self.memories.len() != snapshot.memories.len()
+            || self.tables.len() != snapshot.tables.len()
+            || self.globals.len() != snapshot.globals.len()
+            || self.instances.len() != snapshot.instances.len()
+            || self.functions.len() != snapshot.functions
+            || self.extern_objs.len() != snapshot.extern_objs
+            || self.function_environments.len() != snapshot.function_environments
+        {
+            return Err(RestoreError::ObjectsChanged);
+        }
+
+        for (index, saved) in snapshot.memories.iter().enumerate() {
+            let memory = &mut self.memories[index];
+            let old_size = memory.size();
+            memory.reset(saved.size)?;
+
+            let definition = memory.vmmemory().as_ref();
+            for (offset, bytes) in &saved.data {
+                ptr::copy_nonoverlapping(bytes.as_ptr(), definition.base.add(*offset), bytes.len());
+            }
+
+            // Pointers into the memory are only invalidated if it shrank
+            if old_size != saved.size {
+                if self.memory_generations.len() <= index {
+                    self.memory_generations.resize(index + 1, 0);
+                }
+                self.memory_generations[index] += 1;
+            }
+        }
+
+        for (table, elements) in self.tables.iter_mut().zip(&snapshot.tables) {
+            let len = u32::try_from(elements.len()).unwrap();
+            table.truncate(len);
+            for (i, element) in (0..len).zip(elements) {
+                table
+                    .set(i, element.clone())
+                    .map_err(|_| RestoreError::ObjectsChanged)?;
+            }
+        }
+
+        for (global, val) in self.globals.iter().zip(&snapshot.globals) {
+            global.vmglobal().as_mut().val = *val;
+        }
+
+        for (instance, segments) in self.instances.iter().zip(&snapshot.instances) {
+            instance.instance().restore_passive_segments(segments);
+        }
+
+        Ok(())
+    }
+}
+
+/// The state of the objects in a [`StoreObjects`], taken with
+/// [`StoreObjects::snapshot`].
+pub struct StoreSnapshot {
+    memories: Vec<MemorySnapshot>,
+    tables: Vec<Vec<TableElement>>,
+    globals: Vec<RawValue>,
+    instances: Vec<PassiveSegments>,
+    functions: usize,
+    extern_objs: usize,
+    function_environments: usize,
+}
+
+// The snapshot only holds plain data and pointers to functions owned by the
+// store it was taken from, and is only used together with that store.
+unsafe impl Send for StoreSnapshot {}
+unsafe impl Sync for StoreSnapshot {}
+
+impl fmt::Debug for StoreSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreSnapshot")
+            .field("memories", &self.memories)
+            .field("tables", &self.tables.len())
+            .field("globals", &self.globals.len())
+            .field("instances", &self.instances.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The size of a memory, and the parts of it which aren't zero.
+struct MemorySnapshot {
+    size: Pages,
+    /// Runs of non-zero pages, by offset.
+    data: Vec<(usize, Box<[u8]>)>,
+}
+
+impl MemorySnapshot {
+    /// Memories are compared a native page at a time, so untouched pages
+    /// don't take up any room in the snapshot.
+    const CHUNK_SIZE: usize = 4096;
+
+    fn new(memory: &VMMemory) -> Self {
+        let contents = unsafe {
+            let definition = memory.vmmemory().as_ref();
+            std::slice::from_raw_parts(definition.base, definition.current_length)
+        };
+
+        let mut data: Vec<(usize, Box<[u8]>)> = Vec::new();
+        let mut run: Option<usize> = None;
+        for (i, chunk) in contents.chunks(Self::CHUNK_SIZE).enumerate() {
+            let offset = i * Self::CHUNK_SIZE;
+            match (run, chunk.iter().all(|b| *b == 0)) {
+                (None, false) => run = Some(offset),
+                (Some(start), true) => {
+                    data.push((start, contents[start..offset].into()));
+                    run = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = run {
+            data.push((start, contents[start..].into()));
+        }
+
+        Self {
+            size: memory.size(),
+            data,
+        }
+    }
+}
+
+impl fmt::Debug for MemorySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemorySnapshot")
+            .field("size", &self.size)
+            .field(
+                "data",
+                &self.data.iter().map(|(_, d)| d.len()).sum::<usize>(),
+            )
+            .finish()
+    }
+}
+
+/// The error returned by [`StoreObjects::restore`].
+#[derive(Debug, Error)]
+pub enum RestoreError {
+    /// Objects were added to the store after the snapshot was taken.
+    #[error("objects were added to the store after the snapshot was taken")]
+    ObjectsChanged,
+    /// A memory couldn't be reset.
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
 }
 
 /// Handle to an object managed by a context.
@@ -217,6 +217,24 @@ impl VMTable {
         Some(size)
     }
 
+    /// Shrink the table to `len` elements, dropping the rest.
+    ///
+    /// Does nothing if the table isn't bigger than that.
+    pub fn truncate(&mut self, len: u32) {
+        if len >= self.size() {
+            return;
+        }
+
+        self.vec.truncate(usize::try_from(len).unwrap());
+
+        // update table definition
+        unsafe {
+            let mut td_ptr = self.get_vm_table_definition();
+            let td = td_ptr.as_mut();
+            td.current_elements = len;
+        }
+    }
+
     /// Get reference to the specified element.
     ///
     /// Returns `None` if the index is out of bounds.
@@ -270,7 +288,7 @@ impl VMTable {
     /// # Errors
     ///
     /// Returns an error if the range is out of bounds of either the source or
-    /// destination tables.
+    /// destination tables, or if the tables have different element types.
     pub fn copy(
         &mut self,
         src_table: &Self,
@@ -280,6 +298,12 @@ impl VMTable {
     ) -> Result<(), Trap> {
         // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-copy
 
+        // Validation rejects this for `table.copy`, but the tables can also
+        // come from the host
+        if self.table.ty != src_table.table.ty {
+            return Err(Trap::lib(TrapCode::BadSignature));
+        }
+
         if src_index
             .checked_add(len)
             .map_or(true, |n| n > src_table.size())
@@ -10,8 +10,9 @@ mod traphandlers;
 
 pub use trap::Trap;
 pub use traphandlers::{
-    catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, set_stack_size,
-    wasmer_call_trampoline, TrapHandlerFn, VMConfig,
+    catch_traps, default_stack_size, on_host_stack, raise_lib_trap, raise_user_trap,
+    set_stack_guard_size, set_stack_size, stack_guard_size, wasmer_call_trampoline, TrapHandlerFn,
+    VMConfig,
 };
 pub use traphandlers::{init_traps, resume_panic};
 pub use wasmer_types::TrapCode;
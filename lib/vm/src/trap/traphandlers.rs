@@ -8,7 +8,7 @@ use crate::vmcontext::{VMFunctionContext, VMTrampoline};
 use crate::{Trap, VMFunctionBody};
 use backtrace::Backtrace;
 use core::ptr::{read, read_unaligned};
-use corosensei::stack::DefaultStack;
+use corosensei::stack::{DefaultStack, Stack};
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
 use corosensei::{CoroutineResult, ScopedCoroutine, Yielder};
 use scopeguard::defer;
@@ -20,15 +20,18 @@ use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
 use std::ptr::{self, NonNull};
-use std::sync::atomic::{compiler_fence, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::atomic::{compiler_fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
 use std::sync::{Mutex, Once};
 use wasmer_types::TrapCode;
 
 /// Configuration for the the runtime VM
-/// Currently only the stack size is configurable
+/// Currently the stack size and the size of its guard are configurable
 pub struct VMConfig {
     /// Optionnal stack size (in byte) of the VM. Value lower than 8K will be rounded to 8K.
     pub wasm_stack_size: Option<usize>,
+    /// Optional size (in bytes) of the extra guard below the stack (see
+    /// [`set_stack_guard_size`]), instead of the global one.
+    pub wasm_stack_guard_size: Option<usize>,
 }
 
 // TrapInformation can be stored in the "Undefined Instruction" itself.
@@ -38,9 +41,62 @@ static MAGIC: u8 = 0xc0;
 
 static DEFAULT_STACK_SIZE: AtomicUsize = AtomicUsize::new(1024 * 1024);
 
+/// Smallest stack a call into wasm is given.
+const MIN_STACK_SIZE: usize = 8 * 1024;
+
+/// Largest stack a call into wasm is given.
+const MAX_STACK_SIZE: usize = 100 * 1024 * 1024;
+
+/// Stacks for nested calls are rounded down to a multiple of this so the
+/// stack pool doesn't fill up with stacks of slightly different sizes.
+const NESTED_STACK_GRANULARITY: usize = 64 * 1024;
+
 /// Default stack size is 1MB.
 pub fn set_stack_size(size: usize) {
-    DEFAULT_STACK_SIZE.store(size.max(8 * 1024).min(100 * 1024 * 1024), Ordering::Relaxed);
+    DEFAULT_STACK_SIZE.store(
+        size.max(MIN_STACK_SIZE).min(MAX_STACK_SIZE),
+        Ordering::Relaxed,
+    );
+}
+
+/// The stack size used by calls whose `VMConfig` doesn't set one.
+pub fn default_stack_size() -> usize {
+    DEFAULT_STACK_SIZE.load(Ordering::Relaxed)
+}
+
+static STACK_GUARD_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Make `size` bytes (rounded up to whole pages) below the usable part of
+/// every wasm stack inaccessible, on top of the single guard page the stacks
+/// always have. Stacks allocated from then on get the bigger guard, and keep
+/// the full usable size.
+///
+/// A function whose frame is bigger than a page can move the stack pointer
+/// past a single guard page, so overflowing the stack writes to whatever is
+/// mapped below it. A bigger guard makes those overflows hit the guard
+/// instead, which the signal handlers (running on their own `sigaltstack`)
+/// turn into a stack overflow trap.
+///
+/// This does nothing on platforms without `mprotect`.
+pub fn set_stack_guard_size(size: usize) {
+    STACK_GUARD_SIZE.store(round_stack_guard_size(size), Ordering::Relaxed);
+}
+
+/// Rounds a guard size up to whole pages, or to 0 on platforms without
+/// `mprotect`.
+fn round_stack_guard_size(size: usize) -> usize {
+    if cfg!(unix) {
+        let page_size = region::page::size();
+        let size = size.checked_add(page_size - 1).unwrap_or(usize::MAX) / page_size * page_size;
+        size.min(MAX_STACK_SIZE)
+    } else {
+        0
+    }
+}
+
+/// The size of the extra guard below wasm stacks (see [`set_stack_guard_size`]).
+pub fn stack_guard_size() -> usize {
+    STACK_GUARD_SIZE.load(Ordering::Relaxed)
 }
 
 cfg_if::cfg_if! {
@@ -106,51 +162,133 @@ cfg_if::cfg_if! {
         static mut PREV_SIGBUS: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
         static mut PREV_SIGILL: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
         static mut PREV_SIGFPE: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+        static mut PREV_SIGURG: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
 
-        unsafe fn platform_init() {
-            let register = |slot: &mut MaybeUninit<libc::sigaction>, signal: i32| {
-                let mut handler: libc::sigaction = mem::zeroed();
-                // The flags here are relatively careful, and they are...
-                //
-                // SA_SIGINFO gives us access to information like the program
-                // counter from where the fault happened.
-                //
-                // SA_ONSTACK allows us to handle signals on an alternate stack,
-                // so that the handler can run in response to running out of
-                // stack space on the main stack. Rust installs an alternate
-                // stack with sigaltstack, so we rely on that.
-                //
-                // SA_NODEFER allows us to reenter the signal handler if we
-                // crash while handling the signal, and fall through to the
-                // Breakpad handler by testing handlingSegFault.
-                handler.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
-                handler.sa_sigaction = trap_handler as usize;
-                libc::sigemptyset(&mut handler.sa_mask);
-                if libc::sigaction(signal, &handler, slot.as_mut_ptr()) != 0 {
-                    panic!(
-                        "unable to install signal handler: {}",
-                        io::Error::last_os_error(),
-                    );
+        thread_local! {
+            /// The signal this thread is forwarding to the previous handler.
+            static FORWARDED_SIGNAL: Cell<libc::c_int> = Cell::new(0);
+        }
+
+        /// The `si_code` of a SIGBUS raised by a misaligned access.
+        const BUS_ADRALN: libc::c_int = 1;
+
+        /// The fault address without the tag aarch64 allows in its top byte
+        /// (used by memory tagging and pointer authentication), which the
+        /// kernel may leave in `si_addr`.
+        fn untagged(addr: usize) -> usize {
+            if cfg!(target_arch = "aarch64") {
+                addr & 0x00ff_ffff_ffff_ffff
+            } else {
+                addr
+            }
+        }
+
+        /// Serializes the (re)installation of the handlers, so two threads
+        /// finding them replaced don't both take the other's for the previous.
+        static REGISTRATION: Mutex<()> = Mutex::new(());
+
+        /// Whether `platform_init` has installed the handlers.
+        static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+        /// x86 uses SIGFPE to report division by zero.
+        const HANDLE_SIGFPE: bool = cfg!(target_arch = "x86") || cfg!(target_arch = "x86_64");
+
+        /// On ARM, unaligned atomics and accesses past the end of a mapped
+        /// file raise SIGBUS, and on Darwin so do guard page accesses.
+        const HANDLE_SIGBUS: bool = cfg!(target_arch = "arm")
+            || cfg!(target_arch = "aarch64")
+            || cfg!(target_vendor = "apple");
+
+        /// Install `trap_handler` for `signal`, keeping the handler it replaces
+        /// in `slot` to forward the signals that aren't wasm traps to.
+        unsafe fn register(slot: &mut MaybeUninit<libc::sigaction>, signal: i32) {
+            let mut handler: libc::sigaction = mem::zeroed();
+            // The flags here are relatively careful, and they are...
+            //
+            // SA_SIGINFO gives us access to information like the program
+            // counter from where the fault happened.
+            //
+            // SA_ONSTACK allows us to handle signals on an alternate stack,
+            // so that the handler can run in response to running out of
+            // stack space on the main stack. `lazy_per_thread_init` makes
+            // sure every thread entering wasm has one that's big enough.
+            //
+            // SA_NODEFER allows us to reenter the signal handler if we
+            // crash while handling the signal, and fall through to the
+            // Breakpad handler by testing handlingSegFault.
+            handler.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
+            // Interrupts can arrive while the host is in the middle of a
+            // system call, which shouldn't fail because of them.
+            if signal == libc::SIGURG {
+                handler.sa_flags |= libc::SA_RESTART;
+            }
+            handler.sa_sigaction = trap_handler as usize;
+            libc::sigemptyset(&mut handler.sa_mask);
+            if libc::sigaction(signal, &handler, slot.as_mut_ptr()) != 0 {
+                panic!(
+                    "unable to install signal handler: {}",
+                    io::Error::last_os_error(),
+                );
+            }
+        }
+
+        /// Put `trap_handler` back in front of any handler installed over it
+        /// since, e.g. by a library a host function loaded, which then gets
+        /// the signals that aren't wasm traps like the handlers that were
+        /// there before us.
+        ///
+        /// This is called each time wasm is entered, so it only queries the
+        /// handlers unless one was replaced.
+        unsafe fn reinstall_replaced_handlers() {
+            if !INSTALLED.load(Ordering::Acquire) {
+                return;
+            }
+
+            let is_ours = |signal: i32| {
+                let mut current: libc::sigaction = mem::zeroed();
+                libc::sigaction(signal, ptr::null(), &mut current) != 0
+                    || current.sa_sigaction == trap_handler as usize
+            };
+            let reinstall = |slot: &mut MaybeUninit<libc::sigaction>, signal: i32| {
+                if !is_ours(signal) {
+                    let _guard = REGISTRATION.lock().unwrap_or_else(|e| e.into_inner());
+                    if !is_ours(signal) {
+                        register(slot, signal);
+                    }
                 }
             };
 
+            reinstall(&mut PREV_SIGSEGV, libc::SIGSEGV);
+            reinstall(&mut PREV_SIGILL, libc::SIGILL);
+            if HANDLE_SIGFPE {
+                reinstall(&mut PREV_SIGFPE, libc::SIGFPE);
+            }
+            if HANDLE_SIGBUS {
+                reinstall(&mut PREV_SIGBUS, libc::SIGBUS);
+            }
+        }
+
+        unsafe fn platform_init() {
+            let _guard = REGISTRATION.lock().unwrap_or_else(|e| e.into_inner());
+
             // Allow handling OOB with signals on all architectures
             register(&mut PREV_SIGSEGV, libc::SIGSEGV);
 
             // Handle `unreachable` instructions which execute `ud2` right now
             register(&mut PREV_SIGILL, libc::SIGILL);
 
-            // x86 uses SIGFPE to report division by zero
-            if cfg!(target_arch = "x86") || cfg!(target_arch = "x86_64") {
+            if HANDLE_SIGFPE {
                 register(&mut PREV_SIGFPE, libc::SIGFPE);
             }
 
-            // On ARM, handle Unaligned Accesses.
-            // On Darwin, guard page accesses are raised as SIGBUS.
-            if cfg!(target_arch = "arm") || cfg!(target_vendor = "apple") {
+            if HANDLE_SIGBUS {
                 register(&mut PREV_SIGBUS, libc::SIGBUS);
             }
 
+            // Interrupts are sent to the threads running wasm as SIGURG, which
+            // is ignored by default
+            register(&mut PREV_SIGURG, libc::SIGURG);
+
             // This is necessary to support debugging under LLDB on Darwin.
             // For more details see https://github.com/mono/mono/commit/8e75f5a28e6537e56ad70bf870b86e22539c2fb7
             #[cfg(target_vendor = "apple")]
@@ -187,6 +325,8 @@ cfg_if::cfg_if! {
                     MACHINE_THREAD_STATE,
                 );
             }
+
+            INSTALLED.store(true, Ordering::Release);
         }
 
         unsafe extern "C" fn trap_handler(
@@ -194,6 +334,9 @@ cfg_if::cfg_if! {
             siginfo: *mut libc::siginfo_t,
             context: *mut libc::c_void,
         ) {
+            if signum == libc::SIGURG {
+                return interrupt_handler(signum, siginfo, context);
+            }
             let previous = match signum {
                 libc::SIGSEGV => &PREV_SIGSEGV,
                 libc::SIGBUS => &PREV_SIGBUS,
@@ -204,7 +347,7 @@ cfg_if::cfg_if! {
             // We try to get the fault address associated to this signal
             let maybe_fault_address = match signum {
                 libc::SIGSEGV | libc::SIGBUS => {
-                    Some((*siginfo).si_addr() as usize)
+                    Some(untagged((*siginfo).si_addr() as usize))
                 }
                 _ => None,
             };
@@ -214,6 +357,15 @@ cfg_if::cfg_if! {
                     let addr = (*siginfo).si_addr() as usize;
                     process_illegal_op(addr)
                 }
+                // Atomics are the only misaligned accesses that fault on
+                // ARM. Linux reports them as such, while the other codes
+                // (BUS_ADRERR, and the memory errors of BUS_MCEERR_*) are
+                // classified by the fault address like SIGSEGV.
+                libc::SIGBUS if cfg!(any(target_os = "linux", target_os = "android"))
+                    && (*siginfo).si_code == BUS_ADRALN =>
+                {
+                    Some(TrapCode::UnalignedAtomic)
+                }
                 _ => None,
             };
             let ucontext = &mut *(context as *mut libc::ucontext_t);
@@ -240,16 +392,75 @@ cfg_if::cfg_if! {
             // it. It will either crash synchronously, fix up the instruction
             // so that execution can continue and return, or trigger a crash by
             // returning the signal to it's original disposition and returning.
+            //
+            // A handler installed over ours and then put behind it by
+            // `reinstall_replaced_handlers` may forward the signal straight
+            // back to us, in which case we crash with the default disposition
+            // rather than going around in circles.
+            let forwarding = FORWARDED_SIGNAL.with(|cell| cell.replace(signum));
+            defer! {
+                FORWARDED_SIGNAL.with(|cell| cell.set(forwarding));
+            }
             let previous = &*previous.as_ptr();
+            // Faults raised by the kernel (a positive `si_code`) can't be
+            // ignored either, since returning re-executes them forever.
+            if forwarding == signum
+                || previous.sa_sigaction == libc::SIG_DFL
+                || (previous.sa_sigaction == libc::SIG_IGN && (*siginfo).si_code > 0)
+            {
+                let mut default: libc::sigaction = mem::zeroed();
+                default.sa_sigaction = libc::SIG_DFL;
+                libc::sigaction(signum, &default, ptr::null_mut());
+            } else if previous.sa_sigaction != libc::SIG_IGN {
+                if previous.sa_flags & libc::SA_SIGINFO != 0 {
+                    mem::transmute::<
+                        usize,
+                        extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void),
+                    >(previous.sa_sigaction)(signum, siginfo, context)
+                } else {
+                    mem::transmute::<usize, extern "C" fn(libc::c_int)>(
+                        previous.sa_sigaction
+                    )(signum)
+                }
+            }
+        }
+
+        unsafe fn interrupt_handler(
+            signum: libc::c_int,
+            siginfo: *mut libc::siginfo_t,
+            context: *mut libc::c_void,
+        ) {
+            let ucontext = &mut *(context as *mut libc::ucontext_t);
+            let (pc, sp) = get_pc_sp(ucontext);
+            // Calls are only stopped from inside compiled wasm code, which has
+            // nothing to clean up. Anywhere else, the interrupt is left to the
+            // next signal or to the host function returning.
+            if crate::interrupt::is_interrupted() && crate::interrupt::is_wasm_pc(pc) {
+                let handled = TrapHandlerContext::handle_trap(
+                    pc,
+                    sp,
+                    None,
+                    Some(TrapCode::Interrupted),
+                    |regs| update_context(ucontext, regs),
+                    |_| false,
+                );
+                if handled {
+                    return;
+                }
+            }
+
+            // Otherwise the signal is passed on to any previous handler. Unlike
+            // the other signals, the disposition is never reset since SIGURG is
+            // ignored by default.
+            let previous = &*PREV_SIGURG.as_ptr();
             if previous.sa_flags & libc::SA_SIGINFO != 0 {
                 mem::transmute::<
                     usize,
                     extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void),
                 >(previous.sa_sigaction)(signum, siginfo, context)
-            } else if previous.sa_sigaction == libc::SIG_DFL
+            } else if previous.sa_sigaction != libc::SIG_DFL
+                && previous.sa_sigaction != libc::SIG_IGN
             {
-                libc::sigaction(signum, previous, ptr::null_mut());
-            } else if previous.sa_sigaction != libc::SIG_IGN {
                 mem::transmute::<usize, extern "C" fn(libc::c_int)>(
                     previous.sa_sigaction
                 )(signum)
@@ -434,6 +645,10 @@ cfg_if::cfg_if! {
         use winapi::um::minwinbase::*;
         use winapi::vc::excpt::*;
 
+        /// Vectored exception handlers are added to a list rather than
+        /// replacing each other, so ours stays installed.
+        unsafe fn reinstall_replaced_handlers() {}
+
         unsafe fn platform_init() {
             // our trap handler needs to go first, so that we can recover from
             // wasm faults and continue execution, so pass `1` as a true value
@@ -485,6 +700,8 @@ cfg_if::cfg_if! {
                 EXCEPTION_ILLEGAL_INSTRUCTION => {
                     process_illegal_op(pc)
                 }
+                // Raised when the guard page below the stack is touched
+                EXCEPTION_STACK_OVERFLOW => Some(TrapCode::StackOverflow),
                 _ => None,
             };
             // This is basically the same as the unix version above, only with a
@@ -647,12 +864,67 @@ pub unsafe fn catch_traps<F, R>(
 where
     F: FnOnce() -> R,
 {
-    // Ensure that per-thread initialization is done.
+    // Ensure that per-thread initialization is done, and that our signal
+    // handlers haven't been replaced since the last call.
     lazy_per_thread_init()?;
-    let stack_size = config
+    reinstall_replaced_handlers();
+    let limit = config
         .wasm_stack_size
-        .unwrap_or_else(|| DEFAULT_STACK_SIZE.load(Ordering::Relaxed));
-    on_wasm_stack(stack_size, trap_handler, closure).map_err(UnwindReason::into_trap)
+        .unwrap_or_else(default_stack_size)
+        .max(MIN_STACK_SIZE)
+        .min(MAX_STACK_SIZE);
+    // A call made by a host function that was itself called from wasm only
+    // gets what's left of the outer call's stack, minus the host frames in
+    // between, so re-entrant calls can't add up to more than the limit.
+    let stack_size = match STACK_BUDGET.with(Cell::get) {
+        Some(budget) => {
+            let host_used = budget.host_sp.saturating_sub(stack_pointer());
+            let remaining = budget.remaining.saturating_sub(host_used);
+            remaining.min(limit) / NESTED_STACK_GRANULARITY * NESTED_STACK_GRANULARITY
+        }
+        None => limit,
+    };
+    if stack_size < MIN_STACK_SIZE {
+        return Err(Trap::lib(TrapCode::StackOverflow));
+    }
+    let guard_size = config
+        .wasm_stack_guard_size
+        .map_or_else(stack_guard_size, round_stack_guard_size);
+    on_wasm_stack(stack_size, guard_size, trap_handler, closure).map_err(UnwindReason::into_trap)
+}
+
+/// The stack left to calls into wasm made from the host function that's
+/// currently running, if it was called from wasm.
+#[derive(Clone, Copy)]
+struct StackBudget {
+    /// Bytes left as of `host_sp`.
+    remaining: usize,
+    /// The host stack pointer when switching back from the wasm stack.
+    host_sp: usize,
+}
+
+/// The wasm stack that's currently running on this thread.
+#[derive(Clone, Copy)]
+struct WasmStack {
+    /// The top of the stack, where wasm frames start.
+    base: usize,
+    /// The number of bytes wasm is allowed to use.
+    size: usize,
+    /// The bottom of the guard page below the stack, which corosensei
+    /// doesn't count as part of it.
+    guard_start: usize,
+}
+
+thread_local! {
+    static WASM_STACK: Cell<Option<WasmStack>> = Cell::new(None);
+    static STACK_BUDGET: Cell<Option<StackBudget>> = Cell::new(None);
+}
+
+/// Approximates the current stack pointer with the address of a local.
+#[inline(never)]
+fn stack_pointer() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
 }
 
 // We need two separate thread-local variables here:
@@ -781,15 +1053,25 @@ impl<T> TrapHandlerContextInner<T> {
         trap_code: Option<TrapCode>,
         update_regs: &mut dyn FnMut(TrapHandlerRegs),
     ) -> bool {
+        // Addresses in the guard page at the bottom of the stack count as on
+        // it: a function allocating a big frame moves the stack pointer there
+        // before probing it.
+        let on_stack = |addr: usize| {
+            self.coro_trap_handler.stack_ptr_in_bounds(addr)
+                || WASM_STACK.with(Cell::get).map_or(false, |stack| {
+                    (stack.guard_start..stack.base).contains(&addr)
+                })
+        };
+
         // Check if this trap occurred while executing on the Wasm stack. We can
         // only recover from traps if that is the case.
-        if !self.coro_trap_handler.stack_ptr_in_bounds(sp) {
+        if !on_stack(sp) {
             return false;
         }
 
         let signal_trap = trap_code.or_else(|| {
             maybe_fault_address.map(|addr| {
-                if self.coro_trap_handler.stack_ptr_in_bounds(addr) {
+                if on_stack(addr) {
                     TrapCode::StackOverflow
                 } else {
                     TrapCode::HeapAccessOutOfBounds
@@ -865,11 +1147,27 @@ unsafe fn unwind_with(reason: UnwindReason) -> ! {
     unreachable!();
 }
 
+/// Allocate a stack with `stack_size` usable bytes, and `guard_size` bytes
+/// below them which fault when accessed.
+fn new_wasm_stack(stack_size: usize, guard_size: usize) -> DefaultStack {
+    let stack = DefaultStack::new(stack_size + guard_size).unwrap();
+    #[cfg(unix)]
+    if guard_size > 0 {
+        // The stack grows down towards its limit, which is just above the
+        // guard page `DefaultStack` always has
+        let limit = stack.limit().get() as *mut libc::c_void;
+        let r = unsafe { libc::mprotect(limit, guard_size, libc::PROT_NONE) };
+        assert_eq!(r, 0, "mprotect to configure the stack guard failed");
+    }
+    stack
+}
+
 /// Runs the given function on a separate stack so that its stack usage can be
 /// bounded. Stack overflows and other traps can be caught and execution
 /// returned to the root of the stack.
 fn on_wasm_stack<F: FnOnce() -> T, T>(
     stack_size: usize,
+    guard_size: usize,
     trap_handler: Option<*const TrapHandlerFn<'static>>,
     f: F,
 ) -> Result<T, UnwindReason> {
@@ -877,15 +1175,44 @@ fn on_wasm_stack<F: FnOnce() -> T, T>(
     // system calls. We therefore keep a cache of pre-allocated stacks which
     // allows them to be reused multiple times.
     // FIXME(Amanieu): We should refactor this to avoid the lock.
+    //
+    // Stacks are keyed by the size they were requested with, so a call never
+    // runs on a stack bigger than its limit, and by the size of their guard.
     lazy_static::lazy_static! {
-        static ref STACK_POOL: Mutex<Vec<DefaultStack>> = Mutex::new(vec![]);
+        static ref STACK_POOL: Mutex<Vec<(usize, usize, DefaultStack)>> = Mutex::new(vec![]);
+    }
+    let stack = {
+        let mut pool = STACK_POOL.lock().unwrap();
+        match pool
+            .iter()
+            .rposition(|(size, guard, _)| *size == stack_size && *guard == guard_size)
+        {
+            Some(index) => pool.swap_remove(index).2,
+            None => {
+                drop(pool);
+                new_wasm_stack(stack_size, guard_size)
+            }
+        }
+    };
+    let mut stack = scopeguard::guard(stack, |stack| {
+        STACK_POOL
+            .lock()
+            .unwrap()
+            .push((stack_size, guard_size, stack))
+    });
+
+    // Record where the stack starts so host functions called from it can work
+    // out how much of it is left.
+    let previous_stack = WASM_STACK.with(|cell| {
+        cell.replace(Some(WasmStack {
+            base: stack.base().get(),
+            size: stack_size,
+            guard_start: stack.limit().get() - region::page::size(),
+        }))
+    });
+    defer! {
+        WASM_STACK.with(|cell| cell.set(previous_stack));
     }
-    let stack = STACK_POOL
-        .lock()
-        .unwrap()
-        .pop()
-        .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
-    let mut stack = scopeguard::guard(stack, |stack| STACK_POOL.lock().unwrap().push(stack));
 
     // Create a coroutine with a new stack to run the function on.
     let mut coro = ScopedCoroutine::with_stack(&mut *stack, move |yielder, ()| {
@@ -942,6 +1269,14 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
         YIELDER.with(|cell| cell.set(yielder_ptr));
     }
 
+    // Whatever wasm hasn't used of its stack is left for calls back into wasm
+    // made by the host.
+    let wasm_remaining = WASM_STACK.with(Cell::get).map(|stack| {
+        stack
+            .size
+            .saturating_sub(stack.base.saturating_sub(stack_pointer()))
+    });
+
     // on_parent_stack requires the closure to be Send so that the Yielder
     // cannot be called from the parent stack. This is not a problem for us
     // since we don't expose the Yielder.
@@ -950,6 +1285,15 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
     let wrapped = SendWrapper(f);
     yielder.on_parent_stack(move || {
         let wrapped = wrapped;
+        let previous_budget = STACK_BUDGET.with(|cell| {
+            cell.replace(wasm_remaining.map(|remaining| StackBudget {
+                remaining,
+                host_sp: stack_pointer(),
+            }))
+        });
+        defer! {
+            STACK_BUDGET.with(|cell| cell.set(previous_budget));
+        }
         (wrapped.0)()
     })
 }
@@ -1067,6 +1411,23 @@ pub fn lazy_per_thread_init() -> Result<(), Trap> {
                 _ => return,
             };
             unsafe {
+                // Stop using the stack before deallocating it (unless another
+                // one was registered since), so signals raised by the TLS
+                // destructors running after this one don't run on freed
+                // memory.
+                let mut current: libc::stack_t = mem::zeroed();
+                let r = libc::sigaltstack(ptr::null(), &mut current);
+                if r == 0
+                    && current.ss_sp == (ptr as usize + region::page::size()) as *mut libc::c_void
+                {
+                    let disabled = libc::stack_t {
+                        ss_sp: ptr::null_mut(),
+                        ss_flags: libc::SS_DISABLE,
+                        ss_size: MIN_STACK_SIZE,
+                    };
+                    libc::sigaltstack(&disabled, ptr::null_mut());
+                }
+
                 // Deallocate the stack memory.
                 let r = libc::munmap(ptr, size);
                 debug_assert_eq!(r, 0, "munmap failed during thread shutdown");
@@ -316,17 +316,14 @@ mod test_vmglobal_import {
 /// caller's responsibility to synchronize.
 pub(crate) unsafe fn memory_copy(
     mem: &VMMemoryDefinition,
-    dst: u32,
-    src: u32,
-    len: u32,
+    dst: u64,
+    src: u64,
+    len: u64,
 ) -> Result<(), Trap> {
     // https://webassembly.github.io/reference-types/core/exec/instructions.html#exec-memory-copy
-    if src
-        .checked_add(len)
-        .map_or(true, |n| usize::try_from(n).unwrap() > mem.current_length)
-        || dst
-            .checked_add(len)
-            .map_or(true, |m| usize::try_from(m).unwrap() > mem.current_length)
+    let current_length = mem.current_length as u64;
+    if src.checked_add(len).map_or(true, |n| n > current_length)
+        || dst.checked_add(len).map_or(true, |m| m > current_length)
     {
         return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
     }
@@ -355,13 +352,13 @@ pub(crate) unsafe fn memory_copy(
 /// caller's responsibility to synchronize.
 pub(crate) unsafe fn memory_fill(
     mem: &VMMemoryDefinition,
-    dst: u32,
+    dst: u64,
     val: u32,
-    len: u32,
+    len: u64,
 ) -> Result<(), Trap> {
     if dst
         .checked_add(len)
-        .map_or(true, |m| usize::try_from(m).unwrap() > mem.current_length)
+        .map_or(true, |m| m > mem.current_length as u64)
     {
         return Err(Trap::lib(TrapCode::HeapAccessOutOfBounds));
     }
@@ -709,6 +706,23 @@ impl VMBuiltinFunctionsArray {
             wasmer_vm_memory32_atomic_notify as usize;
         ptrs[VMBuiltinFunctionIndex::get_imported_memory_atomic_notify_index().index() as usize] =
             wasmer_vm_imported_memory32_atomic_notify as usize;
+        ptrs[VMBuiltinFunctionIndex::get_lazy_compile_index().index() as usize] =
+            wasmer_vm_lazy_compile as usize;
+        ptrs[VMBuiltinFunctionIndex::get_throw_index().index() as usize] = wasmer_vm_throw as usize;
+        ptrs[VMBuiltinFunctionIndex::get_rethrow_index().index() as usize] =
+            wasmer_vm_rethrow as usize;
+        ptrs[VMBuiltinFunctionIndex::get_exception_drop_index().index() as usize] =
+            wasmer_vm_exception_drop as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_copy_index().index() as usize] =
+            wasmer_vm_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_copy_index().index() as usize] =
+            wasmer_vm_imported_memory64_copy as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_fill_index().index() as usize] =
+            wasmer_vm_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_imported_memory64_fill_index().index() as usize] =
+            wasmer_vm_imported_memory64_fill as usize;
+        ptrs[VMBuiltinFunctionIndex::get_memory64_init_index().index() as usize] =
+            wasmer_vm_memory64_init as usize;
 
         debug_assert!(ptrs.iter().cloned().all(|p| p != 0));
 
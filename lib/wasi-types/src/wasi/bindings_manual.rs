@@ -342,7 +342,7 @@ impl From<std::io::Error> for Errno {
             ErrorKind::InvalidInput => Errno::Io,
             ErrorKind::InvalidData => Errno::Io,
             ErrorKind::TimedOut => Errno::Timedout,
-            ErrorKind::WriteZero => Errno::Io,
+            ErrorKind::WriteZero => Errno::Nospc,
             ErrorKind::Interrupted => Errno::Intr,
             ErrorKind::Other => Errno::Io,
             ErrorKind::UnexpectedEof => Errno::Io,
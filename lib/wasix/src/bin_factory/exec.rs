@@ -95,7 +95,17 @@ pub fn spawn_exec_module(
 
         // Determine if we are going to create memory and import it or just rely on self creation of memory
         let memory_spawn = match shared_memory {
-            Some(ty) => SpawnMemoryType::CreateMemoryOfType(ty),
+            Some(ty) => {
+                let ty = env
+                    .capabilities
+                    .threading
+                    .shared_memory_type(ty)
+                    .map_err(|err| {
+                        error!("wasi[{}]::invalid shared memory size - {}", pid, err);
+                        SpawnError::MemoryAllocationFailed
+                    })?;
+                SpawnMemoryType::CreateMemoryOfType(ty)
+            }
             None => SpawnMemoryType::CreateMemory,
         };
 
@@ -169,6 +179,7 @@ fn call_module(
     let env = ctx.data(&store);
     let pid = env.pid();
     let tasks = env.tasks().clone();
+    let _span = env.thread_span().entered();
     handle.thread.set_status_running();
 
     // If we need to rewind then do so
@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+use wasmer::{MemoryError, MemoryType, Pages, WASM_PAGE_SIZE};
+
 use crate::http::HttpClientCapabilityV1;
 
 /// Defines capabilities for a Wasi environment.
@@ -6,6 +10,8 @@ pub struct Capabilities {
     pub insecure_allow_all: bool,
     pub http_client: HttpClientCapabilityV1,
     pub threading: CapabilityThreadingV1,
+    pub syscalls: CapabilitySyscallsV1,
+    pub signals: CapabilitySignalsV1,
 }
 
 impl Capabilities {
@@ -14,6 +20,8 @@ impl Capabilities {
             insecure_allow_all: false,
             http_client: Default::default(),
             threading: Default::default(),
+            syscalls: Default::default(),
+            signals: Default::default(),
         }
     }
 
@@ -24,10 +32,14 @@ impl Capabilities {
             insecure_allow_all,
             http_client,
             threading,
+            syscalls,
+            signals,
         } = other;
         self.insecure_allow_all |= insecure_allow_all;
         self.http_client.update(http_client);
         self.threading.update(threading);
+        self.syscalls.update(syscalls);
+        self.signals.update(signals);
     }
 }
 
@@ -45,18 +57,157 @@ pub struct CapabilityThreadingV1 {
     /// [`None`] means no limit.
     pub max_threads: Option<usize>,
 
+    /// Maximum number of threads a single process can have running at once,
+    /// not counting its main thread.
+    ///
+    /// [`None`] means no limit.
+    pub max_threads_per_process: Option<usize>,
+
     /// Flag that indicates if asynchronous threading is disabled
     /// (default = false)
     pub enable_asynchronous_threading: bool,
+
+    /// How large, in bytes, the shared memory a module imports may grow.
+    ///
+    /// Threads' stacks are allocated by the module inside this memory, so
+    /// they come out of the same budget.
+    ///
+    /// [`None`] means the maximum the module declares is used.
+    pub shared_memory_size: Option<u64>,
 }
 
 impl CapabilityThreadingV1 {
     pub fn update(&mut self, other: CapabilityThreadingV1) {
         let CapabilityThreadingV1 {
             max_threads,
+            max_threads_per_process,
             enable_asynchronous_threading,
+            shared_memory_size,
         } = other;
         self.enable_asynchronous_threading |= enable_asynchronous_threading;
         self.max_threads = max_threads.or(self.max_threads);
+        self.max_threads_per_process = max_threads_per_process.or(self.max_threads_per_process);
+        self.shared_memory_size = shared_memory_size.or(self.shared_memory_size);
+    }
+
+    /// The type of the shared memory to create for a module which imports a
+    /// memory of type `ty`, with its maximum set to
+    /// [`CapabilityThreadingV1::shared_memory_size`].
+    ///
+    /// The size is rounded down to a whole number of pages, and has to fit
+    /// between the minimum and maximum the module declares.
+    pub fn shared_memory_type(&self, mut ty: MemoryType) -> Result<MemoryType, MemoryError> {
+        let size = match self.shared_memory_size {
+            Some(size) => size,
+            None => return Ok(ty),
+        };
+
+        let pages = u32::try_from(size / WASM_PAGE_SIZE as u64)
+            .map(Pages)
+            .unwrap_or(Pages(u32::MAX));
+
+        if let Some(maximum) = ty.maximum {
+            if pages > maximum {
+                return Err(MemoryError::MaximumMemoryTooLarge {
+                    max_requested: pages,
+                    max_allowed: maximum,
+                });
+            }
+        }
+        if pages < ty.minimum {
+            return Err(MemoryError::MinimumMemoryTooLarge {
+                min_requested: ty.minimum,
+                max_allowed: pages,
+            });
+        }
+
+        ty.maximum = Some(pages);
+        Ok(ty)
+    }
+}
+
+/// Defines which WASI and WASIX syscalls a module may call.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilitySyscallsV1 {
+    /// The names of the syscalls the module is allowed to call (e.g.
+    /// `fd_write`). Any other syscall fails with [`Errno::Nosys`], although
+    /// `proc_exit` is always allowed so the module can still exit.
+    ///
+    /// [`None`] means every syscall is allowed.
+    ///
+    /// [`Errno::Nosys`]: wasmer_wasix_types::wasi::Errno::Nosys
+    pub allowlist: Option<BTreeSet<String>>,
+}
+
+impl CapabilitySyscallsV1 {
+    /// Check whether the syscall called `name` may be called.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        match &self.allowlist {
+            Some(allowlist) => name == "proc_exit" || allowlist.contains(name),
+            None => true,
+        }
+    }
+
+    pub fn update(&mut self, other: CapabilitySyscallsV1) {
+        let CapabilitySyscallsV1 { allowlist } = other;
+        self.allowlist = allowlist.or(self.allowlist.take());
+    }
+}
+
+/// Defines what the signals a module raises may do outside of it.
+#[derive(Debug, Default, Clone)]
+pub struct CapabilitySignalsV1 {
+    /// Whether the signals a module raises with `proc_raise` (and the timer
+    /// signals it sets up with `proc_raise_interval`) are also raised in the
+    /// host process, which gets e.g. killed by a `SIGTERM` as a result.
+    ///
+    /// Only supported on Unix, and only signals the host has an equivalent
+    /// of are forwarded.
+    pub forward_to_host: bool,
+}
+
+impl CapabilitySignalsV1 {
+    pub fn update(&mut self, other: CapabilitySignalsV1) {
+        let CapabilitySignalsV1 { forward_to_host } = other;
+        self.forward_to_host |= forward_to_host;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threading(shared_memory_size: u64) -> CapabilityThreadingV1 {
+        CapabilityThreadingV1 {
+            shared_memory_size: Some(shared_memory_size),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shared_memory_size_sets_the_maximum() {
+        let ty = MemoryType::new(1, Some(65536), true);
+
+        let ty = threading(256 * 1024 * 1024).shared_memory_type(ty).unwrap();
+
+        assert_eq!(ty.maximum, Some(Pages(4096)));
+        assert_eq!(ty.minimum, Pages(1));
+    }
+
+    #[test]
+    fn shared_memory_size_is_checked_against_the_module() {
+        let ty = MemoryType::new(16, Some(1024), true);
+
+        let too_large = threading(1024 * 1024 * 1024).shared_memory_type(ty);
+        let too_small = threading(WASM_PAGE_SIZE as u64).shared_memory_type(ty);
+
+        assert!(matches!(
+            too_large,
+            Err(MemoryError::MaximumMemoryTooLarge { .. })
+        ));
+        assert!(matches!(
+            too_small,
+            Err(MemoryError::MinimumMemoryTooLarge { .. })
+        ));
     }
 }
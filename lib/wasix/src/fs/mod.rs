@@ -1677,6 +1677,33 @@ impl WasiFs {
         Ok(())
     }
 
+    /// Put one end of a pipe at the file descriptor `fd`, which mustn't be
+    /// in use. `rights` decides whether it can be read from, written to, or
+    /// both.
+    pub fn create_pipe_fd(
+        &self,
+        inodes: &WasiInodes,
+        fd: WasiFd,
+        pipe: virtual_fs::Pipe,
+        rights: Rights,
+    ) -> Result<(), FsError> {
+        if self.fd_map.read().unwrap().contains_key(&fd) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let inode = self.create_inode_with_default_stat(
+            inodes,
+            Kind::Pipe { pipe },
+            false,
+            "pipe".to_string().into(),
+        );
+        self.create_fd_ext(rights, rights, Fdflags::empty(), 0, inode, fd)
+            .map_err(fs_error_from_wasi_err)?;
+        // Files opened later mustn't reuse the descriptor
+        self.next_fd.fetch_max(fd + 1, Ordering::SeqCst);
+        Ok(())
+    }
+
     pub fn clone_fd(&self, fd: WasiFd) -> Result<WasiFd, Errno> {
         let fd = self.get_fd(fd)?;
         let idx = self.next_fd.fetch_add(1, Ordering::SeqCst);
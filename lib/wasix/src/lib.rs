@@ -64,8 +64,8 @@ pub use wasmer;
 pub use wasmer_wasix_types;
 
 use wasmer::{
-    imports, namespace, AsStoreMut, Exports, FunctionEnv, Imports, Memory32, MemoryAccessError,
-    MemorySize, RuntimeError,
+    imports, namespace, AsStoreMut, Exports, Extern, Function, FunctionEnv, Imports, Memory32,
+    MemoryAccessError, MemorySize, RuntimeError, Type, Value,
 };
 
 pub use virtual_fs;
@@ -271,19 +271,25 @@ impl Clone for WasiVFork {
 /// Create an [`Imports`] with an existing [`WasiEnv`]. `WasiEnv`
 /// needs a [`WasiState`], that can be constructed from a
 /// [`WasiEnvBuilder`](state::WasiEnvBuilder).
+///
+/// The result is a [`wasmer::Resolver`], so it can be chained with other
+/// imports (e.g. to override individual syscalls) using
+/// [`Imports::chain_front`] and [`Imports::chain_back`].
 pub fn generate_import_object_from_env(
     store: &mut impl AsStoreMut,
     ctx: &FunctionEnv<WasiEnv>,
     version: WasiVersion,
 ) -> Imports {
-    match version {
+    let mut imports = match version {
         WasiVersion::Snapshot0 => generate_import_object_snapshot0(store, ctx),
         WasiVersion::Snapshot1 | WasiVersion::Latest => {
             generate_import_object_snapshot1(store, ctx)
         }
         WasiVersion::Wasix32v1 => generate_import_object_wasix32_v1(store, ctx),
         WasiVersion::Wasix64v1 => generate_import_object_wasix64_v1(store, ctx),
-    }
+    };
+    restrict_syscalls(store, ctx, &mut imports);
+    imports
 }
 
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
@@ -667,8 +673,6 @@ fn import_object_for_all_wasi_versions(
     let exports_wasix_32v1 = wasix_exports_32(store, env);
     let exports_wasix_64v1 = wasix_exports_64(store, env);
 
-    // Allowed due to JS feature flag complications.
-    #[allow(unused_mut)]
     let mut imports = imports! {
         "wasi" => exports_wasi_generic,
         "wasi_unstable" => exports_wasi_unstable,
@@ -676,6 +680,7 @@ fn import_object_for_all_wasi_versions(
         "wasix_32v1" => exports_wasix_32v1,
         "wasix_64v1" => exports_wasix_64v1,
     };
+    restrict_syscalls(store, env, &mut imports);
 
     // TODO: clean this up!
     cfg_if::cfg_if! {
@@ -708,6 +713,59 @@ fn import_object_for_all_wasi_versions(
     (imports, init)
 }
 
+/// The namespaces WASI and WASIX syscalls are imported from.
+const SYSCALL_NAMESPACES: &[&str] = &[
+    "wasi",
+    "wasi_unstable",
+    "wasi_snapshot_preview1",
+    "wasix_32v1",
+    "wasix_64v1",
+];
+
+/// Replace every syscall which isn't in the environment's
+/// [allowlist](capabilities::CapabilitySyscallsV1) with a stub that fails
+/// with [`Errno::Nosys`].
+fn restrict_syscalls(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    imports: &mut Imports,
+) {
+    let syscalls = env.as_ref(store).capabilities.syscalls.clone();
+    if syscalls.allowlist.is_none() {
+        return;
+    }
+
+    let denied: Vec<_> = imports
+        .iter()
+        .filter(|(ns, name, _)| SYSCALL_NAMESPACES.contains(ns) && !syscalls.is_allowed(name))
+        .filter_map(|(ns, name, extern_)| match extern_ {
+            Extern::Function(f) => Some((ns.to_string(), name.to_string(), f.ty(store))),
+            _ => None,
+        })
+        .collect();
+
+    for (ns, name, ty) in denied {
+        let results = ty.results().to_vec();
+        let syscall = name.clone();
+        let stub = Function::new(store, ty, move |_args| {
+            tracing::debug!(%syscall, "blocked a syscall which isn't in the allowlist");
+            Ok(results
+                .iter()
+                .map(|ty| match ty {
+                    Type::I32 => Value::I32(Errno::Nosys as i32),
+                    Type::I64 => Value::I64(0),
+                    Type::F32 => Value::F32(0.0),
+                    Type::F64 => Value::F64(0.0),
+                    Type::V128 => Value::V128(0),
+                    Type::ExternRef => Value::ExternRef(None),
+                    Type::FuncRef => Value::FuncRef(None),
+                })
+                .collect())
+        });
+        imports.define(&ns, &name, stub);
+    }
+}
+
 /// Combines a state generating function with the import list for legacy WASI
 fn generate_import_object_snapshot0(
     store: &mut impl AsStoreMut,
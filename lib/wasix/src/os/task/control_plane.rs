@@ -39,6 +39,9 @@ impl WasiControlPlaneHandle {
 pub struct ControlPlaneConfig {
     /// Total number of tasks (processes + threads) that can be spawned.
     pub max_task_count: Option<usize>,
+    /// Number of threads (excluding the main thread) that a single process
+    /// can have running at once.
+    pub max_threads_per_process: Option<usize>,
     /// Flag that indicates if asynchronous threading is enables (opt-in)
     pub enable_asynchronous_threading: bool,
 }
@@ -47,6 +50,7 @@ impl ControlPlaneConfig {
     pub fn new() -> Self {
         Self {
             max_task_count: None,
+            max_threads_per_process: None,
             enable_asynchronous_threading: false,
         }
     }
@@ -121,6 +125,10 @@ impl WasiControlPlane {
     }
 
     /// Creates a new process
+    ///
+    /// Process IDs are only unique within this control plane and start at 1,
+    /// so the first process (the one a runner starts) is PID 1 with a parent
+    /// of 0, rather than getting the host's PID.
     // FIXME: De-register terminated processes!
     // Currently they just accumulate.
     pub fn new_process(&self) -> Result<WasiProcess, ControlPlaneError> {
@@ -199,6 +207,12 @@ pub enum ControlPlaneError {
         /// The maximum number of tasks.
         max: usize,
     },
+    /// A process has reached the maximum number of threads it may run.
+    #[error("The maximum number of threads per process has been reached ({max})")]
+    ThreadLimitReached {
+        /// The maximum number of threads.
+        max: usize,
+    },
 }
 
 #[cfg(test)]
@@ -210,6 +224,7 @@ mod tests {
     fn test_control_plane_task_limits() {
         let p = WasiControlPlane::new(ControlPlaneConfig {
             max_task_count: Some(2),
+            max_threads_per_process: None,
             enable_asynchronous_threading: false,
         });
 
@@ -228,6 +243,7 @@ mod tests {
     fn test_control_plane_task_limits_with_dropped_threads() {
         let p = WasiControlPlane::new(ControlPlaneConfig {
             max_task_count: Some(2),
+            max_threads_per_process: None,
             enable_asynchronous_threading: false,
         });
 
@@ -245,4 +261,52 @@ mod tests {
             ControlPlaneError::TaskLimitReached { max: 2 }
         );
     }
+
+    /// Ensure the per-process thread limit ignores the main thread and is
+    /// released when threads exit.
+    #[test]
+    fn test_control_plane_thread_limits() {
+        let p = WasiControlPlane::new(ControlPlaneConfig {
+            max_task_count: None,
+            max_threads_per_process: Some(2),
+            enable_asynchronous_threading: false,
+        });
+
+        let p1 = p.new_process().unwrap();
+        let _main = p1.new_thread().unwrap();
+        let t1 = p1.new_thread().unwrap();
+        let _t2 = p1.new_thread().unwrap();
+
+        assert_eq!(
+            p1.new_thread().unwrap_err(),
+            ControlPlaneError::ThreadLimitReached { max: 2 }
+        );
+
+        // Other processes have their own limit
+        let p2 = p.new_process().unwrap();
+        let _p2_main = p2.new_thread().unwrap();
+        let _p2_t1 = p2.new_thread().unwrap();
+
+        drop(t1);
+        let _t3 = p1.new_thread().unwrap();
+    }
+
+    /// Ensure processes are numbered within their control plane, so the
+    /// first one always sees itself as PID 1.
+    #[test]
+    fn test_control_plane_process_ids() {
+        let p = WasiControlPlane::new(ControlPlaneConfig::new());
+
+        let p1 = p.new_process().unwrap();
+        let _main = p1.new_thread().unwrap();
+        assert_eq!(p1.pid(), WasiProcessId::from(1u32));
+        assert_eq!(p1.ppid(), WasiProcessId::from(0u32));
+
+        // Other control planes have their own numbering
+        let other = WasiControlPlane::new(ControlPlaneConfig::new());
+        assert_eq!(
+            other.new_process().unwrap().pid(),
+            WasiProcessId::from(1u32)
+        );
+    }
 }
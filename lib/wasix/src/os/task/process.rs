@@ -179,13 +179,22 @@ impl WasiProcess {
     /// Creates a a thread and returns it
     pub fn new_thread(&self) -> Result<WasiThreadHandle, ControlPlaneError> {
         let control_plane = self.compute.must_upgrade();
-        let task_count_guard = control_plane.register_task()?;
+
+        // Hold the lock until the thread is inserted so concurrent spawns
+        // can't exceed the per-process limit
+        let mut inner = self.inner.write().unwrap();
 
         // Determine if its the main thread or not
-        let is_main = {
-            let inner = self.inner.read().unwrap();
-            inner.thread_count == 0
-        };
+        let is_main = inner.thread_count == 0;
+
+        if let Some(max) = control_plane.config().max_threads_per_process {
+            // The main thread doesn't count towards the limit
+            if !is_main && inner.thread_count as usize > max {
+                return Err(ControlPlaneError::ThreadLimitReached { max });
+            }
+        }
+
+        let task_count_guard = control_plane.register_task()?;
 
         // Generate a new process ID (this is because the process ID and thread ID
         // address space must not overlap in libc). For the main proecess the TID=PID
@@ -197,7 +206,6 @@ impl WasiProcess {
         };
 
         // The wait finished should be the process version if its the main thread
-        let mut inner = self.inner.write().unwrap();
         let finished = if is_main {
             self.finished.clone()
         } else {
@@ -26,3 +26,102 @@ pub struct WasiSignalInterval {
     /// Last time that a signal was triggered
     pub last_signal: u128,
 }
+
+/// The host's number for `signal`, if the host has it.
+#[cfg(unix)]
+pub fn host_signal(signal: Signal) -> Option<libc::c_int> {
+    let host = match signal {
+        Signal::Signone => return None,
+        Signal::Sighup => libc::SIGHUP,
+        Signal::Sigint => libc::SIGINT,
+        Signal::Sigquit => libc::SIGQUIT,
+        Signal::Sigill => libc::SIGILL,
+        Signal::Sigtrap => libc::SIGTRAP,
+        Signal::Sigabrt => libc::SIGABRT,
+        Signal::Sigbus => libc::SIGBUS,
+        Signal::Sigfpe => libc::SIGFPE,
+        Signal::Sigkill => libc::SIGKILL,
+        Signal::Sigusr1 => libc::SIGUSR1,
+        Signal::Sigsegv => libc::SIGSEGV,
+        Signal::Sigusr2 => libc::SIGUSR2,
+        Signal::Sigpipe => libc::SIGPIPE,
+        Signal::Sigalrm => libc::SIGALRM,
+        Signal::Sigterm => libc::SIGTERM,
+        Signal::Sigchld => libc::SIGCHLD,
+        Signal::Sigcont => libc::SIGCONT,
+        Signal::Sigstop => libc::SIGSTOP,
+        Signal::Sigtstp => libc::SIGTSTP,
+        Signal::Sigttin => libc::SIGTTIN,
+        Signal::Sigttou => libc::SIGTTOU,
+        Signal::Sigurg => libc::SIGURG,
+        Signal::Sigxcpu => libc::SIGXCPU,
+        Signal::Sigxfsz => libc::SIGXFSZ,
+        Signal::Sigvtalrm => libc::SIGVTALRM,
+        Signal::Sigprof => libc::SIGPROF,
+        Signal::Sigwinch => libc::SIGWINCH,
+        Signal::Sigsys => libc::SIGSYS,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Signal::Sigstkflt => libc::SIGSTKFLT,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Signal::Sigpoll => libc::SIGPOLL,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Signal::Sigpwr => libc::SIGPWR,
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        Signal::Sigstkflt | Signal::Sigpoll | Signal::Sigpwr => return None,
+    };
+    Some(host)
+}
+
+/// Raise `signal` in the host process, as if the host had raised it
+/// itself. Returns whether the host has an equivalent of the signal.
+///
+/// Whatever the host does with the signal happens before this returns, so
+/// for most signals the host process will be gone.
+#[cfg(unix)]
+pub fn raise_on_host(signal: Signal) -> bool {
+    let Some(host) = host_signal(signal) else {
+        return false;
+    };
+    tracing::debug!(?signal, host, "raising the signal on the host");
+    unsafe { libc::raise(host) == 0 }
+}
+
+#[cfg(unix)]
+extern "C" {
+    // Not bound by the `libc` crate on every target
+    fn setitimer(
+        which: libc::c_int,
+        new_value: *const libc::itimerval,
+        old_value: *mut libc::itimerval,
+    ) -> libc::c_int;
+}
+
+/// Arm the host timer that raises `signal` (`SIGALRM`, `SIGVTALRM` or
+/// `SIGPROF`) in the host process after `interval`, and then every
+/// `interval` if `repeat` is set. No `interval` disarms the timer.
+///
+/// Returns whether `signal` has a host timer.
+#[cfg(unix)]
+pub fn set_host_interval(signal: Signal, interval: Option<Duration>, repeat: bool) -> bool {
+    let which = match signal {
+        Signal::Sigalrm => libc::ITIMER_REAL,
+        Signal::Sigvtalrm => libc::ITIMER_VIRTUAL,
+        Signal::Sigprof => libc::ITIMER_PROF,
+        _ => return false,
+    };
+    let to_timeval = |duration: Duration| libc::timeval {
+        tv_sec: duration.as_secs() as libc::time_t,
+        tv_usec: duration.subsec_micros() as libc::suseconds_t,
+    };
+    let value = to_timeval(interval.unwrap_or_default());
+    let timer = libc::itimerval {
+        it_interval: if repeat {
+            value
+        } else {
+            to_timeval(Duration::ZERO)
+        },
+        it_value: value,
+    };
+    tracing::debug!(?signal, ?interval, repeat, "arming the host timer");
+    unsafe { setitimer(which, &timer, std::ptr::null_mut()) == 0 }
+}
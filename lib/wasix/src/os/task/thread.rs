@@ -514,6 +514,8 @@ pub enum WasiThreadError {
     /// This will happen if WASM is running in a thread has not been created by the spawn_wasm call
     #[error("WASM context is invalid")]
     InvalidWasmContext,
+    #[error("Failed to spawn the thread - {0}")]
+    SpawnFailed(std::io::Error),
 }
 
 impl From<WasiThreadError> for Errno {
@@ -526,6 +528,7 @@ impl From<WasiThreadError> for Errno {
             WasiThreadError::InstanceCreateFailed(_) => Errno::Noexec,
             WasiThreadError::InitFailed(_) => Errno::Noexec,
             WasiThreadError::InvalidWasmContext => Errno::Noexec,
+            WasiThreadError::SpawnFailed(_) => Errno::Again,
         }
     }
 }
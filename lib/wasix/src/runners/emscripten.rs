@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use wasmer::{FunctionEnv, Instance, Module, Store};
 use wasmer_emscripten::{
     generate_emscripten_env, is_emscripten_module, run_emscripten_instance, EmEnv,
-    EmscriptenGlobals,
+    EmscriptenGlobals, EmscriptenState,
 };
 use webc::metadata::{annotations::Emscripten, Command};
 
@@ -16,6 +16,8 @@ use crate::{bin_factory::BinaryPackage, Runtime};
 #[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EmscriptenRunner {
     args: Vec<String>,
+    #[serde(default)]
+    pid_namespace: bool,
 }
 
 impl EmscriptenRunner {
@@ -39,6 +41,34 @@ impl EmscriptenRunner {
     pub fn set_args(&mut self, args: Vec<String>) {
         self.args = args;
     }
+
+    /// Give the module a PID of 1 and a parent PID of 0, instead of letting
+    /// `getpid` and `getppid` return the host's PIDs.
+    pub fn set_pid_namespace(&mut self, pid_namespace: bool) {
+        self.pid_namespace = pid_namespace;
+    }
+
+    /// Run an Emscripten module that doesn't come from a package (e.g. a
+    /// `*.wasm` file), passing it the CLI args.
+    pub fn run_module(&self, name: &str, module: &Module, mut store: Store) -> Result<(), Error> {
+        let (mut globals, env) = prepare_emscripten_env(&mut store, module, name, self.state())?;
+
+        exec_module(
+            &mut store,
+            module,
+            &mut globals,
+            env,
+            name,
+            self.args.clone(),
+        )
+    }
+
+    fn state(&self) -> EmscriptenState {
+        EmscriptenState {
+            pid_namespace: self.pid_namespace,
+            ..Default::default()
+        }
+    }
 }
 
 impl crate::runners::Runner for EmscriptenRunner {
@@ -65,7 +95,8 @@ impl crate::runners::Runner for EmscriptenRunner {
         module.set_name(command_name);
 
         let mut store = runtime.new_store();
-        let (mut globals, env) = prepare_emscripten_env(&mut store, &module, command_name)?;
+        let (mut globals, env) =
+            prepare_emscripten_env(&mut store, &module, command_name, self.state())?;
 
         exec_module(
             &mut store,
@@ -84,12 +115,13 @@ fn prepare_emscripten_env(
     store: &mut Store,
     module: &Module,
     name: &str,
+    state: EmscriptenState,
 ) -> Result<(EmscriptenGlobals, FunctionEnv<EmEnv>), anyhow::Error> {
     if !is_emscripten_module(module) {
         return Err(anyhow!("Atom {name:?} is not an emscripten module"));
     }
 
-    let env = FunctionEnv::new(store, EmEnv::new());
+    let env = FunctionEnv::new(store, EmEnv::new_with_state(state));
     let emscripten_globals = EmscriptenGlobals::new(store, &env, module);
     let emscripten_globals = emscripten_globals.map_err(|e| anyhow!("{}", e))?;
     env.as_mut(store)
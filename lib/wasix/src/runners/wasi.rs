@@ -1,8 +1,9 @@
 //! WebC container support for running WASI modules
 
-use std::sync::Arc;
+use std::{fs::File, sync::Arc};
 
 use anyhow::{Context, Error};
+use virtual_fs::WriteLimitMode;
 use webc::metadata::{annotations::Wasi, Command};
 
 use crate::{
@@ -88,6 +89,39 @@ impl WasiRunner {
         self.wasi.forward_host_env = true;
     }
 
+    /// Make stdin behave like `/dev/null`, so reading from it returns EOF
+    /// straight away.
+    pub fn with_stdin_null(mut self) -> Self {
+        self.set_stdin_null();
+        self
+    }
+
+    pub fn set_stdin_null(&mut self) {
+        self.wasi.stdin_null = true;
+    }
+
+    /// Only let the first `limit` bytes the program writes to stdout
+    /// through, see [`WasiEnvBuilder::set_stdout_limit`].
+    pub fn with_stdout_limit(mut self, limit: u64, mode: WriteLimitMode) -> Self {
+        self.set_stdout_limit(limit, mode);
+        self
+    }
+
+    pub fn set_stdout_limit(&mut self, limit: u64, mode: WriteLimitMode) {
+        self.wasi.stdout_limit = Some((limit, mode));
+    }
+
+    /// Copy everything the program writes to stdout into `file` as well,
+    /// like the `tee` command.
+    pub fn with_stdout_tee(mut self, file: File) -> Self {
+        self.set_stdout_tee(file);
+        self
+    }
+
+    pub fn set_stdout_tee(&mut self, file: File) {
+        self.wasi.stdout_tee = Some(Arc::new(file));
+    }
+
     pub fn with_mapped_directories<I, D>(mut self, dirs: I) -> Self
     where
         I: IntoIterator<Item = D>,
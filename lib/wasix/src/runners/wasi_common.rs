@@ -1,12 +1,16 @@
 use std::{
     collections::HashMap,
+    fs::File,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use anyhow::{Context, Error};
 use futures::future::BoxFuture;
-use virtual_fs::{FileSystem, FsError, OverlayFileSystem, RootFileSystemBuilder};
+use virtual_fs::{
+    FileSystem, FsError, NullFile, OverlayFileSystem, RootFileSystemBuilder, TeeFile,
+    WriteLimitMode,
+};
 use webc::metadata::annotations::Wasi as WasiAnnotation;
 
 use crate::{
@@ -19,6 +23,9 @@ pub(crate) struct CommonWasiOptions {
     pub(crate) args: Vec<String>,
     pub(crate) env: HashMap<String, String>,
     pub(crate) forward_host_env: bool,
+    pub(crate) stdin_null: bool,
+    pub(crate) stdout_limit: Option<(u64, WriteLimitMode)>,
+    pub(crate) stdout_tee: Option<Arc<File>>,
     pub(crate) mapped_dirs: Vec<MappedDirectory>,
     pub(crate) injected_packages: Vec<BinaryPackage>,
     pub(crate) capabilities: Capabilities,
@@ -42,6 +49,24 @@ impl CommonWasiOptions {
 
         builder.set_fs(fs);
 
+        if self.stdin_null {
+            builder.set_stdin(Box::<NullFile>::default());
+        }
+
+        if let Some(file) = &self.stdout_tee {
+            let copy = file
+                .try_clone()
+                .context("Unable to duplicate the file stdout is copied to")?;
+            builder.set_stdout(Box::new(TeeFile::new(
+                Box::<crate::state::Stdout>::default(),
+                Box::new(copy),
+            )));
+        }
+
+        if let Some((limit, mode)) = self.stdout_limit {
+            builder.set_stdout_limit(limit, mode);
+        }
+
         for pkg in &self.injected_packages {
             builder.add_webc(pkg.clone());
         }
@@ -136,6 +161,10 @@ fn prepare_filesystem(
                         format!("Unable to create the \"{}\" directory", parent.display())
                     })?;
                 }
+                // The root filesystem comes with an empty `/tmp`, which would
+                // stop a host directory from being mounted there. Only empty
+                // directories can be removed, so nothing gets hidden.
+                let _ = root_fs.remove_dir(&guest_path);
 
                 root_fs
                     .mount(guest_path.clone(), &host_fs, host_path.clone())
@@ -59,6 +59,8 @@ impl Handler {
                 insecure_allow_all: true,
                 http_client: HttpClientCapabilityV1::new_allow_all(),
                 threading: Default::default(),
+                syscalls: Default::default(),
+                signals: Default::default(),
             });
 
         let module = self.module.clone();
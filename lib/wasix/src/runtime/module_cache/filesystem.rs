@@ -143,7 +143,9 @@ fn deserialize(bytes: &[u8], engine: &Engine) -> Result<Module, CacheError> {
     // - ModuleCache::save(): 2.4s, 72MB binary
     // - ModuleCache::load(): 822ms
 
-    match unsafe { Module::deserialize(engine, bytes) } {
+    // Cached artifacts are checked before being loaded, so a corrupted or
+    // stale file is evicted instead of crashing the process.
+    match unsafe { Module::deserialize_checked(engine, bytes) } {
         // The happy case
         Ok(m) => Ok(m),
         Err(wasmer::DeserializeError::Incompatible(_)) => {
@@ -151,7 +153,7 @@ fn deserialize(bytes: &[u8], engine: &Engine) -> Result<Module, CacheError> {
                 .decode(bytes)
                 .map_err(CacheError::other)?;
 
-            let m = unsafe { Module::deserialize(engine, bytes)? };
+            let m = unsafe { Module::deserialize_checked(engine, bytes)? };
 
             Ok(m)
         }
@@ -237,6 +239,28 @@ mod tests {
         assert_eq!(exports, ["add"]);
     }
 
+    #[tokio::test]
+    async fn corrupted_files_are_evicted() {
+        let temp = TempDir::new().unwrap();
+        let engine = Engine::default();
+        let module = Module::new(&engine, ADD_WAT).unwrap();
+        let key = ModuleHash::from_bytes([0; 32]);
+        let cache = FileSystemCache::new(temp.path());
+        let expected_path = cache.path(key, engine.deterministic_id());
+        std::fs::create_dir_all(expected_path.parent().unwrap()).unwrap();
+        let mut serialized = module.serialize().unwrap().to_vec();
+        *serialized.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&expected_path, &serialized).unwrap();
+
+        let err = cache.load(key, &engine).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            CacheError::Deserialize(wasmer::DeserializeError::ChecksumMismatch)
+        ));
+        assert!(!expected_path.exists());
+    }
+
     /// For backwards compatibility, make sure we can still work with LZW
     /// compressed modules.
     #[tokio::test]
@@ -73,6 +73,23 @@ impl BuiltinPackageLoader {
         ))
     }
 
+    /// Where the filesystem cache keeps the `*.webc` file with the given
+    /// hash, whether or not it has been downloaded yet.
+    ///
+    /// Returns `None` if there is no filesystem cache.
+    pub fn cache_path(&self, hash: &WebcHash) -> Option<PathBuf> {
+        self.cache.as_ref().map(|cache| cache.path(hash))
+    }
+
+    /// Every `*.webc` file in the filesystem cache, with the hash it was
+    /// saved under (the hash the registry gave when it was downloaded).
+    pub fn cached_webcs(&self) -> Result<Vec<(WebcHash, PathBuf)>, Error> {
+        match self.cache.as_ref() {
+            Some(cache) => cache.entries(),
+            None => Ok(Vec::new()),
+        }
+    }
+
     #[tracing::instrument(level = "debug", skip_all, fields(pkg.hash=%hash))]
     async fn get_cached(&self, hash: &WebcHash) -> Result<Option<Container>, Error> {
         if let Some(cached) = self.in_memory.lookup(hash) {
@@ -91,8 +108,12 @@ impl BuiltinPackageLoader {
         Ok(None)
     }
 
+    /// Fetch the raw bytes for a package, bypassing any caches.
+    ///
+    /// Note that `dist.webc_sha256` isn't checked, so callers which care
+    /// should do that themselves.
     #[tracing::instrument(level = "debug", skip_all, fields(%dist.webc, %dist.webc_sha256))]
-    async fn download(&self, dist: &DistributionInfo) -> Result<Bytes, Error> {
+    pub async fn download(&self, dist: &DistributionInfo) -> Result<Bytes, Error> {
         if dist.webc.scheme() == "file" {
             match crate::runtime::resolver::utils::file_path_from_url(&dist.webc) {
                 Ok(path) => {
@@ -302,6 +323,37 @@ impl FileSystemCache {
         }
     }
 
+    fn entries(&self) -> Result<Vec<(WebcHash, PathBuf)>, Error> {
+        let entries = match std::fs::read_dir(&self.cache_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                let msg = format!("Unable to read \"{}\"", self.cache_dir.display());
+                return Err(Error::new(e).context(msg));
+            }
+        };
+
+        let mut webcs = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let hash = path
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_suffix(".bin"))
+                .and_then(|hex| {
+                    let mut hash = [0_u8; 32];
+                    hex::decode_to_slice(hex, &mut hash).ok()?;
+                    Some(WebcHash::from_bytes(hash))
+                });
+            // Skip the temporary files of downloads still being saved
+            if let Some(hash) = hash {
+                webcs.push((hash, path));
+            }
+        }
+        webcs.sort();
+
+        Ok(webcs)
+    }
+
     fn path(&self, hash: &WebcHash) -> PathBuf {
         let hash = hash.as_bytes();
         let mut filename = String::with_capacity(hash.len() * 2);
@@ -421,4 +473,21 @@ mod tests {
         let in_memory = loader.in_memory.0.read().unwrap();
         assert!(in_memory.contains_key(&summary.dist.webc_sha256));
     }
+
+    #[test]
+    fn list_the_cached_webcs() {
+        let temp = TempDir::new().unwrap();
+        let client = Arc::new(DummyClient::with_responses([]));
+        let loader = BuiltinPackageLoader::new_with_client(temp.path().join("checkouts"), client);
+        // Nothing has been downloaded yet
+        assert!(loader.cached_webcs().unwrap().is_empty());
+
+        let hash = WebcHash::sha256(PYTHON);
+        let path = loader.cache_path(&hash).unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, PYTHON).unwrap();
+        std::fs::write(path.with_file_name(".tmpAbC123"), b"partial").unwrap();
+
+        assert_eq!(loader.cached_webcs().unwrap(), vec![(hash, path)]);
+    }
 }
@@ -13,7 +13,12 @@ use super::{TaskWasm, TaskWasmRunProperties, VirtualTaskManager};
 
 /// A task manager that uses tokio to spawn tasks.
 #[derive(Clone, Debug)]
-pub struct TokioTaskManager(Handle);
+pub struct TokioTaskManager {
+    handle: Handle,
+    /// The stack size of the threads WASM tasks run on, or `None` to run
+    /// them on tokio's blocking threads.
+    wasm_thread_stack_size: Option<usize>,
+}
 
 /// This holds the currently set shared runtime which should be accessed via
 /// TokioTaskManager::shared() and/or set via TokioTaskManager::set_shared()
@@ -21,11 +26,22 @@ static GLOBAL_RUNTIME: Mutex<Option<(Arc<tokio::runtime::Runtime>, Handle)>> = M
 
 impl TokioTaskManager {
     pub fn new(rt: Handle) -> Self {
-        Self(rt)
+        Self {
+            handle: rt,
+            wasm_thread_stack_size: None,
+        }
+    }
+
+    /// Runs each WASM task (e.g. the threads a module spawns) on a native
+    /// thread of its own, with a stack of `stack_size` bytes, rather than
+    /// on one of tokio's blocking threads.
+    pub fn with_wasm_thread_stack_size(mut self, stack_size: usize) -> Self {
+        self.wasm_thread_stack_size = Some(stack_size);
+        self
     }
 
     pub fn runtime_handle(&self) -> tokio::runtime::Handle {
-        self.0.clone()
+        self.handle.clone()
     }
 
     /// Allows the caller to set the shared runtime that will be used by other
@@ -48,7 +64,7 @@ impl TokioTaskManager {
     /// independent ones in a process.
     pub fn shared() -> Self {
         if let Ok(handle) = tokio::runtime::Handle::try_current() {
-            Self(handle)
+            Self::new(handle)
         } else {
             let mut guard = GLOBAL_RUNTIME.lock().unwrap();
             let rt = guard.get_or_insert_with(|| {
@@ -56,7 +72,7 @@ impl TokioTaskManager {
                 let handle = rt.handle().clone();
                 (Arc::new(rt), handle)
             });
-            Self(rt.1.clone())
+            Self::new(rt.1.clone())
         }
     }
 }
@@ -67,6 +83,31 @@ impl Default for TokioTaskManager {
     }
 }
 
+/// Runs `task` on a thread of its own with a stack of `stack_size` bytes,
+/// or on one of tokio's blocking threads without one.
+fn spawn_wasm_thread(
+    handle: &Handle,
+    stack_size: Option<usize>,
+    task: impl FnOnce() + Send + 'static,
+) -> Result<(), WasiThreadError> {
+    match stack_size {
+        Some(stack_size) => {
+            let handle = handle.clone();
+            std::thread::Builder::new()
+                .stack_size(stack_size)
+                .spawn(move || {
+                    let _guard = handle.enter();
+                    task();
+                })
+                .map_err(WasiThreadError::SpawnFailed)?;
+        }
+        None => {
+            handle.spawn_blocking(task);
+        }
+    }
+    Ok(())
+}
+
 struct TokioRuntimeGuard<'g> {
     #[allow(unused)]
     inner: tokio::runtime::EnterGuard<'g>,
@@ -94,7 +135,7 @@ impl VirtualTaskManager for TokioTaskManager {
             dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> + Send + 'static,
         >,
     ) -> Result<(), WasiThreadError> {
-        self.0.spawn(async move {
+        self.handle.spawn(async move {
             let fut = task();
             fut.await
         });
@@ -103,13 +144,13 @@ impl VirtualTaskManager for TokioTaskManager {
 
     /// See [`VirtualTaskManager::runtime`].
     fn runtime(&self) -> &Handle {
-        &self.0
+        &self.handle
     }
 
     #[allow(dyn_drop)]
     fn runtime_enter<'g>(&'g self) -> Box<dyn std::ops::Drop + 'g> {
         Box::new(TokioRuntimeGuard {
-            inner: self.0.enter(),
+            inner: self.handle.enter(),
         })
     }
 
@@ -129,11 +170,12 @@ impl VirtualTaskManager for TokioTaskManager {
         // the poller to completion
         if let Some(trigger) = task.trigger {
             let trigger = trigger();
-            let handle = self.0.clone();
-            self.0.spawn(async move {
+            let handle = self.handle.clone();
+            let stack_size = self.wasm_thread_stack_size;
+            self.handle.spawn(async move {
                 let result = trigger.await;
                 // Build the task that will go on the callback
-                handle.spawn_blocking(move || {
+                let spawned = spawn_wasm_thread(&handle, stack_size, move || {
                     // Invoke the callback
                     run(TaskWasmRunProperties {
                         ctx,
@@ -141,17 +183,20 @@ impl VirtualTaskManager for TokioTaskManager {
                         trigger_result: Some(result),
                     });
                 });
+                if let Err(err) = spawned {
+                    tracing::error!("unable to resume the WASM task - {err}");
+                }
             });
         } else {
             // Run the callback on a dedicated thread
-            self.0.spawn_blocking(move || {
+            spawn_wasm_thread(&self.handle, self.wasm_thread_stack_size, move || {
                 // Invoke the callback
                 run(TaskWasmRunProperties {
                     ctx,
                     store,
                     trigger_result: None,
                 });
-            });
+            })?;
         }
         Ok(())
     }
@@ -161,7 +206,7 @@ impl VirtualTaskManager for TokioTaskManager {
         &self,
         task: Box<dyn FnOnce() + Send + 'static>,
     ) -> Result<(), WasiThreadError> {
-        self.0.spawn_blocking(move || {
+        self.handle.spawn_blocking(move || {
             task();
         });
         Ok(())
@@ -174,3 +219,31 @@ impl VirtualTaskManager for TokioTaskManager {
             .unwrap_or(8))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_threads_with_a_stack_size_can_use_the_runtime() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        spawn_wasm_thread(rt.handle(), Some(8 * 1024 * 1024), move || {
+            // Recursing this deep would overflow tokio's default 2 MiB stack
+            fn recurse(depth: usize) -> usize {
+                let frame = [depth as u8; 1024];
+                if depth == 0 {
+                    return frame[0] as usize;
+                }
+                recurse(depth - 1) + std::hint::black_box(frame)[1] as usize
+            }
+            let in_runtime = Handle::try_current().is_ok();
+            sender.send((in_runtime, recurse(3000))).unwrap();
+        })
+        .unwrap();
+
+        let (in_runtime, _) = receiver.recv().unwrap();
+        assert!(in_runtime);
+    }
+}
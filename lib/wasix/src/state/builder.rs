@@ -9,9 +9,11 @@ use std::{
 use bytes::Bytes;
 use rand::Rng;
 use thiserror::Error;
-use virtual_fs::{ArcFile, FsError, TmpFileSystem, VirtualFile};
-use wasmer::{AsStoreMut, Instance, Module, RuntimeError, Store};
-use wasmer_wasix_types::wasi::{Errno, ExitCode};
+use virtual_fs::{
+    ArcFile, FsError, LimitedWriteFile, Pipe, TmpFileSystem, VirtualFile, WriteLimitMode,
+};
+use wasmer::{AsStoreMut, Imports, Instance, Module, RuntimeError, Store};
+use wasmer_wasix_types::wasi::{Errno, ExitCode, Fd as WasiFd, Rights};
 
 #[cfg(feature = "sys")]
 use crate::PluggableRuntime;
@@ -59,6 +61,11 @@ pub struct WasiEnvBuilder {
     pub(super) stdout: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    /// Caps how many bytes can be written to `stdout`.
+    pub(super) stdout_limit: Option<(u64, WriteLimitMode)>,
+    /// Ends of pipes to put at particular file descriptors, with their
+    /// rights.
+    pub(super) pipes: Vec<(WasiFd, Pipe, Rights)>,
     pub(super) fs: Option<WasiFsRoot>,
     pub(super) runtime: Option<Arc<dyn crate::Runtime + Send + Sync + 'static>>,
 
@@ -69,6 +76,9 @@ pub struct WasiEnvBuilder {
     pub(super) map_commands: HashMap<String, PathBuf>,
 
     pub(super) capabilites: Capabilities,
+
+    /// Imports which take precedence over the WASI ones.
+    pub(super) additional_imports: Imports,
 }
 
 impl std::fmt::Debug for WasiEnvBuilder {
@@ -83,6 +93,7 @@ impl std::fmt::Debug for WasiEnvBuilder {
             .field("stdout_override exists", &self.stdout.is_some())
             .field("stderr_override exists", &self.stderr.is_some())
             .field("stdin_override exists", &self.stdin.is_some())
+            .field("pipes", &self.pipes)
             .field("runtime_override_exists", &self.runtime.is_some())
             .finish()
     }
@@ -493,6 +504,21 @@ impl WasiEnvBuilder {
         self.stdout = Some(new_file);
     }
 
+    /// Only let the first `limit` bytes written to `stdout` through. What
+    /// happens to writes after that depends on `mode`: they either fail
+    /// with `ENOSPC` or are silently discarded.
+    pub fn stdout_limit(mut self, limit: u64, mode: WriteLimitMode) -> Self {
+        self.set_stdout_limit(limit, mode);
+        self
+    }
+
+    /// Only let the first `limit` bytes written to `stdout` through. What
+    /// happens to writes after that depends on `mode`: they either fail
+    /// with `ENOSPC` or are silently discarded.
+    pub fn set_stdout_limit(&mut self, limit: u64, mode: WriteLimitMode) {
+        self.stdout_limit = Some((limit, mode));
+    }
+
     /// Overwrite the default WASI `stderr`, if you want to hold on to the
     /// original `stderr` use [`WasiFs::swap_file`] after building.
     pub fn stderr(mut self, new_file: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
@@ -520,6 +546,21 @@ impl WasiEnvBuilder {
         self.stdin = Some(new_file);
     }
 
+    /// Put one end of a pipe at the file descriptor `fd`, e.g. to share
+    /// the pipe with another instance. `rights` decides whether the program
+    /// can read from it, write to it, or both.
+    pub fn pipe(mut self, fd: WasiFd, pipe: Pipe, rights: Rights) -> Self {
+        self.add_pipe(fd, pipe, rights);
+        self
+    }
+
+    /// Put one end of a pipe at the file descriptor `fd`, e.g. to share
+    /// the pipe with another instance. `rights` decides whether the program
+    /// can read from it, write to it, or both.
+    pub fn add_pipe(&mut self, fd: WasiFd, pipe: Pipe, rights: Rights) {
+        self.pipes.push((fd, pipe, rights));
+    }
+
     /// Sets the FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -572,6 +613,27 @@ impl WasiEnvBuilder {
         self.capabilites = capabilities;
     }
 
+    /// Satisfy the module's imports with `imports` before falling back to
+    /// the WASI functions, e.g. to link it with the exports of another
+    /// module. Imports added later replace earlier ones with the same name.
+    ///
+    /// The imports must belong to the store the module is instantiated in,
+    /// and aren't available to threads the module spawns.
+    pub fn imports(mut self, imports: &Imports) -> Self {
+        self.add_imports(imports);
+        self
+    }
+
+    /// Satisfy the module's imports with `imports` before falling back to
+    /// the WASI functions, e.g. to link it with the exports of another
+    /// module. Imports added later replace earlier ones with the same name.
+    ///
+    /// The imports must belong to the store the module is instantiated in,
+    /// and aren't available to threads the module spawns.
+    pub fn add_imports(&mut self, imports: &Imports) {
+        self.additional_imports.extend(imports);
+    }
+
     /// Consumes the [`WasiEnvBuilder`] and produces a [`WasiEnvInit`], which
     /// can be used to construct a new [`WasiEnv`].
     ///
@@ -663,7 +725,12 @@ impl WasiEnvBuilder {
                 .swap_file(__WASI_STDIN_FILENO, stdin)
                 .map_err(WasiStateCreationError::FileSystemError)?;
 
-            if let Some(stdout_override) = self.stdout.take() {
+            let mut stdout = self.stdout.take();
+            if let Some((limit, mode)) = self.stdout_limit {
+                let inner = stdout.unwrap_or_else(|| Box::<super::Stdout>::default());
+                stdout = Some(Box::new(LimitedWriteFile::new(inner, limit, mode)));
+            }
+            if let Some(stdout_override) = stdout {
                 wasi_fs
                     .swap_file(__WASI_STDOUT_FILENO, stdout_override)
                     .map_err(WasiStateCreationError::FileSystemError)?;
@@ -675,6 +742,16 @@ impl WasiEnvBuilder {
                     .map_err(WasiStateCreationError::FileSystemError)?;
             }
 
+            for (fd, pipe, rights) in self.pipes.drain(..) {
+                wasi_fs
+                    .create_pipe_fd(&inodes, fd, pipe, rights)
+                    .map_err(|e| {
+                        WasiStateCreationError::WasiFsSetupError(format!(
+                            "unable to put a pipe at file descriptor {fd}: {e}"
+                        ))
+                    })?;
+            }
+
             if let Some(f) = &self.setup_fs_fn {
                 f(&inodes, &mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
             }
@@ -726,6 +803,7 @@ impl WasiEnvBuilder {
 
         let plane_config = ControlPlaneConfig {
             max_task_count: capabilities.threading.max_threads,
+            max_threads_per_process: capabilities.threading.max_threads_per_process,
             enable_asynchronous_threading: capabilities.threading.enable_asynchronous_threading,
         };
         let control_plane = WasiControlPlane::new(plane_config);
@@ -744,6 +822,7 @@ impl WasiEnvBuilder {
             call_initialize: true,
             can_deep_sleep: false,
             extra_tracing: true,
+            additional_imports: self.additional_imports,
         };
 
         Ok(init)
@@ -805,7 +884,8 @@ impl WasiEnvBuilder {
         let start = instance.exports.get_function("_start")?;
         env.data(&store).thread.set_status_running();
 
-        let result = crate::run_wasi_func_start(start, store);
+        let span = env.data(&store).thread_span();
+        let result = span.in_scope(|| crate::run_wasi_func_start(start, store));
         let (result, exit_code) = wasi_exit_code(result);
 
         let pid = env.data(&store).pid();
@@ -895,6 +975,8 @@ fn run_with_deep_sleep(
     env: WasiFunctionEnv,
     sender: std::sync::mpsc::Sender<Result<(), WasiRuntimeError>>,
 ) {
+    let _span = env.data(&store).thread_span().entered();
+
     if let Some((rewind_state, rewind_result)) = rewind_state {
         tracing::trace!("Rewinding");
         let errno = if rewind_state.is_64bit {
@@ -13,8 +13,8 @@ use tracing::{trace, warn};
 use virtual_fs::{AsyncWriteExt, FileSystem, FsError, VirtualFile};
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
-    AsStoreMut, AsStoreRef, FunctionEnvMut, Global, Instance, Memory, MemoryType, MemoryView,
-    Module, TypedFunction,
+    AsStoreMut, AsStoreRef, Extern, FunctionEnvMut, Global, Imports, Instance, InterruptHandle,
+    Memory, MemoryType, MemoryView, Module, TypedFunction,
 };
 use wasmer_wasix_types::{
     types::Signal,
@@ -29,7 +29,7 @@ use crate::{
     os::task::{
         control_plane::ControlPlaneError,
         process::{WasiProcess, WasiProcessId},
-        thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
+        thread::{WasiMemoryLayout, WasiThread, WasiThreadError, WasiThreadHandle, WasiThreadId},
     },
     runtime::{resolver::PackageSpecifier, SpawnMemoryType},
     syscalls::platform_clock_time_get,
@@ -126,6 +126,10 @@ pub struct WasiInstanceHandles {
     #[allow(dead_code)]
     #[derivative(Debug = "ignore")]
     pub(crate) asyncify_get_state: Option<TypedFunction<(), i32>>,
+
+    /// Stops the calls running in the store, which blocking syscalls
+    /// give up on.
+    pub(crate) interrupt: InterruptHandle,
 }
 
 impl WasiInstanceHandles {
@@ -171,6 +175,7 @@ impl WasiInstanceHandles {
                 .exports
                 .get_typed_function(store, "asyncify_get_state")
                 .ok(),
+            interrupt: store.as_store_ref().interrupt_handle(),
             instance,
         }
     }
@@ -230,6 +235,10 @@ pub struct WasiEnvInit {
 
     /// Indicates if extra tracing should be output
     pub extra_tracing: bool,
+
+    /// Imports which take precedence over the WASI ones when instantiating
+    /// the module.
+    pub additional_imports: Imports,
 }
 
 impl WasiEnvInit {
@@ -266,6 +275,8 @@ impl WasiEnvInit {
             call_initialize: self.call_initialize,
             can_deep_sleep: self.can_deep_sleep,
             extra_tracing: false,
+            // These belong to the original store
+            additional_imports: Imports::new(),
         }
     }
 }
@@ -299,6 +310,10 @@ pub struct WasiEnv {
     /// Is this environment capable and setup for deep sleeping
     pub enable_deep_sleep: bool,
 
+    /// The stack limit of the store the environment was initialized in,
+    /// which the stores of the threads it spawns are given too
+    pub(crate) stack_limit: Option<usize>,
+
     /// Inner functions and references that are loaded before the environment starts
     /// (inner is not safe to send between threads and so it is private and will
     ///  not be cloned when `WasiEnv` is cloned)
@@ -328,6 +343,7 @@ impl Clone for WasiEnv {
             runtime: self.runtime.clone(),
             capabilities: self.capabilities.clone(),
             enable_deep_sleep: self.enable_deep_sleep,
+            stack_limit: self.stack_limit,
         }
     }
 }
@@ -364,6 +380,7 @@ impl WasiEnv {
             runtime: self.runtime.clone(),
             capabilities: self.capabilities.clone(),
             enable_deep_sleep: self.enable_deep_sleep,
+            stack_limit: self.stack_limit,
         };
         Ok((new_env, handle))
     }
@@ -376,6 +393,16 @@ impl WasiEnv {
         self.thread.tid()
     }
 
+    /// A span to enter while this thread runs the module, so tracing layers
+    /// can tell which process and thread each syscall came from.
+    pub(crate) fn thread_span(&self) -> tracing::Span {
+        tracing::trace_span!(
+            "wasi_thread",
+            pid = self.pid().raw(),
+            tid = self.tid().raw()
+        )
+    }
+
     /// Returns true if this module is capable of deep sleep
     /// (needs asyncify to unwind and rewin)
     ///
@@ -425,6 +452,7 @@ impl WasiEnv {
             bin_factory: init.bin_factory,
             enable_deep_sleep: init.capabilities.threading.enable_asynchronous_threading,
             capabilities: init.capabilities,
+            stack_limit: None,
         };
         env.owned_handles.push(thread);
 
@@ -448,6 +476,7 @@ impl WasiEnv {
     ) -> Result<(Instance, WasiFunctionEnv), WasiRuntimeError> {
         let call_initialize = init.call_initialize;
         let spawn_type = init.memory_ty.take();
+        let additional_imports = std::mem::take(&mut init.additional_imports);
 
         if init.extra_tracing {
             for import in module.imports() {
@@ -472,7 +501,15 @@ impl WasiEnv {
             SpawnMemoryType::CreateMemoryOfType(t)
         } else {
             match shared_memory {
-                Some(ty) => SpawnMemoryType::CreateMemoryOfType(ty),
+                Some(ty) => {
+                    let ty = func_env
+                        .data(&store)
+                        .capabilities
+                        .threading
+                        .shared_memory_type(ty)
+                        .map_err(WasiThreadError::MemoryCreateFailed)?;
+                    SpawnMemoryType::CreateMemoryOfType(ty)
+                }
                 None => SpawnMemoryType::CreateMemory,
             }
         };
@@ -488,6 +525,13 @@ impl WasiEnv {
         } else {
             None
         };
+        import_object.extend(&additional_imports);
+        // The WASI functions need to use whichever memory the module ends up
+        // importing
+        let imported_memory = match additional_imports.get_export("env", "memory") {
+            Some(Extern::Memory(memory)) => Some(memory),
+            _ => imported_memory,
+        };
 
         // Construct the instance.
         let instance = match Instance::new(&mut store, &module, &import_object) {
@@ -721,6 +765,18 @@ impl WasiEnv {
         )
     }
 
+    /// Completes once the calls running in the store this environment was
+    /// initialized with are interrupted, see [`InterruptHandle`].
+    pub(crate) fn interrupted(&self) -> impl std::future::Future<Output = ()> + Send + Sync {
+        let interrupt = self.try_inner().map(|inner| inner.interrupt.clone());
+        async move {
+            match interrupt {
+                Some(interrupt) => interrupt.interrupted().await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+
     /// Providers safe access to the initialized part of WasiEnv
     pub(crate) fn try_inner(&self) -> Option<WasiInstanceGuard<'_>> {
         self.inner.get()
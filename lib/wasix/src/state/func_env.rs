@@ -42,6 +42,9 @@ impl WasiFunctionEnv {
         // Create a new store and put the memory object in it
         // (but only if it has imported memory)
         let mut store = env.runtime.new_store();
+        if let Some(stack_limit) = env.stack_limit {
+            store.set_stack_limit(stack_limit);
+        }
         let memory = env
             .tasks()
             .build_memory(&mut store.as_store_mut(), spawn_type)?;
@@ -143,9 +146,11 @@ impl WasiFunctionEnv {
 
         let new_inner = WasiInstanceHandles::new(memory, store, instance);
         let stack_pointer = new_inner.stack_pointer.clone();
+        let stack_limit = store.as_store_ref().stack_limit();
 
         let env = self.data_mut(store);
         env.set_inner(new_inner);
+        env.stack_limit = stack_limit;
 
         env.state.fs.set_is_wasix(is_wasix_module);
 
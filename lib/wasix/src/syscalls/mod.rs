@@ -233,6 +233,7 @@ pub async unsafe fn stderr_write(
 fn block_on_with_timeout<T, Fut>(
     tasks: &Arc<dyn VirtualTaskManager>,
     timeout: Option<Duration>,
+    interrupted: impl Future<Output = ()>,
     work: Fut,
 ) -> Result<Result<T, Errno>, WasiError>
 where
@@ -260,6 +261,8 @@ where
             res = work => res,
             // Optional timeout
             _ = timeout => Ok(Err(Errno::Timedout)),
+            // The store was interrupted, which traps once the syscall returns
+            _ = interrupted => Ok(Err(Errno::Intr)),
         }
     };
 
@@ -332,8 +335,9 @@ where
     // Block on the work
     let mut pinned_work = Box::pin(work);
     let tasks = env.tasks().clone();
+    let interrupted = env.interrupted();
     let poller = Poller { ctx, pinned_work };
-    block_on_with_timeout(&tasks, timeout, poller)
+    block_on_with_timeout(&tasks, timeout, interrupted, poller)
 }
 
 /// Future that will be polled by asyncify methods
@@ -422,6 +426,7 @@ where
 
     // Define the work
     let tasks = ctx.data().tasks().clone();
+    let interrupted = ctx.data().interrupted();
     let work = async move {
         let env = ctx.data();
 
@@ -455,6 +460,8 @@ where
                 }))?;
                 AsyncifyAction::Unwind
             },
+            // The store was interrupted, which traps once the syscall returns
+            _ = interrupted => return Err(WasiError::Exit(Errno::Intr.into())),
         })
     };
 
@@ -505,7 +512,7 @@ where
     // Block on the work
     let mut pinned_work = Box::pin(work);
     let poller = Poller { env, pinned_work };
-    block_on_with_timeout(env.tasks(), timeout, poller)
+    block_on_with_timeout(env.tasks(), timeout, env.interrupted(), poller)
 }
 
 // This should be compiled away, it will simply wait forever however its never
@@ -557,7 +564,13 @@ where
     };
 
     // Block on the work and process it
-    env.tasks().block_on(work)
+    let interrupted = env.interrupted();
+    env.tasks().block_on(async move {
+        tokio::select! {
+            res = work => res,
+            _ = interrupted => Err(Errno::Intr),
+        }
+    })
 }
 
 /// Performs mutable work on a socket under an asynchronous runtime with
@@ -574,6 +587,7 @@ where
 {
     let env = ctx.data();
     let tasks = env.tasks().clone();
+    let interrupted = env.interrupted();
 
     let fd_entry = env.state.fs.get_fd(sock)?;
     if !rights.is_empty() && !fd_entry.rights.contains(rights) {
@@ -592,7 +606,12 @@ where
             let work = actor(socket, fd_entry);
 
             // Block on the work and process it
-            tasks.block_on(work)
+            tasks.block_on(async move {
+                tokio::select! {
+                    res = work => res,
+                    _ = interrupted => Err(Errno::Intr),
+                }
+            })
         }
         _ => Err(Errno::Notsock),
     }
@@ -612,6 +631,7 @@ where
 {
     let env = ctx.data();
     let tasks = env.tasks().clone();
+    let interrupted = env.interrupted();
 
     let fd_entry = env.state.fs.get_fd(sock)?;
     if !rights.is_empty() && !fd_entry.rights.contains(rights) {
@@ -649,6 +669,7 @@ where
 {
     let env = ctx.data();
     let tasks = env.tasks().clone();
+    let interrupted = env.interrupted();
 
     let fd_entry = env.state.fs.get_fd(sock)?;
     if !rights.is_empty() && !fd_entry.rights.contains(rights) {
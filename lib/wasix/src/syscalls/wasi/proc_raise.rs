@@ -10,6 +10,12 @@ use crate::syscalls::*;
 #[instrument(level = "debug", skip_all, fields(sig), ret, err)]
 pub fn proc_raise(mut ctx: FunctionEnvMut<'_, WasiEnv>, sig: Signal) -> Result<Errno, WasiError> {
     let env = ctx.data();
+    // With `--posix-signals` the host gets the signal first, so the signals
+    // that terminate a process take down the host process too
+    #[cfg(unix)]
+    if env.capabilities.signals.forward_to_host {
+        crate::os::task::signal::raise_on_host(sig);
+    }
     env.process.signal_process(sig);
 
     wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
@@ -35,6 +41,10 @@ pub fn proc_raise_interval(
         a => Some(Duration::from_millis(a)),
     };
     let repeat = matches!(repeat, Bool::True);
+    #[cfg(unix)]
+    if env.capabilities.signals.forward_to_host {
+        crate::os::task::signal::set_host_interval(sig, interval, repeat);
+    }
     env.process.signal_interval(sig, interval, repeat);
 
     wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
@@ -243,6 +243,7 @@ fn run<M: MemorySize>(
     let tasks = env.tasks().clone();
     let pid = env.pid();
     let tid = env.tid();
+    let _span = env.thread_span().entered();
 
     // If we need to rewind then do so
     if let Some((rewind_state, rewind_result)) = rewind_state {
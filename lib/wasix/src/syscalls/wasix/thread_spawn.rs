@@ -3,7 +3,7 @@ use std::f32::consts::E;
 use super::*;
 use crate::{
     capture_snapshot,
-    os::task::thread::WasiMemoryLayout,
+    os::task::{control_plane::ControlPlaneError, thread::WasiMemoryLayout},
     runtime::task_manager::{TaskWasm, TaskWasmRunProperties},
     syscalls::*,
     WasiThreadHandle,
@@ -75,6 +75,13 @@ pub(crate) fn thread_spawn_internal<M: MemorySize>(
     // Create the handle that represents this thread
     let mut thread_handle = match env.process.new_thread() {
         Ok(h) => Arc::new(h),
+        Err(ControlPlaneError::ThreadLimitReached { max }) => {
+            warn!(
+                max,
+                "thread failed - the process has reached its thread limit"
+            );
+            return Err(Errno::Again);
+        }
         Err(err) => {
             error!(
                 stack_base = layout.stack_lower,
@@ -150,6 +157,7 @@ fn call_module<M: MemorySize>(
 ) -> Result<Tid, Errno> {
     let env = ctx.data(&store);
     let tasks = env.tasks().clone();
+    let _span = env.thread_span().entered();
 
     // This function calls into the module
     let call_module_internal = move |env: &WasiFunctionEnv, store: &mut Store| {
@@ -0,0 +1,51 @@
+#![cfg(not(feature = "js"))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{WasiEnv, WasiRuntimeError};
+
+#[tokio::test]
+async fn test_interrupt_sleep() {
+    let mut store = Store::default();
+    let module = Module::new(
+        &store,
+        br#"
+    (module
+        (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        (func $main (export "_start")
+            ;; Sleep for an hour
+            (call $thread_sleep (i64.const 3600000000000))
+            drop
+        )
+    )
+    "#,
+    )
+    .unwrap();
+
+    let interrupt = store.interrupt_handle();
+    let done = AtomicBool::new(false);
+    let result = std::thread::scope(|scope| {
+        let runner = scope.spawn(|| {
+            let result = WasiEnv::builder("sleep").run_with_store(module, &mut store);
+            done.store(true, Ordering::SeqCst);
+            result
+        });
+        // Interrupts sent before the module starts do nothing
+        while !done.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(50));
+            interrupt.interrupt();
+        }
+        runner.join().unwrap()
+    });
+
+    match result {
+        Err(WasiRuntimeError::Runtime(error)) => assert!(error.is_interrupted()),
+        other => panic!("expected an interrupt, got {other:?}"),
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::Result;
+use wasmer_middlewares::coverage::CoverageReport;
+use wasmer_middlewares::{Coverage, Metering};
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+
+/// `classify`'s blocks are its entry, the `then` and `else` arms of the
+/// `if`, and the function's `end`.
+const CLASSIFY: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (func $classify (export "classify") (param $n i32) (result i32)
+        (if (result i32) (i32.and (local.get $n) (i32.const 1))
+            (then (i32.const 1))
+            (else (i32.const 0))))
+    (func $never (export "never") (result i32)
+        (i32.const 42))
+)
+"#;
+
+fn classify(store: &mut Store, instance: &Instance, n: i32) -> Result<i32> {
+    let classify: TypedFunction<i32, i32> =
+        instance.exports.get_typed_function(store, "classify")?;
+    Ok(classify.call(store, n)?)
+}
+
+/// Singlepass only supports a single memory, so modules which have one of
+/// their own can't be instrumented with it.
+fn supports_coverage(config: &crate::Config) -> bool {
+    config.compiler != crate::Compiler::Singlepass
+}
+
+/// The hits of the blocks of each function, in order.
+fn hits(report: &CoverageReport) -> Vec<Vec<u64>> {
+    report
+        .functions
+        .values()
+        .map(|function| function.blocks.values().map(|block| block.hits).collect())
+        .collect()
+}
+
+#[compiler_test(coverage)]
+fn records_the_blocks_which_ran(mut config: crate::Config) -> Result<()> {
+    if !supports_coverage(&config) {
+        return Ok(());
+    }
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let mut store = config.store();
+    let module = Module::new(&store, CLASSIFY)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    assert_eq!(classify(&mut store, &instance, 2)?, 0);
+    let mut report = CoverageReport::new();
+    report.add_instance(&mut store, &instance);
+
+    assert_eq!(report.functions.keys().copied().collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(report.functions[&0].name.as_deref(), Some("classify"));
+    assert_eq!(hits(&report), [vec![1, 0, 1, 1], vec![0]]);
+
+    // The blocks are recorded in a memory of their own
+    let memory = instance.exports.get_memory("memory")?;
+    assert_eq!(memory.view(&store).read_u8(0)?, 0);
+
+    Ok(())
+}
+
+#[compiler_test(coverage)]
+fn merges_instances(mut config: crate::Config) -> Result<()> {
+    if !supports_coverage(&config) {
+        return Ok(());
+    }
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let mut store = config.store();
+    let module = Module::new(&store, CLASSIFY)?;
+    let odd = Instance::new(&mut store, &module, &imports! {})?;
+    let even = Instance::new(&mut store, &module, &imports! {})?;
+    let idle = Instance::new(&mut store, &module, &imports! {})?;
+
+    classify(&mut store, &odd, 3)?;
+    classify(&mut store, &odd, 5)?;
+    classify(&mut store, &even, 4)?;
+
+    let mut report = CoverageReport::new();
+    for instance in [&odd, &even, &idle] {
+        report.add_instance(&mut store, instance);
+    }
+    assert_eq!(hits(&report), [vec![2, 1, 1, 2], vec![0]]);
+
+    Ok(())
+}
+
+#[compiler_test(coverage)]
+fn coverage_with_metering(mut config: crate::Config) -> Result<()> {
+    if !supports_coverage(&config) {
+        return Ok(());
+    }
+    config
+        .middlewares
+        .push(Arc::new(Metering::new(u64::MAX, |_: &Operator| -> u64 {
+            1
+        })));
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let mut store = config.store();
+    let module = Module::new(&store, CLASSIFY)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    assert_eq!(classify(&mut store, &instance, 7)?, 1);
+    let mut report = CoverageReport::new();
+    report.add_instance(&mut store, &instance);
+    assert_eq!(hits(&report), [vec![1, 1, 0, 1], vec![0]]);
+
+    Ok(())
+}
+
+#[compiler_test(coverage)]
+fn lcov_output(mut config: crate::Config) -> Result<()> {
+    if !supports_coverage(&config) {
+        return Ok(());
+    }
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let mut store = config.store();
+    let module = Module::new(&store, CLASSIFY)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    classify(&mut store, &instance, 2)?;
+    let mut report = CoverageReport::new();
+    report.add_instance(&mut store, &instance);
+
+    // Only `classify` has source lines, one per block from line 10
+    let starts: Vec<usize> = report.functions[&0].blocks.keys().copied().collect();
+    let mut out = vec![];
+    report.write_lcov(&mut out, "classify.wasm", |range| {
+        match starts.iter().position(|start| *start == range.start) {
+            Some(i) => vec![(PathBuf::from("classify.c"), 10 + i as u64)],
+            None => vec![],
+        }
+    })?;
+    let out = String::from_utf8(out)?;
+
+    let expected = "SF:classify.c\n\
+                    FN:10,classify\n\
+                    FNDA:1,classify\n\
+                    FNF:1\n\
+                    FNH:1\n\
+                    DA:10,1\n\
+                    DA:11,0\n\
+                    DA:12,1\n\
+                    DA:13,1\n\
+                    LF:4\n\
+                    LH:3\n\
+                    end_of_record\n";
+    assert!(out.starts_with(expected), "{out}");
+    assert!(
+        out.ends_with(
+            "SF:classify.wasm\nFN:0,never\nFNDA:0,never\nFNF:1\nFNH:0\nLF:0\nLH:0\nend_of_record\n"
+        ),
+        "{out}"
+    );
+
+    Ok(())
+}
+
+#[compiler_test(coverage)]
+fn singlepass_rejects_modules_with_their_own_memory(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(Coverage::new()));
+    let store = config.store();
+    let result = Module::new(&store, CLASSIFY);
+    if supports_coverage(&config) {
+        result?;
+        return Ok(());
+    }
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(&err, CompileError::UnsupportedFeature(feature) if feature.contains("multiple memories")),
+        "{err}"
+    );
+    Ok(())
+}
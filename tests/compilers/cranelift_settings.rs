@@ -0,0 +1,204 @@
+//! Cranelift's tuning settings: the optimization level, the e-graph based
+//! mid-end and the inlining of tiny functions, along with the code size
+//! limit, which mustn't change what modules compute.
+#![cfg(feature = "cranelift")]
+
+use anyhow::Result;
+use std::path::Path;
+use wasmer::*;
+use wasmer_types::TrapCode;
+use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
+use wasmer_wast::Wast;
+
+/// Every combination of the settings.
+fn combinations() -> Vec<Cranelift> {
+    let mut combinations = Vec::new();
+    for opt_level in [
+        CraneliftOptLevel::None,
+        CraneliftOptLevel::Speed,
+        CraneliftOptLevel::SpeedAndSize,
+    ] {
+        for use_egraphs in [false, true] {
+            for inline_threshold in [0, 16] {
+                let mut config = Cranelift::new();
+                config
+                    .opt_level(opt_level)
+                    .use_egraphs(use_egraphs)
+                    .inline_threshold(inline_threshold);
+                config.enable_verifier();
+                combinations.push(config);
+            }
+        }
+    }
+    combinations
+}
+
+fn store(config: Cranelift) -> Store {
+    Store::new(wasmer_compiler::EngineBuilder::new(config).engine())
+}
+
+const HELPERS: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (global $base (mut i32) (i32.const 16))
+    (data (i32.const 16) "\01\02\03\04\05\06\07\08")
+
+    (func $add (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1)))
+    (func $load (param i32) (result i32)
+        (i32.load (i32.add (global.get $base) (local.get 0))))
+    (func $max (param i64 i64) (result i64)
+        (select (local.get 0) (local.get 1) (i64.gt_s (local.get 0) (local.get 1))))
+    (func $splat_add (param i32) (result v128)
+        (i32x4.add (i32x4.splat (local.get 0)) (v128.const i32x4 1 2 3 4)))
+    (func $pair (param i32) (result i32 i32)
+        (local.get 0) (i32.mul (local.get 0) (i32.const 2)))
+
+    (func (export "sum") (param $n i32) (result i32)
+        (local $i i32) (local $sum i32)
+        (block $done
+            (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                (local.set $sum (call $add (local.get $sum) (local.get $i)))
+                (local.set $i (call $add (local.get $i) (i32.const 1)))
+                (br $next)))
+        (local.get $sum))
+    (func (export "load") (param i32) (result i32)
+        (call $load (local.get 0)))
+    (func (export "max") (param i64 i64) (result i64)
+        (call $max (local.get 0) (local.get 1)))
+    (func (export "lane") (param i32) (result i32)
+        (i32x4.extract_lane 3 (call $splat_add (local.get 0))))
+    (func (export "pair") (param i32) (result i32)
+        (call $pair (local.get 0))
+        (i32.sub))
+)
+"#;
+
+#[test]
+fn inlined_functions_compute_the_same() -> Result<()> {
+    for config in combinations() {
+        let settings = format!("{config:?}");
+        let mut store = store(config);
+        let module = Module::new(&store, HELPERS)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let sum = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "sum")?;
+        assert_eq!(sum.call(&mut store, 100)?, 4950, "{settings}");
+        let load = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "load")?;
+        assert_eq!(load.call(&mut store, 0)?, 0x0403_0201, "{settings}");
+        let max = instance
+            .exports
+            .get_typed_function::<(i64, i64), i64>(&store, "max")?;
+        assert_eq!(max.call(&mut store, -5, 3)?, 3, "{settings}");
+        let lane = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "lane")?;
+        assert_eq!(lane.call(&mut store, 10)?, 14, "{settings}");
+        let pair = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "pair")?;
+        assert_eq!(pair.call(&mut store, 7)?, -7, "{settings}");
+
+        // The inlined load still traps out of bounds
+        let err = load.call(&mut store, 65536).unwrap_err();
+        assert_eq!(
+            err.to_trap(),
+            Some(TrapCode::HeapAccessOutOfBounds),
+            "{settings}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn settings_change_the_deterministic_id() {
+    let ids: Vec<String> = combinations()
+        .into_iter()
+        .map(|config| {
+            wasmer_compiler::EngineBuilder::new(config)
+                .engine()
+                .deterministic_id()
+                .to_string()
+        })
+        .collect();
+
+    for (i, id) in ids.iter().enumerate() {
+        assert!(!ids[..i].contains(id), "{id} is repeated");
+    }
+}
+
+/// With a code size limit the module doesn't fit in, tiny functions aren't
+/// inlined anymore, so a trap in `$load` comes from `$load` itself rather
+/// than from the exported function calling it.
+#[test]
+fn code_size_limit_inlines_less() -> Result<()> {
+    let trapping_function = |max_size: Option<u64>| -> Result<u32> {
+        let mut config = Cranelift::new();
+        config.inline_threshold(16).code_size_limit(max_size);
+        let mut store = store(config);
+        let module = Module::new(&store, HELPERS)?;
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+        let load = instance
+            .exports
+            .get_typed_function::<i32, i32>(&store, "load")?;
+        assert_eq!(load.call(&mut store, 4)?, 0x0807_0605);
+        let err = load.call(&mut store, 65536).unwrap_err();
+        assert_eq!(err.clone().to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+        Ok(err.trace()[0].func_index())
+    };
+
+    assert_eq!(trapping_function(None)?, 6);
+    assert_eq!(trapping_function(Some(1 << 20))?, 6);
+    assert_eq!(trapping_function(Some(1))?, 1);
+
+    let id = |max_size: Option<u64>| {
+        let mut config = Cranelift::new();
+        config.code_size_limit(max_size);
+        wasmer_compiler::EngineBuilder::new(config)
+            .engine()
+            .deterministic_id()
+            .to_string()
+    };
+    assert_ne!(id(None), id(Some(1 << 20)));
+
+    Ok(())
+}
+
+/// The spec tests with the most calls to small functions, with every
+/// combination of the settings.
+#[test]
+fn spec_tests_with_every_setting() -> Result<()> {
+    const SPEC_TESTS: &[&str] = &[
+        "address.wast",
+        "call.wast",
+        "conversions.wast",
+        "f32.wast",
+        "f64.wast",
+        "float_exprs.wast",
+        "i32.wast",
+        "i64.wast",
+        "int_exprs.wast",
+        "left-to-right.wast",
+        "memory_trap.wast",
+    ];
+
+    for config in combinations() {
+        let settings = format!("{config:?}");
+        for test in SPEC_TESTS {
+            let mut wast = Wast::new_with_spectest(store(config.clone()));
+            wast.fail_fast = false;
+            let path = Path::new("tests/wast/spec").join(test);
+            wast.run_file(&path)
+                .map_err(|e| e.context(format!("{test} with {settings}")))?;
+        }
+    }
+
+    Ok(())
+}
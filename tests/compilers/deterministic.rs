@@ -1,5 +1,5 @@
 use anyhow::Result;
-use wasmer::{wat2wasm, Module, Store};
+use wasmer::{wat2wasm, CompileThreads, Module, Store};
 
 fn compile_and_compare(wasm: &[u8]) -> Result<()> {
     let store = Store::default();
@@ -42,3 +42,132 @@ fn deterministic_table() -> Result<()> {
 
     compile_and_compare(&wasm_bytes)
 }
+
+/// Compiles `wasm` on `jobs` threads, and serializes the artifact.
+fn compile_with_jobs(config: &crate::Config, wasm: &[u8], jobs: usize) -> Result<Vec<u8>> {
+    let engine = wasmer_compiler::EngineBuilder::new(config.compiler_config(false))
+        .set_compile_threads(CompileThreads::new(jobs))
+        .engine();
+    let module = Module::new(&Store::new(engine), wasm)?;
+    Ok(module.serialize()?.to_vec())
+}
+
+#[compiler_test(deterministic)]
+fn deterministic_whatever_the_threads(config: crate::Config) -> Result<()> {
+    // Enough functions, trampolines and imports to be spread over threads
+    let mut wat = String::from("(module\n");
+    for i in 0..16 {
+        wat += &format!(
+            "(import \"env\" \"f{i}\" (func (param i32) (result i{})))\n",
+            if i % 2 == 0 { 32 } else { 64 }
+        );
+    }
+    for i in 0..128 {
+        wat += &format!(
+            "(func (export \"g{i}\") (param i32 i64) (result i64)
+                (i64.add (i64.extend_i32_u (i32.mul (local.get 0) (i32.const {i})))
+                         (call $h (local.get 1))))\n"
+        );
+    }
+    wat += "(func $h (param i64) (result i64) (i64.rotl (local.get 0) (i64.const 7))))";
+    let wasm = wat2wasm(wat.as_bytes())?;
+
+    let sequential = compile_with_jobs(&config, &wasm, 0)?;
+    assert!(compile_with_jobs(&config, &wasm, 1)? == sequential);
+    assert!(compile_with_jobs(&config, &wasm, 8)? == sequential);
+
+    Ok(())
+}
+
+/// Modules exercising the parts of the artifact kept in maps, or which
+/// could pick up something from the host: names, passive segments, custom
+/// sections, and lots of functions and trampolines.
+fn corpus() -> Vec<String> {
+    let mut functions = String::from("(module $many\n");
+    for i in 0..64 {
+        functions += &format!(
+            "(func $f{i} (export \"f{i}\") (param i32) (result i32)
+                (i32.add (local.get 0) (i32.const {i})))\n"
+        );
+    }
+    functions += ")";
+
+    vec![
+        r#"(module $named
+            (func $first (export "first") (result i32) (call $second))
+            (func $second (result i32) (i32.const 42))
+            (func $third (param f64) (result f64) (f64.sqrt (local.get 0))))"#
+            .to_string(),
+        r#"(module
+            (memory 1)
+            (table 4 funcref)
+            (func $a) (func $b) (func $c)
+            (elem $e0 func $a $b)
+            (elem $e1 func $c)
+            (elem $e2 func $b $c $a)
+            (data $d0 "first")
+            (data $d1 "second")
+            (data $d2 "third")
+            (func (export "init")
+                (table.init $e2 (i32.const 0) (i32.const 0) (i32.const 3))
+                (memory.init $d1 (i32.const 0) (i32.const 0) (i32.const 6))
+                (elem.drop $e0)
+                (data.drop $d0)))"#
+            .to_string(),
+        r#"(module
+            (import "env" "log" (func $log (param i32 i64 f32 f64)))
+            (import "env" "memory" (memory 1))
+            (global $g (mut i32) (i32.const 7))
+            (func (export "run") (param i32)
+                (call $log (local.get 0) (i64.const 1) (f32.const 2) (f64.const 3))
+                (global.set $g (local.get 0))))"#
+            .to_string(),
+        functions,
+    ]
+}
+
+#[compiler_test(deterministic)]
+fn deterministic_corpus(config: crate::Config) -> Result<()> {
+    for wat in corpus() {
+        let mut wasm = wat2wasm(wat.as_bytes())?.to_vec();
+        // Custom sections are kept in the artifact too
+        for (name, contents) in [("b", "second"), ("a", "first"), ("b", "third")] {
+            wasm.push(0);
+            wasm.push((1 + name.len() + contents.len()) as u8);
+            wasm.push(name.len() as u8);
+            wasm.extend_from_slice(name.as_bytes());
+            wasm.extend_from_slice(contents.as_bytes());
+        }
+
+        // Each compilation is on a new engine, so nothing is shared
+        let first = compile_with_jobs(&config, &wasm, 0)?;
+        let second = compile_with_jobs(&config, &wasm, 0)?;
+        let parallel = compile_with_jobs(&config, &wasm, 4)?;
+
+        assert!(first == second, "{wat}");
+        assert!(first == parallel, "{wat}");
+    }
+
+    Ok(())
+}
+
+#[compiler_test(deterministic)]
+fn deterministic_wherever_the_file(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wasm = wat2wasm(br#"(module (func (export "run")))"#)?;
+    let first_dir = tempfile::tempdir()?;
+    let second_dir = tempfile::tempdir()?;
+    std::fs::create_dir(second_dir.path().join("nested"))?;
+    let first_path = first_dir.path().join("module.wasm");
+    let second_path = second_dir.path().join("nested").join("module.wasm");
+    std::fs::write(&first_path, &wasm)?;
+    std::fs::write(&second_path, &wasm)?;
+
+    let first = Module::from_file(&store, &first_path)?;
+    let second = Module::from_file(&store, &second_path)?;
+
+    assert_eq!(first.name(), Some("module"));
+    assert!(first.serialize()? == second.serialize()?);
+
+    Ok(())
+}
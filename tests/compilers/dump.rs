@@ -0,0 +1,94 @@
+//! Dumping each compiled function's WebAssembly, IR and machine code with
+//! `CompilerConfig::enable_dump`.
+
+use anyhow::Result;
+use std::path::Path;
+use wasmer::*;
+
+const WAT: &str = r#"
+    (module $dumped
+        (func $add (export "add") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.add)
+        (func $add_twice (export "add_twice") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            call $add
+            local.get 1
+            call $add)
+    )
+"#;
+
+fn compile_with_dump(config: &crate::Config, dir: &Path, filters: Vec<DumpFilter>) -> Result<()> {
+    let mut compiler_config = config.compiler_config(false);
+    compiler_config.enable_dump(CompilerDump::new(dir, filters));
+    let store = Store::new(config.engine(compiler_config));
+    Module::new(&store, WAT)?;
+    Ok(())
+}
+
+fn read_index(dir: &Path) -> Result<serde_json::Value> {
+    let index = std::fs::read(dir.join("index.json"))?;
+    Ok(serde_json::from_slice(&index)?)
+}
+
+#[compiler_test(dump)]
+fn dumps_every_function(config: crate::Config) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    compile_with_dump(&config, dir.path(), vec![])?;
+
+    let index = read_index(dir.path())?;
+    assert_eq!(index["module"], "dumped");
+    let functions = index["functions"].as_array().unwrap();
+    assert_eq!(functions.len(), 2);
+    assert_eq!(functions[0]["index"], 0);
+    assert_eq!(functions[0]["name"], "add");
+    assert_eq!(functions[0]["wat"], "00000-add.wat");
+    assert_eq!(functions[1]["index"], 1);
+    assert_eq!(functions[1]["name"], "add_twice");
+
+    for function in functions {
+        let wat = std::fs::read(dir.path().join(function["wat"].as_str().unwrap()))?;
+        // Each function is dumped as a module of its own
+        wat2wasm(&wat)?;
+
+        let disassembly =
+            std::fs::read_to_string(dir.path().join(function["disassembly"].as_str().unwrap()))?;
+        assert!(!disassembly.is_empty());
+
+        match config.compiler {
+            crate::Compiler::Singlepass => assert!(function["ir"].is_null()),
+            _ => {
+                let ir =
+                    std::fs::read_to_string(dir.path().join(function["ir"].as_str().unwrap()))?;
+                assert!(!ir.is_empty());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[compiler_test(dump)]
+fn dumps_only_the_selected_functions(config: crate::Config) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    compile_with_dump(&config, dir.path(), vec!["add_twice".parse().unwrap()])?;
+
+    let index = read_index(dir.path())?;
+    let functions = index["functions"].as_array().unwrap();
+    assert_eq!(functions.len(), 1);
+    assert_eq!(functions[0]["name"], "add_twice");
+    assert!(!dir.path().join("00000-add.wat").exists());
+    assert!(dir.path().join("00001-add_twice.wat").exists());
+
+    let dir = tempfile::tempdir()?;
+    compile_with_dump(&config, dir.path(), vec![DumpFilter::Index(0)])?;
+
+    let index = read_index(dir.path())?;
+    let functions = index["functions"].as_array().unwrap();
+    assert_eq!(functions.len(), 1);
+    assert_eq!(functions[0]["name"], "add");
+
+    Ok(())
+}
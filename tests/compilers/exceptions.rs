@@ -0,0 +1,134 @@
+//! The exception handling proposal: exceptions thrown by WebAssembly code or
+//! host functions are caught by `try` blocks, and the uncaught ones come
+//! back to the host.
+
+use crate::{Compiler, Config};
+use anyhow::Result;
+use wasmer::*;
+
+/// The store for `config` with the exception handling proposal, if the
+/// compiler supports it.
+fn exceptions_store(mut config: Config) -> Option<Store> {
+    let mut features = Features::default();
+    features.exceptions(true);
+    config.set_features(features);
+    let store = config.store();
+
+    if config.compiler != Compiler::Cranelift {
+        // The other compilers refuse to compile anything with the proposal
+        // enabled
+        let err = Module::new(&store, "(module)").unwrap_err();
+        assert!(err.to_string().contains("exceptions"), "{err}");
+        return None;
+    }
+    Some(store)
+}
+
+#[compiler_test(exceptions)]
+fn host_exceptions_are_caught(config: crate::Config) -> Result<()> {
+    let mut store = match exceptions_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (tag $e (import "host" "e") (param i32))
+            (func $throw (import "host" "throw") (param i32))
+
+            (func (export "catch") (param i32) (result i32)
+                (try (result i32)
+                    (do (call $throw (local.get 0)) (i32.const -1))
+                    (catch $e (i32.add (i32.const 1)))))
+            (func (export "rethrow") (param i32)
+                (try
+                    (do (call $throw (local.get 0)))
+                    (catch_all (rethrow 0))))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let tag = Tag::new(&mut store, [Type::I32]);
+    let env = FunctionEnv::new(&mut store, tag.clone());
+    let throw = Function::new_typed_with_env(
+        &mut store,
+        &env,
+        |env: FunctionEnvMut<Tag>, value: i32| -> Result<(), RuntimeError> {
+            let tag = env.data().clone();
+            Err(Exception::new(&env, &tag, &[Value::I32(value)])?.into())
+        },
+    );
+    let imports = imports! {
+        "host" => {
+            "e" => tag.clone(),
+            "throw" => throw,
+        }
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let catch: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "catch")?;
+    assert_eq!(catch.call(&mut store, 41)?, 42);
+
+    let rethrow: TypedFunction<i32, ()> = instance.exports.get_typed_function(&store, "rethrow")?;
+    let e = rethrow.call(&mut store, 7).unwrap_err();
+    let exception = e.downcast_ref::<Exception>().unwrap();
+    assert_eq!(exception.tag(), &tag);
+    assert_eq!(exception.payload(), &[Value::I32(7)]);
+
+    // Nothing is left pending after the exception came back to the host
+    assert_eq!(catch.call(&mut store, 1)?, 2);
+    Ok(())
+}
+
+#[compiler_test(exceptions)]
+fn guest_exceptions_go_through_host_functions(config: crate::Config) -> Result<()> {
+    let mut store = match exceptions_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (tag $e (export "e") (param i64 f64))
+            (func $call_back (import "host" "call_back"))
+
+            (func (export "throw") (throw $e (i64.const 3) (f64.const 0.5)))
+            (func (export "catch") (result f64)
+                (local $f f64)
+                (try (result f64)
+                    (do (call $call_back) (f64.const 0))
+                    (catch $e
+                        (local.set $f)
+                        (f64.add (f64.convert_i64_s) (local.get $f)))))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+
+    // Calls `throw`, and returns the exception it gets back to the caller
+    let env = FunctionEnv::new(&mut store, None::<Instance>);
+    let call_back = Function::new_typed_with_env(
+        &mut store,
+        &env,
+        |mut env: FunctionEnvMut<Option<Instance>>| -> Result<(), RuntimeError> {
+            let instance = env.data().clone().unwrap();
+            let throw = instance.exports.get_function("throw").unwrap();
+            throw.call(&mut env, &[])?;
+            Ok(())
+        },
+    );
+    let imports = imports! {
+        "host" => {
+            "call_back" => call_back,
+        }
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    *env.as_mut(&mut store) = Some(instance.clone());
+
+    let catch: TypedFunction<(), f64> = instance.exports.get_typed_function(&store, "catch")?;
+    assert_eq!(catch.call(&mut store)?, 0.5 + 3.0);
+
+    let tag = instance.exports.get::<Tag>("e")?;
+    let throw = instance.exports.get_function("throw")?;
+    let e = throw.call(&mut store, &[]).unwrap_err();
+    let exception = e.downcast_ref::<Exception>().unwrap();
+    assert_eq!(exception.tag(), tag);
+    assert_eq!(exception.payload(), &[Value::I64(3), Value::F64(0.5)]);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! The extended constant expressions proposal, which allows `add`, `sub`
+//! and `mul` in the initializers of globals and the offsets of data and
+//! element segments.
+
+use anyhow::Result;
+use wasmer::*;
+
+fn extended_const_store(mut config: crate::Config) -> Store {
+    let mut features = Features::default();
+    features.extended_const = true;
+    config.set_features(features);
+    config.store()
+}
+
+#[compiler_test(extended_const)]
+fn global_initializers(config: crate::Config) -> Result<()> {
+    let mut store = extended_const_store(config);
+    let wat = r#"
+        (module
+            (import "env" "base" (global $base i32))
+            (import "env" "base64" (global $base64 i64))
+            (global (export "constant") i32 (i32.mul (i32.const 6) (i32.add (i32.const 3) (i32.const 4))))
+            (global (export "offset") i32 (i32.add (global.get $base) (i32.const 16)))
+            (global (export "before") i32 (i32.sub (global.get $base) (i32.const 4)))
+            (global (export "wrapped") i32 (i32.add (global.get $base) (i32.const 0x7fffffff)))
+            (global (export "offset64") i64 (i64.add (i64.const -8) (global.get $base64)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let imports = imports! {
+        "env" => {
+            "base" => Global::new(&mut store, Value::I32(1024)),
+            "base64" => Global::new(&mut store, Value::I64(1 << 40)),
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let global = |store: &mut Store, name: &str| -> Result<Value> {
+        Ok(instance.exports.get_global(name)?.get(store))
+    };
+    assert_eq!(global(&mut store, "constant")?, Value::I32(42));
+    assert_eq!(global(&mut store, "offset")?, Value::I32(1040));
+    assert_eq!(global(&mut store, "before")?, Value::I32(1020));
+    assert_eq!(
+        global(&mut store, "wrapped")?,
+        Value::I32(1024_i32.wrapping_add(i32::MAX))
+    );
+    assert_eq!(global(&mut store, "offset64")?, Value::I64((1 << 40) - 8));
+
+    Ok(())
+}
+
+#[compiler_test(extended_const)]
+fn segment_offsets(config: crate::Config) -> Result<()> {
+    let mut store = extended_const_store(config);
+    let wat = r#"
+        (module
+            (import "env" "base" (global $base i32))
+            (memory 1)
+            (table 4 funcref)
+            (data (offset (i32.add (global.get $base) (i32.const 8))) "\2a")
+            (elem (offset (i32.sub (global.get $base) (i32.const 97))) $answer)
+            (func $answer (result i32) i32.const 42)
+            (func (export "load") (param i32) (result i32)
+                (i32.load8_u (local.get 0)))
+            (func (export "call") (param i32) (result i32)
+                (call_indirect (result i32) (local.get 0)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let imports = imports! {
+        "env" => {
+            "base" => Global::new(&mut store, Value::I32(100)),
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let load = instance
+        .exports
+        .get_typed_function::<i32, i32>(&store, "load")?;
+    assert_eq!(load.call(&mut store, 108)?, 42);
+    assert_eq!(load.call(&mut store, 107)?, 0);
+    let call = instance
+        .exports
+        .get_typed_function::<i32, i32>(&store, "call")?;
+    assert_eq!(call.call(&mut store, 3)?, 42);
+
+    Ok(())
+}
+
+#[compiler_test(extended_const)]
+fn segment_offsets_out_of_bounds(config: crate::Config) -> Result<()> {
+    let mut store = extended_const_store(config);
+    let wat = r#"
+        (module
+            (import "env" "base" (global $base i32))
+            (memory 1)
+            (data (offset (i32.add (global.get $base) (i32.const 65536))) "\2a")
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let imports = imports! {
+        "env" => {
+            "base" => Global::new(&mut store, Value::I32(0)),
+        },
+    };
+    assert!(Instance::new(&mut store, &module, &imports).is_err());
+
+    Ok(())
+}
+
+#[compiler_test(extended_const)]
+fn disabled(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (global i32 (i32.add (i32.const 1) (i32.const 2)))
+        )
+    "#;
+    assert!(Module::new(&store, wat).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Checking the requested WebAssembly proposals against what each compiler
+//! supports.
+use anyhow::Result;
+use wasmer::*;
+
+use crate::Compiler;
+
+/// The proposals each compiler is known not to support.
+fn unsupported_proposals(compiler: &Compiler) -> Vec<&'static str> {
+    match compiler {
+        Compiler::Singlepass => vec![
+            "simd",
+            "multi-value",
+            "tail-call",
+            "module-linking",
+            "memory64",
+            "exceptions",
+            "relaxed-simd",
+        ],
+        Compiler::Cranelift => vec!["module-linking"],
+        Compiler::LLVM => vec![
+            "tail-call",
+            "module-linking",
+            "memory64",
+            "exceptions",
+            "relaxed-simd",
+        ],
+    }
+}
+
+#[compiler_test(features)]
+fn requesting_unsupported_proposals_is_an_error(config: crate::Config) -> Result<()> {
+    let compiler_config = config.compiler_config(false);
+
+    let err = FeaturesBuilder::from(Features::none())
+        .threads(true)
+        .simd(true)
+        .multi_value(true)
+        .tail_call(true)
+        .module_linking(true)
+        .memory64(true)
+        .exceptions(true)
+        .relaxed_simd(true)
+        .extended_const(true)
+        .build_for(&*compiler_config)
+        .unwrap_err();
+
+    assert_eq!(err.proposals, unsupported_proposals(&config.compiler));
+    Ok(())
+}
+
+#[compiler_test(features)]
+fn unsupported_defaults_are_turned_off(config: crate::Config) -> Result<()> {
+    let compiler_config = config.compiler_config(false);
+
+    let features = FeaturesBuilder::from(Features::all()).build_for(&*compiler_config)?;
+
+    let unsupported = unsupported_proposals(&config.compiler);
+    for proposal in Features::PROPOSALS {
+        assert_eq!(
+            features.is_enabled(proposal),
+            Some(!unsupported.contains(&proposal)),
+            "{proposal}"
+        );
+    }
+    Ok(())
+}
+
+#[compiler_test(features)]
+fn engine_reports_its_features(config: crate::Config) -> Result<()> {
+    let compiler_config = config.compiler_config(false);
+    let features = Features::builder()
+        .threads(false)
+        .build_for(&*compiler_config)?;
+    let engine: Engine = EngineBuilder::new(compiler_config)
+        .set_features(Some(features.clone()))
+        .engine()
+        .into();
+
+    assert_eq!(engine.features(), features);
+    assert!(!engine.features().threads);
+
+    let wasm = wat2wasm(br#"(module (memory 1 1 shared))"#)?;
+    let required = Module::required_features(&wasm)?;
+    assert_eq!(required.enabled(), vec!["threads"]);
+    assert!(Module::new(&engine, &wasm).is_err());
+    Ok(())
+}
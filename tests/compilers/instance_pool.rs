@@ -0,0 +1,257 @@
+//! Reusing instances through an `InstancePool`, checked against instances
+//! that were created from scratch.
+
+use anyhow::Result;
+use wasmer::*;
+
+const MODULE: &str = r#"
+    (module
+        (import "host" "seed" (global $seed i32))
+        (memory (export "memory") 1 4)
+        (table (export "table") 2 10 funcref)
+        (global $counter (export "counter") (mut i32) (i32.const 0))
+        (global $started (export "started") (mut i32) (i32.const 0))
+
+        (data (i32.const 16) "active data")
+        (data $passive "passive data")
+        (elem $fns func $one $two)
+
+        (func $one (result i32)
+            i32.const 1)
+        (func $two (result i32)
+            i32.const 2)
+
+        (func $start
+            (global.set $started (global.get $seed))
+            (i32.store (i32.const 0) (i32.const 0xdead)))
+        (start $start)
+
+        ;; Changes everything the pool has to reset
+        (func (export "mutate")
+            (memory.init $passive (i32.const 100) (i32.const 0) (i32.const 12))
+            data.drop $passive
+            (table.init $fns (i32.const 0) (i32.const 0) (i32.const 2))
+            elem.drop $fns
+            (drop (table.grow (ref.null func) (i32.const 3)))
+            (drop (memory.grow (i32.const 2)))
+            (i32.store (i32.const 70000) (i32.const 7))
+            (memory.fill (i32.const 16) (i32.const 0xff) (i32.const 11))
+            (global.set $counter (i32.add (global.get $counter) (i32.const 1))))
+
+        (func (export "copy_passive") (param $dst i32)
+            (memory.init $passive (local.get $dst) (i32.const 0) (i32.const 12)))
+
+        (func (export "init_table")
+            (table.init $fns (i32.const 0) (i32.const 0) (i32.const 2))))
+"#;
+
+fn imports(store: &mut Store) -> Imports {
+    imports! {
+        "host" => {
+            "seed" => Global::new(store, Value::I32(42)),
+        },
+    }
+}
+
+/// Everything a request can see of an instance.
+#[derive(Debug, PartialEq)]
+struct Observation {
+    memory: Vec<u8>,
+    counter: Value,
+    started: Value,
+    table: Vec<bool>,
+    passive_data: bool,
+    passive_elements: bool,
+}
+
+fn observe(instance: &Instance, store: &mut Store) -> Result<Observation> {
+    let table = instance.exports.get_table("table")?;
+    let copy_passive: TypedFunction<i32, ()> =
+        instance.exports.get_typed_function(store, "copy_passive")?;
+    let init_table: TypedFunction<(), ()> =
+        instance.exports.get_typed_function(store, "init_table")?;
+
+    let memory = instance
+        .exports
+        .get_memory("memory")?
+        .view(store)
+        .copy_to_vec()?;
+    let table = (0..table.size(store))
+        .map(|i| matches!(table.get(store, i), Some(Value::FuncRef(Some(_)))))
+        .collect();
+
+    Ok(Observation {
+        memory,
+        counter: instance.exports.get_global("counter")?.get(store),
+        started: instance.exports.get_global("started")?.get(store),
+        table,
+        // Both of these trap once the segments have been dropped
+        passive_data: copy_passive.call(store, 200).is_ok(),
+        passive_elements: init_table.call(store).is_ok(),
+    })
+}
+
+/// What a request which changes everything it can sees.
+fn request(instance: &Instance, store: &mut Store) -> Result<Vec<Observation>> {
+    let mutate: TypedFunction<(), ()> = instance.exports.get_typed_function(store, "mutate")?;
+    let before = observe(instance, store)?;
+    mutate.call(store)?;
+    let after = observe(instance, store)?;
+    Ok(vec![before, after])
+}
+
+#[compiler_test(instance_pool)]
+fn reused_instances_behave_like_fresh_ones(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let engine = store.engine().clone();
+    let module = Module::new(&store, MODULE)?;
+
+    let expected = {
+        let mut store = Store::new(engine.clone());
+        let imports = imports(&mut store);
+        let instance = Instance::new(&mut store, &module, &imports)?;
+        request(&instance, &mut store)?
+    };
+    // The request actually changed things
+    assert_ne!(expected[0], expected[1]);
+
+    let pool = InstancePool::new(engine, 1).with_imports(|store, _| imports(store));
+    for _ in 0..3 {
+        let mut pooled = pool.get(&module)?;
+        let (instance, store) = pooled.instance_and_store_mut();
+        assert_eq!(request(instance, store)?, expected);
+    }
+
+    let stats = pool.stats();
+    assert_eq!(stats.instantiations, 1);
+    assert_eq!(stats.reuses, 2);
+    assert_eq!(stats.discarded, 0);
+
+    Ok(())
+}
+
+#[compiler_test(instance_pool)]
+fn reused_instances_survive_traps(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory (export "memory") 1)
+            (global $count (mut i32) (i32.const 0))
+            (func (export "count_then_trap") (result i32)
+                (global.set $count (i32.add (global.get $count) (i32.const 1)))
+                (i32.store (i32.const 0) (global.get $count))
+                (if (i32.gt_u (global.get $count) (i32.const 1)) (then unreachable))
+                (global.get $count)))"#,
+    )?;
+
+    let pool = InstancePool::new(store.engine().clone(), 1);
+    for _ in 0..3 {
+        let mut pooled = pool.get(&module)?;
+        let (instance, store) = pooled.instance_and_store_mut();
+        let count: TypedFunction<(), i32> = instance
+            .exports
+            .get_typed_function(store, "count_then_trap")?;
+        assert_eq!(count.call(store)?, 1);
+        assert!(count.call(store).is_err());
+    }
+    assert_eq!(pool.stats().reuses, 2);
+
+    Ok(())
+}
+
+#[compiler_test(instance_pool)]
+fn pool_occupancy(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let first = Module::new(&store, "(module (memory 1))")?;
+    let second = Module::new(&store, "(module (table 1 funcref))")?;
+
+    let pool = InstancePool::new(store.engine().clone(), 2);
+    pool.preallocate(&first, 5)?;
+    assert_eq!(
+        pool.stats(),
+        InstancePoolStats {
+            capacity: 2,
+            in_use: 0,
+            idle: 2,
+            instantiations: 2,
+            reuses: 0,
+            discarded: 0,
+        }
+    );
+
+    let a = pool.get(&first)?;
+    let b = pool.get(&first)?;
+    assert_eq!(pool.stats().in_use, 2);
+    assert_eq!(pool.stats().idle, 0);
+    assert!(matches!(
+        pool.get(&first),
+        Err(InstancePoolError::Exhausted { capacity: 2 })
+    ));
+    drop(a);
+
+    // The idle instance of the other module makes room for this one
+    let c = pool.get(&second)?;
+    assert_eq!(c.module(), &second);
+    drop(b);
+    drop(c);
+
+    let stats = pool.stats();
+    assert_eq!(stats.in_use, 0);
+    assert_eq!(stats.idle, 2);
+    assert_eq!(stats.instantiations, 3);
+    assert_eq!(stats.reuses, 2);
+
+    Ok(())
+}
+
+#[compiler_test(instance_pool)]
+fn changed_stores_are_not_reused(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, "(module (memory 1))")?;
+
+    let pool = InstancePool::new(store.engine().clone(), 1);
+    let mut pooled = pool.get(&module)?;
+    Function::new_typed(pooled.store_mut(), || {});
+    drop(pooled);
+
+    let stats = pool.stats();
+    assert_eq!(stats.idle, 0);
+    assert_eq!(stats.discarded, 1);
+
+    // The slot is free again
+    pool.get(&module)?;
+    assert_eq!(pool.stats().instantiations, 2);
+
+    Ok(())
+}
+
+#[compiler_test(instance_pool)]
+fn max_memory(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (memory 1)
+            (func (export "grow") (param i32) (result i32)
+                (memory.grow (local.get 0))))"#,
+    )?;
+    let too_big = Module::new(&store, "(module (memory 3))")?;
+
+    let pool = InstancePool::new(store.engine().clone(), 2).with_max_memory(Pages(2));
+    for _ in 0..2 {
+        let mut pooled = pool.get(&module)?;
+        let (instance, store) = pooled.instance_and_store_mut();
+        let grow: TypedFunction<i32, i32> = instance.exports.get_typed_function(store, "grow")?;
+        assert_eq!(grow.call(store, 1)?, 1);
+        assert_eq!(grow.call(store, 1)?, -1);
+    }
+
+    assert!(matches!(
+        pool.get(&too_big),
+        Err(InstancePoolError::Instantiation(_))
+    ));
+    assert_eq!(pool.stats().in_use, 0);
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Stopping running calls from another thread with an `InterruptHandle`.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+const MODULE: &str = r#"
+    (module
+        (import "host" "sleep" (func $sleep))
+
+        (func (export "spin")
+            (loop $forever
+                br $forever))
+
+        (func (export "sleep")
+            call $sleep)
+
+        (func (export "add") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.add))
+"#;
+
+/// Runs `call` while interrupting the store from another thread. Interrupts
+/// sent before the call starts do nothing, so they're sent until it returns.
+fn interrupt_during<T>(interrupt: InterruptHandle, call: impl FnOnce() -> T) -> T {
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            while !done.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(50));
+                interrupt.interrupt();
+            }
+        });
+        let result = call();
+        done.store(true, Ordering::SeqCst);
+        result
+    })
+}
+
+fn instantiate(store: &mut Store) -> Result<Instance> {
+    let module = Module::new(store, MODULE)?;
+    let interrupt = store.interrupt_handle();
+    let sleep = Function::new_typed(store, move || {
+        // Stands in for a blocking syscall, which gives up once interrupted
+        while !interrupt.is_interrupted() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+    Ok(Instance::new(
+        store,
+        &module,
+        &imports! {
+            "host" => {
+                "sleep" => sleep,
+            },
+        },
+    )?)
+}
+
+// Compiled code is only stopped by signals on unix
+#[cfg(unix)]
+#[compiler_test(interrupt)]
+fn interrupt_cpu_loop(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let instance = instantiate(&mut store)?;
+    let spin: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "spin")?;
+
+    let error = interrupt_during(store.interrupt_handle(), || spin.call(&mut store)).unwrap_err();
+    assert!(error.is_interrupted());
+    assert_eq!(error.to_trap(), Some(TrapCode::Interrupted));
+
+    Ok(())
+}
+
+#[compiler_test(interrupt)]
+fn interrupt_sleeping_guest(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let instance = instantiate(&mut store)?;
+    let sleep: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "sleep")?;
+
+    let error = interrupt_during(store.interrupt_handle(), || sleep.call(&mut store)).unwrap_err();
+    assert!(error.is_interrupted());
+
+    Ok(())
+}
+
+#[compiler_test(interrupt)]
+fn store_is_usable_after_interrupt(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let instance = instantiate(&mut store)?;
+    let sleep: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "sleep")?;
+    let add: TypedFunction<(i32, i32), i32> = instance.exports.get_typed_function(&store, "add")?;
+
+    let error = interrupt_during(store.interrupt_handle(), || sleep.call(&mut store)).unwrap_err();
+    assert!(error.is_interrupted());
+    assert_eq!(add.call(&mut store, 1, 2)?, 3);
+
+    // Nothing is running, so this doesn't affect later calls
+    store.interrupt_handle().interrupt();
+    assert!(!store.interrupt_handle().is_interrupted());
+    assert_eq!(add.call(&mut store, 3, 4)?, 7);
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Modules compiled one function at a time, as each function is first
+//! called. Compilers without lazy compilation just compile everything up
+//! front, so these should pass with all of them.
+
+use anyhow::Result;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+fn lazy_store(config: &crate::Config) -> Store {
+    let mut compiler_config = config.compiler_config(false);
+    compiler_config.enable_lazy_compilation(true);
+    Store::new(config.engine(compiler_config))
+}
+
+const CALLS: &str = r#"
+    (module $calls
+        (type $unary (func (param i32) (result i32)))
+        (table 2 funcref)
+        (elem (i32.const 0) $double $fib)
+
+        (func $fib (export "fib") (param i32) (result i32)
+            local.get 0
+            i32.const 2
+            i32.lt_u
+            if (result i32)
+                local.get 0
+            else
+                local.get 0
+                i32.const 1
+                i32.sub
+                call $fib
+                local.get 0
+                i32.const 2
+                i32.sub
+                call $fib
+                i32.add
+            end)
+
+        (func $double (param i32) (result i32)
+            local.get 0
+            i32.const 2
+            i32.mul)
+
+        (func (export "call_indirect") (param i32 i32) (result i32)
+            local.get 1
+            local.get 0
+            call_indirect (type $unary))
+
+        (func (export "fail")
+            call $unreachable)
+
+        (func $unreachable
+            unreachable))
+"#;
+
+#[compiler_test(lazy)]
+fn recursive_calls(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let module = Module::new(&store, CALLS)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let fib: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 20)?, 6765);
+    // Everything has been compiled by now
+    assert_eq!(fib.call(&mut store, 10)?, 55);
+
+    Ok(())
+}
+
+#[compiler_test(lazy)]
+fn indirect_calls(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let module = Module::new(&store, CALLS)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let call_indirect: TypedFunction<(i32, i32), i32> = instance
+        .exports
+        .get_typed_function(&store, "call_indirect")?;
+    assert_eq!(call_indirect.call(&mut store, 0, 21)?, 42);
+    assert_eq!(call_indirect.call(&mut store, 1, 10)?, 55);
+    assert_eq!(call_indirect.call(&mut store, 0, 4)?, 8);
+
+    Ok(())
+}
+
+#[compiler_test(lazy)]
+fn each_instance_shares_compiled_functions(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let module = Module::new(&store, CALLS)?;
+    let first = Instance::new(&mut store, &module, &imports! {})?;
+    let second = Instance::new(&mut store, &module, &imports! {})?;
+
+    let fib: TypedFunction<i32, i32> = first.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 15)?, 610);
+    let fib: TypedFunction<i32, i32> = second.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 15)?, 610);
+
+    Ok(())
+}
+
+#[compiler_test(lazy)]
+fn traps_in_lazily_compiled_functions(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let module = Module::new(&store, CALLS)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let fail: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "fail")?;
+    let error = fail.call(&mut store).unwrap_err();
+
+    assert_eq!(
+        error.clone().to_trap(),
+        Some(TrapCode::UnreachableCodeReached)
+    );
+    let trace = error.trace();
+    assert_eq!(trace[0].module_name(), "calls");
+    assert_eq!(trace[0].function_name(), Some("unreachable"));
+    // Getting back to the caller needs the unwind info of both functions
+    assert_eq!(trace.len(), 2);
+    assert_eq!(trace[1].func_index(), 3);
+
+    Ok(())
+}
+
+#[compiler_test(lazy)]
+fn serialize_lazily_compiled_module(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let module = Module::new(&store, CALLS)?;
+    // Call something first, so only part of the module has been compiled
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let fib: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 5)?, 5);
+
+    let serialized = module.serialize()?;
+
+    let mut headless_store = config.headless_store();
+    let module = unsafe { Module::deserialize(&headless_store, serialized)? };
+    assert_eq!(module.name(), Some("calls"));
+    let instance = Instance::new(&mut headless_store, &module, &imports! {})?;
+    let call_indirect: TypedFunction<(i32, i32), i32> = instance
+        .exports
+        .get_typed_function(&headless_store, "call_indirect")?;
+    assert_eq!(call_indirect.call(&mut headless_store, 1, 10)?, 55);
+
+    Ok(())
+}
+
+#[compiler_test(lazy)]
+fn perfmap_has_lazily_compiled_functions(config: crate::Config) -> Result<()> {
+    let mut store = lazy_store(&config);
+    let dir = tempfile::tempdir()?;
+    let perfmap = dir.path().join("perf.map");
+    store.engine().enable_perfmap(&perfmap)?;
+
+    let module = Module::new(&store, CALLS)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let fib: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "fib")?;
+    assert_eq!(fib.call(&mut store, 5)?, 5);
+
+    // The stubs are written when the module is instantiated, and the real
+    // body of each function when it's compiled
+    let contents = std::fs::read_to_string(&perfmap)?;
+    let fib_entries = contents
+        .lines()
+        .filter(|line| line.ends_with(" calls::fib"))
+        .count();
+    let expected = if store.engine().lazy_compilation() {
+        2
+    } else {
+        1
+    };
+    assert_eq!(fib_entries, expected, "{contents}");
+
+    Ok(())
+}
@@ -6,13 +6,31 @@
 extern crate compiler_test_derive;
 
 mod config;
+mod coverage;
+mod cranelift_settings;
 mod deterministic;
+mod dump;
+mod exceptions;
+mod extended_const;
+mod features;
 mod imports;
+mod instance_pool;
+mod interrupt;
 mod issues;
+mod lazy;
+mod memory64;
+mod memory_buffer;
 mod metering;
 mod middlewares;
+mod nan_canonicalization;
+mod profiling;
+mod reference_types;
+mod relaxed_simd;
 // mod multi_value_imports;
 mod serialize;
+mod shifts;
+mod stack_limit;
+mod tail_calls;
 mod traps;
 mod typed_functions;
 mod wasi;
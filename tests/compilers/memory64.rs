@@ -0,0 +1,100 @@
+//! The 64-bit memory proposal: memories indexed with `i64` addresses, which
+//! can be bigger than 4GiB.
+
+use crate::{Compiler, Config};
+use anyhow::Result;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+/// The first address past 4GiB.
+const FOUR_GIB: u64 = 1 << 32;
+
+/// The store for `config` with the 64-bit memory proposal, if the compiler
+/// supports it.
+fn memory64_store(mut config: Config) -> Option<Store> {
+    let mut features = Features::default();
+    features.memory64(true);
+    config.set_features(features);
+    let store = config.store();
+
+    if config.compiler != Compiler::Cranelift {
+        let err = Module::new(&store, "(module (memory i64 1))").unwrap_err();
+        assert!(err.to_string().contains("memory64"), "{err}");
+        return None;
+    }
+    Some(store)
+}
+
+// 4GiB memories need a 64-bit address space
+#[cfg(target_pointer_width = "64")]
+#[compiler_test(memory64)]
+fn addresses_above_4gib(config: crate::Config) -> Result<()> {
+    let mut store = match memory64_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (memory (export "memory") i64 1)
+            (func (export "grow") (param i64) (result i64)
+                (memory.grow (local.get 0)))
+            (func (export "size") (result i64)
+                (memory.size))
+            (func (export "store") (param i64 i64)
+                (i64.store (local.get 0) (local.get 1)))
+            (func (export "load") (param i64) (result i64)
+                (i64.load (local.get 0)))
+            (func (export "fill") (param i64 i32 i64)
+                (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "copy") (param i64 i64 i64)
+                (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let grow: TypedFunction<u64, i64> = instance.exports.get_typed_function(&store, "grow")?;
+    let size: TypedFunction<(), u64> = instance.exports.get_typed_function(&store, "size")?;
+    let store_fn: TypedFunction<(u64, u64), ()> =
+        instance.exports.get_typed_function(&store, "store")?;
+    let load: TypedFunction<u64, u64> = instance.exports.get_typed_function(&store, "load")?;
+    let fill: TypedFunction<(u64, u32, u64), ()> =
+        instance.exports.get_typed_function(&store, "fill")?;
+    let copy: TypedFunction<(u64, u64, u64), ()> =
+        instance.exports.get_typed_function(&store, "copy")?;
+
+    // Going past 4GiB needs that much address space
+    if grow.call(&mut store, 65536)? == -1 {
+        return Ok(());
+    }
+    assert_eq!(size.call(&mut store)?, 65537);
+
+    // Addresses above 4GiB don't wrap around to the start of the memory
+    store_fn.call(&mut store, FOUR_GIB + 16, 0x0123_4567_89ab_cdef)?;
+    assert_eq!(load.call(&mut store, FOUR_GIB + 16)?, 0x0123_4567_89ab_cdef);
+    assert_eq!(load.call(&mut store, 16)?, 0);
+
+    let memory = instance.exports.get_memory("memory")?;
+    let mut bytes = [0; 8];
+    memory.view(&store).read(FOUR_GIB + 16, &mut bytes)?;
+    assert_eq!(bytes, 0x0123_4567_89ab_cdef_u64.to_le_bytes());
+
+    fill.call(&mut store, FOUR_GIB + 0x100, 0xab, 8)?;
+    assert_eq!(
+        load.call(&mut store, FOUR_GIB + 0x100)?,
+        0xabab_abab_abab_abab
+    );
+    copy.call(&mut store, 0x100, FOUR_GIB + 16, 8)?;
+    assert_eq!(load.call(&mut store, 0x100)?, 0x0123_4567_89ab_cdef);
+
+    // The last page is accessible, but nothing past it
+    let end = 65537 * 65536;
+    store_fn.call(&mut store, end - 8, 42)?;
+    assert_eq!(load.call(&mut store, end - 8)?, 42);
+    let e = load.call(&mut store, end - 7).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    let e = fill.call(&mut store, end - 7, 0, 8).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    let e = copy.call(&mut store, 0, end - 7, 8).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+    Ok(())
+}
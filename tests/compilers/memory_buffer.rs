@@ -0,0 +1,90 @@
+//! Getting the contents of a memory out of a store once an instance has
+//! finished running.
+
+use anyhow::Result;
+use wasmer::*;
+
+/// A 512 MiB memory with a marker written near the end.
+const MODULE: &str = r#"
+    (module
+        (memory (export "memory") 8192)
+        (func (export "run")
+            (i32.store (i32.const 0) (i32.const 0xc0ffee))
+            (i32.store (i32.const 536870000) (i32.const 0xdecade))))
+"#;
+
+const MEMORY_BYTES: usize = 8192 * 65536;
+
+fn run(store: &mut Store, module: &Module) -> Result<Memory> {
+    let instance = Instance::new(store, module, &imports! {})?;
+    let run: TypedFunction<(), ()> = instance.exports.get_typed_function(store, "run")?;
+    run.call(store)?;
+    Ok(instance.exports.get_memory("memory")?.clone())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[compiler_test(memory_buffer)]
+fn take_buffer_moves_the_allocation(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let module = Module::new(&store, MODULE)?;
+    let memory = run(&mut store, &module)?;
+    let data_ptr = memory.view(&store).data_ptr();
+
+    let buffer = memory.take_buffer(store)?;
+
+    // The pages were handed over, not copied
+    assert_eq!(buffer.as_ptr(), data_ptr as *const u8);
+    assert_eq!(buffer.len(), MEMORY_BYTES);
+    assert_eq!(read_u32(&buffer, 0), 0xc0ffee);
+    assert_eq!(read_u32(&buffer, 536870000), 0xdecade);
+
+    Ok(())
+}
+
+#[compiler_test(memory_buffer)]
+fn try_clone_into_buffer_copies(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let module = Module::new(&store, MODULE)?;
+    let memory = run(&mut store, &module)?;
+
+    let mut buffer = memory.try_clone_into_buffer(&store)?;
+    assert_ne!(buffer.as_ptr(), memory.view(&store).data_ptr() as *const u8);
+    assert_eq!(buffer.len(), MEMORY_BYTES);
+    assert_eq!(read_u32(&buffer, 536870000), 0xdecade);
+
+    // The copy and the memory are independent
+    buffer[0] = 0;
+    assert_eq!(memory.view(&store).read_u8(0)?, 0xee);
+
+    Ok(())
+}
+
+#[compiler_test(memory_buffer)]
+fn take_buffer_errors(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let shared = Memory::new(&mut store, MemoryType::new(1, Some(1), true))?;
+    assert!(matches!(
+        shared.take_buffer(store),
+        Err(MemoryBufferError::Shared)
+    ));
+
+    let mut store = config.store();
+    let memory = Memory::new(&mut store, MemoryType::new(1, None, false))?;
+    let other_store = config.store();
+    assert!(matches!(
+        memory.try_clone_into_buffer(&other_store),
+        Err(MemoryBufferError::WrongStore)
+    ));
+    assert!(matches!(
+        memory.take_buffer(other_store),
+        Err(MemoryBufferError::WrongStore)
+    ));
+
+    // The memory's own store is still intact
+    assert_eq!(memory.take_buffer(store)?.len(), 65536);
+
+    Ok(())
+}
@@ -1,7 +1,12 @@
 use anyhow::Result;
+use wasmer_middlewares::metering::{
+    function_name, get_per_function_cost, get_remaining_points, set_exhaustion_callback,
+    set_remaining_points, CostSchedule, Exhaustion, MeteringPoints, OperatorContext, OperatorGroup,
+    WithContext,
+};
 use wasmer_middlewares::Metering;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use wasmer::wasmparser::Operator;
 use wasmer::FunctionEnv;
 use wasmer::*;
@@ -170,3 +175,237 @@ fn complex_loop(mut config: crate::Config) -> Result<()> {
     f.call(&mut store, 10_000_000, 4).unwrap_err();
     Ok(())
 }
+
+const SCHEDULED: &str = r#"
+(module
+    (memory 1)
+    (func $double (param i32) (result i32)
+        (i32.mul (local.get 0) (i32.const 2)))
+    (func (export "run") (param i32) (result i32)
+        (i32.div_u (call $double (local.get 0)) (i32.const 3)))
+    (func (export "grow") (param i32) (result i32)
+        (memory.grow (local.get 0)))
+    (func (export "size") (result i32)
+        (memory.size))
+)
+"#;
+
+/// The points `run` takes with `metering`.
+fn points_to_run(mut config: crate::Config, metering: Arc<dyn ModuleMiddleware>) -> Result<u64> {
+    config.middlewares.push(metering);
+    let mut store = config.store();
+    let module = Module::new(&store, SCHEDULED)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+    assert_eq!(run.call(&mut store, 9)?, 6);
+    match get_remaining_points(&mut store, &instance) {
+        MeteringPoints::Remaining(points) => Ok(1_000 - points),
+        MeteringPoints::Exhausted => panic!("the points ran out"),
+    }
+}
+
+#[compiler_test(metering)]
+fn cost_schedules(config: crate::Config) -> Result<()> {
+    // `local.get`, `call`, then `local.get`, `i32.const`, `i32.mul` and
+    // `end` in `$double`, then `i32.const`, `i32.div_u` and `end`
+    let flat = Metering::new(1_000, CostSchedule::flat(1));
+    assert_eq!(points_to_run(config.clone(), Arc::new(flat))?, 9);
+
+    let schedule: CostSchedule = r#"
+        default = 1
+
+        [groups]
+        call = 10
+        integer_division = 5
+        control = 0
+    "#
+    .parse()?;
+    let grouped = Metering::new(1_000, schedule);
+    assert_eq!(points_to_run(config, Arc::new(grouped))?, 20);
+
+    Ok(())
+}
+
+#[compiler_test(metering)]
+fn cost_depends_on_the_function(config: crate::Config) -> Result<()> {
+    let offsets = Arc::new(Mutex::new(Vec::new()));
+    let recorded = offsets.clone();
+    let metering = Metering::new(
+        1_000,
+        WithContext(move |_: &Operator, context: &OperatorContext| -> u64 {
+            recorded.lock().unwrap().push(context.offset);
+            match context.function_name {
+                Some("double") => 100,
+                _ => 1,
+            }
+        }),
+    );
+    assert_eq!(points_to_run(config, Arc::new(metering))?, 405);
+
+    // Each of the 14 operators in the module is at its own offset
+    let mut offsets = offsets.lock().unwrap().clone();
+    offsets.sort_unstable();
+    offsets.dedup();
+    assert_eq!(offsets.len(), 14, "{offsets:?}");
+
+    Ok(())
+}
+
+#[compiler_test(metering)]
+fn memory_grow_cost(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(
+        Metering::new(1_000, CostSchedule::flat(1)).with_memory_grow_cost(100),
+    ));
+    let mut store = config.store();
+    let module = Module::new(&store, SCHEDULED)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let grow: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "grow")?;
+    let size: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "size")?;
+
+    // `local.get`, `memory.grow` and `end`, and 100 points per page
+    assert_eq!(grow.call(&mut store, 2)?, 1);
+    assert_eq!(
+        get_remaining_points(&mut store, &instance),
+        MeteringPoints::Remaining(797)
+    );
+    assert_eq!(grow.call(&mut store, 0)?, 3);
+    assert_eq!(
+        get_remaining_points(&mut store, &instance),
+        MeteringPoints::Remaining(794)
+    );
+
+    // Memory doesn't grow when there aren't enough points left
+    assert!(grow.call(&mut store, 8).is_err());
+    assert_eq!(
+        get_remaining_points(&mut store, &instance),
+        MeteringPoints::Exhausted
+    );
+    set_remaining_points(&mut store, &instance, 10);
+    assert_eq!(size.call(&mut store)?, 3);
+
+    Ok(())
+}
+
+#[compiler_test(metering)]
+fn cost_schedules_change_the_deterministic_id(config: crate::Config) -> Result<()> {
+    let id = |metering: Arc<dyn ModuleMiddleware>| {
+        let mut config = config.clone();
+        config.middlewares.push(metering);
+        config.store().engine().deterministic_id().to_string()
+    };
+
+    let closure = id(Arc::new(Metering::new(1_000, cost_always_one)));
+    let flat = id(Arc::new(Metering::new(1_000, CostSchedule::flat(1))));
+    let costly_calls = id(Arc::new(Metering::new(
+        1_000,
+        CostSchedule::flat(1).with_group_cost(OperatorGroup::Call, 10),
+    )));
+    let costly_grow = id(Arc::new(
+        Metering::new(1_000, CostSchedule::flat(1)).with_memory_grow_cost(100),
+    ));
+
+    assert!(!closure.contains("metering"), "{closure}");
+    assert!(flat.starts_with(&closure), "{flat}");
+    assert_ne!(flat, costly_calls);
+    assert_ne!(flat, costly_grow);
+    assert_eq!(
+        flat,
+        id(Arc::new(Metering::new(1_000, CostSchedule::flat(1))))
+    );
+
+    Ok(())
+}
+
+const HOT: &str = r#"
+(module
+    (func $hot (param i32) (result i32)
+        (local $i i32) (local $sum i32)
+        (block $done
+            (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get 0)))
+                (local.set $sum (i32.add (local.get $sum) (local.get $i)))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+        (local.get $sum))
+    (func $cold (result i32)
+        (i32.const 1))
+    (func (export "run") (param i32) (result i32)
+        (i32.add (call $hot (local.get 0)) (call $cold)))
+)
+"#;
+
+#[compiler_test(metering)]
+fn per_function_cost(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(
+        Metering::new(1_000_000, cost_always_one).with_per_function_accounting(),
+    ));
+    let mut store = config.store();
+    let module = Module::new(&store, HOT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+    assert_eq!(run.call(&mut store, 100)?, 4951);
+
+    let costs = get_per_function_cost(&mut store, &instance);
+    let (hot, hot_points) = costs[0];
+    assert_eq!(function_name(&module, hot), Some("hot"));
+    // `i32.const` and `end` in `$cold`, and `local.get`, the calls,
+    // `i32.add` and `end` in `run`
+    assert_eq!(costs[1..], [(2, 5), (1, 2)]);
+    assert!(hot_points > 100 * 5, "{costs:?}");
+
+    // Every point spent is accounted for
+    let spent: u64 = costs.iter().map(|(_, points)| points).sum();
+    assert_eq!(
+        get_remaining_points(&mut store, &instance),
+        MeteringPoints::Remaining(1_000_000 - spent)
+    );
+
+    Ok(())
+}
+
+#[compiler_test(metering)]
+fn exhaustion_callback(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(
+        Metering::new(500, cost_always_one).with_per_function_accounting(),
+    ));
+    let mut store = config.store();
+    let module = Module::new(&store, HOT)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let exhaustions = Arc::new(Mutex::new(Vec::<Exhaustion>::new()));
+    let recorded = exhaustions.clone();
+    set_exhaustion_callback(&mut store, &instance, move |exhaustion| {
+        recorded.lock().unwrap().push(exhaustion.clone());
+    });
+
+    let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "run")?;
+    assert!(run.call(&mut store, 1_000).is_err());
+    assert_eq!(
+        get_remaining_points(&mut store, &instance),
+        MeteringPoints::Exhausted
+    );
+
+    // The callback was called from the hot loop, with the points spent
+    // until then
+    let exhaustions = exhaustions.lock().unwrap();
+    assert_eq!(exhaustions.len(), 1);
+    let exhaustion = &exhaustions[0];
+    assert_eq!(exhaustion.function_index, 0);
+    assert_eq!(exhaustion.function_name.as_deref(), Some("hot"));
+    assert_eq!(exhaustion.per_function_cost[0].0, 0);
+    assert_eq!(
+        exhaustion.per_function_cost,
+        get_per_function_cost(&mut store, &instance)
+    );
+    let spent: u64 = exhaustion
+        .per_function_cost
+        .iter()
+        .map(|(_, points)| points)
+        .sum();
+    assert!(spent <= 500, "{spent}");
+
+    Ok(())
+}
@@ -210,3 +210,110 @@ fn middleware_chain_order_2(mut config: crate::Config) -> Result<()> {
     assert_eq!(result, 48);
     Ok(())
 }
+
+#[derive(Debug)]
+struct RejectDivGen;
+
+/// Fails to compile any function dividing `i32`s.
+#[derive(Debug)]
+struct RejectDiv;
+
+impl ModuleMiddleware for RejectDivGen {
+    fn generate_function_middleware(&self, _: LocalFunctionIndex) -> Box<dyn FunctionMiddleware> {
+        Box::new(RejectDiv)
+    }
+}
+
+impl FunctionMiddleware for RejectDiv {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if let Operator::I32DivS = operator {
+            return Err(MiddlewareError::new(
+                "reject_div",
+                "i32.div_s isn't allowed",
+            ));
+        }
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+/// The offsets of the `i32.div_s` instructions in `wasm`.
+fn div_offsets(wasm: &[u8]) -> Result<Vec<usize>> {
+    use wasmer::wasmparser::{Parser, Payload};
+
+    let mut offsets = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CodeSectionEntry(body) = payload? {
+            let mut operators = body.get_operators_reader()?;
+            while !operators.eof() {
+                if let (Operator::I32DivS, offset) = operators.read_with_offset()? {
+                    offsets.push(offset);
+                }
+            }
+        }
+    }
+    Ok(offsets)
+}
+
+#[compiler_test(middlewares)]
+fn middleware_errors_say_which_function_failed(mut config: crate::Config) -> Result<()> {
+    config.set_middlewares(vec![Arc::new(RejectDivGen) as Arc<dyn ModuleMiddleware>]);
+    let store = config.store();
+    let wasm = wat2wasm(
+        br#"(module
+        (import "env" "log" (func $log (param i32)))
+        (func $add (export "add") (param i32 i32) (result i32)
+           (i32.add (local.get 0)
+                    (local.get 1)))
+        (func $div (export "div") (param i32 i32) (result i32)
+           (i32.div_s (local.get 0)
+                      (local.get 1)))
+        (func (param i32 i32) (result i32)
+           (i32.div_s (local.get 1)
+                      (local.get 0)))
+)"#,
+    )?;
+    let offsets = div_offsets(&wasm)?;
+
+    let error = Module::new(&store, &wasm).unwrap_err();
+
+    // Both functions are reported, in order, rather than only one of them
+    let errors = match &error {
+        CompileError::Multiple(errors) => errors,
+        error => panic!("Expected both functions to fail, got: {error}"),
+    };
+    let locations: Vec<_> = errors
+        .iter()
+        .map(|error| error.function_location().cloned())
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            Some(FunctionLocation {
+                index: FunctionIndex::from_u32(2),
+                name: Some("div".to_string()),
+                offset: offsets[0],
+            }),
+            Some(FunctionLocation {
+                index: FunctionIndex::from_u32(3),
+                name: None,
+                offset: offsets[1],
+            }),
+        ]
+    );
+    let message = error.to_string();
+    assert!(
+        message.contains(&format!(
+            "Unable to compile function 2 (\"div\") at offset 0x{:x}",
+            offsets[0]
+        )),
+        "{message}"
+    );
+    assert!(message.contains("i32.div_s isn't allowed"), "{message}");
+
+    Ok(())
+}
@@ -0,0 +1,283 @@
+//! Runs a battery of floating point operations producing NaNs with every
+//! enabled compiler, to check that with NaN canonicalization they all
+//! produce the exact same bits.
+
+use crate::{Compiler, Config};
+use anyhow::Result;
+use wasmer::{imports, Instance, Module, Value};
+
+const F32_CANONICAL_NAN: u32 = 0x7fc0_0000;
+const F64_CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+const F32_INPUTS: &[u32] = &[
+    // NaNs with a payload, quiet and signaling, and a negative one
+    0x7fa0_0001,
+    0x7f80_0001,
+    0xffc0_1234,
+    // Infinity, minus infinity, zero and minus one
+    0x7f80_0000,
+    0xff80_0000,
+    0x0000_0000,
+    0xbf80_0000,
+];
+
+const F64_INPUTS: &[u64] = &[
+    0x7ff4_0000_0000_0001,
+    0x7ff0_0000_0000_0001,
+    0xfff8_0000_0000_1234,
+    0x7ff0_0000_0000_0000,
+    0xfff0_0000_0000_0000,
+    0x0000_0000_0000_0000,
+    0xbff0_0000_0000_0000,
+];
+
+const BINARY_OPS: &[&str] = &["add", "sub", "mul", "div", "min", "max"];
+const UNARY_OPS: &[&str] = &["sqrt", "ceil", "floor", "trunc", "nearest"];
+
+fn compilers() -> Vec<Compiler> {
+    vec![
+        #[cfg(feature = "singlepass")]
+        Compiler::Singlepass,
+        #[cfg(feature = "cranelift")]
+        Compiler::Cranelift,
+        #[cfg(feature = "llvm")]
+        Compiler::LLVM,
+    ]
+}
+
+/// A module exporting `<type>.<op>` for each operation, taking and
+/// returning the bits of the floats so the exact NaNs can be compared.
+fn scalar_module() -> String {
+    let mut funcs = String::new();
+    for (float, int) in [("f32", "i32"), ("f64", "i64")] {
+        for op in BINARY_OPS {
+            funcs.push_str(&format!(
+                r#"(func (export "{float}.{op}") (param {int} {int}) (result {int})
+                    ({int}.reinterpret_{float}
+                        ({float}.{op}
+                            ({float}.reinterpret_{int} (local.get 0))
+                            ({float}.reinterpret_{int} (local.get 1)))))
+                "#
+            ));
+        }
+        for op in UNARY_OPS {
+            funcs.push_str(&format!(
+                r#"(func (export "{float}.{op}") (param {int}) (result {int})
+                    ({int}.reinterpret_{float}
+                        ({float}.{op} ({float}.reinterpret_{int} (local.get 0)))))
+                "#
+            ));
+        }
+    }
+    format!(
+        r#"(module
+            {funcs}
+            (func (export "f64.promote_f32") (param i32) (result i64)
+                (i64.reinterpret_f64 (f64.promote_f32 (f32.reinterpret_i32 (local.get 0)))))
+            (func (export "f32.demote_f64") (param i64) (result i32)
+                (i32.reinterpret_f32 (f32.demote_f64 (f64.reinterpret_i64 (local.get 0)))))
+        )"#
+    )
+}
+
+/// The same operations on every lane of a vector.
+fn simd_module() -> String {
+    let mut funcs = String::new();
+    for shape in ["f32x4", "f64x2"] {
+        for op in BINARY_OPS {
+            funcs.push_str(&format!(
+                r#"(func (export "{shape}.{op}") (param v128 v128) (result v128)
+                    ({shape}.{op} (local.get 0) (local.get 1)))
+                "#
+            ));
+        }
+        for op in UNARY_OPS {
+            funcs.push_str(&format!(
+                r#"(func (export "{shape}.{op}") (param v128) (result v128)
+                    ({shape}.{op} (local.get 0)))
+                "#
+            ));
+        }
+    }
+    format!(
+        r#"(module
+            {funcs}
+            (func (export "f64x2.promote_low_f32x4") (param v128) (result v128)
+                (f64x2.promote_low_f32x4 (local.get 0)))
+            (func (export "f32x4.demote_f64x2_zero") (param v128) (result v128)
+                (f32x4.demote_f64x2_zero (local.get 0)))
+        )"#
+    )
+}
+
+/// Vectors of the inputs, each starting at another one so that lanes get
+/// paired with different inputs.
+fn f32x4_inputs() -> Vec<u128> {
+    (0..F32_INPUTS.len())
+        .map(|start| {
+            (0..4).fold(0, |vector, lane| {
+                let bits = F32_INPUTS[(start + lane) % F32_INPUTS.len()];
+                vector | ((bits as u128) << (lane * 32))
+            })
+        })
+        .collect()
+}
+
+fn f64x2_inputs() -> Vec<u128> {
+    (0..F64_INPUTS.len())
+        .map(|start| {
+            (0..2).fold(0, |vector, lane| {
+                let bits = F64_INPUTS[(start + lane) % F64_INPUTS.len()];
+                vector | ((bits as u128) << (lane * 64))
+            })
+        })
+        .collect()
+}
+
+/// Call each export with each of its arguments, returning the results.
+fn run(
+    config: Config,
+    wat: &str,
+    calls: &[(String, Vec<Vec<Value>>)],
+) -> Result<Vec<(String, Vec<Value>, Vec<Value>)>> {
+    let mut store = config.store();
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let mut results = Vec::new();
+    for (name, args) in calls {
+        let function = instance.exports.get_function(name)?;
+        for args in args {
+            let result = function.call(&mut store, args)?;
+            results.push((name.clone(), args.clone(), result.into_vec()));
+        }
+    }
+    Ok(results)
+}
+
+fn scalar_calls() -> Vec<(String, Vec<Vec<Value>>)> {
+    let f32s = || F32_INPUTS.iter().map(|bits| Value::I32(*bits as i32));
+    let f64s = || F64_INPUTS.iter().map(|bits| Value::I64(*bits as i64));
+    let pairs = |values: Vec<Value>| {
+        values
+            .iter()
+            .flat_map(|a| values.iter().map(move |b| vec![a.clone(), b.clone()]))
+            .collect::<Vec<_>>()
+    };
+    let singles = |values: Vec<Value>| values.into_iter().map(|v| vec![v]).collect::<Vec<_>>();
+
+    let mut calls = Vec::new();
+    for op in BINARY_OPS {
+        calls.push((format!("f32.{op}"), pairs(f32s().collect())));
+        calls.push((format!("f64.{op}"), pairs(f64s().collect())));
+    }
+    for op in UNARY_OPS {
+        calls.push((format!("f32.{op}"), singles(f32s().collect())));
+        calls.push((format!("f64.{op}"), singles(f64s().collect())));
+    }
+    calls.push(("f64.promote_f32".to_string(), singles(f32s().collect())));
+    calls.push(("f32.demote_f64".to_string(), singles(f64s().collect())));
+    calls
+}
+
+fn simd_calls() -> Vec<(String, Vec<Vec<Value>>)> {
+    let vectors = |inputs: Vec<u128>| inputs.into_iter().map(Value::V128).collect::<Vec<_>>();
+    let pairs = |values: Vec<Value>| {
+        values
+            .iter()
+            .zip(values.iter().rev())
+            .map(|(a, b)| vec![a.clone(), b.clone()])
+            .collect::<Vec<_>>()
+    };
+    let singles = |values: Vec<Value>| values.into_iter().map(|v| vec![v]).collect::<Vec<_>>();
+
+    let mut calls = Vec::new();
+    for op in BINARY_OPS {
+        calls.push((format!("f32x4.{op}"), pairs(vectors(f32x4_inputs()))));
+        calls.push((format!("f64x2.{op}"), pairs(vectors(f64x2_inputs()))));
+    }
+    for op in UNARY_OPS {
+        calls.push((format!("f32x4.{op}"), singles(vectors(f32x4_inputs()))));
+        calls.push((format!("f64x2.{op}"), singles(vectors(f64x2_inputs()))));
+    }
+    calls.push((
+        "f64x2.promote_low_f32x4".to_string(),
+        singles(vectors(f32x4_inputs())),
+    ));
+    calls.push((
+        "f32x4.demote_f64x2_zero".to_string(),
+        singles(vectors(f64x2_inputs())),
+    ));
+    calls
+}
+
+/// Whether every NaN in `result` of the operation `name` is the canonical
+/// one.
+fn only_canonical_nans(name: &str, result: &[Value]) -> bool {
+    let f32_ok = |bits: u32| !f32::from_bits(bits).is_nan() || bits == F32_CANONICAL_NAN;
+    let f64_ok = |bits: u64| !f64::from_bits(bits).is_nan() || bits == F64_CANONICAL_NAN;
+    let shape = match name {
+        "f64.promote_f32" => "f64",
+        "f32.demote_f64" => "f32",
+        "f64x2.promote_low_f32x4" => "f64x2",
+        "f32x4.demote_f64x2_zero" => "f32x4",
+        _ => name.split('.').next().unwrap(),
+    };
+    match (shape, result) {
+        ("f32", [Value::I32(bits)]) => f32_ok(*bits as u32),
+        ("f64", [Value::I64(bits)]) => f64_ok(*bits as u64),
+        ("f32x4", [Value::V128(bits)]) => (0..4).all(|lane| f32_ok((bits >> (lane * 32)) as u32)),
+        ("f64x2", [Value::V128(bits)]) => (0..2).all(|lane| f64_ok((bits >> (lane * 64)) as u64)),
+        _ => panic!("unexpected result {result:?} of {name}"),
+    }
+}
+
+/// Run `calls` on the module with each of `compilers`, with NaN
+/// canonicalization, and check they all only produce the canonical NaN.
+fn check_compilers_agree(
+    compilers: Vec<Compiler>,
+    wat: &str,
+    calls: &[(String, Vec<Vec<Value>>)],
+) -> Result<()> {
+    let mut reference: Option<(Compiler, Vec<_>)> = None;
+    for compiler in compilers {
+        let mut config = Config::new(compiler.clone());
+        config.set_nan_canonicalization(true);
+        let results = run(config, wat, calls)?;
+
+        for (name, args, result) in &results {
+            assert!(
+                only_canonical_nans(name, result),
+                "{compiler:?} returned a non-canonical NaN: {name}{args:?} = {result:?}"
+            );
+        }
+
+        match &reference {
+            Some((reference_compiler, expected)) => {
+                for (expected, actual) in expected.iter().zip(&results) {
+                    assert_eq!(
+                        expected, actual,
+                        "{reference_compiler:?} and {compiler:?} disagree"
+                    );
+                }
+            }
+            None => reference = Some((compiler, results)),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn compilers_agree_on_scalar_nans() -> Result<()> {
+    check_compilers_agree(compilers(), &scalar_module(), &scalar_calls())
+}
+
+#[test]
+fn compilers_agree_on_simd_nans() -> Result<()> {
+    // Singlepass doesn't support SIMD
+    let compilers = compilers()
+        .into_iter()
+        .filter(|compiler| *compiler != Compiler::Singlepass)
+        .collect();
+    check_compilers_agree(compilers, &simd_module(), &simd_calls())
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use wasmer_middlewares::profiling::get_profile;
+use wasmer_middlewares::{Metering, Profiling};
+
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::*;
+
+/// `run` calls `$inner` once per iteration of its loop, and `$inner`'s
+/// loop runs its header once per element and once more to leave it.
+const LOOPS: &str = r#"
+(module
+    (import "env" "tick" (func $tick))
+    (func $inner (param $n i32) (result i32)
+        (local $i i32) (local $sum i32)
+        (block $done
+            (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                (local.set $sum (i32.add (local.get $sum) (local.get $i)))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+        (local.get $sum))
+    (func (export "run") (param $outer i32) (result i32)
+        (local $j i32) (local $total i32)
+        (loop $again
+            (call $tick)
+            (local.set $total (i32.add (local.get $total) (call $inner (i32.const 10))))
+            (local.set $j (i32.add (local.get $j) (i32.const 1)))
+            (br_if $again (i32.lt_u (local.get $j) (local.get $outer))))
+        (local.get $total))
+    (func $never (result i32)
+        (loop $forever (br $forever))
+        (i32.const 0))
+)
+"#;
+
+fn instantiate(mut store: Store) -> Result<(Store, Instance)> {
+    let module = Module::new(&store, LOOPS)?;
+    let imports = imports! {
+        "env" => {
+            "tick" => Function::new_typed(&mut store, || {}),
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    Ok((store, instance))
+}
+
+fn run(store: &mut Store, instance: &Instance, outer: i32) -> Result<i32> {
+    let run: TypedFunction<i32, i32> = instance.exports.get_typed_function(store, "run")?;
+    Ok(run.call(store, outer)?)
+}
+
+#[compiler_test(profiling)]
+fn counts_match_the_iterations(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(Profiling::new()));
+    let (mut store, instance) = instantiate(config.store())?;
+
+    assert_eq!(run(&mut store, &instance, 7)?, 7 * 45);
+    let profile = get_profile(&mut store, &instance);
+
+    // The imported function isn't profiled
+    assert_eq!(
+        profile.functions.keys().copied().collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+
+    let inner = &profile.functions[&1];
+    assert_eq!(inner.name.as_deref(), Some("inner"));
+    assert_eq!(inner.calls, 7);
+    assert_eq!(inner.loops.values().copied().collect::<Vec<_>>(), [7 * 11]);
+    assert!(inner.loops.keys().all(|offset| *offset > inner.offset));
+
+    let run_profile = &profile.functions[&2];
+    assert_eq!(run_profile.name, None);
+    assert_eq!(run_profile.calls, 1);
+    assert_eq!(run_profile.loops.values().copied().collect::<Vec<_>>(), [7]);
+
+    let never = &profile.functions[&3];
+    assert_eq!(never.calls, 0);
+    assert_eq!(never.loops.values().copied().collect::<Vec<_>>(), [0]);
+
+    // The counts add up over calls
+    run(&mut store, &instance, 3)?;
+    let profile = get_profile(&mut store, &instance);
+    assert_eq!(profile.functions[&1].calls, 10);
+    assert_eq!(profile.functions[&2].calls, 2);
+    assert_eq!(
+        profile.functions[&2]
+            .loops
+            .values()
+            .copied()
+            .collect::<Vec<_>>(),
+        [10]
+    );
+
+    Ok(())
+}
+
+#[compiler_test(profiling)]
+fn counts_saturate(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(Profiling::new()));
+    let (mut store, instance) = instantiate(config.store())?;
+
+    // The count of the calls to `$inner` is one short of the maximum
+    let (_, inner_calls) = instance
+        .exports
+        .iter()
+        .find(|(name, _)| name.starts_with("wasmer_profiling_calls_1_"))
+        .unwrap();
+    let inner_calls = match inner_calls {
+        Extern::Global(global) => global.clone(),
+        _ => panic!("The count of calls isn't a global"),
+    };
+    inner_calls.set(&mut store, Value::I64((u64::MAX - 1) as i64))?;
+
+    run(&mut store, &instance, 1)?;
+    assert_eq!(
+        get_profile(&mut store, &instance).functions[&1].calls,
+        u64::MAX
+    );
+    run(&mut store, &instance, 2)?;
+    assert_eq!(
+        get_profile(&mut store, &instance).functions[&1].calls,
+        u64::MAX
+    );
+
+    Ok(())
+}
+
+#[compiler_test(profiling)]
+fn profiling_with_metering(mut config: crate::Config) -> Result<()> {
+    config
+        .middlewares
+        .push(Arc::new(Metering::new(u64::MAX, |_: &Operator| -> u64 {
+            1
+        })));
+    config.middlewares.push(Arc::new(Profiling::new()));
+    let (mut store, instance) = instantiate(config.store())?;
+
+    run(&mut store, &instance, 5)?;
+    let profile = get_profile(&mut store, &instance);
+    assert_eq!(profile.functions[&1].calls, 5);
+    assert_eq!(
+        profile.functions[&1]
+            .loops
+            .values()
+            .copied()
+            .collect::<Vec<_>>(),
+        [5 * 11]
+    );
+    assert_eq!(
+        profile.functions[&2]
+            .loops
+            .values()
+            .copied()
+            .collect::<Vec<_>>(),
+        [5]
+    );
+
+    Ok(())
+}
+
+#[compiler_test(profiling)]
+fn callgrind_output(mut config: crate::Config) -> Result<()> {
+    config.middlewares.push(Arc::new(Profiling::new()));
+    let (mut store, instance) = instantiate(config.store())?;
+
+    run(&mut store, &instance, 2)?;
+    let profile = get_profile(&mut store, &instance);
+    let mut out = vec![];
+    profile.write_callgrind(&mut out)?;
+    let out = String::from_utf8(out)?;
+
+    let inner = &profile.functions[&1];
+    let (loop_offset, _) = inner.loops.iter().next().unwrap();
+    let expected = format!("fn=inner\n{:#x} 2\n{:#x} 22\n", inner.offset, loop_offset);
+    assert!(out.starts_with("# callgrind format\n"), "{out}");
+    assert!(out.contains(&expected), "{out}");
+    assert!(out.contains("fn=wasm-function[2]\n"), "{out}");
+    assert!(out.contains("fn=never\n"), "{out}");
+
+    Ok(())
+}
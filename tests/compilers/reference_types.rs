@@ -0,0 +1,253 @@
+//! The reference types proposal: `ref.func`, `ref.is_null`, multiple
+//! tables of funcrefs and externrefs, and the table instructions, along
+//! with the collection of the externrefs they hold.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+/// A host object which counts how many times it has been dropped.
+struct Counted(Arc<AtomicUsize>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[compiler_test(reference_types)]
+fn tables_after_an_imported_one(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (import "env" "table" (table $imported 1 funcref))
+            (table $funcs 2 funcref)
+            (table $externs 1 externref)
+            (type $ret_i32 (func (result i32)))
+            (func $answer (result i32) i32.const 42)
+            (elem declare func $answer)
+
+            (func (export "set_and_call") (result i32)
+                (table.set $funcs (i32.const 1) (ref.func $answer))
+                (call_indirect $funcs (type $ret_i32) (i32.const 1)))
+            (func (export "is_null") (param i32) (result i32)
+                (ref.is_null (table.get $funcs (local.get 0))))
+            (func (export "stash") (param externref) (result i32)
+                (table.set $externs (i32.const 0) (local.get 0))
+                (ref.is_null (table.get $externs (i32.const 0))))
+            (func (export "grow") (param i32) (result i32)
+                (table.grow $externs (ref.null extern) (local.get 0)))
+            (func (export "sizes") (result i32)
+                (i32.add
+                    (i32.mul (table.size $imported) (i32.const 100))
+                    (i32.add
+                        (i32.mul (table.size $funcs) (i32.const 10))
+                        (table.size $externs))))
+            (func (export "grow_imported") (result i32)
+                (table.grow $imported (ref.func $answer) (i32.const 2)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let table = Table::new(
+        &mut store,
+        TableType::new(Type::FuncRef, 1, None),
+        Value::FuncRef(None),
+    )?;
+    let imports = imports! {
+        "env" => {
+            "table" => table.clone(),
+        },
+    };
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let set_and_call: TypedFunction<(), i32> = instance
+        .exports
+        .get_typed_function(&store, "set_and_call")?;
+    let is_null: TypedFunction<i32, i32> =
+        instance.exports.get_typed_function(&store, "is_null")?;
+    let stash: TypedFunction<Option<ExternRef>, i32> =
+        instance.exports.get_typed_function(&store, "stash")?;
+    let grow: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "grow")?;
+    let sizes: TypedFunction<(), i32> = instance.exports.get_typed_function(&store, "sizes")?;
+    let grow_imported: TypedFunction<(), i32> = instance
+        .exports
+        .get_typed_function(&store, "grow_imported")?;
+
+    // The local tables aren't mixed up with the imported one
+    assert_eq!(is_null.call(&mut store, 1)?, 1);
+    assert_eq!(set_and_call.call(&mut store)?, 42);
+    assert_eq!(is_null.call(&mut store, 1)?, 0);
+    assert!(table.get(&mut store, 0).unwrap().unwrap_funcref().is_none());
+
+    let extern_ref = ExternRef::new(&mut store, 7_u32);
+    assert_eq!(stash.call(&mut store, Some(extern_ref))?, 0);
+    assert_eq!(stash.call(&mut store, None)?, 1);
+
+    assert_eq!(sizes.call(&mut store)?, 121);
+    assert_eq!(grow.call(&mut store, 3)?, 1);
+    assert_eq!(grow_imported.call(&mut store)?, 1);
+    assert_eq!(sizes.call(&mut store)?, 324);
+    assert_eq!(table.size(&store), 3);
+    assert!(table.get(&mut store, 2).unwrap().unwrap_funcref().is_some());
+
+    Ok(())
+}
+
+#[compiler_test(reference_types)]
+fn externref_element_segments(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (table $t 4 externref)
+            (elem (table $t) (i32.const 1) externref (ref.null extern))
+            (elem $passive externref (ref.null extern) (ref.null extern))
+
+            (func (export "stash") (param i32 externref)
+                (table.set $t (local.get 0) (local.get 1)))
+            (func (export "is_null") (param i32) (result i32)
+                (ref.is_null (table.get $t (local.get 0))))
+            (func (export "init") (param i32)
+                (table.init $t $passive (local.get 0) (i32.const 0) (i32.const 2)))
+            (func (export "drop")
+                (elem.drop $passive))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let stash: TypedFunction<(i32, Option<ExternRef>), ()> =
+        instance.exports.get_typed_function(&store, "stash")?;
+    let is_null: TypedFunction<i32, i32> =
+        instance.exports.get_typed_function(&store, "is_null")?;
+    let init: TypedFunction<i32, ()> = instance.exports.get_typed_function(&store, "init")?;
+    let elem_drop: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "drop")?;
+
+    for i in 0..4 {
+        let extern_ref = ExternRef::new(&mut store, i);
+        stash.call(&mut store, i, Some(extern_ref))?;
+    }
+    init.call(&mut store, 2)?;
+    let nulls = (0..4)
+        .map(|i| is_null.call(&mut store, i))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(nulls, [0, 0, 1, 1]);
+
+    elem_drop.call(&mut store)?;
+    let err = init.call(&mut store, 0).unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::TableAccessOutOfBounds));
+
+    Ok(())
+}
+
+/// Externrefs spread over tables with `table.fill`, `table.copy` and
+/// `table.grow` are kept alive as long as a table holds them, and dropped
+/// exactly once when the last one lets go of them.
+#[compiler_test(reference_types)]
+fn externrefs_in_bulk_table_operations(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (table $a (export "a") 8 externref)
+            (table $b (export "b") 4 externref)
+
+            (func (export "fill") (param i32 externref i32)
+                (table.fill $a (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "copy_to_b") (param i32 i32 i32)
+                (table.copy $b $a (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "copy_within_a") (param i32 i32 i32)
+                (table.copy $a $a (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "grow_b") (param externref i32) (result i32)
+                (table.grow $b (local.get 0) (local.get 1)))
+            (func (export "clear")
+                (table.fill $a (i32.const 0) (ref.null extern) (table.size $a))
+                (table.fill $b (i32.const 0) (ref.null extern) (table.size $b)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let fill: TypedFunction<(i32, Option<ExternRef>, i32), ()> =
+        instance.exports.get_typed_function(&store, "fill")?;
+    let copy_to_b: TypedFunction<(i32, i32, i32), ()> =
+        instance.exports.get_typed_function(&store, "copy_to_b")?;
+    let copy_within_a: TypedFunction<(i32, i32, i32), ()> = instance
+        .exports
+        .get_typed_function(&store, "copy_within_a")?;
+    let grow_b: TypedFunction<(Option<ExternRef>, i32), i32> =
+        instance.exports.get_typed_function(&store, "grow_b")?;
+    let clear: TypedFunction<(), ()> = instance.exports.get_typed_function(&store, "clear")?;
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let first = ExternRef::new(&mut store, Counted(Arc::clone(&drops)));
+    let second = ExternRef::new(&mut store, Counted(Arc::clone(&drops)));
+    let third = ExternRef::new(&mut store, Counted(Arc::clone(&drops)));
+    let fourth = ExternRef::new(&mut store, Counted(Arc::clone(&drops)));
+
+    fill.call(&mut store, 0, Some(first.clone()), 4)?;
+    fill.call(&mut store, 4, Some(second.clone()), 4)?;
+    copy_to_b.call(&mut store, 0, 2, 4)?;
+    copy_within_a.call(&mut store, 1, 0, 7)?;
+    assert_eq!(grow_b.call(&mut store, Some(third.clone()), 2)?, 4);
+    // Out of bounds, so nothing is written
+    let err = fill
+        .call(&mut store, 6, Some(fourth.clone()), 4)
+        .unwrap_err();
+    assert_eq!(err.to_trap(), Some(TrapCode::TableAccessOutOfBounds));
+
+    // Every object is still referenced by the host or a table
+    assert_eq!(store.gc_externrefs(), 0);
+    assert_eq!(drops.load(Ordering::SeqCst), 0);
+    drop((first, second, third, fourth));
+
+    // Only the one which never made it into a table is dropped
+    assert_eq!(store.gc_externrefs(), 1);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    // `third` only made it into `b`, the others are still in `a`
+    let a = instance.exports.get_table("a")?;
+    let b = instance.exports.get_table("b")?;
+    b.fill(&mut store, 0, Value::ExternRef(None), 6)?;
+    assert_eq!(store.gc_externrefs(), 1);
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+    assert!(a.get(&mut store, 0).unwrap().unwrap_externref().is_some());
+
+    clear.call(&mut store)?;
+    assert_eq!(store.gc_externrefs(), 2);
+    assert_eq!(drops.load(Ordering::SeqCst), 4);
+
+    // Collecting again doesn't drop anything twice
+    assert_eq!(store.gc_externrefs(), 0);
+    assert_eq!(drops.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[compiler_test(reference_types)]
+fn copy_between_tables_of_different_types(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (table $funcs 2 funcref)
+            (table $externs 2 externref)
+            (func (export "copy")
+                (table.copy $externs $funcs (i32.const 0) (i32.const 0) (i32.const 1)))
+        )
+    "#;
+    assert!(Module::new(&store, wat).is_err());
+
+    let funcs = Table::new(
+        &mut store,
+        TableType::new(Type::FuncRef, 2, None),
+        Value::FuncRef(None),
+    )?;
+    let externs = Table::new(
+        &mut store,
+        TableType::new(Type::ExternRef, 2, None),
+        Value::ExternRef(None),
+    )?;
+    assert!(Table::copy(&mut store, &externs, 0, &funcs, 0, 1).is_err());
+
+    Ok(())
+}
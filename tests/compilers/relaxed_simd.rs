@@ -0,0 +1,185 @@
+//! The relaxed SIMD proposal, whose instructions give the results of the
+//! deterministic ones they relax, except for the multiply-adds which are
+//! fused where the CPU can.
+
+use crate::{Compiler, Config};
+use anyhow::Result;
+use wasmer::*;
+
+/// The store for `config` with the relaxed SIMD proposal, if the compiler
+/// supports it.
+fn relaxed_simd_store(mut config: Config) -> Option<Store> {
+    let mut features = Features::default();
+    features.simd = true;
+    features.relaxed_simd = true;
+    config.set_features(features);
+    let store = config.store();
+
+    if config.compiler != Compiler::Cranelift {
+        let err = Module::new(&store, "(module)").unwrap_err();
+        assert!(err.to_string().contains("relaxed SIMD"), "{err}");
+        return None;
+    }
+    Some(store)
+}
+
+fn from_lanes(lanes: &[u128], bits: u32) -> u128 {
+    lanes
+        .iter()
+        .rev()
+        .fold(0, |v128, &lane| v128 << bits | lane)
+}
+
+fn i8x16(lanes: [i8; 16]) -> u128 {
+    u128::from_le_bytes(lanes.map(|lane| lane as u8))
+}
+
+fn i16x8(lanes: [i16; 8]) -> u128 {
+    from_lanes(&lanes.map(|lane| lane as u16 as u128), 16)
+}
+
+fn i32x4(lanes: [i32; 4]) -> u128 {
+    from_lanes(&lanes.map(|lane| lane as u32 as u128), 32)
+}
+
+fn f32x4(lanes: [f32; 4]) -> u128 {
+    from_lanes(&lanes.map(|lane| lane.to_bits() as u128), 32)
+}
+
+fn f64x2(lanes: [f64; 2]) -> u128 {
+    from_lanes(&lanes.map(|lane| lane.to_bits() as u128), 64)
+}
+
+/// Call each of the module's functions, which return a `v128`, and compare
+/// it with what's expected.
+fn check(store: &mut Store, wat: &str, expected: &[(&str, u128)]) -> Result<()> {
+    let module = Module::new(store, wat)?;
+    let instance = Instance::new(store, &module, &imports! {})?;
+    for (name, expected) in expected {
+        let result = instance.exports.get_function(name)?.call(store, &[])?;
+        match result[0] {
+            Value::V128(result) => assert_eq!(result, *expected, "{name}: {result:#x}"),
+            ref other => panic!("{name} returned {other:?}"),
+        }
+    }
+    Ok(())
+}
+
+#[compiler_test(relaxed_simd)]
+fn relaxed_instructions(config: crate::Config) -> Result<()> {
+    let mut store = match relaxed_simd_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (func (export "swizzle") (result v128)
+                (i8x16.relaxed_swizzle
+                    (v128.const i8x16 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
+                    (v128.const i8x16 15 0 16 -1 1 1 1 1 1 1 1 1 1 1 1 1)))
+            (func (export "trunc_s") (result v128)
+                (i32x4.relaxed_trunc_f32x4_s (v128.const f32x4 nan 3e9 -3e9 -1.5)))
+            (func (export "trunc_u") (result v128)
+                (i32x4.relaxed_trunc_f32x4_u (v128.const f32x4 nan 3e9 -3e9 -1.5)))
+            (func (export "laneselect") (result v128)
+                (i32x4.relaxed_laneselect
+                    (v128.const i32x4 1 2 3 4)
+                    (v128.const i32x4 5 6 7 8)
+                    (v128.const i32x4 -1 0 -1 0)))
+            (func (export "min") (result v128)
+                (f32x4.relaxed_min
+                    (v128.const f32x4 1 4 -2 0.5)
+                    (v128.const f32x4 3 2 -1 0.25)))
+            (func (export "max") (result v128)
+                (f64x2.relaxed_max (v128.const f64x2 1 -4) (v128.const f64x2 3 -8)))
+            (func (export "q15mulr") (result v128)
+                (i16x8.relaxed_q15mulr_s
+                    (v128.const i16x8 16384 -32768 100 -100 0 0 0 0)
+                    (v128.const i16x8 16384 -32768 16384 16384 0 0 0 0)))
+            (func (export "dot") (result v128)
+                (i16x8.relaxed_dot_i8x16_i7x16_s
+                    (v128.const i8x16 1 2 3 4 5 6 7 8 -1 -2 -3 -4 127 -128 0 0)
+                    (v128.const i8x16 1 1 2 2 -3 3 1 -1 5 5 1 1 1 1 9 9)))
+            (func (export "dot_add") (result v128)
+                (i32x4.relaxed_dot_i8x16_i7x16_add_s
+                    (v128.const i8x16 1 2 3 4 5 6 7 8 -1 -2 -3 -4 127 -128 0 0)
+                    (v128.const i8x16 1 1 2 2 -3 3 1 -1 5 5 1 1 1 1 9 9)
+                    (v128.const i32x4 10 20 30 40)))
+        )
+    "#;
+
+    check(
+        &mut store,
+        wat,
+        &[
+            (
+                "swizzle",
+                i8x16([25, 10, 0, 0, 11, 11, 11, 11, 11, 11, 11, 11, 11, 11, 11, 11]),
+            ),
+            ("trunc_s", i32x4([0, i32::MAX, i32::MIN, -1])),
+            ("trunc_u", i32x4([0, 3_000_000_000_u32 as i32, 0, 0])),
+            ("laneselect", i32x4([1, 6, 3, 8])),
+            ("min", f32x4([1.0, 2.0, -2.0, 0.25])),
+            ("max", f64x2([3.0, -4.0])),
+            ("q15mulr", i16x8([8192, 32767, 50, -50, 0, 0, 0, 0])),
+            ("dot", i16x8([3, 14, 3, -1, -15, -7, -1, 0])),
+            ("dot_add", i32x4([27, 22, 8, 39])),
+        ],
+    )
+}
+
+#[compiler_test(relaxed_simd)]
+fn relaxed_multiply_adds(config: crate::Config) -> Result<()> {
+    let mut store = match relaxed_simd_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (func (export "madd") (result v128)
+                (f32x4.relaxed_madd
+                    (v128.const f32x4 1.5 2 3 4)
+                    (v128.const f32x4 2 2 2 2)
+                    (v128.const f32x4 1 1 1 1)))
+            (func (export "nmadd") (result v128)
+                (f32x4.relaxed_nmadd
+                    (v128.const f32x4 1.5 2 3 4)
+                    (v128.const f32x4 2 2 2 2)
+                    (v128.const f32x4 1 1 1 1)))
+            (func (export "madd_f64") (result v128)
+                (f64x2.relaxed_madd
+                    (v128.const f64x2 1.5 2)
+                    (v128.const f64x2 2 2)
+                    (v128.const f64x2 1 1)))
+            ;; (1 + 2^-12)^2 - 1 is 2^-11 + 2^-24, but rounding the product
+            ;; first loses the 2^-24
+            (func (export "rounding") (result v128)
+                (f32x4.relaxed_madd
+                    (v128.const i32x4 0x3f800800 0x3f800800 0x3f800800 0x3f800800)
+                    (v128.const i32x4 0x3f800800 0x3f800800 0x3f800800 0x3f800800)
+                    (v128.const f32x4 -1 -1 -1 -1)))
+        )
+    "#;
+
+    #[cfg(target_arch = "x86_64")]
+    let fused = std::is_x86_feature_detected!("fma");
+    #[cfg(not(target_arch = "x86_64"))]
+    let fused = false;
+    let x = f32::from_bits(0x3f80_0800);
+    let rounding = if fused {
+        x.mul_add(x, -1.0)
+    } else {
+        x * x - 1.0
+    };
+
+    check(
+        &mut store,
+        wat,
+        &[
+            ("madd", f32x4([4.0, 5.0, 7.0, 9.0])),
+            ("nmadd", f32x4([-2.0, -3.0, -5.0, -7.0])),
+            ("madd_f64", f64x2([4.0, 5.0])),
+            ("rounding", f32x4([rounding; 4])),
+        ],
+    )
+}
@@ -85,3 +85,233 @@ fn test_deserialize(config: crate::Config) -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+const ADD_WAT: &str = r#"
+    (module
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1))))
+"#;
+
+#[compiler_test(serialize)]
+fn test_deserialize_checked(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let serialized_bytes = module.serialize()?;
+
+    let deserialized_module = unsafe { Module::deserialize_checked(&store, serialized_bytes)? };
+
+    let instance = Instance::new(&mut store, &deserialized_module, &imports! {})?;
+    let add = instance.exports.get_function("add")?;
+    let result = add.call(&mut store, &[Value::I32(1), Value::I32(2)])?;
+    assert_eq!(result.to_vec(), vec![Value::I32(3)]);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_checked_rejects_other_targets(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let serialized_bytes = module.serialize()?;
+
+    let other_triple = if Triple::host().architecture == Architecture::X86_64 {
+        "aarch64-unknown-linux-gnu"
+    } else {
+        "x86_64-unknown-linux-gnu"
+    };
+    let target = Target::new(other_triple.parse().unwrap(), CpuFeature::set());
+    let engine: Engine = EngineBuilder::new(config.compiler_config(false))
+        .set_target(Some(target))
+        .engine()
+        .into();
+
+    let err = unsafe { Module::deserialize_checked(&engine, serialized_bytes) }.unwrap_err();
+
+    match err {
+        DeserializeError::IncompatibleTarget { artifact, engine } => {
+            assert_eq!(artifact, Triple::host().to_string());
+            assert_eq!(engine, other_triple);
+        }
+        other => panic!("Unexpected error: {other}"),
+    }
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_records_cpu_features(config: crate::Config) -> Result<()> {
+    let (baseline, extra, missing) = match Triple::host().architecture {
+        Architecture::X86_64 => ("+sse2", "+sse2,+avx,+bmi", "avx, bmi"),
+        Architecture::Aarch64(_) => ("", "+neon,+lse,-sve", "neon, lse"),
+        _ => return Ok(()),
+    };
+    let engine = |list: &str| -> Result<Engine> {
+        let target = Target::new(Triple::host(), CpuFeature::set()).with_cpu_features_str(list)?;
+        Ok(EngineBuilder::new(config.compiler_config(false))
+            .set_target(Some(target))
+            .engine()
+            .into())
+    };
+
+    let with_features = engine(extra)?;
+    let module = Module::new(&with_features, ADD_WAT)?;
+    let serialized_bytes = module.serialize()?;
+    unsafe { Module::deserialize_checked(&with_features, serialized_bytes.clone())? };
+
+    let err =
+        unsafe { Module::deserialize_checked(&engine(baseline)?, serialized_bytes) }.unwrap_err();
+    match err {
+        DeserializeError::IncompatibleTarget { artifact, .. } => {
+            assert_eq!(artifact, format!("the {missing} CPU features"));
+        }
+        other => panic!("Unexpected error: {other}"),
+    }
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_checked_rejects_mutated_artifacts(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, ADD_WAT)?;
+    let serialized_bytes = module.serialize()?.to_vec();
+
+    // A xorshift PRNG with a fixed seed, so failures are reproducible
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+
+    for iteration in 0..1000 {
+        let mut mutated = serialized_bytes.clone();
+        match next() % 3 {
+            0 => {
+                let index = next() % mutated.len();
+                mutated[index] ^= 1 << (next() % 8);
+            }
+            1 => mutated.truncate(next() % mutated.len()),
+            _ => {
+                let index = next() % mutated.len();
+                mutated.insert(index, next() as u8);
+            }
+        }
+
+        let result = unsafe { Module::deserialize_checked(&store, mutated) };
+        assert!(
+            result.is_err(),
+            "mutation {iteration} was deserialized successfully"
+        );
+    }
+
+    // Old artifacts without a checksum can't be checked
+    let mut old = serialized_bytes;
+    old[24..28].copy_from_slice(&5_u32.to_ne_bytes());
+    let err = unsafe { Module::deserialize_checked(&store, old) }.unwrap_err();
+    assert!(matches!(err, DeserializeError::Incompatible(_)));
+
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_records_compiler(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(&store, "(module)")?;
+    assert_eq!(
+        format!("engine-{}", module.compiler()),
+        store.engine().deterministic_id()
+    );
+
+    let serialized_bytes = module.serialize()?;
+    let deserialized_module = unsafe { Module::deserialize_checked(&store, serialized_bytes)? };
+    assert_eq!(deserialized_module.compiler(), module.compiler());
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_records_nan_canonicalization(mut config: crate::Config) -> Result<()> {
+    let engine_id = config.store().engine().deterministic_id().to_string();
+
+    config.set_nan_canonicalization(true);
+    let store = config.store();
+    // Caches key on the id, so modules compiled without it aren't reused
+    assert_ne!(store.engine().deterministic_id(), engine_id);
+
+    let module = Module::new(&store, "(module)")?;
+    assert!(module.compiler().ends_with("-canonicalize-nans"));
+
+    let serialized_bytes = module.serialize()?;
+    let deserialized_module = unsafe { Module::deserialize_checked(&store, serialized_bytes)? };
+    assert_eq!(deserialized_module.compiler(), module.compiler());
+    Ok(())
+}
+
+#[cfg(feature = "llvm")]
+#[test]
+fn test_llvm_opt_levels_round_trip() -> Result<()> {
+    use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
+
+    let wat = r#"
+        (module
+            (memory 1)
+            (func (export "sum_of_squares") (param $n i32) (result i64)
+                (local $i i32)
+                (local $sum i64)
+                (block $done
+                    (loop $loop
+                        (br_if $done (i32.ge_u (local.get $i) (local.get $n)))
+                        (i32.store (i32.shl (local.get $i) (i32.const 2)) (local.get $i))
+                        (local.set $sum
+                            (i64.add
+                                (local.get $sum)
+                                (i64.extend_i32_u
+                                    (i32.mul
+                                        (i32.load (i32.shl (local.get $i) (i32.const 2)))
+                                        (local.get $i)))))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br $loop)))
+                (local.get $sum)))
+    "#;
+
+    let mut ids = Vec::new();
+    for opt_level in [
+        LLVMOptLevel::O0,
+        LLVMOptLevel::O1,
+        LLVMOptLevel::O2,
+        LLVMOptLevel::O3,
+        LLVMOptLevel::Os,
+    ] {
+        let mut compiler = LLVM::new();
+        compiler.opt_level(opt_level);
+        compiler.enable_verifier();
+        let engine: Engine = compiler.into();
+        ids.push(engine.deterministic_id().to_string());
+
+        let module = Module::new(&engine, wat)?;
+        assert_eq!(module.compiler(), format!("llvm-{opt_level}"));
+        let serialized_bytes = module.serialize()?;
+
+        let mut store = Store::new(engine);
+        let module = unsafe { Module::deserialize_checked(&store, serialized_bytes)? };
+        assert_eq!(module.compiler(), format!("llvm-{opt_level}"));
+
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let sum_of_squares: TypedFunction<i32, i64> = instance
+            .exports
+            .get_typed_function(&store, "sum_of_squares")?;
+        assert_eq!(sum_of_squares.call(&mut store, 1000)?, 332_833_500);
+    }
+
+    // Caches key on the id, so each level needs its own
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 5);
+
+    let mut compiler = LLVM::new();
+    compiler.vectorize(false).licm(false);
+    let engine: Engine = compiler.into();
+    assert_eq!(
+        engine.deterministic_id(),
+        "engine-llvm-O3-novectorize-nolicm"
+    );
+    Ok(())
+}
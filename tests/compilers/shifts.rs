@@ -0,0 +1,200 @@
+//! Shifts and rotates, which singlepass lowers differently for constant and
+//! dynamic amounts, and for values that have been spilled to the stack.
+
+use anyhow::Result;
+use wasmer::*;
+
+const SHIFTS: &[&str] = &["shl", "shr_s", "shr_u", "rotl", "rotr"];
+
+const INPUTS: &[u64] = &[
+    0,
+    1,
+    0x0123_4567_89ab_cdef,
+    0x8000_0000_0000_0001,
+    0xfedc_ba98_7654_3210,
+    u64::MAX,
+];
+
+/// `i64.rotl x, 40`, with `x` spilled to the stack by the values pushed
+/// before it.
+#[compiler_test(shifts)]
+fn i64_rotl_constant_spilled(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+    (module
+        (func (export "rotl_40") (param $x i64) (result i64)
+            local.get $x
+            local.get $x
+            local.get $x
+            local.get $x
+            local.get $x
+            local.get $x
+            local.get $x
+            local.get $x
+            i64.const 40
+            i64.rotl
+            i64.xor
+            i64.xor
+            i64.xor
+            i64.xor
+            i64.xor
+            i64.xor
+            i64.xor
+        )
+    )"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let rotl_40 = instance
+        .exports
+        .get_typed_function::<i64, i64>(&store, "rotl_40")?;
+
+    // The 7 other copies of `x` xor down to a single one
+    let x = 0x0123_4567_89ab_cdef_u64;
+    let expected = x.rotate_left(40) ^ x;
+    assert_eq!(rotl_40.call(&mut store, x as i64)? as u64, expected);
+
+    Ok(())
+}
+
+/// A function applying `op` to its parameter and `amount`, or to its 2
+/// parameters when there's no `amount`.
+///
+/// With `spill`, copies of the value are pushed first, so singlepass runs
+/// out of registers and has to keep the operands on the stack. There's an
+/// even number of them, so they cancel out.
+fn shift_function(ty: &str, op: &str, amount: Option<u32>, spill: bool) -> String {
+    let fillers = if spill { 8 } else { 0 };
+    let name = match amount {
+        Some(amount) => format!("{ty}.{op}/{amount}"),
+        None => format!("{ty}.{op}"),
+    };
+    let mut body = String::new();
+    for _ in 0..fillers {
+        body += "local.get $x\n";
+    }
+    body += "local.get $x\n";
+    match amount {
+        Some(amount) => body += &format!("{ty}.const {amount}\n"),
+        None => body += "local.get $y\n",
+    }
+    body += &format!("{ty}.{op}\n");
+    for _ in 0..fillers {
+        body += &format!("{ty}.xor\n");
+    }
+    let spilled = if spill { "/spilled" } else { "" };
+
+    format!(
+        "(func (export \"{name}{spilled}\") (param $x {ty}) (param $y {ty}) (result {ty})\n{body})\n"
+    )
+}
+
+/// Every shift and rotate, by every amount in `0..=127`.
+fn shifts_module() -> String {
+    let mut wat = String::from("(module\n");
+    for ty in ["i32", "i64"] {
+        for op in SHIFTS {
+            for spill in [false, true] {
+                wat += &shift_function(ty, op, None, spill);
+                for amount in 0..=127 {
+                    wat += &shift_function(ty, op, Some(amount), spill);
+                }
+            }
+        }
+    }
+    wat += ")";
+    wat
+}
+
+/// What `op` evaluates to in WebAssembly, for comparison.
+fn reference_i64(op: &str, x: u64, amount: u32) -> u64 {
+    let amount = amount % 64;
+    match op {
+        "shl" => x << amount,
+        "shr_s" => ((x as i64) >> amount) as u64,
+        "shr_u" => x >> amount,
+        "rotl" => x.rotate_left(amount),
+        "rotr" => x.rotate_right(amount),
+        _ => unreachable!(),
+    }
+}
+
+fn reference_i32(op: &str, x: u32, amount: u32) -> u32 {
+    let amount = amount % 32;
+    match op {
+        "shl" => x << amount,
+        "shr_s" => ((x as i32) >> amount) as u32,
+        "shr_u" => x >> amount,
+        "rotl" => x.rotate_left(amount),
+        "rotr" => x.rotate_right(amount),
+        _ => unreachable!(),
+    }
+}
+
+/// Run every function in [`shifts_module()`] on [`INPUTS`], returning the
+/// name, input, amount and result of each call.
+fn run_shifts(config: &crate::Config) -> Result<Vec<(String, u64, u32, Value)>> {
+    let mut store = config.store();
+    let module = Module::new(&store, shifts_module())?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    let mut results = Vec::new();
+    for ty in ["i32", "i64"] {
+        for op in SHIFTS {
+            for spilled in ["", "/spilled"] {
+                for amount in 0..=127_u32 {
+                    let constant = format!("{ty}.{op}/{amount}{spilled}");
+                    let dynamic = format!("{ty}.{op}{spilled}");
+                    for &x in INPUTS {
+                        let args = match ty {
+                            "i32" => [Value::I32(x as i32), Value::I32(amount as i32)],
+                            _ => [Value::I64(x as i64), Value::I64(amount as i64)],
+                        };
+                        for name in [&constant, &dynamic] {
+                            let f = instance.exports.get_function(name)?;
+                            let result = f.call(&mut store, &args)?[0].clone();
+                            results.push((name.clone(), x, amount, result));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+#[compiler_test(shifts)]
+fn shifts_and_rotates(config: crate::Config) -> Result<()> {
+    for (name, x, amount, result) in run_shifts(&config)? {
+        let op = name.split(['.', '/']).nth(1).unwrap();
+        let expected = if name.starts_with("i32") {
+            Value::I32(reference_i32(op, x as u32, amount) as i32)
+        } else {
+            Value::I64(reference_i64(op, x, amount) as i64)
+        };
+        assert_eq!(result, expected, "{name} of {x:#x} by {amount}");
+    }
+
+    Ok(())
+}
+
+/// Singlepass and Cranelift agree on every shift and rotate.
+#[cfg(all(feature = "singlepass", feature = "cranelift"))]
+#[test]
+fn shifts_and_rotates_singlepass_matches_cranelift() -> Result<()> {
+    use crate::{Compiler, Config};
+
+    let singlepass = run_shifts(&Config::new(Compiler::Singlepass))?;
+    let cranelift = run_shifts(&Config::new(Compiler::Cranelift))?;
+
+    assert_eq!(singlepass.len(), cranelift.len());
+    for (singlepass, cranelift) in singlepass.iter().zip(&cranelift) {
+        let (name, x, amount, _) = singlepass;
+        assert_eq!(
+            singlepass, cranelift,
+            "{name} of {x:#x} by {amount} differs between singlepass and cranelift"
+        );
+    }
+
+    Ok(())
+}
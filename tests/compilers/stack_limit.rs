@@ -0,0 +1,204 @@
+//! Running calls with a per-store stack limit, and the errors they fail
+//! with when they run out of it.
+
+use anyhow::Result;
+use wasmer::*;
+
+const RECURSION: &str = r#"
+    (module
+        (import "host" "reenter" (func $reenter))
+        (global $depth (export "depth") (mut i32) (i32.const 0))
+
+        (func $recurse (export "recurse")
+            global.get $depth
+            i32.const 1
+            i32.add
+            global.set $depth
+            call $recurse)
+
+        (func (export "recurse_through_host")
+            global.get $depth
+            i32.const 1
+            i32.add
+            global.set $depth
+            call $reenter)
+
+        (func (export "add") (param i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.add))
+"#;
+
+/// An instance whose `reenter` import calls `recurse_through_host` again, so
+/// each level of recursion goes through the host.
+fn instantiate(store: &mut Store) -> Result<Instance> {
+    let module = Module::new(store, RECURSION)?;
+    let env = FunctionEnv::new(store, None);
+    let reenter = Function::new_typed_with_env(
+        store,
+        &env,
+        |mut env: FunctionEnvMut<Option<TypedFunction<(), ()>>>| -> Result<(), RuntimeError> {
+            let (recurse, mut store) = env.data_and_store_mut();
+            recurse.clone().unwrap().call(&mut store)
+        },
+    );
+    let instance = Instance::new(
+        store,
+        &module,
+        &imports! {
+            "host" => {
+                "reenter" => reenter,
+            },
+        },
+    )?;
+    *env.as_mut(store) = Some(
+        instance
+            .exports
+            .get_typed_function(store, "recurse_through_host")?,
+    );
+    Ok(instance)
+}
+
+/// Calls `name` until it runs out of stack, returning the error and how deep
+/// it got.
+fn exhaust(store: &mut Store, instance: &Instance, name: &str) -> Result<(RuntimeError, i32)> {
+    let depth = instance.exports.get_global("depth")?;
+    depth.set(store, Value::I32(0))?;
+    let recurse: TypedFunction<(), ()> = instance.exports.get_typed_function(store, name)?;
+    let error = recurse.call(store).unwrap_err();
+    Ok((error, depth.get(store).unwrap_i32()))
+}
+
+fn assert_overflowed_at(error: &RuntimeError, limit: usize) {
+    assert!(error.is_stack_overflow(), "unexpected error: {error}");
+    assert_eq!(
+        error.downcast_ref::<StackOverflow>(),
+        Some(&StackOverflow { limit })
+    );
+    assert!(error.message().contains("call stack exhausted"));
+    assert!(error.message().contains(&limit.to_string()));
+}
+
+#[compiler_test(stack_limit)]
+fn recursion_depth_follows_the_limit(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let instance = instantiate(&mut store)?;
+    let add: TypedFunction<(i32, i32), i32> = instance.exports.get_typed_function(&store, "add")?;
+
+    let mut previous_depth = 0;
+    for limit in [64 * 1024, 256 * 1024, 1024 * 1024] {
+        store.set_stack_limit(limit);
+        assert_eq!(store.stack_limit(), Some(limit));
+
+        let (error, depth) = exhaust(&mut store, &instance, "recurse")?;
+        assert_overflowed_at(&error, limit);
+        assert!(depth > previous_depth, "{depth} <= {previous_depth}");
+        previous_depth = depth;
+
+        // The store can still be used afterwards
+        assert_eq!(add.call(&mut store, 1, 2)?, 3);
+    }
+
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn engine_default_stack_limit(config: crate::Config) -> Result<()> {
+    let mut engine = config.engine(config.compiler_config(false));
+    engine.set_default_stack_limit(128 * 1024);
+    let mut store = Store::new(engine);
+    let instance = instantiate(&mut store)?;
+
+    let (error, _) = exhaust(&mut store, &instance, "recurse")?;
+    assert_overflowed_at(&error, 128 * 1024);
+
+    // The store's own limit takes precedence
+    store.set_stack_limit(64 * 1024);
+    let (error, _) = exhaust(&mut store, &instance, "recurse")?;
+    assert_overflowed_at(&error, 64 * 1024);
+
+    Ok(())
+}
+
+#[compiler_test(stack_limit)]
+fn reentrant_calls_share_the_limit(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let instance = instantiate(&mut store)?;
+    let add: TypedFunction<(i32, i32), i32> = instance.exports.get_typed_function(&store, "add")?;
+
+    let mut previous_depth = 0;
+    for limit in [256 * 1024, 1024 * 1024] {
+        store.set_stack_limit(limit);
+
+        // Without accounting for the stacks of the nested calls, this would
+        // keep going until the thread's own stack overflowed
+        let (error, depth) = exhaust(&mut store, &instance, "recurse_through_host")?;
+        assert_overflowed_at(&error, limit);
+        assert!(depth > previous_depth, "{depth} <= {previous_depth}");
+        previous_depth = depth;
+
+        assert_eq!(add.call(&mut store, 1, 2)?, 3);
+    }
+
+    Ok(())
+}
+
+/// A module whose `recurse` function never returns, with `locals` locals
+/// which are all live across the recursive call, so each of its frames is
+/// at least `locals * 8` bytes.
+fn huge_frames(locals: usize) -> String {
+    let mut wat =
+        String::from(r#"(module (func $recurse (export "recurse") (param i64) (result i64)"#);
+    wat.push_str(&format!("\n(local {})", "i64 ".repeat(locals)));
+    for i in 1..=locals {
+        wat.push_str(&format!(
+            "\n(local.set {i} (i64.add (local.get 0) (i64.const {i})))"
+        ));
+    }
+    wat.push_str("\n(call $recurse (i64.add (local.get 0) (i64.const 1)))");
+    for i in 1..=locals {
+        wat.push_str(&format!("\n(i64.add (local.get {i}))"));
+    }
+    wat.push_str("))");
+    wat
+}
+
+#[compiler_test(stack_limit)]
+fn huge_frames_overflow_into_the_guard(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    // Frames of 32KiB, far bigger than the single guard page below stacks
+    let module = Module::new(&store, huge_frames(4096))?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let recurse: TypedFunction<i64, i64> =
+        instance.exports.get_typed_function(&store, "recurse")?;
+
+    for limit in [64 * 1024, 1024 * 1024] {
+        store.set_stack_limit(limit);
+        let error = recurse.call(&mut store, 0).unwrap_err();
+        assert_overflowed_at(&error, limit);
+    }
+
+    Ok(())
+}
+
+// The extra guard is only mapped on platforms with `mprotect`
+#[cfg(unix)]
+#[compiler_test(stack_limit)]
+fn probes_in_steps_of_the_engine_guard(config: crate::Config) -> Result<()> {
+    let mut compiler_config = config.compiler_config(false);
+    compiler_config.set_stack_probes(StackProbes::Every(64 * 1024));
+    let mut engine = config.engine(compiler_config);
+    engine.set_stack_guard_size(64 * 1024);
+    assert_eq!(engine.stack_guard_size(), Some(64 * 1024));
+    let mut store = Store::new(engine);
+    let module = Module::new(&store, huge_frames(4096))?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let recurse: TypedFunction<i64, i64> =
+        instance.exports.get_typed_function(&store, "recurse")?;
+
+    store.set_stack_limit(1024 * 1024);
+    let error = recurse.call(&mut store, 0).unwrap_err();
+    assert_overflowed_at(&error, 1024 * 1024);
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! The tail call proposal: tail calls replace the caller's frame, so
+//! recursing through them doesn't grow the stack.
+
+use crate::{Compiler, Config};
+use anyhow::Result;
+use wasmer::*;
+use wasmer_types::TrapCode;
+
+/// Deeper than the stack allows with calls that keep the caller's frame.
+const DEPTH: i64 = 1_000_000;
+
+/// The store for `config` with the tail call proposal, if the compiler
+/// supports it.
+fn tail_call_store(mut config: Config) -> Option<Store> {
+    let mut features = Features::default();
+    features.tail_call(true);
+    config.set_features(features);
+    let store = config.store();
+
+    if config.compiler != Compiler::Cranelift {
+        // The other compilers refuse to compile anything with the proposal
+        // enabled, instead of failing when they run into a tail call
+        let err = Module::new(&store, "(module)").unwrap_err();
+        assert!(err.to_string().contains("tail calls"), "{err}");
+        return None;
+    }
+    Some(store)
+}
+
+#[compiler_test(tail_calls)]
+fn deep_mutual_recursion(config: crate::Config) -> Result<()> {
+    let mut store = match tail_call_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (type $i64_to_i32 (func (param i64) (result i32)))
+            (table funcref (elem $even_indirect $odd_indirect))
+
+            (func $even (export "even") (param i64) (result i32)
+                (if (result i32) (i64.eqz (local.get 0))
+                    (then (i32.const 1))
+                    (else (return_call $odd (i64.sub (local.get 0) (i64.const 1))))))
+            (func $odd (export "odd") (param i64) (result i32)
+                (if (result i32) (i64.eqz (local.get 0))
+                    (then (i32.const 0))
+                    (else (return_call $even (i64.sub (local.get 0) (i64.const 1))))))
+
+            (func $even_indirect (export "even_indirect") (param i64) (result i32)
+                (if (result i32) (i64.eqz (local.get 0))
+                    (then (i32.const 1))
+                    (else (return_call_indirect (type $i64_to_i32)
+                        (i64.sub (local.get 0) (i64.const 1))
+                        (i32.const 1)))))
+            (func $odd_indirect (export "odd_indirect") (param i64) (result i32)
+                (if (result i32) (i64.eqz (local.get 0))
+                    (then (i32.const 0))
+                    (else (return_call_indirect (type $i64_to_i32)
+                        (i64.sub (local.get 0) (i64.const 1))
+                        (i32.const 0)))))
+
+            ;; n + (n - 1) + ... + 1, going back and forth between functions
+            ;; with different parameters and locals
+            (func (export "sum") (param $n i64) (result i64)
+                (return_call $sum_2 (local.get $n) (i64.const 0)))
+            (func $sum_2 (param $n i64) (param $sum i64) (result i64)
+                (local f64)
+                (if (result i64) (i64.eqz (local.get $n))
+                    (then (local.get $sum))
+                    (else (return_call $sum_3
+                        (i64.sub (local.get $n) (i64.const 1))
+                        (i64.add (local.get $sum) (local.get $n))
+                        (i32.const 7)))))
+            (func $sum_3 (param $n i64) (param $sum i64) (param i32) (result i64)
+                (local $set i64)
+                ;; The locals are zero again on every call
+                (if (i64.ne (local.get $set) (i64.const 0)) (then (unreachable)))
+                (local.set $set (i64.const 1))
+                (return_call $sum_2 (local.get $n) (local.get $sum)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    for (name, expected) in [
+        ("even", 1),
+        ("odd", 0),
+        ("even_indirect", 1),
+        ("odd_indirect", 0),
+    ] {
+        let function: TypedFunction<i64, i32> =
+            instance.exports.get_typed_function(&store, name)?;
+        assert_eq!(function.call(&mut store, DEPTH)?, expected, "{name}");
+        assert_eq!(
+            function.call(&mut store, DEPTH + 1)?,
+            1 - expected,
+            "{name}"
+        );
+    }
+
+    let sum: TypedFunction<i64, i64> = instance.exports.get_typed_function(&store, "sum")?;
+    assert_eq!(sum.call(&mut store, DEPTH)?, DEPTH * (DEPTH + 1) / 2);
+    Ok(())
+}
+
+#[compiler_test(tail_calls)]
+fn tail_calls_check_signatures(config: crate::Config) -> Result<()> {
+    let mut store = match tail_call_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module
+            (type $i32_to_i32 (func (param i32) (result i32)))
+            (table funcref (elem $identity $nothing))
+
+            (func $identity (param i32) (result i32) (local.get 0))
+            (func $nothing)
+            (func (export "call") (param i32) (result i32)
+                (return_call_indirect (type $i32_to_i32) (i32.const 42) (local.get 0)))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let call: TypedFunction<i32, i32> = instance.exports.get_typed_function(&store, "call")?;
+
+    assert_eq!(call.call(&mut store, 0)?, 42);
+    let e = call.call(&mut store, 1).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::BadSignature));
+    let e = call.call(&mut store, 2).unwrap_err();
+    assert_eq!(e.to_trap(), Some(TrapCode::TableAccessOutOfBounds));
+    Ok(())
+}
+
+#[compiler_test(tail_calls)]
+fn tail_called_frames_replace_the_caller(config: crate::Config) -> Result<()> {
+    let mut store = match tail_call_store(config) {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let wat = r#"
+        (module $tail_calls
+            (func (export "run") (return_call $fail))
+            (func $fail (unreachable))
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let run = instance.exports.get_function("run")?;
+
+    let e = run.call(&mut store, &[]).unwrap_err();
+    let trace = e.trace();
+    assert_eq!(trace.len(), 1);
+    assert_eq!(trace[0].module_name(), "tail_calls");
+    assert_eq!(trace[0].func_index(), 1);
+    assert_eq!(trace[0].function_name(), Some("fail"));
+    Ok(())
+}
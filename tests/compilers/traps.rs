@@ -1,6 +1,7 @@
 use anyhow::Result;
 use std::panic::{self, AssertUnwindSafe};
 use wasmer::*;
+use wasmer_types::TrapCode;
 
 #[compiler_test(traps)]
 fn test_trap_return(config: crate::Config) -> Result<()> {
@@ -80,6 +81,218 @@ fn test_trap_trace(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+/// The frames are unwound through the unwind information of every function
+/// in the chain, so it needs to describe each of their frames, with their
+/// saved registers, correctly.
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_trace_deep_call_chain(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module $deep_mod
+            (func (export "run") (param i32)
+                (call $recurse (local.get 0) (f64.const 1.5)))
+            (func $recurse (param $n i32) (param $x f64)
+                (local $a i64) (local $b f64)
+                (local.set $a (i64.extend_i32_u (local.get $n)))
+                (local.set $b (f64.mul (local.get $x) (local.get $x)))
+                (if (i32.eqz (local.get $n)) (then unreachable))
+                (call $recurse
+                    (i32.sub (local.get $n) (i32.const 1))
+                    (f64.add (local.get $b) (f64.convert_i64_u (local.get $a)))))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let run_func = instance
+        .exports
+        .get_typed_function::<i32, ()>(&store, "run")?;
+
+    for depth in [50, 200] {
+        let e = run_func
+            .call(&mut store, depth)
+            .expect_err("error calling function");
+
+        let trace = e.trace();
+        assert_eq!(trace.len(), depth as usize + 2);
+        for frame in &trace[..=depth as usize] {
+            assert_eq!(frame.module_name(), "deep_mod");
+            assert_eq!(frame.func_index(), 1);
+            assert_eq!(frame.function_name(), Some("recurse"));
+        }
+        assert_eq!(trace.last().unwrap().func_index(), 0);
+        assert!(
+            e.message().contains("unreachable"),
+            "wrong message: {}",
+            e.message()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_trace_uses_custom_module_name(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module $hello_mod
+            (func (export "run") (call $hello))
+            (func $hello (unreachable))
+        )
+    "#;
+
+    let mut module = Module::new(&store, wat)?;
+    assert!(module.set_name("tenant-a"));
+    // The name should survive a round trip through serialization too
+    let module = unsafe { Module::deserialize(&store, module.serialize()?)? };
+    assert_eq!(module.name(), Some("tenant-a"));
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    let e = run_func
+        .call(&mut store, &[])
+        .expect_err("error calling function");
+
+    assert_eq!(e.trace()[0].module_name(), "tenant-a");
+    assert!(e.to_string().contains("at hello (tenant-a[1]:"), "{e}");
+
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn test_perfmap_uses_module_and_function_names(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let dir = tempfile::tempdir()?;
+    let perfmap = dir.path().join("perf.map");
+    store.engine().enable_perfmap(&perfmap)?;
+    let wat = r#"
+        (module $hello_mod
+            (func $work (export "work"))
+        )
+    "#;
+
+    let mut module = Module::new(&store, wat)?;
+    assert!(module.set_name("tenant-b"));
+    Instance::new(&mut store, &module, &imports! {})?;
+    Instance::new(&mut store, &module, &imports! {})?;
+
+    // Each function is only written once, no matter how many times its
+    // module is instantiated
+    let contents = std::fs::read_to_string(&perfmap)?;
+    let entries: Vec<&str> = contents.lines().collect();
+    assert_eq!(entries.len(), 1, "{contents}");
+    let fields: Vec<&str> = entries[0].splitn(3, ' ').collect();
+    assert!(usize::from_str_radix(fields[0], 16)? > 0);
+    assert!(usize::from_str_radix(fields[1], 16)? > 0);
+    assert_eq!(fields[2], "tenant-b::work");
+
+    Ok(())
+}
+
+/// Read a perf map's entries as their names and address ranges.
+fn read_perfmap(path: &std::path::Path) -> Result<Vec<(String, std::ops::Range<usize>)>> {
+    let contents = std::fs::read_to_string(path)?;
+    contents
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.splitn(3, ' ').collect();
+            let start = usize::from_str_radix(fields[0], 16)?;
+            let length = usize::from_str_radix(fields[1], 16)?;
+            Ok((fields[2].to_string(), start..start + length))
+        })
+        .collect()
+}
+
+const PROFILED_WAT: &str = r#"
+    (module $profiled
+        (func $double (export "double") (param i32) (result i32)
+            local.get 0
+            i32.const 2
+            i32.mul)
+        (func $quadruple (export "quadruple") (param i32) (result i32)
+            local.get 0
+            call $double
+            call $double)
+    )
+"#;
+
+#[compiler_test(traps)]
+fn test_perfmap_matches_function_code_ranges(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let dir = tempfile::tempdir()?;
+    let perfmap = dir.path().join("perf.map");
+    store.engine().enable_perfmap(&perfmap)?;
+
+    let module = Module::new(&store, PROFILED_WAT)?;
+    Instance::new(&mut store, &module, &imports! {})?;
+
+    let ranges = module.function_code_ranges();
+    assert_eq!(ranges.len(), 2);
+    assert!(ranges.iter().all(|range| !range.is_empty()));
+    assert_eq!(
+        read_perfmap(&perfmap)?,
+        vec![
+            ("profiled::double".to_string(), ranges[0].clone()),
+            ("profiled::quadruple".to_string(), ranges[1].clone()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[compiler_test(traps)]
+fn test_jitdump_has_function_code(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let dir = tempfile::tempdir()?;
+    let jitdump = dir.path().join("jit.dump");
+    store.engine().enable_jitdump(&jitdump)?;
+
+    let module = Module::new(&store, PROFILED_WAT)?;
+    Instance::new(&mut store, &module, &imports! {})?;
+
+    let dump = std::fs::read(&jitdump)?;
+    let u32_at = |offset: usize| u32::from_ne_bytes(dump[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_ne_bytes(dump[offset..offset + 8].try_into().unwrap());
+    assert_eq!(u32_at(0), 0x4A69_5444, "bad magic");
+    assert_eq!(u32_at(4), 1, "bad version");
+    assert_eq!(u32_at(20), std::process::id());
+
+    // Every JIT_CODE_LOAD record, as its name, address range and code
+    let mut loads = Vec::new();
+    let mut offset = u32_at(8) as usize;
+    while offset < dump.len() {
+        let (id, size) = (u32_at(offset), u32_at(offset + 4) as usize);
+        if id == 0 {
+            let address = u64_at(offset + 32) as usize;
+            let length = u64_at(offset + 40) as usize;
+            let name_start = offset + 56;
+            let name_end = name_start + dump[name_start..].iter().position(|b| *b == 0).unwrap();
+            let name = std::str::from_utf8(&dump[name_start..name_end])?.to_string();
+            let code = dump[name_end + 1..offset + size].to_vec();
+            loads.push((name, address..address + length, code));
+        }
+        offset += size;
+    }
+
+    let ranges = module.function_code_ranges();
+    assert_eq!(loads.len(), 2);
+    for ((name, range, code), expected) in loads.iter().zip(&ranges) {
+        assert!(name.starts_with("profiled::"));
+        assert_eq!(range, expected);
+        // SAFETY: the module is alive, so its code is still mapped
+        let actual = unsafe { std::slice::from_raw_parts(range.start as *const u8, range.len()) };
+        assert_eq!(code, actual);
+    }
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     let mut store = config.store();
@@ -246,6 +459,134 @@ RuntimeError: unreachable
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn trap_display_pretty_with_source_map(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module $m
+            (func $die unreachable)
+            (func call $die)
+            (func $foo call 1)
+            (func (export "bar") call $foo)
+        )
+    "#;
+
+    let module = Module::new_with_source_map(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let run_func = instance
+        .exports
+        .get_function("bar")
+        .expect("expected function export");
+
+    let e = run_func
+        .call(&mut store, &[])
+        .expect_err("error calling function");
+    assert_eq!(
+        e.to_string(),
+        "\
+RuntimeError: unreachable
+    at die (m[0]:0x23, m:3:24)
+    at <unnamed> (m[1]:0x27, m:4:19)
+    at foo (m[2]:0x2c, m:5:24)
+    at <unnamed> (m[3]:0x31, m:6:34)"
+    );
+    Ok(())
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn trap_display_multi_module_with_source_map(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module $a
+            (func $die unreachable)
+            (func call $die)
+            (func $foo call 1)
+            (func (export "bar") call $foo)
+        )
+    "#;
+
+    let module = Module::new_with_source_map(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let bar = instance.exports.get_function("bar")?.clone();
+
+    // Only the module compiled with a source map gets the text locations
+    let wat = r#"
+        (module $b
+            (import "" "" (func $bar))
+            (func $middle call $bar)
+            (func (export "bar2") call $middle)
+        )
+    "#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(
+        &mut store,
+        &module,
+        &imports! {
+            "" => {
+                "" => bar
+            }
+        },
+    )?;
+    let bar2 = instance
+        .exports
+        .get_function("bar2")
+        .expect("expected function export");
+
+    let e = bar2
+        .call(&mut store, &[])
+        .expect_err("error calling function");
+    assert_eq!(
+        e.to_string(),
+        "\
+RuntimeError: unreachable
+    at die (a[0]:0x23, a:3:24)
+    at <unnamed> (a[1]:0x27, a:4:19)
+    at foo (a[2]:0x2c, a:5:24)
+    at <unnamed> (a[3]:0x31, a:6:34)
+    at middle (b[1]:0x29)
+    at <unnamed> (b[2]:0x2e)"
+    );
+    Ok(())
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn trap_display_demangled_names(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module $m
+            (func $_ZN5space3dieEv unreachable)
+            (func $_ZN4core9panicking5panic17h5b8b5b6b0b8d1e6aE call 0)
+            (func (export "run") call 1)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let serialized = module.serialize()?;
+    let deserialized = unsafe { Module::deserialize(&store, serialized)? };
+
+    for module in [module, deserialized] {
+        let instance = Instance::new(&mut store, &module, &imports! {})?;
+        let run = instance.exports.get_function("run")?;
+
+        let e = run
+            .call(&mut store, &[])
+            .expect_err("error calling function");
+
+        let names: Vec<_> = e.trace().iter().map(|f| f.function_name()).collect();
+        assert_eq!(
+            names,
+            [Some("space::die()"), Some("core::panicking::panic"), None]
+        );
+        assert!(e.to_string().contains("at space::die() (m[0]:"));
+        assert!(e.to_string().contains("at <unnamed> (m[2]:"));
+    }
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn trap_start_function_import(config: crate::Config) -> Result<()> {
     let mut store = config.store();
@@ -490,3 +831,294 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[derive(Debug, PartialEq)]
+enum RateLimitError {
+    RateLimited,
+}
+
+impl std::fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rate limited")
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+/// Instantiates a module where calling `outer` goes through three levels
+/// of host functions: the caller, `middle` and `innermost`.
+fn instantiate_nested(store: &mut Store, innermost: Function) -> Result<Instance> {
+    let binary = r#"
+        (module
+            (import "host" "middle" (func $middle))
+            (import "host" "innermost" (func $innermost))
+            (func (export "outer") call $middle)
+            (func (export "inner") call $innermost)
+        )
+    "#;
+    let module = Module::new(store, binary)?;
+    let env = FunctionEnv::new(store, None::<Function>);
+    let middle = Function::new_typed_with_env(
+        store,
+        &env,
+        |mut env: FunctionEnvMut<Option<Function>>| -> Result<(), RuntimeError> {
+            let inner = env.data().clone().unwrap();
+            // Go through `anyhow` too, which must not hide the original error
+            let result: Result<()> = (|| {
+                inner.call(&mut env, &[])?;
+                Ok(())
+            })();
+            result.map_err(RuntimeError::from)
+        },
+    );
+    let instance = Instance::new(
+        store,
+        &module,
+        &imports! {
+            "host" => {
+                "middle" => middle,
+                "innermost" => innermost,
+            }
+        },
+    )?;
+    let inner = instance.exports.get_function("inner")?.clone();
+    *env.as_mut(store) = Some(inner);
+    Ok(instance)
+}
+
+#[compiler_test(traps)]
+fn downcast_typed_host_error_across_nested_calls(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let innermost = Function::new_typed(&mut store, || -> Result<(), RateLimitError> {
+        Err(RateLimitError::RateLimited)
+    });
+    let instance = instantiate_nested(&mut store, innermost)?;
+
+    let outer = instance.exports.get_function("outer")?;
+    let err = outer.call(&mut store, &[]).unwrap_err();
+    assert!(err.is::<RateLimitError>());
+    assert_eq!(
+        err.downcast_ref::<RateLimitError>(),
+        Some(&RateLimitError::RateLimited)
+    );
+    assert_eq!(
+        err.downcast::<RateLimitError>().ok(),
+        Some(RateLimitError::RateLimited)
+    );
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn downcast_dynamic_host_error_across_nested_calls(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let sig = FunctionType::new(vec![], vec![]);
+    let innermost = Function::new(&mut store, &sig, |_| {
+        Err(anyhow::anyhow!(RateLimitError::RateLimited).into())
+    });
+    let instance = instantiate_nested(&mut store, innermost)?;
+
+    let outer = instance.exports.get_function("outer")?;
+    let err = outer.call(&mut store, &[]).unwrap_err();
+    assert_eq!(err.message(), "rate limited");
+    assert_eq!(
+        err.downcast_ref::<RateLimitError>(),
+        Some(&RateLimitError::RateLimited)
+    );
+    assert_eq!(
+        err.downcast::<RateLimitError>().ok(),
+        Some(RateLimitError::RateLimited)
+    );
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn resume_host_panic_across_nested_calls(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let sig = FunctionType::new(vec![], vec![]);
+    let innermost = Function::new(&mut store, &sig, |_| panic!("this is a nested panic"));
+    let instance = instantiate_nested(&mut store, innermost)?;
+
+    let outer = instance.exports.get_function("outer")?.clone();
+    let err = panic::catch_unwind(AssertUnwindSafe(|| {
+        drop(outer.call(&mut store, &[]));
+    }))
+    .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<&'static str>(),
+        Some(&"this is a nested panic")
+    );
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn memory64_loads_stores_and_traps(mut config: crate::Config) -> Result<()> {
+    let mut features = Features::default();
+    features.memory64(true);
+    config.set_features(features);
+    let mut store = config.store();
+    let wat = r#"
+        (module
+          (memory (export "memory") i64 1)
+          (func (export "store") (param i64 i64)
+            local.get 0
+            local.get 1
+            i64.store)
+          (func (export "load") (param i64) (result i64)
+            local.get 0
+            i64.load)
+          (func (export "load_far") (param i64) (result i64)
+            local.get 0
+            i64.load offset=4294967296)
+          (func (export "grow") (param i64) (result i64)
+            local.get 0
+            memory.grow))
+    "#;
+
+    let module = match Module::new(&store, wat) {
+        Ok(module) => module,
+        // Only Cranelift supports 64-bit memories so far
+        Err(CompileError::UnsupportedFeature(_))
+            if config.compiler != crate::Compiler::Cranelift =>
+        {
+            return Ok(())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let store_fn: TypedFunction<(u64, u64), ()> =
+        instance.exports.get_typed_function(&store, "store")?;
+    let load: TypedFunction<u64, u64> = instance.exports.get_typed_function(&store, "load")?;
+    let load_far: TypedFunction<u64, u64> =
+        instance.exports.get_typed_function(&store, "load_far")?;
+    let grow: TypedFunction<u64, u64> = instance.exports.get_typed_function(&store, "grow")?;
+
+    store_fn.call(&mut store, 8, 0x0123_4567_89ab_cdef)?;
+    assert_eq!(load.call(&mut store, 8)?, 0x0123_4567_89ab_cdef);
+
+    let memory = instance.exports.get_memory("memory")?;
+    assert!(memory.ty(&store).memory64);
+    let bytes = memory.view(&store).copy_to_vec()?;
+    assert_eq!(bytes[8..16], 0x0123_4567_89ab_cdef_u64.to_le_bytes());
+
+    assert_eq!(grow.call(&mut store, 1)?, 1);
+    assert_eq!(grow.call(&mut store, u64::MAX)?, u64::MAX);
+
+    let e = load.call(&mut store, 2 * 65536).unwrap_err();
+    assert!(e.message().contains("out of bounds"), "{}", e.message());
+    // Offsets which don't fit in 32 bits must not wrap back into bounds
+    let e = load_far.call(&mut store, 0).unwrap_err();
+    assert!(e.message().contains("out of bounds"), "{}", e.message());
+    let e = load_far.call(&mut store, u64::MAX - 0xffff).unwrap_err();
+    assert!(e.message().contains("out of bounds"), "{}", e.message());
+    Ok(())
+}
+
+/// Signal handlers installed over wasmer's, like the ones of libraries
+/// loaded by host functions, which pass every signal on to the handler they
+/// replaced.
+#[cfg(unix)]
+mod third_party_handlers {
+    use std::mem::{self, MaybeUninit};
+    use std::ptr;
+    use std::sync::Once;
+
+    static mut PREV_SIGSEGV: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+    static mut PREV_SIGBUS: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+
+    extern "C" fn handler(
+        signum: libc::c_int,
+        siginfo: *mut libc::siginfo_t,
+        context: *mut libc::c_void,
+    ) {
+        unsafe {
+            let previous = match signum {
+                libc::SIGSEGV => &*PREV_SIGSEGV.as_ptr(),
+                _ => &*PREV_SIGBUS.as_ptr(),
+            };
+            if previous.sa_sigaction == libc::SIG_DFL || previous.sa_sigaction == libc::SIG_IGN {
+                libc::sigaction(signum, previous, ptr::null_mut());
+            } else if previous.sa_flags & libc::SA_SIGINFO != 0 {
+                mem::transmute::<
+                    usize,
+                    extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void),
+                >(previous.sa_sigaction)(signum, siginfo, context)
+            } else {
+                mem::transmute::<usize, extern "C" fn(libc::c_int)>(previous.sa_sigaction)(signum)
+            }
+        }
+    }
+
+    /// Install the handlers, once for the whole process.
+    pub fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER | libc::SA_ONSTACK;
+            action.sa_sigaction = handler as usize;
+            libc::sigemptyset(&mut action.sa_mask);
+            assert_eq!(
+                libc::sigaction(libc::SIGSEGV, &action, PREV_SIGSEGV.as_mut_ptr()),
+                0
+            );
+            assert_eq!(
+                libc::sigaction(libc::SIGBUS, &action, PREV_SIGBUS.as_mut_ptr()),
+                0
+            );
+        });
+    }
+
+    /// Whether the handler for SIGSEGV is ours.
+    pub fn is_first() -> bool {
+        unsafe {
+            let mut current: libc::sigaction = mem::zeroed();
+            libc::sigaction(libc::SIGSEGV, ptr::null(), &mut current);
+            current.sa_sigaction == handler as usize
+        }
+    }
+}
+
+// Wasmer puts its handlers back in front of the ones installed over them, so
+// out of bounds accesses keep trapping on every thread, and the handlers
+// only get the signals which aren't from wasm.
+#[cfg(unix)]
+#[compiler_test(traps)]
+fn concurrent_oob_traps_with_third_party_handlers(config: crate::Config) -> Result<()> {
+    const THREADS: usize = 16;
+    const CALLS: usize = 200;
+
+    let store = config.store();
+    let wat = r#"
+        (module
+          (memory 1)
+          (func (export "load") (param i32) (result i32)
+            (i32.load (local.get 0))))
+    "#;
+    let module = Module::new(&store, wat)?;
+    let engine = store.engine().clone();
+
+    third_party_handlers::install();
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let engine = engine.clone();
+            let module = module.clone();
+            std::thread::spawn(move || -> Result<()> {
+                let mut store = Store::new(engine);
+                let instance = Instance::new(&mut store, &module, &imports! {})?;
+                let load: TypedFunction<i32, i32> =
+                    instance.exports.get_typed_function(&store, "load")?;
+                for i in 0..CALLS {
+                    assert_eq!(load.call(&mut store, (i % 100) as i32 * 4)?, 0);
+                    let e = load.call(&mut store, 65536 + i as i32).unwrap_err();
+                    assert_eq!(e.to_trap(), Some(TrapCode::HeapAccessOutOfBounds));
+                    assert!(!third_party_handlers::is_first());
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+
+    Ok(())
+}
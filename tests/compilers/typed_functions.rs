@@ -241,6 +241,95 @@ fn typed_function_works_for_wasm_function_manyparams_dynamic(
     Ok(())
 }
 
+/// Build a module which exports a function taking `arity` i32 parameters
+/// that forwards them to the imported `env.weighted_sum`.
+fn forwarding_module(arity: usize) -> String {
+    let params = vec!["i32"; arity].join(" ");
+    let args: String = (0..arity).map(|i| format!("(local.get {i})")).collect();
+    format!(
+        r#"(module
+            (func $weighted_sum (import "env" "weighted_sum") (param {params}) (result i32))
+            (func (export "forward") (param {params}) (result i32)
+                (call $weighted_sum {args})))"#
+    )
+}
+
+#[compiler_test(typed_functions)]
+fn typed_functions_with_20_params(config: crate::Config) -> anyhow::Result<()> {
+    let mut store = config.store();
+    let module = Module::new(&store, forwarding_module(20))?;
+
+    #[rustfmt::skip]
+    let weighted_sum = Function::new_typed(
+        &mut store,
+        |a1: i32, a2: i32, a3: i32, a4: i32, a5: i32, a6: i32, a7: i32, a8: i32, a9: i32, a10: i32,
+         a11: i32, a12: i32, a13: i32, a14: i32, a15: i32, a16: i32, a17: i32, a18: i32, a19: i32,
+         a20: i32| -> i32 {
+            [a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11, a12, a13, a14, a15, a16, a17, a18, a19, a20]
+                .iter()
+                .enumerate()
+                .map(|(i, a)| (i as i32 + 1) * a)
+                .sum()
+        },
+    );
+    let instance = Instance::new(
+        &mut store,
+        &module,
+        &imports! { "env" => { "weighted_sum" => weighted_sum } },
+    )?;
+
+    #[rustfmt::skip]
+    type Params = (
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+    );
+    let forward: TypedFunction<Params, i32> =
+        instance.exports.get_typed_function(&store, "forward")?;
+    #[rustfmt::skip]
+    let result = forward.call(
+        &mut store, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2,
+    )?;
+    assert_eq!(result, (1..=19).sum::<i32>() + 20 * 2);
+    Ok(())
+}
+
+#[compiler_test(typed_functions)]
+fn typed_functions_with_26_params(config: crate::Config) -> anyhow::Result<()> {
+    let mut store = config.store();
+    let module = Module::new(&store, forwarding_module(26))?;
+
+    // Dynamic host functions aren't limited to 26 parameters like native
+    // ones, so they work for any arity
+    let ty = FunctionType::new(vec![ValueType::I32; 26], vec![ValueType::I32]);
+    let weighted_sum = Function::new(&mut store, &ty, |args| {
+        let sum = args
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (i as i32 + 1) * a.unwrap_i32())
+            .sum();
+        Ok(vec![Value::I32(sum)])
+    });
+    let instance = Instance::new(
+        &mut store,
+        &module,
+        &imports! { "env" => { "weighted_sum" => weighted_sum } },
+    )?;
+
+    #[rustfmt::skip]
+    type Params = (
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+    );
+    let forward: TypedFunction<Params, i32> =
+        instance.exports.get_typed_function(&store, "forward")?;
+    #[rustfmt::skip]
+    let result = forward.call(
+        &mut store, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 3,
+    )?;
+    assert_eq!(result, (1..=25).sum::<i32>() + 26 * 3);
+    Ok(())
+}
+
 #[compiler_test(typed_functions)]
 fn static_host_function_without_env(config: crate::Config) -> anyhow::Result<()> {
     let mut store = config.store();
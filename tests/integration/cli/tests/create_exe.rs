@@ -684,3 +684,169 @@ fn create_exe_with_object_input(args: Vec<String>) -> anyhow::Result<()> {
 fn create_exe_with_object_input_default() -> anyhow::Result<()> {
     create_exe_with_object_input(vec![])
 }
+
+/// The code of objects created with `--aot-text-section-size` is split into
+/// sections no bigger than that, except for those with a single function.
+#[test]
+fn create_obj_text_section_size() -> anyhow::Result<()> {
+    use object::{Object, ObjectSection, ObjectSymbol, SectionKind, SymbolKind};
+
+    const LIMIT: u64 = 64 * 1024;
+
+    let temp_dir = tempfile::tempdir()?;
+    let operating_dir = temp_dir.path().to_owned();
+    let object_path = operating_dir.join("wasm.o");
+    WasmerCreateObj {
+        current_dir: operating_dir.clone(),
+        wasm_path: operating_dir.join(create_exe_test_wasm_path()),
+        output_object_path: object_path.clone(),
+        compiler: Compiler::Cranelift,
+        extra_cli_flags: vec!["--aot-text-section-size".to_string(), "64k".to_string()],
+        ..Default::default()
+    }
+    .run()
+    .context("Failed to create-obj wasm with Wasmer")?;
+
+    let data = fs::read(&object_path)?;
+    let file = object::File::parse(&*data)?;
+    let text_sections: Vec<_> = file
+        .sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+        .collect();
+    assert!(text_sections.len() > 1);
+    for section in text_sections {
+        if section.size() > LIMIT {
+            let functions = file
+                .symbols()
+                .filter(|symbol| {
+                    symbol.kind() == SymbolKind::Text
+                        && symbol.section_index() == Some(section.index())
+                })
+                .count();
+            assert_eq!(functions, 1, "{:?}", section.name());
+        }
+    }
+
+    Ok(())
+}
+
+// Ignored because of -lunwind linker issue on Windows
+// see https://github.com/wasmerio/wasmer/issues/3459
+#[cfg_attr(target_os = "windows", ignore)]
+#[test]
+fn create_exe_with_object_input_text_section_size() -> anyhow::Result<()> {
+    create_exe_with_object_input(vec![
+        "--aot-text-section-size".to_string(),
+        "64k".to_string(),
+    ])
+}
+
+/// A module whose exported `quadruple` calls `double` twice, as a
+/// `(module (func $double ...) (func (export "quadruple") ...))`.
+const QUADRUPLE_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x01, 0x06, 0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f, // (type (func (param i32) (result i32)))
+    0x03, 0x03, 0x02, 0x00, 0x00, // two functions of that type
+    0x07, 0x0d, 0x01, 0x09, b'q', b'u', b'a', b'd', b'r', b'u', b'p', b'l', b'e', 0x00,
+    0x01, // (export "quadruple" (func 1))
+    0x0a, 0x12, 0x02, // code
+    0x07, 0x00, 0x20, 0x00, 0x20, 0x00, 0x6a, 0x0b, // local.get 0 local.get 0 i32.add
+    0x08, 0x00, 0x20, 0x00, 0x10, 0x00, 0x10, 0x00, 0x0b, // local.get 0 call 0 call 0
+];
+
+const QUADRUPLE_SHIM_C: &str = r#"
+int wasmer_function_pic_1(void *vmctx, int x);
+
+int quadruple(int x) {
+    /* The function doesn't touch its instance */
+    return wasmer_function_pic_1(0, x);
+}
+"#;
+
+const DLOPEN_HARNESS_C: &str = r#"
+#include <dlfcn.h>
+#include <stdio.h>
+
+int main(int argc, char **argv) {
+    void *library = dlopen(argv[1], RTLD_NOW);
+    if (!library) {
+        fprintf(stderr, "%s\n", dlerror());
+        return 1;
+    }
+    int (*quadruple)(int) = (int (*)(int))dlsym(library, "quadruple");
+    if (!quadruple) {
+        fprintf(stderr, "%s\n", dlerror());
+        return 1;
+    }
+    printf("%d\n", quadruple(5));
+    return 0;
+}
+"#;
+
+fn cc(operating_dir: &std::path::Path, args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new("cc")
+        .current_dir(operating_dir)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "cc {args:?} failed with: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Objects built with `create-obj --pic` can be linked into a shared
+/// library, which a C program loads and calls into.
+#[cfg(target_os = "linux")]
+#[test]
+fn create_obj_pic_links_into_shared_library() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let operating_dir = temp_dir.path().to_owned();
+    let wasm_path = operating_dir.join("quadruple.wasm");
+    fs::write(&wasm_path, QUADRUPLE_WASM)?;
+    fs::write(operating_dir.join("shim.c"), QUADRUPLE_SHIM_C)?;
+    fs::write(operating_dir.join("harness.c"), DLOPEN_HARNESS_C)?;
+
+    WasmerCreateObj {
+        current_dir: operating_dir.clone(),
+        wasm_path,
+        output_object_path: operating_dir.join("quadruple.o"),
+        compiler: Compiler::Cranelift,
+        extra_cli_flags: vec![
+            "--pic".to_string(),
+            "--prefix".to_string(),
+            "pic".to_string(),
+        ],
+        ..Default::default()
+    }
+    .run()
+    .context("Failed to create-obj wasm with Wasmer")?;
+
+    cc(
+        &operating_dir,
+        &[
+            "-shared",
+            "-fPIC",
+            "-o",
+            "libquadruple.so",
+            "shim.c",
+            "quadruple.o",
+        ],
+    )
+    .context("Failed to link the shared library")?;
+    cc(&operating_dir, &["-o", "harness", "harness.c", "-ldl"])
+        .context("Failed to build the harness")?;
+
+    let result = run_code(
+        &operating_dir,
+        &operating_dir.join("harness"),
+        &[operating_dir.join("libquadruple.so").display().to_string()],
+        false,
+    )
+    .context("Failed to run the harness")?;
+    assert_eq!(result.trim(), "20");
+
+    Ok(())
+}
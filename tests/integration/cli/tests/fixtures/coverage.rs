@@ -0,0 +1,17 @@
+//! Says whether it was given an odd or even number of arguments, for the
+//! `--coverage-out` tests. Compiled with `-g`, so the lines of this file can
+//! be checked in the LCOV output.
+
+#[inline(never)]
+fn classify(n: usize) -> &'static str {
+    if n % 2 == 0 {
+        "even"
+    } else {
+        "odd"
+    }
+}
+
+fn main() {
+    let n = std::env::args().count();
+    println!("{}", classify(n));
+}
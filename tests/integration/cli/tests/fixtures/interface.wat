@@ -0,0 +1,17 @@
+(module
+  (import "env" "log" (func (param i32 i64) (result f32)))
+  (import "env" "memory" (memory 1 16 shared))
+  (import "env" "table" (table 2 funcref))
+  (import "env" "offset" (global i32))
+  (import "env" "counter" (global (mut i64)))
+
+  (table (export "refs") 0 8 externref)
+  (global (export "scale") (mut f32) (f32.const 1))
+
+  (func (export "split") (param v128 f64) (result f64 i32)
+    local.get 1
+    i32.const 0)
+
+  (export "memory" (memory 0))
+  (export "table" (table 0))
+  (export "offset" (global 0)))
@@ -0,0 +1,75 @@
+use std::process::Command;
+use wasmer_integration_tests_cli::get_wasmer_path;
+
+#[test]
+fn inspect_json_matches_the_golden_file() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let module = temp.path().join("interface.wat");
+    std::fs::write(&module, include_str!("./fixtures/interface.wat"))?;
+
+    let output = Command::new(get_wasmer_path())
+        .arg("inspect")
+        .arg("--json")
+        .arg(&module)
+        .output()?;
+
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(output.status.success(), "wasmer inspect failed: {stderr}");
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    let golden = include_str!("./fixtures/interface.json");
+    pretty_assertions::assert_eq!(stdout.trim(), golden.trim());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn disassemble_modules_and_artifacts() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    let module = temp.path().join("add.wat");
+    std::fs::write(
+        &module,
+        r#"(module
+            (func $add (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+            (func $noop (export "noop")))"#,
+    )?;
+    let artifact = temp.path().join("add.wasmu");
+
+    let disassemble = |path: &std::path::Path| -> anyhow::Result<String> {
+        let output = Command::new(get_wasmer_path())
+            .arg("inspect")
+            .arg("--disassemble")
+            .arg("--function=add")
+            .arg(path)
+            .output()?;
+        let stderr = std::str::from_utf8(&output.stderr)?;
+        assert!(output.status.success(), "wasmer inspect failed: {stderr}");
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    let from_module = disassemble(&module)?;
+    assert!(
+        from_module.starts_with(";; function 0 add\n"),
+        "{from_module}"
+    );
+    assert!(
+        from_module.lines().any(|line| line.ends_with(" ret")),
+        "{from_module}"
+    );
+    assert!(!from_module.contains("noop"), "{from_module}");
+
+    let output = Command::new(get_wasmer_path())
+        .arg("compile")
+        .arg(&module)
+        .arg("-o")
+        .arg(&artifact)
+        .output()?;
+    let stderr = std::str::from_utf8(&output.stderr)?;
+    assert!(output.status.success(), "wasmer compile failed: {stderr}");
+
+    pretty_assertions::assert_eq!(disassemble(&artifact)?, from_module);
+
+    Ok(())
+}
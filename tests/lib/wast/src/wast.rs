@@ -146,6 +146,17 @@ impl Wast {
         bail!("expected '{}', got '{}'", expected, actual)
     }
 
+    fn assert_exception(&self, result: Result<Vec<Value>>) -> Result<()> {
+        let error = match result {
+            Ok(values) => bail!("expected exception, got {:?}", values),
+            Err(e) => e,
+        };
+        match error.downcast_ref::<RuntimeError>() {
+            Some(e) if e.downcast_ref::<Exception>().is_some() => Ok(()),
+            _ => bail!("expected exception, got '{}'", error),
+        }
+    }
+
     fn run_directive(&mut self, test: &Path, directive: wast::WastDirective) -> Result<()> {
         use wast::WastDirective::*;
 
@@ -217,8 +228,9 @@ impl Wast {
             QuoteModule { .. } => {
                 // Do nothing
             }
-            AssertException { .. } => {
-                // Do nothing for now
+            AssertException { span: _, exec } => {
+                let result = self.perform_execute(exec);
+                self.assert_exception(result)?;
             }
             AssertMalformed {
                 module,
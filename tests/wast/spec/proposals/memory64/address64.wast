@@ -0,0 +1,102 @@
+;; Load i32 and i64 data with different offsets from a 64-bit memory
+
+(module
+  (memory i64 1)
+  (data (i64.const 0) "abcdefghijklmnopqrstuvwxyz")
+
+  (func (export "8u_good1") (param $i i64) (result i32)
+    (i32.load8_u offset=0 (local.get $i))                   ;; 97 'a'
+  )
+  (func (export "8u_good2") (param $i i64) (result i32)
+    (i32.load8_u align=1 (local.get $i))                    ;; 97 'a'
+  )
+  (func (export "8u_good3") (param $i i64) (result i32)
+    (i32.load8_u offset=1 align=1 (local.get $i))           ;; 98 'b'
+  )
+  (func (export "8u_good4") (param $i i64) (result i32)
+    (i32.load8_u offset=2 align=1 (local.get $i))           ;; 99 'c'
+  )
+  (func (export "8u_good5") (param $i i64) (result i32)
+    (i32.load8_u offset=25 align=1 (local.get $i))          ;; 122 'z'
+  )
+  (func (export "8u_bad") (param $i i64)
+    (drop (i32.load8_u offset=18446744073709551615 (local.get $i)))
+  )
+
+  (func (export "32_good1") (param $i i64) (result i32)
+    (i32.load offset=0 (local.get $i))                      ;; 1684234849 'abcd'
+  )
+  (func (export "32_good2") (param $i i64) (result i32)
+    (i32.load offset=1 align=1 (local.get $i))              ;; 1701077858 'bcde'
+  )
+  (func (export "32_good3") (param $i i64) (result i32)
+    (i32.load offset=2 align=2 (local.get $i))              ;; 1717920867 'cdef'
+  )
+  (func (export "32_good4") (param $i i64) (result i32)
+    (i32.load offset=25 align=1 (local.get $i))             ;; 122 'z\0\0\0'
+  )
+  (func (export "32_bad") (param $i i64)
+    (drop (i32.load offset=4294967296 (local.get $i)))
+  )
+
+  (func (export "64_good1") (param $i i64) (result i64)
+    (i64.load offset=0 (local.get $i))                      ;; 'abcdefgh'
+  )
+  (func (export "64_good2") (param $i i64) (result i64)
+    (i64.load offset=1 align=1 (local.get $i))              ;; 'bcdefghi'
+  )
+  (func (export "64_good3") (param $i i64) (result i64)
+    (i64.load offset=2 align=2 (local.get $i))              ;; 'cdefghij'
+  )
+  (func (export "64_good4") (param $i i64) (result i64)
+    (i64.load offset=25 align=1 (local.get $i))             ;; 'z\0\0\0\0\0\0\0'
+  )
+  (func (export "64_bad") (param $i i64)
+    (drop (i64.load offset=18446744073709551615 (local.get $i)))
+  )
+
+  (func (export "store_far") (param $i i64) (param $v i32)
+    (i32.store offset=4294967296 (local.get $i) (local.get $v))
+  )
+)
+
+(assert_return (invoke "8u_good1" (i64.const 0)) (i32.const 97))
+(assert_return (invoke "8u_good2" (i64.const 0)) (i32.const 97))
+(assert_return (invoke "8u_good3" (i64.const 0)) (i32.const 98))
+(assert_return (invoke "8u_good4" (i64.const 0)) (i32.const 99))
+(assert_return (invoke "8u_good5" (i64.const 0)) (i32.const 122))
+
+(assert_return (invoke "8u_good1" (i64.const 65503)) (i32.const 0))
+(assert_return (invoke "8u_good5" (i64.const 65503)) (i32.const 0))
+(assert_return (invoke "8u_good5" (i64.const 65510)) (i32.const 0))
+(assert_trap (invoke "8u_good5" (i64.const 65511)) "out of bounds memory access")
+(assert_trap (invoke "8u_good1" (i64.const 0x10000)) "out of bounds memory access")
+(assert_trap (invoke "8u_good1" (i64.const 0x100000000)) "out of bounds memory access")
+(assert_trap (invoke "8u_good1" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "8u_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "8u_bad" (i64.const 1)) "out of bounds memory access")
+
+(assert_return (invoke "32_good1" (i64.const 0)) (i32.const 1684234849))
+(assert_return (invoke "32_good2" (i64.const 0)) (i32.const 1701077858))
+(assert_return (invoke "32_good3" (i64.const 0)) (i32.const 1717920867))
+(assert_return (invoke "32_good4" (i64.const 0)) (i32.const 122))
+
+(assert_return (invoke "32_good1" (i64.const 65508)) (i32.const 0))
+(assert_trap (invoke "32_good4" (i64.const 65508)) "out of bounds memory access")
+(assert_trap (invoke "32_good1" (i64.const 0xffffffff)) "out of bounds memory access")
+(assert_trap (invoke "32_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "32_bad" (i64.const 1)) "out of bounds memory access")
+
+(assert_return (invoke "64_good1" (i64.const 0)) (i64.const 7523094288207667809))
+(assert_return (invoke "64_good2" (i64.const 0)) (i64.const 7595434461045744482))
+(assert_return (invoke "64_good3" (i64.const 0)) (i64.const 7667774633883821155))
+(assert_return (invoke "64_good4" (i64.const 0)) (i64.const 122))
+
+(assert_return (invoke "64_good1" (i64.const 65503)) (i64.const 0))
+(assert_trap (invoke "64_good4" (i64.const 65504)) "out of bounds memory access")
+(assert_trap (invoke "64_bad" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "64_bad" (i64.const 1)) "out of bounds memory access")
+
+(assert_trap (invoke "store_far" (i64.const 0) (i32.const 1)) "out of bounds memory access")
+(assert_trap (invoke "store_far" (i64.const -4294967296) (i32.const 1)) "out of bounds memory access")
+(assert_return (invoke "32_good1" (i64.const 0)) (i32.const 1684234849))
@@ -0,0 +1,46 @@
+;; Accesses just past the end of a 64-bit memory trap, even when the address
+;; would be in bounds once truncated to 32 bits
+
+(module
+  (memory i64 1)
+
+  (func $addr_limit (result i64)
+    (i64.mul (memory.size) (i64.const 0x10000))
+  )
+
+  (func (export "store") (param $i i64) (param $v i32)
+    (i32.store (i64.add (call $addr_limit) (local.get $i)) (local.get $v))
+  )
+
+  (func (export "load") (param $i i64) (result i32)
+    (i32.load (i64.add (call $addr_limit) (local.get $i)))
+  )
+
+  (func (export "memory.grow") (param i64) (result i64)
+    (memory.grow (local.get 0))
+  )
+)
+
+(assert_return (invoke "store" (i64.const -4) (i32.const 42)))
+(assert_return (invoke "load" (i64.const -4)) (i32.const 42))
+(assert_trap (invoke "store" (i64.const -3) (i32.const 0x12345678)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -3)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const -2) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -2)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const -1) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const 0) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "store" (i64.const 0x80000000) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0x80000000)) "out of bounds memory access")
+;; 0x10000 + 0xffff0000 is 0x100000000, which is 0 when truncated
+(assert_trap (invoke "store" (i64.const 0xffff0000) (i32.const 13)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0xffff0000)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0x7fffffffffff0000)) "out of bounds memory access")
+(assert_return (invoke "load" (i64.const -4)) (i32.const 42))
+
+(assert_return (invoke "memory.grow" (i64.const 1)) (i64.const 1))
+(assert_return (invoke "store" (i64.const -4) (i32.const 7)))
+(assert_return (invoke "load" (i64.const -4)) (i32.const 7))
+(assert_return (invoke "load" (i64.const -0x10004)) (i32.const 42))
+(assert_trap (invoke "load" (i64.const -3)) "out of bounds memory access")